@@ -0,0 +1,7 @@
+//! Architecture-specific functionality.
+//!
+//! Each submodule is only available when building for its architecture, so
+//! using it from code built for another architecture fails to compile.
+
+#[cfg(target_arch = "x86_64")]
+pub mod x86_64;
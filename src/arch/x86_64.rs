@@ -0,0 +1,235 @@
+//! Access to model-specific registers and I/O ports on x86_64.
+//!
+//! These are privileged operations: the CPU raises a general protection
+//! fault when they are used outside of ring 0, and reading or writing an
+//! MSR that the CPU doesn't implement also faults. UEFI applications run in
+//! ring 0 while boot services are active, but the functions here are still
+//! `unsafe`, as writing to an MSR or an I/O port can change the state of the
+//! machine in ways the firmware doesn't expect.
+//!
+//! For example, the SMRR registers can be read if the CPU reports support
+//! for them:
+//!
+//! ```no_run
+//! use uefi::arch::x86_64::{read_msr, IA32_MTRRCAP, IA32_SMRR_PHYSBASE, MTRRCAP_SMRR};
+//!
+//! let smrr_base = unsafe {
+//!     if read_msr(IA32_MTRRCAP) & MTRRCAP_SMRR != 0 {
+//!         Some(read_msr(IA32_SMRR_PHYSBASE))
+//!     } else {
+//!         None
+//!     }
+//! };
+//! ```
+
+use core::arch::asm;
+use core::marker::PhantomData;
+
+/// `IA32_APIC_BASE`: base address and state of the local APIC.
+pub const IA32_APIC_BASE: u32 = 0x1b;
+
+/// Bit of [`IA32_APIC_BASE`] set on the bootstrap processor.
+pub const APIC_BASE_BSP: u64 = 1 << 8;
+
+/// `IA32_FEATURE_CONTROL`: lock bit and enabled virtualization features.
+pub const IA32_FEATURE_CONTROL: u32 = 0x3a;
+
+/// `IA32_MTRRCAP`: memory type range register capabilities.
+pub const IA32_MTRRCAP: u32 = 0xfe;
+
+/// Bit of [`IA32_MTRRCAP`] set if the SMRR registers are supported.
+pub const MTRRCAP_SMRR: u64 = 1 << 11;
+
+/// `IA32_SMRR_PHYSBASE`: base address and memory type of SMRAM.
+pub const IA32_SMRR_PHYSBASE: u32 = 0x1f2;
+
+/// `IA32_SMRR_PHYSMASK`: size of SMRAM, and whether the SMRR is enabled.
+pub const IA32_SMRR_PHYSMASK: u32 = 0x1f3;
+
+/// `IA32_EFER`: extended feature enables, such as long mode and NX.
+pub const IA32_EFER: u32 = 0xc000_0080;
+
+/// Read a model-specific register.
+///
+/// # Safety
+///
+/// The MSR must be implemented by the CPU, and the caller must be running
+/// in ring 0. Some MSRs have side effects when read.
+#[inline]
+pub unsafe fn read_msr(msr: u32) -> u64 {
+    let (low, high): (u32, u32);
+    asm!(
+        "rdmsr",
+        in("ecx") msr,
+        out("eax") low,
+        out("edx") high,
+        options(nomem, nostack, preserves_flags),
+    );
+    (u64::from(high) << 32) | u64::from(low)
+}
+
+/// Write a model-specific register.
+///
+/// # Safety
+///
+/// The MSR must be implemented by the CPU, `value` must be valid for it,
+/// and the caller must be running in ring 0. Writing an MSR can change how
+/// the CPU accesses memory or handles interrupts, and must not break the
+/// assumptions of the firmware.
+#[inline]
+pub unsafe fn write_msr(msr: u32, value: u64) {
+    asm!(
+        "wrmsr",
+        in("ecx") msr,
+        in("eax") value as u32,
+        in("edx") (value >> 32) as u32,
+        options(nostack, preserves_flags),
+    );
+}
+
+/// A value that can be read from or written to an I/O port: `u8`, `u16` or
+/// `u32`.
+pub trait PortValue: Copy + private::Sealed {
+    /// Read a value from `port`.
+    ///
+    /// # Safety
+    ///
+    /// See [`Port::read`].
+    unsafe fn read_from_port(port: u16) -> Self;
+
+    /// Write a value to `port`.
+    ///
+    /// # Safety
+    ///
+    /// See [`Port::write`].
+    unsafe fn write_to_port(port: u16, value: Self);
+}
+
+impl PortValue for u8 {
+    #[inline]
+    unsafe fn read_from_port(port: u16) -> Self {
+        let value: u8;
+        asm!(
+            "in al, dx",
+            out("al") value,
+            in("dx") port,
+            options(nomem, nostack, preserves_flags),
+        );
+        value
+    }
+
+    #[inline]
+    unsafe fn write_to_port(port: u16, value: Self) {
+        asm!(
+            "out dx, al",
+            in("dx") port,
+            in("al") value,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+}
+
+impl PortValue for u16 {
+    #[inline]
+    unsafe fn read_from_port(port: u16) -> Self {
+        let value: u16;
+        asm!(
+            "in ax, dx",
+            out("ax") value,
+            in("dx") port,
+            options(nomem, nostack, preserves_flags),
+        );
+        value
+    }
+
+    #[inline]
+    unsafe fn write_to_port(port: u16, value: Self) {
+        asm!(
+            "out dx, ax",
+            in("dx") port,
+            in("ax") value,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+}
+
+impl PortValue for u32 {
+    #[inline]
+    unsafe fn read_from_port(port: u16) -> Self {
+        let value: u32;
+        asm!(
+            "in eax, dx",
+            out("eax") value,
+            in("dx") port,
+            options(nomem, nostack, preserves_flags),
+        );
+        value
+    }
+
+    #[inline]
+    unsafe fn write_to_port(port: u16, value: Self) {
+        asm!(
+            "out dx, eax",
+            in("dx") port,
+            in("eax") value,
+            options(nomem, nostack, preserves_flags),
+        );
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+    impl Sealed for u32 {}
+}
+
+/// An I/O port, accessed with values of type `T`.
+///
+/// Creating a port is safe, but accessing it is not: the caller must know
+/// which device is behind the port, and that accessing it doesn't conflict
+/// with the drivers of the firmware.
+#[derive(Debug)]
+pub struct Port<T: PortValue> {
+    port: u16,
+    _value: PhantomData<T>,
+}
+
+impl<T: PortValue> Port<T> {
+    /// Create a handle to the I/O port with the given number.
+    pub const fn new(port: u16) -> Self {
+        Self {
+            port,
+            _value: PhantomData,
+        }
+    }
+
+    /// The number of the port.
+    pub const fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Read a value from the port.
+    ///
+    /// # Safety
+    ///
+    /// The caller must be running in ring 0, or have I/O privileges, and
+    /// reading the port must be valid for the device behind it. Reading a
+    /// port may have side effects such as acknowledging an interrupt.
+    #[inline]
+    pub unsafe fn read(&self) -> T {
+        T::read_from_port(self.port)
+    }
+
+    /// Write a value to the port.
+    ///
+    /// # Safety
+    ///
+    /// The caller must be running in ring 0, or have I/O privileges, and
+    /// writing `value` must be valid for the device behind the port.
+    #[inline]
+    pub unsafe fn write(&mut self, value: T) {
+        T::write_to_port(self.port, value)
+    }
+}
@@ -0,0 +1,201 @@
+use super::chars::{Char16, NUL_16};
+use super::strs::{CStr16, CapacityError, EqStrUntilNul, FromStrWithBufError};
+use core::fmt;
+use core::ops;
+
+/// A UCS-2 null-terminated string stored inline, in an array of `N` code
+/// units.
+///
+/// This is an alternative to [`CString16`] that doesn't need an allocator.
+/// The array also holds the trailing null character, so the string can have
+/// up to `N - 1` characters. `N` must not be zero.
+///
+/// # Examples
+///
+/// ```
+/// use uefi::data_types::ArrayCString16;
+///
+/// let mut s = ArrayCString16::<8>::try_from("abc").unwrap();
+/// s.push('d'.try_into().unwrap()).unwrap();
+/// assert_eq!(s, uefi::prelude::cstr16!("abcd"));
+/// ```
+///
+/// [`CString16`]: crate::CString16
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct ArrayCString16<const N: usize> {
+    /// Characters of the string, followed by null characters.
+    chars: [Char16; N],
+    /// Number of characters, without the trailing null character.
+    len: usize,
+}
+
+impl<const N: usize> ArrayCString16<N> {
+    /// Fails to compile if the array can't hold a null character.
+    const NOT_EMPTY: () = assert!(N > 0, "ArrayCString16 needs room for a null character");
+
+    /// Create an empty string.
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::NOT_EMPTY;
+        Self {
+            chars: [NUL_16; N],
+            len: 0,
+        }
+    }
+
+    /// Maximum number of characters, without the trailing null character.
+    pub const fn capacity(&self) -> usize {
+        N - 1
+    }
+
+    /// Append a character to the string.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CapacityError`] if the string is already at its capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `c` is the null character.
+    pub fn push(&mut self, c: Char16) -> Result<(), CapacityError> {
+        assert_ne!(c, NUL_16, "cannot push a null character");
+        if self.len == self.capacity() {
+            return Err(CapacityError);
+        }
+        self.chars[self.len] = c;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Remove all characters.
+    pub fn clear(&mut self) {
+        self.chars[..self.len].fill(NUL_16);
+        self.len = 0;
+    }
+}
+
+impl<const N: usize> Default for ArrayCString16<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> TryFrom<&str> for ArrayCString16<N> {
+    type Error = FromStrWithBufError;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        let mut output = Self::new();
+        for (i, c) in input.encode_utf16().enumerate() {
+            let c = Char16::try_from(c).map_err(|_| FromStrWithBufError::InvalidChar(i))?;
+            if c == NUL_16 {
+                return Err(FromStrWithBufError::InteriorNul(i));
+            }
+            output
+                .push(c)
+                .map_err(|_| FromStrWithBufError::BufferTooSmall)?;
+        }
+        Ok(output)
+    }
+}
+
+impl<const N: usize> TryFrom<&CStr16> for ArrayCString16<N> {
+    type Error = CapacityError;
+
+    fn try_from(input: &CStr16) -> Result<Self, Self::Error> {
+        let mut output = Self::new();
+        for &c in input.iter() {
+            output.push(c)?;
+        }
+        Ok(output)
+    }
+}
+
+impl<const N: usize> ops::Deref for ArrayCString16<N> {
+    type Target = CStr16;
+
+    fn deref(&self) -> &CStr16 {
+        // The characters following the string are null, so this slice ends
+        // with the first null character.
+        let chars = &self.chars[..=self.len];
+        unsafe { &*(chars as *const [Char16] as *const CStr16) }
+    }
+}
+
+impl<const N: usize> AsRef<CStr16> for ArrayCString16<N> {
+    fn as_ref(&self) -> &CStr16 {
+        self
+    }
+}
+
+impl<const N: usize> fmt::Debug for ArrayCString16<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_ref().fmt(f)
+    }
+}
+
+impl<const N: usize> fmt::Display for ArrayCString16<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_ref().fmt(f)
+    }
+}
+
+impl<const N: usize> PartialEq<&CStr16> for ArrayCString16<N> {
+    fn eq(&self, other: &&CStr16) -> bool {
+        PartialEq::eq(self.as_ref(), *other)
+    }
+}
+
+impl<const N: usize, StrType: AsRef<str>> EqStrUntilNul<StrType> for ArrayCString16<N> {
+    fn eq_str_until_nul(&self, other: &StrType) -> bool {
+        self.as_ref().eq_str_until_nul(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::cstr16;
+
+    #[test]
+    fn test_array_cstring16_from_str() {
+        let s = ArrayCString16::<4>::try_from("abc").unwrap();
+        assert_eq!(s, cstr16!("abc"));
+        assert_eq!(s.to_u16_slice_with_nul(), [97, 98, 99, 0]);
+        assert!(s.eq_str_until_nul(&"abc"));
+
+        assert_eq!(
+            ArrayCString16::<4>::try_from("abcd"),
+            Err(FromStrWithBufError::BufferTooSmall)
+        );
+        assert_eq!(
+            ArrayCString16::<4>::try_from("a😀"),
+            Err(FromStrWithBufError::InvalidChar(1))
+        );
+        assert_eq!(
+            ArrayCString16::<4>::try_from("a\0"),
+            Err(FromStrWithBufError::InteriorNul(1))
+        );
+        assert_eq!(ArrayCString16::<1>::try_from("").unwrap(), cstr16!(""));
+    }
+
+    #[test]
+    fn test_array_cstring16_push() {
+        let mut s = ArrayCString16::<3>::new();
+        assert_eq!(s.capacity(), 2);
+        s.push(Char16::try_from('a').unwrap()).unwrap();
+        s.push(Char16::try_from('b').unwrap()).unwrap();
+        assert_eq!(s.push(Char16::try_from('c').unwrap()), Err(CapacityError));
+        assert_eq!(s, cstr16!("ab"));
+
+        s.clear();
+        assert_eq!(s, ArrayCString16::new());
+        assert_eq!(
+            ArrayCString16::<3>::try_from(cstr16!("xy")).unwrap(),
+            cstr16!("xy")
+        );
+        assert_eq!(
+            ArrayCString16::<3>::try_from(cstr16!("xyz")),
+            Err(CapacityError)
+        );
+    }
+}
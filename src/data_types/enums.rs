@@ -63,6 +63,13 @@ macro_rules! newtype_enum {
                 $(#[$variant_attrs])*
                 pub const $variant: $type = $type($value);
             )*
+
+            /// The known variants, with their names.
+            pub(crate) const VARIANTS: &'static [($type, &'static str)] = &[
+                $(
+                    ($type::$variant, stringify!($variant)),
+                )*
+            ];
         }
 
         impl core::fmt::Debug for $type {
@@ -7,13 +7,14 @@ use core::{ffi::c_void, ptr::NonNull};
 /// Opaque handle to an UEFI entity (protocol, image...), guaranteed to be non-null.
 ///
 /// If you need to have a nullable handle (for a custom UEFI FFI for example) use `Option<Handle>`.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(transparent)]
 pub struct Handle(NonNull<c_void>);
 
 impl Handle {
     /// Creates a new [`Handle`] from a raw address. The address might
     /// come from the Multiboot2 information structure or something similar.
+    /// Returns `None` if the address is null.
     ///
     /// # Example
     /// ```no_run
@@ -35,6 +36,16 @@ impl Handle {
         // shorthand for "|ptr| Self(ptr)"
         NonNull::new(ptr).map(Self)
     }
+
+    /// Get the underlying raw pointer, the `EFI_HANDLE` value, for example
+    /// to pass the handle to C code. The pointer can be turned back into a
+    /// [`Handle`] with [`from_ptr`].
+    ///
+    /// [`from_ptr`]: Self::from_ptr
+    #[must_use]
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.0.as_ptr()
+    }
 }
 
 /// Handle to an event structure
@@ -119,10 +130,16 @@ mod enums;
 
 mod strs;
 pub use self::strs::{
-    CStr16, CStr8, EqStrUntilNul, FromSliceWithNulError, FromStrWithBufError, UnalignedCStr16,
-    UnalignedCStr16Error,
+    CStr16, CStr8, CapacityError, EqStrUntilNul, FromSliceWithNulError, FromStrWithBufError,
+    UnalignedCStr16, UnalignedCStr16Error,
 };
 
+mod array_strs;
+pub use self::array_strs::ArrayCString16;
+
+mod path;
+pub use self::path::{PathComponents, PathPart};
+
 #[cfg(feature = "exts")]
 mod owned_strs;
 #[cfg(feature = "exts")]
@@ -172,4 +189,13 @@ mod tests {
         let buffer = X::align_buf(buffer).unwrap();
         X::assert_aligned(buffer);
     }
+
+    #[test]
+    fn test_handle_ptr() {
+        let ptr = 0x1000 as *mut c_void;
+        let handle = unsafe { Handle::from_ptr(ptr) }.unwrap();
+        assert_eq!(handle.as_ptr(), ptr);
+
+        assert!(unsafe { Handle::from_ptr(core::ptr::null_mut()) }.is_none());
+    }
 }
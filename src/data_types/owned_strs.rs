@@ -85,6 +85,12 @@ impl TryFrom<Vec<u16>> for CString16 {
     }
 }
 
+impl From<&CStr16> for CString16 {
+    fn from(input: &CStr16) -> Self {
+        Self(input.as_slice_with_nul().to_vec())
+    }
+}
+
 impl ops::Deref for CString16 {
     type Target = CStr16;
 
@@ -0,0 +1,275 @@
+//! Splitting of file paths stored in a [`CStr16`].
+//!
+//! UEFI file paths use `\` as the separator, but `/` is accepted too, as
+//! paths are often written with it. A path starting with a separator is
+//! relative to the root of its volume.
+
+use super::array_strs::ArrayCString16;
+use super::chars::Char16;
+use super::strs::{CStr16, CapacityError, EqStrUntilNul};
+#[cfg(feature = "exts")]
+use super::CString16;
+#[cfg(feature = "exts")]
+use crate::alloc_api::vec::Vec;
+use core::fmt;
+
+/// Whether `c` separates the components of a path.
+fn is_separator(c: &Char16) -> bool {
+    matches!(u16::from(*c), 0x5c | 0x2f)
+}
+
+impl CStr16 {
+    /// Iterate over the components of the path stored in this string.
+    ///
+    /// Components are separated by `\` or `/`. Empty components, such as
+    /// those before a leading separator or between doubled separators, are
+    /// skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uefi::prelude::cstr16;
+    /// use uefi::data_types::EqStrUntilNul;
+    ///
+    /// let mut components = cstr16!("\\EFI//BOOT\\BOOTX64.EFI").split_path();
+    /// assert!(components.next().unwrap().eq_str_until_nul(&"EFI"));
+    /// assert!(components.next().unwrap().eq_str_until_nul(&"BOOT"));
+    /// assert!(components.next().unwrap().eq_str_until_nul(&"BOOTX64.EFI"));
+    /// assert!(components.next().is_none());
+    /// ```
+    pub fn split_path(&self) -> PathComponents<'_> {
+        self.part().split_path()
+    }
+
+    /// Whether the path stored in this string starts with a separator, and is
+    /// relative to the root of its volume.
+    pub fn is_absolute_path(&self) -> bool {
+        self.part().is_absolute_path()
+    }
+
+    /// The last component of the path stored in this string, or `None` if it
+    /// has no components.
+    pub fn file_name(&self) -> Option<PathPart<'_>> {
+        self.part().file_name()
+    }
+
+    /// The path without its last component and the separators before it, or
+    /// `None` if it has no components.
+    ///
+    /// The parent of a component at the root of the volume is `\`, and the
+    /// parent of a single relative component is empty.
+    pub fn parent(&self) -> Option<PathPart<'_>> {
+        self.part().parent()
+    }
+
+    /// The whole string as a path part, without the trailing null character.
+    fn part(&self) -> PathPart<'_> {
+        let chars = self.as_slice_with_nul();
+        PathPart(&chars[..chars.len() - 1])
+    }
+}
+
+/// A part of a path stored in a [`CStr16`], such as a component or a parent
+/// path.
+///
+/// Unlike a `CStr16`, a part is not followed by a null character, so it must
+/// be copied to an [`ArrayCString16`] or a `CString16` to be passed to UEFI
+/// functions.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PathPart<'a>(&'a [Char16]);
+
+impl<'a> PathPart<'a> {
+    /// The characters of the part.
+    pub fn as_slice(&self) -> &'a [Char16] {
+        self.0
+    }
+
+    /// The characters of the part, as `u16` code units.
+    pub fn to_u16_slice(&self) -> &'a [u16] {
+        // Safety: `Char16` is a transparent wrapper around `u16`.
+        unsafe { &*(self.0 as *const [Char16] as *const [u16]) }
+    }
+
+    /// Number of characters in the part.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the part has no characters.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the components of the path in this part. See
+    /// [`CStr16::split_path`].
+    pub fn split_path(&self) -> PathComponents<'a> {
+        PathComponents {
+            inner: self.0.split(is_separator as fn(&Char16) -> bool),
+        }
+    }
+
+    /// Whether the path in this part starts with a separator. See
+    /// [`CStr16::is_absolute_path`].
+    pub fn is_absolute_path(&self) -> bool {
+        self.0.first().map_or(false, is_separator)
+    }
+
+    /// The last component of the path in this part. See
+    /// [`CStr16::file_name`].
+    pub fn file_name(&self) -> Option<PathPart<'a>> {
+        self.split_path().next_back()
+    }
+
+    /// The path in this part without its last component. See
+    /// [`CStr16::parent`].
+    pub fn parent(&self) -> Option<PathPart<'a>> {
+        // Drop the trailing separators, then the last component.
+        let end = self.0.iter().rposition(|c| !is_separator(c))?;
+        let trimmed = &self.0[..=end];
+        let start = trimmed.iter().rposition(is_separator).map_or(0, |i| i + 1);
+        let before = &trimmed[..start];
+        let parent = match before.iter().rposition(|c| !is_separator(c)) {
+            Some(last) => &before[..=last],
+            // Keep one separator for the root of the volume.
+            None => &before[..before.len().min(1)],
+        };
+        Some(PathPart(parent))
+    }
+
+    /// Copy the part to a new [`CString16`].
+    #[cfg(feature = "exts")]
+    pub fn to_cstring16(&self) -> CString16 {
+        let mut chars: Vec<u16> = self.to_u16_slice().to_vec();
+        chars.push(0);
+        // The part contains no null characters, as it comes from a `CStr16`.
+        CString16::try_from(chars).unwrap()
+    }
+}
+
+impl<const N: usize> TryFrom<PathPart<'_>> for ArrayCString16<N> {
+    type Error = CapacityError;
+
+    fn try_from(part: PathPart<'_>) -> Result<Self, Self::Error> {
+        let mut output = Self::new();
+        for &c in part.as_slice() {
+            output.push(c)?;
+        }
+        Ok(output)
+    }
+}
+
+impl fmt::Debug for PathPart<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PathPart(\"{}\")", self)
+    }
+}
+
+impl fmt::Display for PathPart<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.0 {
+            <Char16 as fmt::Display>::fmt(c, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl<StrType: AsRef<str>> EqStrUntilNul<StrType> for PathPart<'_> {
+    fn eq_str_until_nul(&self, other: &StrType) -> bool {
+        let mut other = other.as_ref().chars().take_while(|&c| c != '\0');
+        self.0.iter().all(|&c| other.next() == Some(char::from(c))) && other.next().is_none()
+    }
+}
+
+/// Iterator over the components of a path.
+///
+/// This struct is returned by [`CStr16::split_path`].
+#[derive(Clone, Debug)]
+pub struct PathComponents<'a> {
+    inner: core::slice::Split<'a, Char16, fn(&Char16) -> bool>,
+}
+
+impl<'a> Iterator for PathComponents<'a> {
+    type Item = PathPart<'a>;
+
+    fn next(&mut self) -> Option<PathPart<'a>> {
+        self.inner
+            .by_ref()
+            .find(|component| !component.is_empty())
+            .map(PathPart)
+    }
+}
+
+impl<'a> DoubleEndedIterator for PathComponents<'a> {
+    fn next_back(&mut self) -> Option<PathPart<'a>> {
+        self.inner
+            .by_ref()
+            .rfind(|component| !component.is_empty())
+            .map(PathPart)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_api::string::{String, ToString};
+    use crate::prelude::cstr16;
+
+    fn components(path: &CStr16) -> Vec<String> {
+        path.split_path()
+            .map(|component| component.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_split_path() {
+        assert_eq!(
+            components(cstr16!("\\EFI\\BOOT\\FILE.EFI")),
+            ["EFI", "BOOT", "FILE.EFI"]
+        );
+        assert_eq!(
+            components(cstr16!("//EFI\\\\BOOT/FILE.EFI\\")),
+            ["EFI", "BOOT", "FILE.EFI"]
+        );
+        assert_eq!(components(cstr16!("FILE")), ["FILE"]);
+        assert!(components(cstr16!("")).is_empty());
+        assert!(components(cstr16!("\\/")).is_empty());
+
+        assert!(cstr16!("\\EFI").is_absolute_path());
+        assert!(cstr16!("/EFI").is_absolute_path());
+        assert!(!cstr16!("EFI\\BOOT").is_absolute_path());
+        assert!(!cstr16!("").is_absolute_path());
+    }
+
+    #[test]
+    fn test_parent_and_file_name() {
+        let path = cstr16!("\\EFI\\BOOT\\FILE.EFI");
+        assert!(path.file_name().unwrap().eq_str_until_nul(&"FILE.EFI"));
+        let parent = path.parent().unwrap();
+        assert!(parent.eq_str_until_nul(&"\\EFI\\BOOT"));
+        assert!(parent.file_name().unwrap().eq_str_until_nul(&"BOOT"));
+        let parent = parent.parent().unwrap();
+        assert!(parent.eq_str_until_nul(&"\\EFI"));
+        let root = parent.parent().unwrap();
+        assert!(root.eq_str_until_nul(&"\\"));
+        assert_eq!(root.file_name(), None);
+        assert_eq!(root.parent(), None);
+
+        let path = cstr16!("EFI//BOOT/");
+        assert!(path.file_name().unwrap().eq_str_until_nul(&"BOOT"));
+        assert!(path.parent().unwrap().eq_str_until_nul(&"EFI"));
+        assert!(cstr16!("EFI").parent().unwrap().is_empty());
+        assert_eq!(cstr16!("").parent(), None);
+    }
+
+    #[test]
+    fn test_path_part_copy() {
+        let name = cstr16!("\\EFI\\BOOT").file_name().unwrap();
+        assert_eq!(
+            ArrayCString16::<5>::try_from(name).unwrap(),
+            cstr16!("BOOT")
+        );
+        assert_eq!(ArrayCString16::<4>::try_from(name), Err(CapacityError));
+        assert_eq!(name.to_cstring16(), cstr16!("BOOT"));
+        assert_eq!(name.to_u16_slice(), [66, 79, 79, 84]);
+    }
+}
@@ -52,6 +52,11 @@ pub enum FromStrWithBufError {
     BufferTooSmall,
 }
 
+/// Error returned by [`CStr16::copy_into`] if the destination is too small to
+/// hold the string and its trailing null character.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CapacityError;
+
 /// A null-terminated Latin-1 string.
 ///
 /// This type is largely inspired by [`core::ffi::CStr`] with the exception that all characters are
@@ -295,6 +300,40 @@ impl CStr16 {
         self.0.len() * 2
     }
 
+    /// Copies the string, including the trailing null character, to the start
+    /// of `dest`. The rest of `dest` is left unchanged.
+    ///
+    /// This can be used to fill fixed-size `CHAR16` arrays in UEFI
+    /// structures. Returns [`CapacityError`] without writing anything if
+    /// `dest` is too small.
+    pub fn copy_into(&self, dest: &mut [u16]) -> Result<(), CapacityError> {
+        let src = self.to_u16_slice_with_nul();
+        dest.get_mut(..src.len())
+            .ok_or(CapacityError)?
+            .copy_from_slice(src);
+        Ok(())
+    }
+
+    /// Like [`copy_into`], but if `dest` is too small, copies as many
+    /// characters as fit while leaving room for the null character, so the
+    /// result is always null-terminated. Nothing is written if `dest` is
+    /// empty.
+    ///
+    /// Returns `true` if the string was truncated.
+    ///
+    /// [`copy_into`]: Self::copy_into
+    pub fn copy_into_truncating(&self, dest: &mut [u16]) -> bool {
+        let src = self.to_u16_slice();
+        let max_len = match dest.len().checked_sub(1) {
+            Some(max_len) => max_len,
+            None => return !src.is_empty(),
+        };
+        let len = src.len().min(max_len);
+        dest[..len].copy_from_slice(&src[..len]);
+        dest[len] = 0;
+        len < src.len()
+    }
+
     /// Writes each [`Char16`] as a [`char`] (4 bytes long in Rust language) into the buffer.
     /// It is up the the implementer of [`core::fmt::Write`] to convert the char to a string
     /// with proper encoding/charset. For example, in the case of [`alloc::string::String`]
@@ -556,6 +595,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cstr16_copy_into() {
+        let s = CStr16::from_u16_with_nul(&[65, 66, 67, 0]).unwrap();
+
+        // OK: dest is exactly the right size.
+        let mut dest = [1; 4];
+        assert_eq!(s.copy_into(&mut dest), Ok(()));
+        assert_eq!(dest, [65, 66, 67, 0]);
+
+        // OK: dest is bigger than needed, the rest is untouched.
+        let mut dest = [1; 6];
+        assert_eq!(s.copy_into(&mut dest), Ok(()));
+        assert_eq!(dest, [65, 66, 67, 0, 1, 1]);
+
+        // Error: no room for the null character.
+        let mut dest = [1; 3];
+        assert_eq!(s.copy_into(&mut dest), Err(CapacityError));
+        assert_eq!(dest, [1; 3]);
+    }
+
+    #[test]
+    fn test_cstr16_copy_into_truncating() {
+        let s = CStr16::from_u16_with_nul(&[65, 66, 67, 0]).unwrap();
+
+        let mut dest = [1; 5];
+        assert!(!s.copy_into_truncating(&mut dest));
+        assert_eq!(dest, [65, 66, 67, 0, 1]);
+
+        // The string exactly fills dest, including the null character.
+        let mut dest = [1; 4];
+        assert!(!s.copy_into_truncating(&mut dest));
+        assert_eq!(dest, [65, 66, 67, 0]);
+
+        // The last character is replaced by the null character.
+        let mut dest = [1; 3];
+        assert!(s.copy_into_truncating(&mut dest));
+        assert_eq!(dest, [65, 66, 0]);
+
+        // Only the null character fits.
+        let mut dest = [1; 1];
+        assert!(s.copy_into_truncating(&mut dest));
+        assert_eq!(dest, [0]);
+
+        // Nothing fits.
+        assert!(s.copy_into_truncating(&mut []));
+        let empty = CStr16::from_u16_with_nul(&[0]).unwrap();
+        assert!(!empty.copy_into_truncating(&mut []));
+    }
+
     #[test]
     fn test_cstr16_macro() {
         // Just a sanity check to make sure it's spitting out the right characters
@@ -0,0 +1,311 @@
+//! Diagnostics of what the firmware exposes.
+
+use crate::alloc_api::collections::BTreeSet;
+use crate::alloc_api::string::{String, ToString};
+use crate::alloc_api::vec::Vec;
+use crate::proto::driver::ComponentName2;
+use crate::table::boot::{BootServices, OpenProtocolAttributes, OpenProtocolParams, SearchType};
+use crate::{CStr8, Handle};
+use core::fmt::Write;
+
+/// Maximum length of a language code, with its null character.
+const MAX_LANGUAGE_LEN: usize = 32;
+
+/// A protocol of `parent` that was opened for its `child` by the bus driver
+/// `agent`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ChildLink {
+    parent: Handle,
+    child: Handle,
+    agent: Handle,
+}
+
+/// The links between handles found in the open protocol information.
+///
+/// The links are kept sorted, so that the links of a handle are found by a
+/// binary search rather than by scanning all of them.
+struct Links {
+    /// The child links, sorted by parent, then by child.
+    by_parent: Vec<ChildLink>,
+    /// The child links, sorted by child, then by parent.
+    by_child: Vec<ChildLink>,
+    /// The controllers and the drivers managing them, sorted by controller.
+    drivers: Vec<(Handle, Handle)>,
+}
+
+/// Handles have no order, so they are sorted by address.
+fn key(handle: Handle) -> usize {
+    handle.as_ptr() as usize
+}
+
+/// Returns the items of the sorted slice `items` whose key is `key`.
+fn equal_range<T>(items: &[T], key: usize, item_key: impl Fn(&T) -> usize) -> &[T] {
+    let start = items.partition_point(|item| item_key(item) < key);
+    let len = items[start..].partition_point(|item| item_key(item) == key);
+    &items[start..start + len]
+}
+
+impl Links {
+    fn new(mut children: Vec<ChildLink>, mut drivers: Vec<(Handle, Handle)>) -> Self {
+        children.sort_unstable_by_key(|link| (key(link.parent), key(link.child), key(link.agent)));
+        children.dedup();
+        let mut by_child = children.clone();
+        // The sort is stable, so the parents stay sorted.
+        by_child.sort_by_key(|link| key(link.child));
+        drivers.sort_unstable_by_key(|&(controller, driver)| (key(controller), key(driver)));
+        drivers.dedup();
+        Self {
+            by_parent: children,
+            by_child,
+            drivers,
+        }
+    }
+
+    fn collect(bt: &BootServices, handles: &[Handle]) -> Self {
+        let by_child = OpenProtocolAttributes::ByChildController as u32;
+        let by_driver = OpenProtocolAttributes::ByDriver as u32;
+
+        let mut children = Vec::new();
+        let mut drivers = Vec::new();
+        for &handle in handles {
+            let protocols = match bt.protocols_per_handle(handle) {
+                Ok(protocols) => protocols,
+                Err(_) => continue,
+            };
+            for guid in protocols.protocols() {
+                let info = match bt.open_protocol_information(handle, guid) {
+                    Ok(info) => info,
+                    Err(_) => continue,
+                };
+                for entry in info.entries() {
+                    let agent = match entry.agent {
+                        Some(agent) => agent,
+                        None => continue,
+                    };
+                    if entry.attributes & by_child != 0 {
+                        if let Some(child) = entry.controller.filter(|&child| child != handle) {
+                            children.push(ChildLink {
+                                parent: handle,
+                                child,
+                                agent,
+                            });
+                        }
+                    } else if entry.attributes & by_driver != 0 {
+                        drivers.push((handle, agent));
+                    }
+                }
+            }
+        }
+        // Duplicates, such as a child opening several protocols of its
+        // parent, are removed here.
+        Self::new(children, drivers)
+    }
+
+    /// The links to the children of `parent`, sorted by child.
+    fn children_of(&self, parent: Handle) -> &[ChildLink] {
+        equal_range(&self.by_parent, key(parent), |link| key(link.parent))
+    }
+
+    /// The links to the parents of `child`, sorted by parent.
+    fn parents_of(&self, child: Handle) -> &[ChildLink] {
+        equal_range(&self.by_child, key(child), |link| key(link.child))
+    }
+
+    /// The drivers managing `controller`.
+    fn drivers_of(&self, controller: Handle) -> &[(Handle, Handle)] {
+        equal_range(&self.drivers, key(controller), |&(controller, _)| {
+            key(controller)
+        })
+    }
+}
+
+/// Orders `handles` as a depth-first walk of the tree described by `links`,
+/// returning each handle with its depth.
+///
+/// Handles without a parent are roots, and the children of a handle are
+/// sorted by address. A handle with several parents is shown below the
+/// first one only, and handles only reachable through a cycle are shown as
+/// roots at the end.
+fn tree_order(handles: &[Handle], links: &Links) -> Vec<(usize, Handle)> {
+    let mut known: Vec<usize> = handles.iter().map(|&handle| key(handle)).collect();
+    known.sort_unstable();
+    let is_known = |handle: Handle| known.binary_search(&key(handle)).is_ok();
+
+    let mut order = Vec::with_capacity(handles.len());
+    let mut visited = BTreeSet::new();
+    let mut stack = Vec::new();
+
+    let has_parent = |handle: Handle| {
+        links
+            .parents_of(handle)
+            .iter()
+            .any(|link| is_known(link.parent))
+    };
+    let roots = handles.iter().filter(|&&handle| !has_parent(handle));
+    let rest = handles.iter().filter(|&&handle| has_parent(handle));
+    for &root in roots.chain(rest) {
+        stack.push((0, root));
+        while let Some((depth, handle)) = stack.pop() {
+            if !visited.insert(key(handle)) {
+                continue;
+            }
+            order.push((depth, handle));
+            // Push in reverse, so that children are shown in order.
+            for link in links.children_of(handle).iter().rev() {
+                if is_known(link.child) {
+                    stack.push((depth + 1, link.child));
+                }
+            }
+        }
+    }
+    order
+}
+
+/// Returns the first language of a component name protocol, null-terminated
+/// in `buffer`.
+fn first_language<'buf>(
+    component_name: &ComponentName2,
+    buffer: &'buf mut [u8; MAX_LANGUAGE_LEN],
+) -> Option<&'buf CStr8> {
+    let language = component_name.languages().next()?;
+    if language.len() >= buffer.len() {
+        return None;
+    }
+    buffer[..language.len()].copy_from_slice(language);
+    buffer[language.len()] = 0;
+    CStr8::from_bytes_with_nul(&buffer[..=language.len()]).ok()
+}
+
+/// Asks the component name protocol of `driver` for the name of `controller`,
+/// or of its `child`, or of the driver itself if `controller` is `None`.
+fn component_name(
+    bt: &BootServices,
+    driver: Handle,
+    controller: Option<Handle>,
+    child: Option<Handle>,
+) -> Option<String> {
+    // Safety: the protocol is only used during this function and is opened
+    // without affecting the driver.
+    let component_name = unsafe {
+        bt.open_protocol::<ComponentName2>(
+            OpenProtocolParams {
+                handle: driver,
+                agent: bt.image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )
+    }
+    .ok()?;
+    let mut buffer = [0; MAX_LANGUAGE_LEN];
+    let language = first_language(&component_name, &mut buffer)?;
+    let name = match controller {
+        Some(controller) => component_name.get_controller_name(controller, child, language),
+        None => component_name.get_driver_name(language),
+    }
+    .ok()?;
+    Some(name.to_string())
+}
+
+/// The name of `handle` given by the drivers: the name of the controller,
+/// given by the bus driver that created it or by the driver managing it, or
+/// the name of the driver if `handle` is a driver.
+fn handle_name(bt: &BootServices, handle: Handle, links: &Links) -> Option<String> {
+    let child_name = || {
+        links
+            .parents_of(handle)
+            .iter()
+            .find_map(|link| component_name(bt, link.agent, Some(link.parent), Some(handle)))
+    };
+    let controller_name = || {
+        links
+            .drivers_of(handle)
+            .iter()
+            .find_map(|&(_, driver)| component_name(bt, driver, Some(handle), None))
+    };
+    child_name()
+        .or_else(controller_name)
+        .or_else(|| component_name(bt, handle, None, None))
+}
+
+/// Logs every handle of the handle database, as a tree of controllers.
+///
+/// Each handle is described by [`BootServices::debug_handle`], with its device
+/// path and protocols, followed by its name in the [`ComponentName2`]
+/// protocol of its driver, if any. Child controllers created by a bus driver,
+/// such as the partitions of a disk, are indented below their parent.
+///
+/// The output goes to the logger, at the `info` level.
+pub fn print_device_tree(bt: &BootServices) {
+    let handles = match bt.locate_handle_buffer(SearchType::AllHandles) {
+        Ok(handles) => handles,
+        Err(err) => {
+            log::warn!("Failed to list the handles: {:?}", err.status());
+            return;
+        }
+    };
+    let links = Links::collect(bt, &handles);
+
+    log::info!("Device tree ({} handles):", handles.len());
+    for (depth, handle) in tree_order(&handles, &links) {
+        let mut line = String::new();
+        for _ in 0..depth {
+            line.push_str("  ");
+        }
+        write!(line, "{:?} {}", handle.as_ptr(), bt.debug_handle(handle)).unwrap();
+        if let Some(name) = handle_name(bt, handle, &links) {
+            write!(line, " \"{}\"", name).unwrap();
+        }
+        log::info!("{}", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ffi::c_void;
+
+    fn handle(address: usize) -> Handle {
+        unsafe { Handle::from_ptr(address as *mut c_void) }.unwrap()
+    }
+
+    fn link(parent: usize, child: usize) -> ChildLink {
+        ChildLink {
+            parent: handle(parent),
+            child: handle(child),
+            agent: handle(0x100),
+        }
+    }
+
+    fn order(handles: &[usize], children: &[ChildLink]) -> Vec<(usize, usize)> {
+        let handles: Vec<Handle> = handles.iter().map(|&address| handle(address)).collect();
+        tree_order(&handles, &Links::new(children.to_vec(), Vec::new()))
+            .into_iter()
+            .map(|(depth, handle)| (depth, handle.as_ptr() as usize))
+            .collect()
+    }
+
+    #[test]
+    fn test_tree_order() {
+        // A disk with two partitions, and an unrelated handle.
+        let children = [link(0x10, 0x20), link(0x10, 0x30), link(0x20, 0x40)];
+        assert_eq!(
+            order(&[0x40, 0x30, 0x10, 0x50, 0x20], &children),
+            [(0, 0x10), (1, 0x20), (2, 0x40), (1, 0x30), (0, 0x50)]
+        );
+
+        // A handle with two parents is shown once.
+        let children = [link(0x10, 0x30), link(0x20, 0x30)];
+        assert_eq!(
+            order(&[0x10, 0x20, 0x30], &children),
+            [(0, 0x10), (1, 0x30), (0, 0x20)]
+        );
+
+        // Links to handles that are gone are ignored, and cycles are broken.
+        let children = [link(0x10, 0x20), link(0x20, 0x10), link(0x90, 0x30)];
+        assert_eq!(
+            order(&[0x10, 0x20, 0x30], &children),
+            [(0, 0x30), (0, 0x10), (1, 0x20)]
+        );
+    }
+}
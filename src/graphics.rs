@@ -0,0 +1,450 @@
+//! Software rendering that doesn't need a display.
+//!
+//! [`Canvas`] is an image in memory, that can be drawn into without a
+//! [`GraphicsOutput`] protocol, for example to render a screenshot on a
+//! headless machine and encode it with [`Canvas::to_bmp`]. The drawing
+//! primitives are provided by the [`DrawTarget`] trait, which is also
+//! implemented by [`BackBuffer`], so the same code can draw into either:
+//!
+//! ```
+//! use uefi::graphics::{Canvas, DrawTarget};
+//! use uefi::proto::console::gop::{BltPixel, Rect};
+//!
+//! fn draw_title(target: &mut impl DrawTarget) {
+//!     let white = BltPixel::new(255, 255, 255);
+//!     target.fill_rect(Rect::new(0, 0, 64, 9), BltPixel::new(0, 0, 128));
+//!     target.draw_text((2, 2), "Hello", white, 1);
+//!     target.draw_line((0, 9), (63, 9), white);
+//! }
+//!
+//! let mut canvas = Canvas::new(64, 16);
+//! draw_title(&mut canvas);
+//! let bmp = canvas.to_bmp();
+//! ```
+//!
+//! Text is drawn with a built-in font of 3 by 5 pixel glyphs, which has
+//! the digits, the letters and common punctuation. Letters are drawn in
+//! upper case, and other characters as `?`.
+
+use crate::alloc_api::{vec, vec::Vec};
+use crate::proto::console::gop::{
+    encode_bmp, BackBuffer, BltOp, BltPixel, BltRegion, GraphicsOutput, Rect,
+};
+use crate::Result;
+
+/// Width of a glyph of the font, in font pixels.
+const GLYPH_WIDTH: usize = 3;
+
+/// Height of a glyph of the font, in font pixels.
+const GLYPH_HEIGHT: usize = 5;
+
+/// Glyphs of the digits. Each row is stored in the low 3 bits of a byte,
+/// the leftmost pixel in the highest bit.
+const DIGIT_GLYPHS: [[u8; GLYPH_HEIGHT]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// Glyphs of the letters, from `A` to `Z`.
+const LETTER_GLYPHS: [[u8; GLYPH_HEIGHT]; 26] = [
+    [0b010, 0b101, 0b111, 0b101, 0b101],
+    [0b110, 0b101, 0b110, 0b101, 0b110],
+    [0b011, 0b100, 0b100, 0b100, 0b011],
+    [0b110, 0b101, 0b101, 0b101, 0b110],
+    [0b111, 0b100, 0b110, 0b100, 0b111],
+    [0b111, 0b100, 0b110, 0b100, 0b100],
+    [0b011, 0b100, 0b101, 0b101, 0b011],
+    [0b101, 0b101, 0b111, 0b101, 0b101],
+    [0b111, 0b010, 0b010, 0b010, 0b111],
+    [0b001, 0b001, 0b001, 0b101, 0b010],
+    [0b101, 0b101, 0b110, 0b101, 0b101],
+    [0b100, 0b100, 0b100, 0b100, 0b111],
+    [0b101, 0b111, 0b111, 0b101, 0b101],
+    [0b110, 0b101, 0b101, 0b101, 0b101],
+    [0b010, 0b101, 0b101, 0b101, 0b010],
+    [0b110, 0b101, 0b110, 0b100, 0b100],
+    [0b010, 0b101, 0b101, 0b110, 0b011],
+    [0b110, 0b101, 0b110, 0b101, 0b101],
+    [0b011, 0b100, 0b010, 0b001, 0b110],
+    [0b111, 0b010, 0b010, 0b010, 0b010],
+    [0b101, 0b101, 0b101, 0b101, 0b111],
+    [0b101, 0b101, 0b101, 0b101, 0b010],
+    [0b101, 0b101, 0b111, 0b111, 0b101],
+    [0b101, 0b101, 0b010, 0b101, 0b101],
+    [0b101, 0b101, 0b010, 0b010, 0b010],
+    [0b111, 0b001, 0b010, 0b100, 0b111],
+];
+
+/// Get the glyph of `c`, or `None` if the font doesn't have it.
+pub(crate) fn glyph(c: char) -> Option<[u8; GLYPH_HEIGHT]> {
+    let glyph = match c.to_ascii_uppercase() {
+        '0'..='9' => DIGIT_GLYPHS[c as usize - '0' as usize],
+        c @ 'A'..='Z' => LETTER_GLYPHS[c as usize - 'A' as usize],
+        ' ' => [0b000, 0b000, 0b000, 0b000, 0b000],
+        '!' => [0b010, 0b010, 0b010, 0b000, 0b010],
+        '"' => [0b101, 0b101, 0b000, 0b000, 0b000],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        '%' => [0b101, 0b001, 0b010, 0b100, 0b101],
+        '\'' => [0b010, 0b010, 0b000, 0b000, 0b000],
+        '(' => [0b001, 0b010, 0b010, 0b010, 0b001],
+        ')' => [0b100, 0b010, 0b010, 0b010, 0b100],
+        '*' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        '=' => [0b000, 0b111, 0b000, 0b111, 0b000],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '?' => [0b110, 0b001, 0b010, 0b000, 0b010],
+        '[' => [0b011, 0b010, 0b010, 0b010, 0b011],
+        ']' => [0b110, 0b010, 0b010, 0b010, 0b110],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        _ => return None,
+    };
+    Some(glyph)
+}
+
+/// The width and height in pixels of `text` drawn with
+/// [`DrawTarget::draw_text`] at `scale`.
+pub fn text_size(text: &str, scale: usize) -> (usize, usize) {
+    let columns = text.lines().map(|line| line.chars().count()).max();
+    let rows = text.lines().count();
+    match columns {
+        // Glyphs are separated by a column, and lines by a row of pixels.
+        Some(columns) if columns > 0 => (
+            (columns * (GLYPH_WIDTH + 1) - 1) * scale,
+            (rows * (GLYPH_HEIGHT + 1) - 1) * scale,
+        ),
+        _ => (0, 0),
+    }
+}
+
+/// A surface that can be drawn into, such as a [`Canvas`] or a
+/// [`BackBuffer`].
+///
+/// Only [`resolution`] and [`set_pixel`] must be implemented. The other
+/// primitives are built on them, and clip what they draw to the surface.
+///
+/// [`resolution`]: DrawTarget::resolution
+/// [`set_pixel`]: DrawTarget::set_pixel
+pub trait DrawTarget {
+    /// The width and height of the surface.
+    fn resolution(&self) -> (usize, usize);
+
+    /// Set the pixel at `(x, y)`. Pixels out of bounds are ignored.
+    fn set_pixel(&mut self, x: usize, y: usize, color: BltPixel);
+
+    /// Fill a rectangle with a color. The rectangle is clipped to the
+    /// surface.
+    fn fill_rect(&mut self, rect: Rect, color: BltPixel) {
+        let rect = rect.clip(self.resolution());
+        for y in rect.y..rect.y + rect.height {
+            for x in rect.x..rect.x + rect.width {
+                self.set_pixel(x, y, color);
+            }
+        }
+    }
+
+    /// Fill the whole surface with a color.
+    fn clear(&mut self, color: BltPixel) {
+        let (width, height) = self.resolution();
+        self.fill_rect(Rect::new(0, 0, width, height), color);
+    }
+
+    /// Draw a one pixel wide line from `from` to `to`, including both ends.
+    fn draw_line(&mut self, from: (usize, usize), to: (usize, usize), color: BltPixel) {
+        // Bresenham's algorithm, stepping along both axes.
+        let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+        let (x1, y1) = (to.0 as i64, to.1 as i64);
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let step_x = if x < x1 { 1 } else { -1 };
+        let step_y = if y < y1 { 1 } else { -1 };
+        let mut error = dx + dy;
+        loop {
+            self.set_pixel(x as usize, y as usize, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let double = 2 * error;
+            if double >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if double <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Draw `text` with its top-left corner at `(x, y)`, with each pixel of
+    /// the font drawn as a `scale` by `scale` square. Only the pixels of
+    /// the glyphs are drawn, not their background.
+    ///
+    /// A newline starts a new line below the first character. See
+    /// [`text_size`] for the size of the text.
+    fn draw_text(&mut self, (x, y): (usize, usize), text: &str, color: BltPixel, scale: usize) {
+        for (line_index, line) in text.lines().enumerate() {
+            let line_y = y + line_index * (GLYPH_HEIGHT + 1) * scale;
+            for (index, c) in line.chars().enumerate() {
+                let glyph = glyph(c).or_else(|| glyph('?')).unwrap();
+                let glyph_x = x + index * (GLYPH_WIDTH + 1) * scale;
+                for (row, bits) in glyph.iter().enumerate() {
+                    for column in 0..GLYPH_WIDTH {
+                        if bits & (0b100 >> column) != 0 {
+                            let rect = Rect::new(
+                                glyph_x + column * scale,
+                                line_y + row * scale,
+                                scale,
+                                scale,
+                            );
+                            self.fill_rect(rect, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An image in memory, drawn into with the [`DrawTarget`] primitives.
+///
+/// Unlike a [`BackBuffer`], a canvas doesn't track which regions were
+/// modified, and can have any size: it can be copied to any position on the
+/// screen with [`present_to`], or encoded as an image with [`to_bmp`].
+///
+/// [`present_to`]: Canvas::present_to
+/// [`to_bmp`]: Canvas::to_bmp
+#[derive(Debug, Clone)]
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<BltPixel>,
+}
+
+impl Canvas {
+    /// Create a black canvas of the given size.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![BltPixel::new(0, 0, 0); width * height],
+        }
+    }
+
+    /// The pixels of the canvas, row by row.
+    pub fn pixels(&self) -> &[BltPixel] {
+        &self.pixels
+    }
+
+    /// Mutable access to the pixels of the canvas, row by row.
+    pub fn pixels_mut(&mut self) -> &mut [BltPixel] {
+        &mut self.pixels
+    }
+
+    /// Get the pixel at `(x, y)`, or `None` if it is out of bounds.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<BltPixel> {
+        if x < self.width && y < self.height {
+            Some(self.pixels[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    /// Encode the canvas as an uncompressed BMP image, see [`encode_bmp`].
+    pub fn to_bmp(&self) -> Vec<u8> {
+        encode_bmp(&self.pixels, self.resolution())
+    }
+
+    /// Copy the canvas to the screen, with its top-left corner at `dest`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the canvas doesn't fit on the
+    ///   screen at `dest`.
+    ///
+    /// [`Status::INVALID_PARAMETER`]: crate::Status::INVALID_PARAMETER
+    pub fn present_to(&self, gop: &mut GraphicsOutput, dest: (usize, usize)) -> Result {
+        if self.pixels.is_empty() {
+            return Ok(());
+        }
+        gop.blt(BltOp::BufferToVideo {
+            buffer: &self.pixels,
+            src: BltRegion::Full,
+            dest,
+            dims: self.resolution(),
+        })
+    }
+}
+
+impl DrawTarget for Canvas {
+    fn resolution(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: BltPixel) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: BltPixel) {
+        let rect = rect.clip(self.resolution());
+        for row in rect.y..rect.y + rect.height {
+            let start = row * self.width + rect.x;
+            self.pixels[start..start + rect.width].fill(color);
+        }
+    }
+}
+
+impl DrawTarget for BackBuffer {
+    fn resolution(&self) -> (usize, usize) {
+        BackBuffer::resolution(self)
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: BltPixel) {
+        BackBuffer::set_pixel(self, x, y, color);
+    }
+
+    fn fill_rect(&mut self, rect: Rect, color: BltPixel) {
+        BackBuffer::fill_rect(self, rect, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn white() -> BltPixel {
+        BltPixel::new(255, 255, 255)
+    }
+
+    /// FNV-1a hash of the color channels of `pixels`.
+    fn checksum(pixels: &[BltPixel]) -> u32 {
+        pixels
+            .iter()
+            .flat_map(|pixel| [pixel.red, pixel.green, pixel.blue])
+            .fold(0x811c_9dc5, |hash, byte| {
+                (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+            })
+    }
+
+    fn is_white(canvas: &Canvas, x: usize, y: usize) -> bool {
+        canvas
+            .pixel(x, y)
+            .map_or(false, |p| (p.red, p.green, p.blue) == (255, 255, 255))
+    }
+
+    fn white_count(canvas: &Canvas) -> usize {
+        let (width, height) = canvas.resolution();
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| is_white(canvas, x, y))
+            .count()
+    }
+
+    fn draw_pattern(target: &mut impl DrawTarget) {
+        target.clear(BltPixel::new(0, 0, 128));
+        target.fill_rect(Rect::new(4, 4, 8, 40), BltPixel::new(200, 0, 0));
+        target.draw_line((0, 0), (63, 47), white());
+        target.draw_line((63, 0), (0, 47), BltPixel::new(0, 255, 0));
+        target.draw_text((16, 8), "UEFI 2.9\nok?", white(), 2);
+        // Clipped at the edges.
+        target.fill_rect(Rect::new(60, 44, 10, 10), white());
+        target.draw_text((58, 0), "X", white(), 3);
+    }
+
+    #[test]
+    fn test_pattern_checksum() {
+        let mut canvas = Canvas::new(64, 48);
+        draw_pattern(&mut canvas);
+        assert_eq!(checksum(canvas.pixels()), 0xe836_3fa8);
+
+        // The back buffer draws the same pixels.
+        let mut buffer = BackBuffer::with_resolution(64, 48);
+        draw_pattern(&mut buffer);
+        assert_eq!(checksum(buffer.pixels()), checksum(canvas.pixels()));
+        assert_eq!(buffer.dirty_region(), Some(Rect::new(0, 0, 64, 48)));
+    }
+
+    #[test]
+    fn test_draw_line() {
+        let mut canvas = Canvas::new(8, 8);
+        canvas.draw_line((1, 2), (6, 2), white());
+        assert_eq!(white_count(&canvas), 6);
+        assert!(is_white(&canvas, 1, 2));
+        assert!(is_white(&canvas, 6, 2));
+
+        // Steep lines have one pixel per row, in either direction.
+        let mut canvas = Canvas::new(8, 8);
+        canvas.draw_line((5, 7), (3, 0), white());
+        assert_eq!(white_count(&canvas), 8);
+        assert!(is_white(&canvas, 5, 7));
+        assert!(is_white(&canvas, 3, 0));
+        for y in 0..8 {
+            assert_eq!((0..8).filter(|&x| is_white(&canvas, x, y)).count(), 1);
+        }
+
+        // A single point.
+        let mut canvas = Canvas::new(8, 8);
+        canvas.draw_line((4, 4), (4, 4), white());
+        assert_eq!(white_count(&canvas), 1);
+
+        // Pixels out of bounds are skipped.
+        let mut canvas = Canvas::new(8, 8);
+        canvas.draw_line((0, 0), (20, 20), white());
+        assert_eq!(white_count(&canvas), 8);
+    }
+
+    #[test]
+    fn test_draw_text() {
+        let mut canvas = Canvas::new(7, 5);
+        canvas.draw_text((0, 0), "a1", white(), 1);
+        let expected = [".#...#.", "#.#.##.", "###..#.", "#.#..#.", "#.#.###"];
+        for (y, row) in expected.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                assert_eq!(is_white(&canvas, x, y), c == '#', "pixel ({}, {})", x, y);
+            }
+        }
+
+        // Each font pixel becomes a square.
+        let mut canvas = Canvas::new(6, 10);
+        canvas.draw_text((0, 0), "-", white(), 2);
+        assert_eq!(white_count(&canvas), 12);
+        assert!(is_white(&canvas, 0, 4) && is_white(&canvas, 5, 5));
+
+        // Unknown characters are drawn as question marks.
+        let mut unknown = Canvas::new(3, 5);
+        unknown.draw_text((0, 0), "é", white(), 1);
+        let mut question = Canvas::new(3, 5);
+        question.draw_text((0, 0), "?", white(), 1);
+        assert_eq!(checksum(unknown.pixels()), checksum(question.pixels()));
+
+        assert_eq!(text_size("a1", 1), (7, 5));
+        assert_eq!(text_size("abc\nd", 2), (22, 22));
+        assert_eq!(text_size("", 2), (0, 0));
+    }
+
+    #[test]
+    fn test_to_bmp() {
+        let mut canvas = Canvas::new(3, 2);
+        canvas.set_pixel(0, 0, BltPixel::new(1, 2, 3));
+        let bmp = canvas.to_bmp();
+        assert_eq!(bmp, encode_bmp(canvas.pixels(), (3, 2)));
+        assert_eq!(&bmp[..2], b"BM");
+        // The top-left pixel is the first pixel of the last row, in BGR.
+        assert_eq!(&bmp[54 + 12..54 + 15], &[3, 2, 1]);
+    }
+}
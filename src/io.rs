@@ -0,0 +1,611 @@
+//! Byte streams, in the style of `std::io`.
+//!
+//! The [`Read`], [`Write`] and [`Seek`] traits mirror the traits of the
+//! same name in `std::io`, so that code parsing or producing byte streams
+//! can be used with files, serial devices and TCP connections alike. They
+//! are implemented for:
+//!
+//! * [`RegularFile`], which implements all three traits.
+//! * [`Serial`]. Reads and writes that time out after transferring some
+//!   bytes return that number of bytes, and fail with
+//!   [`Status::TIMEOUT`] otherwise.
+//! * [`Tcp6Stream`], a [`Tcp6`] connection along with the boot services
+//!   needed to wait for the transfers.
+//! * Byte slices for reading, and `Vec<u8>` for writing.
+//!
+//! [`BufReader`] and [`BufWriter`] add buffering to the slower streams,
+//! and [`copy`] copies a stream to another.
+
+use crate::alloc_api::vec;
+use crate::alloc_api::vec::Vec;
+use crate::proto::console::serial::Serial;
+use crate::proto::media::file::{File, FileInfo, RegularFile};
+use crate::proto::network::tcp6::Tcp6;
+use crate::table::boot::BootServices;
+use crate::Status;
+use core::cmp;
+use core::fmt::Debug;
+
+/// Error returned by the traits of this module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// A UEFI function failed with this status.
+    Status(Status),
+    /// The stream ended before the requested number of bytes was read.
+    UnexpectedEof,
+    /// A write didn't write anything before all of the data was written.
+    WriteZero,
+    /// A seek was to a negative position, or the position overflowed.
+    InvalidSeek,
+}
+
+impl From<Status> for Error {
+    fn from(status: Status) -> Self {
+        Self::Status(status)
+    }
+}
+
+impl<Data: Debug> From<crate::Error<Data>> for Error {
+    fn from(err: crate::Error<Data>) -> Self {
+        Self::Status(err.status())
+    }
+}
+
+/// Result of the functions of this module.
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// A source of bytes, like `std::io::Read`.
+pub trait Read {
+    /// Read bytes into `buf`, and return the number of bytes read. This
+    /// may be less than the size of `buf`; 0 is only returned for an empty
+    /// `buf`, or at the end of the stream.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Fill `buf` completely, reading as many times as needed.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::UnexpectedEof`]: the stream ended before `buf` was
+    ///   filled. The contents of `buf` are unspecified.
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(Error::UnexpectedEof),
+                n => buf = &mut buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Read all bytes until the end of the stream, append them to `buf`, and
+    /// return the number of bytes read.
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        let start = buf.len();
+        let mut chunk = [0; 512];
+        loop {
+            match self.read(&mut chunk)? {
+                0 => return Ok(buf.len() - start),
+                n => buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+}
+
+/// A destination for bytes, like `std::io::Write`.
+pub trait Write {
+    /// Write bytes from `buf`, and return the number of bytes written. This
+    /// may be less than the size of `buf`.
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    /// Make sure that buffered data reaches its destination.
+    fn flush(&mut self) -> Result<()>;
+
+    /// Write all of `buf`, writing as many times as needed.
+    ///
+    /// # Errors
+    ///
+    /// * [`Error::WriteZero`]: a write returned 0 before all of `buf` was
+    ///   written.
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(Error::WriteZero),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Position to seek to with [`Seek::seek`], like `std::io::SeekFrom`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeekFrom {
+    /// The given number of bytes from the start of the stream.
+    Start(u64),
+    /// The given number of bytes from the end of the stream.
+    End(i64),
+    /// The given number of bytes from the current position.
+    Current(i64),
+}
+
+/// A stream with a position that can be changed, like `std::io::Seek`.
+pub trait Seek {
+    /// Change the position in the stream, and return the new position
+    /// from the start of the stream.
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64>;
+
+    /// Get the current position from the start of the stream.
+    fn stream_position(&mut self) -> Result<u64> {
+        self.seek(SeekFrom::Current(0))
+    }
+}
+
+/// A [`Read`] stream with an internal buffer, like `std::io::BufRead`.
+pub trait BufRead: Read {
+    /// Get the buffered data, reading more from the stream if the buffer
+    /// is empty. An empty slice is returned at the end of the stream.
+    fn fill_buf(&mut self) -> Result<&[u8]>;
+
+    /// Mark `amount` bytes of the buffer as read, so they are not returned
+    /// by [`fill_buf`] and [`read`] anymore.
+    ///
+    /// [`fill_buf`]: Self::fill_buf
+    /// [`read`]: Read::read
+    fn consume(&mut self, amount: usize);
+
+    /// Read bytes until `delimiter` or the end of the stream, append them
+    /// to `buf` including the delimiter, and return the number of bytes
+    /// read.
+    fn read_until(&mut self, delimiter: u8, buf: &mut Vec<u8>) -> Result<usize> {
+        let mut read = 0;
+        loop {
+            let (done, used) = {
+                let available = self.fill_buf()?;
+                match available.iter().position(|&b| b == delimiter) {
+                    Some(i) => {
+                        buf.extend_from_slice(&available[..=i]);
+                        (true, i + 1)
+                    }
+                    None => {
+                        buf.extend_from_slice(available);
+                        (available.is_empty(), available.len())
+                    }
+                }
+            };
+            self.consume(used);
+            read += used;
+            if done {
+                return Ok(read);
+            }
+        }
+    }
+}
+
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+}
+
+impl Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let size = cmp::min(buf.len(), self.len());
+        let (head, tail) = self.split_at(size);
+        buf[..size].copy_from_slice(head);
+        *self = tail;
+        Ok(size)
+    }
+}
+
+impl Write for Vec<u8> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for RegularFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        Ok(RegularFile::read(self, buf)?)
+    }
+}
+
+impl Write for RegularFile {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match RegularFile::write(self, buf) {
+            Ok(()) => Ok(buf.len()),
+            // Report the bytes that were written before the error; the
+            // error will likely come up again with the next write.
+            Err(err) if *err.data() > 0 => Ok(*err.data()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(File::flush(self)?)
+    }
+}
+
+impl Seek for RegularFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(position) => position,
+            SeekFrom::End(offset) => {
+                let size = self.get_boxed_info::<FileInfo>()?.file_size();
+                add_offset(size, offset)?
+            }
+            SeekFrom::Current(offset) => add_offset(self.get_position()?, offset)?,
+        };
+        self.set_position(position)?;
+        Ok(position)
+    }
+}
+
+/// Add a signed offset to a position.
+fn add_offset(position: u64, offset: i64) -> Result<u64> {
+    if offset >= 0 {
+        position.checked_add(offset as u64)
+    } else {
+        position.checked_sub(offset.unsigned_abs())
+    }
+    .ok_or(Error::InvalidSeek)
+}
+
+impl Read for Serial<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match Serial::read(self, buf) {
+            Ok(()) => Ok(buf.len()),
+            Err(err) if err.status() == Status::TIMEOUT && *err.data() > 0 => Ok(*err.data()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl Write for Serial<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match Serial::write(self, buf) {
+            Ok(()) => Ok(buf.len()),
+            Err(err) if err.status() == Status::TIMEOUT && *err.data() > 0 => Ok(*err.data()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A connected [`Tcp6`] instance, as a byte stream.
+///
+/// Reads return 0 once the remote side has closed the connection and all
+/// data has been received. Every write is sent immediately with the push
+/// flag, so wrap the stream in a [`BufWriter`] to send fewer, larger
+/// segments.
+pub struct Tcp6Stream<'a> {
+    boot_services: &'a BootServices,
+    tcp: &'a mut Tcp6,
+}
+
+impl<'a> Tcp6Stream<'a> {
+    /// Create a stream for `tcp`, which must be connected.
+    pub fn new(boot_services: &'a BootServices, tcp: &'a mut Tcp6) -> Self {
+        Self { boot_services, tcp }
+    }
+}
+
+impl Read for Tcp6Stream<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match self.tcp.receive(self.boot_services, buf) {
+            Ok(size) => Ok(size),
+            Err(err) if err.status() == Status::CONNECTION_FIN => Ok(0),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl Write for Tcp6Stream<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.tcp.transmit(self.boot_services, buf, true)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The default size of the buffers of [`BufReader`] and [`BufWriter`].
+const DEFAULT_BUFFER_SIZE: usize = 4096;
+
+/// Adds buffering to a [`Read`] stream, like `std::io::BufReader`.
+pub struct BufReader<R> {
+    inner: R,
+    buffer: Vec<u8>,
+    // Range of `buffer` that hasn't been read yet.
+    position: usize,
+    filled: usize,
+}
+
+impl<R: Read> BufReader<R> {
+    /// Create a reader with the default buffer size.
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_BUFFER_SIZE, inner)
+    }
+
+    /// Create a reader with a buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self {
+            inner,
+            buffer: vec![0; capacity],
+            position: 0,
+            filled: 0,
+        }
+    }
+
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Get a mutable reference to the underlying stream. Reading from it
+    /// directly skips the buffered data.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Get back the underlying stream. Buffered data is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for BufReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        // Skip the buffer for large reads if it is empty.
+        if self.position == self.filled && buf.len() >= self.buffer.len() {
+            return self.inner.read(buf);
+        }
+        let size = {
+            let mut available = self.fill_buf()?;
+            available.read(buf)?
+        };
+        self.consume(size);
+        Ok(size)
+    }
+}
+
+impl<R: Read> BufRead for BufReader<R> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        if self.position == self.filled {
+            self.filled = self.inner.read(&mut self.buffer)?;
+            self.position = 0;
+        }
+        Ok(&self.buffer[self.position..self.filled])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position = cmp::min(self.position + amount, self.filled);
+    }
+}
+
+/// Adds buffering to a [`Write`] stream, like `std::io::BufWriter`.
+///
+/// The buffer is flushed when it is full, on [`flush`](Write::flush), and
+/// when the writer is dropped. Errors are ignored in the last case, so
+/// flush explicitly to handle them.
+pub struct BufWriter<W: Write> {
+    // Only `None` after `into_inner`.
+    inner: Option<W>,
+    buffer: Vec<u8>,
+    capacity: usize,
+}
+
+impl<W: Write> BufWriter<W> {
+    /// Create a writer with the default buffer size.
+    pub fn new(inner: W) -> Self {
+        Self::with_capacity(DEFAULT_BUFFER_SIZE, inner)
+    }
+
+    /// Create a writer with a buffer of `capacity` bytes.
+    pub fn with_capacity(capacity: usize, inner: W) -> Self {
+        Self {
+            inner: Some(inner),
+            buffer: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().unwrap()
+    }
+
+    /// Get a mutable reference to the underlying stream. Writing to it
+    /// directly bypasses the buffered data.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().unwrap()
+    }
+
+    /// Write the buffered data, and get back the underlying stream.
+    pub fn into_inner(mut self) -> Result<W> {
+        self.flush_buffer()?;
+        Ok(self.inner.take().unwrap())
+    }
+
+    /// Write the buffered data to the underlying stream, without flushing
+    /// the stream itself.
+    fn flush_buffer(&mut self) -> Result<()> {
+        let inner = self.inner.as_mut().unwrap();
+        let mut written = 0;
+        let result = loop {
+            if written == self.buffer.len() {
+                break Ok(());
+            }
+            match inner.write(&self.buffer[written..]) {
+                Ok(0) => break Err(Error::WriteZero),
+                Ok(n) => written += n,
+                Err(err) => break Err(err),
+            }
+        };
+        // Keep the data that couldn't be written.
+        self.buffer.drain(..written);
+        result
+    }
+}
+
+impl<W: Write> Write for BufWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if self.buffer.len() + buf.len() > self.capacity {
+            self.flush_buffer()?;
+        }
+        if buf.len() >= self.capacity {
+            self.get_mut().write(buf)
+        } else {
+            self.buffer.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.flush_buffer()?;
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write> Drop for BufWriter<W> {
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            // Errors can't be reported from here.
+            let _ = self.flush_buffer();
+        }
+    }
+}
+
+/// Copy all bytes from `reader` to `writer`, until the end of `reader`,
+/// and return the number of bytes copied.
+pub fn copy<R, W>(reader: &mut R, writer: &mut W) -> Result<u64>
+where
+    R: Read + ?Sized,
+    W: Write + ?Sized,
+{
+    let mut buffer = [0; 1024];
+    let mut copied = 0;
+    loop {
+        match reader.read(&mut buffer)? {
+            0 => return Ok(copied),
+            n => {
+                writer.write_all(&buffer[..n])?;
+                copied += n as u64;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A reader returning at most `chunk` bytes per read.
+    struct ChunkedReader<'a> {
+        data: &'a [u8],
+        chunk: usize,
+        reads: usize,
+    }
+
+    impl Read for ChunkedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            self.reads += 1;
+            let size = cmp::min(buf.len(), self.chunk);
+            self.data.read(&mut buf[..size])
+        }
+    }
+
+    #[test]
+    fn test_read_exact() {
+        let mut reader = ChunkedReader {
+            data: b"hello world",
+            chunk: 3,
+            reads: 0,
+        };
+        let mut buf = [0; 5];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello");
+        assert_eq!(reader.reads, 2);
+
+        let mut buf = [0; 10];
+        assert_eq!(reader.read_exact(&mut buf), Err(Error::UnexpectedEof));
+    }
+
+    #[test]
+    fn test_buf_reader() {
+        let reader = ChunkedReader {
+            data: b"first\nsecond\nlast",
+            chunk: 4,
+            reads: 0,
+        };
+        let mut reader = BufReader::with_capacity(8, reader);
+
+        let mut line = Vec::new();
+        assert_eq!(reader.read_until(b'\n', &mut line).unwrap(), 6);
+        assert_eq!(line, b"first\n");
+
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"second\nlast");
+    }
+
+    #[test]
+    fn test_buf_writer() {
+        let mut writer = BufWriter::with_capacity(4, Vec::new());
+        writer.write_all(b"ab").unwrap();
+        assert!(writer.get_ref().is_empty());
+        writer.write_all(b"cde").unwrap();
+        assert_eq!(writer.get_ref(), b"ab");
+        // Writes larger than the buffer go directly to the stream.
+        writer.write_all(b"fghijk").unwrap();
+        assert_eq!(writer.get_ref(), b"abcdefghijk");
+        writer.write_all(b"l").unwrap();
+        assert_eq!(writer.into_inner().unwrap(), b"abcdefghijkl");
+    }
+
+    #[test]
+    fn test_copy() {
+        let data: Vec<u8> = (0..=255).cycle().take(3000).collect();
+        let mut reader = ChunkedReader {
+            data: &data,
+            chunk: 700,
+            reads: 0,
+        };
+        let mut output = Vec::new();
+        assert_eq!(copy(&mut reader, &mut output).unwrap(), 3000);
+        assert_eq!(output, data);
+    }
+
+    #[test]
+    fn test_add_offset() {
+        assert_eq!(add_offset(10, 5), Ok(15));
+        assert_eq!(add_offset(10, -10), Ok(0));
+        assert_eq!(add_offset(10, -11), Err(Error::InvalidSeek));
+        assert_eq!(add_offset(u64::MAX, 1), Err(Error::InvalidSeek));
+    }
+}
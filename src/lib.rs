@@ -58,8 +58,30 @@ pub mod proto;
 
 pub mod prelude;
 
+pub mod arch;
+
+#[cfg(feature = "exts")]
+pub mod debug;
+
+#[cfg(feature = "exts")]
+pub mod graphics;
+
+#[cfg(feature = "exts")]
+pub mod io;
+
+#[cfg(feature = "selftest")]
+pub mod selftest;
+
+pub mod stopwatch;
+
 #[cfg(feature = "alloc")]
 pub mod alloc;
 
+#[cfg(feature = "loader")]
+pub mod loader;
+
 #[cfg(feature = "logger")]
 pub mod logger;
+
+#[cfg(feature = "exts")]
+pub mod rt;
@@ -0,0 +1,457 @@
+//! Loading of ELF64 executables.
+//!
+//! [`load`] loads a statically linked ELF64 executable, such as an OS
+//! kernel, at the physical addresses given in its program headers and
+//! returns its entry point. The executable must be built for the current
+//! target architecture, and must not require dynamic relocations.
+//!
+//! If the firmware provides the [`MemoryProtection`] protocol, the
+//! permissions of each segment are applied to its pages, so that writable
+//! segments can't be executed and executable segments can't be written.
+//! Each segment is loaded into its own pages, so segments must not share a
+//! page: such executables are rejected with [`LoadError::OverlappingSegments`].
+
+use super::{read_u16, read_u32, read_u64};
+use crate::proto::security::MemoryProtection;
+use crate::table::boot::{
+    AllocateType, BootServices, MemoryAttribute, MemoryType, OpenProtocolAttributes,
+    OpenProtocolParams,
+};
+use crate::Error;
+use core::ops::Range;
+
+const PAGE_SIZE: u64 = 4096;
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const EV_CURRENT: u8 = 1;
+
+const ET_EXEC: u16 = 2;
+const ET_DYN: u16 = 3;
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+
+const EHDR_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+
+/// The ELF machine type of the current target.
+#[cfg(target_arch = "x86_64")]
+const CURRENT_MACHINE: Option<u16> = Some(62);
+#[cfg(target_arch = "aarch64")]
+const CURRENT_MACHINE: Option<u16> = Some(183);
+#[cfg(target_arch = "riscv64")]
+const CURRENT_MACHINE: Option<u16> = Some(243);
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "riscv64"
+)))]
+const CURRENT_MACHINE: Option<u16> = None;
+
+/// Errors that can occur while loading an ELF executable.
+#[derive(Debug)]
+pub enum LoadError {
+    /// The data does not start with the ELF magic number.
+    InvalidMagic,
+    /// The executable is not a little-endian ELF64 file.
+    UnsupportedClass,
+    /// The executable is built for a different architecture.
+    WrongMachine {
+        /// The machine type of the executable.
+        found: u16,
+    },
+    /// The file is not an executable, for example a relocatable object.
+    NotExecutable,
+    /// The executable is position-independent or dynamically linked, and
+    /// would need dynamic relocations.
+    DynamicRelocations,
+    /// A segment is both writable and executable, so W^X can't be applied.
+    WritableAndExecutable,
+    /// Two segments occupy the same page, so they can't be allocated and
+    /// protected separately.
+    OverlappingSegments {
+        /// The index of the program header of the first segment.
+        first: usize,
+        /// The index of the program header of the second segment.
+        second: usize,
+    },
+    /// The headers are truncated or inconsistent.
+    Malformed,
+    /// The firmware failed to allocate or protect the memory of a segment.
+    Uefi(Error),
+}
+
+/// A loadable segment, from a `PT_LOAD` program header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Segment {
+    flags: u32,
+    file_range: Range<usize>,
+    /// Physical address of the segment.
+    address: u64,
+    mem_size: u64,
+}
+
+impl Segment {
+    /// The range of pages occupied by the segment.
+    fn pages(&self) -> Range<u64> {
+        let start = self.address & !(PAGE_SIZE - 1);
+        let end = (self.address + self.mem_size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        start..end
+    }
+
+    fn page_count(&self) -> usize {
+        let pages = self.pages();
+        ((pages.end - pages.start) / PAGE_SIZE) as usize
+    }
+}
+
+/// A validated ELF64 executable.
+struct ElfFile<'a> {
+    data: &'a [u8],
+    entry: u64,
+    ph_offset: usize,
+    ph_count: usize,
+}
+
+impl<'a> ElfFile<'a> {
+    fn parse(data: &'a [u8]) -> Result<Self, LoadError> {
+        if data.len() < ELF_MAGIC.len() || data[..ELF_MAGIC.len()] != ELF_MAGIC {
+            return Err(LoadError::InvalidMagic);
+        }
+        if data.len() < EHDR_SIZE {
+            return Err(LoadError::Malformed);
+        }
+        if data[4] != ELFCLASS64 || data[5] != ELFDATA2LSB || data[6] != EV_CURRENT {
+            return Err(LoadError::UnsupportedClass);
+        }
+
+        let machine = read_u16(data, 18);
+        if Some(machine) != CURRENT_MACHINE {
+            return Err(LoadError::WrongMachine { found: machine });
+        }
+        match read_u16(data, 16) {
+            ET_EXEC => {}
+            ET_DYN => return Err(LoadError::DynamicRelocations),
+            _ => return Err(LoadError::NotExecutable),
+        }
+
+        let ph_offset = usize::try_from(read_u64(data, 32)).map_err(|_| LoadError::Malformed)?;
+        let ph_entry_size = usize::from(read_u16(data, 54));
+        let ph_count = usize::from(read_u16(data, 56));
+        if ph_count != 0 && ph_entry_size != PHDR_SIZE {
+            return Err(LoadError::Malformed);
+        }
+        let ph_end = ph_count
+            .checked_mul(PHDR_SIZE)
+            .and_then(|size| size.checked_add(ph_offset))
+            .ok_or(LoadError::Malformed)?;
+        if ph_end > data.len() {
+            return Err(LoadError::Malformed);
+        }
+
+        let elf = Self {
+            data,
+            entry: read_u64(data, 24),
+            ph_offset,
+            ph_count,
+        };
+        for index in 0..ph_count {
+            if let Some(segment) = elf.segment(index)? {
+                elf.check_overlap(index, &segment)?;
+            }
+        }
+        Ok(elf)
+    }
+
+    /// Returns the loadable segment described by program header `index`, or
+    /// `None` for other program headers.
+    fn segment(&self, index: usize) -> Result<Option<Segment>, LoadError> {
+        let ph = &self.data[self.ph_offset + index * PHDR_SIZE..][..PHDR_SIZE];
+        match read_u32(ph, 0) {
+            PT_LOAD => {}
+            PT_DYNAMIC => return Err(LoadError::DynamicRelocations),
+            _ => return Ok(None),
+        }
+
+        let flags = read_u32(ph, 4);
+        let offset = read_u64(ph, 8);
+        let address = read_u64(ph, 24);
+        let file_size = read_u64(ph, 32);
+        let mem_size = read_u64(ph, 40);
+
+        if flags & PF_W != 0 && flags & PF_X != 0 {
+            return Err(LoadError::WritableAndExecutable);
+        }
+        let file_end = offset.checked_add(file_size).ok_or(LoadError::Malformed)?;
+        if file_size > mem_size
+            || file_end > self.data.len() as u64
+            || mem_size
+                .checked_add(PAGE_SIZE)
+                .and_then(|size| address.checked_add(size))
+                .is_none()
+        {
+            return Err(LoadError::Malformed);
+        }
+
+        Ok(Some(Segment {
+            flags,
+            file_range: offset as usize..file_end as usize,
+            address,
+            mem_size,
+        }))
+    }
+
+    /// Check that `segment`, from program header `index`, doesn't share a
+    /// page with the segments of the previous program headers.
+    ///
+    /// Executables have few program headers, so comparing each pair is
+    /// fine.
+    fn check_overlap(&self, index: usize, segment: &Segment) -> Result<(), LoadError> {
+        let pages = segment.pages();
+        for first in 0..index {
+            if let Some(other) = self.segment(first)? {
+                let other_pages = other.pages();
+                if pages.start < other_pages.end && other_pages.start < pages.end {
+                    return Err(LoadError::OverlappingSegments {
+                        first,
+                        second: index,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn segments(&self) -> impl Iterator<Item = Segment> + '_ {
+        // The segments were validated in `parse`.
+        (0..self.ph_count).filter_map(|index| self.segment(index).ok().flatten())
+    }
+}
+
+/// Loads the ELF64 executable `image` and returns the address of its entry
+/// point.
+///
+/// Each `PT_LOAD` segment is copied to newly allocated pages at its physical
+/// address, and the rest of the segment is zero-filled. If loading fails,
+/// all the pages allocated for the segments are freed again.
+///
+/// The pages are allocated as [`MemoryType::LOADER_CODE`] for executable
+/// segments and [`MemoryType::LOADER_DATA`] for the others, so they are
+/// still available after exiting boot services.
+pub fn load(bt: &BootServices, image: &[u8]) -> Result<u64, LoadError> {
+    let elf = ElfFile::parse(image)?;
+
+    for (index, segment) in elf.segments().enumerate() {
+        if let Err(err) = load_segment(bt, &elf, &segment) {
+            free_segments(bt, &elf, index);
+            return Err(err);
+        }
+    }
+
+    if let Err(err) = protect_segments(bt, &elf) {
+        free_segments(bt, &elf, elf.segments().count());
+        return Err(LoadError::Uefi(err));
+    }
+    Ok(elf.entry)
+}
+
+/// Frees the pages of the first `count` loadable segments.
+fn free_segments(bt: &BootServices, elf: &ElfFile, count: usize) {
+    for segment in elf.segments().take(count) {
+        // Nothing can be done if freeing fails.
+        let _ = bt.free_pages(segment.pages().start, segment.page_count());
+    }
+}
+
+fn load_segment(bt: &BootServices, elf: &ElfFile, segment: &Segment) -> Result<(), LoadError> {
+    let pages = segment.pages();
+    let mem_ty = if segment.flags & PF_X != 0 {
+        MemoryType::LOADER_CODE
+    } else {
+        MemoryType::LOADER_DATA
+    };
+    bt.allocate_pages(
+        AllocateType::Address(pages.start as usize),
+        mem_ty,
+        segment.page_count(),
+    )
+    .map_err(LoadError::Uefi)?;
+
+    // Safety: the pages were just allocated, and the segment lies within
+    // them.
+    unsafe {
+        let base = pages.start as usize as *mut u8;
+        bt.set_mem(base, (pages.end - pages.start) as usize, 0);
+        let src = &elf.data[segment.file_range.clone()];
+        bt.memmove(segment.address as usize as *mut u8, src.as_ptr(), src.len());
+    }
+    Ok(())
+}
+
+/// Applies the permissions of the segments to their pages with the
+/// [`MemoryProtection`] protocol, if it is available.
+fn protect_segments(bt: &BootServices, elf: &ElfFile) -> crate::Result {
+    let handle = match bt.get_handle_for_protocol::<MemoryProtection>() {
+        Ok(handle) => handle,
+        Err(_) => return Ok(()),
+    };
+    let protection = unsafe {
+        bt.open_protocol::<MemoryProtection>(
+            OpenProtocolParams {
+                handle,
+                agent: bt.image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )?
+    };
+
+    for segment in elf.segments() {
+        let pages = segment.pages();
+        if segment.flags & PF_X != 0 {
+            protection.clear_memory_attributes(pages.clone(), MemoryAttribute::EXECUTE_PROTECT)?;
+            protection.set_memory_attributes(pages, MemoryAttribute::READ_ONLY)?;
+        } else if segment.flags & PF_W != 0 {
+            protection.clear_memory_attributes(pages.clone(), MemoryAttribute::READ_ONLY)?;
+            protection.set_memory_attributes(pages, MemoryAttribute::EXECUTE_PROTECT)?;
+        } else {
+            protection.set_memory_attributes(
+                pages,
+                MemoryAttribute::READ_ONLY | MemoryAttribute::EXECUTE_PROTECT,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds an ELF executable with the given program headers, each given
+    /// as `(type, flags, offset, address, file_size, mem_size)`.
+    fn build_elf(phdrs: &[(u32, u32, u64, u64, u64, u64)]) -> [u8; 512] {
+        let mut data = [0; 512];
+        data[..4].copy_from_slice(&ELF_MAGIC);
+        data[4] = ELFCLASS64;
+        data[5] = ELFDATA2LSB;
+        data[6] = EV_CURRENT;
+        data[16..18].copy_from_slice(&ET_EXEC.to_le_bytes());
+        data[18..20].copy_from_slice(&CURRENT_MACHINE.unwrap().to_le_bytes());
+        data[24..32].copy_from_slice(&0x10_0000u64.to_le_bytes());
+        data[32..40].copy_from_slice(&(EHDR_SIZE as u64).to_le_bytes());
+        data[54..56].copy_from_slice(&(PHDR_SIZE as u16).to_le_bytes());
+        data[56..58].copy_from_slice(&(phdrs.len() as u16).to_le_bytes());
+
+        for (i, &(ty, flags, offset, address, file_size, mem_size)) in phdrs.iter().enumerate() {
+            let ph = &mut data[EHDR_SIZE + i * PHDR_SIZE..][..PHDR_SIZE];
+            ph[0..4].copy_from_slice(&ty.to_le_bytes());
+            ph[4..8].copy_from_slice(&flags.to_le_bytes());
+            ph[8..16].copy_from_slice(&offset.to_le_bytes());
+            ph[16..24].copy_from_slice(&address.to_le_bytes());
+            ph[24..32].copy_from_slice(&address.to_le_bytes());
+            ph[32..40].copy_from_slice(&file_size.to_le_bytes());
+            ph[40..48].copy_from_slice(&mem_size.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse() {
+        let data = build_elf(&[
+            (PT_LOAD, PF_X, 256, 0x10_0000, 16, 16),
+            (6, 0, 0, 0, 0, 0),
+            (PT_LOAD, PF_W, 272, 0x10_1000, 8, 0x2000),
+        ]);
+        let elf = ElfFile::parse(&data).unwrap();
+        assert_eq!(elf.entry, 0x10_0000);
+
+        let mut segments = elf.segments();
+        let text = segments.next().unwrap();
+        assert_eq!(text.file_range, 256..272);
+        assert_eq!(text.pages(), 0x10_0000..0x10_1000);
+        let data_segment = segments.next().unwrap();
+        assert_eq!(data_segment.pages(), 0x10_1000..0x10_3000);
+        assert_eq!(data_segment.page_count(), 2);
+        assert!(segments.next().is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let mut data = build_elf(&[]);
+        data[0] = 0;
+        assert!(matches!(
+            ElfFile::parse(&data),
+            Err(LoadError::InvalidMagic)
+        ));
+
+        let mut data = build_elf(&[]);
+        data[4] = 1;
+        assert!(matches!(
+            ElfFile::parse(&data),
+            Err(LoadError::UnsupportedClass)
+        ));
+
+        let mut data = build_elf(&[]);
+        data[18..20].copy_from_slice(&0xffffu16.to_le_bytes());
+        assert!(matches!(
+            ElfFile::parse(&data),
+            Err(LoadError::WrongMachine { found: 0xffff })
+        ));
+
+        let mut data = build_elf(&[]);
+        data[16..18].copy_from_slice(&ET_DYN.to_le_bytes());
+        assert!(matches!(
+            ElfFile::parse(&data),
+            Err(LoadError::DynamicRelocations)
+        ));
+
+        let data = build_elf(&[(PT_DYNAMIC, 0, 0, 0, 0, 0)]);
+        assert!(matches!(
+            ElfFile::parse(&data),
+            Err(LoadError::DynamicRelocations)
+        ));
+
+        let data = build_elf(&[(PT_LOAD, PF_W | PF_X, 256, 0x10_0000, 16, 16)]);
+        assert!(matches!(
+            ElfFile::parse(&data),
+            Err(LoadError::WritableAndExecutable)
+        ));
+
+        // The segment data is beyond the end of the file.
+        let data = build_elf(&[(PT_LOAD, PF_X, 256, 0x10_0000, 1024, 1024)]);
+        assert!(matches!(ElfFile::parse(&data), Err(LoadError::Malformed)));
+
+        // The file size is larger than the memory size.
+        let data = build_elf(&[(PT_LOAD, PF_X, 256, 0x10_0000, 16, 8)]);
+        assert!(matches!(ElfFile::parse(&data), Err(LoadError::Malformed)));
+
+        // The segment ends beyond the address space.
+        let data = build_elf(&[(PT_LOAD, 0, 256, 0x10_0000, 0, u64::MAX)]);
+        assert!(matches!(ElfFile::parse(&data), Err(LoadError::Malformed)));
+
+        // The data segment starts in the last page of the code segment.
+        let data = build_elf(&[
+            (PT_LOAD, PF_X, 256, 0x10_0000, 16, 16),
+            (6, 0, 0, 0, 0, 0),
+            (PT_LOAD, PF_W, 272, 0x10_0800, 8, 8),
+        ]);
+        assert!(matches!(
+            ElfFile::parse(&data),
+            Err(LoadError::OverlappingSegments {
+                first: 0,
+                second: 2
+            })
+        ));
+
+        assert!(matches!(
+            ElfFile::parse(&data[..32]),
+            Err(LoadError::Malformed)
+        ));
+    }
+}
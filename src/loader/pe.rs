@@ -0,0 +1,315 @@
+//! Inspection of PE/COFF images.
+//!
+//! [`parse`] validates the headers of a PE/COFF image, such as a UEFI
+//! application or driver, and returns the information needed to check it
+//! before passing it to [`BootServices::load_image`]. Only the headers are
+//! read; the image is not relocated or loaded.
+//!
+//! [`BootServices::load_image`]: crate::table::boot::BootServices::load_image
+
+use super::{read_u16, read_u32};
+use crate::proto::loaded_image::LoadedImage;
+
+const DOS_MAGIC: [u8; 2] = *b"MZ";
+const PE_SIGNATURE: [u8; 4] = *b"PE\0\0";
+
+/// Offset of the PE header's offset in the DOS header.
+const LFANEW_OFFSET: usize = 0x3c;
+
+const COFF_HEADER_SIZE: usize = 20;
+const SECTION_HEADER_SIZE: usize = 40;
+
+const PE32_MAGIC: u16 = 0x10b;
+const PE32_PLUS_MAGIC: u16 = 0x20b;
+
+/// Offset of the subsystem field in both the PE32 and PE32+ optional
+/// headers, which are only read up to and including that field.
+const SUBSYSTEM_OFFSET: usize = 68;
+
+newtype_enum! {
+    /// Machine type of a PE/COFF image.
+    pub enum MachineType: u16 => {
+        /// 32-bit x86.
+        I386 = 0x014c,
+        /// 32-bit ARM, which may contain Thumb code.
+        ARM = 0x01c2,
+        /// Itanium.
+        IA64 = 0x0200,
+        /// EFI byte code.
+        EBC = 0x0ebc,
+        /// 64-bit RISC-V.
+        RISCV64 = 0x5064,
+        /// 64-bit x86.
+        X64 = 0x8664,
+        /// 64-bit ARM.
+        AARCH64 = 0xaa64,
+    }
+}
+
+newtype_enum! {
+    /// Subsystem of a PE/COFF image.
+    ///
+    /// Only the UEFI subsystems are listed; [`parse`] rejects images with
+    /// other subsystems.
+    pub enum Subsystem: u16 => {
+        /// UEFI application.
+        EFI_APPLICATION = 10,
+        /// UEFI driver that is unloaded when boot services are exited.
+        EFI_BOOT_SERVICE_DRIVER = 11,
+        /// UEFI driver that stays loaded after boot services are exited.
+        EFI_RUNTIME_DRIVER = 12,
+    }
+}
+
+/// Errors that can occur while parsing a PE/COFF image.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The data does not start with the DOS `MZ` magic number.
+    InvalidDosMagic,
+    /// The PE header does not start with the `PE\0\0` signature.
+    InvalidPeSignature,
+    /// The optional header is neither in the PE32 nor in the PE32+ format.
+    UnsupportedFormat,
+    /// The image is not a UEFI application or driver.
+    NotEfiSubsystem {
+        /// The subsystem of the image.
+        found: u16,
+    },
+    /// The headers are truncated or inconsistent.
+    Malformed,
+}
+
+/// A section header from the section table of a PE/COFF image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Section {
+    /// Name of the section, padded with null bytes.
+    pub name: [u8; 8],
+    /// Size of the section when loaded into memory.
+    pub virtual_size: u32,
+    /// Address of the section when loaded into memory, relative to the
+    /// image base.
+    pub virtual_address: u32,
+    /// Size of the section's data in the file.
+    pub size_of_raw_data: u32,
+    /// Offset of the section's data in the file.
+    pub pointer_to_raw_data: u32,
+    /// Flags describing the section's contents and permissions.
+    pub characteristics: u32,
+}
+
+impl Section {
+    /// The name of the section, without the null padding.
+    pub fn name(&self) -> &[u8] {
+        let len = self.name.iter().position(|&b| b == 0).unwrap_or(8);
+        &self.name[..len]
+    }
+}
+
+/// The validated headers of a PE/COFF image, as returned by [`parse`].
+#[derive(Debug)]
+pub struct PeInfo<'a> {
+    machine: MachineType,
+    subsystem: Subsystem,
+    entry_point: u32,
+    size_of_image: u32,
+    section_table: &'a [u8],
+}
+
+impl<'a> PeInfo<'a> {
+    /// The architecture the image was built for.
+    pub fn machine(&self) -> MachineType {
+        self.machine
+    }
+
+    /// Whether the image is an application or a driver.
+    pub fn subsystem(&self) -> Subsystem {
+        self.subsystem
+    }
+
+    /// Address of the entry point, relative to the image base.
+    pub fn entry_point_rva(&self) -> u32 {
+        self.entry_point
+    }
+
+    /// Size in bytes of the image when loaded into memory.
+    pub fn size_of_image(&self) -> u32 {
+        self.size_of_image
+    }
+
+    /// Iterate over the section table.
+    pub fn sections(&self) -> impl Iterator<Item = Section> + 'a {
+        self.section_table
+            .chunks_exact(SECTION_HEADER_SIZE)
+            .map(|header| Section {
+                name: header[..8].try_into().unwrap(),
+                virtual_size: read_u32(header, 8),
+                virtual_address: read_u32(header, 12),
+                size_of_raw_data: read_u32(header, 16),
+                pointer_to_raw_data: read_u32(header, 20),
+                characteristics: read_u32(header, 36),
+            })
+    }
+}
+
+/// Parses and validates the headers of the PE/COFF image `image`.
+///
+/// The image must be a UEFI application or driver. Its machine type is not
+/// checked against the current architecture, so that images for other
+/// architectures can be inspected as well.
+pub fn parse(image: &[u8]) -> Result<PeInfo<'_>, ParseError> {
+    if image.len() < DOS_MAGIC.len() || image[..DOS_MAGIC.len()] != DOS_MAGIC {
+        return Err(ParseError::InvalidDosMagic);
+    }
+    if image.len() < LFANEW_OFFSET + 4 {
+        return Err(ParseError::Malformed);
+    }
+
+    let pe_offset = read_u32(image, LFANEW_OFFSET) as usize;
+    let opt_offset = pe_offset
+        .checked_add(PE_SIGNATURE.len() + COFF_HEADER_SIZE)
+        .ok_or(ParseError::Malformed)?;
+    let coff_offset = pe_offset + PE_SIGNATURE.len();
+    if image.len() < opt_offset {
+        return Err(ParseError::Malformed);
+    }
+    if image[pe_offset..coff_offset] != PE_SIGNATURE {
+        return Err(ParseError::InvalidPeSignature);
+    }
+
+    let coff = &image[coff_offset..opt_offset];
+    let machine = MachineType(read_u16(coff, 0));
+    let section_count = usize::from(read_u16(coff, 2));
+    let opt_size = usize::from(read_u16(coff, 16));
+
+    let section_offset = opt_offset + opt_size;
+    let section_end = section_offset + section_count * SECTION_HEADER_SIZE;
+    if opt_size < SUBSYSTEM_OFFSET + 2 || image.len() < section_end {
+        return Err(ParseError::Malformed);
+    }
+
+    let opt = &image[opt_offset..section_offset];
+    match read_u16(opt, 0) {
+        PE32_MAGIC | PE32_PLUS_MAGIC => {}
+        _ => return Err(ParseError::UnsupportedFormat),
+    }
+    let subsystem = Subsystem(read_u16(opt, SUBSYSTEM_OFFSET));
+    match subsystem {
+        Subsystem::EFI_APPLICATION
+        | Subsystem::EFI_BOOT_SERVICE_DRIVER
+        | Subsystem::EFI_RUNTIME_DRIVER => {}
+        Subsystem(found) => return Err(ParseError::NotEfiSubsystem { found }),
+    }
+
+    Ok(PeInfo {
+        machine,
+        subsystem,
+        entry_point: read_u32(opt, 16),
+        size_of_image: read_u32(opt, 56),
+        section_table: &image[section_offset..section_end],
+    })
+}
+
+/// Parses and validates the headers of an image that was loaded by the
+/// firmware, such as the currently running image.
+///
+/// See [`parse`] for details.
+pub fn parse_loaded_image(image: &LoadedImage) -> Result<PeInfo<'_>, ParseError> {
+    let (base, size) = image.info();
+    if base.is_null() {
+        return Err(ParseError::Malformed);
+    }
+    // Safety: the firmware keeps the image mapped at `base` for as long as
+    // its `LoadedImage` protocol is installed.
+    let data = unsafe { core::slice::from_raw_parts(base.cast::<u8>(), size as usize) };
+    parse(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PE_OFFSET: usize = 0x80;
+    const OPT_OFFSET: usize = PE_OFFSET + 4 + COFF_HEADER_SIZE;
+    const OPT_SIZE: usize = 240;
+
+    /// Builds the headers of a PE32+ image with a `.text` section.
+    fn build_pe(subsystem: u16) -> [u8; 512] {
+        let mut data = [0; 512];
+        data[..2].copy_from_slice(&DOS_MAGIC);
+        data[LFANEW_OFFSET..LFANEW_OFFSET + 4].copy_from_slice(&(PE_OFFSET as u32).to_le_bytes());
+        data[PE_OFFSET..PE_OFFSET + 4].copy_from_slice(&PE_SIGNATURE);
+
+        let coff = &mut data[PE_OFFSET + 4..OPT_OFFSET];
+        coff[0..2].copy_from_slice(&MachineType::X64.0.to_le_bytes());
+        coff[2..4].copy_from_slice(&1u16.to_le_bytes());
+        coff[16..18].copy_from_slice(&(OPT_SIZE as u16).to_le_bytes());
+
+        let opt = &mut data[OPT_OFFSET..OPT_OFFSET + OPT_SIZE];
+        opt[0..2].copy_from_slice(&PE32_PLUS_MAGIC.to_le_bytes());
+        opt[16..20].copy_from_slice(&0x1234u32.to_le_bytes());
+        opt[56..60].copy_from_slice(&0x3000u32.to_le_bytes());
+        opt[68..70].copy_from_slice(&subsystem.to_le_bytes());
+
+        let section = &mut data[OPT_OFFSET + OPT_SIZE..][..SECTION_HEADER_SIZE];
+        section[..5].copy_from_slice(b".text");
+        section[8..12].copy_from_slice(&0x800u32.to_le_bytes());
+        section[12..16].copy_from_slice(&0x1000u32.to_le_bytes());
+        section[16..20].copy_from_slice(&0xa00u32.to_le_bytes());
+        section[20..24].copy_from_slice(&0x400u32.to_le_bytes());
+        section[36..40].copy_from_slice(&0x6000_0020u32.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_parse() {
+        let data = build_pe(10);
+        let info = parse(&data).unwrap();
+        assert_eq!(info.machine(), MachineType::X64);
+        assert_eq!(info.subsystem(), Subsystem::EFI_APPLICATION);
+        assert_eq!(info.entry_point_rva(), 0x1234);
+        assert_eq!(info.size_of_image(), 0x3000);
+
+        assert_eq!(info.sections().count(), 1);
+        let section = info.sections().next().unwrap();
+        assert_eq!(section.name(), b".text");
+        assert_eq!(section.virtual_address, 0x1000);
+        assert_eq!(section.virtual_size, 0x800);
+        assert_eq!(section.pointer_to_raw_data, 0x400);
+        assert_eq!(section.size_of_raw_data, 0xa00);
+        assert_eq!(section.characteristics, 0x6000_0020);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let mut data = build_pe(10);
+        data[0] = 0;
+        assert!(matches!(parse(&data), Err(ParseError::InvalidDosMagic)));
+
+        let mut data = build_pe(10);
+        data[PE_OFFSET] = 0;
+        assert!(matches!(parse(&data), Err(ParseError::InvalidPeSignature)));
+
+        let mut data = build_pe(10);
+        data[OPT_OFFSET] = 0;
+        assert!(matches!(parse(&data), Err(ParseError::UnsupportedFormat)));
+
+        // Windows console application.
+        let data = build_pe(3);
+        assert!(matches!(
+            parse(&data),
+            Err(ParseError::NotEfiSubsystem { found: 3 })
+        ));
+
+        // The section table is truncated.
+        let data = build_pe(10);
+        assert!(matches!(
+            parse(&data[..OPT_OFFSET + OPT_SIZE + 8]),
+            Err(ParseError::Malformed)
+        ));
+
+        // The PE header is beyond the end of the file.
+        let mut data = build_pe(10);
+        data[LFANEW_OFFSET..LFANEW_OFFSET + 4].copy_from_slice(&0xffff_fff0u32.to_le_bytes());
+        assert!(matches!(parse(&data), Err(ParseError::Malformed)));
+    }
+}
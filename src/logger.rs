@@ -56,13 +56,7 @@ impl log::Log for Logger {
     fn log(&self, record: &log::Record) {
         if let Some(mut ptr) = self.writer {
             let writer = unsafe { ptr.as_mut() };
-            let result = DecoratedLog::write(
-                writer,
-                record.level(),
-                record.args(),
-                record.file().unwrap_or("<unknown file>"),
-                record.line().unwrap_or(0),
-            );
+            let result = write_record(writer, record);
 
             // Some UEFI implementations, such as the one used by VirtualBox,
             // may intermittently drop out some text from SimpleTextOutput and
@@ -92,6 +86,21 @@ impl log::Log for Logger {
 unsafe impl Sync for Logger {}
 unsafe impl Send for Logger {}
 
+/// Write `record` to `writer` in the format used by [`Logger`], with the
+/// level, file and line in front of each line of the message.
+///
+/// This lets loggers that send records to other outputs, such as a serial
+/// device or a file, produce the same output as the console.
+pub fn write_record<W: fmt::Write>(writer: &mut W, record: &log::Record) -> fmt::Result {
+    DecoratedLog::write(
+        writer,
+        record.level(),
+        record.args(),
+        record.file().unwrap_or("<unknown file>"),
+        record.line().unwrap_or(0),
+    )
+}
+
 /// Writer wrapper which prints a log level in front of every line of text
 ///
 /// This is less easy than it sounds because...
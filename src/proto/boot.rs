@@ -0,0 +1,93 @@
+//! Boot manager protocols.
+
+use crate::data_types::Guid;
+use crate::proto::device_path::{DevicePath, FfiDevicePath};
+use crate::proto::Protocol;
+use crate::{unsafe_guid, Result, Status};
+
+newtype_enum! {
+    /// A class of devices that can be connected with
+    /// [`BootManagerPolicy::connect_device_class`].
+    pub enum DeviceClass: Guid => {
+        /// Console devices, such as keyboards and displays.
+        CONSOLE = Guid::from_values(
+            0xcab0e94c,
+            0xe15f,
+            0x11e3,
+            0x918d,
+            0xb8e8562cbafa,
+        ),
+
+        /// Network devices.
+        NETWORK = Guid::from_values(
+            0xd04159dc,
+            0xe15f,
+            0x11e3,
+            0xb261,
+            0xb8e8562cbafa,
+        ),
+
+        /// All devices, including storage devices, which have no class of
+        /// their own.
+        CONNECT_ALL = Guid::from_values(
+            0x113b2126,
+            0xfc8a,
+            0x11e3,
+            0xbd6c,
+            0xb8e8562cbafa,
+        ),
+    }
+}
+
+/// The boot manager policy protocol.
+///
+/// This protocol lets an application ask the boot manager to connect devices
+/// according to the platform policy, instead of calling
+/// [`BootServices::connect_controller`] on every controller.
+///
+/// [`BootServices::connect_controller`]: crate::table::boot::BootServices::connect_controller
+#[repr(C)]
+#[unsafe_guid("fedf8e0c-e147-11e3-9903-b8e8562cbafa")]
+#[derive(Protocol)]
+pub struct BootManagerPolicy {
+    revision: u64,
+    connect_device_path: extern "efiapi" fn(
+        this: &Self,
+        device_path: *const FfiDevicePath,
+        recursive: bool,
+    ) -> Status,
+    connect_device_class: extern "efiapi" fn(this: &Self, class: &Guid) -> Status,
+}
+
+impl BootManagerPolicy {
+    /// Connects the device identified by `device_path`, and if `recursive`
+    /// is set, all of its children, using the platform policy.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the device path could not be found.
+    /// * [`Status::SECURITY_VIOLATION`]: the platform policy does not allow
+    ///   connecting this device.
+    /// * [`Status::UNSUPPORTED`]: the platform does not support connecting
+    ///   devices this way. Callers can fall back to calling
+    ///   [`BootServices::connect_controller`] themselves.
+    ///
+    /// [`BootServices::connect_controller`]: crate::table::boot::BootServices::connect_controller
+    pub fn connect_device_path(&self, device_path: &DevicePath, recursive: bool) -> Result {
+        (self.connect_device_path)(self, device_path.as_ffi_ptr(), recursive).into()
+    }
+
+    /// Connects all devices of the given class using the platform policy.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the class is not supported by the platform.
+    /// * [`Status::UNSUPPORTED`]: the platform does not support connecting
+    ///   devices by class. Callers can fall back to calling
+    ///   [`BootServices::connect_controller`] themselves.
+    ///
+    /// [`BootServices::connect_controller`]: crate::table::boot::BootServices::connect_controller
+    pub fn connect_device_class(&self, class: DeviceClass) -> Result {
+        (self.connect_device_class)(self, &class.0).into()
+    }
+}
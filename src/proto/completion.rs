@@ -0,0 +1,107 @@
+//! Waiting for the completion tokens of asynchronous protocol functions.
+//!
+//! Asynchronous functions of protocols such as [`Tcp6`] and [`Udp6`] take a
+//! completion token (`EFI_*_COMPLETION_TOKEN`), which starts with an event
+//! and a status. The driver sets the status and signals the event when the
+//! operation completes. [`CompletionEvent`] creates the event, and
+//! [`CompletionEvent::block_on`] submits an operation and waits for it, so
+//! that the blocking wrappers of these functions don't each repeat that
+//! logic.
+//!
+//! [`Tcp6`]: super::network::tcp6::Tcp6
+//! [`Udp6`]: super::network::udp6::Udp6
+
+use crate::table::boot::{BootServices, EventType, Tpl};
+use crate::{Event, Result, Status};
+use core::ptr;
+
+/// An event used to wait for the completion token of an asynchronous
+/// operation.
+///
+/// The event is created at [`Tpl::CALLBACK`], without a notification
+/// function. Drivers usually complete operations from their own
+/// notification functions, so the caller must wait below
+/// [`Tpl::CALLBACK`] for the operations to make progress.
+///
+/// The event is closed when this is dropped, so the operation must have
+/// completed or been cancelled by then.
+pub(crate) struct CompletionEvent<'boot> {
+    boot_services: &'boot BootServices,
+    event: Option<Event>,
+}
+
+impl<'boot> CompletionEvent<'boot> {
+    pub(crate) fn new(boot_services: &'boot BootServices) -> Result<Self> {
+        let event =
+            unsafe { boot_services.create_event(EventType::empty(), Tpl::CALLBACK, None, None)? };
+        Ok(Self {
+            boot_services,
+            event: Some(event),
+        })
+    }
+
+    /// Returns a copy of the event to be stored in a completion token.
+    pub(crate) fn event(&self) -> Event {
+        unsafe { self.event.as_ref().unwrap().unsafe_clone() }
+    }
+
+    /// Waits for the event to be signaled, calling `poll` in between
+    /// checks to speed up the processing of packets. Returns `false` if
+    /// checking the event failed, in which case the caller must cancel the
+    /// operation.
+    pub(crate) fn wait(&self, mut poll: impl FnMut()) -> bool {
+        loop {
+            match self.boot_services.check_event(self.event.as_ref().unwrap()) {
+                Ok(true) => return true,
+                Ok(false) => poll(),
+                Err(_) => return false,
+            }
+        }
+    }
+
+    /// Starts an operation with `submit`, waits for it to complete, and
+    /// returns the status stored in its completion token.
+    ///
+    /// `poll` is called while waiting, see [`wait`]. If waiting fails,
+    /// `cancel` is called to cancel the operation, and [`Status::ABORTED`]
+    /// is returned. Without `cancel`, for protocols that can't cancel
+    /// operations, waiting is retried until the operation completes.
+    ///
+    /// # Safety
+    ///
+    /// The completion token passed to the driver by `submit` must hold
+    /// [`event`], and `status` must point to its status. The token, and the
+    /// buffers it refers to, must stay valid until this returns: the driver
+    /// is done with them once the operation has completed or been cancelled.
+    ///
+    /// [`event`]: Self::event
+    /// [`wait`]: Self::wait
+    pub(crate) unsafe fn block_on(
+        &self,
+        submit: impl FnOnce() -> Status,
+        status: *const Status,
+        mut poll: impl FnMut(),
+        cancel: Option<impl FnOnce()>,
+    ) -> Result {
+        Result::from(submit())?;
+        if !self.wait(&mut poll) {
+            match cancel {
+                Some(cancel) => {
+                    cancel();
+                    return Err(Status::ABORTED.into());
+                }
+                None => while !self.wait(&mut poll) {},
+            }
+        }
+        // The driver updates the status behind the compiler's back.
+        ptr::read_volatile(status).into()
+    }
+}
+
+impl Drop for CompletionEvent<'_> {
+    fn drop(&mut self) {
+        if let Some(event) = self.event.take() {
+            let _ = self.boot_services.close_event(event);
+        }
+    }
+}
@@ -52,7 +52,8 @@
 //! the frame buffer gets sent to the screen, but it's safe to assume that
 //! the graphics card will re-draw the buffer at around the monitor's refresh rate.
 //! You will have to implement your own double buffering if you want to
-//! avoid tearing with animations.
+//! avoid tearing with animations. With the `exts` feature, [`BackBuffer`]
+//! provides an off-screen buffer that can be presented in a single blit.
 
 use crate::proto::Protocol;
 use crate::{unsafe_guid, Result, Status};
@@ -60,6 +61,13 @@ use core::marker::PhantomData;
 use core::mem;
 use core::ptr;
 
+#[cfg(feature = "exts")]
+use crate::alloc_api::{vec, vec::Vec};
+#[cfg(feature = "exts")]
+use crate::proto::media::file::{Directory, File, FileAttribute};
+#[cfg(feature = "exts")]
+use crate::{CStr16, ResultExt};
+
 /// Provides access to the video hardware's frame buffer.
 ///
 /// The GOP can be used to set the properties of the frame buffer,
@@ -366,6 +374,123 @@ pub struct PixelBitmask {
     pub reserved: u32,
 }
 
+/// A single color channel of a [`PixelConverter`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Channel {
+    shift: u32,
+    /// Maximum value of the channel, i.e. the mask shifted down to bit 0.
+    max: u32,
+}
+
+impl Channel {
+    /// Returns `None` if the mask is empty or not contiguous.
+    fn new(mask: u32) -> Option<Self> {
+        if mask == 0 {
+            return None;
+        }
+        let shift = mask.trailing_zeros();
+        let max = mask >> shift;
+        if max & max.wrapping_add(1) != 0 {
+            return None;
+        }
+        Some(Self { shift, max })
+    }
+
+    fn pack(&self, value: u8) -> u32 {
+        let max = u64::from(self.max);
+        let scaled = (u64::from(value) * max + 127) / 255;
+        (scaled as u32) << self.shift
+    }
+
+    fn unpack(&self, packed: u32) -> u8 {
+        let max = u64::from(self.max);
+        let value = u64::from((packed >> self.shift) & self.max);
+        ((value * 255 + max / 2) / max) as u8
+    }
+}
+
+/// Converts between [`BltPixel`]s and the pixel values stored in a frame
+/// buffer.
+///
+/// This supports the [`PixelFormat::Rgb`] and [`PixelFormat::Bgr`] formats,
+/// as well as [`PixelFormat::Bitmask`] formats with 16, 24 or 32 bits per
+/// pixel. Channels with fewer or more than 8 bits are scaled accordingly.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PixelConverter {
+    red: Channel,
+    green: Channel,
+    blue: Channel,
+    bytes_per_pixel: usize,
+}
+
+impl PixelConverter {
+    /// Create a converter for the pixel format of a graphics mode.
+    ///
+    /// Returns `None` for [`PixelFormat::BltOnly`], and for bitmasks that
+    /// are not supported (see [`from_bitmask`]).
+    ///
+    /// [`from_bitmask`]: PixelConverter::from_bitmask
+    pub fn new(info: &ModeInfo) -> Option<Self> {
+        match info.pixel_format() {
+            PixelFormat::Rgb => Self::from_bitmask(PixelBitmask {
+                red: 0x0000_00ff,
+                green: 0x0000_ff00,
+                blue: 0x00ff_0000,
+                reserved: 0xff00_0000,
+            }),
+            PixelFormat::Bgr => Self::from_bitmask(PixelBitmask {
+                red: 0x00ff_0000,
+                green: 0x0000_ff00,
+                blue: 0x0000_00ff,
+                reserved: 0xff00_0000,
+            }),
+            PixelFormat::Bitmask => Self::from_bitmask(info.mask),
+            PixelFormat::BltOnly => None,
+        }
+    }
+
+    /// Create a converter from a custom pixel bitmask.
+    ///
+    /// The size of a pixel is derived from the highest bit set in any of the
+    /// masks. Returns `None` if one of the color masks is empty or not
+    /// contiguous, or if the pixel size is not 16, 24 or 32 bits.
+    pub fn from_bitmask(mask: PixelBitmask) -> Option<Self> {
+        let all = mask.red | mask.green | mask.blue | mask.reserved;
+        let bits = 32 - all.leading_zeros() as usize;
+        let bytes_per_pixel = (bits + 7) / 8;
+        if !(2..=4).contains(&bytes_per_pixel) {
+            return None;
+        }
+
+        Some(Self {
+            red: Channel::new(mask.red)?,
+            green: Channel::new(mask.green)?,
+            blue: Channel::new(mask.blue)?,
+            bytes_per_pixel,
+        })
+    }
+
+    /// Returns the size of a pixel in bytes.
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.bytes_per_pixel
+    }
+
+    /// Convert a pixel to its value in the frame buffer. Reserved bits are
+    /// set to zero.
+    pub fn pack(&self, pixel: BltPixel) -> u32 {
+        self.red.pack(pixel.red) | self.green.pack(pixel.green) | self.blue.pack(pixel.blue)
+    }
+
+    /// Convert a value read from the frame buffer to a pixel.
+    pub fn unpack(&self, packed: u32) -> BltPixel {
+        BltPixel::new(
+            self.red.unpack(packed),
+            self.green.unpack(packed),
+            self.blue.unpack(packed),
+        )
+    }
+}
+
 /// Represents a graphics mode compatible with a given graphics device.
 pub struct Mode {
     index: u32,
@@ -496,6 +621,67 @@ impl From<u32> for BltPixel {
     }
 }
 
+/// Blend `src` over `dst`, with an opacity of `alpha` for `src`.
+///
+/// An `alpha` of 0 returns `dst` and an `alpha` of 255 returns `src`. Each
+/// channel is computed as `(src * alpha + dst * (255 - alpha)) / 255`,
+/// rounded to the nearest integer. Only integer arithmetic is used, so this
+/// does not depend on the FPU being available.
+pub fn blend_pixel(dst: BltPixel, src: BltPixel, alpha: u8) -> BltPixel {
+    let alpha = u16::from(alpha);
+    let blend = |dst: u8, src: u8| {
+        let sum = u16::from(src) * alpha + u16::from(dst) * (255 - alpha);
+        // The sum is at most 255 * 255, so the result fits in a `u8`.
+        ((sum + 127) / 255) as u8
+    };
+    BltPixel::new(
+        blend(dst.red, src.red),
+        blend(dst.green, src.green),
+        blend(dst.blue, src.blue),
+    )
+}
+
+/// Fill the first `dims.1` rows of `dims.0` pixels of `buffer` with a
+/// vertical linear gradient, from `top` in the first row to `bottom` in the
+/// last row.
+///
+/// Each row is interpolated with integer arithmetic, rounded to the nearest
+/// integer, so the first and last rows are exactly `top` and `bottom`. A
+/// single row is filled with `top`.
+///
+/// # Panics
+///
+/// Panics if `buffer` has fewer than `dims.0 * dims.1` pixels.
+pub fn fill_gradient(
+    buffer: &mut [BltPixel],
+    (width, height): (usize, usize),
+    top: BltPixel,
+    bottom: BltPixel,
+) {
+    assert!(
+        width.saturating_mul(height) <= buffer.len(),
+        "BltBuffer access out of bounds"
+    );
+    if width == 0 {
+        return;
+    }
+
+    let steps = height.saturating_sub(1).max(1) as u64;
+    let lerp = |top: u8, bottom: u8, row: u64| {
+        let sum = u64::from(top) * (steps - row) + u64::from(bottom) * row;
+        // A weighted average of two `u8` values fits in a `u8`.
+        ((sum + steps / 2) / steps) as u8
+    };
+    for (row, pixels) in buffer.chunks_exact_mut(width).take(height).enumerate() {
+        let row = row as u64;
+        pixels.fill(BltPixel::new(
+            lerp(top.red, bottom.red, row),
+            lerp(top.green, bottom.green, row),
+            lerp(top.blue, bottom.blue, row),
+        ));
+    }
+}
+
 /// Region of the `BltBuffer` which we are operating on
 ///
 /// Some `Blt` operations can operate on either the full `BltBuffer` or a
@@ -562,6 +748,519 @@ pub enum BltOp<'buf> {
     },
 }
 
+/// A rectangle on the screen or in a buffer.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Rect {
+    /// X coordinate of the top-left corner.
+    pub x: usize,
+    /// Y coordinate of the top-left corner.
+    pub y: usize,
+    /// Width of the rectangle.
+    pub width: usize,
+    /// Height of the rectangle.
+    pub height: usize,
+}
+
+impl Rect {
+    /// Create a new rectangle from its top-left corner and its dimensions.
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Returns `true` if the rectangle contains no pixels.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Returns the smallest rectangle containing both `self` and `other`.
+    #[must_use]
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Rect::new(x, y, right - x, bottom - y)
+    }
+
+    /// Returns the part of the rectangle that fits within `(width, height)`.
+    #[must_use]
+    pub fn clip(&self, (width, height): (usize, usize)) -> Rect {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        let right = self.x.saturating_add(self.width).min(width);
+        let bottom = self.y.saturating_add(self.height).min(height);
+        Rect::new(x, y, right - x, bottom - y)
+    }
+}
+
+/// An off-screen buffer for flicker-free rendering.
+///
+/// Drawing happens in a buffer in regular memory, which is then copied to
+/// the screen with a single [`BltOp::BufferToVideo`] operation. Since the
+/// firmware converts the pixels during the blit, this works with every pixel
+/// format, including [`PixelFormat::BltOnly`].
+///
+/// The buffer keeps track of the region that was modified since it was last
+/// presented, so that [`present_dirty`] only needs to copy that region.
+///
+/// [`present_dirty`]: BackBuffer::present_dirty
+#[cfg(feature = "exts")]
+pub struct BackBuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<BltPixel>,
+    dirty: Option<Rect>,
+}
+
+#[cfg(feature = "exts")]
+impl BackBuffer {
+    /// Create a black back buffer with the resolution of the current mode.
+    pub fn new(gop: &GraphicsOutput) -> Self {
+        let (width, height) = gop.current_mode_info().resolution();
+        Self::with_resolution(width, height)
+    }
+
+    /// Create a black back buffer with the given resolution.
+    pub fn with_resolution(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![BltPixel::new(0, 0, 0); width * height],
+            dirty: None,
+        }
+    }
+
+    /// The width and height of the buffer.
+    pub fn resolution(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// The pixels of the buffer, row by row.
+    pub fn pixels(&self) -> &[BltPixel] {
+        &self.pixels
+    }
+
+    /// Mutable access to the pixels of the buffer, row by row.
+    ///
+    /// The whole buffer is marked as dirty.
+    pub fn pixels_mut(&mut self) -> &mut [BltPixel] {
+        self.mark_dirty(Rect::new(0, 0, self.width, self.height));
+        &mut self.pixels
+    }
+
+    /// Get the pixel at `(x, y)`, or `None` if it is out of bounds.
+    pub fn pixel(&self, x: usize, y: usize) -> Option<BltPixel> {
+        if x < self.width && y < self.height {
+            Some(self.pixels[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    /// Set the pixel at `(x, y)`. Pixels out of bounds are ignored.
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: BltPixel) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+            self.mark_dirty(Rect::new(x, y, 1, 1));
+        }
+    }
+
+    /// Fill a rectangle with a color. The rectangle is clipped to the buffer.
+    pub fn fill_rect(&mut self, rect: Rect, color: BltPixel) {
+        let rect = rect.clip(self.resolution());
+        for row in rect.y..rect.y + rect.height {
+            let start = row * self.width + rect.x;
+            self.pixels[start..start + rect.width].fill(color);
+        }
+        self.mark_dirty(rect);
+    }
+
+    /// Fill the whole buffer with a color.
+    pub fn clear(&mut self, color: BltPixel) {
+        self.fill_rect(Rect::new(0, 0, self.width, self.height), color);
+    }
+
+    /// Mark a region as modified, so that it is copied by the next call to
+    /// [`present_dirty`](Self::present_dirty).
+    pub fn mark_dirty(&mut self, rect: Rect) {
+        let rect = rect.clip(self.resolution());
+        if rect.is_empty() {
+            return;
+        }
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(&rect),
+            None => rect,
+        });
+    }
+
+    /// The region modified since the buffer was last presented, if any.
+    pub fn dirty_region(&self) -> Option<Rect> {
+        self.dirty
+    }
+
+    /// Copy the whole buffer to the top-left corner of the screen.
+    pub fn present(&mut self, gop: &mut GraphicsOutput) -> Result {
+        self.present_region(gop, Rect::new(0, 0, self.width, self.height))
+    }
+
+    /// Copy only the region modified since the buffer was last presented.
+    pub fn present_dirty(&mut self, gop: &mut GraphicsOutput) -> Result {
+        match self.dirty {
+            Some(dirty) => self.present_region(gop, dirty),
+            None => Ok(()),
+        }
+    }
+
+    /// Copy a region of the buffer to the same location on the screen.
+    ///
+    /// The region is clipped to the buffer. If it covers the whole dirty
+    /// region, the buffer is no longer considered dirty.
+    pub fn present_region(&mut self, gop: &mut GraphicsOutput, rect: Rect) -> Result {
+        let rect = rect.clip(self.resolution());
+        if !rect.is_empty() {
+            gop.blt(BltOp::BufferToVideo {
+                buffer: &self.pixels,
+                src: BltRegion::SubRectangle {
+                    coords: (rect.x, rect.y),
+                    px_stride: self.width,
+                },
+                dest: (rect.x, rect.y),
+                dims: (rect.width, rect.height),
+            })?;
+        }
+        if let Some(dirty) = self.dirty {
+            if rect.union(&dirty) == rect {
+                self.dirty = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What a [`ProgressBar`] last drew.
+#[cfg(feature = "exts")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ProgressState {
+    /// The completion in percent.
+    Percent(u8),
+    /// The offset of the stripes of the indeterminate animation.
+    Indeterminate(usize),
+}
+
+/// A progress bar drawn into a region of a [`BackBuffer`].
+///
+/// The bar is filled from the left in proportion to the progress, with the
+/// percentage written in its middle if it is tall enough. When the total is
+/// unknown, moving stripes are drawn instead.
+///
+/// To avoid redrawing the screen for every block of a long operation,
+/// [`update`] only draws when the percentage changes, and reports whether
+/// the buffer needs to be presented:
+///
+/// ```no_run
+/// use uefi::proto::console::gop::{BackBuffer, GraphicsOutput, ProgressBar};
+///
+/// fn show_progress(
+///     gop: &mut GraphicsOutput,
+///     buffer: &mut BackBuffer,
+///     bar: &mut ProgressBar,
+///     done: u64,
+///     total: u64,
+/// ) {
+///     if bar.update(buffer, done, total) {
+///         let _ = buffer.present_dirty(gop);
+///     }
+/// }
+/// ```
+///
+/// [`update`]: ProgressBar::update
+#[cfg(feature = "exts")]
+#[derive(Debug, Clone)]
+pub struct ProgressBar {
+    rect: Rect,
+    foreground: BltPixel,
+    background: BltPixel,
+    state: Option<ProgressState>,
+}
+
+#[cfg(feature = "exts")]
+impl ProgressBar {
+    /// Create a progress bar drawn in `rect`, in white on dark gray.
+    pub fn new(rect: Rect) -> Self {
+        Self {
+            rect,
+            foreground: BltPixel::new(255, 255, 255),
+            background: BltPixel::new(64, 64, 64),
+            state: None,
+        }
+    }
+
+    /// Use the given colors for the filled part of the bar and its
+    /// background. The label is drawn with the opposite color of the part
+    /// of the bar it is on.
+    #[must_use]
+    pub fn with_colors(mut self, foreground: BltPixel, background: BltPixel) -> Self {
+        self.foreground = foreground;
+        self.background = background;
+        self.state = None;
+        self
+    }
+
+    /// The region of the bar.
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// Draw the bar on the next update even if the progress didn't change,
+    /// for example after the buffer was cleared.
+    pub fn invalidate(&mut self) {
+        self.state = None;
+    }
+
+    /// Draw the progress of an operation that has done `current` out of
+    /// `total` units of work, such as bytes. If `total` is 0, the total is
+    /// unknown and the indeterminate animation advances by one step.
+    ///
+    /// Returns `true` if the bar was drawn, in which case the buffer must be
+    /// presented for the change to be visible. In determinate mode, the bar
+    /// is only drawn when the percentage changes.
+    pub fn update(&mut self, buffer: &mut BackBuffer, current: u64, total: u64) -> bool {
+        let state = if total == 0 {
+            match self.state {
+                Some(ProgressState::Indeterminate(offset)) => {
+                    ProgressState::Indeterminate(offset.wrapping_add(1))
+                }
+                _ => ProgressState::Indeterminate(0),
+            }
+        } else {
+            let percent = u128::from(current.min(total)) * 100 / u128::from(total);
+            ProgressState::Percent(percent as u8)
+        };
+        if self.state == Some(state) {
+            return false;
+        }
+        self.state = Some(state);
+
+        match state {
+            ProgressState::Percent(percent) => self.draw_percent(buffer, percent),
+            ProgressState::Indeterminate(offset) => self.draw_stripes(buffer, offset),
+        }
+        true
+    }
+
+    fn draw_percent(&self, buffer: &mut BackBuffer, percent: u8) {
+        let rect = self.rect;
+        let filled = rect.width * usize::from(percent) / 100;
+        buffer.fill_rect(
+            Rect::new(rect.x, rect.y, filled, rect.height),
+            self.foreground,
+        );
+        buffer.fill_rect(
+            Rect::new(rect.x + filled, rect.y, rect.width - filled, rect.height),
+            self.background,
+        );
+
+        // Scale the glyphs to fit the bar with a margin of one glyph pixel
+        // above and below.
+        let scale = rect.height / 7;
+        if scale == 0 {
+            return;
+        }
+        let digit = |n: u8| char::from(b'0' + n % 10);
+        let mut label = ['0'; 4];
+        let mut count = 0;
+        if percent >= 100 {
+            label[count] = '1';
+            count += 1;
+        }
+        if percent >= 10 {
+            label[count] = digit(percent / 10);
+            count += 1;
+        }
+        label[count] = digit(percent);
+        label[count + 1] = '%';
+        count += 2;
+
+        // Each glyph is followed by a column of spacing, except the last.
+        let text_width = (count * 4 - 1) * scale;
+        if text_width > rect.width {
+            return;
+        }
+        let text_x = rect.x + (rect.width - text_width) / 2;
+        let text_y = rect.y + (rect.height - 5 * scale) / 2;
+        for (i, &c) in label[..count].iter().enumerate() {
+            let glyph = crate::graphics::glyph(c).unwrap();
+            for (row, bits) in glyph.iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) == 0 {
+                        continue;
+                    }
+                    let x = text_x + (i * 4 + column) * scale;
+                    let y = text_y + row * scale;
+                    let color = if x < rect.x + filled {
+                        self.background
+                    } else {
+                        self.foreground
+                    };
+                    buffer.fill_rect(Rect::new(x, y, scale, scale), color);
+                }
+            }
+        }
+    }
+
+    fn draw_stripes(&self, buffer: &mut BackBuffer, offset: usize) {
+        let rect = self.rect;
+        let stripe = (rect.height / 2).max(1);
+        for row in 0..rect.height {
+            // Stripes lean to the right, and move right as `offset` grows.
+            let shift = (offset % (2 * stripe) + rect.height - row) % (2 * stripe);
+            let mut x = 0;
+            while x < rect.width {
+                let phase = (x + 2 * stripe - shift) % (2 * stripe);
+                let length = (stripe - phase % stripe).min(rect.width - x);
+                let color = if phase < stripe {
+                    self.foreground
+                } else {
+                    self.background
+                };
+                buffer.fill_rect(Rect::new(rect.x + x, rect.y + row, length, 1), color);
+                x += length;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "exts")]
+impl<'boot> GraphicsOutput<'boot> {
+    /// Read the visible contents of the screen into a new buffer.
+    ///
+    /// Returns the pixels, row by row, along with the resolution of the
+    /// current mode. The pixels are read with a [`BltOp::VideoToBltBuffer`]
+    /// operation, so this works with every pixel format, including
+    /// [`PixelFormat::BltOnly`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the device can't read back the frame
+    ///   buffer.
+    /// * [`Status::DEVICE_ERROR`]: the device had an error and could not
+    ///   complete the request.
+    pub fn capture(&mut self) -> Result<(Vec<BltPixel>, (usize, usize))> {
+        let dims = self.current_mode_info().resolution();
+        let mut pixels = vec![BltPixel::new(0, 0, 0); dims.0 * dims.1];
+        if !pixels.is_empty() {
+            self.blt(BltOp::VideoToBltBuffer {
+                buffer: &mut pixels,
+                src: (0, 0),
+                dest: BltRegion::Full,
+                dims,
+            })?;
+        }
+        Ok((pixels, dims))
+    }
+}
+
+/// Size of the file header and `BITMAPINFOHEADER` of a BMP image.
+#[cfg(feature = "exts")]
+const BMP_HEADER_SIZE: usize = 14 + 40;
+
+/// Encode `dims.0` by `dims.1` pixels, stored row by row, as an
+/// uncompressed 24-bit BMP image.
+///
+/// # Panics
+///
+/// Panics if `pixels` has fewer than `dims.0 * dims.1` pixels, or if the
+/// image is too large for the BMP format.
+#[cfg(feature = "exts")]
+pub fn encode_bmp(pixels: &[BltPixel], (width, height): (usize, usize)) -> Vec<u8> {
+    assert!(
+        width.saturating_mul(height) <= pixels.len(),
+        "BltBuffer access out of bounds"
+    );
+
+    // Rows are padded to a multiple of four bytes.
+    let row_size = (width * 3 + 3) & !3;
+    let image_size = row_size * height;
+    let file_size = u32::try_from(BMP_HEADER_SIZE + image_size).expect("image is too large");
+    let width_field = i32::try_from(width).expect("image is too wide");
+    let height_field = i32::try_from(height).expect("image is too tall");
+
+    let mut bmp = Vec::with_capacity(file_size as usize);
+    // File header.
+    bmp.extend_from_slice(b"BM");
+    bmp.extend_from_slice(&file_size.to_le_bytes());
+    bmp.extend_from_slice(&[0; 4]);
+    bmp.extend_from_slice(&(BMP_HEADER_SIZE as u32).to_le_bytes());
+    // `BITMAPINFOHEADER`.
+    bmp.extend_from_slice(&40u32.to_le_bytes());
+    bmp.extend_from_slice(&width_field.to_le_bytes());
+    bmp.extend_from_slice(&height_field.to_le_bytes());
+    // One plane, 24 bits per pixel, no compression.
+    bmp.extend_from_slice(&1u16.to_le_bytes());
+    bmp.extend_from_slice(&24u16.to_le_bytes());
+    bmp.extend_from_slice(&0u32.to_le_bytes());
+    bmp.extend_from_slice(&(image_size as u32).to_le_bytes());
+    // 72 DPI horizontally and vertically.
+    bmp.extend_from_slice(&2835u32.to_le_bytes());
+    bmp.extend_from_slice(&2835u32.to_le_bytes());
+    // No palette.
+    bmp.extend_from_slice(&[0; 8]);
+
+    // Rows are stored bottom to top, with pixels in BGR order.
+    if width > 0 {
+        for row in pixels[..width * height].chunks_exact(width).rev() {
+            for pixel in row {
+                bmp.extend_from_slice(&[pixel.blue, pixel.green, pixel.red]);
+            }
+            bmp.resize(bmp.len() + row_size - width * 3, 0);
+        }
+    }
+    bmp
+}
+
+/// Encode pixels as a BMP image with [`encode_bmp`], and write it to the
+/// file at `path` relative to `directory`.
+///
+/// Any existing file at `path` is replaced.
+///
+/// # Errors
+///
+/// See [`Directory::create_file`], [`File::delete`] and
+/// [`RegularFile::write`].
+///
+/// [`Directory::create_file`]: crate::proto::media::file::Directory::create_file
+/// [`File::delete`]: crate::proto::media::file::File::delete
+/// [`RegularFile::write`]: crate::proto::media::file::RegularFile::write
+#[cfg(feature = "exts")]
+pub fn save_bmp(
+    directory: &mut Directory,
+    path: &CStr16,
+    pixels: &[BltPixel],
+    dims: (usize, usize),
+) -> Result {
+    let bmp = encode_bmp(pixels, dims);
+
+    // Recreate the file rather than truncating it, since truncation
+    // requires changing the file's info.
+    directory
+        .create_file(path, FileAttribute::empty())?
+        .delete()?;
+    let mut file = directory.create_file(path, FileAttribute::empty())?;
+    file.write(&bmp).discard_errdata()?;
+    file.flush()
+}
+
 /// Direct access to a memory-mapped frame buffer
 pub struct FrameBuffer<'gop> {
     base: *mut u8,
@@ -654,4 +1353,214 @@ impl<'gop> FrameBuffer<'gop> {
         );
         (self.base.add(index) as *const T).read_volatile()
     }
+
+    /// Write a pixel to the frame buffer, starting at the i-th byte, in the
+    /// pixel format of `converter`.
+    ///
+    /// # Safety
+    ///
+    /// This operation is unsafe because...
+    /// - The converter must match the pixel format specified by the mode info
+    /// - You must honor the stride specified by the mode info
+    /// - There is no bound checking on memory accesses in release mode
+    #[inline]
+    pub unsafe fn write_pixel(
+        &mut self,
+        index: usize,
+        converter: &PixelConverter,
+        pixel: BltPixel,
+    ) {
+        let bytes = converter.pack(pixel).to_le_bytes();
+        for (i, byte) in bytes[..converter.bytes_per_pixel()].iter().enumerate() {
+            self.write_byte(index + i, *byte);
+        }
+    }
+
+    /// Read a pixel from the frame buffer, starting at the i-th byte, in the
+    /// pixel format of `converter`.
+    ///
+    /// # Safety
+    ///
+    /// This operation is unsafe because...
+    /// - The converter must match the pixel format specified by the mode info
+    /// - You must honor the stride specified by the mode info
+    /// - There is no bound checking on memory accesses in release mode
+    #[inline]
+    pub unsafe fn read_pixel(&self, index: usize, converter: &PixelConverter) -> BltPixel {
+        let mut bytes = [0; 4];
+        for (i, byte) in bytes[..converter.bytes_per_pixel()].iter_mut().enumerate() {
+            *byte = self.read_byte(index + i);
+        }
+        converter.unpack(u32::from_le_bytes(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_pixel_eq(a: BltPixel, b: BltPixel) {
+        assert_eq!((a.red, a.green, a.blue), (b.red, b.green, b.blue));
+    }
+
+    #[test]
+    fn test_pixel_converter_32bit() {
+        let converter = PixelConverter::from_bitmask(PixelBitmask {
+            red: 0x00ff_0000,
+            green: 0x0000_ff00,
+            blue: 0x0000_00ff,
+            reserved: 0xff00_0000,
+        })
+        .unwrap();
+        assert_eq!(converter.bytes_per_pixel(), 4);
+
+        let pixel = BltPixel::new(0x12, 0x34, 0x56);
+        assert_eq!(converter.pack(pixel), 0x0012_3456);
+        assert_pixel_eq(converter.unpack(0xff12_3456), pixel);
+    }
+
+    #[test]
+    fn test_pixel_converter_rgb565() {
+        let converter = PixelConverter::from_bitmask(PixelBitmask {
+            red: 0xf800,
+            green: 0x07e0,
+            blue: 0x001f,
+            reserved: 0,
+        })
+        .unwrap();
+        assert_eq!(converter.bytes_per_pixel(), 2);
+
+        assert_eq!(converter.pack(BltPixel::new(255, 255, 255)), 0xffff);
+        assert_eq!(converter.pack(BltPixel::new(255, 0, 0)), 0xf800);
+        assert_eq!(converter.pack(BltPixel::new(0, 0, 0)), 0);
+        assert_pixel_eq(converter.unpack(0x07e0), BltPixel::new(0, 255, 0));
+    }
+
+    #[test]
+    fn test_blend_pixel() {
+        let dst = BltPixel::new(0, 100, 255);
+        let src = BltPixel::new(255, 200, 0);
+        assert_pixel_eq(blend_pixel(dst, src, 0), dst);
+        assert_pixel_eq(blend_pixel(dst, src, 255), src);
+        // 255 * 128 / 255 = 128, (200 * 128 + 100 * 127) / 255 = 150.2 and
+        // 255 * 127 / 255 = 127.
+        assert_pixel_eq(blend_pixel(dst, src, 128), BltPixel::new(128, 150, 127));
+    }
+
+    #[test]
+    fn test_fill_gradient() {
+        let top = BltPixel::new(0, 255, 10);
+        let bottom = BltPixel::new(255, 0, 10);
+        let mut buffer = [BltPixel::new(1, 2, 3); 2 * 4 + 1];
+        fill_gradient(&mut buffer, (2, 4), top, bottom);
+
+        let expected = [
+            top,
+            BltPixel::new(85, 170, 10),
+            BltPixel::new(170, 85, 10),
+            bottom,
+        ];
+        for (row, expected) in buffer[..8].chunks(2).zip(expected) {
+            assert_pixel_eq(row[0], expected);
+            assert_pixel_eq(row[1], expected);
+        }
+        // Pixels past the region are left alone.
+        assert_pixel_eq(buffer[8], BltPixel::new(1, 2, 3));
+
+        let mut buffer = [BltPixel::new(1, 2, 3); 3];
+        fill_gradient(&mut buffer, (3, 1), top, bottom);
+        for pixel in buffer {
+            assert_pixel_eq(pixel, top);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "exts")]
+    fn test_progress_bar() {
+        let white = BltPixel::new(255, 255, 255);
+        let black = BltPixel::new(0, 0, 0);
+        let mut buffer = BackBuffer::with_resolution(100, 20);
+        let mut bar = ProgressBar::new(Rect::new(0, 0, 100, 14)).with_colors(white, black);
+
+        assert!(bar.update(&mut buffer, 1, 4));
+        // The percentage didn't change, so nothing is drawn.
+        assert!(!bar.update(&mut buffer, 250, 1000));
+        assert!(bar.update(&mut buffer, 500, 1000));
+
+        // The left half is filled, apart from the label in its middle.
+        assert_pixel_eq(buffer.pixel(0, 0).unwrap(), white);
+        assert_pixel_eq(buffer.pixel(49, 13).unwrap(), white);
+        assert_pixel_eq(buffer.pixel(50, 0).unwrap(), black);
+        assert_pixel_eq(buffer.pixel(99, 13).unwrap(), black);
+        // "50%" is 11 glyph pixels wide at scale 2, so it starts at x = 39,
+        // and the top of the "5" is at y = 2.
+        assert_pixel_eq(buffer.pixel(39, 2).unwrap(), black);
+        assert_pixel_eq(buffer.pixel(39, 1).unwrap(), white);
+        // The bar doesn't draw outside its region.
+        assert_pixel_eq(buffer.pixel(0, 14).unwrap(), black);
+
+        assert!(bar.update(&mut buffer, 2000, 1000));
+        assert_pixel_eq(buffer.pixel(99, 0).unwrap(), white);
+        assert!(!bar.update(&mut buffer, 1000, 1000));
+        bar.invalidate();
+        assert!(bar.update(&mut buffer, 1000, 1000));
+    }
+
+    #[test]
+    #[cfg(feature = "exts")]
+    fn test_progress_bar_indeterminate() {
+        let white = BltPixel::new(255, 255, 255);
+        let black = BltPixel::new(0, 0, 0);
+        let mut buffer = BackBuffer::with_resolution(16, 4);
+        let mut bar = ProgressBar::new(Rect::new(0, 0, 16, 4)).with_colors(white, black);
+
+        // Every update advances the animation.
+        assert!(bar.update(&mut buffer, 0, 0));
+        let first: Vec<_> = (0..16).map(|x| buffer.pixel(x, 0).unwrap().red).collect();
+        assert!(bar.update(&mut buffer, 0, 0));
+        let second: Vec<_> = (0..16).map(|x| buffer.pixel(x, 0).unwrap().red).collect();
+        assert_ne!(first, second);
+        // The stripes are 2 pixels wide, and move by one pixel per update.
+        assert_eq!(&first[..15], &second[1..]);
+        assert!(first.contains(&255) && first.contains(&0));
+    }
+
+    #[test]
+    fn test_pixel_converter_invalid() {
+        // Non-contiguous mask.
+        assert!(PixelConverter::from_bitmask(PixelBitmask {
+            red: 0x0000_0f0f,
+            green: 0x0000_f0f0,
+            blue: 0x00ff_0000,
+            reserved: 0,
+        })
+        .is_none());
+
+        // 8-bit pixels.
+        assert!(PixelConverter::from_bitmask(PixelBitmask {
+            red: 0xe0,
+            green: 0x1c,
+            blue: 0x03,
+            reserved: 0,
+        })
+        .is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "exts")]
+    fn test_encode_bmp() {
+        let red = BltPixel::new(255, 0, 0);
+        let blue = BltPixel::new(0, 0, 255);
+        // Two rows of one pixel each, so each row needs one padding byte.
+        let bmp = encode_bmp(&[red, blue], (1, 2));
+
+        assert_eq!(bmp.len(), BMP_HEADER_SIZE + 2 * 4);
+        assert_eq!(&bmp[0..2], b"BM");
+        assert_eq!(&bmp[2..6], &(bmp.len() as u32).to_le_bytes());
+        assert_eq!(&bmp[18..22], &1i32.to_le_bytes());
+        assert_eq!(&bmp[22..26], &2i32.to_le_bytes());
+        assert_eq!(&bmp[28..30], &24u16.to_le_bytes());
+        // The bottom row comes first.
+        assert_eq!(&bmp[BMP_HEADER_SIZE..], &[255, 0, 0, 0, 0, 0, 255, 0]);
+    }
 }
@@ -3,8 +3,10 @@
 use core::fmt::Write;
 
 use crate::proto::Protocol;
-use crate::{unsafe_guid, Result, Status};
+use crate::{unsafe_guid, Error, Result, Status};
 use bitflags::bitflags;
+#[cfg(feature = "exts")]
+use {crate::proto::device_path::DevicePath, crate::Guid};
 
 /// Provides access to a serial I/O device.
 ///
@@ -101,6 +103,41 @@ impl<'boot> Serial<'boot> {
         )
     }
 
+    /// Reads data from this device, waiting at most `timeout` microseconds
+    /// for each byte, and returns the number of bytes read.
+    ///
+    /// Unlike [`read`], a read that times out is not an error: it returns
+    /// the number of bytes read until then, which may be less than the size
+    /// of `data`, or 0. The timeout attribute of the device is restored
+    /// afterwards.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::DEVICE_ERROR`]: the device reported an error. The error
+    ///   data is the number of bytes read before it.
+    /// * [`Status::INVALID_PARAMETER`]: the device doesn't support
+    ///   `timeout`.
+    ///
+    /// [`read`]: Self::read
+    pub fn read_with_timeout(&mut self, data: &mut [u8], timeout: u32) -> Result<usize, usize> {
+        let old_mode = *self.io_mode;
+        let mut mode = old_mode;
+        mode.timeout = timeout;
+        self.set_attributes(&mode)
+            .map_err(|err| Error::new(err.status(), 0))?;
+
+        let read = match self.read(data) {
+            Ok(()) => Ok(data.len()),
+            Err(err) if err.status() == Status::TIMEOUT => Ok(*err.data()),
+            Err(err) => Err(err),
+        };
+
+        let restored = self.set_attributes(&old_mode);
+        let read = read?;
+        restored.map_err(|err| Error::new(err.status(), read))?;
+        Ok(read)
+    }
+
     /// Writes data to this device.
     ///
     /// This operation will block until the data has been fully written or an
@@ -113,11 +150,103 @@ impl<'boot> Serial<'boot> {
             |_| buffer_size,
         )
     }
+
+    /// Writes all of `data` to this device.
+    ///
+    /// Unlike [`write`], a write that times out after making some progress
+    /// is retried with the remaining data. A write that times out without
+    /// writing anything, or that fails with any other error, is not retried.
+    /// In that case, the error will indicate how many bytes were written in
+    /// total.
+    ///
+    /// [`write`]: Self::write
+    pub fn write_all(&mut self, data: &[u8]) -> Result<(), usize> {
+        let mut written = 0;
+        while written < data.len() {
+            match self.write(&data[written..]) {
+                Ok(()) => return Ok(()),
+                Err(err) if err.status() == Status::TIMEOUT && *err.data() > 0 => {
+                    written += *err.data();
+                }
+                Err(err) => return Err(Error::new(err.status(), written + *err.data())),
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<'boot> Write for Serial<'boot> {
+    /// Writes the UTF-8 bytes of `s`, retrying on partial writes. If the
+    /// device stops accepting data, `fmt::Error` is returned.
     fn write_str(&mut self, s: &str) -> core::fmt::Result {
-        self.write(s.as_bytes()).map_err(|_| core::fmt::Error)
+        self.write_all(s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
+
+/// Size of the buffer of a [`BufferedSerial`].
+const SERIAL_BUFFER_SIZE: usize = 64;
+
+/// Line-buffered writer for a [`Serial`] device.
+///
+/// Formatted output is accumulated in a small internal buffer, which is
+/// written to the device when it is full, after each newline, when
+/// [`flush`] is called, and when the writer is dropped. This avoids issuing a
+/// separate device write for every fragment produced by `write!`.
+///
+/// As with the [`Write`] implementation of [`Serial`], partial writes are
+/// retried, and a device that stops accepting data results in an
+/// `fmt::Error`.
+///
+/// [`flush`]: Self::flush
+pub struct BufferedSerial<'serial, 'boot> {
+    serial: &'serial mut Serial<'boot>,
+    buffer: [u8; SERIAL_BUFFER_SIZE],
+    len: usize,
+}
+
+impl<'serial, 'boot> BufferedSerial<'serial, 'boot> {
+    /// Create a new buffered writer for `serial`.
+    pub fn new(serial: &'serial mut Serial<'boot>) -> Self {
+        Self {
+            serial,
+            buffer: [0; SERIAL_BUFFER_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Write the buffered data to the device.
+    ///
+    /// If the write fails, the data that could not be written is dropped and
+    /// the error indicates how many bytes were written.
+    pub fn flush(&mut self) -> Result<(), usize> {
+        let len = self.len;
+        self.len = 0;
+        self.serial.write_all(&self.buffer[..len])
+    }
+}
+
+impl<'serial, 'boot> Write for BufferedSerial<'serial, 'boot> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut bytes = s.as_bytes();
+        while !bytes.is_empty() {
+            let count = bytes.len().min(SERIAL_BUFFER_SIZE - self.len);
+            let (chunk, rest) = bytes.split_at(count);
+            self.buffer[self.len..self.len + count].copy_from_slice(chunk);
+            self.len += count;
+            bytes = rest;
+
+            if self.len == SERIAL_BUFFER_SIZE || chunk.contains(&b'\n') {
+                self.flush().map_err(|_| core::fmt::Error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'serial, 'boot> Drop for BufferedSerial<'serial, 'boot> {
+    fn drop(&mut self) {
+        // Ignore the result, we can't do anything about an error here.
+        let _ = self.flush();
     }
 }
 
@@ -230,3 +359,45 @@ pub enum StopBits {
     //         unlikely to be added at this point in time. Therefore, modeling
     //         this C enum as a Rust enum seems safe.
 }
+
+/// GUID installed by the firmware on the handles of the devices used for the
+/// console output, such as the terminal on a serial device.
+#[cfg(feature = "exts")]
+pub(crate) const CONSOLE_OUT_DEVICE_GUID: Guid =
+    Guid::from_values(0xd3b36f2c, 0xd551, 0x11d4, 0x9a46, 0x0090273fc14d);
+
+/// Compressed EISA ID of a 16550 compatible UART (`PNP0501`).
+#[cfg(feature = "exts")]
+const PNP0501: u32 = 0x0501_41d0;
+
+/// Information about a serial device, as returned by
+/// [`BootServices::serial_ports`].
+///
+/// [`BootServices::serial_ports`]: crate::table::boot::BootServices::serial_ports
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct SerialDeviceInfo {
+    /// The number of the legacy COM port, starting at 1, if the device path
+    /// describes a 16550 compatible UART.
+    pub com_port: Option<u32>,
+
+    /// Whether the device is used for the console output. Logging through
+    /// `stdout` goes to this device, so it shouldn't be opened in exclusive
+    /// mode.
+    pub is_console: bool,
+}
+
+#[cfg(feature = "exts")]
+impl SerialDeviceInfo {
+    /// Decode the information from the device path of the serial device.
+    pub(crate) fn new(device_path: &DevicePath, is_console: bool) -> Self {
+        let com_port = device_path
+            .node_iter()
+            .filter_map(|node| node.as_acpi_device_path())
+            .find(|acpi| { acpi.hid } == PNP0501)
+            .map(|acpi| acpi.uid + 1);
+        Self {
+            com_port,
+            is_console,
+        }
+    }
+}
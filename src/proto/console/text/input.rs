@@ -27,6 +27,10 @@ impl Input {
 
     /// Reads the next keystroke from the input device, if any.
     ///
+    /// Returns `Ok(None)` if no key is available yet. Otherwise the key is
+    /// decoded into either a printable character or a special key with a
+    /// [`ScanCode`].
+    ///
     /// Use `wait_for_key_event()` with the `BootServices::wait_for_event()`
     /// interface in order to wait for a key to be pressed.
     ///
@@ -83,10 +87,11 @@ pub struct RawKey {
 newtype_enum! {
 /// A keyboard scan code
 ///
-/// Codes 0x8000 -> 0xFFFF are reserved for future OEM extensibility, therefore
-/// this C enum is _not_ safe to model as a Rust enum (where the compiler must
-/// know about all variants at compile time).
-pub enum ScanCode: u16 => #[allow(missing_docs)] {
+/// This covers all the scan codes defined in the UEFI specification. Codes
+/// 0x8000 -> 0xFFFF are reserved for future OEM extensibility, therefore this
+/// C enum is _not_ safe to model as a Rust enum (where the compiler must know
+/// about all variants at compile time).
+pub enum ScanCode: u16 => {
     /// Null scan code, indicates that the Unicode character should be used.
     NULL        = 0x00,
     /// Move cursor up 1 row.
@@ -97,48 +102,89 @@ pub enum ScanCode: u16 => #[allow(missing_docs)] {
     RIGHT       = 0x03,
     /// Move cursor left 1 column.
     LEFT        = 0x04,
+    /// Move cursor to the beginning of the line.
     HOME        = 0x05,
+    /// Move cursor to the end of the line.
     END         = 0x06,
+    /// Toggle insert / overwrite mode.
     INSERT      = 0x07,
+    /// Delete the character under the cursor.
     DELETE      = 0x08,
+    /// Move cursor up one page.
     PAGE_UP     = 0x09,
+    /// Move cursor down one page.
     PAGE_DOWN   = 0x0A,
+    /// Function key F1.
     FUNCTION_1  = 0x0B,
+    /// Function key F2.
     FUNCTION_2  = 0x0C,
+    /// Function key F3.
     FUNCTION_3  = 0x0D,
+    /// Function key F4.
     FUNCTION_4  = 0x0E,
+    /// Function key F5.
     FUNCTION_5  = 0x0F,
+    /// Function key F6.
     FUNCTION_6  = 0x10,
+    /// Function key F7.
     FUNCTION_7  = 0x11,
+    /// Function key F8.
     FUNCTION_8  = 0x12,
+    /// Function key F9.
     FUNCTION_9  = 0x13,
+    /// Function key F10.
     FUNCTION_10 = 0x14,
+    /// Function key F11.
     FUNCTION_11 = 0x15,
+    /// Function key F12.
     FUNCTION_12 = 0x16,
+    /// Escape key.
     ESCAPE      = 0x17,
 
+    /// Function key F13.
     FUNCTION_13 = 0x68,
+    /// Function key F14.
     FUNCTION_14 = 0x69,
+    /// Function key F15.
     FUNCTION_15 = 0x6A,
+    /// Function key F16.
     FUNCTION_16 = 0x6B,
+    /// Function key F17.
     FUNCTION_17 = 0x6C,
+    /// Function key F18.
     FUNCTION_18 = 0x6D,
+    /// Function key F19.
     FUNCTION_19 = 0x6E,
+    /// Function key F20.
     FUNCTION_20 = 0x6F,
+    /// Function key F21.
     FUNCTION_21 = 0x70,
+    /// Function key F22.
     FUNCTION_22 = 0x71,
+    /// Function key F23.
     FUNCTION_23 = 0x72,
+    /// Function key F24.
     FUNCTION_24 = 0x73,
 
+    /// Toggle audio mute.
     MUTE        = 0x7F,
+    /// Increase audio volume.
     VOLUME_UP   = 0x80,
+    /// Decrease audio volume.
     VOLUME_DOWN = 0x81,
 
+    /// Increase display brightness.
     BRIGHTNESS_UP   = 0x100,
+    /// Decrease display brightness.
     BRIGHTNESS_DOWN = 0x101,
+    /// Suspend the system.
     SUSPEND         = 0x102,
+    /// Hibernate the system.
     HIBERNATE       = 0x103,
+    /// Toggle the display.
     TOGGLE_DISPLAY  = 0x104,
+    /// Enter the recovery environment.
     RECOVERY        = 0x105,
+    /// Eject the media.
     EJECT           = 0x106,
 }}
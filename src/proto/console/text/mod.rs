@@ -4,4 +4,7 @@ mod input;
 pub use self::input::{Input, Key, ScanCode};
 
 mod output;
-pub use self::output::{Color, Output, OutputMode};
+pub use self::output::{Color, Output, OutputInfo, OutputMode};
+
+mod pager;
+pub use self::pager::Pager;
@@ -166,7 +166,64 @@ impl<'boot> Output<'boot> {
         assert!(bgc < 8, "An invalid background color was requested");
 
         let attr = ((bgc & 0x7) << 4) | (fgc & 0xF);
-        (self.set_attribute)(self, attr).into()
+        self.set_raw_attribute(attr as u8)
+    }
+
+    /// Returns the raw attribute used for new characters.
+    ///
+    /// Bits 0 to 3 are the foreground [`Color`], and bits 4 to 6 are the
+    /// background color. The meaning of bit 7 depends on the firmware.
+    pub fn attribute(&self) -> u8 {
+        self.data.attribute as u8
+    }
+
+    /// Sets the raw attribute used for new characters, with the layout
+    /// described in [`attribute`].
+    ///
+    /// Bit 7 is cleared, as the UEFI specification requires it to be zero.
+    ///
+    /// [`attribute`]: Self::attribute
+    pub fn set_raw_attribute(&mut self, attribute: u8) -> Result {
+        (self.set_attribute)(self, usize::from(attribute & 0x7f)).into()
+    }
+
+    /// Returns whether the device supports bright background colors, which
+    /// are selected by bit 7 of the attribute.
+    ///
+    /// The UEFI specification only defines 8 background colors, but some
+    /// firmware renders all 16 [`Color`]s as background colors. This is
+    /// probed by setting an attribute with bit 7 set and reading it back;
+    /// the previous attribute is restored afterwards. The result for the
+    /// last probed device is cached, so the probe normally runs only once.
+    pub fn supports_bright_background(&mut self) -> bool {
+        let data: *const OutputData = self.data;
+        if let Some((cached, supported)) = unsafe { BRIGHT_BACKGROUND } {
+            if cached == data {
+                return supported;
+            }
+        }
+
+        let previous = self.data.attribute as usize;
+        let bright = previous | 0x80;
+        let supported = (self.set_attribute)(self, bright).is_success()
+            && self.data.attribute as usize == bright;
+        // The previous attribute was accepted before, so it can be restored.
+        let _ = (self.set_attribute)(self, previous);
+
+        unsafe {
+            BRIGHT_BACKGROUND = Some((data, supported));
+        }
+        supported
+    }
+
+    /// Returns information about the device: the number of text modes, the
+    /// current mode, and whether the cursor is visible.
+    pub fn info(&self) -> OutputInfo {
+        OutputInfo {
+            max_mode: self.data.max_mode as usize,
+            mode: usize::try_from(self.data.mode).ok(),
+            cursor_visible: self.data.cursor_visible,
+        }
     }
 }
 
@@ -303,6 +360,22 @@ impl<'out, 'boot> Iterator for OutputModeIter<'out, 'boot> {
     }
 }
 
+/// Result of [`Output::supports_bright_background`] for the device whose
+/// data is at the given address.
+static mut BRIGHT_BACKGROUND: Option<(*const OutputData, bool)> = None;
+
+/// Information about an output device, as returned by [`Output::info`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct OutputInfo {
+    /// The number of text modes supported by the device. Modes are numbered
+    /// from 0 to `max_mode - 1`, but some of them may not be available.
+    pub max_mode: usize,
+    /// The index of the current text mode, or `None` if no mode is set.
+    pub mode: Option<usize>,
+    /// Whether the cursor is visible.
+    pub cursor_visible: bool,
+}
+
 /// Additional data of the output device.
 #[derive(Debug)]
 #[repr(C)]
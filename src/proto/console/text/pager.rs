@@ -0,0 +1,309 @@
+use super::{Input, Output};
+use crate::table::boot::BootServices;
+use crate::{Result, Status};
+use core::fmt;
+use core::fmt::Write;
+
+/// Prompt shown when the page is full.
+const PROMPT: &str = "-- More --";
+
+/// Spaces written in one call to erase text.
+const SPACES: &str = "                ";
+
+/// Writes text to an [`Output`] one screen at a time.
+///
+/// When the screen has been filled, the pager shows a `-- More --` prompt and
+/// waits for a key to be pressed on the [`Input`] before writing the rest.
+/// This keeps long output readable on a real console, which can't be
+/// scrolled back.
+///
+/// The output can be confined to a part of the screen with
+/// [`set_scroll_region`], for example to keep a header visible.
+///
+/// The pager implements [`fmt::Write`], so it can be used with `write!()` and
+/// `writeln!()`. The size of the screen is queried from the current output
+/// mode every time text is written, so mode changes are picked up.
+///
+/// [`set_scroll_region`]: Self::set_scroll_region
+pub struct Pager<'a, 'boot> {
+    output: &'a mut Output<'boot>,
+    input: &'a mut Input,
+    boot_services: &'a BootServices,
+    /// First row and number of rows of the scrolling region, if any.
+    region: Option<(usize, usize)>,
+    cursor: Cursor,
+}
+
+impl<'a, 'boot> Pager<'a, 'boot> {
+    /// Creates a pager writing to `output` and waiting for keys on `input`.
+    ///
+    /// Lines already on the screen above the cursor count towards the first
+    /// page.
+    pub fn new(
+        output: &'a mut Output<'boot>,
+        input: &'a mut Input,
+        boot_services: &'a BootServices,
+    ) -> Self {
+        let (column, row) = output.cursor_position();
+        Self {
+            output,
+            input,
+            boot_services,
+            region: None,
+            cursor: Cursor { lines: row, column },
+        }
+    }
+
+    /// Confines the output to the `rows` rows starting at row `top`, leaving
+    /// the rest of the screen, such as a header, untouched. The region is
+    /// cleared, and writing starts at its top-left corner.
+    ///
+    /// The UEFI console can only scroll the whole screen, so the region isn't
+    /// scrolled: it is cleared after each prompt instead, and writing restarts
+    /// at its top. If the mode changes to a screen which is too small for the
+    /// region, the region is shrunk, or dropped if its first row is gone.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the output mode can't be queried.
+    /// * [`Status::INVALID_PARAMETER`]: the region has less than two rows, one
+    ///   of which is needed by the prompt, or doesn't fit on the screen.
+    /// * [`Status::DEVICE_ERROR`]: the region couldn't be cleared.
+    pub fn set_scroll_region(&mut self, top: usize, rows: usize) -> Result {
+        let (columns, screen_rows) = match self.output.current_mode()? {
+            Some(mode) => (mode.columns(), mode.rows()),
+            None => return Err(Status::UNSUPPORTED.into()),
+        };
+        if rows < 2 || rows > screen_rows.saturating_sub(top) {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+
+        self.region = Some((top, rows));
+        self.clear_region(top, rows, columns)
+            .map_err(|_| Status::DEVICE_ERROR.into())
+    }
+
+    /// Lets the output use the whole screen again. The rows above the
+    /// scrolling region count towards the current page.
+    pub fn reset_scroll_region(&mut self) {
+        if let Some((top, _)) = self.region.take() {
+            self.cursor.lines += top;
+        }
+    }
+
+    /// Writes `line` followed by a line break.
+    pub fn write_line(&mut self, line: &str) -> fmt::Result {
+        self.write_str(line)?;
+        self.write_str("\n")
+    }
+
+    /// Terminates the current line if text has been written to it without a
+    /// line break, so that subsequent output starts on a new line.
+    pub fn flush(&mut self) -> fmt::Result {
+        if self.cursor.column != 0 {
+            self.write_str("\n")?;
+        }
+        Ok(())
+    }
+
+    /// Returns the size of a page as `(columns, rows)`, which is the size of
+    /// the scrolling region if there is one, and of the screen otherwise. If
+    /// the output mode can't be queried, the page is treated as infinitely
+    /// large, so no prompts are shown.
+    fn page_size(&mut self) -> (usize, usize) {
+        let (columns, screen_rows) = match self.output.current_mode() {
+            Ok(Some(mode)) if mode.columns() > 0 && mode.rows() > 1 => {
+                (mode.columns(), mode.rows())
+            }
+            _ => return (usize::MAX, usize::MAX),
+        };
+
+        match self.region {
+            Some((top, rows)) if top + 2 <= screen_rows => (columns, rows.min(screen_rows - top)),
+            Some(_) => {
+                self.reset_scroll_region();
+                (columns, screen_rows)
+            }
+            None => (columns, screen_rows),
+        }
+    }
+
+    /// Shows the prompt, waits for a key press and erases the prompt again,
+    /// or the whole page of `rows` rows of a scrolling region.
+    fn prompt(&mut self, columns: usize, rows: usize) -> fmt::Result {
+        self.output.write_str(PROMPT)?;
+
+        // Consume the key, so that it isn't seen by the next reader.
+        self.boot_services
+            .wait_for_key(self.input)
+            .map_err(|_| fmt::Error)?;
+
+        match self.region {
+            Some((top, _)) => self.clear_region(top, rows, columns),
+            None => {
+                self.output.write_char('\r')?;
+                self.write_spaces(PROMPT.len())?;
+                self.output.write_char('\r')?;
+                self.cursor = Cursor::default();
+                Ok(())
+            }
+        }
+    }
+
+    /// Erases the `rows` rows starting at row `top`, and moves the cursor to
+    /// the first of them.
+    fn clear_region(&mut self, top: usize, rows: usize, columns: usize) -> fmt::Result {
+        for row in top..top + rows {
+            self.output
+                .set_cursor_position(0, row)
+                .map_err(|_| fmt::Error)?;
+            // Writing to the last column would wrap to the next row.
+            self.write_spaces(columns - 1)?;
+        }
+        self.output
+            .set_cursor_position(0, top)
+            .map_err(|_| fmt::Error)?;
+        self.cursor = Cursor::default();
+        Ok(())
+    }
+
+    /// Writes `count` spaces.
+    fn write_spaces(&mut self, mut count: usize) -> fmt::Result {
+        while count > 0 {
+            let len = count.min(SPACES.len());
+            self.output.write_str(&SPACES[..len])?;
+            count -= len;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'boot> fmt::Write for Pager<'a, 'boot> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let (mut columns, mut rows) = self.page_size();
+
+        let mut start = 0;
+        for (i, ch) in s.char_indices() {
+            if self.cursor.advance(ch, columns, rows) {
+                let end = i + ch.len_utf8();
+                self.output.write_str(&s[start..end])?;
+                start = end;
+
+                self.prompt(columns, rows)?;
+                (columns, rows) = self.page_size();
+            }
+        }
+
+        self.output.write_str(&s[start..])
+    }
+}
+
+/// Position of the cursor on the current page, which decides where the
+/// console wraps lines and where pages break.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Cursor {
+    /// Number of lines written since the start of the page.
+    lines: usize,
+    /// Column of the cursor on the current line.
+    column: usize,
+}
+
+impl Cursor {
+    /// Moves the cursor past `ch` on a page of `columns` by `rows`, and
+    /// returns whether the page is full. The last row of the page is kept
+    /// free for the prompt.
+    fn advance(&mut self, ch: char, columns: usize, rows: usize) -> bool {
+        if ch == '\n' {
+            self.lines += 1;
+            self.column = 0;
+        } else {
+            self.column += 1;
+            if self.column >= columns {
+                // The console wraps the line.
+                self.lines += 1;
+                self.column = 0;
+            }
+        }
+        self.lines >= rows - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the byte offsets in `text` after which the pager prompts, on a
+    /// page of `columns` by `rows`.
+    fn page_breaks(text: &str, columns: usize, rows: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut cursor = Cursor::default();
+        text.char_indices().filter_map(move |(i, ch)| {
+            if cursor.advance(ch, columns, rows) {
+                cursor = Cursor::default();
+                Some(i + ch.len_utf8())
+            } else {
+                None
+            }
+        })
+    }
+
+    #[test]
+    fn test_line_breaking() {
+        let mut cursor = Cursor::default();
+        for ch in "abc".chars() {
+            assert!(!cursor.advance(ch, 4, 10));
+        }
+        assert_eq!(
+            cursor,
+            Cursor {
+                lines: 0,
+                column: 3
+            }
+        );
+
+        // Writing to the last column wraps the line.
+        assert!(!cursor.advance('d', 4, 10));
+        assert_eq!(
+            cursor,
+            Cursor {
+                lines: 1,
+                column: 0
+            }
+        );
+
+        assert!(!cursor.advance('e', 4, 10));
+        assert!(!cursor.advance('\n', 4, 10));
+        assert_eq!(
+            cursor,
+            Cursor {
+                lines: 2,
+                column: 0
+            }
+        );
+
+        // Characters count as one column each, whatever their UTF-8 length.
+        for ch in "äöü".chars() {
+            assert!(!cursor.advance(ch, 4, 10));
+        }
+        assert_eq!(
+            cursor,
+            Cursor {
+                lines: 2,
+                column: 3
+            }
+        );
+    }
+
+    #[test]
+    fn test_page_breaking() {
+        // The last row is kept for the prompt.
+        assert!(page_breaks("a\nb\nc\nd\ne", 80, 3).eq([4, 8]));
+        assert!(page_breaks("a\nb\n", 80, 4).eq([]));
+
+        // Wrapped lines count towards the page.
+        assert!(page_breaks("abcdefghij", 4, 3).eq([8]));
+        assert!(page_breaks("abcd\nef\n", 4, 3).eq([5]));
+
+        // Without a known screen size, there are no prompts.
+        assert!(page_breaks("a\n\n\n\n\n\n\n\n", usize::MAX, usize::MAX).eq([]));
+    }
+}
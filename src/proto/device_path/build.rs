@@ -0,0 +1,288 @@
+//! Building device paths.
+//!
+//! A [`DevicePathBuilder`] appends nodes to a device path stored in a
+//! `Vec<u8>`, and then adds the [`END_ENTIRE`] node to get a
+//! [`DevicePath`].
+//!
+//! ```
+//! use uefi::proto::device_path::build::DevicePathBuilder;
+//! use uefi::Guid;
+//!
+//! let vendor = Guid::from_values(0x12345678, 0x1234, 0x5678, 0x9abc, 0xdef012345678);
+//! let mut buffer = Vec::new();
+//! let path = DevicePathBuilder::with_vec(&mut buffer)
+//!     .push_vendor_hw(vendor, &[1, 2, 3])
+//!     .unwrap()
+//!     .finalize();
+//! assert_eq!(path.node_iter().count(), 1);
+//! ```
+//!
+//! [`END_ENTIRE`]: DeviceSubType::END_ENTIRE
+
+use super::{DevicePath, DevicePathHeader, DevicePathNode, DeviceSubType, DeviceType};
+use crate::alloc_api::vec::Vec;
+use crate::Guid;
+use core::{mem, slice};
+
+/// Error returned when a node can't be added to a [`DevicePathBuilder`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// The node is larger than the largest length that its header can
+    /// hold, `u16::MAX` bytes.
+    NodeTooLarge {
+        /// Length of the node, including its header.
+        length: usize,
+    },
+    /// Vendor-defined nodes don't exist for this device type.
+    UnsupportedVendorType(DeviceType),
+}
+
+/// Builds a [`DevicePath`] in a `Vec<u8>`.
+///
+/// The nodes are appended with the `push_*` methods, in order from the
+/// root of the path, and [`finalize`] returns the path.
+///
+/// [`finalize`]: Self::finalize
+#[derive(Debug)]
+pub struct DevicePathBuilder<'a> {
+    vec: &'a mut Vec<u8>,
+}
+
+impl<'a> DevicePathBuilder<'a> {
+    /// Create a builder that stores the path in `vec`. The previous
+    /// contents of `vec` are cleared.
+    pub fn with_vec(vec: &'a mut Vec<u8>) -> Self {
+        vec.clear();
+        Self { vec }
+    }
+
+    /// Append a node with the given type, sub-type and data following the
+    /// header.
+    ///
+    /// # Errors
+    ///
+    /// * [`BuildError::NodeTooLarge`]: the node doesn't fit in the 16-bit
+    ///   length of its header.
+    pub fn push_raw(
+        self,
+        device_type: DeviceType,
+        sub_type: DeviceSubType,
+        data: &[u8],
+    ) -> Result<Self, BuildError> {
+        self.push_parts(device_type, sub_type, &[data])
+    }
+
+    /// Append a copy of `node`.
+    pub fn push_node(self, node: &DevicePathNode) -> Self {
+        let length = usize::from(node.length());
+        let bytes = unsafe { slice::from_raw_parts(node.as_ffi_ptr().cast::<u8>(), length) };
+        self.vec.extend_from_slice(bytes);
+        self
+    }
+
+    /// Append copies of the nodes of `path`, without its [`END_ENTIRE`]
+    /// node, so that more nodes can be appended to it.
+    ///
+    /// [`END_ENTIRE`]: DeviceSubType::END_ENTIRE
+    pub fn push_path(self, path: &DevicePath) -> Self {
+        let length = path.len_bytes() - mem::size_of::<DevicePathHeader>();
+        self.vec.extend_from_slice(&path.data[..length]);
+        self
+    }
+
+    /// Append a vendor-defined node of the given type, made of the vendor
+    /// GUID followed by `data`.
+    ///
+    /// Vendor-defined nodes exist for the [`HARDWARE`], [`MESSAGING`] and
+    /// [`MEDIA`] types, which have the shortcuts [`push_vendor_hw`],
+    /// [`push_vendor_messaging`] and [`push_vendor_media`].
+    ///
+    /// # Errors
+    ///
+    /// * [`BuildError::UnsupportedVendorType`]: `device_type` is not one of
+    ///   the types above.
+    /// * [`BuildError::NodeTooLarge`]: the node doesn't fit in the 16-bit
+    ///   length of its header.
+    ///
+    /// [`HARDWARE`]: DeviceType::HARDWARE
+    /// [`MESSAGING`]: DeviceType::MESSAGING
+    /// [`MEDIA`]: DeviceType::MEDIA
+    /// [`push_vendor_hw`]: Self::push_vendor_hw
+    /// [`push_vendor_messaging`]: Self::push_vendor_messaging
+    /// [`push_vendor_media`]: Self::push_vendor_media
+    pub fn push_vendor(
+        self,
+        device_type: DeviceType,
+        guid: Guid,
+        data: &[u8],
+    ) -> Result<Self, BuildError> {
+        let sub_type = match device_type {
+            DeviceType::HARDWARE => DeviceSubType::HARDWARE_VENDOR,
+            DeviceType::MESSAGING => DeviceSubType::MESSAGING_VENDOR,
+            DeviceType::MEDIA => DeviceSubType::MEDIA_VENDOR,
+            _ => return Err(BuildError::UnsupportedVendorType(device_type)),
+        };
+        // The GUID is stored in its in-memory layout, which is also the
+        // layout used by UEFI.
+        let guid_bytes = unsafe {
+            slice::from_raw_parts((&guid as *const Guid).cast::<u8>(), mem::size_of::<Guid>())
+        };
+        self.push_parts(device_type, sub_type, &[guid_bytes, data])
+    }
+
+    /// Append a vendor-defined hardware node. See [`push_vendor`].
+    ///
+    /// [`push_vendor`]: Self::push_vendor
+    pub fn push_vendor_hw(self, guid: Guid, data: &[u8]) -> Result<Self, BuildError> {
+        self.push_vendor(DeviceType::HARDWARE, guid, data)
+    }
+
+    /// Append a vendor-defined messaging node. See [`push_vendor`].
+    ///
+    /// [`push_vendor`]: Self::push_vendor
+    pub fn push_vendor_messaging(self, guid: Guid, data: &[u8]) -> Result<Self, BuildError> {
+        self.push_vendor(DeviceType::MESSAGING, guid, data)
+    }
+
+    /// Append a vendor-defined media node. See [`push_vendor`].
+    ///
+    /// [`push_vendor`]: Self::push_vendor
+    pub fn push_vendor_media(self, guid: Guid, data: &[u8]) -> Result<Self, BuildError> {
+        self.push_vendor(DeviceType::MEDIA, guid, data)
+    }
+
+    /// Append the [`END_ENTIRE`] node, and get the path.
+    ///
+    /// [`END_ENTIRE`]: DeviceSubType::END_ENTIRE
+    pub fn finalize(self) -> &'a DevicePath {
+        let vec = self.vec;
+        push_header(vec, DeviceType::END, DeviceSubType::END_ENTIRE, 4);
+        unsafe { DevicePath::from_ffi_ptr(vec.as_ptr().cast()) }
+    }
+
+    /// Append a node made of the concatenation of `parts`.
+    fn push_parts(
+        self,
+        device_type: DeviceType,
+        sub_type: DeviceSubType,
+        parts: &[&[u8]],
+    ) -> Result<Self, BuildError> {
+        let length =
+            mem::size_of::<DevicePathHeader>() + parts.iter().map(|part| part.len()).sum::<usize>();
+        let header_length =
+            u16::try_from(length).map_err(|_| BuildError::NodeTooLarge { length })?;
+
+        push_header(self.vec, device_type, sub_type, header_length);
+        for part in parts {
+            self.vec.extend_from_slice(part);
+        }
+        Ok(self)
+    }
+}
+
+/// Append a node header to `vec`.
+fn push_header(vec: &mut Vec<u8>, device_type: DeviceType, sub_type: DeviceSubType, length: u16) {
+    vec.push(device_type.0);
+    vec.push(sub_type.0);
+    vec.extend_from_slice(&length.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_api::vec;
+
+    const VENDOR: Guid = Guid::from_values(0x01020304, 0x0506, 0x0708, 0x090a, 0x0b0c0d0e0f10);
+
+    #[test]
+    fn test_push_vendor() {
+        let mut buffer = Vec::new();
+        let path = DevicePathBuilder::with_vec(&mut buffer)
+            .push_vendor_hw(VENDOR, &[0xaa])
+            .unwrap()
+            .push_vendor_messaging(VENDOR, &[])
+            .unwrap()
+            .push_vendor_media(VENDOR, &[0xbb, 0xcc])
+            .unwrap()
+            .finalize();
+
+        let nodes: Vec<_> = path.node_iter().collect();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(
+            nodes[0].full_type(),
+            (DeviceType::HARDWARE, DeviceSubType::HARDWARE_VENDOR)
+        );
+        assert_eq!(
+            nodes[1].full_type(),
+            (DeviceType::MESSAGING, DeviceSubType::MESSAGING_VENDOR)
+        );
+        assert_eq!(
+            nodes[2].full_type(),
+            (DeviceType::MEDIA, DeviceSubType::MEDIA_VENDOR)
+        );
+        assert_eq!(nodes[0].length(), 21);
+        assert_eq!(nodes[1].length(), 20);
+        assert_eq!(nodes[2].length(), 22);
+
+        // The GUID is stored with its first three fields in little endian.
+        assert_eq!(
+            &nodes[2].data,
+            &[
+                0x04, 0x03, 0x02, 0x01, 0x06, 0x05, 0x08, 0x07, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+                0x0f, 0x10, 0xbb, 0xcc
+            ]
+        );
+        assert_eq!(path.len_bytes(), 21 + 20 + 22 + 4);
+    }
+
+    #[test]
+    fn test_unsupported_vendor_type() {
+        let mut buffer = Vec::new();
+        assert_eq!(
+            DevicePathBuilder::with_vec(&mut buffer)
+                .push_vendor(DeviceType::ACPI, VENDOR, &[])
+                .unwrap_err(),
+            BuildError::UnsupportedVendorType(DeviceType::ACPI)
+        );
+    }
+
+    #[test]
+    fn test_node_too_large() {
+        let mut buffer = Vec::new();
+        let data = vec![0; usize::from(u16::MAX)];
+        assert_eq!(
+            DevicePathBuilder::with_vec(&mut buffer)
+                .push_vendor_media(VENDOR, &data)
+                .unwrap_err(),
+            BuildError::NodeTooLarge {
+                length: 4 + 16 + usize::from(u16::MAX)
+            }
+        );
+
+        // The largest possible node is fine.
+        let data = vec![0; usize::from(u16::MAX) - 4 - 16];
+        let path = DevicePathBuilder::with_vec(&mut buffer)
+            .push_vendor_media(VENDOR, &data)
+            .unwrap()
+            .finalize();
+        assert_eq!(path.node_iter().next().unwrap().length(), u16::MAX);
+    }
+
+    #[test]
+    fn test_push_path() {
+        let mut first = Vec::new();
+        let prefix = DevicePathBuilder::with_vec(&mut first)
+            .push_raw(DeviceType::HARDWARE, DeviceSubType::HARDWARE_PCI, &[1, 2])
+            .unwrap()
+            .finalize();
+
+        let mut second = Vec::new();
+        let path = DevicePathBuilder::with_vec(&mut second)
+            .push_path(prefix)
+            .push_node(prefix.node_iter().next().unwrap())
+            .finalize();
+        assert!(path.starts_with(prefix));
+        assert_eq!(path.node_iter().count(), 2);
+        assert_eq!(path.len_bytes(), 6 + 6 + 4);
+    }
+}
@@ -59,20 +59,26 @@
 //! boundary.
 //!
 //! Note: the API provided by this module is currently mostly limited to
-//! reading existing device paths rather than constructing new ones.
+//! reading existing device paths. New paths can be constructed with the
+//! [`build`] module, which requires the `exts` feature.
 //!
 //! [`END_ENTIRE`]: DeviceSubType::END_ENTIRE
 //! [`END_INSTANCE`]: DeviceSubType::END_INSTANCE
 //! [`device_type`]: DevicePathNode::device_type
 //! [`sub_type`]: DevicePathNode::sub_type
 
+#[cfg(feature = "exts")]
+pub mod build;
 pub mod text;
 
 use crate::data_types::UnalignedCStr16;
 use crate::proto::{Protocol, ProtocolPointer};
-use crate::{unsafe_guid, Guid};
+use crate::table::boot::{BootServices, MemoryType};
+use crate::{unsafe_guid, Guid, Result};
 use core::ffi::c_void;
+use core::fmt::{self, Debug, Formatter};
 use core::marker::{PhantomData, PhantomPinned};
+use core::ops::Deref;
 use core::{mem, ptr};
 
 /// Opaque type that should be used to represent a pointer to a
@@ -179,6 +185,20 @@ impl DevicePathNode {
         }
     }
 
+    /// Convert to an [`AcpiDevicePath`]. Returns `None` if the node is not
+    /// of the appropriate type.
+    pub fn as_acpi_device_path(&self) -> Option<&AcpiDevicePath> {
+        if self.full_type() == (DeviceType::ACPI, DeviceSubType::ACPI) {
+            assert!({ self.header.length } == ACPI_DEVICE_PATH_LENGTH);
+
+            let p = self as *const Self;
+            let p = p.cast::<AcpiDevicePath>();
+            Some(unsafe { &*p })
+        } else {
+            None
+        }
+    }
+
     /// Convert to a [`HardDriveMediaDevicePath`]. Returns `None` if the
     /// node is not of the appropriate type.
     pub fn as_hard_drive_media_device_path(&self) -> Option<&HardDriveMediaDevicePath> {
@@ -230,11 +250,22 @@ impl DevicePathInstance {
 ///
 /// See the [module-level documentation] for more details.
 ///
+/// # Comparison
+///
+/// Device paths are compared node by node, including any
+/// [`END_INSTANCE`] nodes separating the instances. A [`DevicePath`]
+/// always ends at its [`END_ENTIRE`] node, so data following that node in
+/// memory is not taken into account. Device paths are ordered
+/// lexicographically by their raw bytes. As the sub-type of [`END_ENTIRE`]
+/// (`0xff`) is larger than that of [`END_INSTANCE`] (`0x01`), a path sorts
+/// after a multi-instance path that starts with it.
+///
 /// [module-level documentation]: crate::proto::device_path
 /// [`END_ENTIRE`]: DeviceSubType::END_ENTIRE
+/// [`END_INSTANCE`]: DeviceSubType::END_INSTANCE
 #[repr(C, packed)]
 #[unsafe_guid("09576e91-6d3f-11d2-8e39-00a0c969723b")]
-#[derive(Debug, Eq, PartialEq, Protocol)]
+#[derive(Debug, Eq, Ord, PartialEq, PartialOrd, Protocol)]
 pub struct DevicePath {
     data: [u8],
 }
@@ -287,6 +318,14 @@ impl DevicePath {
     }
 
     /// Get an iterator over the [`DevicePathInstance`]s in this path.
+    ///
+    /// Each instance ends at the next [`END_INSTANCE`] node, and the last
+    /// one at the [`END_ENTIRE`] node. This can be used to get the devices
+    /// of multi-instance paths, such as the `ConOut` variable, which lists
+    /// all the configured console output devices.
+    ///
+    /// [`END_ENTIRE`]: DeviceSubType::END_ENTIRE
+    /// [`END_INSTANCE`]: DeviceSubType::END_INSTANCE
     pub fn instance_iter(&self) -> DevicePathInstanceIterator {
         DevicePathInstanceIterator {
             remaining_path: Some(self),
@@ -303,6 +342,101 @@ impl DevicePath {
             stop_condition: StopCondition::EndEntireNode,
         }
     }
+
+    /// Size in bytes of the entire path, including the [`END_ENTIRE`]
+    /// node.
+    ///
+    /// [`END_ENTIRE`]: DeviceSubType::END_ENTIRE
+    pub fn len_bytes(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Check whether the nodes of `prefix` match the first nodes of this
+    /// path. The [`END_ENTIRE`] node of `prefix` is not compared, so a path
+    /// starts with itself, and every path starts with an empty path.
+    ///
+    /// Instance separators are compared like any other node, so a prefix
+    /// made of a single instance only matches the start of the first
+    /// instance of this path.
+    ///
+    /// This can be used to find the devices behind a bus or hub, whose
+    /// paths start with the path of the bus or hub.
+    ///
+    /// [`END_ENTIRE`]: DeviceSubType::END_ENTIRE
+    pub fn starts_with(&self, prefix: &DevicePath) -> bool {
+        let prefix_len = prefix.len_bytes() - mem::size_of::<DevicePathHeader>();
+        // Nodes are prefixed with their length, so matching bytes also
+        // means matching node boundaries.
+        self.data.starts_with(&prefix.data[..prefix_len])
+    }
+
+    /// Copy the path into a buffer allocated from pool memory.
+    ///
+    /// This is useful to keep a path after closing the protocol it was
+    /// read from.
+    pub fn to_owned<'boot>(
+        &self,
+        boot_services: &'boot BootServices,
+    ) -> Result<PoolDevicePath<'boot>> {
+        let buf = boot_services.allocate_pool(MemoryType::LOADER_DATA, self.len_bytes())?;
+        unsafe {
+            ptr::copy_nonoverlapping(self.data.as_ptr(), buf, self.len_bytes());
+        }
+        Ok(unsafe { PoolDevicePath::from_pool(boot_services, buf.cast()) })
+    }
+}
+
+/// A [`DevicePath`] stored in memory allocated from the pool, which is
+/// freed when this is dropped.
+///
+/// This struct is returned by [`DevicePath::to_owned`] and
+/// [`SdMmcPassThru::build_device_path`].
+///
+/// [`SdMmcPassThru::build_device_path`]: crate::proto::media::sdmmc::SdMmcPassThru::build_device_path
+pub struct PoolDevicePath<'a> {
+    boot_services: &'a BootServices,
+    path: *const FfiDevicePath,
+}
+
+impl<'a> PoolDevicePath<'a> {
+    /// Take ownership of a path allocated from pool memory.
+    ///
+    /// # Safety
+    ///
+    /// `path` must be a valid device path allocated with
+    /// [`BootServices::allocate_pool`] that isn't used elsewhere.
+    pub(crate) unsafe fn from_pool(
+        boot_services: &'a BootServices,
+        path: *const FfiDevicePath,
+    ) -> Self {
+        Self {
+            boot_services,
+            path,
+        }
+    }
+}
+
+impl Deref for PoolDevicePath<'_> {
+    type Target = DevicePath;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { DevicePath::from_ffi_ptr(self.path) }
+    }
+}
+
+impl Drop for PoolDevicePath<'_> {
+    fn drop(&mut self) {
+        let addr = self.path as *mut u8;
+        self.boot_services
+            .free_pool(addr)
+            .unwrap_or_else(|e| panic!("Failed to free pool {addr:#?}: {e:?}"));
+    }
+}
+
+impl Debug for PoolDevicePath<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
 }
 
 /// Iterator over the [`DevicePathInstance`]s in a [`DevicePath`].
@@ -570,6 +704,9 @@ pub struct AcpiDevicePath {
     pub uid: u32,
 }
 
+/// [`AcpiDevicePath`] is a fixed-length structure of 12 bytes.
+const ACPI_DEVICE_PATH_LENGTH: u16 = 12;
+
 /// File Path Media Device Path.
 #[repr(C, packed)]
 pub struct FilePathMediaDevicePath {
@@ -791,6 +928,57 @@ mod tests {
         assert!(iter.next().is_none());
     }
 
+    #[test]
+    fn test_device_path_comparison() {
+        let mut raw_data = create_raw_device_path();
+        let dp = unsafe { DevicePath::from_ffi_ptr(raw_data.as_ptr().cast()) };
+        assert_eq!(dp.len_bytes(), 6 + 8 + 4 + 6 + 8 + 4);
+
+        // Data after the end-entire node is ignored.
+        let mut longer_data = raw_data.clone();
+        longer_data.extend([0xff; 4]);
+        let longer = unsafe { DevicePath::from_ffi_ptr(longer_data.as_ptr().cast()) };
+        assert_eq!(dp, longer);
+
+        // The first instance, as a path of its own.
+        let mut first_data = Vec::new();
+        add_node(&mut first_data, 0xa0, 0xb0, &[10, 11]);
+        add_node(&mut first_data, 0xa1, 0xb1, &[20, 21, 22, 23]);
+        add_node(
+            &mut first_data,
+            DeviceType::END.0,
+            DeviceSubType::END_ENTIRE.0,
+            &[],
+        );
+        let first = unsafe { DevicePath::from_ffi_ptr(first_data.as_ptr().cast()) };
+        assert_ne!(dp, first);
+        // The end-entire node of `first` is compared with the end-instance
+        // node of `dp`.
+        assert!(first > dp);
+        assert!(dp.starts_with(first));
+        assert!(dp.starts_with(dp));
+        assert!(!first.starts_with(dp));
+
+        // A path that's a prefix of the first node's data, but not of its
+        // nodes.
+        let mut partial_data = Vec::new();
+        add_node(&mut partial_data, 0xa0, 0xb0, &[10]);
+        add_node(
+            &mut partial_data,
+            DeviceType::END.0,
+            DeviceSubType::END_ENTIRE.0,
+            &[],
+        );
+        let partial = unsafe { DevicePath::from_ffi_ptr(partial_data.as_ptr().cast()) };
+        assert!(!dp.starts_with(partial));
+
+        // Change the last byte of the second instance.
+        raw_data[6 + 8 + 4 + 6 + 7] = 0;
+        let changed = unsafe { DevicePath::from_ffi_ptr(raw_data.as_ptr().cast()) };
+        assert!(changed.starts_with(first));
+        assert!(changed < longer);
+    }
+
     #[test]
     fn test_file_path_media() {
         // Manually create data for a `FilePathMediaDevicePath` node.
@@ -0,0 +1,552 @@
+//! Driver model protocols: binding, configuration, naming and diagnostics.
+//!
+//! The [`model`] module implements these protocols for drivers written in
+//! Rust.
+
+#[cfg(feature = "exts")]
+pub mod model;
+
+use crate::data_types::Guid;
+use crate::proto::device_path::FfiDevicePath;
+use crate::proto::Protocol;
+use crate::{unsafe_guid, CStr16, CStr8, Char16, Char8, Handle, Result, Status};
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::{mem, ptr, slice};
+
+/// GUID of the [`ClpParameterBlock`] parameter type.
+pub const CLP_PARAMETER_TYPE: Guid =
+    Guid::from_values(0x345ecc0e, 0x0cb6, 0x4b75, 0xbb57, 0x1b129c47333e);
+
+newtype_enum! {
+    /// The outcome of processing a configuration query, reported to the
+    /// platform with [`PlatformToDriverConfiguration::response`].
+    pub enum ConfigurationAction: u32 => {
+        /// The configuration was applied; no further action is needed.
+        NONE = 0,
+        /// The controller must be stopped for the configuration to take effect.
+        STOP_CONTROLLER = 1,
+        /// The controller must be restarted for the configuration to take
+        /// effect.
+        RESTART_CONTROLLER = 2,
+        /// The platform must be restarted for the configuration to take
+        /// effect.
+        RESTART_PLATFORM = 3,
+        /// The configuration could not be stored in non-volatile storage.
+        NVRAM_FAILED = 4,
+        /// The driver does not support the parameter type.
+        UNSUPPORTED_GUID = 5,
+    }
+}
+
+/// The platform to driver configuration protocol.
+///
+/// This protocol is used by drivers to query the platform for configuration
+/// data of a controller, and to report back whether it was applied.
+///
+/// A driver queries the configuration starting with instance `0`. After
+/// processing an instance, it reports the outcome with [`response`] and
+/// then queries the next instance, until [`query`] returns `None`.
+///
+/// [`query`]: Self::query
+/// [`response`]: Self::response
+#[repr(C)]
+#[unsafe_guid("642cd590-8059-4c0a-a958-c5ec07d23c4b")]
+#[derive(Protocol)]
+pub struct PlatformToDriverConfiguration {
+    query: unsafe extern "efiapi" fn(
+        this: &Self,
+        controller_handle: Handle,
+        child_handle: Option<Handle>,
+        instance: *const usize,
+        parameter_type_guid: *mut *const Guid,
+        parameter_block: *mut *mut c_void,
+        parameter_block_size: *mut usize,
+    ) -> Status,
+    response: unsafe extern "efiapi" fn(
+        this: &Self,
+        controller_handle: Handle,
+        child_handle: Option<Handle>,
+        instance: *const usize,
+        parameter_type_guid: *const Guid,
+        parameter_block: *mut c_void,
+        parameter_block_size: usize,
+        configuration_action: ConfigurationAction,
+    ) -> Status,
+}
+
+impl PlatformToDriverConfiguration {
+    /// Queries the configuration `instance` of `controller`, or of its
+    /// `child` if given.
+    ///
+    /// Returns `None` if there is no such instance, which ends the iteration
+    /// over the instances.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `controller` is not a valid handle.
+    /// * [`Status::COMPROMISED_DATA`]: the platform returned a null
+    ///   parameter type.
+    pub fn query(
+        &self,
+        controller: Handle,
+        child: Option<Handle>,
+        instance: usize,
+    ) -> Result<Option<ConfigurationQuery<'_>>> {
+        let mut parameter_type = ptr::null();
+        let mut parameter_block = ptr::null_mut();
+        let mut parameter_block_size = 0;
+        let status = unsafe {
+            (self.query)(
+                self,
+                controller,
+                child,
+                &instance,
+                &mut parameter_type,
+                &mut parameter_block,
+                &mut parameter_block_size,
+            )
+        };
+
+        match status {
+            Status::NOT_FOUND => Ok(None),
+            status => {
+                Result::from(status)?;
+                if parameter_type.is_null() {
+                    return Err(Status::COMPROMISED_DATA.into());
+                }
+                Ok(Some(ConfigurationQuery {
+                    instance,
+                    parameter_type: unsafe { *parameter_type },
+                    parameter_block: parameter_block.cast(),
+                    parameter_block_size,
+                    _lifetime: PhantomData,
+                }))
+            }
+        }
+    }
+
+    /// Reports to the platform how `query` was processed. This must be
+    /// called for each query before querying the next instance.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `controller` is not a valid handle,
+    ///   or `query` does not belong to it.
+    pub fn response(
+        &self,
+        controller: Handle,
+        child: Option<Handle>,
+        query: ConfigurationQuery<'_>,
+        action: ConfigurationAction,
+    ) -> Result {
+        unsafe {
+            (self.response)(
+                self,
+                controller,
+                child,
+                &query.instance,
+                &query.parameter_type,
+                query.parameter_block.cast(),
+                query.parameter_block_size,
+                action,
+            )
+        }
+        .into()
+    }
+}
+
+/// Configuration data returned by [`PlatformToDriverConfiguration::query`].
+///
+/// The parameter block is owned by the platform and stays valid until the
+/// query is passed to [`PlatformToDriverConfiguration::response`].
+#[derive(Debug)]
+pub struct ConfigurationQuery<'a> {
+    instance: usize,
+    parameter_type: Guid,
+    parameter_block: *mut u8,
+    parameter_block_size: usize,
+    _lifetime: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> ConfigurationQuery<'a> {
+    /// The instance this configuration was queried for.
+    pub fn instance(&self) -> usize {
+        self.instance
+    }
+
+    /// The GUID identifying the format of the parameter block.
+    pub fn parameter_type(&self) -> &Guid {
+        &self.parameter_type
+    }
+
+    /// The raw parameter block.
+    pub fn raw_parameter_block(&mut self) -> &mut [u8] {
+        if self.parameter_block.is_null() {
+            &mut []
+        } else {
+            unsafe { slice::from_raw_parts_mut(self.parameter_block, self.parameter_block_size) }
+        }
+    }
+
+    /// The parameter block, decoded if its type is known.
+    ///
+    /// Returns [`MalformedParameterBlock`] if the type is known, but the size
+    /// or alignment of the block don't match it.
+    pub fn parameter_block(
+        &mut self,
+    ) -> core::result::Result<ParameterBlock<'_>, MalformedParameterBlock> {
+        if self.parameter_type == CLP_PARAMETER_TYPE {
+            let ptr = self.parameter_block.cast::<ClpParameterBlock>();
+            if ptr.is_null()
+                || self.parameter_block_size < mem::size_of::<ClpParameterBlock>()
+                || ptr.align_offset(mem::align_of::<ClpParameterBlock>()) != 0
+            {
+                return Err(MalformedParameterBlock);
+            }
+            Ok(ParameterBlock::Clp(unsafe { &mut *ptr }))
+        } else {
+            Ok(ParameterBlock::Other(self.raw_parameter_block()))
+        }
+    }
+}
+
+/// A decoded parameter block, see [`ConfigurationQuery::parameter_block`].
+#[derive(Debug)]
+pub enum ParameterBlock<'a> {
+    /// A DMTF SM CLP command, identified by [`CLP_PARAMETER_TYPE`].
+    Clp(&'a mut ClpParameterBlock),
+    /// A parameter block of a type that is not decoded by this crate.
+    Other(&'a mut [u8]),
+}
+
+/// Returned by [`ConfigurationQuery::parameter_block`] if the parameter block
+/// doesn't match its type.
+#[derive(Clone, Copy, Debug)]
+pub struct MalformedParameterBlock;
+
+/// A DMTF SM CLP command to be executed by the driver.
+///
+/// The driver reports the result by setting the status fields, and
+/// optionally a return string, before calling
+/// [`PlatformToDriverConfiguration::response`].
+#[derive(Debug)]
+#[repr(C)]
+pub struct ClpParameterBlock {
+    command: *const u8,
+    command_length: u32,
+    return_string: *const u8,
+    return_string_length: u32,
+    /// The status of the command, as defined by the CLP specification.
+    pub cmd_status: u8,
+    /// The error value of the command, as defined by the CLP specification.
+    pub error_value: u8,
+    /// The message code of the command, as defined by the CLP specification.
+    pub msg_code: u16,
+}
+
+impl ClpParameterBlock {
+    /// The CLP command string, in ASCII.
+    pub fn command(&self) -> &[u8] {
+        if self.command.is_null() {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(self.command, self.command_length as usize) }
+        }
+    }
+
+    /// The CLP return string set by the driver, in ASCII.
+    pub fn return_string(&self) -> &[u8] {
+        if self.return_string.is_null() {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(self.return_string, self.return_string_length as usize) }
+        }
+    }
+
+    /// Sets the CLP return string, in ASCII. The platform may read it after
+    /// the response, so it must be `'static`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the string is longer than `u32::MAX` bytes.
+    pub fn set_return_string(&mut self, return_string: &'static [u8]) {
+        self.return_string_length = return_string
+            .len()
+            .try_into()
+            .expect("CLP return string is too long");
+        self.return_string = return_string.as_ptr();
+    }
+}
+
+/// The driver binding protocol, installed by drivers following the UEFI
+/// driver model on their image handle.
+///
+/// The firmware calls its functions from [`BootServices::connect_controller`]
+/// and [`BootServices::disconnect_controller`] to find the drivers that
+/// support a controller, and to start and stop them, so they are not exposed
+/// here. See [`model`] to implement this protocol.
+///
+/// [`BootServices::connect_controller`]: crate::table::boot::BootServices::connect_controller
+/// [`BootServices::disconnect_controller`]: crate::table::boot::BootServices::disconnect_controller
+#[repr(C)]
+#[unsafe_guid("18a031ab-b443-4d1a-a5c0-0c09261e9f71")]
+#[derive(Protocol)]
+pub struct DriverBinding {
+    supported: unsafe extern "efiapi" fn(
+        this: *const Self,
+        controller: Handle,
+        remaining_device_path: *const FfiDevicePath,
+    ) -> Status,
+    start: unsafe extern "efiapi" fn(
+        this: *const Self,
+        controller: Handle,
+        remaining_device_path: *const FfiDevicePath,
+    ) -> Status,
+    stop: unsafe extern "efiapi" fn(
+        this: *const Self,
+        controller: Handle,
+        number_of_children: usize,
+        child_handle_buffer: *const Handle,
+    ) -> Status,
+    version: u32,
+    image_handle: Handle,
+    driver_binding_handle: Handle,
+}
+
+impl DriverBinding {
+    /// The version of the driver. When several drivers support a
+    /// controller, those with a higher version are tried first.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// The image handle of the driver.
+    pub fn image_handle(&self) -> Handle {
+        self.image_handle
+    }
+
+    /// The handle this protocol is installed on, usually the image handle.
+    pub fn driver_binding_handle(&self) -> Handle {
+        self.driver_binding_handle
+    }
+}
+
+/// Convert the `SupportedLanguages` field of a driver protocol, which some
+/// drivers leave null.
+unsafe fn languages_from_ptr<'a>(languages: *const Char8) -> Option<&'a CStr8> {
+    if languages.is_null() {
+        None
+    } else {
+        Some(CStr8::from_ptr(languages))
+    }
+}
+
+/// The component name protocol, installed by drivers on their driver binding
+/// handle to give human-readable names to themselves and to the controllers
+/// they manage.
+///
+/// Names are given in the languages of [`supported_languages`], which are
+/// RFC 4646 language codes such as `en-US`.
+///
+/// [`supported_languages`]: Self::supported_languages
+#[repr(C)]
+#[unsafe_guid("6a7a5cff-e8d9-4f70-bada-75ab3025ce14")]
+#[derive(Protocol)]
+pub struct ComponentName2 {
+    get_driver_name: unsafe extern "efiapi" fn(
+        this: *const Self,
+        language: *const Char8,
+        driver_name: &mut *const Char16,
+    ) -> Status,
+    get_controller_name: unsafe extern "efiapi" fn(
+        this: *const Self,
+        controller: Handle,
+        child: Option<Handle>,
+        language: *const Char8,
+        controller_name: &mut *const Char16,
+    ) -> Status,
+    supported_languages: *const Char8,
+}
+
+impl ComponentName2 {
+    /// The languages the names are available in, as a list of RFC 4646
+    /// language codes separated by semicolons, for example `en-US;fr-FR`.
+    /// Returns `None` if the driver doesn't provide the list.
+    pub fn supported_languages(&self) -> Option<&CStr8> {
+        unsafe { languages_from_ptr(self.supported_languages) }
+    }
+
+    /// Iterate over the [`supported_languages`], without the separators.
+    ///
+    /// [`supported_languages`]: Self::supported_languages
+    pub fn languages(&self) -> impl Iterator<Item = &[u8]> {
+        let languages = self.supported_languages().map_or(&[][..], CStr8::to_bytes);
+        languages
+            .split(|&c| c == b';')
+            .filter(|language| !language.is_empty())
+    }
+
+    /// Returns the name of the driver in `language`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the name is not available in `language`.
+    pub fn get_driver_name(&self, language: &CStr8) -> Result<&CStr16> {
+        let mut name = ptr::null();
+        unsafe { (self.get_driver_name)(self, language.as_ptr(), &mut name) }
+            .into_with_val(|| unsafe { CStr16::from_ptr(name) })
+    }
+
+    /// Returns the name of `controller` in `language`, or the name of its
+    /// `child` if given.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the driver doesn't manage the controller
+    ///   or the child, or the name is not available in `language`.
+    pub fn get_controller_name(
+        &self,
+        controller: Handle,
+        child: Option<Handle>,
+        language: &CStr8,
+    ) -> Result<&CStr16> {
+        let mut name = ptr::null();
+        unsafe { (self.get_controller_name)(self, controller, child, language.as_ptr(), &mut name) }
+            .into_with_val(|| unsafe { CStr16::from_ptr(name) })
+    }
+}
+
+newtype_enum! {
+    /// The kind of diagnostics run by [`DriverDiagnostics2`].
+    pub enum DiagnosticType: u32 => {
+        /// Quick diagnostics, run in a few seconds.
+        STANDARD = 0,
+        /// Thorough diagnostics, which may take a long time.
+        EXTENDED = 1,
+        /// Diagnostics meant for the manufacturing environment.
+        MANUFACTURING = 2,
+        /// Cancel diagnostics running in the background.
+        CANCEL = 3,
+    }
+}
+
+/// The driver diagnostics protocol, installed by drivers on their driver
+/// binding handle to test the controllers they manage.
+///
+/// This corresponds to the `EFI_DRIVER_DIAGNOSTICS2_PROTOCOL` type in the C
+/// API. The results are returned in a buffer that the caller must free, so
+/// running diagnostics is not exposed here. See [`model`] to implement this
+/// protocol.
+#[repr(C)]
+#[unsafe_guid("4d330321-025f-4aac-90d8-5ed900173b63")]
+#[derive(Protocol)]
+pub struct DriverDiagnostics2 {
+    run_diagnostics: unsafe extern "efiapi" fn(
+        this: *const Self,
+        controller: Handle,
+        child: Option<Handle>,
+        diagnostic_type: DiagnosticType,
+        language: *const Char8,
+        error_type: *mut *const Guid,
+        buffer_size: *mut usize,
+        buffer: *mut *mut Char16,
+    ) -> Status,
+    supported_languages: *const Char8,
+}
+
+impl DriverDiagnostics2 {
+    /// The languages of the diagnostic messages, as a list of RFC 4646
+    /// language codes separated by semicolons. Returns `None` if the driver
+    /// doesn't provide the list.
+    pub fn supported_languages(&self) -> Option<&CStr8> {
+        unsafe { languages_from_ptr(self.supported_languages) }
+    }
+}
+
+newtype_enum! {
+    /// The kind of defaults set by [`DriverConfiguration2::force_defaults`].
+    pub enum DefaultType: u32 => {
+        /// The standard defaults.
+        STANDARD = 0,
+        /// The defaults for the manufacturing environment.
+        MANUFACTURING = 1,
+        /// Defaults that are safe in any environment.
+        SAFE = 2,
+    }
+}
+
+/// The driver configuration protocol, installed by drivers on their driver
+/// binding handle to configure the controllers they manage.
+///
+/// This corresponds to the `EFI_DRIVER_CONFIGURATION2_PROTOCOL` type in the
+/// C API. It is deprecated in favor of HII forms, but still installed by
+/// some drivers. The required actions use the values of
+/// [`ConfigurationAction`] from `NONE` to `RESTART_PLATFORM`. See [`model`]
+/// to implement this protocol.
+#[repr(C)]
+#[unsafe_guid("bfd7dc1d-24f1-40d9-82e7-2e09bb6b4ebe")]
+#[derive(Protocol)]
+pub struct DriverConfiguration2 {
+    set_options: unsafe extern "efiapi" fn(
+        this: *const Self,
+        controller: Handle,
+        child: Option<Handle>,
+        language: *const Char8,
+        action_required: *mut ConfigurationAction,
+    ) -> Status,
+    options_valid: unsafe extern "efiapi" fn(
+        this: *const Self,
+        controller: Handle,
+        child: Option<Handle>,
+    ) -> Status,
+    force_defaults: unsafe extern "efiapi" fn(
+        this: *const Self,
+        controller: Handle,
+        child: Option<Handle>,
+        default_type: DefaultType,
+        action_required: *mut ConfigurationAction,
+    ) -> Status,
+    supported_languages: *const Char8,
+}
+
+impl DriverConfiguration2 {
+    /// The languages the options can be set in, as a list of RFC 4646
+    /// language codes separated by semicolons. Returns `None` if the driver
+    /// doesn't provide the list.
+    pub fn supported_languages(&self) -> Option<&CStr8> {
+        unsafe { languages_from_ptr(self.supported_languages) }
+    }
+
+    /// Checks whether the options of `controller`, or of its `child` if
+    /// given, are valid.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the driver doesn't manage the controller
+    ///   or the child.
+    /// * [`Status::DEVICE_ERROR`]: the options are not valid.
+    pub fn options_valid(&self, controller: Handle, child: Option<Handle>) -> Result {
+        unsafe { (self.options_valid)(self, controller, child) }.into()
+    }
+
+    /// Sets the options of `controller`, or of its `child` if given, to
+    /// their defaults of the given type, and returns what is needed for them
+    /// to take effect.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the driver doesn't manage the controller
+    ///   or the child, or doesn't support `default_type`.
+    /// * [`Status::DEVICE_ERROR`]: the defaults could not be set.
+    pub fn force_defaults(
+        &self,
+        controller: Handle,
+        child: Option<Handle>,
+        default_type: DefaultType,
+    ) -> Result<ConfigurationAction> {
+        let mut action = ConfigurationAction::NONE;
+        unsafe { (self.force_defaults)(self, controller, child, default_type, &mut action) }
+            .into_with_val(|| action)
+    }
+}
@@ -0,0 +1,748 @@
+//! Implementing a driver that follows the UEFI driver model.
+//!
+//! A [`DriverModel`] collects the callbacks of a driver, and
+//! [`DriverModel::install`] installs on the image handle of the driver the
+//! protocols the firmware looks for:
+//!
+//! * [`DriverBinding`], always, whose callbacks are Rust closures: the
+//!   firmware calls `supported` from [`BootServices::connect_controller`] to
+//!   find the drivers of a controller, then `start` on the first one that
+//!   supports it, and `stop` from [`BootServices::disconnect_controller`].
+//! * [`ComponentName2`], with [`DriverModel::component_name`], to name the
+//!   driver and the controllers it manages.
+//! * [`DriverDiagnostics2`], with [`DriverModel::diagnostics`].
+//! * [`DriverConfiguration2`], with [`DriverModel::configuration`].
+//!
+//! The protocols are uninstalled when the returned [`InstalledDriver`] is
+//! uninstalled or dropped, after disconnecting the driver from all the
+//! controllers it manages.
+//!
+//! # Image requirements
+//!
+//! The image must be built as a boot service driver (PE subsystem 11), so
+//! that the firmware keeps it in memory after its entry point returns. The
+//! entry point installs the protocols and returns [`Status::SUCCESS`]; the
+//! `start` callback is only called later, when the controllers are
+//! connected, for example by the boot manager or by the `connect` shell
+//! command.
+//!
+//! A driver that can be unloaded sets the unload function of its
+//! [`LoadedImage`] with [`LoadedImage::set_unload`]. That function must
+//! uninstall the driver, which is why the [`InstalledDriver`] is kept in a
+//! static.
+//!
+//! # Safety
+//!
+//! As with the [file system provider], the callbacks are called by the
+//! firmware at times that are not under the control of the driver:
+//!
+//! * They must not panic, as the panic can't unwind through the firmware.
+//! * They must not re-enter the same protocol, for example by connecting a
+//!   controller from `start`, which may lead back to this driver while its
+//!   callback is borrowed mutably.
+//! * Boot services must not be exited while the driver is installed.
+//!
+//! The callbacks of the different protocols are separate closures or
+//! objects; state they share, such as the list of started controllers, must
+//! be kept with shared ownership, for example in an `Rc<RefCell<_>>`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::prelude::*;
+//! use uefi::proto::driver::model::{DriverModel, InstalledDriver};
+//! use uefi::proto::loaded_image::LoadedImage;
+//! use uefi::proto::media::block::BlockIO;
+//! use uefi::table::boot::OpenProtocolParams;
+//! use uefi::Handle;
+//!
+//! static mut DRIVER: Option<InstalledDriver<'static>> = None;
+//!
+//! extern "efiapi" fn unload(_image: Handle) -> Status {
+//!     // Safety: the driver is only used by the entry point and this
+//!     // function, which don't run at the same time.
+//!     match unsafe { DRIVER.take() } {
+//!         Some(driver) => driver
+//!             .uninstall()
+//!             .map_or_else(|err| err.status(), |()| Status::SUCCESS),
+//!         None => Status::SUCCESS,
+//!     }
+//! }
+//!
+//! # fn efi_main(image: Handle, st: SystemTable<Boot>) -> Status {
+//! // The driver stays installed after the entry point returns, so it needs
+//! // the boot services for the lifetime of the image.
+//! let st: &'static SystemTable<Boot> = Box::leak(Box::new(st));
+//! let bt = st.boot_services();
+//!
+//! let driver = DriverModel::new(
+//!     0x10,
+//!     move |controller, _| {
+//!         // Only check that the protocol is there, without opening it.
+//!         bt.test_protocol::<BlockIO>(OpenProtocolParams {
+//!             handle: controller,
+//!             agent: bt.image_handle(),
+//!             controller: None,
+//!         })
+//!     },
+//!     |_controller, _| Ok(()),
+//!     |_controller, _children| Ok(()),
+//! )
+//! .component_name(cstr8!("en"), cstr16!("Example Driver"));
+//!
+//! let driver = unsafe { driver.install(bt, image) }.expect("Failed to install the driver");
+//! unsafe {
+//!     // Safety: `unload` uninstalls everything the driver installed.
+//!     bt.open_protocol_exclusive::<LoadedImage>(image)
+//!         .expect("Failed to open the loaded image")
+//!         .set_unload(unload);
+//!     DRIVER = Some(driver);
+//! }
+//! Status::SUCCESS
+//! # }
+//! ```
+//!
+//! [file system provider]: crate::proto::media::fs::provider
+//! [`LoadedImage`]: crate::proto::loaded_image::LoadedImage
+//! [`LoadedImage::set_unload`]: crate::proto::loaded_image::LoadedImage::set_unload
+//! [`BootServices::connect_controller`]: crate::table::boot::BootServices::connect_controller
+//! [`BootServices::disconnect_controller`]: crate::table::boot::BootServices::disconnect_controller
+
+use super::{
+    ComponentName2, ConfigurationAction, DefaultType, DiagnosticType, DriverBinding,
+    DriverConfiguration2, DriverDiagnostics2,
+};
+use crate::alloc_api::boxed::Box;
+use crate::alloc_api::vec;
+use crate::alloc_api::vec::Vec;
+use crate::data_types::CString16;
+use crate::proto::device_path::{DevicePath, FfiDevicePath};
+use crate::table::boot::{BootServices, MemoryType, SearchType};
+use crate::{CStr16, CStr8, Char16, Char8, Guid, Handle, Identify, Result, Status};
+use core::ffi::c_void;
+use core::{mem, ptr, slice};
+
+/// Callback checking whether the driver supports a controller, or starting
+/// the driver on it. The device path, if given, is the remaining path of the
+/// child controller to create.
+type BindingCallback = Box<dyn FnMut(Handle, Option<&DevicePath>) -> Result>;
+
+/// Callback stopping the driver on a controller, or only on the given
+/// children if there are any.
+type StopCallback = Box<dyn FnMut(Handle, &[Handle]) -> Result>;
+
+/// Callback naming a controller, or its child if given.
+type ControllerNameCallback = Box<dyn FnMut(Handle, Option<Handle>) -> Option<&'static CStr16>>;
+
+/// Callback running diagnostics on a controller, or on its child if given.
+type DiagnosticsCallback =
+    Box<dyn FnMut(Handle, Option<Handle>, DiagnosticType) -> Result<DiagnosticsReport>>;
+
+/// The configuration options of a driver, served through the
+/// [`DriverConfiguration2`] protocol. See [`DriverModel::configuration`].
+///
+/// The methods correspond to the functions of the protocol. Each applies to
+/// `controller`, or to its `child` if given, and returns
+/// [`Status::UNSUPPORTED`] if the driver doesn't manage them.
+pub trait ConfigurationProvider {
+    /// Let the user set the options, in `language`, and return what is needed
+    /// for them to take effect.
+    fn set_options(
+        &mut self,
+        controller: Handle,
+        child: Option<Handle>,
+        language: &CStr8,
+    ) -> Result<ConfigurationAction>;
+
+    /// Check whether the options are valid, returning
+    /// [`Status::DEVICE_ERROR`] if they are not.
+    fn options_valid(&mut self, controller: Handle, child: Option<Handle>) -> Result;
+
+    /// Set the options to their defaults of the given type, and return what
+    /// is needed for them to take effect.
+    fn force_defaults(
+        &mut self,
+        controller: Handle,
+        child: Option<Handle>,
+        default_type: DefaultType,
+    ) -> Result<ConfigurationAction>;
+}
+
+/// The outcome of diagnostics run by the callback of
+/// [`DriverModel::diagnostics`].
+#[derive(Debug, Default)]
+pub struct DiagnosticsReport {
+    /// Whether the controller passed the diagnostics. If not, the caller
+    /// gets [`Status::DEVICE_ERROR`] with the rest of the report.
+    pub passed: bool,
+
+    /// A GUID defined by the driver describing the error, if any.
+    pub error_type: Option<Guid>,
+
+    /// A message describing the outcome, in the language the diagnostics
+    /// were run in.
+    pub message: Option<CString16>,
+}
+
+/// The callbacks of a driver, to install its protocols with [`install`].
+///
+/// See the [module-level documentation](self) for details.
+///
+/// [`install`]: DriverModel::install
+pub struct DriverModel {
+    version: u32,
+    supported: BindingCallback,
+    start: BindingCallback,
+    stop: StopCallback,
+    names: Option<(&'static CStr8, &'static CStr16)>,
+    controller_name: Option<ControllerNameCallback>,
+    diagnostics: Option<(&'static CStr8, DiagnosticsCallback)>,
+    configuration: Option<(&'static CStr8, Box<dyn ConfigurationProvider>)>,
+}
+
+impl DriverModel {
+    /// Create a driver with the given version and driver binding callbacks.
+    ///
+    /// * `supported` returns `Ok` if the driver supports the controller.
+    ///   It is called for many controllers, so it should be fast, and must
+    ///   not change the state of the controller: it may open protocols, but
+    ///   must close them again.
+    /// * `start` starts the driver on the controller, opening its protocols
+    ///   with [`OpenProtocolAttributes::ByDriver`]. A bus driver creates the
+    ///   child controllers, or only the one described by the remaining
+    ///   device path if given.
+    /// * `stop` stops the driver on the controller and closes its protocols,
+    ///   or only destroys the given child controllers if there are any.
+    ///
+    /// When several drivers support a controller, those with a higher
+    /// `version` are tried first.
+    ///
+    /// [`OpenProtocolAttributes::ByDriver`]: crate::table::boot::OpenProtocolAttributes::ByDriver
+    pub fn new<Supported, Start, Stop>(
+        version: u32,
+        supported: Supported,
+        start: Start,
+        stop: Stop,
+    ) -> Self
+    where
+        Supported: FnMut(Handle, Option<&DevicePath>) -> Result + 'static,
+        Start: FnMut(Handle, Option<&DevicePath>) -> Result + 'static,
+        Stop: FnMut(Handle, &[Handle]) -> Result + 'static,
+    {
+        Self {
+            version,
+            supported: Box::new(supported),
+            start: Box::new(start),
+            stop: Box::new(stop),
+            names: None,
+            controller_name: None,
+            diagnostics: None,
+            configuration: None,
+        }
+    }
+
+    /// Install a [`ComponentName2`] protocol naming the driver
+    /// `driver_name`, in the only language of `languages`, or in all of them
+    /// if there are several separated by semicolons.
+    pub fn component_name(
+        mut self,
+        languages: &'static CStr8,
+        driver_name: &'static CStr16,
+    ) -> Self {
+        self.names = Some((languages, driver_name));
+        self
+    }
+
+    /// Name the controllers managed by the driver, or their children, in the
+    /// [`ComponentName2`] protocol. Controllers for which `name` returns
+    /// `None` are not named. This has no effect without a
+    /// [`component_name`].
+    ///
+    /// [`component_name`]: DriverModel::component_name
+    pub fn controller_name<F>(mut self, name: F) -> Self
+    where
+        F: FnMut(Handle, Option<Handle>) -> Option<&'static CStr16> + 'static,
+    {
+        self.controller_name = Some(Box::new(name));
+        self
+    }
+
+    /// Install a [`DriverDiagnostics2`] protocol, whose messages are in
+    /// `languages`, running `run` to test a controller or its child. `run`
+    /// returns [`Status::UNSUPPORTED`] for the types of diagnostics or the
+    /// controllers it doesn't support.
+    pub fn diagnostics<F>(mut self, languages: &'static CStr8, run: F) -> Self
+    where
+        F: FnMut(Handle, Option<Handle>, DiagnosticType) -> Result<DiagnosticsReport> + 'static,
+    {
+        self.diagnostics = Some((languages, Box::new(run)));
+        self
+    }
+
+    /// Install a [`DriverConfiguration2`] protocol, whose options can be set
+    /// in `languages`, served by `provider`.
+    pub fn configuration(
+        mut self,
+        languages: &'static CStr8,
+        provider: Box<dyn ConfigurationProvider>,
+    ) -> Self {
+        self.configuration = Some((languages, provider));
+        self
+    }
+
+    /// Install the protocols of the driver on its image handle `image`.
+    ///
+    /// The protocols are installed all at once with
+    /// [`BootServices::install_multiple_protocol_interfaces`], and
+    /// uninstalled when the returned [`InstalledDriver`] is dropped.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: one of the protocols is already
+    ///   installed on `image`.
+    ///
+    /// See [`BootServices::install_multiple_protocol_interfaces`] for the
+    /// other errors.
+    ///
+    /// # Safety
+    ///
+    /// The requirements listed in the [module-level documentation](self)
+    /// must be upheld.
+    pub unsafe fn install(self, bt: &BootServices, image: Handle) -> Result<InstalledDriver<'_>> {
+        let binding = Box::into_raw(Box::new(ProvidedBinding {
+            protocol: DriverBinding {
+                supported: binding_supported,
+                start: binding_start,
+                stop: binding_stop,
+                version: self.version,
+                image_handle: image,
+                driver_binding_handle: image,
+            },
+            supported: self.supported,
+            start: self.start,
+            stop: self.stop,
+        }));
+        let mut interfaces = vec![(DriverBinding::GUID, binding as *const c_void)];
+
+        if let Some((languages, driver_name)) = self.names {
+            let names = Box::into_raw(Box::new(ProvidedNames {
+                protocol: ComponentName2 {
+                    get_driver_name,
+                    get_controller_name,
+                    supported_languages: languages.as_ptr(),
+                },
+                driver_name,
+                controller_name: self.controller_name,
+            }));
+            interfaces.push((ComponentName2::GUID, names as *const c_void));
+        }
+
+        if let Some((languages, run)) = self.diagnostics {
+            let diagnostics = Box::into_raw(Box::new(ProvidedDiagnostics {
+                protocol: DriverDiagnostics2 {
+                    run_diagnostics,
+                    supported_languages: languages.as_ptr(),
+                },
+                boot_services: bt,
+                run,
+                error_type: Guid::default(),
+            }));
+            interfaces.push((DriverDiagnostics2::GUID, diagnostics as *const c_void));
+        }
+
+        if let Some((languages, provider)) = self.configuration {
+            let configuration = Box::into_raw(Box::new(ProvidedConfiguration {
+                protocol: DriverConfiguration2 {
+                    set_options,
+                    options_valid,
+                    force_defaults,
+                    supported_languages: languages.as_ptr(),
+                },
+                provider,
+            }));
+            interfaces.push((DriverConfiguration2::GUID, configuration as *const c_void));
+        }
+
+        let installed = match bt.install_multiple_protocol_interfaces(Some(image), &interfaces) {
+            Ok(installed) => installed,
+            Err(err) => {
+                free_interfaces(&interfaces);
+                return Err(err);
+            }
+        };
+        // The interfaces are uninstalled by `InstalledDriver` instead.
+        installed.leak();
+        Ok(InstalledDriver {
+            boot_services: bt,
+            handle: image,
+            interfaces,
+        })
+    }
+}
+
+/// The protocols of a driver installed with [`DriverModel::install`], which
+/// are uninstalled when this is dropped.
+#[derive(Debug)]
+pub struct InstalledDriver<'boot> {
+    boot_services: &'boot BootServices,
+    handle: Handle,
+    interfaces: Vec<(Guid, *const c_void)>,
+}
+
+impl InstalledDriver<'_> {
+    /// The handle the protocols are installed on, which is the image handle
+    /// of the driver.
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+
+    /// Disconnect the driver from all its controllers and uninstall its
+    /// protocols, as when this is dropped, but report whether that
+    /// succeeded.
+    ///
+    /// If the protocols couldn't be uninstalled, because the driver still
+    /// manages a controller that refused to stop, they stay installed and
+    /// their memory is leaked, since the firmware may still use them.
+    ///
+    /// # Errors
+    ///
+    /// See [`BootServices::uninstall_multiple_protocol_interfaces`].
+    pub fn uninstall(self) -> Result {
+        let result = unsafe { self.uninstall_impl() };
+        mem::forget(self);
+        result
+    }
+
+    unsafe fn uninstall_impl(&self) -> Result {
+        // Stop the driver on the controllers it manages, as the firmware
+        // doesn't do it when the protocols are uninstalled. Controllers not
+        // managed by the driver are left alone by the firmware.
+        if let Ok(handles) = self
+            .boot_services
+            .locate_handle_buffer(SearchType::AllHandles)
+        {
+            for &controller in handles.iter() {
+                let _ =
+                    self.boot_services
+                        .disconnect_controller(controller, Some(self.handle), None);
+            }
+        }
+        self.boot_services
+            .uninstall_multiple_protocol_interfaces(self.handle, &self.interfaces)?;
+        free_interfaces(&self.interfaces);
+        Ok(())
+    }
+}
+
+impl Drop for InstalledDriver<'_> {
+    fn drop(&mut self) {
+        // There is nothing else to do if a controller refuses to stop.
+        let _ = unsafe { self.uninstall_impl() };
+    }
+}
+
+/// Free the interfaces allocated by [`DriverModel::install`].
+unsafe fn free_interfaces(interfaces: &[(Guid, *const c_void)]) {
+    for &(guid, interface) in interfaces {
+        let interface = interface as *mut c_void;
+        if guid == DriverBinding::GUID {
+            drop(Box::from_raw(interface.cast::<ProvidedBinding>()));
+        } else if guid == ComponentName2::GUID {
+            drop(Box::from_raw(interface.cast::<ProvidedNames>()));
+        } else if guid == DriverDiagnostics2::GUID {
+            drop(Box::from_raw(interface.cast::<ProvidedDiagnostics>()));
+        } else if guid == DriverConfiguration2::GUID {
+            drop(Box::from_raw(interface.cast::<ProvidedConfiguration>()));
+        }
+    }
+}
+
+/// Convert the result of a callback to the status returned to the firmware.
+fn status(result: Result) -> Status {
+    result.map_or_else(|err| err.status(), |()| Status::SUCCESS)
+}
+
+/// Whether `language` is one of the semicolon-separated `languages`.
+fn is_supported_language(languages: &CStr8, language: &CStr8) -> bool {
+    let language = language.to_bytes();
+    languages
+        .to_bytes()
+        .split(|&c| c == b';')
+        .any(|supported| !supported.is_empty() && supported == language)
+}
+
+/// Check the language passed by the caller, which may be null.
+unsafe fn language<'a>(supported: *const Char8, language: *const Char8) -> Result<&'a CStr8> {
+    if language.is_null() {
+        return Err(Status::INVALID_PARAMETER.into());
+    }
+    let language = CStr8::from_ptr(language);
+    if is_supported_language(CStr8::from_ptr(supported), language) {
+        Ok(language)
+    } else {
+        Err(Status::UNSUPPORTED.into())
+    }
+}
+
+/// `EFI_DRIVER_BINDING_PROTOCOL` as installed by [`DriverModel::install`],
+/// followed by its callbacks. The protocol comes first, so that a pointer to
+/// the protocol is also a pointer to this.
+#[repr(C)]
+struct ProvidedBinding {
+    protocol: DriverBinding,
+    supported: BindingCallback,
+    start: BindingCallback,
+    stop: StopCallback,
+}
+
+unsafe fn remaining_path<'a>(path: *const FfiDevicePath) -> Option<&'a DevicePath> {
+    if path.is_null() {
+        None
+    } else {
+        Some(DevicePath::from_ffi_ptr(path))
+    }
+}
+
+unsafe extern "efiapi" fn binding_supported(
+    this: *const DriverBinding,
+    controller: Handle,
+    remaining_device_path: *const FfiDevicePath,
+) -> Status {
+    let binding = &mut *(this as *mut ProvidedBinding);
+    status((binding.supported)(
+        controller,
+        remaining_path(remaining_device_path),
+    ))
+}
+
+unsafe extern "efiapi" fn binding_start(
+    this: *const DriverBinding,
+    controller: Handle,
+    remaining_device_path: *const FfiDevicePath,
+) -> Status {
+    let binding = &mut *(this as *mut ProvidedBinding);
+    status((binding.start)(
+        controller,
+        remaining_path(remaining_device_path),
+    ))
+}
+
+unsafe extern "efiapi" fn binding_stop(
+    this: *const DriverBinding,
+    controller: Handle,
+    number_of_children: usize,
+    child_handle_buffer: *const Handle,
+) -> Status {
+    let binding = &mut *(this as *mut ProvidedBinding);
+    let children = if number_of_children == 0 || child_handle_buffer.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(child_handle_buffer, number_of_children)
+    };
+    status((binding.stop)(controller, children))
+}
+
+/// `EFI_COMPONENT_NAME2_PROTOCOL` as installed by [`DriverModel::install`],
+/// followed by the names.
+#[repr(C)]
+struct ProvidedNames {
+    protocol: ComponentName2,
+    driver_name: &'static CStr16,
+    controller_name: Option<ControllerNameCallback>,
+}
+
+unsafe extern "efiapi" fn get_driver_name(
+    this: *const ComponentName2,
+    language_ptr: *const Char8,
+    driver_name: &mut *const Char16,
+) -> Status {
+    let names = &*this.cast::<ProvidedNames>();
+    match language(names.protocol.supported_languages, language_ptr) {
+        Ok(_) => {
+            *driver_name = names.driver_name.as_ptr();
+            Status::SUCCESS
+        }
+        Err(err) => err.status(),
+    }
+}
+
+unsafe extern "efiapi" fn get_controller_name(
+    this: *const ComponentName2,
+    controller: Handle,
+    child: Option<Handle>,
+    language_ptr: *const Char8,
+    controller_name: &mut *const Char16,
+) -> Status {
+    let names = &mut *(this as *mut ProvidedNames);
+    if let Err(err) = language(names.protocol.supported_languages, language_ptr) {
+        return err.status();
+    }
+    match names
+        .controller_name
+        .as_mut()
+        .and_then(|name| name(controller, child))
+    {
+        Some(name) => {
+            *controller_name = name.as_ptr();
+            Status::SUCCESS
+        }
+        None => Status::UNSUPPORTED,
+    }
+}
+
+/// `EFI_DRIVER_DIAGNOSTICS2_PROTOCOL` as installed by
+/// [`DriverModel::install`], followed by its callback.
+#[repr(C)]
+struct ProvidedDiagnostics {
+    protocol: DriverDiagnostics2,
+    /// Boot services, to allocate the messages. They outlive the protocol,
+    /// which is uninstalled by [`InstalledDriver`].
+    boot_services: *const BootServices,
+    run: DiagnosticsCallback,
+    /// The error type of the last report, which the caller gets a pointer to.
+    error_type: Guid,
+}
+
+/// Copy `message` to a buffer allocated from the pool, which the caller
+/// frees.
+unsafe fn pool_string(bt: &BootServices, message: &CStr16) -> Result<(*mut Char16, usize)> {
+    let chars = message.as_slice_with_nul();
+    let size = mem::size_of_val(chars);
+    let buffer = bt
+        .allocate_pool(MemoryType::BOOT_SERVICES_DATA, size)?
+        .cast::<Char16>();
+    ptr::copy_nonoverlapping(chars.as_ptr(), buffer, chars.len());
+    Ok((buffer, size))
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "efiapi" fn run_diagnostics(
+    this: *const DriverDiagnostics2,
+    controller: Handle,
+    child: Option<Handle>,
+    diagnostic_type: DiagnosticType,
+    language_ptr: *const Char8,
+    error_type: *mut *const Guid,
+    buffer_size: *mut usize,
+    buffer: *mut *mut Char16,
+) -> Status {
+    let diagnostics = &mut *(this as *mut ProvidedDiagnostics);
+    if error_type.is_null() || buffer_size.is_null() || buffer.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    if let Err(err) = language(diagnostics.protocol.supported_languages, language_ptr) {
+        return err.status();
+    }
+    let report = match (diagnostics.run)(controller, child, diagnostic_type) {
+        Ok(report) => report,
+        Err(err) => return err.status(),
+    };
+
+    *error_type = ptr::null();
+    if let Some(guid) = report.error_type {
+        diagnostics.error_type = guid;
+        *error_type = &diagnostics.error_type;
+    }
+    *buffer_size = 0;
+    *buffer = ptr::null_mut();
+    if let Some(message) = &report.message {
+        match pool_string(&*diagnostics.boot_services, message) {
+            Ok((message, size)) => {
+                *buffer = message;
+                *buffer_size = size;
+            }
+            Err(err) => return err.status(),
+        }
+    }
+
+    if report.passed {
+        Status::SUCCESS
+    } else {
+        Status::DEVICE_ERROR
+    }
+}
+
+/// `EFI_DRIVER_CONFIGURATION2_PROTOCOL` as installed by
+/// [`DriverModel::install`], followed by its provider.
+#[repr(C)]
+struct ProvidedConfiguration {
+    protocol: DriverConfiguration2,
+    provider: Box<dyn ConfigurationProvider>,
+}
+
+/// Write the action returned by the provider to `action_required`.
+unsafe fn action_result(
+    result: Result<ConfigurationAction>,
+    action_required: *mut ConfigurationAction,
+) -> Status {
+    match result {
+        Ok(action) => {
+            *action_required = action;
+            Status::SUCCESS
+        }
+        Err(err) => err.status(),
+    }
+}
+
+unsafe extern "efiapi" fn set_options(
+    this: *const DriverConfiguration2,
+    controller: Handle,
+    child: Option<Handle>,
+    language_ptr: *const Char8,
+    action_required: *mut ConfigurationAction,
+) -> Status {
+    let configuration = &mut *(this as *mut ProvidedConfiguration);
+    if action_required.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    let language = match language(configuration.protocol.supported_languages, language_ptr) {
+        Ok(language) => language,
+        Err(err) => return err.status(),
+    };
+    let result = configuration
+        .provider
+        .set_options(controller, child, language);
+    action_result(result, action_required)
+}
+
+unsafe extern "efiapi" fn options_valid(
+    this: *const DriverConfiguration2,
+    controller: Handle,
+    child: Option<Handle>,
+) -> Status {
+    let configuration = &mut *(this as *mut ProvidedConfiguration);
+    status(configuration.provider.options_valid(controller, child))
+}
+
+unsafe extern "efiapi" fn force_defaults(
+    this: *const DriverConfiguration2,
+    controller: Handle,
+    child: Option<Handle>,
+    default_type: DefaultType,
+    action_required: *mut ConfigurationAction,
+) -> Status {
+    let configuration = &mut *(this as *mut ProvidedConfiguration);
+    if action_required.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    let result = configuration
+        .provider
+        .force_defaults(controller, child, default_type);
+    action_result(result, action_required)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::cstr8;
+
+    #[test]
+    fn test_is_supported_language() {
+        assert!(is_supported_language(cstr8!("en"), cstr8!("en")));
+        assert!(is_supported_language(cstr8!("en-US;fr"), cstr8!("fr")));
+        assert!(is_supported_language(cstr8!("en-US;fr"), cstr8!("en-US")));
+        assert!(!is_supported_language(cstr8!("en-US;fr"), cstr8!("en")));
+        assert!(!is_supported_language(cstr8!("en;;fr"), cstr8!("")));
+        assert!(!is_supported_language(cstr8!(""), cstr8!("en")));
+    }
+}
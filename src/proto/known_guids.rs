@@ -0,0 +1,192 @@
+//! Names of well-known GUIDs.
+//!
+//! This module maps the GUIDs of the protocols, file information types,
+//! variable vendors and configuration tables defined in this crate to their
+//! names, which is useful when printing debug information. It is only
+//! available with the `known-guids` feature, to avoid the size of the string
+//! table in builds that don't need it.
+
+use crate::proto::boot::BootManagerPolicy;
+use crate::proto::console::gop::GraphicsOutput;
+use crate::proto::console::pointer::Pointer;
+use crate::proto::console::serial::Serial;
+use crate::proto::console::text::{Input, Output};
+use crate::proto::debug::DebugSupport;
+use crate::proto::device_path::text::{DevicePathFromText, DevicePathToText};
+use crate::proto::device_path::DevicePath;
+use crate::proto::driver::{
+    ComponentName2, DriverBinding, DriverConfiguration2, DriverDiagnostics2,
+    PlatformToDriverConfiguration,
+};
+use crate::proto::loaded_image::LoadedImage;
+use crate::proto::media::block::BlockIO;
+use crate::proto::media::disk::{DiskIo, DiskIo2};
+use crate::proto::media::file::{FileInfo, FileSystemInfo, FileSystemVolumeLabel};
+use crate::proto::media::fs::SimpleFileSystem;
+use crate::proto::media::load_file::{LoadFile, LoadFile2};
+use crate::proto::media::partition::PartitionInfo;
+use crate::proto::media::ram_disk::RamDisk;
+use crate::proto::media::sdmmc::SdMmcPassThru;
+use crate::proto::media::security::StorageSecurityCommand;
+use crate::proto::misc::{FirmwareManagement, Timestamp};
+#[cfg(feature = "exts")]
+use crate::proto::network::adapter_info::AdapterInformation;
+use crate::proto::network::ip6_config::Ip6Config;
+use crate::proto::network::pxe::BaseCode;
+use crate::proto::network::tcp6::{Tcp6, Tcp6ServiceBinding};
+use crate::proto::network::udp6::{Udp6, Udp6ServiceBinding};
+use crate::proto::network::wifi::{Supplicant, SupplicantServiceBinding, WirelessMacConnection2};
+use crate::proto::pi::mp::MpServices;
+use crate::proto::rng::Rng;
+use crate::proto::security::{DeferredImageLoad, Hash2, Hash2ServiceBinding, MemoryProtection};
+use crate::proto::shim::ShimLock;
+#[cfg(feature = "exts")]
+use crate::proto::string::RegularExpression;
+use crate::proto::string::UnicodeCollation;
+use crate::proto::tpm::Tcg2;
+use crate::proto::usb::{Usb2HostController, UsbFunctionIo};
+use crate::table::cfg;
+use crate::table::runtime::VariableVendor;
+use crate::{Guid, Identify};
+
+/// Builds a table entry named after a type implementing [`Identify`].
+macro_rules! identified {
+    ($ty:ident) => {
+        (<$ty as Identify>::GUID, stringify!($ty))
+    };
+}
+
+/// Builds a table entry named after a configuration table GUID.
+macro_rules! config_table {
+    ($name:ident) => {
+        (cfg::$name, concat!("cfg::", stringify!($name)))
+    };
+}
+
+/// The known GUIDs and their names.
+///
+/// Types are named after their Rust type, and constants after their path,
+/// for example `LoadedImage` or `cfg::ACPI2_GUID`.
+pub const KNOWN_GUIDS: &[(Guid, &str)] = &[
+    // Protocols.
+    #[cfg(feature = "exts")]
+    identified!(AdapterInformation),
+    identified!(BaseCode),
+    identified!(BlockIO),
+    identified!(BootManagerPolicy),
+    identified!(ComponentName2),
+    identified!(DebugSupport),
+    identified!(DeferredImageLoad),
+    identified!(DevicePath),
+    identified!(DevicePathFromText),
+    identified!(DevicePathToText),
+    identified!(DiskIo),
+    identified!(DiskIo2),
+    identified!(DriverBinding),
+    identified!(DriverConfiguration2),
+    identified!(DriverDiagnostics2),
+    identified!(FirmwareManagement),
+    identified!(GraphicsOutput),
+    identified!(Hash2),
+    identified!(Hash2ServiceBinding),
+    identified!(Input),
+    identified!(Ip6Config),
+    identified!(LoadFile),
+    identified!(LoadFile2),
+    identified!(LoadedImage),
+    identified!(MemoryProtection),
+    identified!(MpServices),
+    identified!(Output),
+    identified!(PartitionInfo),
+    identified!(PlatformToDriverConfiguration),
+    identified!(Pointer),
+    identified!(RamDisk),
+    #[cfg(feature = "exts")]
+    identified!(RegularExpression),
+    identified!(Rng),
+    identified!(SdMmcPassThru),
+    identified!(Serial),
+    identified!(ShimLock),
+    identified!(SimpleFileSystem),
+    identified!(StorageSecurityCommand),
+    identified!(Supplicant),
+    identified!(SupplicantServiceBinding),
+    identified!(Tcg2),
+    identified!(Tcp6),
+    identified!(Tcp6ServiceBinding),
+    identified!(Timestamp),
+    identified!(Udp6),
+    identified!(Udp6ServiceBinding),
+    identified!(UnicodeCollation),
+    identified!(Usb2HostController),
+    identified!(UsbFunctionIo),
+    identified!(WirelessMacConnection2),
+    // File information types.
+    identified!(FileInfo),
+    identified!(FileSystemInfo),
+    identified!(FileSystemVolumeLabel),
+    // Variable vendors.
+    (
+        VariableVendor::GLOBAL_VARIABLE.0,
+        "VariableVendor::GLOBAL_VARIABLE",
+    ),
+    (
+        VariableVendor::IMAGE_SECURITY_DATABASE.0,
+        "VariableVendor::IMAGE_SECURITY_DATABASE",
+    ),
+    (
+        VariableVendor::CAPSULE_REPORT.0,
+        "VariableVendor::CAPSULE_REPORT",
+    ),
+    // `VariableVendor::MEMORY_TYPE_INFORMATION` is the GUID of the
+    // `MEMORY_TYPE_INFORMATION_GUID` configuration table below.
+    (VariableVendor::SHELL.0, "VariableVendor::SHELL"),
+    // Configuration tables.
+    config_table!(ACPI_GUID),
+    config_table!(ACPI2_GUID),
+    config_table!(SMBIOS_GUID),
+    config_table!(SMBIOS3_GUID),
+    config_table!(PROPERTIES_TABLE_GUID),
+    config_table!(HAND_OFF_BLOCK_LIST_GUID),
+    config_table!(MEMORY_TYPE_INFORMATION_GUID),
+    config_table!(MEMORY_STATUS_CODE_RECORD_GUID),
+    config_table!(DXE_SERVICES_GUID),
+    config_table!(LZMA_COMPRESS_GUID),
+    config_table!(TIANO_COMPRESS_GUID),
+    config_table!(DEBUG_IMAGE_INFO_GUID),
+];
+
+/// Returns the name of `guid` if it is one of the [`KNOWN_GUIDS`].
+pub fn name_for_guid(guid: &Guid) -> Option<&'static str> {
+    KNOWN_GUIDS
+        .iter()
+        .find(|(known, _)| known == guid)
+        .map(|(_, name)| *name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_name_for_guid() {
+        assert_eq!(name_for_guid(&LoadedImage::GUID), Some("LoadedImage"));
+        assert_eq!(name_for_guid(&cfg::ACPI2_GUID), Some("cfg::ACPI2_GUID"));
+        assert_eq!(
+            name_for_guid(&VariableVendor::GLOBAL_VARIABLE.0),
+            Some("VariableVendor::GLOBAL_VARIABLE")
+        );
+        assert_eq!(name_for_guid(&Guid::default()), None);
+    }
+
+    #[test]
+    fn test_known_guids_unique() {
+        for (i, (guid, name)) in KNOWN_GUIDS.iter().enumerate() {
+            assert!(
+                KNOWN_GUIDS[i + 1..].iter().all(|(other, _)| other != guid),
+                "duplicate GUID for {}",
+                name
+            );
+        }
+    }
+}
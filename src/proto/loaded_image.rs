@@ -167,4 +167,19 @@ impl LoadedImage {
     pub fn info(&self) -> (*const c_void, u64) {
         (self.image_base, self.image_size)
     }
+
+    /// Set the function called by `BootServices::unload_image` to unload
+    /// this image. Without one, the image can't be unloaded.
+    ///
+    /// # Safety
+    ///
+    /// `unload` must stop everything the image installed, such as the
+    /// protocols of a driver, before returning [`Status::SUCCESS`], since
+    /// the memory of the image is freed afterwards.
+    pub unsafe fn set_unload(
+        &mut self,
+        unload: extern "efiapi" fn(image_handle: Handle) -> Status,
+    ) {
+        self.unload = unload;
+    }
 }
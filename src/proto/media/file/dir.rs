@@ -1,7 +1,9 @@
-use super::{File, FileHandle, FileInfo, FromUefi, RegularFile};
+use super::{File, FileAttribute, FileHandle, FileInfo, FileMode, FileType, FromUefi, RegularFile};
 use crate::data_types::Align;
-use crate::Result;
+use crate::{CStr16, Result, Status};
 use core::ffi::c_void;
+#[cfg(feature = "exts")]
+use {super::OwnedFileInfo, crate::alloc_api::vec, crate::alloc_api::vec::Vec};
 
 /// A `FileHandle` that is also a directory.
 ///
@@ -60,6 +62,90 @@ impl Directory {
     pub fn reset_entry_readout(&mut self) -> Result {
         self.0.set_position(0)
     }
+
+    /// Iterate over the entries of the directory, from the first one.
+    ///
+    /// Unlike [`read_entry`], this copies each entry into an
+    /// [`OwnedFileInfo`], so entries can be collected and kept after the
+    /// iteration. The read buffer is grown as needed for long file names.
+    /// Iteration stops after the first error.
+    ///
+    /// The read position of the directory is reset first, so that all
+    /// entries are returned, and the directory can be used again once the
+    /// iterator is dropped.
+    ///
+    /// [`read_entry`]: Self::read_entry
+    #[cfg(feature = "exts")]
+    pub fn entries(&mut self) -> DirectoryEntries<'_> {
+        let reset = self.reset_entry_readout();
+        DirectoryEntries {
+            dir: self,
+            buffer: vec![0; DirectoryEntries::INITIAL_BUFFER_SIZE],
+            error: reset.err().map(|err| err.status()),
+            done: false,
+        }
+    }
+
+    /// Create a directory named `name` in this directory, with the given
+    /// `attributes`, and open it.
+    ///
+    /// The attributes are passed to the firmware in the same call that
+    /// creates the directory, so the directory never exists without them.
+    /// The firmware creates a directory rather than a regular file because
+    /// the `DIRECTORY` attribute is set; it is added to `attributes` if
+    /// missing. If an entry with this name already exists, it is opened
+    /// instead and its attributes are left unchanged.
+    ///
+    /// # Errors
+    /// * `uefi::Status::INVALID_PARAMETER`  `attributes` contains bits that are not valid file
+    ///                                      attributes
+    /// * `uefi::Status::ACCESS_DENIED`      A regular file with this name already exists
+    ///
+    /// See [`File::open`] for the other errors.
+    pub fn create_directory(
+        &mut self,
+        name: &CStr16,
+        attributes: FileAttribute,
+    ) -> Result<Directory> {
+        let attributes = validate_attributes(attributes)? | FileAttribute::DIRECTORY;
+        match self.create(name, attributes)? {
+            FileType::Dir(dir) => Ok(dir),
+            FileType::Regular(_) => Err(Status::ACCESS_DENIED.into()),
+        }
+    }
+
+    /// Create a regular file named `name` in this directory, with the given
+    /// `attributes`, and open it for reading and writing.
+    ///
+    /// The attributes are passed to the firmware in the same call that
+    /// creates the file. If an entry with this name already exists, it is
+    /// opened instead and its attributes are left unchanged.
+    ///
+    /// Note that a file created with `READ_ONLY` can still be written
+    /// through the returned handle, as the attribute only applies to later
+    /// opens.
+    ///
+    /// # Errors
+    /// * `uefi::Status::INVALID_PARAMETER`  `attributes` contains `DIRECTORY`, or bits that are
+    ///                                      not valid file attributes
+    /// * `uefi::Status::ACCESS_DENIED`      A directory with this name already exists
+    ///
+    /// See [`File::open`] for the other errors.
+    pub fn create_file(&mut self, name: &CStr16, attributes: FileAttribute) -> Result<RegularFile> {
+        let attributes = validate_attributes(attributes)?;
+        if attributes.contains(FileAttribute::DIRECTORY) {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        match self.create(name, attributes)? {
+            FileType::Regular(file) => Ok(file),
+            FileType::Dir(_) => Err(Status::ACCESS_DENIED.into()),
+        }
+    }
+
+    fn create(&mut self, name: &CStr16, attributes: FileAttribute) -> Result<FileType> {
+        self.open(name, FileMode::CreateReadWrite, attributes)?
+            .into_type()
+    }
 }
 
 impl File for Directory {
@@ -76,3 +162,69 @@ impl File for Directory {
         Ok(true)
     }
 }
+
+/// Iterator over the entries of a [`Directory`].
+///
+/// This struct is returned by [`Directory::entries`].
+#[cfg(feature = "exts")]
+pub struct DirectoryEntries<'a> {
+    dir: &'a mut Directory,
+    buffer: Vec<u8>,
+    // Error from resetting the read position, returned by the first call
+    // to `next`.
+    error: Option<Status>,
+    done: bool,
+}
+
+#[cfg(feature = "exts")]
+impl DirectoryEntries<'_> {
+    /// Enough for the fixed-size part of a `FileInfo` and a short name.
+    const INITIAL_BUFFER_SIZE: usize = 256;
+
+    fn read_entry(&mut self) -> Result<Option<OwnedFileInfo>> {
+        loop {
+            // `Vec<u8>` is only byte-aligned, so there is extra room to
+            // align the start of the buffer.
+            let buffer = FileInfo::align_buf(&mut self.buffer).unwrap();
+            let required = match self.dir.read_entry(buffer) {
+                Ok(entry) => return Ok(entry.map(|info| OwnedFileInfo::from(&*info))),
+                Err(err) => match err.split() {
+                    (Status::BUFFER_TOO_SMALL, Some(size)) => size,
+                    (status, _) => return Err(status.into()),
+                },
+            };
+            self.buffer
+                .resize(required + <FileInfo as Align>::alignment(), 0);
+        }
+    }
+}
+
+#[cfg(feature = "exts")]
+impl Iterator for DirectoryEntries<'_> {
+    type Item = Result<OwnedFileInfo>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if let Some(status) = self.error.take() {
+            self.done = true;
+            return Some(Err(status.into()));
+        }
+
+        let entry = self.read_entry();
+        if !matches!(entry, Ok(Some(_))) {
+            self.done = true;
+        }
+        entry.transpose()
+    }
+}
+
+/// Check that `attributes` only contains bits defined by the specification.
+fn validate_attributes(attributes: FileAttribute) -> Result<FileAttribute> {
+    if FileAttribute::VALID_ATTR.contains(attributes) {
+        Ok(attributes)
+    } else {
+        Err(Status::INVALID_PARAMETER.into())
+    }
+}
@@ -1,6 +1,8 @@
 use super::FileAttribute;
 use crate::data_types::Align;
 use crate::table::runtime::Time;
+#[cfg(feature = "exts")]
+use crate::CString16;
 use crate::{unsafe_guid, CStr16, Char16, Identify};
 use core::ffi::c_void;
 use core::{mem, ptr};
@@ -224,6 +226,43 @@ impl FileInfo {
     }
 }
 
+/// An owned copy of a [`FileInfo`], as returned by [`Directory::entries`].
+///
+/// [`Directory::entries`]: super::Directory::entries
+#[cfg(feature = "exts")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OwnedFileInfo {
+    /// File size (number of bytes stored in the file)
+    pub file_size: u64,
+    /// Physical space consumed by the file on the file system volume
+    pub physical_size: u64,
+    /// Time when the file was created
+    pub create_time: Time,
+    /// Time when the file was last accessed
+    pub last_access_time: Time,
+    /// Time when the file's contents were last modified
+    pub modification_time: Time,
+    /// Attribute bits for the file
+    pub attribute: FileAttribute,
+    /// Name of the file
+    pub file_name: CString16,
+}
+
+#[cfg(feature = "exts")]
+impl From<&FileInfo> for OwnedFileInfo {
+    fn from(info: &FileInfo) -> Self {
+        Self {
+            file_size: info.file_size(),
+            physical_size: info.physical_size(),
+            create_time: *info.create_time(),
+            last_access_time: *info.last_access_time(),
+            modification_time: *info.modification_time(),
+            attribute: info.attribute(),
+            file_name: CString16::from(info.file_name()),
+        }
+    }
+}
+
 impl Align for FileInfo {
     fn alignment() -> usize {
         8
@@ -8,7 +8,11 @@
 
 mod dir;
 mod info;
+#[cfg(feature = "exts")]
+mod reader;
 mod regular;
+#[cfg(feature = "exts")]
+mod token;
 
 use crate::{CStr16, Char16, Guid, Result, Status};
 use bitflags::bitflags;
@@ -23,8 +27,17 @@ use {
     core::slice,
 };
 
-pub use self::info::{FileInfo, FileProtocolInfo, FileSystemInfo, FileSystemVolumeLabel, FromUefi};
+pub use self::info::{
+    FileInfo, FileInfoCreationError, FileProtocolInfo, FileSystemInfo, FileSystemVolumeLabel,
+    FromUefi,
+};
+#[cfg(feature = "exts")]
+pub use self::reader::FileReader;
+#[cfg(feature = "exts")]
+pub use self::token::FileIoToken;
 pub use self::{dir::Directory, regular::RegularFile};
+#[cfg(feature = "exts")]
+pub use self::{dir::DirectoryEntries, info::OwnedFileInfo};
 
 /// Common interface to `FileHandle`, `RegularFile`, and `Directory`.
 ///
@@ -395,6 +408,10 @@ bitflags! {
         /// System file, indicates this file is an internal operating system file.
         const SYSTEM = 1 << 2;
         /// This file is a directory.
+        ///
+        /// When opening with [`FileMode::CreateReadWrite`], this bit makes
+        /// the firmware create a directory instead of a regular file. It
+        /// can't be changed on existing files.
         const DIRECTORY = 1 << 4;
         /// This file is compressed.
         const ARCHIVE = 1 << 5;
@@ -0,0 +1,84 @@
+use super::{File, FileInfo, RegularFile};
+use crate::{Error, Result};
+
+/// A reader for reading a [`RegularFile`] in chunks.
+///
+/// The reader keeps track of its position in the file, so that a large file
+/// can be read piece by piece, for example to report progress between
+/// chunks.
+///
+/// Firmware may return fewer bytes than requested even before the end of
+/// the file is reached. Such short reads are not treated as the end of the
+/// file; only a read returning zero bytes is. [`is_eof`] returns whether the
+/// end of the file was reached.
+///
+/// [`is_eof`]: FileReader::is_eof
+pub struct FileReader {
+    file: RegularFile,
+    position: u64,
+    size: u64,
+    eof: bool,
+}
+
+impl FileReader {
+    /// Create a reader for `file`, starting at the file's current position.
+    ///
+    /// The size of the file is read from its [`FileInfo`].
+    pub fn new(mut file: RegularFile) -> Result<Self> {
+        let size = file.get_boxed_info::<FileInfo>()?.file_size();
+        let position = file.get_position()?;
+        Ok(Self {
+            file,
+            position,
+            size,
+            eof: false,
+        })
+    }
+
+    /// Read the next chunk of the file into `buffer`, returning the number
+    /// of bytes read.
+    ///
+    /// This may read fewer bytes than fit into `buffer`. A return value of
+    /// zero for a non-empty `buffer` means the end of the file was reached.
+    ///
+    /// # Errors
+    ///
+    /// See [`RegularFile::read`].
+    pub fn read_chunk(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+
+        let read = self
+            .file
+            .read(buffer)
+            .map_err(|err| Error::from(err.status()))?;
+        if read == 0 {
+            self.eof = true;
+        }
+        self.position += read as u64;
+        Ok(read)
+    }
+
+    /// The current position in the file.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// The number of bytes between the current position and the end of the
+    /// file, according to the size of the file when the reader was created.
+    pub fn remaining(&self) -> u64 {
+        self.size.saturating_sub(self.position)
+    }
+
+    /// Returns `true` once a read has returned zero bytes.
+    pub fn is_eof(&self) -> bool {
+        self.eof
+    }
+
+    /// Get back the file being read. Its position is the position of the
+    /// reader.
+    pub fn into_inner(self) -> RegularFile {
+        self.file
+    }
+}
@@ -1,5 +1,10 @@
 use super::{File, FileHandle, FileInternal};
 use crate::{Result, Status};
+#[cfg(feature = "exts")]
+use {
+    super::token::{FileIoToken, Transfer},
+    crate::table::boot::BootServices,
+};
 
 /// A `FileHandle` that is also a regular (data) file.
 ///
@@ -99,6 +104,87 @@ impl RegularFile {
     }
 }
 
+#[cfg(feature = "exts")]
+impl RegularFile {
+    /// Start reading data from the file into `buffer`, without waiting for
+    /// the read to complete.
+    ///
+    /// The returned token is used to wait for the read to complete and to
+    /// get the number of bytes that were read, like [`read`]. The file and
+    /// the buffer stay borrowed until the token is dropped.
+    ///
+    /// If the file system doesn't support asynchronous reads, which were
+    /// added in revision 2 of the File protocol, the data is read
+    /// synchronously and the token is already complete when it is returned.
+    ///
+    /// # Errors
+    ///
+    /// See [`read`]. Some errors are only returned when the read completes.
+    ///
+    /// # Safety
+    ///
+    /// The firmware keeps writing to `buffer` until the read completes,
+    /// which is only guaranteed by dropping the token or calling
+    /// [`FileIoToken::wait`]. The token must not be leaked, for example
+    /// with [`mem::forget`], before that.
+    ///
+    /// [`read`]: RegularFile::read
+    /// [`mem::forget`]: core::mem::forget
+    pub unsafe fn read_async<'a>(
+        &'a mut self,
+        bt: &'a BootServices,
+        buffer: &'a mut [u8],
+    ) -> Result<FileIoToken<'a>> {
+        FileIoToken::start(
+            bt,
+            self.imp(),
+            Transfer::Read,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+        )
+    }
+
+    /// Start writing `buffer` to the file, without waiting for the write to
+    /// complete.
+    ///
+    /// The returned token is used to wait for the write to complete, like
+    /// [`write`]. The file and the buffer stay borrowed until the token is
+    /// dropped.
+    ///
+    /// If the file system doesn't support asynchronous writes, which were
+    /// added in revision 2 of the File protocol, the data is written
+    /// synchronously and the token is already complete when it is returned.
+    ///
+    /// # Errors
+    ///
+    /// See [`write`]. Some errors are only returned when the write
+    /// completes.
+    ///
+    /// # Safety
+    ///
+    /// The firmware keeps reading `buffer` until the write completes, which
+    /// is only guaranteed by dropping the token or calling
+    /// [`FileIoToken::wait`]. The token must not be leaked, for example
+    /// with [`mem::forget`], before that.
+    ///
+    /// [`write`]: RegularFile::write
+    /// [`mem::forget`]: core::mem::forget
+    pub unsafe fn write_async<'a>(
+        &'a mut self,
+        bt: &'a BootServices,
+        buffer: &'a [u8],
+    ) -> Result<FileIoToken<'a>> {
+        // The firmware doesn't write to the buffer.
+        FileIoToken::start(
+            bt,
+            self.imp(),
+            Transfer::Write,
+            buffer.as_ptr() as *mut u8,
+            buffer.len(),
+        )
+    }
+}
+
 impl File for RegularFile {
     #[inline]
     fn handle(&mut self) -> &mut FileHandle {
@@ -0,0 +1,190 @@
+use super::FileImpl;
+use crate::alloc_api::boxed::Box;
+use crate::proto::completion::CompletionEvent;
+use crate::table::boot::BootServices;
+use crate::{Event, Result, ResultExt, Status};
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ptr;
+
+/// Revision of the File protocol that added the asynchronous functions.
+const REVISION_2: u64 = 0x0002_0000;
+
+/// The function pointer table for revision 2 of the File protocol, which
+/// extends the table of revision 1 with asynchronous functions.
+#[repr(C)]
+struct FileImpl2 {
+    base: FileImpl,
+    _open_ex: usize,
+    read_ex: unsafe extern "efiapi" fn(this: &mut FileImpl, token: *mut RawFileIoToken) -> Status,
+    write_ex: unsafe extern "efiapi" fn(this: &mut FileImpl, token: *mut RawFileIoToken) -> Status,
+}
+
+#[repr(C)]
+struct RawFileIoToken {
+    event: Event,
+    status: Status,
+    buffer_size: usize,
+    buffer: *mut c_void,
+}
+
+/// The direction of an asynchronous transfer.
+#[derive(Clone, Copy)]
+pub(super) enum Transfer {
+    Read,
+    Write,
+}
+
+/// Token of an outstanding asynchronous read or write, returned by
+/// [`RegularFile::read_async`] and [`RegularFile::write_async`].
+///
+/// The token borrows the file and the buffer of the transfer, since the
+/// firmware keeps accessing them until the transfer has completed. Dropping
+/// the token before the transfer has completed blocks until it does, as
+/// file transfers can't be cancelled. If waiting fails, the token and its
+/// event are leaked rather than freed while the firmware may still use
+/// them.
+///
+/// The token must not be leaked before the transfer has completed, for
+/// example with [`mem::forget`], since that ends the borrows while the
+/// firmware still accesses the buffer. This is why the functions that
+/// return a token are unsafe.
+///
+/// [`mem::forget`]: core::mem::forget
+/// [`RegularFile::read_async`]: super::RegularFile::read_async
+/// [`RegularFile::write_async`]: super::RegularFile::write_async
+pub struct FileIoToken<'a> {
+    bt: &'a BootServices,
+    // The firmware writes to the token when the transfer completes, so it
+    // must not move. Only freed once the transfer has completed.
+    raw: ManuallyDrop<Box<RawFileIoToken>>,
+    // Closes the event of the token once the transfer has completed.
+    completion: ManuallyDrop<CompletionEvent<'a>>,
+    _file: PhantomData<&'a mut FileImpl>,
+    _buffer: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> FileIoToken<'a> {
+    /// Start a transfer of `len` bytes at `buffer`, falling back to a
+    /// synchronous transfer if the file doesn't support asynchronous ones.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must be valid for `len` bytes for `'a`, and the token must
+    /// not be leaked before the transfer has completed.
+    pub(super) unsafe fn start(
+        bt: &'a BootServices,
+        file: &'a mut FileImpl,
+        transfer: Transfer,
+        buffer: *mut u8,
+        len: usize,
+    ) -> Result<Self> {
+        let completion = CompletionEvent::new(bt)?;
+        let mut token = FileIoToken {
+            bt,
+            raw: ManuallyDrop::new(Box::new(RawFileIoToken {
+                event: completion.event(),
+                status: Status::NOT_READY,
+                buffer_size: len,
+                buffer: buffer.cast(),
+            })),
+            completion: ManuallyDrop::new(completion),
+            _file: PhantomData,
+            _buffer: PhantomData,
+        };
+
+        let status = if file.revision >= REVISION_2 {
+            // Safety: the revision guarantees that the table has the
+            // asynchronous functions.
+            let file2 = &mut *(file as *mut FileImpl).cast::<FileImpl2>();
+            let raw: *mut RawFileIoToken = &mut **token.raw;
+            match transfer {
+                Transfer::Read => (file2.read_ex)(&mut file2.base, raw),
+                Transfer::Write => (file2.write_ex)(&mut file2.base, raw),
+            }
+        } else {
+            Status::UNSUPPORTED
+        };
+
+        match status {
+            Status::SUCCESS => Ok(token),
+            Status::UNSUPPORTED => {
+                // Do a synchronous transfer instead, and complete the
+                // token right away.
+                let mut size = len;
+                let status = match transfer {
+                    Transfer::Read => (file.read)(file, &mut size, buffer),
+                    Transfer::Write => (file.write)(file, &mut size, buffer),
+                };
+                token.raw.buffer_size = size;
+                token.raw.status = status;
+                bt.signal_event(&token.raw.event)?;
+                Ok(token)
+            }
+            // The transfer wasn't started, so it's fine to drop the token.
+            status => {
+                token.raw.status = status;
+                Err(status.into())
+            }
+        }
+    }
+
+    /// The event that is signaled when the transfer completes.
+    ///
+    /// The event can be waited on with [`BootServices::wait_for_event`],
+    /// along with other events, or polled with
+    /// [`BootServices::check_event`].
+    pub fn event(&self) -> &Event {
+        &self.raw.event
+    }
+
+    fn status(&self) -> Status {
+        // The firmware may update the status at any time.
+        unsafe { ptr::read_volatile(&self.raw.status) }
+    }
+
+    /// Returns `true` if the transfer has completed.
+    pub fn is_complete(&self) -> bool {
+        self.status() != Status::NOT_READY
+    }
+
+    /// Wait for the transfer to complete, and return the number of bytes
+    /// that were transferred.
+    ///
+    /// # Errors
+    ///
+    /// See [`RegularFile::read`] and [`RegularFile::write`].
+    ///
+    /// [`RegularFile::read`]: super::RegularFile::read
+    /// [`RegularFile::write`]: super::RegularFile::write
+    pub fn wait(self) -> Result<usize> {
+        self.wait_for_completion()?;
+        let size = unsafe { ptr::read_volatile(&self.raw.buffer_size) };
+        self.status().into_with_val(|| size)
+    }
+
+    fn wait_for_completion(&self) -> Result {
+        while !self.is_complete() {
+            // Safety: the clone is only used while the token is alive.
+            let mut events = [unsafe { self.raw.event.unsafe_clone() }];
+            self.bt.wait_for_event(&mut events).discard_errdata()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FileIoToken<'_> {
+    fn drop(&mut self) {
+        // The firmware must be done with the token and the buffer before
+        // they are freed. If waiting fails, it may still write to the token
+        // and signal its event, so both are leaked.
+        if self.wait_for_completion().is_ok() {
+            // Safety: the fields are not used after this.
+            unsafe {
+                ManuallyDrop::drop(&mut self.raw);
+                ManuallyDrop::drop(&mut self.completion);
+            }
+        }
+    }
+}
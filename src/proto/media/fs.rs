@@ -1,5 +1,8 @@
 //! File system support protocols.
 
+#[cfg(feature = "exts")]
+pub mod provider;
+
 use super::file::{Directory, FileHandle, FileImpl};
 use crate::proto::Protocol;
 use crate::{unsafe_guid, Result, Status};
@@ -0,0 +1,799 @@
+//! Serving a [`SimpleFileSystem`] implemented in Rust.
+//!
+//! The [`SimpleFileSystemProvider`] and [`FileProvider`] traits describe a
+//! file system, and [`install_file_system`] installs it on a handle, so the
+//! firmware and other images can use it like a file system on a disk. This
+//! module takes care of the FFI side of the protocols: each file opened
+//! through the protocol is a separate [`FileProvider`] object, which is
+//! dropped when the file is closed.
+//!
+//! [`StaticFileSystem`] is a read-only implementation serving a directory
+//! tree that is built into the image, and an example of how to implement
+//! the traits.
+//!
+//! # Safety
+//!
+//! Serving a protocol means that the firmware and other images call into
+//! this code at times that are not under the control of the image, which
+//! comes with requirements that the compiler can't check:
+//!
+//! * The trait methods must not panic: the panic can't unwind through the
+//!   firmware, and the file system is likely in use by code that is not
+//!   prepared for the image to stop.
+//! * The trait methods must not re-enter the file system, for example by
+//!   opening a file system protocol that leads back to the same file. The
+//!   provider is borrowed mutably during each call, so a second call would
+//!   create an aliasing reference.
+//! * The firmware may call the methods from event notifications, so data
+//!   shared with the rest of the image must be shared as if with an
+//!   interrupt handler.
+//! * Boot services must not be exited while the file system is installed
+//!   or files are open: the memory of the providers is allocated from the
+//!   pool, and the firmware is gone after that.
+//!
+//! The providers are `'static`, so files that stay open after the file
+//! system is uninstalled remain valid.
+//!
+//! Only revision 1 of the file protocol is implemented, so asynchronous
+//! reads and writes are not supported.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::prelude::*;
+//! use uefi::proto::media::fs::provider::{
+//!     install_file_system, StaticContents, StaticFileSystem, StaticNode,
+//! };
+//!
+//! # fn example(bt: &BootServices) -> uefi::Result {
+//! // The tree must be `'static`; leaking it allows building it at runtime.
+//! let files = vec![StaticNode {
+//!     name: cstr16!("hello.txt"),
+//!     contents: StaticContents::File(b"Hello, world!\n"),
+//! }];
+//! let root = vec![StaticNode {
+//!     name: cstr16!("docs"),
+//!     contents: StaticContents::Directory(files.leak()),
+//! }];
+//! let fs = StaticFileSystem::new(cstr16!("static"), root.leak());
+//!
+//! // Install the file system on a new handle. It can be opened like any
+//! // other file system until `installed` is dropped.
+//! let installed = unsafe { install_file_system(bt, None, Box::new(fs))? };
+//! let handle = installed.handle();
+//! # let _ = handle;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`SimpleFileSystem`]: super::SimpleFileSystem
+
+use super::SimpleFileSystem;
+use crate::alloc_api::boxed::Box;
+use crate::alloc_api::vec;
+use crate::alloc_api::vec::Vec;
+use crate::data_types::Align;
+use crate::proto::media::file::{
+    FileAttribute, FileInfo, FileInfoCreationError, FileMode, FileSystemInfo, FileSystemVolumeLabel,
+};
+use crate::table::boot::BootServices;
+use crate::table::runtime::Time;
+use crate::{CStr16, Char16, Error, Guid, Handle, Identify, Result, Status};
+use core::ffi::c_void;
+use core::{cmp, mem, ptr, slice};
+
+/// A file system, which opens the root directory of its volume.
+///
+/// See the [module-level documentation](self) for the requirements on
+/// implementations.
+pub trait SimpleFileSystemProvider {
+    /// Open the root directory of the volume.
+    fn open_volume(&mut self) -> Result<Box<dyn FileProvider>>;
+}
+
+/// A file or directory opened through a [`SimpleFileSystemProvider`].
+///
+/// The methods correspond to the functions of `EFI_FILE_PROTOCOL`, and
+/// report errors with the statuses listed in the UEFI specification. The
+/// methods of read-only file systems don't have to be implemented.
+///
+/// See the [module-level documentation](self) for the requirements on
+/// implementations.
+pub trait FileProvider {
+    /// Open the file `name`, relative to this file, or to the root if it
+    /// starts with a backslash.
+    fn open(
+        &mut self,
+        name: &CStr16,
+        mode: FileMode,
+        attributes: FileAttribute,
+    ) -> Result<Box<dyn FileProvider>>;
+
+    /// Read data from the file at the current position into `buffer`, and
+    /// return the number of bytes read. Reading at the end of the file
+    /// returns 0.
+    ///
+    /// Reading a directory writes the [`FileInfo`] of the next entry
+    /// instead. If the buffer is too small, the error must be
+    /// [`Status::BUFFER_TOO_SMALL`] with the required size.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Option<usize>>;
+
+    /// Write `buffer` to the file at the current position, and return the
+    /// number of bytes written.
+    fn write(&mut self, _buffer: &[u8]) -> Result<usize> {
+        Err(Status::WRITE_PROTECTED.into())
+    }
+
+    /// Get the current position in the file. Directories don't have a
+    /// position, and return [`Status::UNSUPPORTED`].
+    fn get_position(&mut self) -> Result<u64>;
+
+    /// Set the current position in the file. Directories only support
+    /// setting the position to 0, which restarts reading the entries.
+    fn set_position(&mut self, position: u64) -> Result;
+
+    /// Write the information of type `information_type` to `buffer`, and
+    /// return its size. If the buffer is too small, the error must be
+    /// [`Status::BUFFER_TOO_SMALL`] with the required size.
+    fn get_info(
+        &mut self,
+        information_type: &Guid,
+        buffer: &mut [u8],
+    ) -> Result<usize, Option<usize>>;
+
+    /// Change the information of type `information_type`.
+    fn set_info(&mut self, _information_type: &Guid, _buffer: &[u8]) -> Result {
+        Err(Status::WRITE_PROTECTED.into())
+    }
+
+    /// Delete the file, and return whether it was deleted. The file is
+    /// closed in both cases.
+    fn delete(&mut self) -> bool {
+        false
+    }
+
+    /// Write any buffered data to the volume.
+    fn flush(&mut self) -> Result {
+        Ok(())
+    }
+}
+
+/// `EFI_FILE_PROTOCOL_REVISION`.
+const FILE_PROTOCOL_REVISION: u64 = 0x0001_0000;
+/// `EFI_SIMPLE_FILE_SYSTEM_PROTOCOL_REVISION`.
+const SIMPLE_FILE_SYSTEM_REVISION: u64 = 0x0001_0000;
+
+/// `EFI_FILE_PROTOCOL`, with raw parameters since the values come from
+/// the caller and may be invalid.
+#[repr(C)]
+struct RawFile {
+    revision: u64,
+    open: unsafe extern "efiapi" fn(
+        this: *mut RawFile,
+        new_handle: *mut *mut RawFile,
+        file_name: *const Char16,
+        open_mode: u64,
+        attributes: u64,
+    ) -> Status,
+    close: unsafe extern "efiapi" fn(this: *mut RawFile) -> Status,
+    delete: unsafe extern "efiapi" fn(this: *mut RawFile) -> Status,
+    read: unsafe extern "efiapi" fn(
+        this: *mut RawFile,
+        buffer_size: *mut usize,
+        buffer: *mut u8,
+    ) -> Status,
+    write: unsafe extern "efiapi" fn(
+        this: *mut RawFile,
+        buffer_size: *mut usize,
+        buffer: *const u8,
+    ) -> Status,
+    get_position: unsafe extern "efiapi" fn(this: *mut RawFile, position: *mut u64) -> Status,
+    set_position: unsafe extern "efiapi" fn(this: *mut RawFile, position: u64) -> Status,
+    get_info: unsafe extern "efiapi" fn(
+        this: *mut RawFile,
+        information_type: *const Guid,
+        buffer_size: *mut usize,
+        buffer: *mut u8,
+    ) -> Status,
+    set_info: unsafe extern "efiapi" fn(
+        this: *mut RawFile,
+        information_type: *const Guid,
+        buffer_size: usize,
+        buffer: *const c_void,
+    ) -> Status,
+    flush: unsafe extern "efiapi" fn(this: *mut RawFile) -> Status,
+}
+
+/// A file handed out to the firmware. The protocol comes first, so that a
+/// pointer to the protocol is also a pointer to this.
+#[repr(C)]
+struct ProvidedFile {
+    raw: RawFile,
+    provider: Box<dyn FileProvider>,
+}
+
+impl ProvidedFile {
+    fn into_raw(provider: Box<dyn FileProvider>) -> *mut RawFile {
+        let file = Box::new(ProvidedFile {
+            raw: RawFile {
+                revision: FILE_PROTOCOL_REVISION,
+                open: file_open,
+                close: file_close,
+                delete: file_delete,
+                read: file_read,
+                write: file_write,
+                get_position: file_get_position,
+                set_position: file_set_position,
+                get_info: file_get_info,
+                set_info: file_set_info,
+                flush: file_flush,
+            },
+            provider,
+        });
+        Box::into_raw(file).cast()
+    }
+
+    /// Get the provider of a file created by `into_raw`.
+    unsafe fn provider<'a>(this: *mut RawFile) -> &'a mut dyn FileProvider {
+        (*this.cast::<ProvidedFile>()).provider.as_mut()
+    }
+}
+
+/// Create a mutable slice from a buffer passed by the caller, which may be
+/// null if it is empty.
+unsafe fn buffer_mut<'a>(buffer: *mut u8, size: usize) -> Option<&'a mut [u8]> {
+    if size == 0 {
+        Some(&mut [])
+    } else if buffer.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts_mut(buffer, size))
+    }
+}
+
+/// Create a shared slice from a buffer passed by the caller, which may be
+/// null if it is empty.
+unsafe fn buffer_ref<'a>(buffer: *const u8, size: usize) -> Option<&'a [u8]> {
+    if size == 0 {
+        Some(&[])
+    } else if buffer.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(buffer, size))
+    }
+}
+
+/// Write the result of a method returning a size, or the required size if
+/// the buffer is too small, to `buffer_size`.
+unsafe fn sized_result(result: Result<usize, Option<usize>>, buffer_size: *mut usize) -> Status {
+    match result {
+        Ok(size) => {
+            *buffer_size = size;
+            Status::SUCCESS
+        }
+        Err(err) => {
+            let (status, size) = err.split();
+            if let Some(size) = size {
+                *buffer_size = size;
+            }
+            status
+        }
+    }
+}
+
+unsafe extern "efiapi" fn file_open(
+    this: *mut RawFile,
+    new_handle: *mut *mut RawFile,
+    file_name: *const Char16,
+    open_mode: u64,
+    attributes: u64,
+) -> Status {
+    const READ: u64 = FileMode::Read as u64;
+    const READ_WRITE: u64 = FileMode::ReadWrite as u64;
+    const CREATE_READ_WRITE: u64 = FileMode::CreateReadWrite as u64;
+
+    let mode = match open_mode {
+        READ => FileMode::Read,
+        READ_WRITE => FileMode::ReadWrite,
+        CREATE_READ_WRITE => FileMode::CreateReadWrite,
+        _ => return Status::INVALID_PARAMETER,
+    };
+    let attributes = match FileAttribute::from_bits(attributes) {
+        Some(attributes) => attributes,
+        None => return Status::INVALID_PARAMETER,
+    };
+    if new_handle.is_null() || file_name.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+
+    let name = CStr16::from_ptr(file_name);
+    match ProvidedFile::provider(this).open(name, mode, attributes) {
+        Ok(file) => {
+            *new_handle = ProvidedFile::into_raw(file);
+            Status::SUCCESS
+        }
+        Err(err) => err.status(),
+    }
+}
+
+unsafe extern "efiapi" fn file_close(this: *mut RawFile) -> Status {
+    drop(Box::from_raw(this.cast::<ProvidedFile>()));
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn file_delete(this: *mut RawFile) -> Status {
+    let mut file = Box::from_raw(this.cast::<ProvidedFile>());
+    if file.provider.delete() {
+        Status::SUCCESS
+    } else {
+        Status::WARN_DELETE_FAILURE
+    }
+}
+
+unsafe extern "efiapi" fn file_read(
+    this: *mut RawFile,
+    buffer_size: *mut usize,
+    buffer: *mut u8,
+) -> Status {
+    if buffer_size.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    match buffer_mut(buffer, *buffer_size) {
+        Some(buffer) => sized_result(ProvidedFile::provider(this).read(buffer), buffer_size),
+        None => Status::INVALID_PARAMETER,
+    }
+}
+
+unsafe extern "efiapi" fn file_write(
+    this: *mut RawFile,
+    buffer_size: *mut usize,
+    buffer: *const u8,
+) -> Status {
+    if buffer_size.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    match buffer_ref(buffer, *buffer_size) {
+        Some(buffer) => {
+            let result = ProvidedFile::provider(this).write(buffer);
+            sized_result(
+                result.map_err(|err| Error::new(err.status(), None)),
+                buffer_size,
+            )
+        }
+        None => Status::INVALID_PARAMETER,
+    }
+}
+
+unsafe extern "efiapi" fn file_get_position(this: *mut RawFile, position: *mut u64) -> Status {
+    if position.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    match ProvidedFile::provider(this).get_position() {
+        Ok(pos) => {
+            *position = pos;
+            Status::SUCCESS
+        }
+        Err(err) => err.status(),
+    }
+}
+
+unsafe extern "efiapi" fn file_set_position(this: *mut RawFile, position: u64) -> Status {
+    ProvidedFile::provider(this)
+        .set_position(position)
+        .map_or_else(|err| err.status(), |()| Status::SUCCESS)
+}
+
+unsafe extern "efiapi" fn file_get_info(
+    this: *mut RawFile,
+    information_type: *const Guid,
+    buffer_size: *mut usize,
+    buffer: *mut u8,
+) -> Status {
+    if information_type.is_null() || buffer_size.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    match buffer_mut(buffer, *buffer_size) {
+        Some(buffer) => {
+            let result = ProvidedFile::provider(this).get_info(&*information_type, buffer);
+            sized_result(result, buffer_size)
+        }
+        None => Status::INVALID_PARAMETER,
+    }
+}
+
+unsafe extern "efiapi" fn file_set_info(
+    this: *mut RawFile,
+    information_type: *const Guid,
+    buffer_size: usize,
+    buffer: *const c_void,
+) -> Status {
+    if information_type.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    match buffer_ref(buffer.cast(), buffer_size) {
+        Some(buffer) => ProvidedFile::provider(this)
+            .set_info(&*information_type, buffer)
+            .map_or_else(|err| err.status(), |()| Status::SUCCESS),
+        None => Status::INVALID_PARAMETER,
+    }
+}
+
+unsafe extern "efiapi" fn file_flush(this: *mut RawFile) -> Status {
+    ProvidedFile::provider(this)
+        .flush()
+        .map_or_else(|err| err.status(), |()| Status::SUCCESS)
+}
+
+/// `EFI_SIMPLE_FILE_SYSTEM_PROTOCOL` as installed by
+/// [`install_file_system`], followed by its provider.
+#[repr(C)]
+struct ProvidedFileSystem {
+    revision: u64,
+    open_volume:
+        unsafe extern "efiapi" fn(this: *mut ProvidedFileSystem, root: *mut *mut RawFile) -> Status,
+    provider: Box<dyn SimpleFileSystemProvider>,
+}
+
+unsafe extern "efiapi" fn open_volume(
+    this: *mut ProvidedFileSystem,
+    root: *mut *mut RawFile,
+) -> Status {
+    if root.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    match (*this).provider.open_volume() {
+        Ok(file) => {
+            *root = ProvidedFile::into_raw(file);
+            Status::SUCCESS
+        }
+        Err(err) => err.status(),
+    }
+}
+
+/// Install a [`SimpleFileSystem`] protocol served by `provider` on
+/// `handle`, or on a new handle if `handle` is `None`.
+///
+/// The protocol is uninstalled when the returned [`InstalledFileSystem`] is
+/// dropped.
+///
+/// # Errors
+///
+/// * [`Status::INVALID_PARAMETER`]: `handle` already has a file system.
+///
+/// See [`BootServices::install_protocol_interface`] for the other errors.
+///
+/// # Safety
+///
+/// The requirements listed in the [module-level documentation](self) must
+/// be upheld.
+pub unsafe fn install_file_system(
+    bt: &BootServices,
+    handle: Option<Handle>,
+    provider: Box<dyn SimpleFileSystemProvider>,
+) -> Result<InstalledFileSystem<'_>> {
+    let fs = Box::into_raw(Box::new(ProvidedFileSystem {
+        revision: SIMPLE_FILE_SYSTEM_REVISION,
+        open_volume,
+        provider,
+    }));
+    match bt.install_protocol_interface(handle, &SimpleFileSystem::GUID, fs.cast()) {
+        Ok(handle) => Ok(InstalledFileSystem {
+            boot_services: bt,
+            handle,
+            fs,
+        }),
+        Err(err) => {
+            drop(Box::from_raw(fs));
+            Err(err)
+        }
+    }
+}
+
+/// A file system installed with [`install_file_system`], which is
+/// uninstalled when this is dropped.
+pub struct InstalledFileSystem<'boot> {
+    boot_services: &'boot BootServices,
+    handle: Handle,
+    fs: *mut ProvidedFileSystem,
+}
+
+impl InstalledFileSystem<'_> {
+    /// The handle the file system is installed on.
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+
+    /// Uninstall the file system, as when this is dropped, but report
+    /// whether that succeeded.
+    ///
+    /// If the protocol couldn't be uninstalled, because it is still opened
+    /// by a driver that refuses to stop using it, the memory of the
+    /// provider is leaked, since the firmware may still use it.
+    ///
+    /// # Errors
+    ///
+    /// See [`BootServices::uninstall_protocol_interface`].
+    pub fn uninstall(self) -> Result {
+        let result = unsafe { self.uninstall_impl() };
+        mem::forget(self);
+        result
+    }
+
+    unsafe fn uninstall_impl(&self) -> Result {
+        self.boot_services.uninstall_protocol_interface(
+            self.handle,
+            &SimpleFileSystem::GUID,
+            self.fs.cast(),
+        )?;
+        drop(Box::from_raw(self.fs));
+        Ok(())
+    }
+}
+
+impl Drop for InstalledFileSystem<'_> {
+    fn drop(&mut self) {
+        // There is nothing else to do if the protocol is still in use.
+        let _ = unsafe { self.uninstall_impl() };
+    }
+}
+
+/// The contents of a [`StaticNode`].
+#[derive(Clone, Copy, Debug)]
+pub enum StaticContents {
+    /// A regular file with the given data.
+    File(&'static [u8]),
+    /// A directory with the given entries.
+    Directory(&'static [StaticNode]),
+}
+
+/// An entry of a directory served by [`StaticFileSystem`].
+#[derive(Debug)]
+pub struct StaticNode {
+    /// Name of the entry.
+    pub name: &'static CStr16,
+    /// Contents of the entry.
+    pub contents: StaticContents,
+}
+
+/// A read-only file system serving a static directory tree.
+///
+/// Names are compared case-sensitively, and all entries have the
+/// `READ_ONLY` attribute and invalid timestamps. Files can only be opened
+/// with [`FileMode::Read`].
+#[derive(Debug)]
+pub struct StaticFileSystem {
+    label: &'static CStr16,
+    root: &'static [StaticNode],
+}
+
+impl StaticFileSystem {
+    /// Create a file system with the volume label `label`, whose root
+    /// directory contains `root`.
+    pub fn new(label: &'static CStr16, root: &'static [StaticNode]) -> Self {
+        Self { label, root }
+    }
+}
+
+impl SimpleFileSystemProvider for StaticFileSystem {
+    fn open_volume(&mut self) -> Result<Box<dyn FileProvider>> {
+        Ok(Box::new(StaticFile {
+            label: self.label,
+            root: self.root,
+            path: Vec::new(),
+            position: 0,
+        }))
+    }
+}
+
+/// A file opened from a [`StaticFileSystem`].
+struct StaticFile {
+    label: &'static CStr16,
+    root: &'static [StaticNode],
+    // Entries from the root to this file, empty for the root directory.
+    path: Vec<&'static StaticNode>,
+    // Offset in bytes for files, index of the next entry for directories.
+    position: u64,
+}
+
+const BACKSLASH: u16 = b'\\' as u16;
+const DOT: u16 = b'.' as u16;
+
+impl StaticFile {
+    fn contents(&self) -> StaticContents {
+        Self::contents_of(self.root, &self.path)
+    }
+
+    fn contents_of(root: &'static [StaticNode], path: &[&'static StaticNode]) -> StaticContents {
+        path.last()
+            .map_or(StaticContents::Directory(root), |node| node.contents)
+    }
+
+    /// Resolve `name` relative to this file.
+    fn lookup(&self, name: &CStr16) -> Option<Vec<&'static StaticNode>> {
+        let name = name.to_u16_slice();
+        let mut path = if name.first() == Some(&BACKSLASH) {
+            Vec::new()
+        } else {
+            let mut path = self.path.clone();
+            // Names are relative to the directory of regular files.
+            if let StaticContents::File(_) = self.contents() {
+                path.pop();
+            }
+            path
+        };
+
+        for component in name.split(|&c| c == BACKSLASH) {
+            match component {
+                [] | [DOT] => {}
+                [DOT, DOT] => {
+                    path.pop();
+                }
+                _ => {
+                    let entries = match Self::contents_of(self.root, &path) {
+                        StaticContents::Directory(entries) => entries,
+                        StaticContents::File(_) => return None,
+                    };
+                    let node = entries
+                        .iter()
+                        .find(|node| node.name.to_u16_slice() == component)?;
+                    path.push(node);
+                }
+            }
+        }
+        Some(path)
+    }
+
+    fn file_info<'buf>(
+        storage: &'buf mut [u8],
+        name: &CStr16,
+        contents: StaticContents,
+    ) -> core::result::Result<&'buf mut FileInfo, FileInfoCreationError> {
+        let (size, attribute) = match contents {
+            StaticContents::File(data) => (data.len() as u64, FileAttribute::READ_ONLY),
+            StaticContents::Directory(_) => {
+                (0, FileAttribute::READ_ONLY | FileAttribute::DIRECTORY)
+            }
+        };
+        let time = Time::invalid();
+        FileInfo::new(storage, size, size, time, time, time, attribute, name)
+    }
+
+    fn volume_size(entries: &[StaticNode]) -> u64 {
+        entries
+            .iter()
+            .map(|node| match node.contents {
+                StaticContents::File(data) => data.len() as u64,
+                StaticContents::Directory(entries) => Self::volume_size(entries),
+            })
+            .sum()
+    }
+}
+
+/// Write the info created by `create` to the start of `buffer`, which
+/// doesn't have to be aligned, and return its size.
+fn write_info<Info, F>(buffer: &mut [u8], create: F) -> Result<usize, Option<usize>>
+where
+    Info: Align + ?Sized,
+    F: for<'buf> Fn(&'buf mut [u8]) -> core::result::Result<&'buf mut Info, FileInfoCreationError>,
+{
+    let size = match create(&mut []) {
+        Ok(info) => mem::size_of_val(info),
+        Err(FileInfoCreationError::InsufficientStorage(size)) => size,
+    };
+    if buffer.len() < size {
+        return Err(Error::new(Status::BUFFER_TOO_SMALL, Some(size)));
+    }
+
+    // Create the info in a temporary buffer, which has room for aligning
+    // the info.
+    let mut storage = vec![0; size + Info::alignment()];
+    let info = create(&mut storage).expect("the storage is large enough");
+    unsafe {
+        ptr::copy_nonoverlapping(
+            (info as *const Info).cast::<u8>(),
+            buffer.as_mut_ptr(),
+            size,
+        );
+    }
+    Ok(size)
+}
+
+impl FileProvider for StaticFile {
+    fn open(
+        &mut self,
+        name: &CStr16,
+        mode: FileMode,
+        _attributes: FileAttribute,
+    ) -> Result<Box<dyn FileProvider>> {
+        let path = self.lookup(name).ok_or(Status::NOT_FOUND)?;
+        if mode != FileMode::Read {
+            return Err(Status::WRITE_PROTECTED.into());
+        }
+        Ok(Box::new(StaticFile {
+            label: self.label,
+            root: self.root,
+            path,
+            position: 0,
+        }))
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Option<usize>> {
+        match self.contents() {
+            StaticContents::File(data) => {
+                let start = usize::try_from(self.position)
+                    .ok()
+                    .filter(|&start| start <= data.len())
+                    .ok_or_else(|| Error::new(Status::DEVICE_ERROR, None))?;
+                let size = cmp::min(buffer.len(), data.len() - start);
+                buffer[..size].copy_from_slice(&data[start..start + size]);
+                self.position += size as u64;
+                Ok(size)
+            }
+            StaticContents::Directory(entries) => {
+                let entry = match entries.get(self.position as usize) {
+                    Some(entry) => entry,
+                    None => return Ok(0),
+                };
+                let size = write_info(buffer, |storage| {
+                    Self::file_info(storage, entry.name, entry.contents)
+                })?;
+                self.position += 1;
+                Ok(size)
+            }
+        }
+    }
+
+    fn get_position(&mut self) -> Result<u64> {
+        match self.contents() {
+            StaticContents::File(_) => Ok(self.position),
+            StaticContents::Directory(_) => Err(Status::UNSUPPORTED.into()),
+        }
+    }
+
+    fn set_position(&mut self, position: u64) -> Result {
+        match self.contents() {
+            StaticContents::File(data) => {
+                // The maximum position means the end of the file.
+                self.position = if position == u64::MAX {
+                    data.len() as u64
+                } else {
+                    position
+                };
+                Ok(())
+            }
+            StaticContents::Directory(_) if position == 0 => {
+                self.position = 0;
+                Ok(())
+            }
+            StaticContents::Directory(_) => Err(Status::UNSUPPORTED.into()),
+        }
+    }
+
+    fn get_info(
+        &mut self,
+        information_type: &Guid,
+        buffer: &mut [u8],
+    ) -> Result<usize, Option<usize>> {
+        if *information_type == FileInfo::GUID {
+            // The root directory has an empty name.
+            let empty = [0];
+            let name = self.path.last().map_or_else(
+                || unsafe { CStr16::from_u16_with_nul_unchecked(&empty) },
+                |node| node.name,
+            );
+            let contents = self.contents();
+            write_info(buffer, |storage| Self::file_info(storage, name, contents))
+        } else if *information_type == FileSystemInfo::GUID {
+            let volume_size = Self::volume_size(self.root);
+            write_info(buffer, |storage| {
+                FileSystemInfo::new(storage, true, volume_size, 0, 512, self.label)
+            })
+        } else if *information_type == FileSystemVolumeLabel::GUID {
+            write_info(buffer, |storage| {
+                FileSystemVolumeLabel::new(storage, self.label)
+            })
+        } else {
+            Err(Error::new(Status::UNSUPPORTED, None))
+        }
+    }
+}
@@ -0,0 +1,414 @@
+//! Parsing of the GUID Partition Table.
+//!
+//! [`read_gpt`] reads the GPT of a whole disk through the [`BlockIO`]
+//! protocol. The partition table header and the partition entry array are
+//! both checked against their CRC32 checksums, which are computed by the
+//! firmware. If the primary table is missing or corrupt, the backup table
+//! at the end of the disk is used instead.
+//!
+//! Firmware usually already provides a [`PartitionInfo`] protocol on each
+//! partition it finds, so this is mostly useful for reading disks that
+//! the firmware didn't parse, or for inspecting the table itself.
+//!
+//! [`PartitionInfo`]: super::partition::PartitionInfo
+
+use super::block::{BlockIO, Lba};
+use super::partition::{GptPartitionEntry, GptPartitionType};
+use crate::alloc_api::{vec, vec::Vec};
+use crate::table::boot::BootServices;
+use crate::table::Crc32Validated;
+use crate::{Guid, Result, Status};
+use core::{mem, ptr};
+
+/// The signature of a GPT header, `"EFI PART"` in ASCII.
+pub const GPT_SIGNATURE: u64 = 0x5452_4150_2049_4645;
+
+/// The size of the fields of [`GptHeader`], which is the minimum size of
+/// the header on disk.
+const HEADER_MIN_SIZE: usize = 92;
+
+/// Offset of [`GptHeader::header_crc32`] within the header.
+const HEADER_CRC_OFFSET: usize = 16;
+
+/// The minimum size of a partition entry on disk.
+const ENTRY_MIN_SIZE: usize = 128;
+
+/// GPT header, as stored at the start of the primary and backup tables.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug)]
+pub struct GptHeader {
+    /// Must be [`GPT_SIGNATURE`].
+    pub signature: u64,
+
+    /// Revision of the header format.
+    pub revision: u32,
+
+    /// Size of the header in bytes.
+    pub header_size: u32,
+
+    /// CRC32 of the header, computed with this field set to zero.
+    pub header_crc32: u32,
+
+    /// Reserved, must be zero.
+    pub reserved: u32,
+
+    /// LBA containing this header.
+    pub my_lba: u64,
+
+    /// LBA containing the other copy of the header.
+    pub alternate_lba: u64,
+
+    /// First LBA that may be used by a partition.
+    pub first_usable_lba: u64,
+
+    /// Last LBA that may be used by a partition.
+    pub last_usable_lba: u64,
+
+    /// GUID identifying the disk.
+    pub disk_guid: Guid,
+
+    /// Starting LBA of the partition entry array.
+    pub partition_entry_lba: u64,
+
+    /// Number of entries in the partition entry array.
+    pub number_of_partition_entries: u32,
+
+    /// Size in bytes of each entry in the partition entry array.
+    pub size_of_partition_entry: u32,
+
+    /// CRC32 of the partition entry array.
+    pub partition_entry_array_crc32: u32,
+}
+
+/// The CRC covers `header_size` bytes, which may be more than the fields of
+/// the header, up to the size of the block holding it. Such a header must be
+/// validated in place, like the one returned by [`Gpt::header`], which is
+/// followed by the rest of the header read from disk.
+impl Crc32Validated for GptHeader {
+    const CRC_OFFSET: usize = HEADER_CRC_OFFSET;
+
+    fn crc_size(&self) -> usize {
+        self.header_size as usize
+    }
+}
+
+/// A validated GUID Partition Table, as returned by [`read_gpt`].
+#[derive(Debug)]
+pub struct Gpt {
+    // The `header_size` bytes of the header, as read from disk.
+    header: Vec<u8>,
+    entries: Vec<GptPartitionEntry>,
+    is_backup: bool,
+}
+
+impl Gpt {
+    /// Get the header of the table.
+    ///
+    /// The header is followed by the rest of the `header_size` bytes read
+    /// from disk, so [`Crc32Validated::validate_crc`] can be called on it.
+    pub fn header(&self) -> &GptHeader {
+        // Safety: `read_table` checked that the header is at least as large
+        // as `GptHeader`, whose alignment is 1.
+        unsafe { &*self.header.as_ptr().cast::<GptHeader>() }
+    }
+
+    /// Get all entries of the partition entry array, including unused
+    /// entries.
+    pub fn entries(&self) -> &[GptPartitionEntry] {
+        &self.entries
+    }
+
+    /// Iterate over the entries that describe a partition, skipping the
+    /// unused entries.
+    pub fn used_entries(&self) -> impl Iterator<Item = &GptPartitionEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| { entry.partition_type_guid } != GptPartitionType::UNUSED_ENTRY)
+    }
+
+    /// Whether the table was read from the backup GPT at the end of the
+    /// disk, because the primary GPT was missing or corrupt.
+    pub fn is_backup(&self) -> bool {
+        self.is_backup
+    }
+}
+
+/// Read and validate the GUID Partition Table of a disk.
+///
+/// `block_io` must be the Block I/O protocol of a whole disk rather than
+/// of a partition. The primary table is read from LBA 1; if it is missing
+/// or corrupt, the backup table in the last LBA of the disk is read
+/// instead, and [`Gpt::is_backup`] returns `true`.
+///
+/// # Errors
+///
+/// If neither table is valid, the error of the primary table is returned:
+///
+/// * [`Status::NO_MEDIA`]: there is no media in the device.
+/// * [`Status::NOT_FOUND`]: the header doesn't have the GPT signature.
+/// * [`Status::CRC_ERROR`]: the header or the partition entry array
+///   doesn't match its checksum.
+/// * [`Status::VOLUME_CORRUPTED`]: the header contains invalid values.
+///
+/// Errors from [`BlockIO::read_blocks`] are also returned.
+pub fn read_gpt(bt: &BootServices, block_io: &BlockIO) -> Result<Gpt> {
+    if !block_io.media().is_media_present() {
+        return Err(Status::NO_MEDIA.into());
+    }
+
+    match read_table(bt, block_io, 1) {
+        Ok(gpt) => Ok(gpt),
+        Err(primary_err) => {
+            let last_block = block_io.media().last_block();
+            match read_table(bt, block_io, last_block) {
+                Ok(gpt) => Ok(Gpt {
+                    is_backup: true,
+                    ..gpt
+                }),
+                Err(_) => Err(primary_err),
+            }
+        }
+    }
+}
+
+/// Read the table whose header is at `lba`.
+fn read_table(bt: &BootServices, block_io: &BlockIO, lba: Lba) -> Result<Gpt> {
+    let media = block_io.media();
+    let block_size = media.block_size() as usize;
+    let crc32 = |data: &[u8]| bt.calculate_crc32(data);
+
+    let mut storage = Vec::new();
+    let buffer = aligned_buffer(&mut storage, block_size, media.io_align());
+    block_io.read_blocks(media.media_id(), lba, buffer)?;
+    let header = parse_header(buffer, lba, crc32)?;
+    let header_bytes = buffer[..header.header_size as usize].to_vec();
+
+    // Make sure the entry array is within the disk before allocating a
+    // buffer for it.
+    let array_size = entry_array_size(&header).ok_or(Status::VOLUME_CORRUPTED)?;
+    let array_blocks = (array_size + block_size - 1) / block_size;
+    let array_end = header
+        .partition_entry_lba
+        .checked_add(array_blocks as u64)
+        .ok_or(Status::VOLUME_CORRUPTED)?;
+    if array_end > media.last_block().saturating_add(1) {
+        return Err(Status::VOLUME_CORRUPTED.into());
+    }
+    if array_size == 0 {
+        return Ok(Gpt {
+            header: header_bytes,
+            entries: Vec::new(),
+            is_backup: false,
+        });
+    }
+
+    let mut storage = Vec::new();
+    let buffer = aligned_buffer(&mut storage, array_blocks * block_size, media.io_align());
+    block_io.read_blocks(media.media_id(), header.partition_entry_lba, buffer)?;
+    let entries = parse_entries(&header, &buffer[..array_size], crc32)?;
+
+    Ok(Gpt {
+        header: header_bytes,
+        entries,
+        is_backup: false,
+    })
+}
+
+/// Allocate a zeroed buffer of `len` bytes within `storage`, aligned as
+/// required by [`BlockIO::read_blocks`].
+fn aligned_buffer(storage: &mut Vec<u8>, len: usize, io_align: u32) -> &mut [u8] {
+    // An alignment of zero or one means that any alignment is fine.
+    let align = io_align.max(1) as usize;
+    *storage = vec![0; len + align - 1];
+    let offset = storage.as_ptr().align_offset(align);
+    &mut storage[offset..offset + len]
+}
+
+/// Total size in bytes of the partition entry array described by `header`.
+fn entry_array_size(header: &GptHeader) -> Option<usize> {
+    (header.number_of_partition_entries as usize)
+        .checked_mul(header.size_of_partition_entry as usize)
+}
+
+/// Parse and validate the header at the start of `block`, which was read
+/// from `lba`. The header's checksum field in `block` is zeroed while
+/// validating it, and restored afterwards.
+fn parse_header(
+    block: &mut [u8],
+    lba: Lba,
+    crc32: impl Fn(&[u8]) -> Result<u32>,
+) -> Result<GptHeader> {
+    if block.len() < HEADER_MIN_SIZE {
+        return Err(Status::VOLUME_CORRUPTED.into());
+    }
+
+    let header = unsafe { ptr::read_unaligned(block.as_ptr().cast::<GptHeader>()) };
+    if header.signature != GPT_SIGNATURE {
+        return Err(Status::NOT_FOUND.into());
+    }
+
+    let header_size = header.header_size as usize;
+    if !(HEADER_MIN_SIZE..=block.len()).contains(&header_size) {
+        return Err(Status::VOLUME_CORRUPTED.into());
+    }
+
+    // The checksum covers the header with the checksum field zeroed.
+    let crc_range = GptHeader::CRC_OFFSET..GptHeader::CRC_OFFSET + mem::size_of::<u32>();
+    block[crc_range.clone()].fill(0);
+    let crc = crc32(&block[..header_size]);
+    block[crc_range].copy_from_slice(&{ header.header_crc32 }.to_le_bytes());
+    if crc? != header.header_crc32 {
+        return Err(Status::CRC_ERROR.into());
+    }
+
+    let entry_size = header.size_of_partition_entry as usize;
+    if header.my_lba != lba || entry_size < ENTRY_MIN_SIZE || entry_size % 8 != 0 {
+        return Err(Status::VOLUME_CORRUPTED.into());
+    }
+
+    Ok(header)
+}
+
+/// Validate the partition entry array `array` against its checksum in
+/// `header`, then parse its entries.
+fn parse_entries(
+    header: &GptHeader,
+    array: &[u8],
+    crc32: impl Fn(&[u8]) -> Result<u32>,
+) -> Result<Vec<GptPartitionEntry>> {
+    if crc32(array)? != header.partition_entry_array_crc32 {
+        return Err(Status::CRC_ERROR.into());
+    }
+
+    // Entries may be larger than `GptPartitionEntry`, in which case the
+    // extra bytes are ignored.
+    let entries = array
+        .chunks_exact(header.size_of_partition_entry as usize)
+        .map(|entry| unsafe { ptr::read_unaligned(entry.as_ptr().cast::<GptPartitionEntry>()) })
+        .collect();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Software CRC32, computed the same way as the firmware service.
+    fn crc32(data: &[u8]) -> Result<u32> {
+        let mut crc = !0u32;
+        for byte in data {
+            crc ^= u32::from(*byte);
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 {
+                    (crc >> 1) ^ 0xedb8_8320
+                } else {
+                    crc >> 1
+                };
+            }
+        }
+        Ok(!crc)
+    }
+
+    /// Build a 512-byte header block at `lba` describing `array`.
+    fn header_block(lba: u64, array: &[u8], num_entries: u32) -> Vec<u8> {
+        let mut block = vec![0; 512];
+        block[0..8].copy_from_slice(&GPT_SIGNATURE.to_le_bytes());
+        block[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+        block[12..16].copy_from_slice(&(HEADER_MIN_SIZE as u32).to_le_bytes());
+        block[24..32].copy_from_slice(&lba.to_le_bytes());
+        block[72..80].copy_from_slice(&2u64.to_le_bytes());
+        block[80..84].copy_from_slice(&num_entries.to_le_bytes());
+        block[84..88].copy_from_slice(&(ENTRY_MIN_SIZE as u32).to_le_bytes());
+        block[88..92].copy_from_slice(&crc32(array).unwrap().to_le_bytes());
+        let crc = crc32(&block[..HEADER_MIN_SIZE]).unwrap();
+        block[16..20].copy_from_slice(&crc.to_le_bytes());
+        block
+    }
+
+    /// Build an entry array with one used entry followed by an unused one.
+    fn entry_array() -> Vec<u8> {
+        let mut array = vec![0; 2 * ENTRY_MIN_SIZE];
+        // Type GUID of an EFI system partition.
+        array[0..16].copy_from_slice(&[
+            0x28, 0x73, 0x2a, 0xc1, 0x1f, 0xf8, 0xd2, 0x11, 0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e,
+            0xc9, 0x3b,
+        ]);
+        array[32..40].copy_from_slice(&34u64.to_le_bytes());
+        array[40..48].copy_from_slice(&2081u64.to_le_bytes());
+        array[56..58].copy_from_slice(&u16::from(b'E').to_le_bytes());
+        array
+    }
+
+    #[test]
+    fn test_parse_gpt() {
+        let array = entry_array();
+        let mut block = header_block(1, &array, 2);
+
+        let header = parse_header(&mut block, 1, crc32).unwrap();
+        assert_eq!({ header.my_lba }, 1);
+        assert_eq!({ header.number_of_partition_entries }, 2);
+
+        let entries = parse_entries(&header, &array, crc32).unwrap();
+        let gpt = Gpt {
+            header: block[..HEADER_MIN_SIZE].to_vec(),
+            entries,
+            is_backup: false,
+        };
+        assert_eq!(gpt.entries().len(), 2);
+        let used: Vec<_> = gpt.used_entries().collect();
+        assert_eq!(used.len(), 1);
+        assert_eq!(
+            { used[0].partition_type_guid },
+            GptPartitionType::EFI_SYSTEM_PARTITION
+        );
+        assert_eq!(used[0].num_blocks(), Some(2048));
+        assert_eq!(u16::from({ used[0].partition_name }[0]), u16::from(b'E'));
+    }
+
+    #[test]
+    fn test_parse_larger_header() {
+        // The CRC of a header larger than its fields covers the extra bytes.
+        let array = entry_array();
+        let mut block = header_block(1, &array, 2);
+        block[12..16].copy_from_slice(&100u32.to_le_bytes());
+        block[16..20].fill(0);
+        let crc = crc32(&block[..100]).unwrap();
+        block[16..20].copy_from_slice(&crc.to_le_bytes());
+
+        let header = parse_header(&mut block, 1, crc32).unwrap();
+        assert_eq!(header.crc_size(), 100);
+        // The checksum field is restored after validation.
+        assert_eq!(block[16..20], crc.to_le_bytes());
+    }
+
+    #[test]
+    fn test_parse_gpt_errors() {
+        let array = entry_array();
+        let block = header_block(1, &array, 2);
+
+        // Missing signature.
+        let mut bad = block.clone();
+        bad[0] = 0;
+        let err = parse_header(&mut bad, 1, crc32).unwrap_err();
+        assert_eq!(err.status(), Status::NOT_FOUND);
+
+        // Corrupt header.
+        let mut bad = block.clone();
+        bad[40] ^= 1;
+        let err = parse_header(&mut bad, 1, crc32).unwrap_err();
+        assert_eq!(err.status(), Status::CRC_ERROR);
+
+        // Header read from the wrong LBA.
+        let mut bad = block.clone();
+        let err = parse_header(&mut bad, 2, crc32).unwrap_err();
+        assert_eq!(err.status(), Status::VOLUME_CORRUPTED);
+
+        // Corrupt entry array.
+        let mut good = block;
+        let header = parse_header(&mut good, 1, crc32).unwrap();
+        let mut bad = array;
+        bad[100] ^= 1;
+        let err = parse_entries(&header, &bad, crc32).unwrap_err();
+        assert_eq!(err.status(), Status::CRC_ERROR);
+    }
+}
@@ -0,0 +1,184 @@
+//! Load file protocols.
+//!
+//! These protocols load a file from a device that doesn't have a file
+//! system, such as a network boot server. The boot manager uses
+//! [`LoadFile`] to boot from such devices, and [`LoadFile2`] loads other
+//! files, such as the initrd given to a Linux kernel.
+
+use crate::proto::device_path::{DevicePath, FfiDevicePath};
+use crate::proto::Protocol;
+use crate::{unsafe_guid, Error, Result, Status};
+use core::ffi::c_void;
+use core::ptr;
+
+/// Signature of `EFI_LOAD_FILE` and `EFI_LOAD_FILE2`.
+type LoadFileFn<P> = unsafe extern "efiapi" fn(
+    this: *mut P,
+    file_path: *const FfiDevicePath,
+    boot_policy: bool,
+    buffer_size: *mut usize,
+    buffer: *mut c_void,
+) -> Status;
+
+/// Call `load_file`, querying the size of the file if `buffer` is `None`.
+unsafe fn load_file_impl<P>(
+    load_file: LoadFileFn<P>,
+    this: *mut P,
+    file_path: &DevicePath,
+    boot_policy: bool,
+    buffer: Option<&mut [u8]>,
+) -> Result<usize, Option<usize>> {
+    // Only `None` is a size query. An empty buffer is passed as a non-null
+    // pointer, so it fails with `BUFFER_TOO_SMALL` like any small buffer.
+    let is_query = buffer.is_none();
+    let (buffer_ptr, mut buffer_size) = match buffer {
+        Some(buffer) => (buffer.as_mut_ptr().cast(), buffer.len()),
+        None => (ptr::null_mut(), 0),
+    };
+
+    let status = load_file(
+        this,
+        file_path.as_ffi_ptr(),
+        boot_policy,
+        &mut buffer_size,
+        buffer_ptr,
+    );
+    match status {
+        Status::SUCCESS => Ok(buffer_size),
+        // The size query is expected to fail this way.
+        Status::BUFFER_TOO_SMALL if is_query => Ok(buffer_size),
+        Status::BUFFER_TOO_SMALL | Status::WARN_FILE_SYSTEM => {
+            Err(Error::new(status, Some(buffer_size)))
+        }
+        _ => Err(Error::new(status, None)),
+    }
+}
+
+/// The load file protocol.
+///
+/// This corresponds to the `EFI_LOAD_FILE_PROTOCOL` type in the C API. It is
+/// installed on devices the boot manager can boot from without a file
+/// system, such as a network interface with PXE or HTTP boot.
+#[repr(C)]
+#[unsafe_guid("56ec3091-954c-11d2-8e3f-00a0c969723b")]
+#[derive(Protocol)]
+pub struct LoadFile {
+    load_file: LoadFileFn<LoadFile>,
+}
+
+impl LoadFile {
+    /// Load the file at `file_path` into `buffer`, and return its size. If
+    /// `buffer` is `None`, only the size of the file is returned, to
+    /// allocate a buffer for a second call.
+    ///
+    /// `file_path` is the remaining device path after the device of this
+    /// protocol, as found by [`BootServices::locate_device_path`], and may
+    /// be empty. How it is interpreted depends on `boot_policy`:
+    ///
+    /// * If `true`, the request comes from the boot manager, and the path
+    ///   describes a boot option rather than a file. The device may find
+    ///   the file to load on its own, for example a network interface asks
+    ///   a DHCP server for the boot file when the path is empty. This may
+    ///   take a long time, and can be aborted by the user.
+    /// * If `false`, the path must describe the exact file to load, and
+    ///   [`Status::NOT_FOUND`] is returned otherwise.
+    ///
+    /// The file may be downloaded again for each call, so its size can
+    /// change between the size query and the second call.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: `buffer` is too small. The error
+    ///   holds the size of the file.
+    /// * [`Status::WARN_FILE_SYSTEM`]: the file was loaded, and is an image
+    ///   of a file system, such as an ISO image downloaded by HTTP boot, to
+    ///   boot from as a RAM disk. The error holds the size of the image.
+    /// * [`Status::NOT_FOUND`]: the file was not found, or there is no boot
+    ///   file for this device.
+    /// * [`Status::UNSUPPORTED`]: the device doesn't support `file_path`.
+    /// * [`Status::INVALID_PARAMETER`]: `file_path` is not valid.
+    /// * [`Status::NO_MEDIA`]: the device has no medium, or the network
+    ///   cable is unplugged.
+    /// * [`Status::NO_RESPONSE`]: the boot server didn't respond.
+    /// * [`Status::ABORTED`]: the user aborted the download.
+    /// * [`Status::DEVICE_ERROR`]: the device reported an error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use uefi::proto::device_path::DevicePath;
+    /// use uefi::proto::media::load_file::LoadFile;
+    /// # use uefi::Result;
+    ///
+    /// fn load_boot_file(load_file: &mut LoadFile, path: &DevicePath) -> Result<Vec<u8>> {
+    ///     // Query the size first, then load the file.
+    ///     let size = load_file
+    ///         .load_file(path, true, None)
+    ///         .map_err(|err| err.status())?;
+    ///     let mut buffer = vec![0; size];
+    ///     let size = load_file
+    ///         .load_file(path, true, Some(&mut buffer))
+    ///         .map_err(|err| err.status())?;
+    ///     buffer.truncate(size);
+    ///     Ok(buffer)
+    /// }
+    /// ```
+    ///
+    /// [`BootServices::locate_device_path`]: crate::table::boot::BootServices::locate_device_path
+    pub fn load_file(
+        &mut self,
+        file_path: &DevicePath,
+        boot_policy: bool,
+        buffer: Option<&mut [u8]>,
+    ) -> Result<usize, Option<usize>> {
+        unsafe {
+            load_file_impl(
+                self.load_file,
+                self as *mut Self,
+                file_path,
+                boot_policy,
+                buffer,
+            )
+        }
+    }
+}
+
+/// The load file 2 protocol.
+///
+/// This corresponds to the `EFI_LOAD_FILE2_PROTOCOL` type in the C API. It
+/// has the same interface as [`LoadFile`], but is not used by the boot
+/// manager, so the path always describes the exact file to load. It is
+/// installed on vendor-defined device paths to provide files to images, for
+/// example the initrd of a Linux kernel.
+#[repr(C)]
+#[unsafe_guid("4006c0c1-fcb3-403e-996d-4a6c8724e06d")]
+#[derive(Protocol)]
+pub struct LoadFile2 {
+    load_file: LoadFileFn<LoadFile2>,
+}
+
+impl LoadFile2 {
+    /// Load the file at `file_path` into `buffer`, and return its size. If
+    /// `buffer` is `None`, only the size of the file is returned.
+    ///
+    /// This is [`LoadFile::load_file`] without a boot policy: `file_path`
+    /// must describe the exact file to load.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: `buffer` is too small. The error
+    ///   holds the size of the file.
+    /// * [`Status::NOT_FOUND`]: the file was not found.
+    /// * [`Status::UNSUPPORTED`]: the device doesn't support `file_path`.
+    /// * [`Status::INVALID_PARAMETER`]: `file_path` is not valid.
+    /// * [`Status::NO_MEDIA`]: the device has no medium.
+    /// * [`Status::DEVICE_ERROR`]: the device reported an error.
+    pub fn load_file(
+        &mut self,
+        file_path: &DevicePath,
+        buffer: Option<&mut [u8]>,
+    ) -> Result<usize, Option<usize>> {
+        // The boot policy must be false for this protocol.
+        unsafe { load_file_impl(self.load_file, self as *mut Self, file_path, false, buffer) }
+    }
+}
@@ -9,4 +9,10 @@ pub mod file;
 pub mod block;
 pub mod disk;
 pub mod fs;
+#[cfg(feature = "exts")]
+pub mod gpt;
+pub mod load_file;
 pub mod partition;
+pub mod ram_disk;
+pub mod sdmmc;
+pub mod security;
@@ -0,0 +1,124 @@
+//! RAM disk protocol.
+
+use crate::data_types::Guid;
+use crate::proto::device_path::{DevicePath, FfiDevicePath};
+use crate::proto::Protocol;
+use crate::{unsafe_guid, Result, Status};
+use core::ptr;
+
+newtype_enum! {
+    /// The type of a RAM disk, which determines how it is exposed to the OS.
+    pub enum RamDiskType: Guid => {
+        /// A volatile virtual disk.
+        VIRTUAL_DISK = Guid::from_values(
+            0x77ab535a,
+            0x45fc,
+            0x624b,
+            0x5560,
+            0xf7b281d1f96e,
+        ),
+
+        /// A volatile virtual CD.
+        VIRTUAL_CD = Guid::from_values(
+            0x3d5abd30,
+            0x4175,
+            0x87ce,
+            0x6d64,
+            0xd2ade523c4bb,
+        ),
+
+        /// A persistent virtual disk.
+        PERSISTENT_VIRTUAL_DISK = Guid::from_values(
+            0x5cea02c9,
+            0x4d07,
+            0x69d3,
+            0x269f,
+            0x4496fbe096f9,
+        ),
+
+        /// A persistent virtual CD.
+        PERSISTENT_VIRTUAL_CD = Guid::from_values(
+            0x08018188,
+            0x42cd,
+            0xbb48,
+            0x100f,
+            0x5387d53ded3d,
+        ),
+    }
+}
+
+/// The RAM disk protocol.
+///
+/// This protocol is used to register a region of memory as a disk, on which
+/// the firmware then installs the usual block I/O protocols.
+#[repr(C)]
+#[unsafe_guid("ab38a0df-6873-44a9-87e6-d4eb56148449")]
+#[derive(Protocol)]
+pub struct RamDisk {
+    register: unsafe extern "efiapi" fn(
+        ram_disk_base: u64,
+        ram_disk_size: u64,
+        ram_disk_type: &Guid,
+        parent_device_path: *const FfiDevicePath,
+        device_path: &mut *const FfiDevicePath,
+    ) -> Status,
+    unregister: unsafe extern "efiapi" fn(device_path: *const FfiDevicePath) -> Status,
+}
+
+impl RamDisk {
+    /// Required alignment of the base address and size of a RAM disk.
+    pub const BLOCK_SIZE: u64 = 512;
+
+    /// Registers the memory region of `size` bytes starting at `base` as a RAM
+    /// disk of the given type.
+    ///
+    /// If `parent` is given, the device path of the new disk is appended to
+    /// it. The returned device path identifies the RAM disk and must be passed
+    /// to [`unregister`] to remove it again. Its memory is allocated from pool
+    /// by the firmware and is not freed.
+    ///
+    /// # Safety
+    ///
+    /// The memory region must stay valid and must not be accessed by other
+    /// code until the RAM disk has been unregistered.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `size` is zero, or `base` or `size` is
+    ///   not a multiple of [`Self::BLOCK_SIZE`]. This is checked before
+    ///   calling the firmware.
+    /// * [`Status::UNSUPPORTED`]: `disk_type` is not supported.
+    /// * [`Status::ALREADY_STARTED`]: a RAM disk with the same base address
+    ///   and size is already registered.
+    /// * [`Status::OUT_OF_RESOURCES`]: not enough memory to register the disk.
+    ///
+    /// [`unregister`]: Self::unregister
+    pub unsafe fn register(
+        &self,
+        base: u64,
+        size: u64,
+        disk_type: RamDiskType,
+        parent: Option<&DevicePath>,
+    ) -> Result<&'static DevicePath> {
+        if size == 0 || base % Self::BLOCK_SIZE != 0 || size % Self::BLOCK_SIZE != 0 {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+
+        let parent = parent.map_or(ptr::null(), DevicePath::as_ffi_ptr);
+        let mut device_path = ptr::null();
+        (self.register)(base, size, &disk_type.0, parent, &mut device_path)
+            .into_with_val(|| DevicePath::from_ffi_ptr(device_path))
+    }
+
+    /// Unregisters the RAM disk identified by `device_path`, which is the
+    /// path returned by [`register`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: no RAM disk with this device path exists.
+    ///
+    /// [`register`]: Self::register
+    pub fn unregister(&self, device_path: &DevicePath) -> Result {
+        unsafe { (self.unregister)(device_path.as_ffi_ptr()) }.into()
+    }
+}
@@ -0,0 +1,263 @@
+//! SD/MMC pass-through protocol.
+
+use crate::proto::device_path::{DevicePath, FfiDevicePath, PoolDevicePath};
+use crate::proto::Protocol;
+use crate::table::boot::BootServices;
+use crate::{unsafe_guid, Result, Status};
+use core::ffi::c_void;
+use core::ptr;
+
+newtype_enum! {
+    /// Type of an SD/MMC command, as defined by the SD and eMMC
+    /// specifications.
+    pub enum SdMmcCommandType: u32 => {
+        /// Broadcast command, without response.
+        BC = 0,
+        /// Broadcast command, with response.
+        BCR = 1,
+        /// Addressed command, without data transfer.
+        AC = 2,
+        /// Addressed command, with data transfer.
+        ADTC = 3,
+    }
+}
+
+newtype_enum! {
+    /// Type of the response to an SD/MMC command, as defined by the SD and
+    /// eMMC specifications.
+    pub enum SdMmcResponseType: u32 => {
+        /// Normal response.
+        R1 = 0,
+        /// Normal response, with busy signaling.
+        R1B = 1,
+        /// 136-bit response with the CID or CSD register.
+        R2 = 2,
+        /// OCR register.
+        R3 = 3,
+        /// Fast I/O response (eMMC), or I/O OCR register (SDIO).
+        R4 = 4,
+        /// Interrupt request response (eMMC), or I/O command response
+        /// (SDIO).
+        R5 = 5,
+        /// I/O command response, with busy signaling.
+        R5B = 6,
+        /// Published RCA response (SD).
+        R6 = 7,
+        /// Card interface condition (SD).
+        R7 = 8,
+    }
+}
+
+/// An SD/MMC command, as sent by [`SdMmcPassThru::pass_thru`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SdMmcCommandBlock {
+    /// Index of the command, for example 9 for `SEND_CSD`.
+    pub command_index: u16,
+    /// Argument of the command.
+    pub command_argument: u32,
+    /// Type of the command.
+    pub command_type: SdMmcCommandType,
+    /// Type of the expected response.
+    pub response_type: SdMmcResponseType,
+}
+
+/// Data phase of an SD/MMC command.
+#[derive(Debug)]
+pub enum SdMmcTransfer<'a> {
+    /// The command doesn't transfer data.
+    None,
+    /// Data is read from the device into the buffer.
+    Read(&'a mut [u8]),
+    /// Data is written from the buffer to the device.
+    Write(&'a [u8]),
+}
+
+/// A command to send with [`SdMmcPassThru::pass_thru`].
+#[derive(Debug)]
+pub struct SdMmcCommandPacket<'a> {
+    /// Timeout of the command in units of 100ns, or 0 to wait as long as
+    /// needed.
+    pub timeout: u64,
+    /// The command to send.
+    pub command: SdMmcCommandBlock,
+    /// The data to transfer. The buffer must be aligned as required by
+    /// [`SdMmcPassThru::io_align`].
+    pub transfer: SdMmcTransfer<'a>,
+}
+
+/// The result of a command sent with [`SdMmcPassThru::pass_thru`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SdMmcResponse {
+    /// The response of the device, from its least significant to its most
+    /// significant 32 bits. Only the first word is used by 48-bit
+    /// responses, and all four words by the 136-bit `R2` response.
+    pub response: [u32; 4],
+    /// The number of bytes that were transferred.
+    pub transferred: usize,
+}
+
+/// `EFI_SD_MMC_STATUS_BLOCK`.
+#[repr(C)]
+struct StatusBlock {
+    response: [u32; 4],
+}
+
+/// `EFI_SD_MMC_PASS_THRU_COMMAND_PACKET`.
+#[repr(C)]
+struct RawCommandPacket {
+    timeout: u64,
+    command_block: *mut SdMmcCommandBlock,
+    status_block: *mut StatusBlock,
+    in_data_buffer: *mut c_void,
+    out_data_buffer: *mut c_void,
+    in_transfer_length: u32,
+    out_transfer_length: u32,
+    transaction_status: Status,
+}
+
+/// The SD/MMC pass-through protocol.
+///
+/// This protocol is installed on SD and eMMC host controllers, and sends
+/// commands directly to the devices in their slots.
+#[repr(C)]
+#[unsafe_guid("716ef0fe-d1ad-4ba9-9514-d2d069ac05e2")]
+#[derive(Protocol)]
+pub struct SdMmcPassThru {
+    io_align: u32,
+    pass_thru: unsafe extern "efiapi" fn(
+        this: &mut SdMmcPassThru,
+        slot: u8,
+        packet: *mut RawCommandPacket,
+        event: *mut c_void,
+    ) -> Status,
+    get_next_slot: extern "efiapi" fn(this: &SdMmcPassThru, slot: &mut u8) -> Status,
+    build_device_path: unsafe extern "efiapi" fn(
+        this: &SdMmcPassThru,
+        slot: u8,
+        device_path: *mut *mut FfiDevicePath,
+    ) -> Status,
+    get_slot_number: unsafe extern "efiapi" fn(
+        this: &SdMmcPassThru,
+        device_path: *const FfiDevicePath,
+        slot: *mut u8,
+    ) -> Status,
+    reset_device: extern "efiapi" fn(this: &mut SdMmcPassThru, slot: u8) -> Status,
+}
+
+impl SdMmcPassThru {
+    /// The alignment required for data buffers, in bytes. A value of 0 or
+    /// 1 means that any alignment is fine.
+    pub fn io_align(&self) -> u32 {
+        self.io_align
+    }
+
+    /// Iterate over the slots of the controller that may contain a device.
+    pub fn slots(&self) -> impl Iterator<Item = u8> + '_ {
+        // Iteration starts from slot 0xff.
+        let mut slot = 0xff;
+        core::iter::from_fn(move || match (self.get_next_slot)(self, &mut slot) {
+            Status::SUCCESS => Some(slot),
+            _ => None,
+        })
+    }
+
+    /// Build a device path node for `slot`, appended to the device path
+    /// of the controller to get the path of the device in that slot.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the slot doesn't exist.
+    /// * [`Status::OUT_OF_RESOURCES`]: the path couldn't be allocated.
+    pub fn build_device_path<'boot>(
+        &self,
+        bt: &'boot BootServices,
+        slot: u8,
+    ) -> Result<PoolDevicePath<'boot>> {
+        let mut path = ptr::null_mut();
+        unsafe { (self.build_device_path)(self, slot, &mut path) }
+            .into_with_val(|| unsafe { PoolDevicePath::from_pool(bt, path) })
+    }
+
+    /// Get the slot described by `device_path`, a device path node that was
+    /// built with [`build_device_path`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the node is not an SD or eMMC node.
+    /// * [`Status::NOT_FOUND`]: the slot doesn't exist.
+    ///
+    /// [`build_device_path`]: Self::build_device_path
+    pub fn get_slot_number(&self, device_path: &DevicePath) -> Result<u8> {
+        let mut slot = 0;
+        unsafe { (self.get_slot_number)(self, device_path.as_ffi_ptr(), &mut slot) }
+            .into_with_val(|| slot)
+    }
+
+    /// Send a command to the device in `slot`, and wait for it to
+    /// complete.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::TIMEOUT`]: the command didn't complete within
+    ///   `packet.timeout`.
+    /// * [`Status::BAD_BUFFER_SIZE`]: the device doesn't support the size
+    ///   of the transfer, or it doesn't fit in 32 bits.
+    /// * [`Status::NOT_FOUND`]: the slot doesn't exist.
+    /// * [`Status::NOT_READY`]: the command couldn't be sent, and may be
+    ///   retried later.
+    /// * [`Status::INVALID_PARAMETER`]: the packet is not valid, for
+    ///   example because the buffer is not aligned.
+    /// * [`Status::UNSUPPORTED`]: the command is not supported by the host
+    ///   controller.
+    /// * [`Status::DEVICE_ERROR`]: the command failed.
+    pub fn pass_thru(
+        &mut self,
+        slot: u8,
+        packet: &mut SdMmcCommandPacket,
+    ) -> Result<SdMmcResponse> {
+        let mut command = packet.command;
+        let mut status = StatusBlock { response: [0; 4] };
+        let mut raw = RawCommandPacket {
+            timeout: packet.timeout,
+            command_block: &mut command,
+            status_block: &mut status,
+            in_data_buffer: ptr::null_mut(),
+            out_data_buffer: ptr::null_mut(),
+            in_transfer_length: 0,
+            out_transfer_length: 0,
+            transaction_status: Status::SUCCESS,
+        };
+        match &mut packet.transfer {
+            SdMmcTransfer::None => {}
+            SdMmcTransfer::Read(buffer) => {
+                raw.in_data_buffer = buffer.as_mut_ptr().cast();
+                raw.in_transfer_length =
+                    u32::try_from(buffer.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+            }
+            SdMmcTransfer::Write(buffer) => {
+                raw.out_data_buffer = buffer.as_ptr().cast::<c_void>() as *mut c_void;
+                raw.out_transfer_length =
+                    u32::try_from(buffer.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+            }
+        }
+
+        unsafe { (self.pass_thru)(self, slot, &mut raw, ptr::null_mut()) }.into_with_val(|| {
+            SdMmcResponse {
+                response: status.response,
+                transferred: (raw.in_transfer_length + raw.out_transfer_length) as usize,
+            }
+        })
+    }
+
+    /// Reset the device in `slot`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NO_MEDIA`]: there is no device in the slot.
+    /// * [`Status::NOT_FOUND`]: the slot doesn't exist.
+    /// * [`Status::DEVICE_ERROR`]: the device couldn't be reset.
+    pub fn reset_device(&mut self, slot: u8) -> Result {
+        (self.reset_device)(self, slot).into()
+    }
+}
@@ -0,0 +1,229 @@
+//! Storage security command protocol.
+//!
+//! [`StorageSecurityCommand`] sends the security protocol commands of the
+//! ATA, SCSI and NVMe standards to a storage device, which are used to
+//! manage self-encrypting drives. For example, the Level 0 Discovery of
+//! TCG Opal is read with protocol [`SecurityProtocol::TCG_1`] and the
+//! protocol-specific value `0x0001`:
+//!
+//! ```no_run
+//! use uefi::proto::media::block::BlockIO;
+//! use uefi::proto::media::security::{SecurityProtocol, StorageSecurityCommand};
+//! use uefi::Result;
+//!
+//! fn opal_discovery(
+//!     block_io: &BlockIO,
+//!     security: &mut StorageSecurityCommand,
+//!     buffer: &mut [u8],
+//! ) -> Result<usize, usize> {
+//!     let media_id = block_io.media().media_id();
+//!     security.receive_data(media_id, 0, SecurityProtocol::TCG_1, 0x0001, buffer)
+//! }
+//! ```
+
+use crate::proto::Protocol;
+use crate::{unsafe_guid, Result, Status};
+
+newtype_enum! {
+    /// Identifier of a security protocol, as defined by the SPC-4 standard.
+    pub enum SecurityProtocol: u8 => {
+        /// Information about the security protocols supported by the device.
+        INFORMATION = 0x00,
+        /// TCG protocol, used by TCG Opal for discovery and sessions.
+        TCG_1 = 0x01,
+        /// TCG protocol, used by TCG Opal for the ComID management.
+        TCG_2 = 0x02,
+        /// TCG protocol.
+        TCG_3 = 0x03,
+        /// TCG protocol.
+        TCG_4 = 0x04,
+        /// TCG protocol.
+        TCG_5 = 0x05,
+        /// TCG protocol.
+        TCG_6 = 0x06,
+        /// IEEE 1667 protocol.
+        IEEE_1667 = 0xee,
+    }
+}
+
+/// The storage security command protocol.
+///
+/// This protocol is installed on the handles of storage devices that
+/// support security protocol commands, alongside [`BlockIO`]. Each command
+/// is checked against the media ID of the current medium, which can be read
+/// with [`BlockIOMedia::media_id`].
+///
+/// [`BlockIO`]: super::block::BlockIO
+/// [`BlockIOMedia::media_id`]: super::block::BlockIOMedia::media_id
+#[repr(C)]
+#[unsafe_guid("c88b0b6d-0dfc-49a7-9cb4-49074b4c3a78")]
+#[derive(Protocol)]
+pub struct StorageSecurityCommand {
+    receive_data: unsafe extern "efiapi" fn(
+        this: &mut Self,
+        media_id: u32,
+        timeout: u64,
+        security_protocol: SecurityProtocol,
+        security_protocol_specific_data: u16,
+        payload_buffer_size: usize,
+        payload_buffer: *mut u8,
+        payload_transfer_size: &mut usize,
+    ) -> Status,
+    send_data: unsafe extern "efiapi" fn(
+        this: &mut Self,
+        media_id: u32,
+        timeout: u64,
+        security_protocol: SecurityProtocol,
+        security_protocol_specific_data: u16,
+        payload_buffer_size: usize,
+        payload_buffer: *const u8,
+    ) -> Status,
+}
+
+impl StorageSecurityCommand {
+    /// Sends a security protocol in command to the device, and reads the
+    /// data it returns into `buffer`. Returns the number of bytes read.
+    ///
+    /// `timeout` is in units of 100 nanoseconds, and 0 waits indefinitely.
+    /// `sp_specific` is the value of the protocol-specific field of the
+    /// command, such as the ComID for TCG protocols.
+    ///
+    /// The error data is also the number of bytes read, as some errors come
+    /// with data in `buffer`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::WARN_BUFFER_TOO_SMALL`]: `buffer` is too small for the
+    ///   data returned by the device, it holds the truncated data.
+    /// * [`Status::SECURITY_VIOLATION`]: the command completed with an
+    ///   error. The status specific to the security protocol, if any, is in
+    ///   `buffer`.
+    /// * [`Status::UNSUPPORTED`]: the device doesn't support security
+    ///   protocol commands.
+    /// * [`Status::MEDIA_CHANGED`]: `media_id` is not the ID of the current
+    ///   medium.
+    /// * [`Status::NO_MEDIA`]: there is no medium in the device.
+    /// * [`Status::DEVICE_ERROR`]: the command failed.
+    /// * [`Status::TIMEOUT`]: the command didn't complete within `timeout`.
+    pub fn receive_data(
+        &mut self,
+        media_id: u32,
+        timeout: u64,
+        security_protocol: SecurityProtocol,
+        sp_specific: u16,
+        buffer: &mut [u8],
+    ) -> Result<usize, usize> {
+        let mut transfer_size = 0;
+        let status = unsafe {
+            (self.receive_data)(
+                self,
+                media_id,
+                timeout,
+                security_protocol,
+                sp_specific,
+                buffer.len(),
+                buffer.as_mut_ptr(),
+                &mut transfer_size,
+            )
+        };
+        // Don't trust the driver to report a size that fits in the buffer.
+        let transfer_size = transfer_size.min(buffer.len());
+        status.into_with(|| transfer_size, |_| transfer_size)
+    }
+
+    /// Sends a security protocol out command to the device, with the data
+    /// of `buffer`.
+    ///
+    /// `timeout` is in units of 100 nanoseconds, and 0 waits indefinitely.
+    /// `sp_specific` is the value of the protocol-specific field of the
+    /// command, such as the ComID for TCG protocols.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::SECURITY_VIOLATION`]: the command completed with an
+    ///   error. The status specific to the security protocol can be read
+    ///   with [`receive_data`].
+    /// * [`Status::UNSUPPORTED`]: the device doesn't support security
+    ///   protocol commands.
+    /// * [`Status::MEDIA_CHANGED`]: `media_id` is not the ID of the current
+    ///   medium.
+    /// * [`Status::NO_MEDIA`]: there is no medium in the device.
+    /// * [`Status::DEVICE_ERROR`]: the command failed.
+    /// * [`Status::TIMEOUT`]: the command didn't complete within `timeout`.
+    ///
+    /// [`receive_data`]: Self::receive_data
+    pub fn send_data(
+        &mut self,
+        media_id: u32,
+        timeout: u64,
+        security_protocol: SecurityProtocol,
+        sp_specific: u16,
+        buffer: &[u8],
+    ) -> Result {
+        unsafe {
+            (self.send_data)(
+                self,
+                media_id,
+                timeout,
+                security_protocol,
+                sp_specific,
+                buffer.len(),
+                buffer.as_ptr(),
+            )
+        }
+        .into()
+    }
+
+    /// Returns the security protocols supported by the device, by reading
+    /// the list returned for [`SecurityProtocol::INFORMATION`] into
+    /// `buffer`.
+    ///
+    /// # Errors
+    ///
+    /// See [`receive_data`]. [`Status::BAD_BUFFER_SIZE`] is returned if the
+    /// list is truncated or malformed.
+    ///
+    /// [`receive_data`]: Self::receive_data
+    pub fn supported_protocols<'buf>(
+        &mut self,
+        media_id: u32,
+        timeout: u64,
+        buffer: &'buf mut [u8],
+    ) -> Result<&'buf [SecurityProtocol]> {
+        let len = self
+            .receive_data(media_id, timeout, SecurityProtocol::INFORMATION, 0, buffer)
+            .map_err(|err| err.status())?;
+        parse_supported_protocols(&buffer[..len]).ok_or_else(|| Status::BAD_BUFFER_SIZE.into())
+    }
+}
+
+/// Parse the list of supported protocols: 6 reserved bytes, the length of
+/// the list as a big-endian `u16`, then one byte per protocol.
+fn parse_supported_protocols(data: &[u8]) -> Option<&[SecurityProtocol]> {
+    let len = u16::from_be_bytes([*data.get(6)?, *data.get(7)?]);
+    let list = data.get(8..8 + usize::from(len))?;
+    // Safety: `SecurityProtocol` is a transparent wrapper around `u8`.
+    Some(unsafe { &*(list as *const [u8] as *const [SecurityProtocol]) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_supported_protocols() {
+        let data = [0, 0, 0, 0, 0, 0, 0, 3, 0x00, 0x01, 0x02, 0xff];
+        assert_eq!(
+            parse_supported_protocols(&data),
+            Some(
+                &[
+                    SecurityProtocol::INFORMATION,
+                    SecurityProtocol::TCG_1,
+                    SecurityProtocol::TCG_2
+                ][..]
+            )
+        );
+        assert_eq!(parse_supported_protocols(&data[..10]), None);
+        assert_eq!(parse_supported_protocols(&data[..7]), None);
+    }
+}
@@ -0,0 +1,493 @@
+//! Miscellaneous protocols.
+
+use crate::proto::Protocol;
+#[cfg(feature = "exts")]
+use crate::{alloc_api::vec::Vec, table::boot::BootServices, CStr16, CString16, Error, Guid};
+use crate::{unsafe_guid, Char16, Result, Status};
+use bitflags::bitflags;
+use core::ffi::c_void;
+use core::mem::MaybeUninit;
+#[cfg(feature = "exts")]
+use core::{
+    mem, ptr, slice,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// Protocol for retrieving a high-resolution timestamp counter.
+///
+/// This corresponds to the `EFI_TIMESTAMP_PROTOCOL` type in the C API.
+#[repr(C)]
+#[unsafe_guid("afbfde41-2e6e-4262-ba65-62b9236e5495")]
+#[derive(Protocol)]
+pub struct Timestamp {
+    get_timestamp: extern "efiapi" fn() -> u64,
+    get_properties: extern "efiapi" fn(properties: *mut TimestampProperties) -> Status,
+}
+
+impl Timestamp {
+    /// Get the current value of the timestamp counter.
+    ///
+    /// The counter counts up from zero to [`TimestampProperties::end_value`]
+    /// and then wraps around.
+    pub fn get_timestamp(&self) -> u64 {
+        (self.get_timestamp)()
+    }
+
+    /// Get the properties of the timestamp counter.
+    pub fn get_properties(&self) -> Result<TimestampProperties> {
+        let mut properties = MaybeUninit::<TimestampProperties>::uninit();
+        (self.get_properties)(properties.as_mut_ptr())
+            .into_with_val(|| unsafe { properties.assume_init() })
+    }
+}
+
+/// Properties of the timestamp counter.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub struct TimestampProperties {
+    /// Frequency of the counter in Hz.
+    pub frequency: u64,
+    /// The value the counter reaches before wrapping around to zero.
+    pub end_value: u64,
+}
+
+/// Progress callback of the [`FirmwareManagement::set_image`] call in
+/// progress, as a pointer to a `&mut dyn FnMut(usize)`.
+///
+/// The progress function of the protocol has no context parameter, so the
+/// callback is passed through this static to [`set_image_progress`].
+#[cfg(feature = "exts")]
+static SET_IMAGE_PROGRESS: AtomicPtr<c_void> = AtomicPtr::new(ptr::null_mut());
+
+/// Progress function passed to the firmware, which forwards the progress to
+/// the callback in [`SET_IMAGE_PROGRESS`].
+#[cfg(feature = "exts")]
+extern "efiapi" fn set_image_progress(completion: usize) -> Status {
+    let callback = SET_IMAGE_PROGRESS
+        .load(Ordering::Acquire)
+        .cast::<&mut dyn FnMut(usize)>();
+    if !callback.is_null() {
+        // Safety: the pointer is only set during the `set_image` call, and
+        // points to a callback that lives until the call returns.
+        unsafe { (*callback)(completion) };
+    }
+    Status::SUCCESS
+}
+
+/// The firmware management protocol, used to read and update the firmware
+/// images of a device.
+///
+/// This corresponds to the `EFI_FIRMWARE_MANAGEMENT_PROTOCOL` type in the C
+/// API. It is installed by the drivers of devices with updatable firmware,
+/// and by the system firmware for its own images.
+#[repr(C)]
+#[unsafe_guid("86c77a67-0b97-4633-a187-49104d0685c7")]
+#[derive(Protocol)]
+pub struct FirmwareManagement {
+    get_image_info: unsafe extern "efiapi" fn(
+        this: &Self,
+        image_info_size: &mut usize,
+        image_info: *mut u8,
+        descriptor_version: &mut u32,
+        descriptor_count: &mut u8,
+        descriptor_size: &mut usize,
+        package_version: &mut u32,
+        package_version_name: &mut *mut Char16,
+    ) -> Status,
+    get_image: unsafe extern "efiapi" fn(
+        this: &Self,
+        image_index: u8,
+        image: *mut u8,
+        image_size: &mut usize,
+    ) -> Status,
+    set_image: unsafe extern "efiapi" fn(
+        this: &Self,
+        image_index: u8,
+        image: *const u8,
+        image_size: usize,
+        vendor_code: *const c_void,
+        progress: Option<extern "efiapi" fn(completion: usize) -> Status>,
+        abort_reason: &mut *mut Char16,
+    ) -> Status,
+    check_image: unsafe extern "efiapi" fn(
+        this: &Self,
+        image_index: u8,
+        image: *const u8,
+        image_size: usize,
+        image_updatable: &mut ImageUpdatable,
+    ) -> Status,
+    get_package_info: unsafe extern "efiapi" fn(
+        this: &Self,
+        package_version: &mut u32,
+        package_version_name: &mut *mut Char16,
+        package_version_name_max_len: &mut u32,
+        attributes_supported: &mut u64,
+        attributes_setting: &mut u64,
+    ) -> Status,
+    set_package_info: unsafe extern "efiapi" fn(
+        this: &Self,
+        image: *const u8,
+        image_size: usize,
+        vendor_code: *const c_void,
+        package_version: u32,
+        package_version_name: *const Char16,
+    ) -> Status,
+}
+
+impl FirmwareManagement {
+    /// Get the descriptors of the firmware images managed by this instance,
+    /// and the version of the firmware package they belong to.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::DEVICE_ERROR`]: the device could not be read.
+    #[cfg(feature = "exts")]
+    pub fn get_image_info(&self, bt: &BootServices) -> Result<FirmwareImageInfo> {
+        let mut size = 0;
+        let mut buffer: Vec<u64> = Vec::new();
+        loop {
+            let mut descriptor_version = 0;
+            let mut descriptor_count = 0;
+            let mut descriptor_size = 0;
+            let mut package_version = 0;
+            let mut package_version_name = ptr::null_mut();
+            let status = unsafe {
+                (self.get_image_info)(
+                    self,
+                    &mut size,
+                    buffer.as_mut_ptr().cast(),
+                    &mut descriptor_version,
+                    &mut descriptor_count,
+                    &mut descriptor_size,
+                    &mut package_version,
+                    &mut package_version_name,
+                )
+            };
+            if status == Status::BUFFER_TOO_SMALL {
+                // The descriptors contain 64-bit fields, so keep the buffer
+                // aligned for them.
+                buffer.resize(
+                    (size + mem::size_of::<u64>() - 1) / mem::size_of::<u64>(),
+                    0,
+                );
+                continue;
+            }
+            Result::from(status)?;
+
+            let package_version_name = unsafe { take_pool_string(bt, package_version_name) }?;
+            let bytes = unsafe {
+                slice::from_raw_parts(
+                    buffer.as_ptr().cast::<u8>(),
+                    size.min(buffer.len() * mem::size_of::<u64>()),
+                )
+            };
+            let descriptors = (0..usize::from(descriptor_count))
+                .map(|i| {
+                    let offset = i * descriptor_size;
+                    let bytes = bytes
+                        .get(offset..offset + descriptor_size)
+                        .ok_or_else(|| Error::from(Status::COMPROMISED_DATA))?;
+                    Ok(unsafe { ImageDescriptor::parse(bytes, descriptor_version) })
+                })
+                .collect::<Result<_>>()?;
+            return Ok(FirmwareImageInfo {
+                package_version,
+                package_version_name,
+                descriptors,
+            });
+        }
+    }
+
+    /// Read a copy of the firmware image with the given index, as found in
+    /// [`ImageDescriptor::image_index`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: there is no image with this index.
+    /// * [`Status::UNSUPPORTED`]: the image can't be read.
+    /// * [`Status::SECURITY_VIOLATION`]: reading the image requires an
+    ///   authentication that failed.
+    #[cfg(feature = "exts")]
+    pub fn get_image(&self, image_index: u8) -> Result<Vec<u8>> {
+        let mut image = Vec::new();
+        loop {
+            let mut size = image.len();
+            match unsafe { (self.get_image)(self, image_index, image.as_mut_ptr(), &mut size) } {
+                Status::BUFFER_TOO_SMALL => image.resize(size, 0),
+                status => {
+                    return status.into_with_val(|| {
+                        image.truncate(size);
+                        image
+                    })
+                }
+            }
+        }
+    }
+
+    /// Check whether `image` can be written to the firmware image with the
+    /// given index.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: there is no image with this index.
+    /// * [`Status::SECURITY_VIOLATION`]: the authentication of the image
+    ///   failed.
+    pub fn check_image(&self, image_index: u8, image: &[u8]) -> Result<ImageUpdatable> {
+        let mut updatable = ImageUpdatable::empty();
+        unsafe {
+            (self.check_image)(
+                self,
+                image_index,
+                image.as_ptr(),
+                image.len(),
+                &mut updatable,
+            )
+        }
+        .into_with_val(|| updatable)
+    }
+
+    /// Write `image` to the firmware image with the given index.
+    ///
+    /// The firmware calls `progress`, if given, with the completion of the
+    /// update in percent. The update may take a long time, and usually
+    /// takes effect after a reset, see [`ImageAttributes::RESET_REQUIRED`].
+    ///
+    /// # Errors
+    ///
+    /// The error data is the reason given by the firmware for aborting the
+    /// update, if any.
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: there is no image with this index.
+    /// * [`Status::ABORTED`]: the update was aborted.
+    /// * [`Status::SECURITY_VIOLATION`]: the authentication of the image
+    ///   failed.
+    /// * [`Status::UNSUPPORTED`]: the image can't be written.
+    ///
+    /// The result of the last update is also reported by
+    /// [`ImageDescriptor::last_attempt_status`].
+    #[cfg(feature = "exts")]
+    pub fn set_image(
+        &mut self,
+        bt: &BootServices,
+        image_index: u8,
+        image: &[u8],
+        progress: Option<&mut dyn FnMut(usize)>,
+    ) -> Result<(), Option<CString16>> {
+        let mut progress = progress;
+        let progress_fn = progress.as_mut().map(|callback| {
+            let callback_ptr: *mut &mut dyn FnMut(usize) = callback;
+            SET_IMAGE_PROGRESS.store(callback_ptr.cast(), Ordering::Release);
+            set_image_progress as extern "efiapi" fn(usize) -> Status
+        });
+        let mut abort_reason = ptr::null_mut();
+        let status = unsafe {
+            (self.set_image)(
+                self,
+                image_index,
+                image.as_ptr(),
+                image.len(),
+                ptr::null(),
+                progress_fn,
+                &mut abort_reason,
+            )
+        };
+        SET_IMAGE_PROGRESS.store(ptr::null_mut(), Ordering::Release);
+
+        let abort_reason = unsafe { take_pool_string(bt, abort_reason) }.unwrap_or(None);
+        status.into_with_err(|_| abort_reason)
+    }
+}
+
+/// Copy a string allocated from pool memory by the firmware, and free it.
+///
+/// # Safety
+///
+/// `string` must be null, or a null-terminated string allocated with
+/// [`BootServices::allocate_pool`] that isn't used elsewhere.
+#[cfg(feature = "exts")]
+unsafe fn take_pool_string(bt: &BootServices, string: *mut Char16) -> Result<Option<CString16>> {
+    if string.is_null() {
+        return Ok(None);
+    }
+    let copy = CString16::from(CStr16::from_ptr(string));
+    bt.free_pool(string.cast())?;
+    Ok(Some(copy))
+}
+
+/// The firmware images of a [`FirmwareManagement`] instance, as returned by
+/// [`FirmwareManagement::get_image_info`].
+#[cfg(feature = "exts")]
+#[derive(Clone, Debug)]
+pub struct FirmwareImageInfo {
+    /// Version of the firmware package, or `0xffff_fffe` if the images are
+    /// versioned individually.
+    pub package_version: u32,
+    /// Name of the package version.
+    pub package_version_name: Option<CString16>,
+    /// Descriptors of the images.
+    pub descriptors: Vec<ImageDescriptor>,
+}
+
+/// Description of a firmware image.
+///
+/// This corresponds to the `EFI_FIRMWARE_IMAGE_DESCRIPTOR` type in the C
+/// API. The fields added by later versions of the descriptor are `None` if
+/// the firmware returns an older version.
+#[cfg(feature = "exts")]
+#[derive(Clone, Debug)]
+pub struct ImageDescriptor {
+    /// Index of the image, from 1 to the number of images, used to select
+    /// it in the other functions of the protocol.
+    pub image_index: u8,
+    /// Type of the image, which matches the type of the capsules updating
+    /// it.
+    pub image_type_id: Guid,
+    /// Identifier of the image, unique among the images of the device.
+    pub image_id: u64,
+    /// Name of the image.
+    pub image_id_name: Option<CString16>,
+    /// Version of the image.
+    pub version: u32,
+    /// Name of the version of the image.
+    pub version_name: Option<CString16>,
+    /// Size of the image in bytes, or 0 if unknown.
+    pub size: usize,
+    /// Attributes supported by the image.
+    pub attributes_supported: ImageAttributes,
+    /// Attributes set for the image.
+    pub attributes_setting: ImageAttributes,
+    /// Hardware compatibilities of the image.
+    pub compatibilities: u64,
+    /// Oldest version that the image can be updated or downgraded to.
+    pub lowest_supported_image_version: Option<u32>,
+    /// Version of the last update attempt.
+    pub last_attempt_version: Option<u32>,
+    /// Result of the last update attempt.
+    pub last_attempt_status: Option<LastAttemptStatus>,
+    /// Instance of the device, to tell apart identical devices.
+    pub hardware_instance: Option<u64>,
+}
+
+/// `EFI_FIRMWARE_IMAGE_DESCRIPTOR`, version 4.
+#[cfg(feature = "exts")]
+#[repr(C)]
+struct FfiImageDescriptor {
+    image_index: u8,
+    image_type_id: Guid,
+    image_id: u64,
+    image_id_name: *const Char16,
+    version: u32,
+    version_name: *const Char16,
+    size: usize,
+    attributes_supported: ImageAttributes,
+    attributes_setting: ImageAttributes,
+    compatibilities: u64,
+    lowest_supported_image_version: u32,
+    last_attempt_version: u32,
+    last_attempt_status: LastAttemptStatus,
+    hardware_instance: u64,
+    _dependencies: *const c_void,
+}
+
+#[cfg(feature = "exts")]
+impl ImageDescriptor {
+    /// Parse a descriptor of the given version from `bytes`, the
+    /// `descriptor_size` bytes returned by the firmware for it.
+    ///
+    /// # Safety
+    ///
+    /// The name pointers of the descriptor must be null or valid strings.
+    unsafe fn parse(bytes: &[u8], version: u32) -> Self {
+        // Older versions of the descriptor are shorter, in which case the
+        // missing fields are left zeroed.
+        let mut ffi = MaybeUninit::<FfiImageDescriptor>::zeroed();
+        let len = bytes.len().min(mem::size_of::<FfiImageDescriptor>());
+        ptr::copy_nonoverlapping(bytes.as_ptr(), ffi.as_mut_ptr().cast::<u8>(), len);
+        let ffi = ffi.assume_init();
+
+        let string = |ptr: *const Char16| {
+            if ptr.is_null() {
+                None
+            } else {
+                Some(CString16::from(CStr16::from_ptr(ptr)))
+            }
+        };
+        Self {
+            image_index: ffi.image_index,
+            image_type_id: ffi.image_type_id,
+            image_id: ffi.image_id,
+            image_id_name: string(ffi.image_id_name),
+            version: ffi.version,
+            version_name: string(ffi.version_name),
+            size: ffi.size,
+            attributes_supported: ffi.attributes_supported,
+            attributes_setting: ffi.attributes_setting,
+            compatibilities: ffi.compatibilities,
+            lowest_supported_image_version: (version >= 2)
+                .then_some(ffi.lowest_supported_image_version),
+            last_attempt_version: (version >= 3).then_some(ffi.last_attempt_version),
+            last_attempt_status: (version >= 3).then_some(ffi.last_attempt_status),
+            hardware_instance: (version >= 3).then_some(ffi.hardware_instance),
+        }
+    }
+}
+
+bitflags! {
+    /// Attributes of a firmware image.
+    #[repr(transparent)]
+    pub struct ImageAttributes: u64 {
+        /// The image can be updated.
+        const IMAGE_UPDATABLE = 0x1;
+        /// A reset is required for an update to take effect.
+        const RESET_REQUIRED = 0x2;
+        /// Updates must be authenticated.
+        const AUTHENTICATION_REQUIRED = 0x4;
+        /// The image is in use.
+        const IN_USE = 0x8;
+        /// The image is a UEFI image.
+        const UEFI_IMAGE = 0x10;
+        /// The image has dependencies on other images.
+        const DEPENDENCY = 0x20;
+    }
+}
+
+bitflags! {
+    /// Result of [`FirmwareManagement::check_image`].
+    #[repr(transparent)]
+    pub struct ImageUpdatable: u32 {
+        /// The image can be written.
+        const VALID = 0x1;
+        /// The image is not valid.
+        const INVALID = 0x2;
+        /// The image is not of the right type.
+        const INVALID_TYPE = 0x4;
+        /// The image is older than the lowest supported version.
+        const INVALID_OLD = 0x8;
+        /// The image can only be written with vendor-specific data.
+        const VALID_WITH_VENDOR_CODE = 0x10;
+    }
+}
+
+newtype_enum! {
+    /// Result of the last update of a firmware image.
+    pub enum LastAttemptStatus: u32 => {
+        /// The update succeeded.
+        SUCCESS = 0,
+        /// The update failed for an unspecified reason.
+        ERROR_UNSUCCESSFUL = 1,
+        /// The device didn't have enough resources for the update.
+        ERROR_INSUFFICIENT_RESOURCES = 2,
+        /// The version of the image is not supported.
+        ERROR_INCORRECT_VERSION = 3,
+        /// The image is not valid.
+        ERROR_INVALID_FORMAT = 4,
+        /// The authentication of the image failed.
+        ERROR_AUTH_ERROR = 5,
+        /// The update was not done because the system was not on AC power.
+        ERROR_PWR_EVT_AC = 6,
+        /// The update was not done because the battery was too low.
+        ERROR_PWR_EVT_BATT = 7,
+        /// The dependencies of the image are not satisfied.
+        ERROR_UNSATISFIED_DEPENDENCIES = 8,
+    }
+}
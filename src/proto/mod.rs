@@ -64,13 +64,22 @@ where
 
 pub use uefi_macros::Protocol;
 
+pub mod boot;
+pub(crate) mod completion;
 pub mod console;
 pub mod debug;
 pub mod device_path;
+pub mod driver;
+#[cfg(feature = "known-guids")]
+pub mod known_guids;
 pub mod loaded_image;
 pub mod media;
+pub mod misc;
 pub mod network;
 pub mod pi;
 pub mod rng;
 pub mod security;
 pub mod shim;
+pub mod string;
+pub mod tpm;
+pub mod usb;
@@ -0,0 +1,207 @@
+//! Adapter Information protocol.
+
+use crate::alloc_api::vec::Vec;
+use crate::data_types::Guid;
+use crate::proto::Protocol;
+use crate::table::boot::{BootServices, PoolGuard};
+use crate::{unsafe_guid, Result, Status};
+use core::ffi::c_void;
+use core::{mem, ptr, slice};
+
+newtype_enum! {
+    /// Type of information block of an [`AdapterInformation`] protocol.
+    pub enum AdapterInfoType: Guid => {
+        /// Whether the network media is connected, decoded by
+        /// [`AdapterInformation::media_state`].
+        MEDIA_STATE = Guid::from_values(
+            0xd7c74207,
+            0xa831,
+            0x4a26,
+            0xb1f5,
+            0xd193065ce8b6,
+        ),
+
+        /// The network boot capabilities of the adapter, decoded by
+        /// [`AdapterInformation::network_boot`].
+        NETWORK_BOOT = Guid::from_values(
+            0x1fbd2960,
+            0x4130,
+            0x41e5,
+            0x94ac,
+            0xd2cf037fb37c,
+        ),
+
+        /// The MAC address used for SAN boot.
+        SAN_MAC_ADDRESS = Guid::from_values(
+            0x114da5ef,
+            0x2cf1,
+            0x4e12,
+            0x9bbb,
+            0xc470b55205d9,
+        ),
+
+        /// Whether the UNDI driver supports IPv6.
+        UNDI_IPV6_SUPPORT = Guid::from_values(
+            0x4bd56be3,
+            0x4975,
+            0x4d8a,
+            0xa0ad,
+            0xc491204b5d4d,
+        ),
+
+        /// The type of media of the adapter.
+        MEDIA_TYPE = Guid::from_values(
+            0x8484472f,
+            0x71ec,
+            0x411a,
+            0xb39c,
+            0x62cd94d9916e,
+        ),
+    }
+}
+
+/// The network boot capabilities and settings of an adapter, from the
+/// [`AdapterInfoType::NETWORK_BOOT`] information block.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetworkBootInfo {
+    pub iscsi_ipv4_boot_capable: bool,
+    pub iscsi_ipv6_boot_capable: bool,
+    pub fcoe_boot_capable: bool,
+    pub offload_capable: bool,
+    pub iscsi_mpio_capable: bool,
+    pub iscsi_ipv4_boot: bool,
+    pub iscsi_ipv6_boot: bool,
+    pub fcoe_boot: bool,
+}
+
+/// Adapter Information protocol.
+///
+/// This protocol returns information blocks about a network adapter, such
+/// as whether its link is up. Each block is identified by an
+/// [`AdapterInfoType`].
+///
+/// This corresponds to the `EFI_ADAPTER_INFORMATION_PROTOCOL` type in the C
+/// API.
+#[repr(C)]
+#[unsafe_guid("e5dd1403-d622-c24e-8488-c71b17f5e802")]
+#[derive(Protocol)]
+pub struct AdapterInformation {
+    get_information: unsafe extern "efiapi" fn(
+        this: &Self,
+        information_type: &Guid,
+        information_block: &mut *mut c_void,
+        information_block_size: &mut usize,
+    ) -> Status,
+    set_information: unsafe extern "efiapi" fn(
+        this: &Self,
+        information_type: &Guid,
+        information_block: *const c_void,
+        information_block_size: usize,
+    ) -> Status,
+    get_supported_types: unsafe extern "efiapi" fn(
+        this: &Self,
+        info_types_buffer: &mut *mut Guid,
+        info_types_buffer_count: &mut usize,
+    ) -> Status,
+}
+
+impl AdapterInformation {
+    /// Returns the raw information block of type `ty`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the adapter doesn't provide this type of
+    ///   information.
+    /// * [`Status::DEVICE_ERROR`]: the adapter failed to return the
+    ///   information.
+    pub fn get_information(&self, bt: &BootServices, ty: AdapterInfoType) -> Result<Vec<u8>> {
+        let mut block = ptr::null_mut();
+        let mut size = 0;
+        let status = unsafe { (self.get_information)(self, &ty.0, &mut block, &mut size) };
+        let _guard = PoolGuard::new(bt, block.cast());
+        Result::from(status)?;
+
+        if block.is_null() {
+            return Ok(Vec::new());
+        }
+        Ok(unsafe { slice::from_raw_parts(block.cast::<u8>(), size) }.to_vec())
+    }
+
+    /// Sets the information block of type `ty`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the adapter doesn't support this type of
+    ///   information.
+    /// * [`Status::WRITE_PROTECTED`]: this type of information can only be
+    ///   read.
+    /// * [`Status::DEVICE_ERROR`]: the adapter failed to set the information.
+    pub fn set_information(&self, ty: AdapterInfoType, data: &[u8]) -> Result {
+        unsafe { (self.set_information)(self, &ty.0, data.as_ptr().cast(), data.len()) }.into()
+    }
+
+    /// Returns the types of information blocks provided by the adapter.
+    pub fn get_supported_types(&self, bt: &BootServices) -> Result<Vec<AdapterInfoType>> {
+        let mut types = ptr::null_mut();
+        let mut count = 0;
+        let status = unsafe { (self.get_supported_types)(self, &mut types, &mut count) };
+        let _guard = PoolGuard::new(bt, types.cast());
+        Result::from(status)?;
+
+        if types.is_null() {
+            return Ok(Vec::new());
+        }
+        let types = unsafe { slice::from_raw_parts(types, count) };
+        Ok(types.iter().copied().map(AdapterInfoType).collect())
+    }
+
+    /// Returns the state of the network media, from the
+    /// [`AdapterInfoType::MEDIA_STATE`] information block.
+    ///
+    /// The state is [`Status::SUCCESS`] if the media is connected, such as
+    /// when the link of an Ethernet adapter is up, [`Status::NO_MEDIA`] if it
+    /// is not connected, and [`Status::NOT_READY`] if it is in the process of
+    /// connecting.
+    ///
+    /// # Errors
+    ///
+    /// See [`get_information`]. In addition, [`Status::COMPROMISED_DATA`] is
+    /// returned if the information block is too short.
+    ///
+    /// [`get_information`]: Self::get_information
+    pub fn media_state(&self, bt: &BootServices) -> Result<Status> {
+        let block = self.get_information(bt, AdapterInfoType::MEDIA_STATE)?;
+        if block.len() < mem::size_of::<Status>() {
+            return Err(Status::COMPROMISED_DATA.into());
+        }
+        Ok(unsafe { ptr::read_unaligned(block.as_ptr().cast::<Status>()) })
+    }
+
+    /// Returns the network boot capabilities and settings of the adapter,
+    /// from the [`AdapterInfoType::NETWORK_BOOT`] information block.
+    ///
+    /// # Errors
+    ///
+    /// See [`media_state`].
+    ///
+    /// [`media_state`]: Self::media_state
+    pub fn network_boot(&self, bt: &BootServices) -> Result<NetworkBootInfo> {
+        let block = self.get_information(bt, AdapterInfoType::NETWORK_BOOT)?;
+        let flags: [u8; 8] = match block.get(..8) {
+            Some(flags) => flags.try_into().unwrap(),
+            None => return Err(Status::COMPROMISED_DATA.into()),
+        };
+        let flags = flags.map(|flag| flag != 0);
+        Ok(NetworkBootInfo {
+            iscsi_ipv4_boot_capable: flags[0],
+            iscsi_ipv6_boot_capable: flags[1],
+            fcoe_boot_capable: flags[2],
+            offload_capable: flags[3],
+            iscsi_mpio_capable: flags[4],
+            iscsi_ipv4_boot: flags[5],
+            iscsi_ipv6_boot: flags[6],
+            fcoe_boot: flags[7],
+        })
+    }
+}
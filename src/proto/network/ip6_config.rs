@@ -0,0 +1,403 @@
+//! IPv6 configuration protocol.
+
+use super::{Ipv6Address, MacAddress};
+use crate::proto::Protocol;
+use crate::{unsafe_guid, CStr16, Error, Event, Result, Status};
+use core::ffi::c_void;
+use core::{mem, slice};
+
+newtype_enum! {
+    /// Type of the configuration data accessed with [`Ip6Config`].
+    pub enum Ip6ConfigDataType: u32 => {
+        /// Basic information about the interface, see
+        /// [`Ip6ConfigInterfaceInfo`]. Read-only.
+        INTERFACE_INFO = 0,
+        /// Alternative interface ID used for stateless address
+        /// auto-configuration.
+        ALT_INTERFACE_ID = 1,
+        /// Whether addresses are configured manually or automatically, see
+        /// [`Ip6ConfigPolicy`].
+        POLICY = 2,
+        /// Number of duplicate address detection messages sent for each
+        /// address.
+        DUP_ADDR_DETECT_TRANSMITS = 3,
+        /// Manually configured addresses, see [`Ip6ConfigManualAddress`].
+        MANUAL_ADDRESS = 4,
+        /// Default gateways.
+        GATEWAY = 5,
+        /// DNS servers.
+        DNS_SERVER = 6,
+    }
+}
+
+newtype_enum! {
+    /// How the addresses of an interface are configured.
+    pub enum Ip6ConfigPolicy: u32 => {
+        /// Addresses, gateways and DNS servers are configured manually.
+        MANUAL = 0,
+        /// Addresses are configured with stateless address
+        /// auto-configuration or DHCPv6.
+        AUTOMATIC = 1,
+    }
+}
+
+/// An address of an interface.
+///
+/// Corresponds to the `EFI_IP6_ADDRESS_INFO` type in the C API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct Ip6AddressInfo {
+    /// The address.
+    pub address: Ipv6Address,
+    /// Length of the subnet prefix in bits.
+    pub prefix_length: u8,
+}
+
+/// An entry of the routing table of an interface.
+///
+/// Corresponds to the `EFI_IP6_ROUTE_TABLE` type in the C API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct Ip6RouteTable {
+    /// Next hop, or [`Ipv6Address::UNSPECIFIED`] if the destination is
+    /// directly reachable.
+    pub gateway: Ipv6Address,
+    /// Destination network.
+    pub destination: Ipv6Address,
+    /// Length of the destination prefix in bits.
+    pub prefix_length: u8,
+}
+
+/// A manually configured address.
+///
+/// Corresponds to the `EFI_IP6_CONFIG_MANUAL_ADDRESS` type in the C API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct Ip6ConfigManualAddress {
+    /// The address.
+    pub address: Ipv6Address,
+    /// Whether the address is an anycast address.
+    pub is_anycast: bool,
+    /// Length of the subnet prefix in bits.
+    pub prefix_length: u8,
+}
+
+/// Basic information about an interface, returned by
+/// [`Ip6Config::interface_info`].
+///
+/// Corresponds to the `EFI_IP6_CONFIG_INTERFACE_INFO` type in the C API.
+#[derive(Debug)]
+#[repr(C)]
+pub struct Ip6ConfigInterfaceInfo {
+    name: [u16; 32],
+    if_type: u8,
+    hw_address_size: u32,
+    hw_address: MacAddress,
+    address_info_count: u32,
+    address_info: *const Ip6AddressInfo,
+    route_count: u32,
+    route_table: *const Ip6RouteTable,
+}
+
+impl Ip6ConfigInterfaceInfo {
+    /// Name of the interface, or `None` if it is not a valid string.
+    pub fn name(&self) -> Option<&CStr16> {
+        let len = self.name.iter().position(|&c| c == 0)?;
+        CStr16::from_u16_with_nul(&self.name[..=len]).ok()
+    }
+
+    /// Type of the interface, as defined in the ARP hardware type registry.
+    /// For example, 1 is Ethernet.
+    pub fn if_type(&self) -> u8 {
+        self.if_type
+    }
+
+    /// Hardware address of the interface.
+    pub fn hw_address(&self) -> &[u8] {
+        let len = (self.hw_address_size as usize).min(self.hw_address.0.len());
+        &self.hw_address.0[..len]
+    }
+
+    /// Addresses currently configured on the interface.
+    pub fn addresses(&self) -> &[Ip6AddressInfo] {
+        unsafe { raw_slice(self.address_info, self.address_info_count) }
+    }
+
+    /// Routing table of the interface.
+    pub fn routes(&self) -> &[Ip6RouteTable] {
+        unsafe { raw_slice(self.route_table, self.route_count) }
+    }
+}
+
+/// Creates a slice from a pointer and length returned by the driver, which
+/// may be null if the length is 0.
+unsafe fn raw_slice<'a, T>(data: *const T, len: u32) -> &'a [T] {
+    if data.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len as usize)
+    }
+}
+
+/// The IPv6 configuration protocol.
+///
+/// This protocol is installed on network interfaces and is used to query and
+/// change the IPv6 configuration of the interface, such as its addresses,
+/// gateways and DNS servers.
+///
+/// Manual configuration only takes effect if the policy is
+/// [`Ip6ConfigPolicy::MANUAL`]. Changing the policy resets the configuration.
+#[repr(C)]
+#[unsafe_guid("937fe521-95ae-4d1a-8929-48bcd90ad31a")]
+#[derive(Protocol)]
+pub struct Ip6Config {
+    set_data: unsafe extern "efiapi" fn(
+        this: &Self,
+        data_type: Ip6ConfigDataType,
+        data_size: usize,
+        data: *const c_void,
+    ) -> Status,
+    get_data: unsafe extern "efiapi" fn(
+        this: &Self,
+        data_type: Ip6ConfigDataType,
+        data_size: &mut usize,
+        data: *mut c_void,
+    ) -> Status,
+    register_data_notify:
+        extern "efiapi" fn(this: &Self, data_type: Ip6ConfigDataType, event: Event) -> Status,
+    unregister_data_notify:
+        extern "efiapi" fn(this: &Self, data_type: Ip6ConfigDataType, event: Event) -> Status,
+}
+
+impl Ip6Config {
+    /// Reads the raw configuration data of type `data_type` into `buffer`,
+    /// returning the size of the data.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: the buffer is too small. The required
+    ///   size is returned in the error.
+    /// * [`Status::NOT_READY`]: the data is being configured asynchronously.
+    /// * [`Status::NOT_FOUND`]: the data is not set.
+    pub fn get_data(
+        &self,
+        data_type: Ip6ConfigDataType,
+        buffer: &mut [u8],
+    ) -> Result<usize, Option<usize>> {
+        unsafe { self.get_data_raw(data_type, buffer.as_mut_ptr().cast(), buffer.len()) }
+    }
+
+    /// Sets the raw configuration data of type `data_type`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_READY`]: the data is being applied asynchronously, for
+    ///   example because duplicate address detection is in progress.
+    ///   Register an event with [`register_data_notify`] to find out when it
+    ///   is done.
+    /// * [`Status::WRITE_PROTECTED`]: the data is read-only.
+    /// * [`Status::BAD_BUFFER_SIZE`]: the size doesn't match the data type.
+    ///
+    /// [`register_data_notify`]: Self::register_data_notify
+    pub fn set_data(&mut self, data_type: Ip6ConfigDataType, data: &[u8]) -> Result {
+        unsafe { (self.set_data)(self, data_type, data.len(), data.as_ptr().cast()) }.into()
+    }
+
+    /// Reads the interface information into `buffer`. The addresses and
+    /// routes are stored in the same buffer, after the
+    /// [`Ip6ConfigInterfaceInfo`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: the buffer is too small. The required
+    ///   size is returned in the error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` is not aligned for [`Ip6ConfigInterfaceInfo`].
+    pub fn interface_info<'buf>(
+        &self,
+        buffer: &'buf mut [u8],
+    ) -> Result<&'buf Ip6ConfigInterfaceInfo, Option<usize>> {
+        assert_eq!(
+            buffer
+                .as_ptr()
+                .align_offset(mem::align_of::<Ip6ConfigInterfaceInfo>()),
+            0,
+            "The provided buffer is not correctly aligned"
+        );
+        self.get_data(Ip6ConfigDataType::INTERFACE_INFO, buffer)?;
+        Ok(unsafe { &*buffer.as_ptr().cast::<Ip6ConfigInterfaceInfo>() })
+    }
+
+    /// Returns the configuration policy.
+    pub fn policy(&self) -> Result<Ip6ConfigPolicy> {
+        let mut policy = Ip6ConfigPolicy::AUTOMATIC;
+        unsafe { self.get_value(Ip6ConfigDataType::POLICY, &mut policy) }?;
+        Ok(policy)
+    }
+
+    /// Sets the configuration policy. This resets the manual configuration.
+    pub fn set_policy(&mut self, policy: Ip6ConfigPolicy) -> Result {
+        unsafe { self.set_value(Ip6ConfigDataType::POLICY, &policy) }
+    }
+
+    /// Returns the number of duplicate address detection messages sent for
+    /// each address.
+    pub fn dup_addr_detect_transmits(&self) -> Result<u32> {
+        let mut transmits = 0;
+        unsafe { self.get_value(Ip6ConfigDataType::DUP_ADDR_DETECT_TRANSMITS, &mut transmits) }?;
+        Ok(transmits)
+    }
+
+    /// Sets the number of duplicate address detection messages sent for
+    /// each address. 0 disables duplicate address detection.
+    pub fn set_dup_addr_detect_transmits(&mut self, transmits: u32) -> Result {
+        unsafe { self.set_value(Ip6ConfigDataType::DUP_ADDR_DETECT_TRANSMITS, &transmits) }
+    }
+
+    /// Reads the manually configured addresses into `buffer`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: the buffer is too small. The required
+    ///   number of addresses is returned in the error.
+    pub fn manual_addresses<'buf>(
+        &self,
+        buffer: &'buf mut [Ip6ConfigManualAddress],
+    ) -> Result<&'buf [Ip6ConfigManualAddress], Option<usize>> {
+        unsafe { self.get_list(Ip6ConfigDataType::MANUAL_ADDRESS, buffer) }
+    }
+
+    /// Sets the manually configured addresses. An empty slice removes all
+    /// of them.
+    ///
+    /// See [`set_data`] for the errors.
+    ///
+    /// [`set_data`]: Self::set_data
+    pub fn set_manual_addresses(&mut self, addresses: &[Ip6ConfigManualAddress]) -> Result {
+        unsafe { self.set_list(Ip6ConfigDataType::MANUAL_ADDRESS, addresses) }
+    }
+
+    /// Reads the default gateways into `buffer`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: the buffer is too small. The required
+    ///   number of addresses is returned in the error.
+    pub fn gateways<'buf>(
+        &self,
+        buffer: &'buf mut [Ipv6Address],
+    ) -> Result<&'buf [Ipv6Address], Option<usize>> {
+        unsafe { self.get_list(Ip6ConfigDataType::GATEWAY, buffer) }
+    }
+
+    /// Sets the default gateways. An empty slice removes all of them.
+    pub fn set_gateways(&mut self, gateways: &[Ipv6Address]) -> Result {
+        unsafe { self.set_list(Ip6ConfigDataType::GATEWAY, gateways) }
+    }
+
+    /// Reads the DNS servers into `buffer`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: the buffer is too small. The required
+    ///   number of addresses is returned in the error.
+    pub fn dns_servers<'buf>(
+        &self,
+        buffer: &'buf mut [Ipv6Address],
+    ) -> Result<&'buf [Ipv6Address], Option<usize>> {
+        unsafe { self.get_list(Ip6ConfigDataType::DNS_SERVER, buffer) }
+    }
+
+    /// Sets the DNS servers. An empty slice removes all of them.
+    pub fn set_dns_servers(&mut self, servers: &[Ipv6Address]) -> Result {
+        unsafe { self.set_list(Ip6ConfigDataType::DNS_SERVER, servers) }
+    }
+
+    /// Registers `event` to be signaled when the data of type `data_type`
+    /// changes.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: the event is already registered for this
+    ///   data type.
+    pub fn register_data_notify(&mut self, data_type: Ip6ConfigDataType, event: &Event) -> Result {
+        (self.register_data_notify)(self, data_type, unsafe { event.unsafe_clone() }).into()
+    }
+
+    /// Unregisters an event registered with [`register_data_notify`].
+    ///
+    /// [`register_data_notify`]: Self::register_data_notify
+    pub fn unregister_data_notify(
+        &mut self,
+        data_type: Ip6ConfigDataType,
+        event: &Event,
+    ) -> Result {
+        (self.unregister_data_notify)(self, data_type, unsafe { event.unsafe_clone() }).into()
+    }
+
+    unsafe fn get_data_raw(
+        &self,
+        data_type: Ip6ConfigDataType,
+        data: *mut c_void,
+        size: usize,
+    ) -> Result<usize, Option<usize>> {
+        let mut data_size = size;
+        (self.get_data)(self, data_type, &mut data_size, data).into_with(
+            || data_size,
+            |status| {
+                if status == Status::BUFFER_TOO_SMALL {
+                    Some(data_size)
+                } else {
+                    None
+                }
+            },
+        )
+    }
+
+    /// Reads fixed-size data of type `T`.
+    unsafe fn get_value<T>(&self, data_type: Ip6ConfigDataType, value: &mut T) -> Result {
+        self.get_data_raw(data_type, (value as *mut T).cast(), mem::size_of::<T>())
+            .map(|_| ())
+            .map_err(|err| err.status().into())
+    }
+
+    unsafe fn set_value<T>(&self, data_type: Ip6ConfigDataType, value: &T) -> Result {
+        (self.set_data)(
+            self,
+            data_type,
+            mem::size_of::<T>(),
+            (value as *const T).cast(),
+        )
+        .into()
+    }
+
+    /// Reads a list of `T`. Data that is not set is returned as an empty
+    /// list, and the required size is returned as a number of elements.
+    unsafe fn get_list<'buf, T>(
+        &self,
+        data_type: Ip6ConfigDataType,
+        buffer: &'buf mut [T],
+    ) -> Result<&'buf [T], Option<usize>> {
+        let size = mem::size_of::<T>();
+        match self.get_data_raw(data_type, buffer.as_mut_ptr().cast(), buffer.len() * size) {
+            Ok(data_size) => Ok(&buffer[..data_size / size]),
+            Err(err) if err.status() == Status::NOT_FOUND => Ok(&buffer[..0]),
+            Err(err) => {
+                let required = err.data().map(|data_size| (data_size + size - 1) / size);
+                Err(Error::new(err.status(), required))
+            }
+        }
+    }
+
+    unsafe fn set_list<T>(&self, data_type: Ip6ConfigDataType, list: &[T]) -> Result {
+        (self.set_data)(
+            self,
+            data_type,
+            mem::size_of_val(list),
+            list.as_ptr().cast(),
+        )
+        .into()
+    }
+}
@@ -1,8 +1,27 @@
 //! Network access protocols.
 //!
 //! These protocols can be used to interact with network resources.
+//!
+//! Most network protocols, such as [`Tcp6`] and [`Udp6`], are not installed
+//! directly on the network interface. Instead, a child handle with an
+//! instance of the protocol is created with the corresponding service binding
+//! protocol, such as [`Tcp6ServiceBinding`], and destroyed again when it is
+//! no longer needed.
+//!
+//! [`Tcp6`]: tcp6::Tcp6
+//! [`Tcp6ServiceBinding`]: tcp6::Tcp6ServiceBinding
+//! [`Udp6`]: udp6::Udp6
+
+use crate::{Handle, Result, Status};
+use core::mem;
 
+#[cfg(feature = "exts")]
+pub mod adapter_info;
+pub mod ip6_config;
 pub mod pxe;
+pub mod tcp6;
+pub mod udp6;
+pub mod wifi;
 
 /// Represents an IPv4/v6 address.
 ///
@@ -28,9 +47,152 @@ impl IpAddress {
     }
 }
 
+impl From<Ipv6Address> for IpAddress {
+    fn from(address: Ipv6Address) -> Self {
+        Self::new_v6(address.0)
+    }
+}
+
+/// Represents an IPv6 address.
+///
+/// Corresponds to the `EFI_IPv6_ADDRESS` type in the C API.
+///
+/// UEFI has no scope IDs: a link-local address always refers to the link of
+/// the network interface that the protocol instance it is used with is bound
+/// to. Use [`is_link_local`] to detect such addresses before passing them on
+/// to code that needs a scope.
+///
+/// [`is_link_local`]: Ipv6Address::is_link_local
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct Ipv6Address(pub [u8; 16]);
+
+impl Ipv6Address {
+    /// The unspecified address `::`.
+    pub const UNSPECIFIED: Self = Self([0; 16]);
+
+    /// The loopback address `::1`.
+    pub const LOCALHOST: Self = Self([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+    /// Returns `true` for the unspecified address `::`.
+    pub fn is_unspecified(&self) -> bool {
+        *self == Self::UNSPECIFIED
+    }
+
+    /// Returns `true` for link-local unicast addresses (`fe80::/10`).
+    pub fn is_link_local(&self) -> bool {
+        self.0[0] == 0xfe && (self.0[1] & 0xc0) == 0x80
+    }
+
+    /// Returns `true` for multicast addresses (`ff00::/8`).
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] == 0xff
+    }
+}
+
 /// Represents a MAC (media access control) address.
 ///
 /// Corresponds to the `EFI_MAC_ADDRESS` type in the C API.
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[repr(C)]
 pub struct MacAddress(pub [u8; 32]);
+
+/// Common layout of the service binding protocols.
+///
+/// Each network protocol has its own service binding protocol, with its own
+/// GUID, which all dereference to this type.
+#[repr(C)]
+pub struct ServiceBinding {
+    create_child: extern "efiapi" fn(this: &Self, child_handle: &mut Option<Handle>) -> Status,
+    destroy_child: extern "efiapi" fn(this: &Self, child_handle: Handle) -> Status,
+}
+
+impl ServiceBinding {
+    /// Creates a child handle with an instance of the network protocol
+    /// installed on it.
+    ///
+    /// If `handle` is given, the protocol instance is installed on that
+    /// handle instead of a new one.
+    pub fn create_child(&self, handle: Option<Handle>) -> Result<Handle> {
+        let mut handle = handle;
+        (self.create_child)(self, &mut handle).into_with_val(|| handle.unwrap())
+    }
+
+    /// Destroys a child handle created with [`create_child`], after
+    /// uninstalling its protocol instance.
+    ///
+    /// [`create_child`]: ServiceBinding::create_child
+    pub fn destroy_child(&self, child: Handle) -> Result {
+        (self.destroy_child)(self, child).into()
+    }
+
+    /// Creates a child handle like [`create_child`], and returns a guard
+    /// that destroys it when dropped.
+    ///
+    /// This makes sure that the child is destroyed when the setup of its
+    /// protocol instance fails part way, for example when an error is
+    /// returned with `?`.
+    ///
+    /// [`create_child`]: ServiceBinding::create_child
+    pub fn create_scoped_child(&self) -> Result<ScopedChild<'_>> {
+        let handle = self.create_child(None)?;
+        Ok(ScopedChild {
+            service_binding: self,
+            handle,
+        })
+    }
+}
+
+/// A child handle created with [`ServiceBinding::create_scoped_child`].
+///
+/// The child is destroyed when this is dropped. The protocols opened on the
+/// child must be closed first, which is the case for a [`ScopedProtocol`]
+/// declared after the child in the same scope.
+///
+/// [`ScopedProtocol`]: crate::table::boot::ScopedProtocol
+pub struct ScopedChild<'a> {
+    service_binding: &'a ServiceBinding,
+    handle: Handle,
+}
+
+impl ScopedChild<'_> {
+    /// The child handle.
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+
+    /// Destroys the child, returning the error that dropping it would
+    /// ignore.
+    pub fn destroy(self) -> Result {
+        let result = self.service_binding.destroy_child(self.handle);
+        mem::forget(self);
+        result
+    }
+}
+
+impl Drop for ScopedChild<'_> {
+    fn drop(&mut self) {
+        // Nothing can be done about a failure while unwinding the setup.
+        let _ = self.service_binding.destroy_child(self.handle);
+    }
+}
+
+/// Defines a service binding protocol with the given GUID.
+macro_rules! service_binding {
+    ($(#[$attrs:meta])* $name:ident, $guid:literal) => {
+        $(#[$attrs])*
+        #[repr(transparent)]
+        #[crate::unsafe_guid($guid)]
+        #[derive(crate::proto::Protocol)]
+        pub struct $name(crate::proto::network::ServiceBinding);
+
+        impl core::ops::Deref for $name {
+            type Target = crate::proto::network::ServiceBinding;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+    };
+}
+pub(crate) use service_binding;
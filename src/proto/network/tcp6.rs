@@ -0,0 +1,468 @@
+//! TCPv6 protocol.
+
+use super::{service_binding, Ipv6Address};
+use crate::proto::completion::CompletionEvent;
+use crate::proto::Protocol;
+use crate::table::boot::BootServices;
+use crate::{unsafe_guid, Event, Handle, Result, Status};
+use core::ffi::c_void;
+use core::ptr;
+
+service_binding! {
+    /// Service binding protocol used to create and destroy [`Tcp6`] instances.
+    Tcp6ServiceBinding, "ec20eb79-6c1a-4664-9a0d-d2e4cc16d664"
+}
+
+newtype_enum! {
+    /// State of a TCP connection, as defined in RFC 793.
+    pub enum Tcp6ConnectionState: u32 => {
+        /// No connection.
+        CLOSED = 0,
+        /// Waiting for a connection request.
+        LISTEN = 1,
+        /// Waiting for a reply to a sent connection request.
+        SYN_SENT = 2,
+        /// Waiting for the acknowledgment of a connection request.
+        SYN_RECEIVED = 3,
+        /// The connection is open, data can be sent and received.
+        ESTABLISHED = 4,
+        /// Waiting for the remote side to close the connection, or for the
+        /// acknowledgment of the sent close request.
+        FIN_WAIT1 = 5,
+        /// Waiting for the remote side to close the connection.
+        FIN_WAIT2 = 6,
+        /// Waiting for the acknowledgment of the sent close request.
+        CLOSING = 7,
+        /// Waiting until the remote side has received the acknowledgment of
+        /// its close request.
+        TIME_WAIT = 8,
+        /// Waiting for the local side to close the connection.
+        CLOSE_WAIT = 9,
+        /// Waiting for the acknowledgment of the sent close request, after
+        /// the remote side has closed the connection.
+        LAST_ACK = 10,
+    }
+}
+
+/// Local and remote endpoints of a connection.
+///
+/// Corresponds to the `EFI_TCP6_ACCESS_POINT` type in the C API.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct Tcp6AccessPoint {
+    /// Local address, or [`Ipv6Address::UNSPECIFIED`] to let the driver pick
+    /// one of the addresses of the interface.
+    pub station_address: Ipv6Address,
+    /// Local port, or 0 to use a random port. Only passive instances may use
+    /// a random port.
+    pub station_port: u16,
+    /// Remote address. Passive instances may use
+    /// [`Ipv6Address::UNSPECIFIED`] to accept connections from any address.
+    pub remote_address: Ipv6Address,
+    /// Remote port. Passive instances may use 0 to accept connections from
+    /// any port.
+    pub remote_port: u16,
+    /// Whether the instance initiates the connection with [`Tcp6::connect`]
+    /// (active), or waits for connections with [`Tcp6::accept`] (passive).
+    pub active_flag: bool,
+}
+
+/// Advanced options of a [`Tcp6`] instance. Drivers may ignore options they
+/// don't support.
+///
+/// Corresponds to the `EFI_TCP6_OPTION` type in the C API.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct Tcp6Option {
+    /// Size of the receive buffer in bytes.
+    pub receive_buffer_size: u32,
+    /// Size of the send buffer in bytes.
+    pub send_buffer_size: u32,
+    /// Maximum number of pending connections of a passive instance.
+    pub max_syn_back_log: u32,
+    /// Connection establishment timeout in seconds.
+    pub connection_timeout: u32,
+    /// Number of retransmissions before the connection is reset.
+    pub data_retries: u32,
+    /// `FIN_WAIT2` timeout in seconds.
+    pub fin_timeout: u32,
+    /// `TIME_WAIT` timeout in seconds.
+    pub time_wait_timeout: u32,
+    /// Number of unanswered keep-alive probes before the connection is
+    /// reset.
+    pub keep_alive_probes: u32,
+    /// Idle time in seconds before keep-alive probes are sent.
+    pub keep_alive_time: u32,
+    /// Time in seconds between keep-alive probes.
+    pub keep_alive_interval: u32,
+    /// Enable the Nagle algorithm.
+    pub enable_nagle: bool,
+    /// Enable the TCP timestamp option.
+    pub enable_time_stamp: bool,
+    /// Enable the TCP window scale option.
+    pub enable_window_scaling: bool,
+    /// Enable selective acknowledgments.
+    pub enable_selective_ack: bool,
+    /// Enable path MTU discovery.
+    pub enable_path_mtu_discovery: bool,
+}
+
+/// Configuration of a [`Tcp6`] instance.
+///
+/// Corresponds to the `EFI_TCP6_CONFIG_DATA` type in the C API.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct Tcp6ConfigData<'a> {
+    /// Traffic class field of transmitted packets.
+    pub traffic_class: u8,
+    /// Hop limit of transmitted packets.
+    pub hop_limit: u8,
+    /// Endpoints of the connection.
+    pub access_point: Tcp6AccessPoint,
+    /// Advanced options, or `None` to use the driver defaults.
+    pub control_option: Option<&'a Tcp6Option>,
+}
+
+/// Same layout as [`Tcp6ConfigData`], but with a writable option pointer for
+/// `GetModeData`.
+#[repr(C)]
+struct FfiConfigData {
+    traffic_class: u8,
+    hop_limit: u8,
+    access_point: Tcp6AccessPoint,
+    control_option: *mut Tcp6Option,
+}
+
+#[repr(C)]
+struct FragmentData {
+    fragment_length: u32,
+    fragment_buffer: *mut c_void,
+}
+
+#[repr(C)]
+struct ReceiveData {
+    urgent_flag: bool,
+    data_length: u32,
+    fragment_count: u32,
+    fragment_table: [FragmentData; 1],
+}
+
+#[repr(C)]
+struct TransmitData {
+    push: bool,
+    urgent: bool,
+    data_length: u32,
+    fragment_count: u32,
+    fragment_table: [FragmentData; 1],
+}
+
+#[repr(C)]
+struct CompletionToken {
+    event: Event,
+    status: Status,
+}
+
+#[repr(C)]
+struct ListenToken {
+    completion_token: CompletionToken,
+    new_child_handle: Option<Handle>,
+}
+
+#[repr(C)]
+union Packet {
+    rx_data: *mut ReceiveData,
+    tx_data: *const TransmitData,
+}
+
+#[repr(C)]
+struct IoToken {
+    completion_token: CompletionToken,
+    packet: Packet,
+}
+
+#[repr(C)]
+struct CloseToken {
+    completion_token: CompletionToken,
+    abort_on_close: bool,
+}
+
+/// The TCPv6 protocol.
+///
+/// Instances of this protocol are created with [`Tcp6ServiceBinding`]. Each
+/// instance handles a single connection; a passive instance creates a new
+/// child handle with another instance for each accepted connection.
+#[repr(C)]
+#[unsafe_guid("46e44855-bd60-4ab7-ab0d-a679b9447d77")]
+#[derive(Protocol)]
+pub struct Tcp6 {
+    get_mode_data: unsafe extern "efiapi" fn(
+        this: &Self,
+        tcp6_state: *mut Tcp6ConnectionState,
+        tcp6_config_data: *mut FfiConfigData,
+        ip6_mode_data: *mut c_void,
+        mnp_config_data: *mut c_void,
+        snp_mode_data: *mut c_void,
+    ) -> Status,
+    configure: extern "efiapi" fn(this: &Self, tcp6_config_data: *const Tcp6ConfigData) -> Status,
+    connect: unsafe extern "efiapi" fn(this: &Self, token: *mut CompletionToken) -> Status,
+    accept: unsafe extern "efiapi" fn(this: &Self, token: *mut ListenToken) -> Status,
+    transmit: unsafe extern "efiapi" fn(this: &Self, token: *mut IoToken) -> Status,
+    receive: unsafe extern "efiapi" fn(this: &Self, token: *mut IoToken) -> Status,
+    close: unsafe extern "efiapi" fn(this: &Self, token: *mut CloseToken) -> Status,
+    cancel: unsafe extern "efiapi" fn(this: &Self, token: *mut CompletionToken) -> Status,
+    poll: extern "efiapi" fn(this: &Self) -> Status,
+}
+
+impl Tcp6 {
+    /// Returns the state of the connection.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_STARTED`]: the instance has not been configured.
+    pub fn state(&self) -> Result<Tcp6ConnectionState> {
+        let mut state = Tcp6ConnectionState::CLOSED;
+        unsafe {
+            (self.get_mode_data)(
+                self,
+                &mut state,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        }
+        .into_with_val(|| state)
+    }
+
+    /// Returns the current configuration, including the advanced options in
+    /// effect.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_STARTED`]: the instance has not been configured.
+    pub fn config_data(&self) -> Result<(Tcp6ConfigData<'static>, Tcp6Option)> {
+        let mut option = Tcp6Option::default();
+        let mut config_data = FfiConfigData {
+            traffic_class: 0,
+            hop_limit: 0,
+            access_point: Tcp6AccessPoint::default(),
+            control_option: &mut option,
+        };
+        unsafe {
+            (self.get_mode_data)(
+                self,
+                ptr::null_mut(),
+                &mut config_data,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        }
+        .into_with_val(|| {
+            let config_data = Tcp6ConfigData {
+                traffic_class: config_data.traffic_class,
+                hop_limit: config_data.hop_limit,
+                access_point: config_data.access_point,
+                control_option: None,
+            };
+            (config_data, option)
+        })
+    }
+
+    /// Configures the instance, or resets it if `config_data` is `None`.
+    /// Resetting aborts the connection without notifying the remote side.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NO_MAPPING`]: no station address is available yet, for
+    ///   example because address auto-configuration is still in progress.
+    /// * [`Status::ACCESS_DENIED`]: the instance is already configured and
+    ///   must be reset first.
+    /// * [`Status::INVALID_PARAMETER`]: the access point is not valid, for
+    ///   example an active instance without a remote address.
+    pub fn configure(&mut self, config_data: Option<&Tcp6ConfigData>) -> Result {
+        let config_data = config_data.map_or(ptr::null(), |data| data as *const _);
+        (self.configure)(self, config_data).into()
+    }
+
+    /// Polls the network interface for incoming and outgoing packets.
+    pub fn poll(&mut self) -> Result {
+        (self.poll)(self).into()
+    }
+
+    /// Connects an active instance to its remote endpoint and waits for the
+    /// connection to be established.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::TIMEOUT`]: the connection was not established within the
+    ///   connection timeout.
+    /// * [`Status::CONNECTION_REFUSED`]: the remote side refused the
+    ///   connection.
+    /// * [`Status::ACCESS_DENIED`]: the instance is passive, or not in the
+    ///   `CLOSED` state.
+    pub fn connect(&mut self, bt: &BootServices) -> Result {
+        let completion = CompletionEvent::new(bt)?;
+        let mut token = CompletionToken {
+            event: completion.event(),
+            status: Status::SUCCESS,
+        };
+        let token_ptr: *mut CompletionToken = &mut token;
+        unsafe { self.run(&completion, token_ptr, self.connect, token_ptr) }
+    }
+
+    /// Waits for an incoming connection on a passive instance, and returns
+    /// the child handle with the [`Tcp6`] instance for the new connection.
+    ///
+    /// The child must be destroyed with [`Tcp6ServiceBinding`] when it is no
+    /// longer needed.
+    pub fn accept(&mut self, bt: &BootServices) -> Result<Handle> {
+        let completion = CompletionEvent::new(bt)?;
+        let mut token = ListenToken {
+            completion_token: CompletionToken {
+                event: completion.event(),
+                status: Status::SUCCESS,
+            },
+            new_child_handle: None,
+        };
+        let token_ptr: *mut ListenToken = &mut token;
+        unsafe {
+            self.run(
+                &completion,
+                token_ptr,
+                self.accept,
+                ptr::addr_of_mut!((*token_ptr).completion_token),
+            )?;
+            ptr::read_volatile(ptr::addr_of!((*token_ptr).new_child_handle))
+                .ok_or_else(|| Status::COMPROMISED_DATA.into())
+        }
+    }
+
+    /// Sends `data` and waits until it has been queued for transmission.
+    ///
+    /// If `push` is set, the data is sent immediately instead of waiting for
+    /// more data to fill a segment.
+    pub fn transmit(&mut self, bt: &BootServices, data: &[u8], push: bool) -> Result {
+        let length = u32::try_from(data.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        let tx_data = TransmitData {
+            push,
+            urgent: false,
+            data_length: length,
+            fragment_count: 1,
+            fragment_table: [FragmentData {
+                fragment_length: length,
+                fragment_buffer: data.as_ptr() as *mut c_void,
+            }],
+        };
+
+        let completion = CompletionEvent::new(bt)?;
+        let mut token = IoToken {
+            completion_token: CompletionToken {
+                event: completion.event(),
+                status: Status::SUCCESS,
+            },
+            packet: Packet { tx_data: &tx_data },
+        };
+        let token_ptr: *mut IoToken = &mut token;
+        unsafe {
+            self.run(
+                &completion,
+                token_ptr,
+                self.transmit,
+                ptr::addr_of_mut!((*token_ptr).completion_token),
+            )
+        }
+    }
+
+    /// Waits for data and copies it to `buffer`, returning the number of
+    /// bytes received. Data that doesn't fit into `buffer` is kept for the
+    /// next call.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_STARTED`]: the instance has not been configured.
+    /// * [`Status::CONNECTION_FIN`]: the remote side closed the connection
+    ///   and all data has been received.
+    /// * [`Status::CONNECTION_RESET`]: the remote side reset the connection.
+    pub fn receive(&mut self, bt: &BootServices, buffer: &mut [u8]) -> Result<usize> {
+        let length = u32::try_from(buffer.len()).unwrap_or(u32::MAX);
+        let mut rx_data = ReceiveData {
+            urgent_flag: false,
+            data_length: length,
+            fragment_count: 1,
+            fragment_table: [FragmentData {
+                fragment_length: length,
+                fragment_buffer: buffer.as_mut_ptr().cast(),
+            }],
+        };
+
+        let completion = CompletionEvent::new(bt)?;
+        let mut token = IoToken {
+            completion_token: CompletionToken {
+                event: completion.event(),
+                status: Status::SUCCESS,
+            },
+            packet: Packet {
+                rx_data: &mut rx_data,
+            },
+        };
+        let token_ptr: *mut IoToken = &mut token;
+        unsafe {
+            self.run(
+                &completion,
+                token_ptr,
+                self.receive,
+                ptr::addr_of_mut!((*token_ptr).completion_token),
+            )?;
+        }
+        Ok(rx_data.data_length as usize)
+    }
+
+    /// Closes the connection and waits for the close to complete. If `abort`
+    /// is set, the connection is reset instead of closed gracefully, and
+    /// pending data is discarded.
+    pub fn close(&mut self, bt: &BootServices, abort: bool) -> Result {
+        let completion = CompletionEvent::new(bt)?;
+        let mut token = CloseToken {
+            completion_token: CompletionToken {
+                event: completion.event(),
+                status: Status::SUCCESS,
+            },
+            abort_on_close: abort,
+        };
+        let token_ptr: *mut CloseToken = &mut token;
+        unsafe {
+            self.run(
+                &completion,
+                token_ptr,
+                self.close,
+                ptr::addr_of_mut!((*token_ptr).completion_token),
+            )
+        }
+    }
+
+    /// Starts an operation with `token` and waits for it to complete,
+    /// cancelling it if waiting fails.
+    ///
+    /// `completion_token` must point to the completion token embedded at
+    /// the start of `token`.
+    unsafe fn run<T>(
+        &self,
+        completion: &CompletionEvent,
+        token: *mut T,
+        operation: unsafe extern "efiapi" fn(&Self, *mut T) -> Status,
+        completion_token: *mut CompletionToken,
+    ) -> Result {
+        completion.block_on(
+            || operation(self, token),
+            ptr::addr_of!((*completion_token).status),
+            || {
+                // The outcome is reported through the token.
+                let _ = (self.poll)(self);
+            },
+            Some(|| {
+                let _ = (self.cancel)(self, completion_token);
+            }),
+        )
+    }
+}
@@ -0,0 +1,288 @@
+//! UDPv6 protocol.
+
+use super::{service_binding, Ipv6Address};
+use crate::proto::completion::CompletionEvent;
+use crate::proto::Protocol;
+use crate::table::boot::BootServices;
+use crate::{unsafe_guid, Event, Result, Status};
+use core::ffi::c_void;
+use core::{ptr, slice};
+
+service_binding! {
+    /// Service binding protocol used to create and destroy [`Udp6`] instances.
+    Udp6ServiceBinding, "66ed4721-3c98-4d3e-81e3-d03dd39a7254"
+}
+
+/// Configuration of a [`Udp6`] instance.
+///
+/// Corresponds to the `EFI_UDP6_CONFIG_DATA` type in the C API.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct Udp6ConfigData {
+    /// Receive datagrams addressed to any station address.
+    pub accept_promiscuous: bool,
+    /// Receive datagrams addressed to any port.
+    pub accept_any_port: bool,
+    /// Allow other instances to use the same station port.
+    pub allow_duplicate_port: bool,
+    /// Traffic class field of transmitted datagrams.
+    pub traffic_class: u8,
+    /// Hop limit of transmitted datagrams.
+    pub hop_limit: u8,
+    /// Receive timeout in microseconds, or 0 for no timeout.
+    pub receive_timeout: u32,
+    /// Transmit timeout in microseconds, or 0 for no timeout.
+    pub transmit_timeout: u32,
+    /// Local address, or [`Ipv6Address::UNSPECIFIED`] to let the driver pick
+    /// one of the addresses of the interface.
+    pub station_address: Ipv6Address,
+    /// Local port, or 0 to use a random port.
+    pub station_port: u16,
+    /// Default remote address, or [`Ipv6Address::UNSPECIFIED`] to receive
+    /// datagrams from any address.
+    pub remote_address: Ipv6Address,
+    /// Default remote port, or 0 to receive datagrams from any port.
+    pub remote_port: u16,
+}
+
+/// Addresses and ports of a datagram.
+///
+/// Corresponds to the `EFI_UDP6_SESSION_DATA` type in the C API.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct Udp6SessionData {
+    /// Address of the sender.
+    pub source_address: Ipv6Address,
+    /// Port of the sender.
+    pub source_port: u16,
+    /// Address of the receiver.
+    pub destination_address: Ipv6Address,
+    /// Port of the receiver.
+    pub destination_port: u16,
+}
+
+#[repr(C)]
+struct FragmentData {
+    fragment_length: u32,
+    fragment_buffer: *mut c_void,
+}
+
+#[repr(C)]
+struct ReceiveData {
+    recycle_signal: Event,
+    udp_session: Udp6SessionData,
+    data_length: u32,
+    fragment_count: u32,
+    fragment_table: [FragmentData; 0],
+}
+
+#[repr(C)]
+struct TransmitData {
+    udp_session_data: *const Udp6SessionData,
+    data_length: u32,
+    fragment_count: u32,
+    fragment_table: [FragmentData; 1],
+}
+
+#[repr(C)]
+union Packet {
+    rx_data: *mut ReceiveData,
+    tx_data: *const TransmitData,
+}
+
+#[repr(C)]
+struct CompletionToken {
+    event: Event,
+    status: Status,
+    packet: Packet,
+}
+
+/// Information about a datagram received with [`Udp6::receive`].
+#[derive(Clone, Copy, Debug)]
+pub struct Udp6Datagram {
+    /// Addresses and ports of the datagram.
+    pub session: Udp6SessionData,
+    /// Length of the datagram. If this is larger than the receive buffer,
+    /// the datagram was truncated.
+    pub length: usize,
+}
+
+/// The UDPv6 protocol.
+///
+/// Instances of this protocol are created with [`Udp6ServiceBinding`].
+#[repr(C)]
+#[unsafe_guid("4f948815-b4b9-43cb-8a33-90e060b34955")]
+#[derive(Protocol)]
+pub struct Udp6 {
+    get_mode_data: extern "efiapi" fn(
+        this: &Self,
+        udp6_config_data: *mut Udp6ConfigData,
+        ip6_mode_data: *mut c_void,
+        mnp_config_data: *mut c_void,
+        snp_mode_data: *mut c_void,
+    ) -> Status,
+    configure: extern "efiapi" fn(this: &Self, udp_config_data: *const Udp6ConfigData) -> Status,
+    groups: extern "efiapi" fn(
+        this: &Self,
+        join_flag: bool,
+        multicast_address: *const Ipv6Address,
+    ) -> Status,
+    transmit: unsafe extern "efiapi" fn(this: &Self, token: *mut CompletionToken) -> Status,
+    receive: unsafe extern "efiapi" fn(this: &Self, token: *mut CompletionToken) -> Status,
+    cancel: unsafe extern "efiapi" fn(this: &Self, token: *mut CompletionToken) -> Status,
+    poll: extern "efiapi" fn(this: &Self) -> Status,
+}
+
+impl Udp6 {
+    /// Returns the current configuration.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_STARTED`]: the instance has not been configured.
+    pub fn config_data(&self) -> Result<Udp6ConfigData> {
+        let mut config_data = Udp6ConfigData::default();
+        (self.get_mode_data)(
+            self,
+            &mut config_data,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        )
+        .into_with_val(|| config_data)
+    }
+
+    /// Configures the instance, or resets it if `config_data` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NO_MAPPING`]: no station address is available yet, for
+    ///   example because address auto-configuration is still in progress.
+    /// * [`Status::ALREADY_STARTED`]: the instance is already configured and
+    ///   must be reset first.
+    /// * [`Status::ACCESS_DENIED`]: the station port is in use by another
+    ///   instance.
+    pub fn configure(&mut self, config_data: Option<&Udp6ConfigData>) -> Result {
+        let config_data = config_data.map_or(ptr::null(), |data| data as *const _);
+        (self.configure)(self, config_data).into()
+    }
+
+    /// Joins the multicast group `address`, or leaves it if `join` is false.
+    /// Passing `None` when leaving leaves all groups.
+    pub fn groups(&mut self, join: bool, address: Option<&Ipv6Address>) -> Result {
+        let address = address.map_or(ptr::null(), |address| address as *const _);
+        (self.groups)(self, join, address).into()
+    }
+
+    /// Polls the network interface for incoming and outgoing packets.
+    pub fn poll(&mut self) -> Result {
+        (self.poll)(self).into()
+    }
+
+    /// Sends `data` in a single datagram and waits for the transmission to
+    /// complete.
+    ///
+    /// If `session` is `None`, the datagram is sent to the default remote
+    /// address and port from the configuration.
+    pub fn transmit(
+        &mut self,
+        bt: &BootServices,
+        session: Option<&Udp6SessionData>,
+        data: &[u8],
+    ) -> Result {
+        let length = u32::try_from(data.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        let tx_data = TransmitData {
+            udp_session_data: session.map_or(ptr::null(), |session| session as *const _),
+            data_length: length,
+            fragment_count: 1,
+            fragment_table: [FragmentData {
+                fragment_length: length,
+                fragment_buffer: data.as_ptr() as *mut c_void,
+            }],
+        };
+
+        let completion = CompletionEvent::new(bt)?;
+        let mut token = CompletionToken {
+            event: completion.event(),
+            status: Status::SUCCESS,
+            packet: Packet { tx_data: &tx_data },
+        };
+        self.run(&completion, &mut token, self.transmit)
+    }
+
+    /// Waits for a datagram and copies it to `buffer`.
+    ///
+    /// Datagrams larger than `buffer` are truncated; compare
+    /// [`Udp6Datagram::length`] to the buffer length to detect that.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::TIMEOUT`]: no datagram was received within the receive
+    ///   timeout.
+    /// * [`Status::ICMP_ERROR`]: an ICMP error was received instead.
+    pub fn receive(&mut self, bt: &BootServices, buffer: &mut [u8]) -> Result<Udp6Datagram> {
+        let completion = CompletionEvent::new(bt)?;
+        let mut token = CompletionToken {
+            event: completion.event(),
+            status: Status::SUCCESS,
+            packet: Packet {
+                rx_data: ptr::null_mut(),
+            },
+        };
+        self.run(&completion, &mut token, self.receive)?;
+
+        // Safety: the driver sets `rx_data` when the receive succeeds, and
+        // keeps it valid until the recycle event is signaled.
+        unsafe {
+            let rx_data = &*token.packet.rx_data;
+            let fragments = slice::from_raw_parts(
+                rx_data.fragment_table.as_ptr(),
+                rx_data.fragment_count as usize,
+            );
+
+            let mut copied = 0;
+            for fragment in fragments {
+                if fragment.fragment_buffer.is_null() {
+                    continue;
+                }
+                let fragment = slice::from_raw_parts(
+                    fragment.fragment_buffer.cast::<u8>(),
+                    fragment.fragment_length as usize,
+                );
+                let len = fragment.len().min(buffer.len() - copied);
+                buffer[copied..copied + len].copy_from_slice(&fragment[..len]);
+                copied += len;
+            }
+
+            let datagram = Udp6Datagram {
+                session: rx_data.udp_session,
+                length: rx_data.data_length as usize,
+            };
+            bt.signal_event(&rx_data.recycle_signal)?;
+            Ok(datagram)
+        }
+    }
+
+    /// Starts an operation with `token` and waits for it to complete,
+    /// cancelling it if waiting fails.
+    fn run(
+        &self,
+        completion: &CompletionEvent,
+        token: &mut CompletionToken,
+        operation: unsafe extern "efiapi" fn(&Self, *mut CompletionToken) -> Status,
+    ) -> Result {
+        let token: *mut CompletionToken = token;
+        unsafe {
+            completion.block_on(
+                || operation(self, token),
+                ptr::addr_of!((*token).status),
+                || {
+                    // The outcome is reported through the token.
+                    let _ = (self.poll)(self);
+                },
+                Some(|| {
+                    let _ = (self.cancel)(self, token);
+                }),
+            )
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! Wireless network protocols.
+//!
+//! [`WirelessMacConnection2`] is installed on the handles of wireless
+//! network interfaces, and can list the networks in range. An instance of
+//! [`Supplicant`], created with [`SupplicantServiceBinding`], reports the
+//! association state of the interface.
+//!
+//! Most machines have no wireless interface with UEFI drivers, so these
+//! protocols are usually not installed. Looking them up then fails with
+//! [`Status::NOT_FOUND`], for example:
+//!
+//! ```no_run
+//! use uefi::prelude::BootServices;
+//! use uefi::proto::network::wifi::WirelessMacConnection2;
+//!
+//! fn has_wifi(bt: &BootServices) -> bool {
+//!     bt.get_handle_for_protocol::<WirelessMacConnection2>().is_ok()
+//! }
+//! ```
+//!
+//! Connecting to networks is not supported yet.
+
+use super::service_binding;
+#[cfg(feature = "exts")]
+use crate::proto::completion::CompletionEvent;
+use crate::proto::Protocol;
+#[cfg(feature = "exts")]
+use crate::table::boot::BootServices;
+use crate::{unsafe_guid, Event, Result, Status};
+#[cfg(feature = "exts")]
+use alloc_api::vec::Vec;
+use core::ffi::c_void;
+use core::fmt::{self, Debug, Formatter};
+#[cfg(feature = "exts")]
+use core::{ptr, slice};
+
+/// Maximum length of an SSID, in bytes.
+const MAX_SSID_LENGTH: usize = 32;
+
+/// The name of a wireless network, up to 32 arbitrary bytes.
+///
+/// Corresponds to the `EFI_80211_SSID` type in the C API.
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct Ssid {
+    length: u8,
+    bytes: [u8; MAX_SSID_LENGTH],
+}
+
+impl Ssid {
+    /// Create an SSID from its bytes, or return `None` if there are more
+    /// than 32.
+    pub fn new(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() > MAX_SSID_LENGTH {
+            return None;
+        }
+        let mut ssid = Self {
+            length: bytes.len() as u8,
+            bytes: [0; MAX_SSID_LENGTH],
+        };
+        ssid.bytes[..bytes.len()].copy_from_slice(bytes);
+        Some(ssid)
+    }
+
+    /// The bytes of the SSID. They are usually, but not necessarily, UTF-8
+    /// text.
+    pub fn as_bytes(&self) -> &[u8] {
+        let length = usize::from(self.length).min(MAX_SSID_LENGTH);
+        &self.bytes[..length]
+    }
+}
+
+impl Debug for Ssid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match core::str::from_utf8(self.as_bytes()) {
+            Ok(name) => write!(f, "Ssid({:?})", name),
+            Err(_) => write!(f, "Ssid({:x?})", self.as_bytes()),
+        }
+    }
+}
+
+newtype_enum! {
+    /// Type of a wireless network.
+    pub enum BssType: u32 => {
+        /// A network with an access point.
+        INFRASTRUCTURE = 0,
+        /// An ad hoc network, without access point.
+        INDEPENDENT = 1,
+        /// A mesh network.
+        MESH = 2,
+        /// Any type.
+        ANY = 3,
+    }
+}
+
+/// A wireless network in range, as returned by
+/// [`WirelessMacConnection2::get_networks`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WirelessNetwork {
+    /// Type of the network.
+    pub bss_type: BssType,
+    /// Name of the network.
+    pub ssid: Ssid,
+    /// Quality of the signal, from 0 to 100.
+    pub quality: u8,
+}
+
+/// `EFI_80211_GET_NETWORKS_DATA`, without SSIDs to look for hidden
+/// networks.
+#[repr(C)]
+struct GetNetworksData {
+    ssid_count: u32,
+    ssid_list: [Ssid; 1],
+}
+
+/// `EFI_80211_NETWORK`.
+#[repr(C)]
+struct Network {
+    bss_type: BssType,
+    ssid: Ssid,
+    akm_suite: *const c_void,
+    cipher_suite: *const c_void,
+}
+
+/// `EFI_80211_NETWORK_DESCRIPTION`.
+#[repr(C)]
+struct NetworkDescription {
+    network: Network,
+    quality: u8,
+}
+
+/// `EFI_80211_GET_NETWORKS_RESULT`, followed by the descriptions.
+#[repr(C)]
+struct GetNetworksResult {
+    count: u8,
+    descriptions: [NetworkDescription; 0],
+}
+
+/// `EFI_80211_GET_NETWORKS_TOKEN`.
+#[repr(C)]
+struct GetNetworksToken {
+    event: Event,
+    status: Status,
+    data: *mut GetNetworksData,
+    result: *mut GetNetworksResult,
+}
+
+/// The wireless MAC connection protocol, version 2.
+///
+/// This protocol is installed on wireless network interfaces, and is used
+/// to scan for networks.
+#[repr(C)]
+#[unsafe_guid("1b0fb9bf-699d-4fdd-a7c3-2546681bf63b")]
+#[derive(Protocol)]
+pub struct WirelessMacConnection2 {
+    get_networks:
+        unsafe extern "efiapi" fn(this: &mut Self, token: *mut GetNetworksToken) -> Status,
+    connect_network: unsafe extern "efiapi" fn(this: &mut Self, token: *mut c_void) -> Status,
+    disconnect_network: unsafe extern "efiapi" fn(this: &mut Self, token: *mut c_void) -> Status,
+}
+
+impl WirelessMacConnection2 {
+    /// Scans for networks in range, and waits for the scan to complete.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the interface can't scan for networks.
+    /// * [`Status::NOT_READY`]: a scan is already in progress.
+    /// * [`Status::DEVICE_ERROR`]: the scan failed.
+    #[cfg(feature = "exts")]
+    pub fn get_networks(&mut self, bt: &BootServices) -> Result<Vec<WirelessNetwork>> {
+        let completion = CompletionEvent::new(bt)?;
+        let mut data = GetNetworksData {
+            ssid_count: 0,
+            ssid_list: [Ssid::new(&[]).unwrap()],
+        };
+        let mut token = GetNetworksToken {
+            event: completion.event(),
+            status: Status::SUCCESS,
+            data: &mut data,
+            result: ptr::null_mut(),
+        };
+        let token_ptr: *mut GetNetworksToken = &mut token;
+
+        unsafe {
+            // The protocol has no function to cancel a scan, so keep waiting
+            // if checking the event fails.
+            completion.block_on(
+                || (self.get_networks)(self, token_ptr),
+                ptr::addr_of!((*token_ptr).status),
+                || {},
+                None::<fn()>,
+            )?;
+
+            let result = ptr::read_volatile(ptr::addr_of!((*token_ptr).result));
+            if result.is_null() {
+                return Ok(Vec::new());
+            }
+            let descriptions = slice::from_raw_parts(
+                ptr::addr_of!((*result).descriptions).cast::<NetworkDescription>(),
+                usize::from((*result).count),
+            );
+            let networks = descriptions
+                .iter()
+                .map(|description| WirelessNetwork {
+                    bss_type: description.network.bss_type,
+                    ssid: description.network.ssid,
+                    quality: description.quality,
+                })
+                .collect();
+            // The result is allocated by the driver, and freed by the caller.
+            bt.free_pool(result.cast())?;
+            Ok(networks)
+        }
+    }
+}
+
+service_binding! {
+    /// Service binding protocol used to create and destroy [`Supplicant`]
+    /// instances.
+    SupplicantServiceBinding, "45bcd98e-59ad-4174-9546-344a07485898"
+}
+
+newtype_enum! {
+    /// Association state of a wireless interface.
+    pub enum LinkState: u32 => {
+        /// Not connected to a network.
+        UNAUTHENTICATED_UNASSOCIATED = 0,
+        /// Authenticated with an access point, but not associated.
+        AUTHENTICATED_UNASSOCIATED = 1,
+        /// Associated, while the keys are being negotiated.
+        PENDING_RSN_AUTHENTICATION = 2,
+        /// Connected to a network.
+        AUTHENTICATED_ASSOCIATED = 3,
+    }
+}
+
+newtype_enum! {
+    /// `EFI_SUPPLICANT_DATA_TYPE`, only with the types that can be read
+    /// with this crate.
+    enum SupplicantDataType: u32 => {
+        TARGET_SSID_NAME = 4,
+        LINK_STATE = 10,
+    }
+}
+
+/// The supplicant protocol.
+///
+/// The supplicant handles the authentication of a wireless interface with
+/// the network it is connected to. Instances are created with
+/// [`SupplicantServiceBinding`].
+#[repr(C)]
+#[unsafe_guid("54fcc43e-aa89-4333-9a85-cdea24051e9e")]
+#[derive(Protocol)]
+pub struct Supplicant {
+    build_response_packet: unsafe extern "efiapi" fn(
+        this: &mut Self,
+        request_buffer: *const u8,
+        request_buffer_size: usize,
+        buffer: *mut u8,
+        buffer_size: *mut usize,
+    ) -> Status,
+    process_packet: unsafe extern "efiapi" fn(
+        this: &mut Self,
+        fragment_table: *mut *mut c_void,
+        fragment_count: *mut u32,
+        crypt_mode: u32,
+    ) -> Status,
+    set_data: unsafe extern "efiapi" fn(
+        this: &mut Self,
+        data_type: SupplicantDataType,
+        data: *const c_void,
+        data_size: usize,
+    ) -> Status,
+    get_data: unsafe extern "efiapi" fn(
+        this: &mut Self,
+        data_type: SupplicantDataType,
+        data: *mut u8,
+        data_size: *mut usize,
+    ) -> Status,
+}
+
+impl Supplicant {
+    /// Returns the association state of the interface.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the supplicant doesn't track the state.
+    pub fn link_state(&mut self) -> Result<LinkState> {
+        let mut state = LinkState::UNAUTHENTICATED_UNASSOCIATED;
+        unsafe { self.get_data_into(SupplicantDataType::LINK_STATE, &mut state) }?;
+        Ok(state)
+    }
+
+    /// Returns the name of the network the interface is connected or
+    /// connecting to.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: no network has been selected.
+    pub fn target_ssid(&mut self) -> Result<Ssid> {
+        let mut ssid = Ssid::new(&[]).unwrap();
+        unsafe { self.get_data_into(SupplicantDataType::TARGET_SSID_NAME, &mut ssid) }?;
+        Ok(ssid)
+    }
+
+    /// Read data of a fixed size into `value`.
+    ///
+    /// # Safety
+    ///
+    /// `T` must be the type of the data.
+    unsafe fn get_data_into<T>(&mut self, data_type: SupplicantDataType, value: &mut T) -> Result {
+        let mut size = core::mem::size_of::<T>();
+        (self.get_data)(self, data_type, (value as *mut T).cast(), &mut size).into()
+    }
+}
@@ -0,0 +1,128 @@
+use crate::proto::device_path::{DevicePath, FfiDevicePath};
+use crate::proto::Protocol;
+use crate::{unsafe_guid, Result, Status};
+use core::ffi::c_void;
+use core::{ptr, slice};
+
+/// Protocol for inspecting images whose loading was deferred by the firmware.
+///
+/// The platform security policy may defer the loading of an image, for
+/// example because it comes from a source that is not trusted yet. This
+/// protocol gives access to the list of deferred images, which is useful to
+/// diagnose why a given binary was not started.
+///
+/// This corresponds to the `EFI_DEFERRED_IMAGE_LOAD_PROTOCOL` type in the
+/// Platform Initialization specification.
+#[repr(C)]
+#[unsafe_guid("15853d7c-3ddf-43e0-a1cb-ebf85b8f872c")]
+#[derive(Protocol)]
+pub struct DeferredImageLoad {
+    get_image_info: unsafe extern "efiapi" fn(
+        this: *const Self,
+        image_index: usize,
+        image_device_path: *mut *const FfiDevicePath,
+        image: *mut *const c_void,
+        image_size: *mut usize,
+        boot_option: *mut bool,
+    ) -> Status,
+}
+
+impl DeferredImageLoad {
+    /// Get information about the deferred image at `index`.
+    ///
+    /// Returns `Ok(None)` once `index` is past the end of the list, so all
+    /// deferred images can be listed by incrementing `index` from zero until
+    /// `None` is returned (see also [`images`]).
+    ///
+    /// The returned data is owned by the firmware. Its lifetime is tied to the
+    /// borrow of the protocol: it must not be used after the protocol has been
+    /// closed.
+    ///
+    /// [`images`]: Self::images
+    pub fn get_image_info(&self, index: usize) -> Result<Option<DeferredImage<'_>>> {
+        let mut device_path = ptr::null();
+        let mut image = ptr::null();
+        let mut image_size = 0;
+        let mut boot_option = false;
+
+        let status = unsafe {
+            (self.get_image_info)(
+                self,
+                index,
+                &mut device_path,
+                &mut image,
+                &mut image_size,
+                &mut boot_option,
+            )
+        };
+
+        match status {
+            Status::NOT_FOUND => Ok(None),
+            status => status.into_with_val(|| unsafe {
+                // Some implementations return null pointers for images they
+                // don't have a path or the contents of.
+                let device_path =
+                    (!device_path.is_null()).then(|| DevicePath::from_ffi_ptr(device_path));
+                let image = if image.is_null() {
+                    &[][..]
+                } else {
+                    slice::from_raw_parts(image.cast::<u8>(), image_size)
+                };
+                Some(DeferredImage {
+                    device_path,
+                    image,
+                    boot_option,
+                })
+            }),
+        }
+    }
+
+    /// Returns an iterator over all the deferred images.
+    ///
+    /// The iterator stops at the first error, which is returned as its last
+    /// item.
+    pub fn images(&self) -> impl Iterator<Item = Result<DeferredImage<'_>>> + '_ {
+        let mut index = 0;
+        let mut done = false;
+        core::iter::from_fn(move || {
+            if done {
+                return None;
+            }
+            let info = self.get_image_info(index).transpose();
+            match info {
+                Some(Ok(_)) => index += 1,
+                _ => done = true,
+            }
+            info
+        })
+    }
+}
+
+/// Information about an image whose loading was deferred.
+///
+/// See [`DeferredImageLoad::get_image_info`].
+#[derive(Debug)]
+pub struct DeferredImage<'a> {
+    device_path: Option<&'a DevicePath>,
+    image: &'a [u8],
+    boot_option: bool,
+}
+
+impl<'a> DeferredImage<'a> {
+    /// The device path of the image, if the firmware provided one.
+    pub fn device_path(&self) -> Option<&'a DevicePath> {
+        self.device_path
+    }
+
+    /// The contents of the image. This is empty if the firmware didn't
+    /// provide them.
+    pub fn image(&self) -> &'a [u8] {
+        self.image
+    }
+
+    /// Returns `true` if the image was loaded from a boot option, and
+    /// `false` if it was loaded by the firmware (e.g. an option ROM).
+    pub fn is_boot_option(&self) -> bool {
+        self.boot_option
+    }
+}
@@ -0,0 +1,270 @@
+//! `Hash2` protocol.
+//!
+//! The [`Hash2`] protocol computes hashes in the firmware, which may use
+//! hardware acceleration. Instances of the protocol are created with
+//! [`Hash2ServiceBinding`]; [`hash_all`] takes care of this for one-shot
+//! hashing.
+
+use crate::data_types::Guid;
+use crate::proto::network::service_binding;
+use crate::proto::Protocol;
+use crate::{unsafe_guid, Result, Status};
+#[cfg(feature = "exts")]
+use {
+    crate::alloc_api::vec,
+    crate::alloc_api::vec::Vec,
+    crate::table::boot::{BootServices, OpenProtocolAttributes, OpenProtocolParams},
+};
+
+service_binding! {
+    /// Service binding protocol used to create and destroy [`Hash2`]
+    /// instances.
+    Hash2ServiceBinding, "da836f8d-217f-4ca0-99c2-1ca4e16077ea"
+}
+
+/// The size in bytes of the largest hash supported by the protocol, which
+/// is the size of a SHA-512 hash.
+pub const MAX_HASH_SIZE: usize = 64;
+
+newtype_enum! {
+    /// Hash algorithms that may be supported by the [`Hash2`] protocol.
+    ///
+    /// Firmware doesn't have to support all of them; use
+    /// [`Hash2::get_hash_size`] to check whether an algorithm is supported.
+    pub enum HashAlgorithm: Guid => {
+        /// SHA-1, with a 20-byte hash.
+        SHA1 = Guid::from_values(
+            0x2ae9d80f,
+            0x3fb2,
+            0x4095,
+            0xb7b1,
+            0xe93157b946b6,
+        ),
+
+        /// SHA-224, with a 28-byte hash.
+        SHA224 = Guid::from_values(
+            0x8df01a06,
+            0x9bd5,
+            0x4bf7,
+            0xb021,
+            0xdb4fd9ccf45b,
+        ),
+
+        /// SHA-256, with a 32-byte hash.
+        SHA256 = Guid::from_values(
+            0x51aa59de,
+            0xfdf2,
+            0x4ea3,
+            0xbc63,
+            0x875fb7842ee9,
+        ),
+
+        /// SHA-384, with a 48-byte hash.
+        SHA384 = Guid::from_values(
+            0xefa96432,
+            0xde33,
+            0x4dd2,
+            0xaee6,
+            0x328c33df777a,
+        ),
+
+        /// SHA-512, with a 64-byte hash.
+        SHA512 = Guid::from_values(
+            0xcaa4381e,
+            0x750c,
+            0x4770,
+            0xb870,
+            0x7a23b4e42130,
+        ),
+
+        /// MD5, with a 16-byte hash.
+        MD5 = Guid::from_values(
+            0x0af7c79c,
+            0x65b5,
+            0x4319,
+            0xb0ae,
+            0x44ec484e4ad7,
+        ),
+    }
+}
+
+/// `EFI_HASH2_OUTPUT`, a union of the hashes of all algorithms.
+#[repr(C)]
+struct Hash2Output([u8; MAX_HASH_SIZE]);
+
+/// Hash2 protocol.
+#[repr(C)]
+#[unsafe_guid("55b1d734-c5e1-49db-9647-b16afb0e305b")]
+#[derive(Protocol)]
+pub struct Hash2 {
+    get_hash_size: unsafe extern "efiapi" fn(
+        this: &Hash2,
+        algorithm: *const HashAlgorithm,
+        hash_size: *mut usize,
+    ) -> Status,
+    hash: unsafe extern "efiapi" fn(
+        this: &mut Hash2,
+        algorithm: *const HashAlgorithm,
+        message: *const u8,
+        message_size: usize,
+        hash: *mut Hash2Output,
+    ) -> Status,
+    hash_init:
+        unsafe extern "efiapi" fn(this: &mut Hash2, algorithm: *const HashAlgorithm) -> Status,
+    hash_update: unsafe extern "efiapi" fn(
+        this: &mut Hash2,
+        message: *const u8,
+        message_size: usize,
+    ) -> Status,
+    hash_final: unsafe extern "efiapi" fn(this: &mut Hash2, hash: *mut Hash2Output) -> Status,
+}
+
+impl Hash2 {
+    /// Returns the size in bytes of the hashes computed with `algorithm`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the algorithm is not supported.
+    pub fn get_hash_size(&self, algorithm: HashAlgorithm) -> Result<usize> {
+        let mut hash_size = 0;
+        unsafe { (self.get_hash_size)(self, &algorithm, &mut hash_size) }
+            .into_with_val(|| hash_size)
+    }
+
+    /// Computes the hash of `data` with `algorithm`, writes it to the start
+    /// of `digest`, and returns its size.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the algorithm is not supported.
+    /// * [`Status::BUFFER_TOO_SMALL`]: `digest` is smaller than the hash.
+    pub fn hash(
+        &mut self,
+        algorithm: HashAlgorithm,
+        data: &[u8],
+        digest: &mut [u8],
+    ) -> Result<usize> {
+        let size = self.get_hash_size(algorithm)?;
+        if digest.len() < size {
+            return Err(Status::BUFFER_TOO_SMALL.into());
+        }
+
+        let mut output = Hash2Output([0; MAX_HASH_SIZE]);
+        unsafe { (self.hash)(self, &algorithm, data.as_ptr(), data.len(), &mut output) }
+            .into_with_val(|| output.copy_to(digest, size))
+    }
+
+    /// Starts hashing data with `algorithm`, through calls to
+    /// [`hash_update`] followed by a call to [`hash_final`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the algorithm is not supported.
+    /// * [`Status::ALREADY_STARTED`]: hashing was started already, and
+    ///   hasn't been finished with [`hash_final`].
+    ///
+    /// [`hash_update`]: Self::hash_update
+    /// [`hash_final`]: Self::hash_final
+    pub fn hash_init(&mut self, algorithm: HashAlgorithm) -> Result {
+        unsafe { (self.hash_init)(self, &algorithm) }.into()
+    }
+
+    /// Adds `data` to the hash started with [`hash_init`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_READY`]: hashing wasn't started with [`hash_init`].
+    ///
+    /// [`hash_init`]: Self::hash_init
+    pub fn hash_update(&mut self, data: &[u8]) -> Result {
+        unsafe { (self.hash_update)(self, data.as_ptr(), data.len()) }.into()
+    }
+
+    /// Finishes the hash started with [`hash_init`], and writes it to
+    /// `digest`.
+    ///
+    /// The protocol doesn't report the size of the hash, so `digest` should
+    /// be as large as the size returned by [`get_hash_size`] for the
+    /// algorithm that was passed to [`hash_init`]; only `digest.len()`
+    /// bytes of the hash are written.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BAD_BUFFER_SIZE`]: `digest` is larger than
+    ///   [`MAX_HASH_SIZE`]. Hashing is not finished in this case.
+    /// * [`Status::NOT_READY`]: hashing wasn't started with [`hash_init`].
+    ///
+    /// [`hash_init`]: Self::hash_init
+    /// [`get_hash_size`]: Self::get_hash_size
+    pub fn hash_final(&mut self, digest: &mut [u8]) -> Result {
+        if digest.len() > MAX_HASH_SIZE {
+            return Err(Status::BAD_BUFFER_SIZE.into());
+        }
+
+        let size = digest.len();
+        let mut output = Hash2Output([0; MAX_HASH_SIZE]);
+        unsafe { (self.hash_final)(self, &mut output) }.into_with_val(|| {
+            output.copy_to(digest, size);
+        })
+    }
+}
+
+impl Hash2Output {
+    /// Copies the first `size` bytes of the hash to `digest`, and returns
+    /// `size`.
+    fn copy_to(&self, digest: &mut [u8], size: usize) -> usize {
+        digest[..size].copy_from_slice(&self.0[..size]);
+        size
+    }
+}
+
+/// Computes the hash of `data` with `algorithm` in the firmware.
+///
+/// This creates a [`Hash2`] instance with the first [`Hash2ServiceBinding`],
+/// and destroys it again afterwards.
+///
+/// # Errors
+///
+/// * [`Status::UNSUPPORTED`]: the firmware doesn't have the `Hash2`
+///   protocol, or doesn't support the algorithm. Callers can fall back to
+///   hashing in software in this case.
+#[cfg(feature = "exts")]
+pub fn hash_all(bt: &BootServices, algorithm: HashAlgorithm, data: &[u8]) -> Result<Vec<u8>> {
+    let handle = bt
+        .get_handle_for_protocol::<Hash2ServiceBinding>()
+        .map_err(|_| Status::UNSUPPORTED)?;
+    let service_binding = unsafe {
+        bt.open_protocol::<Hash2ServiceBinding>(
+            OpenProtocolParams {
+                handle,
+                agent: bt.image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )?
+    };
+
+    let child = service_binding.create_scoped_child()?;
+    let result = unsafe {
+        bt.open_protocol::<Hash2>(
+            OpenProtocolParams {
+                handle: child.handle(),
+                agent: bt.image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )
+    }
+    .and_then(|mut hash2| {
+        let mut digest = vec![0; MAX_HASH_SIZE];
+        let size = hash2.hash(algorithm, data, &mut digest)?;
+        digest.truncate(size);
+        Ok(digest)
+    });
+
+    // The hash error is more relevant than a failure to destroy the child.
+    let destroyed = child.destroy();
+    let digest = result?;
+    destroyed?;
+    Ok(digest)
+}
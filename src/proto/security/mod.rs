@@ -1,4 +1,10 @@
 //! Protocols related to secure technologies.
 
+mod deferred_image_load;
+mod hash2;
 mod memory_protection;
+pub use deferred_image_load::{DeferredImage, DeferredImageLoad};
+#[cfg(feature = "exts")]
+pub use hash2::hash_all;
+pub use hash2::{Hash2, Hash2ServiceBinding, HashAlgorithm, MAX_HASH_SIZE};
 pub use memory_protection::MemoryProtection;
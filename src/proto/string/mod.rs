@@ -0,0 +1,12 @@
+//! String protocols.
+//!
+//! The protocols provide some string operations like
+//! lexical comparison and regular expression matching.
+
+mod unicode_collation;
+pub use unicode_collation::{StrConversionError, UnicodeCollation};
+
+#[cfg(feature = "exts")]
+mod regex;
+#[cfg(feature = "exts")]
+pub use regex::{Capture, RegexSyntaxType, RegularExpression};
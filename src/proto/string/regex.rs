@@ -0,0 +1,192 @@
+use crate::alloc_api::vec::Vec;
+use crate::data_types::Guid;
+use crate::proto::Protocol;
+use crate::table::boot::{BootServices, PoolGuard};
+use crate::{unsafe_guid, CStr16, CString16, Char16, Result, Status};
+use core::{mem, ptr, slice};
+
+newtype_enum! {
+    /// Syntax of a regular expression used with [`RegularExpression`].
+    pub enum RegexSyntaxType: Guid => {
+        /// POSIX extended regular expressions, as used by `egrep`.
+        POSIX_EXTENDED = Guid::from_values(
+            0x5f05b20f,
+            0x4a56,
+            0xc231,
+            0xfa0b,
+            0xa7b1f110041d,
+        ),
+
+        /// Perl compatible regular expressions.
+        PERL = Guid::from_values(
+            0x63e60a51,
+            0x497d,
+            0xd427,
+            0xc4a5,
+            0xb8abdc3aaeb6,
+        ),
+
+        /// ECMAScript regular expressions, as defined by ECMA-262.
+        ECMA_262 = Guid::from_values(
+            0x9a473a4a,
+            0x4ceb,
+            0xb95a,
+            0x415e,
+            0x5ba0bc639b2e,
+        ),
+    }
+}
+
+/// A capture returned by the firmware, pointing into the matched string.
+#[repr(C)]
+struct RegexCapture {
+    capture_ptr: *const Char16,
+    length: usize,
+}
+
+/// A part of a string matched by [`RegularExpression::match_string`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct Capture {
+    /// The matched text.
+    pub text: CString16,
+    /// Offset of the matched text in the string, in characters.
+    pub start: usize,
+    /// Length of the matched text, in characters.
+    pub length: usize,
+}
+
+/// Protocol for matching strings against regular expressions.
+///
+/// Few firmware implementations provide this protocol, so callers should
+/// be prepared for it to be missing.
+///
+/// This corresponds to the `EFI_REGULAR_EXPRESSION_PROTOCOL` type in the C
+/// API.
+#[repr(C)]
+#[unsafe_guid("b3f79d9a-436c-dc11-b052-cd85df524ce6")]
+#[derive(Protocol)]
+pub struct RegularExpression {
+    match_string: unsafe extern "efiapi" fn(
+        this: &Self,
+        string: *const Char16,
+        pattern: *const Char16,
+        syntax_type: *const Guid,
+        result: &mut bool,
+        captures: &mut *mut RegexCapture,
+        captures_count: &mut usize,
+    ) -> Status,
+    get_info: unsafe extern "efiapi" fn(
+        this: &Self,
+        syntax_type_list_size: &mut usize,
+        syntax_type_list: *mut Guid,
+    ) -> Status,
+}
+
+impl RegularExpression {
+    /// Returns the regular expression syntaxes supported by the firmware.
+    pub fn get_info(&self) -> Result<Vec<RegexSyntaxType>> {
+        let mut size = 0;
+        let status = unsafe { (self.get_info)(self, &mut size, ptr::null_mut()) };
+        if status != Status::BUFFER_TOO_SMALL {
+            Result::from(status)?;
+            return Ok(Vec::new());
+        }
+
+        let mut list = Vec::with_capacity(size / mem::size_of::<Guid>());
+        unsafe {
+            Result::from((self.get_info)(self, &mut size, list.as_mut_ptr()))?;
+            list.set_len(size / mem::size_of::<Guid>());
+        }
+        Ok(list.into_iter().map(RegexSyntaxType).collect())
+    }
+
+    /// Matches `string` against `pattern`, returning the captures.
+    ///
+    /// If the string doesn't match, the returned list is empty. Otherwise,
+    /// the first capture is the whole match, followed by one capture per
+    /// group of the pattern. Groups that didn't take part in the match are
+    /// returned as empty captures at the start of the string.
+    ///
+    /// If `syntax` is `None`, [`RegexSyntaxType::POSIX_EXTENDED`] is used if
+    /// supported, and the firmware default otherwise.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the syntax is not supported.
+    /// * [`Status::INVALID_PARAMETER`]: the pattern is not valid.
+    /// * [`Status::COMPROMISED_DATA`]: the firmware returned a capture
+    ///   outside of the string.
+    pub fn match_string(
+        &self,
+        bt: &BootServices,
+        pattern: &CStr16,
+        string: &CStr16,
+        syntax: Option<RegexSyntaxType>,
+    ) -> Result<Vec<Capture>> {
+        let syntax = match syntax {
+            Some(syntax) => Some(syntax),
+            None => self
+                .get_info()?
+                .into_iter()
+                .find(|&syntax| syntax == RegexSyntaxType::POSIX_EXTENDED),
+        };
+        let syntax_ptr = syntax
+            .as_ref()
+            .map_or(ptr::null(), |syntax| &syntax.0 as *const Guid);
+
+        let mut matched = false;
+        let mut captures = ptr::null_mut();
+        let mut captures_count = 0;
+        Result::from(unsafe {
+            (self.match_string)(
+                self,
+                string.as_ptr(),
+                pattern.as_ptr(),
+                syntax_ptr,
+                &mut matched,
+                &mut captures,
+                &mut captures_count,
+            )
+        })?;
+
+        // The capture array is allocated by the firmware, but the captures
+        // themselves point into `string`.
+        let _guard = PoolGuard::new(bt, captures.cast());
+        if !matched || captures.is_null() {
+            return Ok(Vec::new());
+        }
+
+        let chars = string.to_u16_slice();
+        let captures = unsafe { slice::from_raw_parts(captures, captures_count) };
+        captures
+            .iter()
+            .map(|capture| {
+                let (start, length) = if capture.capture_ptr.is_null() {
+                    (0, 0)
+                } else {
+                    let offset =
+                        (capture.capture_ptr as usize).wrapping_sub(chars.as_ptr() as usize);
+                    let start = offset / mem::size_of::<Char16>();
+                    if offset % mem::size_of::<Char16>() != 0
+                        || start > chars.len()
+                        || capture.length > chars.len() - start
+                    {
+                        return Err(Status::COMPROMISED_DATA.into());
+                    }
+                    (start, capture.length)
+                };
+
+                let mut text = Vec::with_capacity(length + 1);
+                text.extend_from_slice(&chars[start..start + length]);
+                text.push(0);
+                // A part of a valid string is a valid string.
+                let text = CString16::try_from(text).unwrap();
+                Ok(Capture {
+                    text,
+                    start,
+                    length,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,162 @@
+use crate::proto::Protocol;
+use crate::{unsafe_guid, CStr16, CStr8, Char16, Char8};
+use core::cmp::Ordering;
+
+/// Protocol for performing locale-aware string operations, such as
+/// case-insensitive comparison and wildcard matching.
+///
+/// The firmware may install several instances of this protocol, one per
+/// supported language. Use [`supports_language`] to pick the right one.
+///
+/// This corresponds to the `EFI_UNICODE_COLLATION2_PROTOCOL` type in the C API.
+///
+/// [`supports_language`]: Self::supports_language
+#[repr(C)]
+#[unsafe_guid("a4c751fc-23ae-4c3e-92e9-4964cf63f349")]
+#[derive(Protocol)]
+pub struct UnicodeCollation {
+    stri_coll: extern "efiapi" fn(this: &Self, s1: *const Char16, s2: *const Char16) -> isize,
+    metai_match:
+        extern "efiapi" fn(this: &Self, string: *const Char16, pattern: *const Char16) -> bool,
+    str_lwr: extern "efiapi" fn(this: &Self, s: *mut Char16),
+    str_upr: extern "efiapi" fn(this: &Self, s: *mut Char16),
+    fat_to_str: extern "efiapi" fn(this: &Self, fat_size: usize, fat: *const Char8, s: *mut Char16),
+    str_to_fat:
+        extern "efiapi" fn(this: &Self, s: *const Char16, fat_size: usize, fat: *mut Char8) -> bool,
+    supported_languages: *const Char8,
+}
+
+impl UnicodeCollation {
+    /// Performs a case-insensitive comparison of two null-terminated strings.
+    pub fn stri_coll(&self, s1: &CStr16, s2: &CStr16) -> Ordering {
+        let order = (self.stri_coll)(self, s1.as_ptr(), s2.as_ptr());
+        order.cmp(&0)
+    }
+
+    /// Performs a case-insensitive comparison between a null-terminated
+    /// string and a null-terminated pattern, returning `true` if the
+    /// string matches the pattern.
+    ///
+    /// The pattern may contain the following special characters:
+    ///
+    /// - `*`: matches zero or more characters.
+    /// - `?`: matches exactly one character.
+    /// - `[<char1><char2>...<charN>]`: matches any character in the set.
+    /// - `[<char1>-<char2>]`: matches any character in the range.
+    pub fn metai_match(&self, s: &CStr16, pattern: &CStr16) -> bool {
+        (self.metai_match)(self, s.as_ptr(), pattern.as_ptr())
+    }
+
+    /// Converts the characters in `s` to lower case characters, writing the
+    /// result to `buf`.
+    pub fn str_lwr<'a>(
+        &self,
+        s: &CStr16,
+        buf: &'a mut [u16],
+    ) -> Result<&'a CStr16, StrConversionError> {
+        let out = copy_to_buf(s, buf)?;
+        (self.str_lwr)(self, out.as_mut_ptr().cast());
+        Ok(unsafe { CStr16::from_u16_with_nul_unchecked(out) })
+    }
+
+    /// Converts the characters in `s` to upper case characters, writing the
+    /// result to `buf`.
+    pub fn str_upr<'a>(
+        &self,
+        s: &CStr16,
+        buf: &'a mut [u16],
+    ) -> Result<&'a CStr16, StrConversionError> {
+        let out = copy_to_buf(s, buf)?;
+        (self.str_upr)(self, out.as_mut_ptr().cast());
+        Ok(unsafe { CStr16::from_u16_with_nul_unchecked(out) })
+    }
+
+    /// Converts the 8.3 FAT file name `fat` to a null-terminated string,
+    /// writing the result to `buf`.
+    pub fn fat_to_str<'a>(
+        &self,
+        fat: &CStr8,
+        buf: &'a mut [u16],
+    ) -> Result<&'a CStr16, StrConversionError> {
+        let fat_size = fat.to_bytes().len();
+        if buf.len() < fat_size + 1 {
+            return Err(StrConversionError::BufferTooSmall);
+        }
+        // The firmware does not necessarily terminate the output when the
+        // FAT name contains a nul character, so pre-fill the buffer.
+        buf.fill(0);
+        (self.fat_to_str)(self, fat_size, fat.as_ptr(), buf.as_mut_ptr().cast());
+
+        let len = buf.iter().position(|&c| c == 0).unwrap_or(fat_size);
+        CStr16::from_u16_with_nul(&buf[..=len]).map_err(|_| StrConversionError::InvalidChar)
+    }
+
+    /// Converts the null-terminated string `s` to legal characters in a FAT
+    /// file name, writing the result to `buf`.
+    ///
+    /// The second element of the returned tuple is `true` if some
+    /// characters could not be represented and were substituted.
+    pub fn str_to_fat<'a>(
+        &self,
+        s: &CStr16,
+        buf: &'a mut [u8],
+    ) -> Result<(&'a CStr8, bool), StrConversionError> {
+        let fat_size = s.to_u16_slice().len();
+        if buf.len() < fat_size + 1 {
+            return Err(StrConversionError::BufferTooSmall);
+        }
+        // Characters like spaces and periods are skipped instead of being
+        // converted, so the output may be shorter than the input.
+        buf.fill(0);
+        let substituted = (self.str_to_fat)(self, s.as_ptr(), fat_size, buf.as_mut_ptr().cast());
+
+        let len = buf.iter().position(|&c| c == 0).unwrap_or(fat_size);
+        let fat = unsafe { CStr8::from_bytes_with_nul_unchecked(&buf[..=len]) };
+        Ok((fat, substituted))
+    }
+
+    /// Returns the list of languages supported by this instance, as an
+    /// ASCII string of [RFC 4646] language codes separated by semicolons.
+    ///
+    /// [RFC 4646]: https://www.rfc-editor.org/rfc/rfc4646
+    pub fn supported_languages(&self) -> &CStr8 {
+        unsafe { CStr8::from_ptr(self.supported_languages) }
+    }
+
+    /// Returns `true` if `language` is one of the [`supported_languages`], or
+    /// one of their primary language tags. For example `"en"` matches both
+    /// `"en"` and `"en-US"`.
+    ///
+    /// [`supported_languages`]: Self::supported_languages
+    pub fn supports_language(&self, language: &str) -> bool {
+        self.supported_languages()
+            .to_bytes()
+            .split(|&c| c == b';')
+            .any(|lang| {
+                lang == language.as_bytes()
+                    || lang
+                        .strip_prefix(language.as_bytes())
+                        .map_or(false, |rest| rest.first() == Some(&b'-'))
+            })
+    }
+}
+
+/// Copy `s` including its nul terminator to the start of `buf`, returning the
+/// part of `buf` that was written.
+fn copy_to_buf<'a>(s: &CStr16, buf: &'a mut [u16]) -> Result<&'a mut [u16], StrConversionError> {
+    let src = s.to_u16_slice_with_nul();
+    let out = buf
+        .get_mut(..src.len())
+        .ok_or(StrConversionError::BufferTooSmall)?;
+    out.copy_from_slice(src);
+    Ok(out)
+}
+
+/// Errors returned by [`UnicodeCollation`] string conversions.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StrConversionError {
+    /// The output buffer is too small to hold the result.
+    BufferTooSmall,
+    /// The firmware produced an invalid UCS-2 character.
+    InvalidChar,
+}
@@ -0,0 +1,590 @@
+//! TPM 2.0 protocols.
+//!
+//! The [`Tcg2`] protocol provides access to a TPM 2.0 device: measuring data
+//! into a PCR, reading the measurement event log, and sending raw TPM 2.0
+//! commands.
+//!
+//! This is the interface defined in the TCG [EFI Protocol Specification],
+//! it is not part of the UEFI specification itself.
+//!
+//! [EFI Protocol Specification]: https://trustedcomputinggroup.org/resource/tcg-efi-protocol-specification/
+
+use crate::proto::Protocol;
+use crate::{unsafe_guid, Result, Status};
+use bitflags::bitflags;
+use core::{fmt, mem, ptr, slice};
+
+#[cfg(feature = "exts")]
+use crate::alloc_api::{vec, vec::Vec};
+
+/// Index of a Platform Configuration Register (PCR).
+pub type PcrIndex = u32;
+
+/// Version of a structure or of the protocol itself.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[repr(C)]
+pub struct Version {
+    /// Major version.
+    pub major: u8,
+    /// Minor version.
+    pub minor: u8,
+}
+
+bitflags! {
+    /// Hash algorithms, used to describe the hashes supported by the TPM
+    /// and the currently active PCR banks.
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct HashAlgorithm: u32 {
+        /// SHA-1 hash.
+        const SHA1 = 0x0000_0001;
+        /// SHA-256 hash.
+        const SHA256 = 0x0000_0002;
+        /// SHA-384 hash.
+        const SHA384 = 0x0000_0004;
+        /// SHA-512 hash.
+        const SHA512 = 0x0000_0008;
+        /// SM3-256 hash.
+        const SM3_256 = 0x0000_0010;
+    }
+}
+
+bitflags! {
+    /// Event log formats.
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct EventLogFormat: u32 {
+        /// TCG 1.2 format: every entry only contains a SHA-1 digest.
+        const TCG_1_2 = 0x0000_0001;
+        /// TCG 2.0 format: every entry contains a digest for each active
+        /// PCR bank. The first entry is a TCG 1.2 formatted "Spec ID"
+        /// event describing the digest sizes.
+        const TCG_2 = 0x0000_0002;
+    }
+}
+
+bitflags! {
+    /// Flags for [`Tcg2::hash_log_extend_event`].
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct HashLogExtendEventFlags: u64 {
+        /// Extend the PCR, but do not add an entry to the event log.
+        const EXTEND_ONLY = 0x0000_0000_0000_0001;
+        /// The data to hash is a PE/COFF image. The firmware will hash
+        /// the image according to the Authenticode rules instead of
+        /// hashing the raw bytes.
+        const PE_COFF_IMAGE = 0x0000_0000_0000_0010;
+    }
+}
+
+newtype_enum! {
+    /// Type of a measurement event.
+    pub enum EventType: u32 => {
+        /// Event recorded before the platform was booted.
+        PREBOOT_CERT = 0x0000_0000,
+        /// POST code.
+        POST_CODE = 0x0000_0001,
+        /// Informational event, not extended into a PCR.
+        NO_ACTION = 0x0000_0003,
+        /// Separates pre-OS and OS-present measurements.
+        SEPARATOR = 0x0000_0004,
+        /// Action taken by the firmware.
+        ACTION = 0x0000_0005,
+        /// Tagged event.
+        EVENT_TAG = 0x0000_0006,
+        /// Contents of the S-CRTM.
+        S_CRTM_CONTENTS = 0x0000_0007,
+        /// Version of the S-CRTM.
+        S_CRTM_VERSION = 0x0000_0008,
+        /// CPU microcode update.
+        CPU_MICROCODE = 0x0000_0009,
+        /// Platform configuration flags.
+        PLATFORM_CONFIG_FLAGS = 0x0000_000a,
+        /// Table of devices.
+        TABLE_OF_DEVICES = 0x0000_000b,
+        /// Compact hash.
+        COMPACT_HASH = 0x0000_000c,
+        /// Initial program loader code.
+        IPL = 0x0000_000d,
+        /// Initial program loader partition data.
+        IPL_PARTITION_DATA = 0x0000_000e,
+        /// Non-host code.
+        NONHOST_CODE = 0x0000_000f,
+        /// Non-host configuration.
+        NONHOST_CONFIG = 0x0000_0010,
+        /// Non-host information.
+        NONHOST_INFO = 0x0000_0011,
+        /// Boot device events are omitted.
+        OMIT_BOOT_DEVICE_EVENTS = 0x0000_0012,
+        /// Configuration variable of a driver.
+        EFI_VARIABLE_DRIVER_CONFIG = 0x8000_0001,
+        /// Boot variable.
+        EFI_VARIABLE_BOOT = 0x8000_0002,
+        /// Boot services application.
+        EFI_BOOT_SERVICES_APPLICATION = 0x8000_0003,
+        /// Boot services driver.
+        EFI_BOOT_SERVICES_DRIVER = 0x8000_0004,
+        /// Runtime services driver.
+        EFI_RUNTIME_SERVICES_DRIVER = 0x8000_0005,
+        /// GPT partition table.
+        EFI_GPT_EVENT = 0x8000_0006,
+        /// Action taken by an EFI application or driver.
+        EFI_ACTION = 0x8000_0007,
+        /// Platform firmware blob.
+        EFI_PLATFORM_FIRMWARE_BLOB = 0x8000_0008,
+        /// Tables handed off to the OS.
+        EFI_HANDOFF_TABLES = 0x8000_0009,
+        /// H-CRTM event.
+        EFI_HCRTM_EVENT = 0x8000_0010,
+        /// Variable used to verify an image (e.g. a `db` entry).
+        EFI_VARIABLE_AUTHORITY = 0x8000_00e0,
+    }
+}
+
+/// Capabilities of the TPM and of the firmware's TCG2 implementation.
+///
+/// Corresponds to the `EFI_TCG2_BOOT_SERVICE_CAPABILITY` type in the C API.
+#[derive(Clone, Copy, Debug)]
+#[repr(C, packed)]
+pub struct BootServiceCapability {
+    size: u8,
+    structure_version: Version,
+    protocol_version: Version,
+    hash_algorithm_bitmap: HashAlgorithm,
+    supported_event_logs: EventLogFormat,
+    tpm_present_flag: u8,
+    max_command_size: u16,
+    max_response_size: u16,
+    manufacturer_id: u32,
+    number_of_pcr_banks: u32,
+    active_pcr_banks: HashAlgorithm,
+}
+
+impl Default for BootServiceCapability {
+    fn default() -> Self {
+        Self {
+            // The firmware uses the size to determine which version of
+            // the structure the caller expects.
+            size: mem::size_of::<Self>() as u8,
+            structure_version: Version::default(),
+            protocol_version: Version::default(),
+            hash_algorithm_bitmap: HashAlgorithm::empty(),
+            supported_event_logs: EventLogFormat::empty(),
+            tpm_present_flag: 0,
+            max_command_size: 0,
+            max_response_size: 0,
+            manufacturer_id: 0,
+            number_of_pcr_banks: 0,
+            active_pcr_banks: HashAlgorithm::empty(),
+        }
+    }
+}
+
+impl BootServiceCapability {
+    /// Version of this structure as filled in by the firmware.
+    pub fn structure_version(&self) -> Version {
+        self.structure_version
+    }
+
+    /// Version of the TCG2 protocol implemented by the firmware.
+    pub fn protocol_version(&self) -> Version {
+        self.protocol_version
+    }
+
+    /// Hash algorithms supported by the TPM, i.e. the PCR banks that may be
+    /// activated.
+    pub fn supported_pcr_banks(&self) -> HashAlgorithm {
+        self.hash_algorithm_bitmap
+    }
+
+    /// Event log formats supported by the firmware.
+    pub fn supported_event_logs(&self) -> EventLogFormat {
+        self.supported_event_logs
+    }
+
+    /// Returns `true` if a TPM is present.
+    pub fn tpm_present(&self) -> bool {
+        self.tpm_present_flag != 0
+    }
+
+    /// Maximum size in bytes of a command that can be sent to the TPM.
+    pub fn max_command_size(&self) -> u16 {
+        self.max_command_size
+    }
+
+    /// Maximum size in bytes of a response that can be received from the TPM.
+    pub fn max_response_size(&self) -> u16 {
+        self.max_response_size
+    }
+
+    /// Vendor ID of the TPM manufacturer.
+    pub fn manufacturer_id(&self) -> u32 {
+        self.manufacturer_id
+    }
+
+    /// Maximum number of PCR banks supported by the TPM.
+    pub fn number_of_pcr_banks(&self) -> u32 {
+        self.number_of_pcr_banks
+    }
+
+    /// Currently active PCR banks.
+    pub fn active_pcr_banks(&self) -> HashAlgorithm {
+        self.active_pcr_banks
+    }
+}
+
+/// Header of a [`PcrEventInputs`].
+///
+/// Corresponds to the `EFI_TCG2_EVENT_HEADER` type in the C API.
+#[derive(Clone, Copy, Debug)]
+#[repr(C, packed)]
+struct EventHeader {
+    header_size: u32,
+    header_version: u16,
+    pcr_index: PcrIndex,
+    event_type: EventType,
+}
+
+/// Description of an event to be measured with
+/// [`Tcg2::hash_log_extend_event`].
+///
+/// This is a dynamically-sized type: use [`PcrEventInputs::new_in_buffer`]
+/// to create one.
+///
+/// Corresponds to the `EFI_TCG2_EVENT` type in the C API.
+#[repr(C, packed)]
+pub struct PcrEventInputs {
+    size: u32,
+    event_header: EventHeader,
+    event: [u8],
+}
+
+impl PcrEventInputs {
+    /// Create a new `PcrEventInputs` in the provided `buffer`.
+    ///
+    /// The `event_data` is stored in the event log entry; it is not what
+    /// gets hashed into the PCR.
+    ///
+    /// If the buffer is too small, an error is returned containing the
+    /// required size in bytes.
+    pub fn new_in_buffer<'buf>(
+        buffer: &'buf mut [u8],
+        pcr_index: PcrIndex,
+        event_type: EventType,
+        event_data: &[u8],
+    ) -> core::result::Result<&'buf mut Self, usize> {
+        let header_size = mem::size_of::<u32>() + mem::size_of::<EventHeader>();
+        let required_size = header_size + event_data.len();
+        if buffer.len() < required_size {
+            return Err(required_size);
+        }
+
+        let event_ptr: *mut Self =
+            ptr::from_raw_parts_mut(buffer.as_mut_ptr().cast::<()>(), event_data.len());
+
+        unsafe {
+            // The struct is packed, so there are no alignment requirements
+            // on the buffer.
+            ptr::addr_of_mut!((*event_ptr).size).write(required_size as u32);
+            ptr::addr_of_mut!((*event_ptr).event_header).write(EventHeader {
+                header_size: mem::size_of::<EventHeader>() as u32,
+                header_version: 1,
+                pcr_index,
+                event_type,
+            });
+            ptr::copy(
+                event_data.as_ptr(),
+                ptr::addr_of_mut!((*event_ptr).event).cast::<u8>(),
+                event_data.len(),
+            );
+            Ok(&mut *event_ptr)
+        }
+    }
+}
+
+impl fmt::Debug for PcrEventInputs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Copy the fields out of the packed struct before formatting them.
+        let size = self.size;
+        let event_header = self.event_header;
+        f.debug_struct("PcrEventInputs")
+            .field("size", &size)
+            .field("event_header", &event_header)
+            .field("event", &&self.event)
+            .finish()
+    }
+}
+
+/// The measurement event log, as returned by [`Tcg2::get_event_log`].
+#[derive(Debug)]
+pub struct EventLog<'tpm> {
+    format: EventLogFormat,
+    data: &'tpm [u8],
+    last_entry_offset: Option<usize>,
+    truncated: bool,
+}
+
+impl<'tpm> EventLog<'tpm> {
+    /// Format of the log.
+    pub fn format(&self) -> EventLogFormat {
+        self.format
+    }
+
+    /// Raw bytes of the whole log, including the last entry.
+    pub fn bytes(&self) -> &'tpm [u8] {
+        self.data
+    }
+
+    /// Raw bytes of the last entry in the log, if the log is not empty.
+    pub fn last_entry(&self) -> Option<&'tpm [u8]> {
+        self.last_entry_offset.map(|offset| &self.data[offset..])
+    }
+
+    /// Returns `true` if the log was truncated because it ran out of space
+    /// and some events are missing.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+/// Size of the fixed part of a TCG 1.2 `TCG_PCR_EVENT`, up to (but not
+/// including) the event data.
+const PCR_EVENT_HEADER_SIZE: usize = 32;
+
+/// Read an unaligned little-endian integer from the event log.
+unsafe fn read_u32(ptr: *const u8) -> u32 {
+    ptr.cast::<u32>().read_unaligned()
+}
+
+/// Read an unaligned little-endian integer from the event log.
+unsafe fn read_u16(ptr: *const u8) -> u16 {
+    ptr.cast::<u16>().read_unaligned()
+}
+
+/// Size of a TCG 1.2 `TCG_PCR_EVENT` entry.
+unsafe fn pcr_event_size(entry: *const u8) -> usize {
+    PCR_EVENT_HEADER_SIZE + read_u32(entry.add(PCR_EVENT_HEADER_SIZE - 4)) as usize
+}
+
+/// Size of a TCG 2.0 `TCG_PCR_EVENT2` entry. The digest sizes are looked up
+/// in the "Spec ID" event at the start of the log.
+unsafe fn pcr_event2_size(spec_id_entry: *const u8, entry: *const u8) -> Option<usize> {
+    // `TCG_EfiSpecIDEventStruct`: the number of algorithms is at offset 24,
+    // followed by an array of (algorithm ID, digest size) pairs.
+    let spec_id = spec_id_entry.add(PCR_EVENT_HEADER_SIZE);
+    let number_of_algorithms = read_u32(spec_id.add(24)) as usize;
+    let digest_sizes = spec_id.add(28);
+    let digest_size = |alg_id: u16| {
+        (0..number_of_algorithms)
+            .map(|i| digest_sizes.add(i * 4))
+            .find(|size| read_u16(*size) == alg_id)
+            .map(|size| read_u16(size.add(2)) as usize)
+    };
+
+    // PCR index and event type, then the `TPML_DIGEST_VALUES`.
+    let digest_count = read_u32(entry.add(8));
+    let mut offset = 12;
+    for _ in 0..digest_count {
+        let alg_id = read_u16(entry.add(offset));
+        offset += 2 + digest_size(alg_id)?;
+    }
+
+    let event_size = read_u32(entry.add(offset)) as usize;
+    Some(offset + 4 + event_size)
+}
+
+/// Protocol for interacting with TPM 2.0 devices.
+///
+/// Corresponds to the `EFI_TCG2_PROTOCOL` type in the C API.
+#[repr(C)]
+#[unsafe_guid("607f766c-7455-42be-930b-e4d76db2720f")]
+#[derive(Protocol)]
+pub struct Tcg2 {
+    get_capability: extern "efiapi" fn(
+        this: &mut Tcg2,
+        protocol_capability: &mut BootServiceCapability,
+    ) -> Status,
+    get_event_log: extern "efiapi" fn(
+        this: &mut Tcg2,
+        event_log_format: EventLogFormat,
+        event_log_location: &mut u64,
+        event_log_last_entry: &mut u64,
+        event_log_truncated: &mut bool,
+    ) -> Status,
+    hash_log_extend_event: unsafe extern "efiapi" fn(
+        this: &mut Tcg2,
+        flags: HashLogExtendEventFlags,
+        data_to_hash: u64,
+        data_to_hash_len: u64,
+        event: *const u8,
+    ) -> Status,
+    submit_command: unsafe extern "efiapi" fn(
+        this: &mut Tcg2,
+        input_parameter_block_size: u32,
+        input_parameter_block: *const u8,
+        output_parameter_block_size: u32,
+        output_parameter_block: *mut u8,
+    ) -> Status,
+    get_active_pcr_banks:
+        extern "efiapi" fn(this: &mut Tcg2, active_pcr_banks: &mut HashAlgorithm) -> Status,
+    set_active_pcr_banks:
+        extern "efiapi" fn(this: &mut Tcg2, active_pcr_banks: HashAlgorithm) -> Status,
+    get_result_of_set_active_pcr_banks: usize,
+}
+
+impl Tcg2 {
+    /// Get information about the protocol and the TPM device.
+    pub fn get_capability(&mut self) -> Result<BootServiceCapability> {
+        let mut capability = BootServiceCapability::default();
+        (self.get_capability)(self, &mut capability).into_with_val(|| capability)
+    }
+
+    /// Get the measurement event log in the requested `format`.
+    ///
+    /// Only one format may be requested at a time. The log stays valid until
+    /// boot services are exited.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::INVALID_PARAMETER`] if `format` is not supported.
+    /// - [`Status::COMPROMISED_DATA`] if the log could not be parsed to
+    ///   determine its length.
+    pub fn get_event_log(&mut self, format: EventLogFormat) -> Result<EventLog<'_>> {
+        let mut location = 0;
+        let mut last_entry = 0;
+        let mut truncated = false;
+        let status =
+            (self.get_event_log)(self, format, &mut location, &mut last_entry, &mut truncated);
+        Result::from(status)?;
+
+        // An empty log, or no TPM at all.
+        if location == 0 || last_entry == 0 {
+            return Ok(EventLog {
+                format,
+                data: &[],
+                last_entry_offset: None,
+                truncated,
+            });
+        }
+
+        let start = location as *const u8;
+        let last = last_entry as *const u8;
+        let last_entry_size = unsafe {
+            if format == EventLogFormat::TCG_2 && last != start {
+                pcr_event2_size(start, last)
+            } else {
+                // The TCG 1.2 format, or the TCG 2.0 "Spec ID" event which is
+                // always stored in the TCG 1.2 format.
+                Some(pcr_event_size(last))
+            }
+        }
+        .ok_or(Status::COMPROMISED_DATA)?;
+
+        let last_entry_offset = (last_entry - location) as usize;
+        let data = unsafe { slice::from_raw_parts(start, last_entry_offset + last_entry_size) };
+        Ok(EventLog {
+            format,
+            data,
+            last_entry_offset: Some(last_entry_offset),
+            truncated,
+        })
+    }
+
+    /// Hash `data` into the PCR described by `event`, and add `event` to the
+    /// event log (unless [`HashLogExtendEventFlags::EXTEND_ONLY`] is set).
+    ///
+    /// This is the way to measure the next boot stage before launching it.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::DEVICE_ERROR`] if the command could not be sent to the TPM.
+    /// - [`Status::VOLUME_FULL`] if the event log is full. The PCR was still
+    ///   extended.
+    /// - [`Status::INVALID_PARAMETER`] if the event is malformed.
+    /// - [`Status::UNSUPPORTED`] if the PE/COFF image type is not supported.
+    pub fn hash_log_extend_event(
+        &mut self,
+        flags: HashLogExtendEventFlags,
+        data: &[u8],
+        event: &PcrEventInputs,
+    ) -> Result {
+        unsafe {
+            (self.hash_log_extend_event)(
+                self,
+                flags,
+                data.as_ptr() as u64,
+                data.len() as u64,
+                (event as *const PcrEventInputs).cast(),
+            )
+        }
+        .into()
+    }
+
+    /// Send a raw TPM 2.0 command and receive the response in `output`.
+    ///
+    /// On success, the returned slice contains the full response, whose
+    /// size is read from the TPM response header.
+    ///
+    /// # Errors
+    ///
+    /// - [`Status::DEVICE_ERROR`] if the command could not be sent to the TPM.
+    /// - [`Status::BUFFER_TOO_SMALL`] if `output` is too small for the
+    ///   response.
+    /// - [`Status::INVALID_PARAMETER`] if a buffer is too large.
+    pub fn submit_command<'buf>(
+        &mut self,
+        input: &[u8],
+        output: &'buf mut [u8],
+    ) -> Result<&'buf [u8]> {
+        let input_size = u32::try_from(input.len()).map_err(|_| Status::INVALID_PARAMETER)?;
+        let output_size = u32::try_from(output.len()).map_err(|_| Status::INVALID_PARAMETER)?;
+
+        let status = unsafe {
+            (self.submit_command)(
+                self,
+                input_size,
+                input.as_ptr(),
+                output_size,
+                output.as_mut_ptr(),
+            )
+        };
+        Result::from(status)?;
+
+        // The response header is a 16-bit tag followed by the big-endian
+        // 32-bit size of the whole response.
+        let response_size = output
+            .get(2..6)
+            .map(|size| u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize)
+            .ok_or(Status::BUFFER_TOO_SMALL)?;
+        output
+            .get(..response_size)
+            .ok_or_else(|| Status::BUFFER_TOO_SMALL.into())
+    }
+
+    /// Get the currently active PCR banks.
+    pub fn get_active_pcr_banks(&mut self) -> Result<HashAlgorithm> {
+        let mut active_pcr_banks = HashAlgorithm::empty();
+        (self.get_active_pcr_banks)(self, &mut active_pcr_banks).into_with_val(|| active_pcr_banks)
+    }
+
+    /// Set the active PCR banks. The change takes effect after a reboot.
+    pub fn set_active_pcr_banks(&mut self, active_pcr_banks: HashAlgorithm) -> Result {
+        (self.set_active_pcr_banks)(self, active_pcr_banks).into()
+    }
+}
+
+#[cfg(feature = "exts")]
+impl Tcg2 {
+    /// Send a raw TPM 2.0 command and return the response in a newly
+    /// allocated buffer.
+    ///
+    /// The buffer is sized according to the maximum response size reported
+    /// by [`get_capability`](Self::get_capability).
+    pub fn submit_command_vec(&mut self, input: &[u8]) -> Result<Vec<u8>> {
+        let max_response_size = self.get_capability()?.max_response_size();
+        let mut output = vec![0; usize::from(max_response_size)];
+        let response_size = self.submit_command(input, &mut output)?.len();
+        output.truncate(response_size);
+        Ok(output)
+    }
+}
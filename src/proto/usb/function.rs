@@ -0,0 +1,707 @@
+use super::DeviceRequest;
+use crate::proto::Protocol;
+use crate::{unsafe_guid, CStr16, Error, Result, Status};
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+
+/// Mask of the endpoint number in an endpoint address.
+const ENDPOINT_NUMBER_MASK: u8 = 0x0f;
+
+/// Bit of an endpoint address that is set for IN endpoints.
+const ENDPOINT_DIRECTION_IN: u8 = 0x80;
+
+newtype_enum! {
+    /// Direction of the data of an endpoint.
+    ///
+    /// Directions are named from the point of view of the host, as in the
+    /// USB specification: the device receives data on `HOST_OUT` endpoints
+    /// and sends data on `HOST_IN` endpoints. [`DEVICE_RX`] and
+    /// [`DEVICE_TX`] are aliases from the point of view of the device.
+    ///
+    /// [`DEVICE_RX`]: EndpointDirection::DEVICE_RX
+    /// [`DEVICE_TX`]: EndpointDirection::DEVICE_TX
+    pub enum EndpointDirection: u32 => {
+        /// Data is sent by the host and received by the device.
+        HOST_OUT = 0,
+        /// Data is sent by the device and received by the host.
+        HOST_IN = 1,
+    }
+}
+
+impl EndpointDirection {
+    /// Data is received by the device, same as [`HOST_OUT`].
+    ///
+    /// [`HOST_OUT`]: EndpointDirection::HOST_OUT
+    pub const DEVICE_RX: Self = Self::HOST_OUT;
+
+    /// Data is sent by the device, same as [`HOST_IN`].
+    ///
+    /// [`HOST_IN`]: EndpointDirection::HOST_IN
+    pub const DEVICE_TX: Self = Self::HOST_IN;
+
+    /// Splits the `endpoint_address` of an [`EndpointDescriptor`] into the
+    /// endpoint index and the direction that [`UsbFunctionIo`] expects.
+    ///
+    /// The index is the endpoint number in the low 4 bits of the address,
+    /// and the direction is given by bit 7, which is set for IN endpoints.
+    pub fn split_address(endpoint_address: u8) -> (u8, Self) {
+        let direction = if endpoint_address & ENDPOINT_DIRECTION_IN != 0 {
+            Self::HOST_IN
+        } else {
+            Self::HOST_OUT
+        };
+        (endpoint_address & ENDPOINT_NUMBER_MASK, direction)
+    }
+}
+
+newtype_enum! {
+    /// Type of the transfers of an endpoint.
+    pub enum EndpointType: u32 => {
+        /// Control transfers, as on endpoint 0.
+        CONTROL = 0,
+        /// Isochronous transfers.
+        ISOCHRONOUS = 1,
+        /// Bulk transfers.
+        BULK = 2,
+        /// Interrupt transfers.
+        INTERRUPT = 3,
+    }
+}
+
+newtype_enum! {
+    /// Speed of the bus a USB device is attached to.
+    pub enum UsbBusSpeed: u32 => {
+        /// The speed is not known yet.
+        UNKNOWN = 0,
+        /// Low speed, 1.5 Mb/s.
+        LOW = 1,
+        /// Full speed, 12 Mb/s.
+        FULL = 2,
+        /// High speed, 480 Mb/s.
+        HIGH = 3,
+        /// Super speed, 5 Gb/s.
+        SUPER = 4,
+    }
+}
+
+newtype_enum! {
+    /// Type of the port the USB device is attached to, returned by
+    /// [`UsbFunctionIo::detect_port`].
+    pub enum PortType: u32 => {
+        /// The type of the port could not be detected.
+        UNKNOWN = 0,
+        /// A standard downstream port of a host or hub.
+        STANDARD_DOWNSTREAM = 1,
+        /// A charging downstream port, that can also supply more current.
+        CHARGING_DOWNSTREAM = 2,
+        /// A dedicated charger, without data lines.
+        DEDICATED_CHARGING = 3,
+        /// A dedicated charger that doesn't follow the specification.
+        INVALID_DEDICATED_CHARGING = 4,
+    }
+}
+
+newtype_enum! {
+    /// Information about the device that can be read with
+    /// [`UsbFunctionIo::get_device_info`].
+    pub enum DeviceInfoId: u32 => {
+        /// No information.
+        UNKNOWN = 0,
+        /// The serial number of the device.
+        SERIAL_NUMBER = 1,
+        /// The name of the manufacturer.
+        MANUFACTURER_NAME = 2,
+        /// The name of the product.
+        PRODUCT_NAME = 3,
+    }
+}
+
+newtype_enum! {
+    /// Status of a transfer, reported by [`FunctionMessage`].
+    pub enum FunctionTransferStatus: u32 => {
+        /// The status is not known.
+        UNKNOWN = 0,
+        /// The transfer completed.
+        COMPLETE = 1,
+        /// The transfer was aborted, for example with
+        /// [`UsbFunctionIo::abort_transfer`].
+        ABORTED = 2,
+        /// The transfer is still in progress.
+        ACTIVE = 3,
+        /// No transfer was queued.
+        NONE = 4,
+    }
+}
+
+newtype_enum! {
+    /// Corresponds to `EFI_USBFN_MESSAGE`.
+    enum MessageType: u32 => {
+        NONE = 0,
+        SETUP_PACKET = 1,
+        ENDPOINT_STATUS_CHANGED_RX = 2,
+        ENDPOINT_STATUS_CHANGED_TX = 3,
+        BUS_EVENT_DETACH = 4,
+        BUS_EVENT_ATTACH = 5,
+        BUS_EVENT_RESET = 6,
+        BUS_EVENT_SUSPEND = 7,
+        BUS_EVENT_RESUME = 8,
+        BUS_EVENT_SPEED = 9,
+    }
+}
+
+/// The result of a transfer queued with [`UsbFunctionIo::transfer`].
+///
+/// Corresponds to the `EFI_USBFN_TRANSFER_RESULT` type in the C API.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct FunctionTransferResult {
+    /// Number of bytes transferred.
+    pub bytes_transferred: usize,
+    /// Status of the transfer.
+    pub status: FunctionTransferStatus,
+    /// Index of the endpoint of the transfer.
+    pub endpoint_index: u8,
+    /// Direction of the endpoint of the transfer.
+    pub direction: EndpointDirection,
+    /// The buffer of the transfer, as passed to [`UsbFunctionIo::transfer`].
+    pub buffer: *mut u8,
+}
+
+/// Corresponds to `EFI_USBFN_MESSAGE_PAYLOAD`.
+#[derive(Clone, Copy)]
+#[repr(C)]
+union MessagePayload {
+    request: DeviceRequest,
+    transfer: FunctionTransferResult,
+    speed: UsbBusSpeed,
+}
+
+/// An event of the USB controller, returned by
+/// [`UsbFunctionIo::event_handler`].
+#[derive(Clone, Copy, Debug)]
+pub enum FunctionMessage {
+    /// Nothing happened since the last call.
+    None,
+    /// The host sent a setup packet on the control endpoint, which must be
+    /// answered by the caller.
+    SetupPacket(DeviceRequest),
+    /// A transfer on a `HOST_OUT` endpoint completed or was aborted.
+    EndpointStatusChangedRx(FunctionTransferResult),
+    /// A transfer on a `HOST_IN` endpoint completed or was aborted.
+    EndpointStatusChangedTx(FunctionTransferResult),
+    /// The device was detached from the host.
+    Detach,
+    /// The device was attached to a host.
+    Attach,
+    /// The host reset the device.
+    Reset,
+    /// The host suspended the device.
+    Suspend,
+    /// The host resumed the device.
+    Resume,
+    /// The speed of the bus was negotiated.
+    Speed(UsbBusSpeed),
+}
+
+/// The standard USB device descriptor.
+///
+/// Corresponds to the `EFI_USB_DEVICE_DESCRIPTOR` type in the C API.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C, packed)]
+pub struct DeviceDescriptor {
+    pub length: u8,
+    pub descriptor_type: u8,
+    pub bcd_usb: u16,
+    pub device_class: u8,
+    pub device_sub_class: u8,
+    pub device_protocol: u8,
+    pub max_packet_size_0: u8,
+    pub id_vendor: u16,
+    pub id_product: u16,
+    pub bcd_device: u16,
+    pub str_manufacturer: u8,
+    pub str_product: u8,
+    pub str_serial_number: u8,
+    pub num_configurations: u8,
+}
+
+/// The standard USB configuration descriptor.
+///
+/// Corresponds to the `EFI_USB_CONFIG_DESCRIPTOR` type in the C API.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C, packed)]
+pub struct ConfigDescriptor {
+    pub length: u8,
+    pub descriptor_type: u8,
+    pub total_length: u16,
+    pub num_interfaces: u8,
+    pub configuration_value: u8,
+    pub configuration: u8,
+    pub attributes: u8,
+    pub max_power: u8,
+}
+
+/// The standard USB interface descriptor.
+///
+/// Corresponds to the `EFI_USB_INTERFACE_DESCRIPTOR` type in the C API.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C, packed)]
+pub struct InterfaceDescriptor {
+    pub length: u8,
+    pub descriptor_type: u8,
+    pub interface_number: u8,
+    pub alternate_setting: u8,
+    pub num_endpoints: u8,
+    pub interface_class: u8,
+    pub interface_sub_class: u8,
+    pub interface_protocol: u8,
+    pub interface: u8,
+}
+
+/// The standard USB endpoint descriptor.
+///
+/// Corresponds to the `EFI_USB_ENDPOINT_DESCRIPTOR` type in the C API.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C, packed)]
+pub struct EndpointDescriptor {
+    pub length: u8,
+    pub descriptor_type: u8,
+    /// Number of the endpoint, with bit 7 set for IN endpoints. See
+    /// [`EndpointDirection::split_address`].
+    pub endpoint_address: u8,
+    pub attributes: u8,
+    pub max_packet_size: u16,
+    pub interval: u8,
+}
+
+/// An interface and its endpoints, part of a [`ConfigInfo`].
+///
+/// Corresponds to the `EFI_USB_INTERFACE_INFO` type in the C API.
+#[derive(Debug)]
+#[repr(C)]
+pub struct InterfaceInfo<'a> {
+    descriptor: &'a InterfaceDescriptor,
+    endpoints: *const &'a EndpointDescriptor,
+    _endpoints: PhantomData<&'a [&'a EndpointDescriptor]>,
+}
+
+impl<'a> InterfaceInfo<'a> {
+    /// Describes an interface with its endpoints.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of endpoints doesn't match the descriptor.
+    pub fn new(
+        descriptor: &'a InterfaceDescriptor,
+        endpoints: &'a [&'a EndpointDescriptor],
+    ) -> Self {
+        assert_eq!(usize::from(descriptor.num_endpoints), endpoints.len());
+        Self {
+            descriptor,
+            endpoints: endpoints.as_ptr(),
+            _endpoints: PhantomData,
+        }
+    }
+}
+
+/// A configuration and its interfaces, part of a [`DeviceInfo`].
+///
+/// Corresponds to the `EFI_USB_CONFIG_INFO` type in the C API.
+#[derive(Debug)]
+#[repr(C)]
+pub struct ConfigInfo<'a> {
+    descriptor: &'a ConfigDescriptor,
+    interfaces: *const &'a InterfaceInfo<'a>,
+    _interfaces: PhantomData<&'a [&'a InterfaceInfo<'a>]>,
+}
+
+impl<'a> ConfigInfo<'a> {
+    /// Describes a configuration with its interfaces.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of interfaces doesn't match the descriptor.
+    pub fn new(descriptor: &'a ConfigDescriptor, interfaces: &'a [&'a InterfaceInfo<'a>]) -> Self {
+        assert_eq!(usize::from(descriptor.num_interfaces), interfaces.len());
+        Self {
+            descriptor,
+            interfaces: interfaces.as_ptr(),
+            _interfaces: PhantomData,
+        }
+    }
+}
+
+/// The descriptors of a device, passed to
+/// [`UsbFunctionIo::configure_enable_endpoints`].
+///
+/// Corresponds to the `EFI_USB_DEVICE_INFO` type in the C API.
+#[derive(Debug)]
+#[repr(C)]
+pub struct DeviceInfo<'a> {
+    descriptor: &'a DeviceDescriptor,
+    configs: *const &'a ConfigInfo<'a>,
+    _configs: PhantomData<&'a [&'a ConfigInfo<'a>]>,
+}
+
+impl<'a> DeviceInfo<'a> {
+    /// Describes a device with its configurations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of configurations doesn't match the
+    /// descriptor.
+    pub fn new(descriptor: &'a DeviceDescriptor, configs: &'a [&'a ConfigInfo<'a>]) -> Self {
+        assert_eq!(usize::from(descriptor.num_configurations), configs.len());
+        Self {
+            descriptor,
+            configs: configs.as_ptr(),
+            _configs: PhantomData,
+        }
+    }
+}
+
+/// The USB function I/O protocol.
+///
+/// This protocol drives a USB controller in device mode, so that the machine
+/// appears as a USB device to a host, for example to receive a firmware
+/// image from a flashing tool.
+///
+/// Once the controller is started and the endpoints are configured, the
+/// caller must regularly call [`event_handler`] to learn about the events of
+/// the bus, answer the setup packets of the host, and find out when the
+/// transfers queued with [`transfer`] have completed.
+///
+/// [`event_handler`]: UsbFunctionIo::event_handler
+/// [`transfer`]: UsbFunctionIo::transfer
+#[repr(C)]
+#[unsafe_guid("32d2963a-fe5d-4f30-b633-6e5dc55803cc")]
+#[derive(Protocol)]
+pub struct UsbFunctionIo {
+    revision: u32,
+    detect_port: extern "efiapi" fn(this: &mut Self, port_type: &mut PortType) -> Status,
+    configure_enable_endpoints:
+        extern "efiapi" fn(this: &mut Self, device_info: &DeviceInfo) -> Status,
+    get_endpoint_max_packet_size: extern "efiapi" fn(
+        this: &mut Self,
+        endpoint_type: EndpointType,
+        bus_speed: UsbBusSpeed,
+        max_packet_size: &mut u16,
+    ) -> Status,
+    get_device_info: unsafe extern "efiapi" fn(
+        this: &mut Self,
+        id: DeviceInfoId,
+        buffer_size: &mut usize,
+        buffer: *mut c_void,
+    ) -> Status,
+    get_vendor_id_product_id:
+        extern "efiapi" fn(this: &mut Self, vendor_id: &mut u16, product_id: &mut u16) -> Status,
+    abort_transfer: extern "efiapi" fn(
+        this: &mut Self,
+        endpoint_index: u8,
+        direction: EndpointDirection,
+    ) -> Status,
+    get_endpoint_stall_state: extern "efiapi" fn(
+        this: &mut Self,
+        endpoint_index: u8,
+        direction: EndpointDirection,
+        state: &mut u8,
+    ) -> Status,
+    set_endpoint_stall_state: extern "efiapi" fn(
+        this: &mut Self,
+        endpoint_index: u8,
+        direction: EndpointDirection,
+        state: u8,
+    ) -> Status,
+    event_handler: unsafe extern "efiapi" fn(
+        this: &mut Self,
+        message: &mut MessageType,
+        payload_size: &mut usize,
+        payload: *mut MessagePayload,
+    ) -> Status,
+    transfer: unsafe extern "efiapi" fn(
+        this: &mut Self,
+        endpoint_index: u8,
+        direction: EndpointDirection,
+        buffer_size: &mut usize,
+        buffer: *mut c_void,
+    ) -> Status,
+    get_max_transfer_size: extern "efiapi" fn(this: &mut Self, max_size: &mut usize) -> Status,
+    allocate_transfer_buffer:
+        extern "efiapi" fn(this: &mut Self, size: usize, buffer: &mut *mut c_void) -> Status,
+    free_transfer_buffer: unsafe extern "efiapi" fn(this: &mut Self, buffer: *mut c_void) -> Status,
+    start_controller: extern "efiapi" fn(this: &mut Self) -> Status,
+    stop_controller: extern "efiapi" fn(this: &mut Self) -> Status,
+    set_endpoint_policy: usize,
+    get_endpoint_policy: usize,
+}
+
+impl UsbFunctionIo {
+    /// Returns the revision of the protocol.
+    pub fn revision(&self) -> u32 {
+        self.revision
+    }
+
+    /// Detects the type of the port the device is attached to.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_READY`]: the controller is not attached to a port.
+    pub fn detect_port(&mut self) -> Result<PortType> {
+        let mut port_type = PortType::UNKNOWN;
+        (self.detect_port)(self, &mut port_type).into_with_val(|| port_type)
+    }
+
+    /// Configures the device with the descriptors in `device_info`, and
+    /// enables its endpoints. The descriptors are returned to the host when
+    /// it enumerates the device.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the descriptors are not valid.
+    /// * [`Status::NOT_READY`]: the controller is not started.
+    pub fn configure_enable_endpoints(&mut self, device_info: &DeviceInfo) -> Result {
+        (self.configure_enable_endpoints)(self, device_info).into()
+    }
+
+    /// Returns the maximum packet size of the endpoints of `endpoint_type` at
+    /// `bus_speed`, to fill the [`EndpointDescriptor`]s.
+    pub fn get_endpoint_max_packet_size(
+        &mut self,
+        endpoint_type: EndpointType,
+        bus_speed: UsbBusSpeed,
+    ) -> Result<u16> {
+        let mut size = 0;
+        (self.get_endpoint_max_packet_size)(self, endpoint_type, bus_speed, &mut size)
+            .into_with_val(|| size)
+    }
+
+    /// Reads the information `id` about the device, such as its serial
+    /// number, into `buffer`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: `buffer` is too small. The error
+    ///   contains the number of characters needed.
+    /// * [`Status::DEVICE_ERROR`]: the information could not be read.
+    /// * [`Status::COMPROMISED_DATA`]: the information is not a valid
+    ///   string.
+    pub fn get_device_info<'buf>(
+        &mut self,
+        id: DeviceInfoId,
+        buffer: &'buf mut [u16],
+    ) -> Result<&'buf CStr16, Option<usize>> {
+        let mut size = mem::size_of_val(buffer);
+        let status =
+            unsafe { (self.get_device_info)(self, id, &mut size, buffer.as_mut_ptr().cast()) };
+        match status {
+            Status::SUCCESS => {
+                // Only keep the string up to the first null character.
+                buffer
+                    .iter()
+                    .position(|&c| c == 0)
+                    .and_then(|len| CStr16::from_u16_with_nul(&buffer[..=len]).ok())
+                    .ok_or_else(|| Error::new(Status::COMPROMISED_DATA, None))
+            }
+            Status::BUFFER_TOO_SMALL => {
+                Err(Error::new(status, Some((size + 1) / mem::size_of::<u16>())))
+            }
+            _ => Err(Error::new(status, None)),
+        }
+    }
+
+    /// Returns the vendor and product ids of the device, as `(vendor,
+    /// product)`.
+    pub fn get_vendor_id_product_id(&mut self) -> Result<(u16, u16)> {
+        let mut vendor_id = 0;
+        let mut product_id = 0;
+        (self.get_vendor_id_product_id)(self, &mut vendor_id, &mut product_id)
+            .into_with_val(|| (vendor_id, product_id))
+    }
+
+    /// Aborts the transfer queued on an endpoint. The abort is reported by
+    /// [`event_handler`](Self::event_handler).
+    pub fn abort_transfer(&mut self, endpoint_index: u8, direction: EndpointDirection) -> Result {
+        (self.abort_transfer)(self, endpoint_index, direction).into()
+    }
+
+    /// Returns whether an endpoint is stalled.
+    pub fn get_endpoint_stall_state(
+        &mut self,
+        endpoint_index: u8,
+        direction: EndpointDirection,
+    ) -> Result<bool> {
+        let mut state = 0;
+        (self.get_endpoint_stall_state)(self, endpoint_index, direction, &mut state)
+            .into_with_val(|| state != 0)
+    }
+
+    /// Stalls an endpoint, or clears its stall, for example to reject a
+    /// setup packet on the control endpoint.
+    pub fn set_endpoint_stall_state(
+        &mut self,
+        endpoint_index: u8,
+        direction: EndpointDirection,
+        stalled: bool,
+    ) -> Result {
+        (self.set_endpoint_stall_state)(self, endpoint_index, direction, u8::from(stalled)).into()
+    }
+
+    /// Returns the next event of the controller, or
+    /// [`FunctionMessage::None`] if nothing happened since the last call.
+    ///
+    /// The firmware doesn't signal events, so this function must be called
+    /// regularly, for example from a timer, while the device is attached.
+    pub fn event_handler(&mut self) -> Result<FunctionMessage> {
+        let mut message = MessageType::NONE;
+        let mut payload = MaybeUninit::<MessagePayload>::zeroed();
+        let mut size = mem::size_of::<MessagePayload>();
+        Result::from(unsafe {
+            (self.event_handler)(self, &mut message, &mut size, payload.as_mut_ptr())
+        })?;
+        // Safety: the payload was zeroed, and the firmware initializes the
+        // field that matches the message.
+        let payload = unsafe { payload.assume_init() };
+        let message = unsafe {
+            match message {
+                MessageType::NONE => FunctionMessage::None,
+                MessageType::SETUP_PACKET => FunctionMessage::SetupPacket(payload.request),
+                MessageType::ENDPOINT_STATUS_CHANGED_RX => {
+                    FunctionMessage::EndpointStatusChangedRx(payload.transfer)
+                }
+                MessageType::ENDPOINT_STATUS_CHANGED_TX => {
+                    FunctionMessage::EndpointStatusChangedTx(payload.transfer)
+                }
+                MessageType::BUS_EVENT_DETACH => FunctionMessage::Detach,
+                MessageType::BUS_EVENT_ATTACH => FunctionMessage::Attach,
+                MessageType::BUS_EVENT_RESET => FunctionMessage::Reset,
+                MessageType::BUS_EVENT_SUSPEND => FunctionMessage::Suspend,
+                MessageType::BUS_EVENT_RESUME => FunctionMessage::Resume,
+                MessageType::BUS_EVENT_SPEED => FunctionMessage::Speed(payload.speed),
+                _ => return Err(Status::COMPROMISED_DATA.into()),
+            }
+        };
+        Ok(message)
+    }
+
+    /// Queues a transfer of `buffer` on an endpoint. Endpoint 0 is the
+    /// control endpoint, used to answer setup packets.
+    ///
+    /// The transfer happens in the background: its completion is reported
+    /// by [`event_handler`]. The endpoint index and direction of an
+    /// endpoint descriptor can be found with
+    /// [`EndpointDirection::split_address`].
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must have been allocated with [`allocate_transfer_buffer`],
+    /// and must not be used or freed until the completion or abort of the
+    /// transfer is reported.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the endpoint is not valid, or the
+    ///   buffer is too large, see [`get_max_transfer_size`].
+    /// * [`Status::NOT_READY`]: the device is not attached, or a transfer is
+    ///   already queued on the endpoint.
+    ///
+    /// [`event_handler`]: Self::event_handler
+    /// [`allocate_transfer_buffer`]: Self::allocate_transfer_buffer
+    /// [`get_max_transfer_size`]: Self::get_max_transfer_size
+    pub unsafe fn transfer(
+        &mut self,
+        endpoint_index: u8,
+        direction: EndpointDirection,
+        buffer: &mut [u8],
+    ) -> Result {
+        let mut size = buffer.len();
+        (self.transfer)(
+            self,
+            endpoint_index,
+            direction,
+            &mut size,
+            buffer.as_mut_ptr().cast(),
+        )
+        .into()
+    }
+
+    /// Returns the maximum size of a transfer.
+    pub fn get_max_transfer_size(&mut self) -> Result<usize> {
+        let mut size = 0;
+        (self.get_max_transfer_size)(self, &mut size).into_with_val(|| size)
+    }
+
+    /// Allocates a buffer of `size` bytes suitable for [`transfer`], for
+    /// example with the alignment required by the controller.
+    ///
+    /// The buffer must be freed with [`free_transfer_buffer`].
+    ///
+    /// [`transfer`]: Self::transfer
+    /// [`free_transfer_buffer`]: Self::free_transfer_buffer
+    pub fn allocate_transfer_buffer(&mut self, size: usize) -> Result<*mut u8> {
+        let mut buffer = ptr::null_mut();
+        (self.allocate_transfer_buffer)(self, size, &mut buffer).into_with_val(|| buffer.cast())
+    }
+
+    /// Frees a buffer allocated with [`allocate_transfer_buffer`].
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must have been returned by [`allocate_transfer_buffer`], and
+    /// must not be used by a transfer.
+    ///
+    /// [`allocate_transfer_buffer`]: Self::allocate_transfer_buffer
+    pub unsafe fn free_transfer_buffer(&mut self, buffer: *mut u8) -> Result {
+        (self.free_transfer_buffer)(self, buffer.cast()).into()
+    }
+
+    /// Starts the controller, so that the device is visible to the host
+    /// once configured.
+    pub fn start_controller(&mut self) -> Result {
+        (self.start_controller)(self).into()
+    }
+
+    /// Stops the controller. The device is detached from the host.
+    pub fn stop_controller(&mut self) -> Result {
+        (self.stop_controller)(self).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_address() {
+        assert_eq!(
+            EndpointDirection::split_address(0x81),
+            (1, EndpointDirection::HOST_IN)
+        );
+        assert_eq!(
+            EndpointDirection::split_address(0x02),
+            (2, EndpointDirection::DEVICE_RX)
+        );
+        assert_eq!(
+            EndpointDirection::split_address(0x8f),
+            (15, EndpointDirection::DEVICE_TX)
+        );
+        // Reserved bits are not part of the index.
+        assert_eq!(
+            EndpointDirection::split_address(0x73),
+            (3, EndpointDirection::HOST_OUT)
+        );
+    }
+
+    #[test]
+    fn test_descriptor_sizes() {
+        assert_eq!(mem::size_of::<DeviceDescriptor>(), 18);
+        assert_eq!(mem::size_of::<ConfigDescriptor>(), 9);
+        assert_eq!(mem::size_of::<InterfaceDescriptor>(), 9);
+        assert_eq!(mem::size_of::<EndpointDescriptor>(), 7);
+    }
+}
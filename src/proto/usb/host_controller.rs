@@ -0,0 +1,441 @@
+use super::DeviceRequest;
+use crate::proto::Protocol;
+use crate::{unsafe_guid, Result, Status};
+use bitflags::bitflags;
+use core::ffi::c_void;
+use core::ptr;
+
+/// Maximum number of data buffers of a bulk transfer.
+const MAX_BULK_BUFFER_NUM: usize = 10;
+
+newtype_enum! {
+    /// Speed of a USB device.
+    pub enum UsbSpeed: u8 => {
+        /// Full speed, 12 Mb/s.
+        FULL = 0,
+        /// Low speed, 1.5 Mb/s.
+        LOW = 1,
+        /// High speed, 480 Mb/s.
+        HIGH = 2,
+        /// Super speed, 5 Gb/s.
+        SUPER = 3,
+    }
+}
+
+newtype_enum! {
+    /// State of a USB host controller.
+    pub enum HostControllerState: u32 => {
+        /// The controller is halted; no transactions are performed.
+        HALT = 0,
+        /// The controller is running and executing transactions.
+        OPERATIONAL = 1,
+        /// The controller is suspended; no transactions are performed, but
+        /// the controller responds to resume signaling.
+        SUSPEND = 2,
+    }
+}
+
+newtype_enum! {
+    /// A feature of a root hub port that can be set or cleared.
+    pub enum PortFeature: u32 => {
+        /// Enable the port.
+        ENABLE = 1,
+        /// Suspend the port.
+        SUSPEND = 2,
+        /// Reset the port.
+        RESET = 4,
+        /// Power the port.
+        POWER = 8,
+        /// Hand the port to a companion controller.
+        OWNER = 13,
+        /// The connection change bit.
+        CONNECT_CHANGE = 16,
+        /// The enable change bit.
+        ENABLE_CHANGE = 17,
+        /// The suspend change bit.
+        SUSPEND_CHANGE = 18,
+        /// The over-current change bit.
+        OVER_CURRENT_CHANGE = 19,
+        /// The reset change bit.
+        RESET_CHANGE = 20,
+    }
+}
+
+bitflags! {
+    /// Kinds of reset of a host controller.
+    #[repr(transparent)]
+    pub struct ResetAttributes: u16 {
+        /// Reset all the controllers and devices on the bus.
+        const GLOBAL = 0x0001;
+        /// Reset only this host controller.
+        const HOST_CONTROLLER = 0x0002;
+        /// Like `GLOBAL`, but without resetting a debug port in use.
+        const GLOBAL_WITH_DEBUG = 0x0004;
+        /// Like `HOST_CONTROLLER`, but without resetting a debug port in use.
+        const HOST_WITH_DEBUG = 0x0008;
+    }
+}
+
+bitflags! {
+    /// Current state of a root hub port.
+    #[repr(transparent)]
+    pub struct PortStatus: u16 {
+        /// A device is connected.
+        const CONNECTION = 0x0001;
+        /// The port is enabled.
+        const ENABLE = 0x0002;
+        /// The port is suspended.
+        const SUSPEND = 0x0004;
+        /// An over-current condition exists.
+        const OVER_CURRENT = 0x0008;
+        /// The port is being reset.
+        const RESET = 0x0010;
+        /// The port is powered.
+        const POWER = 0x0100;
+        /// The connected device is a low speed device.
+        const LOW_SPEED = 0x0200;
+        /// The connected device is a high speed device.
+        const HIGH_SPEED = 0x0400;
+        /// The connected device is a super speed device.
+        const SUPER_SPEED = 0x0800;
+        /// The port is owned by a companion controller.
+        const OWNER = 0x2000;
+    }
+}
+
+bitflags! {
+    /// Changes of the state of a root hub port since the change bits were
+    /// last cleared with [`Usb2HostController::clear_root_hub_port_feature`].
+    #[repr(transparent)]
+    pub struct PortChangeStatus: u16 {
+        /// A device was connected or disconnected.
+        const CONNECTION = 0x0001;
+        /// The port was disabled.
+        const ENABLE = 0x0002;
+        /// The port finished resuming.
+        const SUSPEND = 0x0004;
+        /// The over-current condition changed.
+        const OVER_CURRENT = 0x0008;
+        /// The port finished resetting.
+        const RESET = 0x0010;
+    }
+}
+
+bitflags! {
+    /// Errors that occurred during a transfer.
+    #[repr(transparent)]
+    pub struct TransferResult: u32 {
+        /// The transfer was not executed.
+        const NOT_EXECUTE = 0x0001;
+        /// The device stalled the endpoint.
+        const STALL = 0x0002;
+        /// A data buffer error occurred.
+        const BUFFER = 0x0004;
+        /// The device sent more data than expected.
+        const BABBLE = 0x0008;
+        /// The device responded with NAK.
+        const NAK = 0x0010;
+        /// A CRC error occurred.
+        const CRC = 0x0020;
+        /// The transfer timed out.
+        const TIMEOUT = 0x0040;
+        /// A bit stuffing error occurred.
+        const BIT_STUFF = 0x0080;
+        /// A system error occurred.
+        const SYSTEM = 0x0100;
+    }
+}
+
+/// Status of a root hub port, returned by
+/// [`Usb2HostController::get_root_hub_port_status`].
+///
+/// Corresponds to the `EFI_USB_PORT_STATUS` type in the C API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct UsbPortStatus {
+    /// Current state of the port.
+    pub port_status: PortStatus,
+    /// Changes of the state of the port.
+    pub port_change_status: PortChangeStatus,
+}
+
+/// Capabilities of a host controller, returned by
+/// [`Usb2HostController::get_capability`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UsbCapability {
+    /// Highest speed supported by the controller.
+    pub max_speed: UsbSpeed,
+    /// Number of root hub ports.
+    pub port_number: u8,
+    /// Whether the controller can access memory above 4 GiB.
+    pub is_64_bit_capable: bool,
+}
+
+/// The transaction translator of the high speed hub a low or full speed
+/// device is connected through.
+///
+/// Corresponds to the `EFI_USB2_HC_TRANSACTION_TRANSLATOR` type in the C API.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct TransactionTranslator {
+    /// Address of the hub.
+    pub hub_address: u8,
+    /// Port of the hub the device is connected to.
+    pub port_number: u8,
+}
+
+/// The data stage of a control transfer.
+#[derive(Debug)]
+pub enum ControlTransferData<'a> {
+    /// The transfer has no data stage.
+    None,
+    /// Data is read from the device into the buffer.
+    In(&'a mut [u8]),
+    /// Data is written from the buffer to the device.
+    Out(&'a [u8]),
+}
+
+/// Corresponds to `EFI_USB_DATA_DIRECTION`.
+#[derive(Clone, Copy)]
+#[repr(u32)]
+enum DataDirection {
+    In = 0,
+    Out = 1,
+    NoData = 2,
+}
+
+/// The USB2 host controller protocol.
+///
+/// This protocol gives direct access to a USB host controller and its root
+/// hub, for example to drive a device no driver has been bound to.
+#[repr(C)]
+#[unsafe_guid("3e745226-9818-45b6-a2ac-d7cd0e8ba2bc")]
+#[derive(Protocol)]
+pub struct Usb2HostController {
+    get_capability: extern "efiapi" fn(
+        this: &Self,
+        max_speed: &mut UsbSpeed,
+        port_number: &mut u8,
+        is_64_bit_capable: &mut u8,
+    ) -> Status,
+    reset: extern "efiapi" fn(this: &mut Self, attributes: ResetAttributes) -> Status,
+    get_state: extern "efiapi" fn(this: &Self, state: &mut HostControllerState) -> Status,
+    set_state: extern "efiapi" fn(this: &mut Self, state: HostControllerState) -> Status,
+    control_transfer: unsafe extern "efiapi" fn(
+        this: &mut Self,
+        device_address: u8,
+        device_speed: UsbSpeed,
+        maximum_packet_length: usize,
+        request: *const DeviceRequest,
+        transfer_direction: DataDirection,
+        data: *mut c_void,
+        data_length: *mut usize,
+        timeout: usize,
+        translator: *const TransactionTranslator,
+        transfer_result: &mut TransferResult,
+    ) -> Status,
+    bulk_transfer: unsafe extern "efiapi" fn(
+        this: &mut Self,
+        device_address: u8,
+        endpoint_address: u8,
+        device_speed: UsbSpeed,
+        maximum_packet_length: usize,
+        data_buffers_number: u8,
+        data: *const [*mut c_void; MAX_BULK_BUFFER_NUM],
+        data_length: *mut usize,
+        data_toggle: *mut u8,
+        timeout: usize,
+        translator: *const TransactionTranslator,
+        transfer_result: &mut TransferResult,
+    ) -> Status,
+    async_interrupt_transfer: usize,
+    sync_interrupt_transfer: usize,
+    isochronous_transfer: usize,
+    async_isochronous_transfer: usize,
+    get_root_hub_port_status:
+        extern "efiapi" fn(this: &Self, port_number: u8, port_status: &mut UsbPortStatus) -> Status,
+    set_root_hub_port_feature:
+        extern "efiapi" fn(this: &mut Self, port_number: u8, port_feature: PortFeature) -> Status,
+    clear_root_hub_port_feature:
+        extern "efiapi" fn(this: &mut Self, port_number: u8, port_feature: PortFeature) -> Status,
+    major_revision: u16,
+    minor_revision: u16,
+}
+
+impl Usb2HostController {
+    /// Returns the revision of the USB specification the controller
+    /// supports, as `(major, minor)`.
+    pub fn revision(&self) -> (u16, u16) {
+        (self.major_revision, self.minor_revision)
+    }
+
+    /// Returns the capabilities of the controller.
+    pub fn get_capability(&self) -> Result<UsbCapability> {
+        let mut max_speed = UsbSpeed::FULL;
+        let mut port_number = 0;
+        let mut is_64_bit_capable = 0;
+        (self.get_capability)(
+            self,
+            &mut max_speed,
+            &mut port_number,
+            &mut is_64_bit_capable,
+        )
+        .into_with_val(|| UsbCapability {
+            max_speed,
+            port_number,
+            is_64_bit_capable: is_64_bit_capable != 0,
+        })
+    }
+
+    /// Resets the controller, and depending on `attributes`, the devices on
+    /// the bus.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the reset type is not supported.
+    /// * [`Status::ACCESS_DENIED`]: a debug port is in use, so only the
+    ///   `_WITH_DEBUG` reset types are allowed.
+    pub fn reset(&mut self, attributes: ResetAttributes) -> Result {
+        (self.reset)(self, attributes).into()
+    }
+
+    /// Returns the current state of the controller.
+    pub fn get_state(&self) -> Result<HostControllerState> {
+        let mut state = HostControllerState::HALT;
+        (self.get_state)(self, &mut state).into_with_val(|| state)
+    }
+
+    /// Changes the state of the controller.
+    pub fn set_state(&mut self, state: HostControllerState) -> Result {
+        (self.set_state)(self, state).into()
+    }
+
+    /// Returns the status of the root hub port `port_number`. Ports are
+    /// numbered from 0 to [`UsbCapability::port_number`] - 1.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the port number is not valid.
+    pub fn get_root_hub_port_status(&self, port_number: u8) -> Result<UsbPortStatus> {
+        let mut status = UsbPortStatus {
+            port_status: PortStatus::empty(),
+            port_change_status: PortChangeStatus::empty(),
+        };
+        (self.get_root_hub_port_status)(self, port_number, &mut status).into_with_val(|| status)
+    }
+
+    /// Sets `feature` on the root hub port `port_number`.
+    pub fn set_root_hub_port_feature(&mut self, port_number: u8, feature: PortFeature) -> Result {
+        (self.set_root_hub_port_feature)(self, port_number, feature).into()
+    }
+
+    /// Clears `feature` on the root hub port `port_number`. This is also
+    /// used to acknowledge the change bits, using the `_CHANGE` features.
+    pub fn clear_root_hub_port_feature(&mut self, port_number: u8, feature: PortFeature) -> Result {
+        (self.clear_root_hub_port_feature)(self, port_number, feature).into()
+    }
+
+    /// Performs a control transfer with `request` to the device at
+    /// `device_address`, returning the number of bytes transferred in the
+    /// data stage.
+    ///
+    /// `translator` must be given for low and full speed devices connected
+    /// through a high speed hub. The timeout is in milliseconds, 0 waits
+    /// forever.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::DEVICE_ERROR`]: the transfer failed. The error contains
+    ///   the reason reported by the controller.
+    /// * [`Status::TIMEOUT`]: the transfer timed out.
+    /// * [`Status::INVALID_PARAMETER`]: a parameter is not valid, for
+    ///   example the maximum packet length for the device speed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn control_transfer(
+        &mut self,
+        device_address: u8,
+        device_speed: UsbSpeed,
+        maximum_packet_length: usize,
+        request: &DeviceRequest,
+        data: ControlTransferData<'_>,
+        timeout: usize,
+        translator: Option<&TransactionTranslator>,
+    ) -> Result<usize, TransferResult> {
+        let (direction, buffer, mut length) = match data {
+            ControlTransferData::None => (DataDirection::NoData, ptr::null_mut(), 0),
+            ControlTransferData::In(buffer) => {
+                (DataDirection::In, buffer.as_mut_ptr().cast(), buffer.len())
+            }
+            ControlTransferData::Out(buffer) => (
+                DataDirection::Out,
+                buffer.as_ptr() as *mut c_void,
+                buffer.len(),
+            ),
+        };
+        let mut transfer_result = TransferResult::empty();
+        let status = unsafe {
+            (self.control_transfer)(
+                self,
+                device_address,
+                device_speed,
+                maximum_packet_length,
+                request,
+                direction,
+                buffer,
+                &mut length,
+                timeout,
+                translator.map_or(ptr::null(), |translator| translator as *const _),
+                &mut transfer_result,
+            )
+        };
+        status.into_with(|| length, |_| transfer_result)
+    }
+
+    /// Performs a bulk transfer with the endpoint `endpoint_address` of the
+    /// device at `device_address`, returning the number of bytes
+    /// transferred.
+    ///
+    /// The direction of the transfer is given by bit 7 of the endpoint
+    /// address: if it is set, data is read into `data`, otherwise `data` is
+    /// written to the device. `data_toggle` holds the data toggle to start
+    /// with, and is updated for the next transfer. The timeout is in
+    /// milliseconds, 0 waits forever.
+    ///
+    /// See [`control_transfer`] for the errors.
+    ///
+    /// [`control_transfer`]: Self::control_transfer
+    #[allow(clippy::too_many_arguments)]
+    pub fn bulk_transfer(
+        &mut self,
+        device_address: u8,
+        endpoint_address: u8,
+        device_speed: UsbSpeed,
+        maximum_packet_length: usize,
+        data: &mut [u8],
+        data_toggle: &mut u8,
+        timeout: usize,
+        translator: Option<&TransactionTranslator>,
+    ) -> Result<usize, TransferResult> {
+        let mut buffers = [ptr::null_mut(); MAX_BULK_BUFFER_NUM];
+        buffers[0] = data.as_mut_ptr().cast();
+        let mut length = data.len();
+        let mut transfer_result = TransferResult::empty();
+        let status = unsafe {
+            (self.bulk_transfer)(
+                self,
+                device_address,
+                endpoint_address,
+                device_speed,
+                maximum_packet_length,
+                1,
+                &buffers,
+                &mut length,
+                data_toggle,
+                timeout,
+                translator.map_or(ptr::null(), |translator| translator as *const _),
+                &mut transfer_result,
+            )
+        };
+        status.into_with(|| length, |_| transfer_result)
+    }
+}
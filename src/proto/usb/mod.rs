@@ -0,0 +1,35 @@
+//! USB protocols.
+
+mod function;
+pub use self::function::{
+    ConfigDescriptor, ConfigInfo, DeviceDescriptor, DeviceInfo, DeviceInfoId, EndpointDescriptor,
+    EndpointDirection, EndpointType, FunctionMessage, FunctionTransferResult,
+    FunctionTransferStatus, InterfaceDescriptor, InterfaceInfo, PortType, UsbBusSpeed,
+    UsbFunctionIo,
+};
+
+mod host_controller;
+pub use self::host_controller::{
+    ControlTransferData, HostControllerState, PortChangeStatus, PortFeature, PortStatus,
+    ResetAttributes, TransactionTranslator, TransferResult, Usb2HostController, UsbCapability,
+    UsbPortStatus, UsbSpeed,
+};
+
+/// A USB device request sent in the setup stage of a control transfer, as
+/// defined in chapter 9 of the USB specification.
+///
+/// Corresponds to the `EFI_USB_DEVICE_REQUEST` type in the C API.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct DeviceRequest {
+    /// Direction, type and recipient of the request.
+    pub request_type: u8,
+    /// The specific request.
+    pub request: u8,
+    /// Request-specific value.
+    pub value: u16,
+    /// Request-specific index, often an interface or endpoint number.
+    pub index: u16,
+    /// Number of bytes to transfer in the data stage.
+    pub length: u16,
+}
@@ -101,9 +101,24 @@ pub enum Status: usize => {
     IP_ADDRESS_CONFLICT     = ERROR_BIT | 34,
     /// A HTTP error occurred during the network operation.
     HTTP_ERROR              = ERROR_BIT | 35,
+    /// The remote side closed the connection.
+    CONNECTION_FIN          = ERROR_BIT | 104,
+    /// The remote side reset the connection.
+    CONNECTION_RESET        = ERROR_BIT | 105,
+    /// The remote side refused the connection.
+    CONNECTION_REFUSED      = ERROR_BIT | 106,
 }}
 
 impl Status {
+    /// Returns the name of the status code, for example `"NOT_FOUND"`, or
+    /// `"UNKNOWN"` for implementation-specific status codes.
+    pub fn message(self) -> &'static str {
+        Self::VARIANTS
+            .iter()
+            .find(|&&(status, _)| status == self)
+            .map_or("UNKNOWN", |&(_, name)| name)
+    }
+
     /// Returns true if status code indicates success.
     #[inline]
     pub fn is_success(self) -> bool {
@@ -122,6 +137,21 @@ impl Status {
         self.0 & ERROR_BIT != 0
     }
 
+    /// Converts this status code into a result that is only an error if the
+    /// status is an error. Success and warnings are returned in `Ok`, so
+    /// that the caller can decide what to do with the warning.
+    ///
+    /// The other conversions treat warnings as errors, which is rarely
+    /// wanted for warnings like [`Status::WARN_UNKNOWN_GLYPH`].
+    #[inline]
+    pub fn into_result_with_warnings(self) -> Result<Status, ()> {
+        if self.is_error() {
+            Err(self.into())
+        } else {
+            Ok(self)
+        }
+    }
+
     /// Converts this status code into a result with a given value.
     #[inline]
     pub fn into_with_val<T>(self, val: impl FnOnce() -> T) -> Result<T, ()> {
@@ -172,6 +202,44 @@ impl From<Status> for Result<(), ()> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_status_message() {
+        assert_eq!(Status::SUCCESS.message(), "SUCCESS");
+        assert_eq!(Status::NOT_FOUND.message(), "NOT_FOUND");
+        assert_eq!(Status(ERROR_BIT | 0x1234).message(), "UNKNOWN");
+    }
+
+    #[test]
+    fn test_status_classification() {
+        assert!(Status::SUCCESS.is_success());
+        assert!(!Status::SUCCESS.is_warning());
+        assert!(!Status::SUCCESS.is_error());
+
+        assert!(!Status::WARN_DELETE_FAILURE.is_success());
+        assert!(Status::WARN_DELETE_FAILURE.is_warning());
+        assert!(!Status::WARN_DELETE_FAILURE.is_error());
+
+        assert!(!Status::NOT_FOUND.is_success());
+        assert!(!Status::NOT_FOUND.is_warning());
+        assert!(Status::NOT_FOUND.is_error());
+
+        assert_eq!(
+            Status::SUCCESS.into_result_with_warnings(),
+            Ok(Status::SUCCESS)
+        );
+        assert_eq!(
+            Status::WARN_UNKNOWN_GLYPH.into_result_with_warnings(),
+            Ok(Status::WARN_UNKNOWN_GLYPH)
+        );
+        assert_eq!(
+            Status::NOT_FOUND
+                .into_result_with_warnings()
+                .unwrap_err()
+                .status(),
+            Status::NOT_FOUND
+        );
+    }
+
     #[test]
     fn test_status_to_result() {
         assert!(Result::from(Status::SUCCESS).is_ok());
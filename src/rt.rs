@@ -0,0 +1,143 @@
+//! Cooperative execution of periodic tasks.
+//!
+//! An [`Executor`] runs tasks at regular intervals, driven by timer events.
+//! This makes it possible to interleave background work, such as polling a
+//! network interface, with other work in the main loop of an application.
+//!
+//! The executor is not preemptive: each task runs to completion before the
+//! next one starts, so tasks must be short. The timer events don't have a
+//! notification function, so no task code runs at a raised TPL; tasks run
+//! from [`Executor::run`] or [`Executor::poll`] at the TPL of the caller,
+//! normally [`Tpl::APPLICATION`].
+//!
+//! This module is only available with the `exts` feature.
+//!
+//! [`Tpl::APPLICATION`]: crate::table::boot::Tpl::APPLICATION
+
+use crate::alloc_api::{boxed::Box, vec::Vec};
+use crate::table::boot::{BootServices, EventType, TimerTrigger, Tpl};
+use crate::{Event, Result, ResultExt};
+use core::ops::ControlFlow;
+use core::time::Duration;
+
+/// A periodic task, along with the timer event that schedules it.
+struct Task<'a> {
+    event: Event,
+    run: Box<dyn FnMut() -> ControlFlow<()> + 'a>,
+}
+
+/// Runs tasks at regular intervals.
+///
+/// See the [module-level documentation](self) for details.
+pub struct Executor<'a> {
+    bt: &'a BootServices,
+    tasks: Vec<Task<'a>>,
+}
+
+impl<'a> Executor<'a> {
+    /// Create an executor without tasks.
+    pub fn new(bt: &'a BootServices) -> Self {
+        Self {
+            bt,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Schedule `task` to run every `interval`.
+    ///
+    /// The task keeps running until it returns [`ControlFlow::Break`]. If a
+    /// task runs later than its interval, for example because another task
+    /// took too long, the missed runs are skipped rather than queued. An
+    /// `interval` of zero runs the task on every timer tick.
+    ///
+    /// The interval is rounded down to a multiple of 100ns, the resolution
+    /// of UEFI timers.
+    pub fn spawn_periodic<F>(&mut self, interval: Duration, task: F) -> Result
+    where
+        F: FnMut() -> ControlFlow<()> + 'a,
+    {
+        // Safety: the event doesn't have a notification function.
+        let event = unsafe {
+            self.bt
+                .create_event(EventType::TIMER, Tpl::APPLICATION, None, None)?
+        };
+        let hundreds_ns = u64::try_from(interval.as_nanos() / 100).unwrap_or(u64::MAX);
+        if let Err(err) = self
+            .bt
+            .set_timer(&event, TimerTrigger::Periodic(hundreds_ns))
+        {
+            // The timer error is more relevant than a failure to close.
+            let _ = self.bt.close_event(event);
+            return Err(err);
+        }
+
+        self.tasks.push(Task {
+            event,
+            run: Box::new(task),
+        });
+        Ok(())
+    }
+
+    /// The number of scheduled tasks.
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns `true` if no tasks are scheduled.
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Run the tasks that are due once, without waiting.
+    pub fn poll(&mut self) -> Result {
+        self.run_due(None)
+    }
+
+    /// Run the tasks until they have all finished.
+    ///
+    /// Between runs, this waits for the next task to become due with
+    /// [`BootServices::wait_for_event`], so the processor is idle.
+    pub fn run(&mut self) -> Result {
+        while !self.tasks.is_empty() {
+            // Safety: the clones are only used by `wait_for_event`, while
+            // the events are still open.
+            let mut events: Vec<Event> = self
+                .tasks
+                .iter()
+                .map(|task| unsafe { task.event.unsafe_clone() })
+                .collect();
+            let signaled = self.bt.wait_for_event(&mut events).discard_errdata()?;
+            self.run_due(Some(signaled))?;
+        }
+        Ok(())
+    }
+
+    /// Run the tasks whose events are signaled. `signaled` is the index of
+    /// a task whose event was already reset by `wait_for_event`.
+    fn run_due(&mut self, signaled: Option<usize>) -> Result {
+        let mut index = 0;
+        let mut position = 0;
+        while index < self.tasks.len() {
+            let due =
+                signaled == Some(position) || self.bt.check_event(&self.tasks[index].event)?;
+            position += 1;
+
+            if due && (self.tasks[index].run)().is_break() {
+                let task = self.tasks.remove(index);
+                self.bt.close_event(task.event)?;
+            } else {
+                index += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Executor<'_> {
+    fn drop(&mut self) {
+        for task in self.tasks.drain(..) {
+            // Nothing can be done if closing fails.
+            let _ = self.bt.close_event(task.event);
+        }
+    }
+}
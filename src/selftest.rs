@@ -0,0 +1,370 @@
+//! Self-tests of the UEFI implementation.
+//!
+//! These functions exercise the boot services and common protocols, and
+//! return a [`SelfTestReport`] with the outcome of each check instead of
+//! panicking. They can be used to probe a firmware implementation for
+//! conformance problems, for example in the CI of a firmware project.
+//!
+//! The checks only use the firmware in ways that can be undone: memory
+//! that is allocated is freed, events are closed, and the task priority
+//! level is restored. Checks of protocols that are not available are
+//! reported as [`CheckOutcome::Skipped`].
+
+use crate::alloc_api::vec::Vec;
+use crate::proto::console::gop::{GraphicsOutput, PixelFormat};
+use crate::proto::device_path::{DevicePath, DevicePathHeader};
+use crate::proto::loaded_image::LoadedImage;
+use crate::proto::media::file::{File, FileSystemInfo};
+use crate::proto::ProtocolPointer;
+use crate::table::boot::{
+    AllocateType, BootServices, EventType, MemoryType, OpenProtocolAttributes, OpenProtocolParams,
+    ScopedProtocol, SearchType, TimerTrigger, Tpl,
+};
+use crate::table::{Boot, SystemTable};
+use crate::{Handle, Result, Status};
+use core::fmt::Debug;
+use core::mem;
+
+/// Outcome of a single check.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckOutcome {
+    /// The check passed.
+    Passed,
+    /// The check was not run, because the protocol it checks is not
+    /// available.
+    Skipped,
+    /// A function of the firmware failed with this status.
+    Failed(Status),
+    /// A function of the firmware succeeded, but its result is wrong.
+    Incorrect(&'static str),
+}
+
+impl<Data: Debug> From<crate::Error<Data>> for CheckOutcome {
+    fn from(err: crate::Error<Data>) -> Self {
+        Self::Failed(err.status())
+    }
+}
+
+/// Result of the functions implementing the checks: both variants are
+/// outcomes, and the error variant allows using `?` on firmware functions.
+type CheckResult = core::result::Result<CheckOutcome, CheckOutcome>;
+
+/// Return an [`CheckOutcome::Incorrect`] error unless `condition` holds.
+fn ensure(condition: bool, reason: &'static str) -> core::result::Result<(), CheckOutcome> {
+    if condition {
+        Ok(())
+    } else {
+        Err(CheckOutcome::Incorrect(reason))
+    }
+}
+
+/// A check, along with its outcome.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Check {
+    /// Name of the check, for example `allocate_pages`.
+    pub name: &'static str,
+    /// Outcome of the check.
+    pub outcome: CheckOutcome,
+}
+
+/// Outcome of the checks run by a self-test.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SelfTestReport {
+    checks: Vec<Check>,
+}
+
+impl SelfTestReport {
+    /// All the checks, in the order they were run.
+    pub fn checks(&self) -> &[Check] {
+        &self.checks
+    }
+
+    /// The checks that failed or returned incorrect results.
+    pub fn failures(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|check| {
+            matches!(
+                check.outcome,
+                CheckOutcome::Failed(_) | CheckOutcome::Incorrect(_)
+            )
+        })
+    }
+
+    /// Whether no check failed. Skipped checks don't count as failures.
+    pub fn all_passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Append the checks of `other` to this report.
+    pub fn merge(&mut self, other: SelfTestReport) {
+        self.checks.extend(other.checks);
+    }
+
+    /// Run a check and record its outcome.
+    fn run(&mut self, name: &'static str, check: impl FnOnce() -> CheckResult) {
+        let outcome = match check() {
+            Ok(outcome) | Err(outcome) => outcome,
+        };
+        self.checks.push(Check { name, outcome });
+    }
+}
+
+/// Check the boot services.
+///
+/// This covers memory allocation and the memory map, events and timers,
+/// task priority levels, the memory and CRC32 helpers, and handle
+/// lookups.
+pub fn run_boot_services_tests(bt: &BootServices) -> SelfTestReport {
+    let mut report = SelfTestReport::default();
+    report.run("allocate_pages", || check_allocate_pages(bt));
+    report.run("allocate_pool", || check_allocate_pool(bt));
+    report.run("memory_map", || check_memory_map(bt));
+    report.run("timer_event", || check_timer_event(bt));
+    report.run("signal_event", || check_signal_event(bt));
+    report.run("raise_tpl", || check_raise_tpl(bt));
+    report.run("calculate_crc32", || check_crc32(bt));
+    report.run("copy_mem", || check_copy_mem(bt));
+    report.run("locate_handle_buffer", || check_locate_handle_buffer(bt));
+    report
+}
+
+/// Check the protocols that are commonly installed by the firmware, and
+/// the runtime services that are available before exiting the boot
+/// services.
+///
+/// `image` must be the handle of the running image, and is used to check
+/// the protocols installed for it: [`LoadedImage`], the [`DevicePath`] of
+/// the device it was loaded from, and the file system of that device.
+/// This also checks the text console, [`GraphicsOutput`], and the clock
+/// of the runtime services.
+pub fn run_protocol_tests(image: Handle, st: &mut SystemTable<Boot>) -> SelfTestReport {
+    let mut report = SelfTestReport::default();
+    report.run("text_output", || check_text_output(st));
+    let bt = st.boot_services();
+    report.run("loaded_image", || check_loaded_image(image, bt));
+    report.run("image_file_system", || check_image_file_system(image, bt));
+    report.run("graphics_output", || check_graphics_output(bt));
+    report.run("get_time", || {
+        let time = st.runtime_services().get_time()?;
+        ensure(time.is_valid(), "the time is not valid")?;
+        Ok(CheckOutcome::Passed)
+    });
+    report
+}
+
+fn check_allocate_pages(bt: &BootServices) -> CheckResult {
+    let address = bt.allocate_pages(AllocateType::AnyPages, MemoryType::LOADER_DATA, 1)?;
+    let aligned = address % 4096 == 0;
+    if aligned {
+        // The page must be writable.
+        let page = unsafe { &mut *(address as *mut [u8; 4096]) };
+        page[0] = 0xf0;
+        page[4095] = 0x23;
+    }
+    bt.free_pages(address, 1)?;
+    ensure(aligned, "the pages are not aligned")?;
+    Ok(CheckOutcome::Passed)
+}
+
+fn check_allocate_pool(bt: &BootServices) -> CheckResult {
+    let buffer = bt.allocate_pool(MemoryType::LOADER_DATA, 100)?;
+    let aligned = buffer as usize % 8 == 0;
+    bt.free_pool(buffer)?;
+    ensure(aligned, "the pool memory is not aligned on 8 bytes")?;
+    Ok(CheckOutcome::Passed)
+}
+
+fn check_memory_map(bt: &BootServices) -> CheckResult {
+    let map = bt.memory_map_owned()?;
+    let mut entries = map.entries().to_vec();
+    ensure(!entries.is_empty(), "the memory map is empty")?;
+    ensure(
+        entries
+            .iter()
+            .any(|entry| entry.ty == MemoryType::LOADER_CODE),
+        "the memory map has no loader code",
+    )?;
+
+    entries.sort_unstable_by_key(|entry| entry.phys_start);
+    for pair in entries.windows(2) {
+        let end = pair[0]
+            .page_count
+            .checked_mul(4096)
+            .and_then(|size| pair[0].phys_start.checked_add(size))
+            .ok_or(CheckOutcome::Incorrect(
+                "an entry of the memory map ends past the address space",
+            ))?;
+        ensure(
+            end <= pair[1].phys_start,
+            "entries of the memory map overlap",
+        )?;
+    }
+    Ok(CheckOutcome::Passed)
+}
+
+fn check_timer_event(bt: &BootServices) -> CheckResult {
+    let event = unsafe { bt.create_event(EventType::TIMER, Tpl::APPLICATION, None, None) }?;
+    let result = bt
+        .set_timer(&event, TimerTrigger::Relative(1_000))
+        .and_then(|_| {
+            let mut events = unsafe { [event.unsafe_clone()] };
+            bt.wait_for_event(&mut events)
+                .map_err(|err| err.status().into())
+        })
+        .and_then(|_| {
+            // Waiting for the event cleared its signaled state.
+            bt.check_event(&event)
+        });
+    bt.close_event(event)?;
+    ensure(!result?, "waiting for the event didn't clear its state")?;
+    Ok(CheckOutcome::Passed)
+}
+
+fn check_signal_event(bt: &BootServices) -> CheckResult {
+    let event = unsafe { bt.create_event(EventType::empty(), Tpl::APPLICATION, None, None) }?;
+    let result = bt.signal_event(&event).and_then(|_| {
+        let signaled = bt.check_event(&event)?;
+        let cleared = !bt.check_event(&event)?;
+        Ok((signaled, cleared))
+    });
+    bt.close_event(event)?;
+    let (signaled, cleared) = result?;
+    ensure(signaled, "the event was not signaled")?;
+    ensure(cleared, "checking the event didn't clear its state")?;
+    Ok(CheckOutcome::Passed)
+}
+
+fn check_raise_tpl(bt: &BootServices) -> CheckResult {
+    let old_tpl = bt.current_tpl();
+    if old_tpl.0 > Tpl::CALLBACK.0 {
+        return Ok(CheckOutcome::Skipped);
+    }
+    let raised = {
+        let _guard = unsafe { bt.raise_tpl(Tpl::CALLBACK) };
+        bt.current_tpl()
+    };
+    ensure(raised == Tpl::CALLBACK, "the TPL was not raised")?;
+    ensure(bt.current_tpl() == old_tpl, "the TPL was not restored")?;
+    Ok(CheckOutcome::Passed)
+}
+
+fn check_crc32(bt: &BootServices) -> CheckResult {
+    let crc = bt.calculate_crc32(b"123456789")?;
+    ensure(crc == 0xcbf4_3926, "the CRC32 is wrong")?;
+    Ok(CheckOutcome::Passed)
+}
+
+fn check_copy_mem(bt: &BootServices) -> CheckResult {
+    let mut buffer = [0u8; 8];
+    bt.fill_mem(&mut buffer, 0xaa);
+    ensure(buffer == [0xaa; 8], "fill_mem didn't fill the buffer")?;
+
+    bt.copy_mem(&mut buffer[..4], &[1, 2, 3, 4]);
+    bt.copy_mem_within(&mut buffer, 0..4, 2);
+    ensure(
+        buffer == [1, 2, 1, 2, 3, 4, 0xaa, 0xaa],
+        "overlapping copies are wrong",
+    )?;
+    Ok(CheckOutcome::Passed)
+}
+
+fn check_locate_handle_buffer(bt: &BootServices) -> CheckResult {
+    let handles = bt.locate_handle_buffer(SearchType::AllHandles)?;
+    ensure(
+        handles.handles().contains(&bt.image_handle()),
+        "the image handle was not found",
+    )?;
+    let images = bt.locate_handle_buffer(SearchType::from_proto::<LoadedImage>())?;
+    ensure(
+        images.handles().len() <= handles.handles().len(),
+        "searching by protocol returned more handles than exist",
+    )?;
+    Ok(CheckOutcome::Passed)
+}
+
+fn check_text_output(st: &mut SystemTable<Boot>) -> CheckResult {
+    let stdout = st.stdout();
+    ensure(
+        stdout.current_mode()?.is_some(),
+        "the text output has no mode",
+    )?;
+    let first = stdout.modes().next();
+    ensure(
+        first.map_or(false, |mode| mode.columns() == 80 && mode.rows() == 25),
+        "text mode 0 is not 80x25",
+    )?;
+    Ok(CheckOutcome::Passed)
+}
+
+/// Open a protocol of `handle` without affecting other agents.
+///
+/// # Safety
+///
+/// The protocol must not be uninstalled or used elsewhere while it is open.
+unsafe fn open<P: ProtocolPointer + ?Sized>(
+    bt: &BootServices,
+    handle: Handle,
+) -> Result<ScopedProtocol<'_, P>> {
+    bt.open_protocol::<P>(
+        OpenProtocolParams {
+            handle,
+            agent: bt.image_handle(),
+            controller: None,
+        },
+        OpenProtocolAttributes::GetProtocol,
+    )
+}
+
+fn check_loaded_image(image: Handle, bt: &BootServices) -> CheckResult {
+    let loaded_image = unsafe { open::<LoadedImage>(bt, image) }?;
+    let (base, size) = loaded_image.info();
+    ensure(!base.is_null() && size > 0, "the image is not in memory")?;
+
+    let device_path = unsafe { open::<DevicePath>(bt, loaded_image.device()) }?;
+    ensure(
+        device_path.len_bytes() >= mem::size_of::<DevicePathHeader>(),
+        "the device path has no end node",
+    )?;
+    Ok(CheckOutcome::Passed)
+}
+
+fn check_image_file_system(image: Handle, bt: &BootServices) -> CheckResult {
+    // The image may have been loaded from a device without file system,
+    // such as the network.
+    let mut fs = match bt.get_image_file_system(image) {
+        Ok(fs) => fs,
+        Err(err) if err.status() == Status::NOT_FOUND || err.status() == Status::UNSUPPORTED => {
+            return Ok(CheckOutcome::Skipped)
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let mut root = fs.open_volume()?;
+    let info = root.get_boxed_info::<FileSystemInfo>()?;
+    ensure(
+        info.free_space() <= info.volume_size(),
+        "the free space is larger than the volume",
+    )?;
+    Ok(CheckOutcome::Passed)
+}
+
+fn check_graphics_output(bt: &BootServices) -> CheckResult {
+    let mut gop = match unsafe { bt.get_first_protocol::<GraphicsOutput>() } {
+        Ok(gop) => gop,
+        Err(err) if err.status() == Status::NOT_FOUND => return Ok(CheckOutcome::Skipped),
+        Err(err) => return Err(err.into()),
+    };
+    let info = gop.current_mode_info();
+    let (width, height) = info.resolution();
+    ensure(width > 0 && height > 0, "the resolution is empty")?;
+    ensure(
+        info.stride() >= width,
+        "the stride is smaller than the width",
+    )?;
+    if info.pixel_format() != PixelFormat::BltOnly {
+        // All the pixel formats use 32 bits per pixel.
+        ensure(
+            gop.frame_buffer().size() >= info.stride() * height * 4,
+            "the frame buffer is too small for the mode",
+        )?;
+    }
+    Ok(CheckOutcome::Passed)
+}
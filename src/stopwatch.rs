@@ -0,0 +1,437 @@
+//! Measurement of elapsed time.
+//!
+//! A [`Stopwatch`] reads the [`Timestamp`] protocol if the firmware provides
+//! it, and the real time clock of the runtime services otherwise:
+//!
+//! * The timestamp counter has the resolution given by its
+//!   [`frequency`], usually well below a microsecond. It wraps around after
+//!   [`end_value`], which is handled if it wraps at most once between two
+//!   readings.
+//! * The real time clock usually has a resolution of one second, as reported
+//!   by [`TimeCapabilities::resolution`], so it is only suited to measuring
+//!   long durations. Readings are compared as dates, so durations over the
+//!   end of a day, a month or a year are measured correctly. If the clock is
+//!   set back, the elapsed time is zero rather than negative.
+//!
+//! [`frequency`]: crate::proto::misc::TimestampProperties::frequency
+//! [`end_value`]: crate::proto::misc::TimestampProperties::end_value
+//! [`TimeCapabilities::resolution`]: crate::table::runtime::TimeCapabilities::resolution
+
+use crate::proto::misc::Timestamp;
+use crate::table::boot::{
+    BootServices, OpenProtocolAttributes, OpenProtocolParams, ScopedProtocol,
+};
+use crate::table::runtime::{RuntimeServices, Time};
+use crate::{Result, Status};
+use core::fmt::{self, Debug, Formatter};
+use core::time::Duration;
+
+/// Nanoseconds in a second.
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// The clock read by a [`Stopwatch`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClockSource {
+    /// The counter of the [`Timestamp`] protocol.
+    Timestamp,
+    /// The real time clock, read with [`RuntimeServices::get_time`].
+    Rtc,
+}
+
+/// The clock of a stopwatch, with what is needed to convert its readings.
+enum Clock<'a> {
+    Timestamp {
+        protocol: ScopedProtocol<'a, Timestamp>,
+        frequency: u64,
+        end_value: u64,
+    },
+    Rtc {
+        rt: &'a RuntimeServices,
+        /// Counts per second of the clock.
+        resolution: u32,
+    },
+}
+
+/// A reading of a [`Clock`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Reading {
+    /// Value of the timestamp counter.
+    Ticks(u64),
+    /// Time of the real time clock, in seconds since 1970 and nanoseconds.
+    Rtc { seconds: i64, nanosecond: u32 },
+}
+
+impl Clock<'_> {
+    fn read(&self) -> Result<Reading> {
+        match self {
+            Self::Timestamp { protocol, .. } => Ok(Reading::Ticks(protocol.get_timestamp())),
+            Self::Rtc { rt, .. } => {
+                let time = rt.get_time()?;
+                Ok(Reading::Rtc {
+                    seconds: seconds_since_epoch(&time),
+                    nanosecond: time.nanosecond(),
+                })
+            }
+        }
+    }
+
+    /// Duration between two readings of this clock.
+    fn between(&self, start: Reading, end: Reading) -> Duration {
+        match (self, start, end) {
+            (
+                Self::Timestamp {
+                    frequency,
+                    end_value,
+                    ..
+                },
+                Reading::Ticks(start),
+                Reading::Ticks(end),
+            ) => ticks_to_duration(elapsed_ticks(start, end, *end_value), *frequency),
+            (
+                Self::Rtc { .. },
+                Reading::Rtc {
+                    seconds: start_seconds,
+                    nanosecond: start_nanosecond,
+                },
+                Reading::Rtc {
+                    seconds: end_seconds,
+                    nanosecond: end_nanosecond,
+                },
+            ) => rtc_elapsed(
+                (start_seconds, start_nanosecond),
+                (end_seconds, end_nanosecond),
+            ),
+            // Readings always come from the clock of their stopwatch.
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Measures the time elapsed since it was started, and between laps.
+///
+/// See the [module-level documentation](self) for the clocks that are used
+/// and their resolution.
+///
+/// # Example
+///
+/// ```
+/// use uefi::stopwatch::Stopwatch;
+/// use uefi::table::boot::BootServices;
+/// use uefi::table::runtime::RuntimeServices;
+/// # use uefi::Result;
+///
+/// # fn connect_drivers(_: &BootServices) {}
+/// # fn test(bt: &BootServices, rt: &RuntimeServices) -> Result {
+/// let mut stopwatch = Stopwatch::start(bt, rt)?;
+/// connect_drivers(bt);
+/// log::info!("Connected the drivers in {:?}", stopwatch.lap()?);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Stopwatch<'a> {
+    clock: Clock<'a>,
+    start: Reading,
+    last_lap: Reading,
+}
+
+impl<'a> Stopwatch<'a> {
+    /// Start a stopwatch, using the [`Timestamp`] protocol if available, and
+    /// the real time clock otherwise.
+    ///
+    /// # Errors
+    ///
+    /// See [`RuntimeServices::get_time_and_caps`] for the errors when
+    /// falling back to the real time clock.
+    pub fn start(bt: &'a BootServices, rt: &'a RuntimeServices) -> Result<Self> {
+        match Self::start_with_timestamp(bt) {
+            Ok(stopwatch) => Ok(stopwatch),
+            Err(_) => Self::start_with_rtc(rt),
+        }
+    }
+
+    /// Start a stopwatch reading the [`Timestamp`] protocol.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the protocol is not available.
+    /// * [`Status::UNSUPPORTED`]: the counter reports a frequency of
+    ///   zero.
+    pub fn start_with_timestamp(bt: &'a BootServices) -> Result<Self> {
+        let handle = bt.get_handle_for_protocol::<Timestamp>()?;
+        // Safety: the protocol is only read, so other users of it are not
+        // affected.
+        let protocol = unsafe {
+            bt.open_protocol::<Timestamp>(
+                OpenProtocolParams {
+                    handle,
+                    agent: bt.image_handle(),
+                    controller: None,
+                },
+                OpenProtocolAttributes::GetProtocol,
+            )
+        }?;
+        let properties = protocol.get_properties()?;
+        if properties.frequency == 0 {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        Self::with_clock(Clock::Timestamp {
+            protocol,
+            frequency: properties.frequency,
+            end_value: properties.end_value,
+        })
+    }
+
+    /// Start a stopwatch reading the real time clock.
+    ///
+    /// # Errors
+    ///
+    /// See [`RuntimeServices::get_time_and_caps`].
+    pub fn start_with_rtc(rt: &'a RuntimeServices) -> Result<Self> {
+        let (_, capabilities) = rt.get_time_and_caps()?;
+        Self::with_clock(Clock::Rtc {
+            rt,
+            resolution: capabilities.resolution,
+        })
+    }
+
+    fn with_clock(clock: Clock<'a>) -> Result<Self> {
+        let start = clock.read()?;
+        Ok(Self {
+            clock,
+            start,
+            last_lap: start,
+        })
+    }
+
+    /// The clock read by this stopwatch.
+    pub fn source(&self) -> ClockSource {
+        match self.clock {
+            Clock::Timestamp { .. } => ClockSource::Timestamp,
+            Clock::Rtc { .. } => ClockSource::Rtc,
+        }
+    }
+
+    /// The smallest duration the clock can measure.
+    pub fn resolution(&self) -> Duration {
+        let counts_per_second = match self.clock {
+            Clock::Timestamp { frequency, .. } => frequency,
+            // A resolution of zero is not valid, assume the usual one second.
+            Clock::Rtc { resolution, .. } => u64::from(resolution.max(1)),
+        };
+        ticks_to_duration(1, counts_per_second)
+    }
+
+    /// Time elapsed since the stopwatch was started or restarted.
+    ///
+    /// # Errors
+    ///
+    /// See [`RuntimeServices::get_time`] when reading the real time clock.
+    pub fn elapsed(&self) -> Result<Duration> {
+        let now = self.clock.read()?;
+        Ok(self.clock.between(self.start, now))
+    }
+
+    /// Time elapsed since the previous lap, or since the stopwatch was
+    /// started or restarted for the first lap.
+    ///
+    /// # Errors
+    ///
+    /// See [`RuntimeServices::get_time`] when reading the real time clock.
+    pub fn lap(&mut self) -> Result<Duration> {
+        let now = self.clock.read()?;
+        let lap = self.clock.between(self.last_lap, now);
+        self.last_lap = now;
+        Ok(lap)
+    }
+
+    /// Start measuring again from now.
+    ///
+    /// # Errors
+    ///
+    /// See [`RuntimeServices::get_time`] when reading the real time clock.
+    pub fn restart(&mut self) -> Result {
+        let now = self.clock.read()?;
+        self.start = now;
+        self.last_lap = now;
+        Ok(())
+    }
+}
+
+impl Debug for Stopwatch<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stopwatch")
+            .field("source", &self.source())
+            .field("start", &self.start)
+            .field("last_lap", &self.last_lap)
+            .finish()
+    }
+}
+
+/// Number of ticks from `start` to `end` of a counter wrapping to zero after
+/// `end_value`.
+fn elapsed_ticks(start: u64, end: u64, end_value: u64) -> u64 {
+    if end >= start {
+        end - start
+    } else {
+        // The counter wrapped around once. A start reading past `end_value`
+        // is bogus, and counted from `end_value`.
+        end_value
+            .saturating_sub(start)
+            .saturating_add(end)
+            .saturating_add(1)
+    }
+}
+
+fn ticks_to_duration(ticks: u64, frequency: u64) -> Duration {
+    let seconds = ticks / frequency;
+    let remainder = u128::from(ticks % frequency);
+    let nanos = remainder * u128::from(NANOS_PER_SECOND) / u128::from(frequency);
+    Duration::new(seconds, nanos as u32)
+}
+
+/// Duration between two real time clock readings, as seconds and
+/// nanoseconds, or zero if the clock went back.
+fn rtc_elapsed(start: (i64, u32), end: (i64, u32)) -> Duration {
+    let start_nanos = i128::from(start.0) * i128::from(NANOS_PER_SECOND) + i128::from(start.1);
+    let end_nanos = i128::from(end.0) * i128::from(NANOS_PER_SECOND) + i128::from(end.1);
+    let nanos = (end_nanos - start_nanos).max(0) as u128;
+    let per_second = u128::from(NANOS_PER_SECOND);
+    Duration::new((nanos / per_second) as u64, (nanos % per_second) as u32)
+}
+
+/// Number of seconds between 1970-01-01 and `time`, in UTC if the time zone
+/// of `time` is known, and in local time otherwise.
+fn seconds_since_epoch(time: &Time) -> i64 {
+    let days = days_since_epoch(time.year(), time.month(), time.day());
+    let mut seconds = days * 86_400
+        + i64::from(time.hour()) * 3600
+        + i64::from(time.minute()) * 60
+        + i64::from(time.second());
+    if let Some(offset) = time.time_zone() {
+        // The offset is the local time minus UTC, in minutes.
+        seconds -= i64::from(offset) * 60;
+    }
+    seconds
+}
+
+/// Number of days between 1970-01-01 and the given date of the Gregorian
+/// calendar.
+fn days_since_epoch(year: u16, month: u8, day: u8) -> i64 {
+    // Count years from March, so that the leap day is the last day of the
+    // year.
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::runtime::{Daylight, TimeParams};
+
+    fn time(date: (u16, u8, u8), clock: (u8, u8, u8), time_zone: Option<i16>) -> Time {
+        Time::new(TimeParams {
+            year: date.0,
+            month: date.1,
+            day: date.2,
+            hour: clock.0,
+            minute: clock.1,
+            second: clock.2,
+            nanosecond: 0,
+            time_zone,
+            daylight: Daylight::empty(),
+        })
+        .unwrap()
+    }
+
+    fn rtc_between(start: Time, end: Time) -> Duration {
+        rtc_elapsed(
+            (seconds_since_epoch(&start), start.nanosecond()),
+            (seconds_since_epoch(&end), end.nanosecond()),
+        )
+    }
+
+    #[test]
+    fn test_days_since_epoch() {
+        assert_eq!(days_since_epoch(1970, 1, 1), 0);
+        assert_eq!(days_since_epoch(2000, 3, 1), 11_017);
+        assert_eq!(days_since_epoch(2024, 2, 29), 19_782);
+        assert_eq!(days_since_epoch(1900, 1, 1), -25_567);
+        assert_eq!(
+            seconds_since_epoch(&time((2022, 8, 26), (12, 34, 56), None)),
+            1_661_517_296
+        );
+    }
+
+    #[test]
+    fn test_rtc_elapsed() {
+        // Over midnight, the end of a month, and the end of a year.
+        assert_eq!(
+            rtc_between(
+                time((2022, 8, 26), (23, 59, 58), None),
+                time((2022, 8, 27), (0, 0, 3), None)
+            ),
+            Duration::from_secs(5)
+        );
+        assert_eq!(
+            rtc_between(
+                time((2024, 2, 28), (23, 0, 0), None),
+                time((2024, 3, 1), (1, 0, 0), None)
+            ),
+            Duration::from_secs(26 * 3600)
+        );
+        assert_eq!(
+            rtc_between(
+                time((2022, 12, 31), (23, 59, 59), None),
+                time((2023, 1, 1), (0, 0, 0), None)
+            ),
+            Duration::from_secs(1)
+        );
+
+        // Time zones are taken into account when known.
+        assert_eq!(
+            rtc_between(
+                time((2022, 8, 26), (12, 0, 0), Some(60)),
+                time((2022, 8, 26), (11, 0, 10), Some(0))
+            ),
+            Duration::from_secs(10)
+        );
+
+        // A clock set back doesn't give a negative duration.
+        assert_eq!(
+            rtc_between(
+                time((2022, 8, 26), (12, 0, 0), None),
+                time((2022, 8, 26), (11, 0, 0), None)
+            ),
+            Duration::ZERO
+        );
+
+        assert_eq!(
+            rtc_elapsed((10, 900_000_000), (12, 100_000_000)),
+            Duration::from_millis(1200)
+        );
+    }
+
+    #[test]
+    fn test_elapsed_ticks() {
+        assert_eq!(elapsed_ticks(10, 25, u64::MAX), 15);
+        assert_eq!(elapsed_ticks(0xfff0, 0x10, 0xffff), 0x20);
+        assert_eq!(elapsed_ticks(u64::MAX - 1, 1, u64::MAX), 3);
+        // A start reading past the end value doesn't underflow.
+        assert_eq!(elapsed_ticks(0x1_0000, 5, 0xffff), 6);
+
+        assert_eq!(
+            ticks_to_duration(2_500, 1_000),
+            Duration::from_millis(2_500)
+        );
+        assert_eq!(ticks_to_duration(1, 3), Duration::from_nanos(333_333_333));
+        assert_eq!(
+            ticks_to_duration(u64::MAX, u64::MAX),
+            Duration::from_secs(1)
+        );
+    }
+}
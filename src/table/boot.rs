@@ -2,21 +2,35 @@
 
 use super::{Header, Revision};
 use crate::data_types::Align;
+use crate::proto::console::text::{Input, Key, Output};
 use crate::proto::device_path::{DevicePath, FfiDevicePath};
+use crate::proto::loaded_image::LoadedImage;
 #[cfg(feature = "exts")]
-use crate::proto::{loaded_image::LoadedImage, media::fs::SimpleFileSystem};
+use crate::proto::{
+    console::serial::{Serial, SerialDeviceInfo, CONSOLE_OUT_DEVICE_GUID},
+    device_path::text::{AllowShortcuts, DevicePathToText, DisplayOnly},
+    media::file::{File, FileAttribute, FileMode, RegularFile},
+    media::fs::SimpleFileSystem,
+    media::partition::{GptPartitionType, PartitionInfo},
+};
 use crate::proto::{Protocol, ProtocolPointer};
-use crate::{Char16, Event, Guid, Handle, Result, Status};
+use crate::{CStr16, Char16, Event, Guid, Handle, Identify, Result, ResultExt, Status};
 #[cfg(feature = "exts")]
-use alloc_api::vec::Vec;
+use alloc_api::{string::String, vec::Vec};
 use bitflags::bitflags;
 use core::cell::UnsafeCell;
 use core::ffi::c_void;
-use core::fmt::{Debug, Formatter};
+#[cfg(feature = "exts")]
+use core::fmt::Write;
+use core::fmt::{self, Debug, Formatter};
+use core::iter::Peekable;
 use core::mem::{self, MaybeUninit};
-use core::ops::{Deref, DerefMut};
+use core::ops::{Deref, DerefMut, Range};
 use core::ptr::NonNull;
+use core::str::FromStr;
+use core::sync::atomic::{AtomicPtr, Ordering};
 use core::{ptr, slice};
+use uefi_macros::cstr16;
 
 // TODO: this similar to `SyncUnsafeCell`. Once that is stabilized we
 // can use it instead.
@@ -32,6 +46,10 @@ static IMAGE_HANDLE: GlobalImageHandle = GlobalImageHandle {
     handle: UnsafeCell::new(None),
 };
 
+/// The verifier set with [`BootServices::set_image_verifier`], stored as a
+/// pointer, or null if there is none.
+static IMAGE_VERIFIER: AtomicPtr<c_void> = AtomicPtr::new(ptr::null_mut());
+
 /// Contains pointers to all of the boot services.
 ///
 /// # Accessing `BootServices`
@@ -128,13 +146,28 @@ pub struct BootServices {
     check_event: unsafe extern "efiapi" fn(event: Event) -> Status,
 
     // Protocol handlers
-    install_protocol_interface: usize,
-    reinstall_protocol_interface: usize,
-    uninstall_protocol_interface: usize,
+    install_protocol_interface: unsafe extern "efiapi" fn(
+        handle: &mut Option<Handle>,
+        guid: &Guid,
+        interface_type: u32,
+        interface: *const c_void,
+    ) -> Status,
+    reinstall_protocol_interface: unsafe extern "efiapi" fn(
+        handle: Handle,
+        protocol: &Guid,
+        old_interface: *const c_void,
+        new_interface: *const c_void,
+    ) -> Status,
+    uninstall_protocol_interface:
+        unsafe extern "efiapi" fn(handle: Handle, guid: &Guid, interface: *const c_void) -> Status,
     handle_protocol:
         extern "efiapi" fn(handle: Handle, proto: &Guid, out_proto: &mut *mut c_void) -> Status,
     _reserved: usize,
-    register_protocol_notify: usize,
+    register_protocol_notify: extern "efiapi" fn(
+        protocol: &Guid,
+        event: Event,
+        registration: *mut Option<ProtocolSearchKey>,
+    ) -> Status,
     locate_handle: unsafe extern "efiapi" fn(
         search_ty: i32,
         proto: *const Guid,
@@ -211,7 +244,12 @@ pub struct BootServices {
         agent_handle: Handle,
         controller_handle: Option<Handle>,
     ) -> Status,
-    open_protocol_information: usize,
+    open_protocol_information: unsafe extern "efiapi" fn(
+        handle: Handle,
+        protocol: &Guid,
+        entry_buffer: *mut *mut OpenProtocolInfoEntry,
+        entry_count: *mut usize,
+    ) -> Status,
 
     // Library services
     protocols_per_handle: unsafe extern "efiapi" fn(
@@ -235,7 +273,8 @@ pub struct BootServices {
     uninstall_multiple_protocol_interfaces: usize,
 
     // CRC services
-    calculate_crc32: usize,
+    calculate_crc32:
+        unsafe extern "efiapi" fn(data: *const u8, data_size: usize, crc32: &mut u32) -> Status,
 
     // Misc services
     copy_mem: unsafe extern "efiapi" fn(dest: *mut u8, src: *const u8, len: usize),
@@ -296,7 +335,7 @@ impl BootServices {
     /// Raises a task's priority level and returns its previous level.
     ///
     /// The effect of calling `raise_tpl` with a `Tpl` that is below the current
-    /// one (which, sadly, cannot be queried) is undefined by the UEFI spec,
+    /// one (see [`current_tpl`]) is undefined by the UEFI spec,
     /// which also warns against remaining at high `Tpl`s for a long time.
     ///
     /// This function outputs an RAII guard that will automatically restore the
@@ -307,6 +346,8 @@ impl BootServices {
     /// Raising a task's priority level can affect other running tasks and
     /// critical processes run by UEFI. The highest priority level is the
     /// most dangerous, since it disables interrupts.
+    ///
+    /// [`current_tpl`]: BootServices::current_tpl
     pub unsafe fn raise_tpl(&self, tpl: Tpl) -> TplGuard<'_> {
         TplGuard {
             boot_services: self,
@@ -314,6 +355,27 @@ impl BootServices {
         }
     }
 
+    /// Returns the current task priority level.
+    ///
+    /// UEFI has no service to query the `Tpl`, so this raises it to
+    /// [`Tpl::HIGH_LEVEL`] and immediately restores it, returning the level
+    /// that was active before. This is the only way to read the `Tpl` that is
+    /// sanctioned by the spec.
+    ///
+    /// Interrupts are briefly disabled while the level is raised, so this
+    /// should not be called in hot loops. It is mostly useful for checking
+    /// assumptions, for example that an event notification function runs at
+    /// the expected level.
+    pub fn current_tpl(&self) -> Tpl {
+        // Safety: the level is restored right away, without running any
+        // other code in between.
+        unsafe {
+            let tpl = (self.raise_tpl)(Tpl::HIGH_LEVEL);
+            (self.restore_tpl)(tpl);
+            tpl
+        }
+    }
+
     /// Allocates memory pages from the system.
     ///
     /// UEFI OS loaders should allocate memory of the type `LoaderData`. An `u64`
@@ -431,6 +493,45 @@ impl BootServices {
         (self.free_pool)(addr).into()
     }
 
+    /// Allocates memory of type `mem_ty` from a pool and moves `value` into
+    /// it. The memory is freed when the returned [`PoolBox`] is dropped.
+    ///
+    /// Unlike the global allocator, this lets the memory type be chosen,
+    /// for example [`MemoryType::RUNTIME_SERVICES_DATA`] for data that is
+    /// passed on to the OS. Use [`PoolBox::into_raw`] to keep the memory
+    /// allocated.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: `T` must be aligned to more than 8 bytes,
+    ///   the alignment of pool allocations.
+    ///
+    /// Errors from [`allocate_pool`] are also returned.
+    ///
+    /// [`allocate_pool`]: Self::allocate_pool
+    pub fn allocate_pool_boxed<T>(&self, mem_ty: MemoryType, value: T) -> Result<PoolBox<'_, T>> {
+        if mem::align_of::<T>() > POOL_ALIGNMENT {
+            return Err(Status::UNSUPPORTED.into());
+        }
+
+        // Pool allocations of zero bytes may return a null pointer.
+        let ptr = self
+            .allocate_pool(mem_ty, mem::size_of::<T>().max(1))?
+            .cast::<T>();
+        if ptr.align_offset(mem::align_of::<T>()) != 0 {
+            // The firmware doesn't respect the alignment of pool
+            // allocations, freeing the memory is all that can be done.
+            let _ = self.free_pool(ptr.cast());
+            return Err(Status::UNSUPPORTED.into());
+        }
+
+        unsafe { ptr.write(value) };
+        Ok(PoolBox {
+            boot_services: self,
+            ptr,
+        })
+    }
+
     /// Creates an event
     ///
     /// This function creates a new event of the specified type and returns it.
@@ -576,6 +677,100 @@ impl BootServices {
         )
     }
 
+    /// Wait until a key is pressed on `input`, and return it.
+    ///
+    /// The key is consumed, so it is not returned again by
+    /// [`Input::read_key`]. If no key can be read after the input's
+    /// [`wait_for_key_event`] was signaled, for example because another
+    /// reader consumed it first, this keeps waiting for the next key.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the current TPL is not `Tpl::APPLICATION`.
+    /// * [`Status::DEVICE_ERROR`]: the input device reported an error.
+    ///
+    /// [`wait_for_key_event`]: Input::wait_for_key_event
+    pub fn wait_for_key(&self, input: &mut Input) -> Result<Key> {
+        loop {
+            // Safety: the clone is only used for waiting below, while the
+            // original event stays alive in `input`.
+            let mut events = unsafe { [input.wait_for_key_event().unsafe_clone()] };
+            self.wait_for_event(&mut events).discard_errdata()?;
+            if let Some(key) = input.read_key()? {
+                return Ok(key);
+            }
+        }
+    }
+
+    /// Read a line of text from `input`, echoing it to `output`.
+    ///
+    /// Keys are read until Enter is pressed. The firmware reports the Enter
+    /// key as a carriage return (`'\r'`), but some serial terminals send a
+    /// line feed (`'\n'`) instead, so either ends the line. The line ending
+    /// is not stored in `buf`; a new line is echoed instead.
+    ///
+    /// Backspace removes the last character of the line and erases it from
+    /// `output`. Special keys, such as arrows and function keys, and other
+    /// control characters are ignored.
+    ///
+    /// The line is stored in `buf` followed by a null character, so at most
+    /// `buf.len() - 1` characters can be entered. Once `buf` is full,
+    /// further characters are ignored until Enter or Backspace is pressed.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: `buf` is empty.
+    ///
+    /// Errors from [`wait_for_key`] and [`Output::output_string`] are also
+    /// returned.
+    ///
+    /// [`wait_for_key`]: Self::wait_for_key
+    pub fn read_line<'buf>(
+        &self,
+        input: &mut Input,
+        output: &mut Output,
+        buf: &'buf mut [u16],
+    ) -> Result<&'buf CStr16> {
+        const BACKSPACE: u16 = 0x08;
+        const LINE_FEED: u16 = 0x0a;
+        const CARRIAGE_RETURN: u16 = 0x0d;
+        const DELETE: u16 = 0x7f;
+
+        if buf.is_empty() {
+            return Err(Status::BUFFER_TOO_SMALL.into());
+        }
+
+        let mut len = 0;
+        loop {
+            let ch = match self.wait_for_key(input)? {
+                Key::Printable(ch) => u16::from(ch),
+                Key::Special(_) => continue,
+            };
+            match ch {
+                LINE_FEED | CARRIAGE_RETURN => break,
+                BACKSPACE => {
+                    if len > 0 {
+                        len -= 1;
+                        output.output_string(cstr16!("\u{8} \u{8}"))?;
+                    }
+                }
+                0..=0x1f | DELETE => {}
+                _ if len + 1 < buf.len() => {
+                    buf[len] = ch;
+                    len += 1;
+                    let echo = [ch, 0];
+                    output.output_string(unsafe { CStr16::from_u16_with_nul_unchecked(&echo) })?;
+                }
+                // The buffer is full.
+                _ => {}
+            }
+        }
+
+        output.output_string(cstr16!("\r\n"))?;
+        buf[len] = 0;
+        Ok(unsafe { CStr16::from_u16_with_nul_unchecked(&buf[..=len]) })
+    }
+
     /// Place 'event' in the signaled stated. If 'event' is already in the signaled state,
     /// then nothing further occurs and `Status::SUCCESS` is returned. If `event` is of type
     /// `EventType::NOTIFY_SIGNAL`, then the event's notification function is scheduled to
@@ -608,9 +803,21 @@ impl BootServices {
     /// Checks to see if an event is signaled, without blocking execution to wait for it.
     ///
     /// The returned value will be `true` if the event is in the signaled state,
-    /// otherwise `false` is returned.
-    pub fn check_event(&self, event: Event) -> Result<bool> {
-        let status = unsafe { (self.check_event)(event) };
+    /// otherwise `false` is returned. Checking a signaled event clears its signaled
+    /// state, so each signal is only reported once. If the event has a notification
+    /// function and is not signaled, the notification function is queued.
+    ///
+    /// This can be used to build polling loops over several events, such as timers
+    /// and input events, without blocking in `wait_for_event`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the event is of type `NOTIFY_SIGNAL`, which
+    ///   can't be checked.
+    pub fn check_event(&self, event: &Event) -> Result<bool> {
+        // Safety: cloning this event should be safe, as we're directly passing it to firmware
+        // and not keeping the clone around.
+        let status = unsafe { (self.check_event)(event.unsafe_clone()) };
         match status {
             Status::SUCCESS => Ok(true),
             Status::NOT_READY => Ok(false),
@@ -618,6 +825,96 @@ impl BootServices {
         }
     }
 
+    /// Installs a protocol interface on a handle.
+    ///
+    /// If `handle` is `None`, a new handle is created. The handle the
+    /// interface was installed on is returned.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::OUT_OF_RESOURCES`]: the handle or interface could not be
+    ///   allocated.
+    /// * [`Status::INVALID_PARAMETER`]: `protocol` is already installed on
+    ///   `handle`.
+    ///
+    /// # Safety
+    ///
+    /// `interface` must point to a valid implementation of `protocol` (or be
+    /// null, for protocols without an interface), which must stay valid until
+    /// it is uninstalled.
+    pub unsafe fn install_protocol_interface(
+        &self,
+        handle: Option<Handle>,
+        protocol: &Guid,
+        interface: *const c_void,
+    ) -> Result<Handle> {
+        const NATIVE_INTERFACE: u32 = 0;
+
+        let mut handle = handle;
+        (self.install_protocol_interface)(&mut handle, protocol, NATIVE_INTERFACE, interface)
+            .into_with_val(|| handle.unwrap())
+    }
+
+    /// Removes a protocol interface from a handle. When the last interface is
+    /// removed, the handle is freed.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the interface was not found on the handle.
+    /// * [`Status::ACCESS_DENIED`]: the interface is still in use by a driver
+    ///   that could not be disconnected.
+    ///
+    /// # Safety
+    ///
+    /// The interface must not be uninstalled while other code relies on it,
+    /// for example through a [`ScopedProtocol`] opened without exclusive
+    /// access.
+    pub unsafe fn uninstall_protocol_interface(
+        &self,
+        handle: Handle,
+        protocol: &Guid,
+        interface: *const c_void,
+    ) -> Result {
+        (self.uninstall_protocol_interface)(handle, protocol, interface).into()
+    }
+
+    /// Replaces a protocol interface on a handle with a new one.
+    ///
+    /// The firmware first disconnects the drivers that opened the old
+    /// interface with [`OpenProtocolAttributes::ByDriver`], replaces the
+    /// interface, and then calls [`connect_controller`] on the handle, so
+    /// that those drivers bind to the new interface. Any [`ScopedProtocol`]
+    /// referring to the old interface must not be used afterwards.
+    ///
+    /// `old_interface` must be the exact pointer that was installed for
+    /// `protocol` on `handle`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: `old_interface` is not installed for
+    ///   `protocol` on `handle`.
+    /// * [`Status::ACCESS_DENIED`]: the old interface is still in use by a
+    ///   driver that could not be disconnected. The old interface stays
+    ///   installed in this case.
+    ///
+    /// # Safety
+    ///
+    /// `new_interface` must point to a valid implementation of `protocol`
+    /// (or be null, for protocols without an interface), which must stay
+    /// valid until it is uninstalled. The old interface must not be used
+    /// by other code after it is replaced.
+    ///
+    /// [`connect_controller`]: BootServices::connect_controller
+    pub unsafe fn reinstall_protocol_interface(
+        &self,
+        handle: Handle,
+        protocol: &Guid,
+        old_interface: *const c_void,
+        new_interface: *const c_void,
+    ) -> Result {
+        (self.reinstall_protocol_interface)(handle, protocol, old_interface, new_interface).into()
+    }
+
     /// Query a handle for a certain protocol.
     ///
     /// This function attempts to get the protocol implementation of a handle,
@@ -678,6 +975,7 @@ impl BootServices {
         // Obtain the needed data from the parameters.
         let (ty, guid, key) = match search_ty {
             SearchType::AllHandles => (0, ptr::null(), ptr::null_mut()),
+            SearchType::ByRegisterNotify(key) => (1, ptr::null(), key.0.as_ptr()),
             SearchType::ByProtocol(guid) => (2, guid as *const _, ptr::null_mut()),
         };
 
@@ -692,6 +990,82 @@ impl BootServices {
         }
     }
 
+    /// Registers `event` to be signaled whenever an interface of `protocol`
+    /// is installed or reinstalled, and returns the search type to find the
+    /// handles of these interfaces.
+    ///
+    /// Pass the returned [`SearchType::ByRegisterNotify`] to
+    /// [`locate_handle_buffer`] when the event is signaled, to get the
+    /// handles on which an interface was installed since the last search.
+    /// The registration is removed when `event` is closed.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::OUT_OF_RESOURCES`]: the registration could not be
+    ///   allocated.
+    ///
+    /// [`locate_handle_buffer`]: BootServices::locate_handle_buffer
+    pub fn register_protocol_notify<'guid>(
+        &self,
+        protocol: &'guid Guid,
+        event: &Event,
+    ) -> Result<SearchType<'guid>> {
+        let mut key = None;
+        // Safety: the firmware doesn't keep the clone beyond the lifetime of
+        // the event, as the registration is removed when it is closed.
+        let event = unsafe { event.unsafe_clone() };
+        Result::from((self.register_protocol_notify)(protocol, event, &mut key))?;
+        // The firmware must set the key on success.
+        key.map(SearchType::ByRegisterNotify)
+            .ok_or_else(|| Status::COMPROMISED_DATA.into())
+    }
+
+    /// Creates an event that is signaled whenever an interface of protocol
+    /// `P` is installed or reinstalled, and returns it with the key to find
+    /// the new handles with [`locate_handle_by_registration`].
+    ///
+    /// The event can be waited on with [`wait_for_event`] or polled with
+    /// [`check_event`], for example to detect when a drive is plugged in.
+    /// Closing the event with [`close_event`] removes the registration.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::OUT_OF_RESOURCES`]: the event or the registration could
+    ///   not be allocated.
+    ///
+    /// [`locate_handle_by_registration`]: BootServices::locate_handle_by_registration
+    /// [`wait_for_event`]: BootServices::wait_for_event
+    /// [`check_event`]: BootServices::check_event
+    /// [`close_event`]: BootServices::close_event
+    pub fn create_protocol_notify_event<P: Protocol>(&self) -> Result<(Event, ProtocolSearchKey)> {
+        let event = unsafe { self.create_event(EventType::empty(), Tpl::CALLBACK, None, None) }?;
+        match self.register_protocol_notify(&P::GUID, &event) {
+            Ok(SearchType::ByRegisterNotify(key)) => Ok((event, key)),
+            Ok(_) => unreachable!(),
+            Err(err) => {
+                // Don't leak the event if the registration failed.
+                let _ = self.close_event(event);
+                Err(err)
+            }
+        }
+    }
+
+    /// Returns the next handle on which an interface was installed for the
+    /// registration `key`, or `None` if there are no more.
+    ///
+    /// Each call returns at most one handle, and a handle is only returned
+    /// once for a given key. An interface may have been installed several
+    /// times before the event of the registration is checked, so call this
+    /// function until it returns `None` every time the event is signaled.
+    pub fn locate_handle_by_registration(&self, key: ProtocolSearchKey) -> Option<Handle> {
+        let mut buffer = [MaybeUninit::uninit()];
+        match self.locate_handle(SearchType::ByRegisterNotify(key), Some(&mut buffer)) {
+            // Safety: the firmware has written one handle to the buffer.
+            Ok(1) => Some(unsafe { buffer[0].assume_init() }),
+            _ => None,
+        }
+    }
+
     /// Locates the handle to a device on the device path that supports the specified protocol.
     ///
     /// The `device_path` is updated to point at the remaining part of the [`DevicePath`] after
@@ -702,7 +1076,10 @@ impl BootServices {
     /// If the first node of `device_path` matches the
     /// protocol, the `device_path` is advanced to the device path terminator node. If `device_path`
     /// is a multi-instance device path, the function will operate on the first instance.
-    pub fn locate_device_path<P: Protocol>(&self, device_path: &mut &DevicePath) -> Result<Handle> {
+    pub fn locate_device_path<P: Protocol + ?Sized>(
+        &self,
+        device_path: &mut &DevicePath,
+    ) -> Result<Handle> {
         let mut handle = MaybeUninit::uninit();
         let mut device_path_ptr = device_path.as_ffi_ptr();
         unsafe {
@@ -775,13 +1152,34 @@ impl BootServices {
     /// returned. The image can be started with [`start_image`] or
     /// unloaded with [`unload_image`].
     ///
+    /// If a verifier was set with [`set_image_verifier`], it is called with
+    /// the image before the handle is returned, and
+    /// [`Status::SECURITY_VIOLATION`] is returned if it denies the image.
+    /// Only images loaded from a buffer are verified as files: for a file
+    /// path, the verifier only sees the image loaded in memory.
+    ///
     /// [`start_image`]: BootServices::start_image
     /// [`unload_image`]: BootServices::unload_image
+    /// [`set_image_verifier`]: BootServices::set_image_verifier
     pub fn load_image(
         &self,
         parent_image_handle: Handle,
         source: LoadImageSource,
     ) -> uefi::Result<Handle> {
+        let verifier = Self::image_verifier();
+        if let (Some(verifier), LoadImageSource::FromBuffer { buffer, file_path }) =
+            (verifier, &source)
+        {
+            // Check the file before the firmware parses it.
+            let image = ImageToVerify::File {
+                data: buffer,
+                file_path: *file_path,
+            };
+            if !verifier(image) {
+                return Err(Status::SECURITY_VIOLATION.into());
+            }
+        }
+
         let boot_policy;
         let device_path;
         let source_buffer;
@@ -807,7 +1205,7 @@ impl BootServices {
         };
 
         let mut image_handle = MaybeUninit::uninit();
-        unsafe {
+        let image_handle = unsafe {
             (self.load_image)(
                 boot_policy,
                 parent_image_handle,
@@ -817,6 +1215,105 @@ impl BootServices {
                 &mut image_handle,
             )
             .into_with_val(|| image_handle.assume_init())
+        }?;
+
+        if let (Some(verifier), LoadImageSource::FromFilePath { file_path, .. }) =
+            (verifier, source)
+        {
+            // The file was read by the firmware, so only the loaded image can
+            // be checked.
+            let allowed = self.verify_loaded_image(image_handle, file_path, verifier);
+            if !allowed {
+                let _ = self.unload_image(image_handle);
+                return Err(Status::SECURITY_VIOLATION.into());
+            }
+        }
+        Ok(image_handle)
+    }
+
+    /// Call `verifier` with the image loaded in memory for `image_handle`.
+    /// The image is denied if its [`LoadedImage`] protocol can't be opened.
+    fn verify_loaded_image(
+        &self,
+        image_handle: Handle,
+        file_path: &DevicePath,
+        verifier: ImageVerifier,
+    ) -> bool {
+        let loaded_image = match self.open_protocol_exclusive::<LoadedImage>(image_handle) {
+            Ok(loaded_image) => loaded_image,
+            Err(_) => return false,
+        };
+        let (base, size) = loaded_image.info();
+        // Safety: the firmware loaded the image at this address, and it stays
+        // there until the image is unloaded.
+        let data = unsafe { slice::from_raw_parts(base.cast::<u8>(), size as usize) };
+        verifier(ImageToVerify::Loaded { data, file_path })
+    }
+
+    /// Set a function called by [`load_image`] to allow or deny each image
+    /// before it can be started, or remove it with `None`.
+    ///
+    /// This lets a loader enforce its own policy, such as checking a
+    /// signature of the next stage with its own keys. The verifier returns
+    /// `true` to allow the image. See [`ImageToVerify`] for what it is passed.
+    ///
+    /// This check only applies to images loaded through this library, and
+    /// happens in the application before the firmware is asked to load the
+    /// image. It does not replace Secure Boot, which is still enforced by the
+    /// firmware, and it can't stop images loaded by other applications or
+    /// drivers.
+    ///
+    /// Since the verifier is a function pointer, any state it needs, such as
+    /// the trusted keys, must be stored in a `static`.
+    ///
+    /// # Images loaded from a file path
+    ///
+    /// For [`LoadImageSource::FromFilePath`], the firmware reads the file
+    /// itself, so the verifier can't see the file: it is called after the
+    /// firmware has loaded the image, with the image as laid out in memory,
+    /// with its sections in place and relocations applied
+    /// ([`ImageToVerify::Loaded`]). The image is unloaded if it is denied,
+    /// but checks that need the original file, such as verifying an
+    /// Authenticode signature or a hash of the file, don't work on it.
+    ///
+    /// For such checks, read the file, for example with the
+    /// [`SimpleFileSystem`] protocol, and load it with
+    /// [`LoadImageSource::FromBuffer`], so that the verifier is called with
+    /// the file ([`ImageToVerify::File`]) before the firmware parses it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use uefi::table::boot::{BootServices, ImageToVerify};
+    ///
+    /// fn is_pe_image(image: ImageToVerify) -> bool {
+    ///     // Both the file and the loaded image start with the DOS header of
+    ///     // the PE/COFF headers.
+    ///     image.data().starts_with(b"MZ")
+    /// }
+    ///
+    /// # fn test(boot_services: &BootServices) {
+    /// boot_services.set_image_verifier(Some(is_pe_image));
+    /// # }
+    /// ```
+    ///
+    /// [`load_image`]: BootServices::load_image
+    pub fn set_image_verifier(&self, verifier: Option<ImageVerifier>) {
+        let ptr = verifier.map_or(ptr::null_mut(), |verifier| verifier as *mut c_void);
+        IMAGE_VERIFIER.store(ptr, Ordering::Release);
+    }
+
+    /// The verifier set with [`set_image_verifier`], if any.
+    ///
+    /// [`set_image_verifier`]: BootServices::set_image_verifier
+    fn image_verifier() -> Option<ImageVerifier> {
+        let ptr = IMAGE_VERIFIER.load(Ordering::Acquire);
+        if ptr.is_null() {
+            None
+        } else {
+            // Safety: the pointer was stored by `set_image_verifier`, from a
+            // function pointer of this type.
+            Some(unsafe { mem::transmute::<*mut c_void, ImageVerifier>(ptr) })
         }
     }
 
@@ -1014,6 +1511,7 @@ impl BootServices {
             ScopedProtocol {
                 interface: &*interface,
                 open_params: params,
+                open_attributes: attributes,
                 boot_services: self,
             }
         })
@@ -1044,6 +1542,95 @@ impl BootServices {
         }
     }
 
+    /// Open the first available instance of a protocol.
+    ///
+    /// This locates the first handle supporting `P` and opens the protocol on
+    /// it with [`OpenProtocolAttributes::GetProtocol`], using the current
+    /// image as the agent. Unlike [`open_protocol_exclusive`], this does not
+    /// disconnect drivers that are using the protocol, which makes it a good
+    /// fit for read-only access to singleton-like protocols such as [`Rng`].
+    ///
+    /// Returns [`Status::NOT_FOUND`] if no handle supports the protocol.
+    ///
+    /// # Safety
+    ///
+    /// The protocol is opened in a way that is not tracked as exclusive by
+    /// the UEFI implementation, see the safety documentation of
+    /// [`open_protocol`]. The caller is responsible for ensuring that the
+    /// handle and protocol remain valid until the `ScopedProtocol` is dropped,
+    /// and that the protocol is not used concurrently from somewhere else.
+    ///
+    /// [`open_protocol`]: BootServices::open_protocol
+    /// [`open_protocol_exclusive`]: BootServices::open_protocol_exclusive
+    /// [`Rng`]: crate::proto::rng::Rng
+    pub unsafe fn get_first_protocol<P: ProtocolPointer + ?Sized>(
+        &self,
+    ) -> Result<ScopedProtocol<P>> {
+        let handle = self.get_handle_for_protocol_impl(&P::GUID)?;
+        self.open_protocol::<P>(
+            OpenProtocolParams {
+                handle,
+                agent: self.image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )
+    }
+
+    /// Open a protocol on `handle` without taking exclusive ownership of it,
+    /// run `f` with the protocol, and close the protocol again.
+    ///
+    /// The protocol is opened with [`OpenProtocolAttributes::GetProtocol`],
+    /// using the current image as the agent. Drivers using the protocol stay
+    /// connected, so this is the right choice for devices that are shared
+    /// with the firmware, such as the serial device used for the console.
+    /// Use [`with_protocol_exclusive`] if other users of the protocol must be
+    /// disconnected while `f` runs.
+    ///
+    /// # Safety
+    ///
+    /// As with [`open_protocol`], the UEFI implementation doesn't prevent the
+    /// protocol from being removed while it is open this way. The caller is
+    /// responsible for ensuring that the handle and protocol remain valid
+    /// until `f` returns, and that the protocol is not used concurrently from
+    /// somewhere else.
+    ///
+    /// [`open_protocol`]: BootServices::open_protocol
+    /// [`with_protocol_exclusive`]: BootServices::with_protocol_exclusive
+    pub unsafe fn with_protocol<P: ProtocolPointer + ?Sized, R>(
+        &self,
+        handle: Handle,
+        f: impl FnOnce(&mut P) -> R,
+    ) -> Result<R> {
+        let mut protocol = self.open_protocol::<P>(
+            OpenProtocolParams {
+                handle,
+                agent: self.image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )?;
+        Ok(f(&mut protocol))
+    }
+
+    /// Open a protocol on `handle` in exclusive mode, run `f` with the
+    /// protocol, and close the protocol again.
+    ///
+    /// Opening in exclusive mode disconnects the drivers using the protocol
+    /// until it is closed. For example, opening the console's serial device
+    /// this way breaks the connection between stdout and the device. Use
+    /// [`with_protocol`] to share the protocol instead.
+    ///
+    /// [`with_protocol`]: BootServices::with_protocol
+    pub fn with_protocol_exclusive<P: ProtocolPointer + ?Sized, R>(
+        &self,
+        handle: Handle,
+        f: impl FnOnce(&mut P) -> R,
+    ) -> Result<R> {
+        let mut protocol = self.open_protocol_exclusive::<P>(handle)?;
+        Ok(f(&mut protocol))
+    }
+
     /// Test whether a handle supports a protocol.
     pub fn test_protocol<P: Protocol>(&self, params: OpenProtocolParams) -> Result<()> {
         const TEST_PROTOCOL: u32 = 0x04;
@@ -1087,8 +1674,35 @@ impl BootServices {
         })
     }
 
+    /// Get the list of agents that currently have a protocol interface open on
+    /// a [`Handle`].
+    ///
+    /// This is useful to find out why opening a protocol fails with
+    /// [`Status::ACCESS_DENIED`], for example because another agent has
+    /// already opened it in exclusive mode.
+    pub fn open_protocol_information(
+        &self,
+        handle: Handle,
+        protocol: &Guid,
+    ) -> Result<OpenProtocolInformation> {
+        let mut entries = ptr::null_mut();
+        let mut count = 0;
+
+        unsafe { (self.open_protocol_information)(handle, protocol, &mut entries, &mut count) }
+            .into_with_val(|| OpenProtocolInformation {
+                boot_services: self,
+                entries,
+                count,
+            })
+    }
+
     /// Returns an array of handles that support the requested protocol in a buffer allocated from
     /// pool.
+    ///
+    /// The buffer is freed when the returned [`HandleBuffer`] is dropped.
+    /// Unlike [`find_handles`], this doesn't copy the handles into a `Vec`.
+    ///
+    /// [`find_handles`]: BootServices::find_handles
     pub fn locate_handle_buffer(&self, search_ty: SearchType) -> Result<HandleBuffer> {
         let mut num_handles: usize = 0;
         let mut buffer: *mut Handle = ptr::null_mut();
@@ -1096,6 +1710,7 @@ impl BootServices {
         // Obtain the needed data from the parameters.
         let (ty, guid, key) = match search_ty {
             SearchType::AllHandles => (0, ptr::null(), ptr::null_mut()),
+            SearchType::ByRegisterNotify(key) => (1, ptr::null(), key.0.as_ptr()),
             SearchType::ByProtocol(guid) => (2, guid as *const _, ptr::null_mut()),
         };
 
@@ -1107,6 +1722,58 @@ impl BootServices {
             })
     }
 
+    /// Returns a fingerprint of the set of handles in the database, to cheaply
+    /// detect when handles were created or destroyed, for example after a
+    /// [`connect_controller`] that may or may not have created child handles.
+    ///
+    /// The fingerprint is the number of handles and the CRC-32 of their
+    /// addresses in ascending order, so it doesn't depend on the order in
+    /// which the firmware returns them. It only changes when the set of
+    /// handles changes: interfaces installed on existing handles are not
+    /// detected, use [`create_protocol_notify_event`] for the protocols of
+    /// interest to be notified of those too.
+    ///
+    /// ```no_run
+    /// use uefi::proto::media::block::BlockIO;
+    /// use uefi::table::boot::BootServices;
+    ///
+    /// fn rescan_if_changed(bt: &BootServices) -> uefi::Result {
+    ///     let (event, _key) = bt.create_protocol_notify_event::<BlockIO>()?;
+    ///     let before = bt.handle_database_version()?;
+    ///     // ... connect controllers ...
+    ///     if bt.check_event(&event)? || bt.handle_database_version()? != before {
+    ///         // ... re-scan the devices ...
+    ///     }
+    ///     bt.close_event(event)
+    /// }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// See [`locate_handle_buffer`].
+    ///
+    /// [`connect_controller`]: BootServices::connect_controller
+    /// [`create_protocol_notify_event`]: BootServices::create_protocol_notify_event
+    /// [`locate_handle_buffer`]: BootServices::locate_handle_buffer
+    pub fn handle_database_version(&self) -> Result<HandleDatabaseVersion> {
+        let mut buffer = self.locate_handle_buffer(SearchType::AllHandles)?;
+        let handles = buffer.handles_mut();
+        handles.sort_unstable_by_key(|handle| handle.as_ptr() as usize);
+        let crc32 = if handles.is_empty() {
+            0
+        } else {
+            // Safety: handles are plain pointers, without padding.
+            let bytes = unsafe {
+                slice::from_raw_parts(handles.as_ptr().cast::<u8>(), mem::size_of_val(handles))
+            };
+            self.calculate_crc32(bytes)?
+        };
+        Ok(HandleDatabaseVersion {
+            count: handles.len(),
+            crc32,
+        })
+    }
+
     /// Returns a protocol implementation, if present on the system.
     ///
     /// The caveats of `BootServices::handle_protocol()` also apply here.
@@ -1133,6 +1800,126 @@ impl BootServices {
         })
     }
 
+    /// Installs several protocol interfaces on a handle as one transaction:
+    /// either all of them are installed, or none are.
+    ///
+    /// If `handle` is `None`, a new handle is created. The interfaces are
+    /// given as pairs of protocol GUID and interface pointer. They are
+    /// uninstalled again when the returned guard is dropped, unless
+    /// [`InstalledProtocolInterfaces::leak`] is called.
+    ///
+    /// The firmware's `InstallMultipleProtocolInterfaces` takes the pairs as
+    /// C-variadic arguments, terminated by a null GUID pointer, which can't be
+    /// declared with the `efiapi` ABI. This function therefore follows the
+    /// same steps as the firmware service: it raises the task priority level
+    /// to [`Tpl::NOTIFY`] so that no event notification observes a partial
+    /// state, installs the interfaces one by one, and uninstalls those
+    /// already installed if one of them fails.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `interfaces` is empty, or one of the
+    ///   protocols is already installed on `handle`.
+    /// * [`Status::ALREADY_STARTED`]: one of the interfaces is a
+    ///   [`DevicePath`] that is already installed on another handle. As with
+    ///   the firmware service, installing a device path can't be used to
+    ///   create a second handle for the same device.
+    /// * [`Status::OUT_OF_RESOURCES`]: the handle or an interface could not be
+    ///   allocated.
+    ///
+    /// # Safety
+    ///
+    /// Each interface pointer must point to a valid implementation of its
+    /// protocol (or be null, for protocols without an interface), which must
+    /// stay valid until it is uninstalled.
+    pub unsafe fn install_multiple_protocol_interfaces<'a>(
+        &'a self,
+        handle: Option<Handle>,
+        interfaces: &'a [(Guid, *const c_void)],
+    ) -> Result<InstalledProtocolInterfaces<'a>> {
+        if interfaces.is_empty() {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+
+        let _tpl = self.raise_tpl(Tpl::NOTIFY);
+
+        for (guid, interface) in interfaces {
+            if *guid == DevicePath::GUID && !interface.is_null() {
+                let mut remaining = DevicePath::from_ffi_ptr(interface.cast());
+                if self
+                    .locate_device_path::<DevicePath>(&mut remaining)
+                    .is_ok()
+                    && remaining.node_iter().next().is_none()
+                {
+                    return Err(Status::ALREADY_STARTED.into());
+                }
+            }
+        }
+
+        let mut handle = handle;
+        for (i, (guid, interface)) in interfaces.iter().enumerate() {
+            match self.install_protocol_interface(handle, guid, *interface) {
+                Ok(installed_on) => handle = Some(installed_on),
+                Err(err) => {
+                    if let Some(handle) = handle {
+                        for (guid, interface) in interfaces[..i].iter().rev() {
+                            let _ = self.uninstall_protocol_interface(handle, guid, *interface);
+                        }
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(InstalledProtocolInterfaces {
+            boot_services: self,
+            // There is at least one interface, so the handle is set.
+            handle: handle.unwrap(),
+            interfaces,
+        })
+    }
+
+    /// Uninstalls several protocol interfaces from a handle as one
+    /// transaction: either all of them are uninstalled, or none are.
+    ///
+    /// This is the counterpart of [`install_multiple_protocol_interfaces`].
+    /// If one of the interfaces can't be uninstalled, those already
+    /// uninstalled are reinstalled.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: one of the interfaces could not be
+    ///   uninstalled.
+    ///
+    /// # Safety
+    ///
+    /// The interfaces must not be uninstalled while other code relies on
+    /// them, see [`uninstall_protocol_interface`].
+    ///
+    /// [`install_multiple_protocol_interfaces`]: BootServices::install_multiple_protocol_interfaces
+    /// [`uninstall_protocol_interface`]: BootServices::uninstall_protocol_interface
+    pub unsafe fn uninstall_multiple_protocol_interfaces(
+        &self,
+        handle: Handle,
+        interfaces: &[(Guid, *const c_void)],
+    ) -> Result {
+        let _tpl = self.raise_tpl(Tpl::NOTIFY);
+
+        for (i, (guid, interface)) in interfaces.iter().enumerate() {
+            if self
+                .uninstall_protocol_interface(handle, guid, *interface)
+                .is_err()
+            {
+                for (guid, interface) in interfaces[..i].iter().rev() {
+                    let _ = self.install_protocol_interface(Some(handle), guid, *interface);
+                }
+                return Err(Status::INVALID_PARAMETER.into());
+            }
+        }
+
+        Ok(())
+    }
+
     /// Copies memory from source to destination. The buffers can overlap.
     ///
     /// # Safety
@@ -1152,21 +1939,84 @@ impl BootServices {
     pub unsafe fn set_mem(&self, buffer: *mut u8, size: usize, value: u8) {
         (self.set_mem)(buffer, size, value);
     }
-}
-
-#[cfg(feature = "exts")]
-impl BootServices {
-    /// Returns all the handles implementing a certain protocol.
-    pub fn find_handles<P: Protocol>(&self) -> Result<Vec<Handle>> {
-        // Search by protocol.
-        let search_type = SearchType::from_proto::<P>();
 
-        // Determine how much we need to allocate.
-        let buffer_size = self.locate_handle(search_type, None)?;
+    /// Copies all bytes from `src` into `dest` using the firmware's `CopyMem`
+    /// service. This is the safe counterpart of [`memmove`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the two slices have different lengths.
+    ///
+    /// [`memmove`]: BootServices::memmove
+    pub fn copy_mem(&self, dest: &mut [u8], src: &[u8]) {
+        assert_eq!(
+            dest.len(),
+            src.len(),
+            "source and destination have different lengths"
+        );
+        unsafe { (self.copy_mem)(dest.as_mut_ptr(), src.as_ptr(), dest.len()) }
+    }
 
-        // Allocate a large enough buffer without pointless initialization.
-        let mut handles = Vec::with_capacity(buffer_size);
-        let buffer = handles.spare_capacity_mut();
+    /// Copies the bytes in the range `src` of `buf` to the position `dest`
+    /// in the same buffer, using the firmware's `CopyMem` service.
+    ///
+    /// Like `memmove` (and [`slice::copy_within`]), this handles overlapping
+    /// source and destination ranges, so it can be used to move data within
+    /// a large buffer, such as a loaded image that needs to be relocated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either range is out of bounds of `buf`.
+    pub fn copy_mem_within(&self, buf: &mut [u8], src: Range<usize>, dest: usize) {
+        assert!(
+            src.start <= src.end && src.end <= buf.len(),
+            "source range is out of bounds"
+        );
+        let len = src.end - src.start;
+        assert!(
+            dest <= buf.len() - len,
+            "destination range is out of bounds"
+        );
+        unsafe {
+            let base = buf.as_mut_ptr();
+            (self.copy_mem)(base.add(dest), base.add(src.start), len);
+        }
+    }
+
+    /// Sets all bytes of `dest` to `value` using the firmware's `SetMem`
+    /// service. This is the safe counterpart of [`set_mem`].
+    ///
+    /// [`set_mem`]: BootServices::set_mem
+    pub fn fill_mem(&self, dest: &mut [u8], value: u8) {
+        unsafe { (self.set_mem)(dest.as_mut_ptr(), dest.len(), value) }
+    }
+
+    /// Computes the CRC-32 of `data` using the firmware's `CalculateCrc32`
+    /// service. This is the checksum used in the headers of the UEFI tables.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `data` is empty.
+    pub fn calculate_crc32(&self, data: &[u8]) -> Result<u32> {
+        let mut crc32 = 0;
+        unsafe { (self.calculate_crc32)(data.as_ptr(), data.len(), &mut crc32) }
+            .into_with_val(|| crc32)
+    }
+}
+
+#[cfg(feature = "exts")]
+impl BootServices {
+    /// Returns all the handles implementing a certain protocol.
+    pub fn find_handles<P: Protocol>(&self) -> Result<Vec<Handle>> {
+        // Search by protocol.
+        let search_type = SearchType::from_proto::<P>();
+
+        // Determine how much we need to allocate.
+        let buffer_size = self.locate_handle(search_type, None)?;
+
+        // Allocate a large enough buffer without pointless initialization.
+        let mut handles = Vec::with_capacity(buffer_size);
+        let buffer = handles.spare_capacity_mut();
 
         // Perform the search.
         let buffer_size = self.locate_handle(search_type, Some(buffer))?;
@@ -1197,6 +2047,280 @@ impl BootServices {
 
         self.open_protocol_exclusive(device_handle)
     }
+
+    /// Retrieves the `SimpleFileSystem` protocol of the EFI System Partition.
+    ///
+    /// The ESP is found by looking for a partition that is flagged as a system
+    /// partition by the `PartitionInfo` protocol, or whose GPT partition type
+    /// is [`GptPartitionType::EFI_SYSTEM_PARTITION`]. If no partition matches,
+    /// this falls back to the file system the current image was loaded from.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ABORTED`]: more than one ESP was found, so it is not clear
+    ///   which one to use.
+    /// * [`Status::NOT_FOUND`]: no ESP was found and the file system of the
+    ///   current image could not be opened either.
+    ///
+    /// [`GptPartitionType::EFI_SYSTEM_PARTITION`]: crate::proto::media::partition::GptPartitionType::EFI_SYSTEM_PARTITION
+    pub fn locate_esp(&self) -> Result<ScopedProtocol<SimpleFileSystem>> {
+        let partitions = match self.find_handles::<PartitionInfo>() {
+            Ok(handles) => handles,
+            Err(err) if err.status() == Status::NOT_FOUND => Vec::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut esps = partitions.into_iter().filter(|&handle| {
+            self.open_protocol_exclusive::<PartitionInfo>(handle)
+                .map(|info| {
+                    info.is_system()
+                        || info.gpt_partition_entry().map_or(false, |entry| {
+                            let ty = entry.partition_type_guid;
+                            ty == GptPartitionType::EFI_SYSTEM_PARTITION
+                        })
+                })
+                .unwrap_or(false)
+        });
+
+        match (esps.next(), esps.next()) {
+            (Some(esp), None) => self.open_protocol_exclusive(esp),
+            (Some(_), Some(_)) => Err(Status::ABORTED.into()),
+            (None, _) => self
+                .get_image_file_system(self.image_handle())
+                .map_err(|_| Status::NOT_FOUND.into()),
+        }
+    }
+
+    /// Opens all the file systems.
+    ///
+    /// File systems that fail to open, for example because another agent
+    /// already has exclusive access to them, are skipped. An empty list is
+    /// returned if there are no file systems.
+    ///
+    /// Each file system is opened in exclusive mode, like with
+    /// [`open_protocol_exclusive`]: as long as the returned protocols are
+    /// held, the file systems can't be opened again, including by the
+    /// [`get_image_file_system`] and [`locate_esp`] functions. Drop the
+    /// protocols that aren't needed as soon as possible.
+    ///
+    /// [`open_protocol_exclusive`]: BootServices::open_protocol_exclusive
+    /// [`get_image_file_system`]: BootServices::get_image_file_system
+    /// [`locate_esp`]: BootServices::locate_esp
+    pub fn file_systems(&self) -> Vec<(Handle, ScopedProtocol<SimpleFileSystem>)> {
+        let handles = match self.find_handles::<SimpleFileSystem>() {
+            Ok(handles) => handles,
+            Err(_) => return Vec::new(),
+        };
+
+        handles
+            .into_iter()
+            .filter_map(|handle| {
+                self.open_protocol_exclusive::<SimpleFileSystem>(handle)
+                    .ok()
+                    .map(|fs| (handle, fs))
+            })
+            .collect()
+    }
+
+    /// Searches all the file systems for the regular file at `path`, and
+    /// opens the first match for reading, along with the handle of its file
+    /// system.
+    ///
+    /// The file systems are searched in the order of [`file_systems`], and
+    /// volumes that fail to open are skipped. Each file system is only held
+    /// open while it is searched, but the returned file keeps a reference to
+    /// its volume until it is closed.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: no file system has a regular file at `path`.
+    ///
+    /// [`file_systems`]: BootServices::file_systems
+    pub fn find_file_on_any_fs(&self, path: &CStr16) -> Result<(Handle, RegularFile)> {
+        let handles = self.find_handles::<SimpleFileSystem>()?;
+
+        handles
+            .into_iter()
+            .find_map(|handle| {
+                let mut fs = self
+                    .open_protocol_exclusive::<SimpleFileSystem>(handle)
+                    .ok()?;
+                let file = fs
+                    .open_volume()
+                    .ok()?
+                    .open(path, FileMode::Read, FileAttribute::empty())
+                    .ok()?
+                    .into_regular_file()?;
+                Some((handle, file))
+            })
+            .ok_or_else(|| Status::NOT_FOUND.into())
+    }
+
+    /// Lists the serial devices, along with information decoded from their
+    /// device paths.
+    ///
+    /// This can be used to tell apart the serial device used for the
+    /// console, which usually also carries the log output, from the
+    /// others. An empty list is returned if there are no serial devices.
+    pub fn serial_ports(&self) -> Vec<(Handle, SerialDeviceInfo)> {
+        let handles = match self.find_handles::<Serial>() {
+            Ok(handles) => handles,
+            Err(_) => return Vec::new(),
+        };
+
+        // Safety: the device paths are only used during this function and
+        // are opened without affecting drivers that use them.
+        let device_path = |handle| unsafe {
+            self.open_protocol::<DevicePath>(
+                OpenProtocolParams {
+                    handle,
+                    agent: self.image_handle(),
+                    controller: None,
+                },
+                OpenProtocolAttributes::GetProtocol,
+            )
+            .ok()
+        };
+
+        // A serial device is used for the console if one of its children,
+        // such as the terminal, is a console output device.
+        let console_paths: Vec<_> =
+            match self.locate_handle_buffer(SearchType::ByProtocol(&CONSOLE_OUT_DEVICE_GUID)) {
+                Ok(buffer) => buffer
+                    .handles()
+                    .iter()
+                    .filter_map(|&handle| device_path(handle))
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                let info = match device_path(handle) {
+                    Some(path) => {
+                        let is_console = console_paths
+                            .iter()
+                            .any(|console| console.starts_with(&path));
+                        SerialDeviceInfo::new(&path, is_console)
+                    }
+                    None => SerialDeviceInfo::default(),
+                };
+                (handle, info)
+            })
+            .collect()
+    }
+
+    /// Returns the first serial device that is not used for the console.
+    ///
+    /// Such a device can be opened in exclusive mode without interrupting
+    /// the console or log output. See [`serial_ports`] for details.
+    ///
+    /// [`serial_ports`]: BootServices::serial_ports
+    pub fn find_non_console_serial(&self) -> Option<Handle> {
+        self.serial_ports()
+            .into_iter()
+            .find(|(_, info)| !info.is_console)
+            .map(|(handle, _)| handle)
+    }
+
+    /// Describes a handle for debugging purposes.
+    ///
+    /// The description consists of the text form of the handle's device path,
+    /// if it has one and the `DevicePathToText` protocol is available,
+    /// followed by the GUIDs of all protocols installed on the handle. For
+    /// example: `PciRoot(0x0)/Pci(0x1,0x1) [09576e91-6d3f-11d2-8e39-00a0c969723b, ...]`.
+    ///
+    /// With the `known-guids` feature, known protocols are shown by name
+    /// instead, for example `[DevicePath, ...]`.
+    pub fn debug_handle(&self, handle: Handle) -> String {
+        let mut description = String::new();
+
+        let open = |handle| OpenProtocolParams {
+            handle,
+            agent: self.image_handle(),
+            controller: None,
+        };
+        // Safety: the protocols are only used during this function and are
+        // opened without affecting drivers that use them.
+        let device_path = unsafe {
+            self.open_protocol::<DevicePath>(open(handle), OpenProtocolAttributes::GetProtocol)
+        };
+        if let Ok(device_path) = device_path {
+            let text = self
+                .get_handle_for_protocol::<DevicePathToText>()
+                .and_then(|to_text| unsafe {
+                    self.open_protocol::<DevicePathToText>(
+                        open(to_text),
+                        OpenProtocolAttributes::GetProtocol,
+                    )
+                })
+                .ok()
+                .and_then(|to_text| {
+                    to_text.convert_device_path_to_text(
+                        self,
+                        &device_path,
+                        DisplayOnly(true),
+                        AllowShortcuts(true),
+                    )
+                });
+            match text {
+                Some(text) => write!(description, "{} ", &*text).unwrap(),
+                None => description.push_str("<device path> "),
+            }
+        }
+
+        match self.protocols_per_handle(handle) {
+            Ok(protocols) => {
+                description.push('[');
+                for (i, guid) in protocols.protocols().iter().enumerate() {
+                    if i != 0 {
+                        description.push_str(", ");
+                    }
+                    #[cfg(feature = "known-guids")]
+                    if let Some(name) = crate::proto::known_guids::name_for_guid(guid) {
+                        description.push_str(name);
+                        continue;
+                    }
+                    write!(description, "{}", guid).unwrap();
+                }
+                description.push(']');
+            }
+            Err(err) => write!(description, "[{:?}]", err.status()).unwrap(),
+        }
+
+        description
+    }
+
+    /// Retrieves a copy of the current memory map.
+    ///
+    /// Unlike [`memory_map`], this takes care of allocating a large enough
+    /// buffer. The allocation itself may change the memory map, so it is done
+    /// before the map is retrieved.
+    ///
+    /// [`memory_map`]: BootServices::memory_map
+    pub fn memory_map_owned(&self) -> Result<MemoryMapOwned> {
+        loop {
+            let sizes = self.memory_map_size();
+
+            // Leave room for the descriptors created by the allocations below.
+            let buf_size = sizes.map_size + 4 * sizes.entry_size;
+            let mut buffer = alloc_api::vec![0; buf_size + mem::align_of::<MemoryDescriptor>()];
+            let buffer = MemoryDescriptor::align_buf(&mut buffer).unwrap();
+            let mut descriptors = Vec::with_capacity(buf_size / sizes.entry_size);
+
+            let result = self.memory_map(buffer);
+            match result {
+                Ok((key, iter)) => {
+                    // The capacity is large enough, so this doesn't allocate.
+                    descriptors.extend(iter.copied());
+                    return Ok(MemoryMapOwned { key, descriptors });
+                }
+                Err(err) if err.status() == Status::BUFFER_TOO_SMALL => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
 }
 
 impl super::Table for BootServices {
@@ -1360,6 +2484,57 @@ pub enum LoadImageSource<'a> {
     },
 }
 
+/// A function allowing or denying the images loaded with
+/// [`BootServices::load_image`]. See [`BootServices::set_image_verifier`].
+pub type ImageVerifier = fn(image: ImageToVerify) -> bool;
+
+/// An image passed to the verifier set with
+/// [`BootServices::set_image_verifier`].
+#[derive(Debug)]
+pub enum ImageToVerify<'a> {
+    /// An image loaded from a buffer. The verifier is called with the raw
+    /// file before it is passed to the firmware.
+    File {
+        /// Contents of the image file.
+        data: &'a [u8],
+
+        /// The file path given in [`LoadImageSource::FromBuffer`], if any.
+        file_path: Option<&'a DevicePath>,
+    },
+
+    /// An image loaded by the firmware from a file path. The verifier is
+    /// called with the image as loaded in memory, with its sections in place
+    /// and relocations applied, before the handle is returned. The image is
+    /// unloaded if it is denied.
+    ///
+    /// This is not the file: signatures and hashes of the file can't be
+    /// checked on it. See [`BootServices::set_image_verifier`].
+    Loaded {
+        /// The loaded image, as described by [`LoadedImage::info`].
+        data: &'a [u8],
+
+        /// The path the image was loaded from.
+        file_path: &'a DevicePath,
+    },
+}
+
+impl<'a> ImageToVerify<'a> {
+    /// The bytes of the image, either the file or the loaded image.
+    pub fn data(&self) -> &'a [u8] {
+        match self {
+            Self::File { data, .. } | Self::Loaded { data, .. } => data,
+        }
+    }
+
+    /// The path of the image, if known.
+    pub fn file_path(&self) -> Option<&'a DevicePath> {
+        match self {
+            Self::File { file_path, .. } => *file_path,
+            Self::Loaded { file_path, .. } => Some(file_path),
+        }
+    }
+}
+
 newtype_enum! {
 /// Task priority level.
 ///
@@ -1401,6 +2576,58 @@ impl Drop for TplGuard<'_> {
     }
 }
 
+/// A set of protocol interfaces installed with
+/// [`BootServices::install_multiple_protocol_interfaces`].
+///
+/// The interfaces are uninstalled when this is dropped. Errors during that
+/// are ignored; use [`uninstall`] to handle them.
+///
+/// [`uninstall`]: InstalledProtocolInterfaces::uninstall
+#[derive(Debug)]
+pub struct InstalledProtocolInterfaces<'a> {
+    boot_services: &'a BootServices,
+    handle: Handle,
+    interfaces: &'a [(Guid, *const c_void)],
+}
+
+impl<'a> InstalledProtocolInterfaces<'a> {
+    /// Returns the handle the interfaces are installed on.
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+
+    /// Keeps the interfaces installed and returns their handle.
+    pub fn leak(self) -> Handle {
+        let handle = self.handle;
+        mem::forget(self);
+        handle
+    }
+
+    /// Uninstalls the interfaces.
+    ///
+    /// # Errors
+    ///
+    /// See [`BootServices::uninstall_multiple_protocol_interfaces`]. The
+    /// interfaces stay installed in that case.
+    pub fn uninstall(self) -> Result {
+        let result = unsafe {
+            self.boot_services
+                .uninstall_multiple_protocol_interfaces(self.handle, self.interfaces)
+        };
+        mem::forget(self);
+        result
+    }
+}
+
+impl Drop for InstalledProtocolInterfaces<'_> {
+    fn drop(&mut self) {
+        let _ = unsafe {
+            self.boot_services
+                .uninstall_multiple_protocol_interfaces(self.handle, self.interfaces)
+        };
+    }
+}
+
 // OpenProtocolAttributes is safe to model as a regular enum because it
 // is only used as an input. The attributes are bitflags, but all valid
 // combinations are listed in the spec and only ByDriver and Exclusive
@@ -1417,6 +2644,7 @@ impl Drop for TplGuard<'_> {
 // method: `BootServices::test_protocol`.
 
 /// Attributes for [`BootServices::open_protocol`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[repr(u32)]
 pub enum OpenProtocolAttributes {
     /// Used by drivers to get a protocol interface for a handle. The
@@ -1450,6 +2678,7 @@ pub enum OpenProtocolAttributes {
 }
 
 /// Parameters passed to [`BootServices::open_protocol`].
+#[derive(Debug, Copy, Clone)]
 pub struct OpenProtocolParams {
     /// The handle for the protocol to open.
     pub handle: Handle,
@@ -1476,9 +2705,21 @@ pub struct ScopedProtocol<'a, P: Protocol + ?Sized> {
     pub interface: &'a UnsafeCell<P>,
 
     open_params: OpenProtocolParams,
+    open_attributes: OpenProtocolAttributes,
     boot_services: &'a BootServices,
 }
 
+impl<'a, P: Protocol + ?Sized> ScopedProtocol<'a, P> {
+    /// Get the parameters and attributes the protocol was opened with.
+    ///
+    /// This is an associated function rather than a method so that it does
+    /// not shadow methods of the protocol itself: call it as
+    /// `ScopedProtocol::open_info(&protocol)`.
+    pub fn open_info(this: &Self) -> (&OpenProtocolParams, OpenProtocolAttributes) {
+        (&this.open_params, this.open_attributes)
+    }
+}
+
 impl<'a, P: Protocol + ?Sized> Drop for ScopedProtocol<'a, P> {
     fn drop(&mut self) {
         let status = (self.boot_services.close_protocol)(
@@ -1580,6 +2821,69 @@ impl MemoryType {
         assert!(value >= 0x80000000);
         MemoryType(value)
     }
+
+    /// Returns an iterator over the memory types defined by the UEFI
+    /// specification, from [`RESERVED`] to [`PERSISTENT_MEMORY`].
+    ///
+    /// [`RESERVED`]: MemoryType::RESERVED
+    /// [`PERSISTENT_MEMORY`]: MemoryType::PERSISTENT_MEMORY
+    pub fn standard_types() -> impl Iterator<Item = MemoryType> {
+        Self::VARIANTS.iter().map(|&(ty, _)| ty)
+    }
+
+    /// Returns the name of the memory type if it is defined by the UEFI
+    /// specification.
+    fn name(self) -> Option<&'static str> {
+        Self::VARIANTS
+            .iter()
+            .find(|&&(ty, _)| ty == self)
+            .map(|&(_, name)| name)
+    }
+}
+
+/// Memory types defined by the UEFI specification are displayed by name,
+/// for example `LOADER_DATA`. Other memory types, such as OEM-defined
+/// (`0x70000000..=0x7fffffff`) and OS-defined (`0x80000000..=0xffffffff`)
+/// ones, are displayed as a hexadecimal number, for example `0x80000001`.
+impl fmt::Display for MemoryType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{:#x}", self.0),
+        }
+    }
+}
+
+/// Parses the formats produced by the [`Display`] implementation: the name of
+/// a memory type defined by the UEFI specification, or a hexadecimal number
+/// prefixed with `0x` for any other memory type.
+///
+/// [`Display`]: fmt::Display
+impl FromStr for MemoryType {
+    type Err = ParseMemoryTypeError;
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        if let Some(hex) = s.strip_prefix("0x") {
+            return u32::from_str_radix(hex, 16)
+                .map(MemoryType)
+                .map_err(|_| ParseMemoryTypeError);
+        }
+        Self::VARIANTS
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map(|&(ty, _)| ty)
+            .ok_or(ParseMemoryTypeError)
+    }
+}
+
+/// Error returned when parsing a [`MemoryType`] from a string fails.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParseMemoryTypeError;
+
+impl fmt::Display for ParseMemoryTypeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid memory type")
+    }
 }
 
 /// Memory descriptor version number
@@ -1660,6 +2964,202 @@ bitflags! {
 #[repr(C)]
 pub struct MemoryMapKey(usize);
 
+/// Size in bytes of the pages described by a [`MemoryDescriptor`].
+const PAGE_SIZE: u64 = 4096;
+
+/// Extension trait for iterators over [`MemoryDescriptor`]s, such as the one
+/// returned by [`BootServices::memory_map`].
+pub trait MemoryMapIterExt<'a>: Iterator<Item = &'a MemoryDescriptor> + Sized {
+    /// Merge consecutive descriptors of the same type and attributes that
+    /// describe physically contiguous memory.
+    ///
+    /// Only descriptors that follow each other in the iterator are merged,
+    /// so unless the firmware returns the memory map sorted by address, sort
+    /// the descriptors first. A merged descriptor keeps the virtual address
+    /// of its first descriptor.
+    ///
+    /// This makes it simpler to display the memory map, or to find the
+    /// largest free region:
+    ///
+    /// ```no_run
+    /// use uefi::table::boot::{MemoryDescriptor, MemoryMapIterExt, MemoryType};
+    ///
+    /// fn largest_free_region<'a>(
+    ///     map: impl Iterator<Item = &'a MemoryDescriptor>,
+    /// ) -> Option<MemoryDescriptor> {
+    ///     map.coalesced()
+    ///         .filter(|desc| desc.ty == MemoryType::CONVENTIONAL)
+    ///         .max_by_key(|desc| desc.page_count)
+    /// }
+    /// ```
+    fn coalesced(self) -> Coalesced<'a, Self> {
+        Coalesced {
+            iter: self.peekable(),
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = &'a MemoryDescriptor>> MemoryMapIterExt<'a> for I {}
+
+/// Iterator merging contiguous [`MemoryDescriptor`]s, returned by
+/// [`MemoryMapIterExt::coalesced`].
+#[derive(Debug, Clone)]
+pub struct Coalesced<'a, I: Iterator<Item = &'a MemoryDescriptor>> {
+    iter: Peekable<I>,
+}
+
+impl<'a, I: Iterator<Item = &'a MemoryDescriptor>> Iterator for Coalesced<'a, I> {
+    type Item = MemoryDescriptor;
+
+    fn next(&mut self) -> Option<MemoryDescriptor> {
+        let mut merged = *self.iter.next()?;
+        while let Some(next) = self.iter.next_if(|next| {
+            let end = merged
+                .page_count
+                .checked_mul(PAGE_SIZE)
+                .and_then(|size| merged.phys_start.checked_add(size));
+            next.ty == merged.ty
+                && next.att == merged.att
+                && end == Some(next.phys_start)
+                // Stop merging if the page count would overflow.
+                && merged.page_count.checked_add(next.page_count).is_some()
+        }) {
+            merged.page_count += next.page_count;
+        }
+        Some(merged)
+    }
+}
+
+/// A copy of the memory map, as returned by [`BootServices::memory_map_owned`].
+#[cfg(feature = "exts")]
+#[derive(Debug, Clone)]
+pub struct MemoryMapOwned {
+    key: MemoryMapKey,
+    descriptors: Vec<MemoryDescriptor>,
+}
+
+#[cfg(feature = "exts")]
+impl MemoryMapOwned {
+    /// Create a memory map from a list of descriptors.
+    pub fn from_descriptors(key: MemoryMapKey, descriptors: Vec<MemoryDescriptor>) -> Self {
+        Self { key, descriptors }
+    }
+
+    /// The key of the memory map at the time it was retrieved.
+    pub fn key(&self) -> MemoryMapKey {
+        self.key
+    }
+
+    /// The memory descriptors, in the order returned by the firmware.
+    pub fn entries(&self) -> &[MemoryDescriptor] {
+        &self.descriptors
+    }
+
+    /// The number of [`MemoryType::CONVENTIONAL`] (free) pages.
+    pub fn free_pages(&self) -> u64 {
+        self.descriptors
+            .iter()
+            .filter(|desc| desc.ty == MemoryType::CONVENTIONAL)
+            .map(|desc| desc.page_count)
+            .sum()
+    }
+
+    /// Compare this memory map (the older snapshot) to `other` (the newer
+    /// snapshot).
+    ///
+    /// The descriptors of the two maps don't need to line up: the firmware
+    /// splits and merges regions as memory gets allocated and freed, so the
+    /// maps are compared range by range. Memory that is only described by one
+    /// of the two maps is ignored.
+    pub fn diff(&self, other: &MemoryMapOwned) -> MemoryMapDiff {
+        let mut old = self.descriptors.clone();
+        let mut new = other.descriptors.clone();
+        old.sort_unstable_by_key(|desc| desc.phys_start);
+        new.sort_unstable_by_key(|desc| desc.phys_start);
+
+        let end = |desc: &MemoryDescriptor| desc.phys_start + desc.page_count * PAGE_SIZE;
+
+        let mut changes: Vec<MemoryTypeChange> = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() && j < new.len() {
+            let (a, b) = (&old[i], &new[j]);
+            let start = a.phys_start.max(b.phys_start);
+            let stop = end(a).min(end(b));
+
+            if start < stop && a.ty != b.ty {
+                let page_count = (stop - start) / PAGE_SIZE;
+                match changes.last_mut() {
+                    // Merge with the previous change if it is contiguous.
+                    Some(last)
+                        if last.old_ty == a.ty
+                            && last.new_ty == b.ty
+                            && last.phys_start + last.page_count * PAGE_SIZE == start =>
+                    {
+                        last.page_count += page_count;
+                    }
+                    _ => changes.push(MemoryTypeChange {
+                        phys_start: start,
+                        page_count,
+                        old_ty: a.ty,
+                        new_ty: b.ty,
+                    }),
+                }
+            }
+
+            // Advance past whichever region ends first.
+            if end(a) <= end(b) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        MemoryMapDiff {
+            changes,
+            old_free_pages: self.free_pages(),
+            new_free_pages: other.free_pages(),
+        }
+    }
+}
+
+/// A memory range whose type changed between two memory maps.
+#[cfg(feature = "exts")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MemoryTypeChange {
+    /// Starting physical address of the range.
+    pub phys_start: u64,
+    /// Number of 4 KiB pages in the range.
+    pub page_count: u64,
+    /// Type of the range in the older memory map.
+    pub old_ty: MemoryType,
+    /// Type of the range in the newer memory map.
+    pub new_ty: MemoryType,
+}
+
+/// Differences between two memory maps, as returned by
+/// [`MemoryMapOwned::diff`].
+#[cfg(feature = "exts")]
+#[derive(Debug, Clone)]
+pub struct MemoryMapDiff {
+    changes: Vec<MemoryTypeChange>,
+    old_free_pages: u64,
+    new_free_pages: u64,
+}
+
+#[cfg(feature = "exts")]
+impl MemoryMapDiff {
+    /// The ranges whose type changed, sorted by address.
+    pub fn changes(&self) -> &[MemoryTypeChange] {
+        &self.changes
+    }
+
+    /// Net change in the number of free pages. A negative value means that
+    /// memory was allocated.
+    pub fn free_pages_change(&self) -> i64 {
+        self.new_free_pages as i64 - self.old_free_pages as i64
+    }
+}
+
 /// A structure containing the size of a memory descriptor and the size of the memory map
 pub struct MemoryMapSize {
     /// Size of a single memory descriptor in bytes
@@ -1713,7 +3213,13 @@ pub enum SearchType<'guid> {
     /// If the protocol implements the `Protocol` interface,
     /// you can use the `from_proto` function to construct a new `SearchType`.
     ByProtocol(&'guid Guid),
-    // TODO: add ByRegisterNotify once the corresponding function is implemented.
+    /// Returns the handles on which an interface was installed since the
+    /// last search with this key, for the protocol and event registered by
+    /// [`BootServices::register_protocol_notify`].
+    ///
+    /// [`BootServices::locate_handle`] only returns one handle at a time
+    /// with this search type.
+    ByRegisterNotify(ProtocolSearchKey),
 }
 
 impl<'guid> SearchType<'guid> {
@@ -1723,6 +3229,13 @@ impl<'guid> SearchType<'guid> {
     }
 }
 
+/// Opaque key of a protocol notification, returned by
+/// [`BootServices::register_protocol_notify`] in a
+/// [`SearchType::ByRegisterNotify`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct ProtocolSearchKey(NonNull<c_void>);
+
 bitflags! {
     /// Flags describing the type of an UEFI event and its attributes.
     pub struct EventType: u32 {
@@ -1801,6 +3314,144 @@ impl<'a> ProtocolsPerHandle<'a> {
     }
 }
 
+/// Information about the agents that have a protocol interface open, as
+/// returned by [`BootServices::open_protocol_information`].
+pub struct OpenProtocolInformation<'a> {
+    // The pointer returned by `open_protocol_information` has to be free'd
+    // with `free_pool`, so keep a reference to boot services for that purpose.
+    boot_services: &'a BootServices,
+
+    entries: *mut OpenProtocolInfoEntry,
+    count: usize,
+}
+
+impl<'a> Drop for OpenProtocolInformation<'a> {
+    fn drop(&mut self) {
+        if !self.entries.is_null() {
+            // Ignore the result, we can't do anything about an error here.
+            let _ = self.boot_services.free_pool(self.entries.cast::<u8>());
+        }
+    }
+}
+
+impl<'a> OpenProtocolInformation<'a> {
+    /// Get the open protocol entries.
+    pub fn entries(&self) -> &[OpenProtocolInfoEntry] {
+        if self.entries.is_null() {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(self.entries, self.count) }
+        }
+    }
+}
+
+/// An agent that has a protocol interface open.
+///
+/// Corresponds to the `EFI_OPEN_PROTOCOL_INFORMATION_ENTRY` type in the C API.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct OpenProtocolInfoEntry {
+    /// The handle of the agent that opened the protocol.
+    pub agent: Option<Handle>,
+
+    /// The controller handle the protocol was opened for, if any.
+    pub controller: Option<Handle>,
+
+    /// The attributes the protocol was opened with. This is a bitmask that
+    /// may contain [`OpenProtocolAttributes`] values, as well as the
+    /// `BY_HANDLE_PROTOCOL` (0x01) and `TEST_PROTOCOL` (0x04) attributes.
+    pub attributes: u32,
+
+    /// The number of times the protocol was opened by this agent with these
+    /// attributes.
+    pub open_count: u32,
+}
+
+/// The alignment of pool allocations.
+const POOL_ALIGNMENT: usize = 8;
+
+/// A value stored in memory allocated from a pool, as returned by
+/// [`BootServices::allocate_pool_boxed`].
+///
+/// The value is dropped and the memory is freed when this is dropped.
+pub struct PoolBox<'a, T> {
+    // The memory has to be free'd with `free_pool`, so keep a reference to
+    // boot services for that purpose.
+    boot_services: &'a BootServices,
+    ptr: *mut T,
+}
+
+impl<'a, T> PoolBox<'a, T> {
+    /// Consume the box without dropping the value or freeing the memory,
+    /// and return a pointer to the value, for example to pass it on to the
+    /// OS.
+    #[must_use]
+    pub fn into_raw(self) -> *mut T {
+        let ptr = self.ptr;
+        mem::forget(self);
+        ptr
+    }
+}
+
+impl<T> Deref for PoolBox<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<T> DerefMut for PoolBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.ptr }
+    }
+}
+
+impl<T: Debug> Debug for PoolBox<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T> Drop for PoolBox<'_, T> {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.ptr) };
+        // Ignore the result, we can't do anything about an error here.
+        let _ = self.boot_services.free_pool(self.ptr.cast());
+    }
+}
+
+/// Frees a pool allocation made by the firmware, if any, when dropped.
+///
+/// This is used by protocols whose functions return a buffer allocated by
+/// the firmware, which the caller has to free.
+#[cfg(feature = "exts")]
+pub(crate) struct PoolGuard<'a> {
+    boot_services: &'a BootServices,
+    addr: *mut u8,
+}
+
+#[cfg(feature = "exts")]
+impl<'a> PoolGuard<'a> {
+    /// Free `addr` when the guard is dropped, unless it is null.
+    pub(crate) fn new(boot_services: &'a BootServices, addr: *mut u8) -> Self {
+        Self {
+            boot_services,
+            addr,
+        }
+    }
+}
+
+#[cfg(feature = "exts")]
+impl Drop for PoolGuard<'_> {
+    fn drop(&mut self) {
+        if !self.addr.is_null() {
+            // Nothing can be done if freeing fails.
+            let _ = self.boot_services.free_pool(self.addr);
+        }
+    }
+}
+
 /// A buffer that contains an array of [`Handles`][Handle] that support the requested protocol.
 /// Returned by [`BootServices::locate_handle_buffer`].
 pub struct HandleBuffer<'a> {
@@ -1825,4 +3476,186 @@ impl<'a> HandleBuffer<'a> {
         // appropriate lifetime of the slice.
         unsafe { slice::from_raw_parts(self.buffer, self.count) }
     }
+
+    /// The handles of the buffer, which may be reordered.
+    fn handles_mut(&mut self) -> &mut [Handle] {
+        unsafe { slice::from_raw_parts_mut(self.buffer, self.count) }
+    }
+}
+
+impl Deref for HandleBuffer<'_> {
+    type Target = [Handle];
+
+    fn deref(&self) -> &[Handle] {
+        self.handles()
+    }
+}
+
+/// A fingerprint of the set of handles in the database, returned by
+/// [`BootServices::handle_database_version`].
+///
+/// Two versions are equal if the same handles existed when they were taken,
+/// in all likelihood: a handle that was destroyed and recreated at the same
+/// address, or a CRC-32 collision, go unnoticed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct HandleDatabaseVersion {
+    count: usize,
+    crc32: u32,
+}
+
+impl HandleDatabaseVersion {
+    /// The number of handles in the database.
+    pub fn handle_count(&self) -> usize {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_api::vec;
+
+    fn desc(ty: MemoryType, phys_start: u64, page_count: u64) -> MemoryDescriptor {
+        MemoryDescriptor {
+            ty,
+            phys_start,
+            page_count,
+            ..MemoryDescriptor::default()
+        }
+    }
+
+    #[test]
+    fn test_memory_map_diff() {
+        let old = MemoryMapOwned::from_descriptors(
+            MemoryMapKey(0),
+            vec![
+                desc(MemoryType::CONVENTIONAL, 0x0000, 4),
+                desc(MemoryType::RESERVED, 0x4000, 1),
+                desc(MemoryType::CONVENTIONAL, 0x5000, 8),
+            ],
+        );
+        // The 8-page region gets split in two by an allocation, and the
+        // descriptors are returned in a different order.
+        let new = MemoryMapOwned::from_descriptors(
+            MemoryMapKey(1),
+            vec![
+                desc(MemoryType::CONVENTIONAL, 0x8000, 5),
+                desc(MemoryType::CONVENTIONAL, 0x0000, 4),
+                desc(MemoryType::RESERVED, 0x4000, 1),
+                desc(MemoryType::LOADER_DATA, 0x5000, 3),
+            ],
+        );
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.changes(),
+            [MemoryTypeChange {
+                phys_start: 0x5000,
+                page_count: 3,
+                old_ty: MemoryType::CONVENTIONAL,
+                new_ty: MemoryType::LOADER_DATA,
+            }]
+        );
+        assert_eq!(diff.free_pages_change(), -3);
+
+        // Diffing a map with itself finds no changes.
+        let diff = new.diff(&new);
+        assert!(diff.changes().is_empty());
+        assert_eq!(diff.free_pages_change(), 0);
+    }
+
+    #[test]
+    fn test_coalesced() {
+        let mut write_back = desc(MemoryType::CONVENTIONAL, 0x6000, 1);
+        write_back.att = MemoryAttribute::WRITE_BACK;
+        let descriptors = [
+            desc(MemoryType::CONVENTIONAL, 0x0000, 2),
+            desc(MemoryType::CONVENTIONAL, 0x2000, 1),
+            // Same type, but not contiguous.
+            desc(MemoryType::CONVENTIONAL, 0x4000, 1),
+            desc(MemoryType::CONVENTIONAL, 0x5000, 1),
+            // Contiguous, but with different attributes.
+            write_back,
+            // Contiguous, but with a different type.
+            desc(MemoryType::LOADER_DATA, 0x7000, 1),
+            desc(MemoryType::LOADER_DATA, 0x8000, 3),
+        ];
+
+        let coalesced: Vec<_> = descriptors
+            .iter()
+            .coalesced()
+            .map(|desc| (desc.ty, desc.phys_start, desc.page_count))
+            .collect();
+        assert_eq!(
+            coalesced,
+            [
+                (MemoryType::CONVENTIONAL, 0x0000, 3),
+                (MemoryType::CONVENTIONAL, 0x4000, 2),
+                (MemoryType::CONVENTIONAL, 0x6000, 1),
+                (MemoryType::LOADER_DATA, 0x7000, 4),
+            ]
+        );
+
+        let empty: [MemoryDescriptor; 0] = [];
+        assert_eq!(empty.iter().coalesced().count(), 0);
+        // The end of a descriptor that overflows doesn't match any address.
+        let overflowing = [
+            desc(MemoryType::RESERVED, u64::MAX - 0xfff, u64::MAX),
+            desc(MemoryType::RESERVED, 0, 1),
+        ];
+        assert_eq!(overflowing.iter().coalesced().count(), 2);
+        // Neither does a page count that would overflow once merged.
+        let overflowing = [
+            desc(MemoryType::RESERVED, 0, 1),
+            desc(MemoryType::RESERVED, 0x1000, u64::MAX),
+        ];
+        assert_eq!(overflowing.iter().coalesced().count(), 2);
+    }
+
+    #[test]
+    fn test_memory_map_diff_merges_contiguous_changes() {
+        let old = MemoryMapOwned::from_descriptors(
+            MemoryMapKey(0),
+            vec![
+                desc(MemoryType::CONVENTIONAL, 0x0000, 2),
+                desc(MemoryType::CONVENTIONAL, 0x2000, 2),
+            ],
+        );
+        let new = MemoryMapOwned::from_descriptors(
+            MemoryMapKey(1),
+            vec![desc(MemoryType::BOOT_SERVICES_DATA, 0x0000, 4)],
+        );
+
+        let diff = old.diff(&new);
+        assert_eq!(
+            diff.changes(),
+            [MemoryTypeChange {
+                phys_start: 0x0000,
+                page_count: 4,
+                old_ty: MemoryType::CONVENTIONAL,
+                new_ty: MemoryType::BOOT_SERVICES_DATA,
+            }]
+        );
+        assert_eq!(diff.free_pages_change(), -4);
+    }
+
+    #[test]
+    fn test_memory_type_strings() {
+        use crate::alloc_api::string::ToString;
+
+        assert_eq!(MemoryType::LOADER_DATA.to_string(), "LOADER_DATA");
+        assert_eq!(MemoryType::custom(0x8000_0001).to_string(), "0x80000001");
+        assert_eq!(MemoryType(0x7000_0000).to_string(), "0x70000000");
+
+        for ty in MemoryType::standard_types().chain([MemoryType::custom(0x8000_0001)]) {
+            assert_eq!(ty.to_string().parse(), Ok(ty));
+        }
+        assert_eq!(MemoryType::standard_types().count(), 15);
+        assert_eq!("0x7".parse(), Ok(MemoryType::CONVENTIONAL));
+        assert_eq!(
+            "loader_data".parse::<MemoryType>(),
+            Err(ParseMemoryTypeError)
+        );
+        assert_eq!("0x".parse::<MemoryType>(), Err(ParseMemoryTypeError));
+    }
 }
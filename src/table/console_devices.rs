@@ -0,0 +1,271 @@
+//! Console device variables: `ConIn`, `ConOut`, `ErrOut`, and their `*Dev`
+//! counterparts.
+//!
+//! Each of these global variables holds a multi-instance device path, with
+//! one instance per console device. The firmware connects the devices of
+//! `ConIn`, `ConOut` and `ErrOut` as the consoles on boot, and lists the
+//! devices that could be used as consoles in the `*Dev` variables.
+//!
+//! The variables are read and written as [`ConsoleDevices`] with
+//! [`RuntimeServices::console_devices`] and
+//! [`RuntimeServices::set_console_devices`]. For example, a serial port
+//! can be added to `ConOut` so that the output of the firmware is also
+//! sent to it on the next boot:
+//!
+//! ```no_run
+//! use uefi::proto::device_path::DevicePath;
+//! use uefi::table::console_devices::ConsoleVariable;
+//! use uefi::table::runtime::RuntimeServices;
+//! use uefi::Result;
+//!
+//! fn add_serial_console(rt: &RuntimeServices, serial: &DevicePath) -> Result {
+//!     let mut devices = rt.console_devices(ConsoleVariable::ConOut)?;
+//!     if devices.add(serial) {
+//!         rt.set_console_devices(ConsoleVariable::ConOut, &devices)?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`RuntimeServices::console_devices`]: super::runtime::RuntimeServices::console_devices
+//! [`RuntimeServices::set_console_devices`]: super::runtime::RuntimeServices::set_console_devices
+
+use super::load_option::is_valid_path;
+use crate::alloc_api::vec::Vec;
+use crate::proto::device_path::build::DevicePathBuilder;
+use crate::proto::device_path::{DevicePath, DevicePathInstance, DeviceSubType, DeviceType};
+use crate::CStr16;
+use uefi_macros::cstr16;
+
+/// A global variable listing console devices.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConsoleVariable {
+    /// `ConIn`: the devices used as console input.
+    ConIn,
+    /// `ConOut`: the devices used as console output.
+    ConOut,
+    /// `ErrOut`: the devices used for error output.
+    ErrOut,
+    /// `ConInDev`: the devices that could be used as console input.
+    ConInDev,
+    /// `ConOutDev`: the devices that could be used as console output.
+    ConOutDev,
+    /// `ErrOutDev`: the devices that could be used for error output.
+    ErrOutDev,
+}
+
+impl ConsoleVariable {
+    /// Name of the variable.
+    pub fn name(self) -> &'static CStr16 {
+        match self {
+            Self::ConIn => cstr16!("ConIn"),
+            Self::ConOut => cstr16!("ConOut"),
+            Self::ErrOut => cstr16!("ErrOut"),
+            Self::ConInDev => cstr16!("ConInDev"),
+            Self::ConOutDev => cstr16!("ConOutDev"),
+            Self::ErrOutDev => cstr16!("ErrOutDev"),
+        }
+    }
+
+    /// Whether the variable is set by the firmware on each boot, and can't
+    /// be written. This is the case of the `*Dev` variables.
+    pub fn is_read_only(self) -> bool {
+        matches!(self, Self::ConInDev | Self::ConOutDev | Self::ErrOutDev)
+    }
+}
+
+/// The devices listed in a [`ConsoleVariable`], one device path per
+/// instance of the multi-instance path stored in the variable.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConsoleDevices {
+    /// Single-instance paths, each ending with an end-entire node.
+    instances: Vec<Vec<u8>>,
+}
+
+impl ConsoleDevices {
+    /// Create an empty list of devices.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse the contents of a console variable, or return `None` if it
+    /// isn't a valid device path.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if !is_valid_path(data) {
+            return None;
+        }
+        // Safety: the nodes have been checked to be complete, and the path
+        // ends with an end-entire node.
+        let path = unsafe { DevicePath::from_ffi_ptr(data.as_ptr().cast()) };
+        let instances = non_empty_instances(path).map(copy_instance).collect();
+        Some(Self { instances })
+    }
+
+    /// Iterate over the paths of the devices, in the order they are
+    /// stored in the variable.
+    pub fn iter(&self) -> impl Iterator<Item = &DevicePath> {
+        self.instances
+            .iter()
+            .map(|instance| unsafe { DevicePath::from_ffi_ptr(instance.as_ptr().cast()) })
+    }
+
+    /// Number of devices.
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Whether there are no devices.
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Whether the device at `path` is in the list.
+    pub fn contains(&self, path: &DevicePath) -> bool {
+        self.iter().any(|device| device == path)
+    }
+
+    /// Append each instance of `path` that isn't in the list yet, and
+    /// return whether any was added.
+    pub fn add(&mut self, path: &DevicePath) -> bool {
+        let mut added = false;
+        for instance in non_empty_instances(path) {
+            let instance = copy_instance(instance);
+            if !self.instances.contains(&instance) {
+                self.instances.push(instance);
+                added = true;
+            }
+        }
+        added
+    }
+
+    /// Remove each instance of `path` from the list, and return whether
+    /// any was removed. The order of the other devices is preserved.
+    pub fn remove(&mut self, path: &DevicePath) -> bool {
+        let len = self.instances.len();
+        for instance in non_empty_instances(path) {
+            let instance = copy_instance(instance);
+            self.instances.retain(|device| *device != instance);
+        }
+        self.instances.len() != len
+    }
+
+    /// Serialize the devices as a multi-instance path, the contents of the
+    /// variable. An empty list is serialized as an empty buffer, which
+    /// deletes the variable when written.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut vec = Vec::new();
+        if self.is_empty() {
+            return vec;
+        }
+        let mut builder = DevicePathBuilder::with_vec(&mut vec);
+        for (i, path) in self.iter().enumerate() {
+            builder = builder.push_path(path);
+            if i + 1 != self.len() {
+                builder = builder
+                    .push_raw(DeviceType::END, DeviceSubType::END_INSTANCE, &[])
+                    .unwrap();
+            }
+        }
+        builder.finalize();
+        debug_assert!(is_valid_path(&vec));
+        vec
+    }
+}
+
+/// Iterate over the instances of `path` that have nodes. A variable with no
+/// devices may hold a path made of only the end-entire node.
+fn non_empty_instances(path: &DevicePath) -> impl Iterator<Item = &DevicePathInstance> {
+    path.instance_iter()
+        .filter(|instance| instance.node_iter().next().is_some())
+}
+
+/// Copy the nodes of `instance` to a single-instance path.
+fn copy_instance(instance: &DevicePathInstance) -> Vec<u8> {
+    let mut vec = Vec::new();
+    instance
+        .node_iter()
+        .fold(DevicePathBuilder::with_vec(&mut vec), |builder, node| {
+            builder.push_node(node)
+        })
+        .finalize();
+    vec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_api::vec;
+
+    fn add_node(path: &mut Vec<u8>, device_type: u8, sub_type: u8, node_data: &[u8]) {
+        path.push(device_type);
+        path.push(sub_type);
+        path.extend(u16::try_from(4 + node_data.len()).unwrap().to_le_bytes());
+        path.extend(node_data);
+    }
+
+    /// Create the path of a UART on the given PCI device.
+    fn serial_instance(path: &mut Vec<u8>, pci_device: u8) {
+        add_node(path, 0x02, 0x01, &[0; 8]);
+        add_node(path, 0x01, 0x01, &[0, pci_device]);
+        add_node(path, 0x03, 0x0e, &[0; 15]);
+    }
+
+    fn single_path(pci_device: u8) -> Vec<u8> {
+        let mut path = Vec::new();
+        serial_instance(&mut path, pci_device);
+        add_node(&mut path, 0x7f, 0xff, &[]);
+        path
+    }
+
+    fn path(bytes: &[u8]) -> &DevicePath {
+        unsafe { DevicePath::from_ffi_ptr(bytes.as_ptr().cast()) }
+    }
+
+    #[test]
+    fn test_console_devices() {
+        let mut data = Vec::new();
+        serial_instance(&mut data, 1);
+        add_node(&mut data, 0x7f, 0x01, &[]);
+        serial_instance(&mut data, 2);
+        add_node(&mut data, 0x7f, 0xff, &[]);
+
+        let mut devices = ConsoleDevices::parse(&data).unwrap();
+        assert_eq!(devices.len(), 2);
+        assert!(devices.contains(path(&single_path(1))));
+        assert!(devices.contains(path(&single_path(2))));
+        assert_eq!(devices.to_bytes(), data);
+
+        // Adding a device that is already present does nothing.
+        let third = single_path(3);
+        assert!(!devices.add(path(&single_path(2))));
+        assert!(devices.add(path(&third)));
+        assert_eq!(devices.len(), 3);
+
+        assert!(devices.remove(path(&single_path(1))));
+        assert!(!devices.remove(path(&single_path(1))));
+        let paths: Vec<_> = devices.iter().collect();
+        assert_eq!(paths, [path(&single_path(2)), path(&third)]);
+
+        let bytes = devices.to_bytes();
+        assert_eq!(ConsoleDevices::parse(&bytes).unwrap(), devices);
+        assert_eq!(path(&bytes).instance_iter().count(), 2);
+
+        assert!(devices.remove(path(&bytes)));
+        assert!(devices.is_empty());
+        assert!(devices.to_bytes().is_empty());
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(ConsoleDevices::parse(&[]), None);
+        // Missing end node.
+        let mut data = Vec::new();
+        serial_instance(&mut data, 1);
+        assert_eq!(ConsoleDevices::parse(&data), None);
+        // Truncated node.
+        let mut data = vec![0x7f, 0xff, 4];
+        assert_eq!(ConsoleDevices::parse(&data), None);
+        data.push(0);
+        assert_eq!(ConsoleDevices::parse(&data), Some(ConsoleDevices::new()));
+    }
+}
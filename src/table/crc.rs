@@ -0,0 +1,79 @@
+use super::boot::{BootServices, MemoryType};
+use crate::Status;
+use core::{mem, ptr, slice};
+
+/// Structures protected by a CRC32 that is computed over the structure
+/// with its CRC field set to zero, such as the header of the standard UEFI
+/// tables and the GPT header.
+///
+/// ACPI tables are protected by an 8-bit checksum rather than a CRC, so
+/// they are not validated through this trait.
+pub trait Crc32Validated {
+    /// Offset in bytes of the CRC field within the structure.
+    const CRC_OFFSET: usize;
+
+    /// The number of bytes covered by the CRC, starting at the beginning
+    /// of the structure.
+    fn crc_size(&self) -> usize;
+
+    /// Checks that the CRC field matches the contents of the structure.
+    ///
+    /// The CRC is computed with the `CalculateCrc32` boot service over a
+    /// temporary copy of the structure, in which the CRC field is set to
+    /// zero; the structure itself is not modified.
+    ///
+    /// # Safety
+    ///
+    /// [`crc_size`] bytes starting at `self` must be readable. This always
+    /// holds if `crc_size` is not larger than the structure, but the
+    /// header of a table is followed by the rest of the table, for
+    /// example.
+    ///
+    /// [`crc_size`]: Self::crc_size
+    unsafe fn validate_crc(&self, bt: &BootServices) -> Result<(), CrcError> {
+        let size = self.crc_size();
+        if size < Self::CRC_OFFSET + mem::size_of::<u32>() {
+            return Err(CrcError::InvalidSize { found: size });
+        }
+
+        let data = (self as *const Self).cast::<u8>();
+        let expected = ptr::read_unaligned(data.add(Self::CRC_OFFSET).cast::<u32>());
+
+        let copy = bt
+            .allocate_pool(MemoryType::LOADER_DATA, size)
+            .map_err(|err| CrcError::Service(err.status()))?;
+        ptr::copy_nonoverlapping(data, copy, size);
+        ptr::write_unaligned(copy.add(Self::CRC_OFFSET).cast::<u32>(), 0);
+        let computed = bt.calculate_crc32(slice::from_raw_parts(copy, size));
+        // Freeing the copy can't fail, as it was just allocated.
+        let _ = bt.free_pool(copy);
+
+        let computed = computed.map_err(|err| CrcError::Service(err.status()))?;
+        if computed != expected {
+            return Err(CrcError::Mismatch { expected, computed });
+        }
+        Ok(())
+    }
+}
+
+/// Returned by [`Crc32Validated::validate_crc`] if a structure doesn't match
+/// its CRC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrcError {
+    /// The size covered by the CRC is too small to contain the CRC field.
+    InvalidSize {
+        /// The size covered by the CRC.
+        found: usize,
+    },
+    /// The CRC computed over the structure does not match its CRC field,
+    /// so the structure has been corrupted or modified without updating
+    /// the CRC.
+    Mismatch {
+        /// The CRC read from the structure.
+        expected: u32,
+        /// The CRC computed over the structure.
+        computed: u32,
+    },
+    /// The firmware failed to compute the CRC.
+    Service(Status),
+}
@@ -1,7 +1,9 @@
-use super::Revision;
+use super::boot::BootServices;
+use super::{Crc32Validated, CrcError, Revision};
+use crate::Status;
 
 /// All standard UEFI tables begin with a common header.
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 #[repr(C)]
 pub struct Header {
     /// Unique identifier for this table.
@@ -16,3 +18,76 @@ pub struct Header {
     /// Reserved field that must be set to 0.
     _reserved: u32,
 }
+
+impl Header {
+    /// Checks that the table starting with this header has the expected
+    /// `signature`, is at least `min_size` bytes large, and matches its CRC.
+    ///
+    /// # Safety
+    ///
+    /// The header must be followed by `self.size` bytes of table data.
+    pub(crate) unsafe fn validate(
+        &self,
+        signature: u64,
+        min_size: usize,
+        bt: &BootServices,
+    ) -> Result<(), HeaderError> {
+        if self.signature != signature {
+            return Err(HeaderError::InvalidSignature {
+                found: self.signature,
+            });
+        }
+        let size = self.size as usize;
+        if size < min_size {
+            return Err(HeaderError::InvalidSize { found: self.size });
+        }
+
+        self.validate_crc(bt).map_err(|err| match err {
+            CrcError::InvalidSize { .. } => HeaderError::InvalidSize { found: self.size },
+            CrcError::Mismatch { expected, computed } => {
+                HeaderError::CrcMismatch { expected, computed }
+            }
+            CrcError::Service(status) => HeaderError::CrcService(status),
+        })?;
+        Ok(())
+    }
+}
+
+/// The CRC of a table covers the whole table, of which the header is only
+/// the beginning.
+impl Crc32Validated for Header {
+    // The CRC follows the signature, the revision and the size.
+    const CRC_OFFSET: usize = 16;
+
+    fn crc_size(&self) -> usize {
+        self.size as usize
+    }
+}
+
+/// Returned by [`SystemTable::validate_header`] if the header of a table is
+/// not valid.
+///
+/// [`SystemTable::validate_header`]: super::SystemTable::validate_header
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderError {
+    /// The signature does not identify the expected table.
+    InvalidSignature {
+        /// The signature read from the header.
+        found: u64,
+    },
+    /// The size is smaller than the table defined by the specification.
+    InvalidSize {
+        /// The size read from the header.
+        found: u32,
+    },
+    /// The CRC of the table does not match the CRC in the header, so the
+    /// table has been corrupted or modified without updating the CRC.
+    CrcMismatch {
+        /// The CRC read from the header.
+        expected: u32,
+        /// The CRC computed over the table.
+        computed: u32,
+    },
+    /// The firmware failed to compute the CRC.
+    CrcService(Status),
+}
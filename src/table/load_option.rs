@@ -0,0 +1,445 @@
+//! Load options, the contents of the `Boot####` and `Driver####` variables.
+//!
+//! A [`LoadOption`] describes an entry of the boot menu: its description,
+//! the device path of the image to load, and data passed to the image. The
+//! variables are read and written with [`RuntimeServices::get_variable`]
+//! and [`RuntimeServices::set_variable`], in the
+//! [`VariableVendor::GLOBAL_VARIABLE`] namespace.
+//!
+//! Device paths of boot entries usually start with the hardware path of the
+//! disk, which becomes invalid when the EFI system partition is moved to
+//! another disk. [`LoadOption::relocate`] repairs such entries.
+//!
+//! [`RuntimeServices::get_variable`]: super::runtime::RuntimeServices::get_variable
+//! [`RuntimeServices::set_variable`]: super::runtime::RuntimeServices::set_variable
+//! [`VariableVendor::GLOBAL_VARIABLE`]: super::runtime::VariableVendor::GLOBAL_VARIABLE
+
+use super::boot::{BootServices, OpenProtocolAttributes, OpenProtocolParams};
+use crate::alloc_api::vec::Vec;
+use crate::proto::device_path::{
+    DevicePath, DevicePathHeader, DevicePathNode, HardDriveMediaDevicePath, PartitionSignature,
+};
+use crate::proto::media::partition::PartitionInfo;
+use crate::{CString16, Guid, Handle, Status};
+use core::{mem, slice};
+
+/// Size of the fixed part of `EFI_LOAD_OPTION`: the attributes and the
+/// length of the file path list.
+const LOAD_OPTION_HEADER_SIZE: usize = 6;
+
+/// A load option, as stored in a `Boot####` or `Driver####` variable
+/// (`EFI_LOAD_OPTION`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadOption {
+    /// `LOAD_OPTION_*` attributes, such as whether the option is active.
+    pub attributes: u32,
+    /// Description of the option, shown in the boot menu.
+    pub description: CString16,
+    file_path_list: Vec<u8>,
+    /// Data passed to the loaded image, for example its command line.
+    pub optional_data: Vec<u8>,
+}
+
+impl LoadOption {
+    /// Parse the contents of a load option variable.
+    pub fn parse(data: &[u8]) -> Result<Self, LoadOptionError> {
+        if data.len() < LOAD_OPTION_HEADER_SIZE {
+            return Err(LoadOptionError::Invalid);
+        }
+        let attributes = u32::from_le_bytes(data[0..4].try_into().unwrap());
+        let file_path_list_length = usize::from(u16::from_le_bytes(data[4..6].try_into().unwrap()));
+
+        // The description is not aligned in the variable.
+        let mut description = Vec::new();
+        let mut offset = LOAD_OPTION_HEADER_SIZE;
+        loop {
+            let c = data
+                .get(offset..offset + 2)
+                .ok_or(LoadOptionError::Invalid)?;
+            let c = u16::from_le_bytes([c[0], c[1]]);
+            description.push(c);
+            offset += 2;
+            if c == 0 {
+                break;
+            }
+        }
+        let description = CString16::try_from(description).map_err(|_| LoadOptionError::Invalid)?;
+
+        let file_path_list = data
+            .get(offset..offset + file_path_list_length)
+            .ok_or(LoadOptionError::Invalid)?;
+        if !is_valid_path(file_path_list) {
+            return Err(LoadOptionError::Invalid);
+        }
+
+        Ok(Self {
+            attributes,
+            description,
+            file_path_list: file_path_list.to_vec(),
+            optional_data: data[offset + file_path_list_length..].to_vec(),
+        })
+    }
+
+    /// Serialize the option, for example to write it back to its variable.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(self.attributes.to_le_bytes());
+        // The length was checked when the path was set.
+        data.extend((self.file_path_list.len() as u16).to_le_bytes());
+        for c in self.description.to_u16_slice_with_nul() {
+            data.extend(c.to_le_bytes());
+        }
+        data.extend(&self.file_path_list);
+        data.extend(&self.optional_data);
+        data
+    }
+
+    /// The device path of the image to load. Additional instances of the
+    /// path may be used by the firmware or the image itself.
+    pub fn file_path(&self) -> &DevicePath {
+        // Safety: the path was validated when it was set.
+        unsafe { DevicePath::from_ffi_ptr(self.file_path_list.as_ptr().cast()) }
+    }
+
+    /// The GUID of the GPT partition the image is loaded from, if the first
+    /// instance of the file path contains a hard drive node for a GPT
+    /// partition.
+    pub fn partition_guid(&self) -> Option<Guid> {
+        self.partition_node().map(|(guid, _)| guid)
+    }
+
+    /// Find the first hard drive node with a GUID signature in the first
+    /// instance of the file path, and return that GUID along with the
+    /// offset of the rest of the path following the node.
+    fn partition_node(&self) -> Option<(Guid, usize)> {
+        let instance = self.file_path().instance_iter().next()?;
+        let mut offset = 0;
+        for node in instance.node_iter() {
+            offset += usize::from(node.length());
+            if let Some(guid) = gpt_partition_guid(node) {
+                return Some((guid, offset));
+            }
+        }
+        None
+    }
+
+    /// Re-root the file path onto `partition_path`, the device path of the
+    /// partition the image should now be loaded from.
+    ///
+    /// The nodes of the file path up to and including the hard drive node
+    /// of the partition are replaced by `partition_path`, and the nodes
+    /// following it, such as the path of the image file within the
+    /// partition, are kept as they are. The last node of `partition_path`
+    /// must be a hard drive node with the same partition GUID.
+    ///
+    /// # Errors
+    ///
+    /// * [`LoadOptionError::NoPartition`]: the file path doesn't contain a
+    ///   GPT partition.
+    /// * [`LoadOptionError::PartitionMismatch`]: `partition_path` doesn't
+    ///   end with a hard drive node of the same partition.
+    /// * [`LoadOptionError::PathTooLong`]: the new file path is longer than
+    ///   a load option can describe.
+    pub fn rebase(&self, partition_path: &DevicePath) -> Result<Self, LoadOptionError> {
+        let (guid, suffix_offset) = self.partition_node().ok_or(LoadOptionError::NoPartition)?;
+        let new_guid = partition_path
+            .node_iter()
+            .last()
+            .and_then(gpt_partition_guid);
+        if new_guid != Some(guid) {
+            return Err(LoadOptionError::PartitionMismatch);
+        }
+
+        // Keep the prefix without its end node, and the rest of the old
+        // path including its end node.
+        let prefix_len = partition_path.len_bytes() - mem::size_of::<DevicePathHeader>();
+        let prefix =
+            unsafe { slice::from_raw_parts(partition_path.as_ffi_ptr().cast::<u8>(), prefix_len) };
+        let suffix = &self.file_path_list[suffix_offset..];
+
+        let length = prefix.len() + suffix.len();
+        if length > usize::from(u16::MAX) {
+            return Err(LoadOptionError::PathTooLong { length });
+        }
+        let mut file_path_list = Vec::with_capacity(length);
+        file_path_list.extend(prefix);
+        file_path_list.extend(suffix);
+        debug_assert!(is_valid_path(&file_path_list));
+
+        Ok(Self {
+            attributes: self.attributes,
+            description: self.description.clone(),
+            file_path_list,
+            optional_data: self.optional_data.clone(),
+        })
+    }
+
+    /// Re-root the file path onto the partition of `disk` whose GUID, as
+    /// reported by the [`PartitionInfo`] protocol, matches the partition
+    /// of the file path. See [`rebase`] for how the path is rebuilt.
+    ///
+    /// This repairs boot entries after cloning an EFI system partition to
+    /// another disk, keeping its partition GUID: `disk` is the device path
+    /// of the new disk, and is used to tell the new partition apart from
+    /// the old one if both disks are present.
+    ///
+    /// # Errors
+    ///
+    /// * [`LoadOptionError::NoPartition`]: the file path doesn't contain a
+    ///   GPT partition.
+    /// * [`LoadOptionError::Service`] with [`Status::NOT_FOUND`]: `disk`
+    ///   doesn't have a partition with the same GUID.
+    /// * [`LoadOptionError::PathTooLong`]: the new file path is longer than
+    ///   a load option can describe.
+    ///
+    /// [`rebase`]: Self::rebase
+    pub fn relocate(&self, bt: &BootServices, disk: &DevicePath) -> Result<Self, LoadOptionError> {
+        let guid = self.partition_guid().ok_or(LoadOptionError::NoPartition)?;
+        let handles = bt
+            .find_handles::<PartitionInfo>()
+            .map_err(|err| LoadOptionError::Service(err.status()))?;
+
+        // Safety: the protocols are only used during this function and are
+        // opened without affecting drivers that use them.
+        let params = |handle: Handle| OpenProtocolParams {
+            handle,
+            agent: bt.image_handle(),
+            controller: None,
+        };
+        for handle in handles {
+            let matches = unsafe {
+                bt.open_protocol::<PartitionInfo>(
+                    params(handle),
+                    OpenProtocolAttributes::GetProtocol,
+                )
+            }
+            .ok()
+            .and_then(|info| {
+                info.gpt_partition_entry()
+                    .map(|entry| entry.unique_partition_guid)
+            }) == Some(guid);
+            if !matches {
+                continue;
+            }
+
+            if let Ok(path) = unsafe {
+                bt.open_protocol::<DevicePath>(params(handle), OpenProtocolAttributes::GetProtocol)
+            } {
+                if path.starts_with(disk) {
+                    return self.rebase(&path);
+                }
+            }
+        }
+        Err(LoadOptionError::Service(Status::NOT_FOUND))
+    }
+}
+
+/// The GUID of `node`, if it is a hard drive node for a GPT partition.
+///
+/// Load options come from variables that anything can write, so a hard
+/// drive node of the wrong length is ignored rather than converted, which
+/// would panic.
+fn gpt_partition_guid(node: &DevicePathNode) -> Option<Guid> {
+    if usize::from(node.length()) != mem::size_of::<HardDriveMediaDevicePath>() {
+        return None;
+    }
+    match node
+        .as_hard_drive_media_device_path()?
+        .partition_signature()?
+    {
+        PartitionSignature::GUID(guid) => Some(guid),
+        PartitionSignature::MBR(_) => None,
+    }
+}
+
+/// Check that `path` is made of complete device path nodes, and ends with
+/// an end-entire node.
+pub(crate) fn is_valid_path(path: &[u8]) -> bool {
+    let header_size = mem::size_of::<DevicePathHeader>();
+    let mut rest = path;
+    while rest.len() >= header_size {
+        let length = usize::from(u16::from_le_bytes([rest[2], rest[3]]));
+        if length < header_size || length > rest.len() {
+            return false;
+        }
+        // End-entire node (type 0x7f, sub-type 0xff).
+        if rest[0] == 0x7f && rest[1] == 0xff {
+            return length == rest.len();
+        }
+        rest = &rest[length..];
+    }
+    false
+}
+
+/// Error returned when parsing or rebuilding a [`LoadOption`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadOptionError {
+    /// The data is not a valid load option.
+    Invalid,
+    /// The file path doesn't contain a hard drive node for a GPT partition.
+    NoPartition,
+    /// The new partition path doesn't end with a hard drive node for the
+    /// partition of the file path.
+    PartitionMismatch,
+    /// The new file path is longer than the 65535 bytes that a load option
+    /// can describe.
+    PathTooLong {
+        /// The length the new file path would have.
+        length: usize,
+    },
+    /// A boot service failed.
+    Service(Status),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_api::vec;
+
+    fn add_node(path: &mut Vec<u8>, device_type: u8, sub_type: u8, node_data: &[u8]) {
+        path.push(device_type);
+        path.push(sub_type);
+        path.extend(u16::try_from(4 + node_data.len()).unwrap().to_le_bytes());
+        path.extend(node_data);
+    }
+
+    /// Add a hard drive node for the GPT partition with the given GUID.
+    fn add_hard_drive_node(path: &mut Vec<u8>, guid: [u8; 16]) {
+        let mut data = Vec::new();
+        data.extend(1u32.to_le_bytes());
+        data.extend(0x800u64.to_le_bytes());
+        data.extend(0x1000u64.to_le_bytes());
+        data.extend(guid);
+        // GPT format, GUID signature.
+        data.extend([0x02, 0x02]);
+        add_node(path, 0x04, 0x01, &data);
+    }
+
+    fn add_end_node(path: &mut Vec<u8>) {
+        add_node(path, 0x7f, 0xff, &[]);
+    }
+
+    /// Create the path of a partition on a disk attached to the given PCI
+    /// device.
+    fn partition_path(pci_device: u8, guid: [u8; 16]) -> Vec<u8> {
+        let mut path = Vec::new();
+        add_node(&mut path, 0x02, 0x01, &[0; 8]);
+        add_node(&mut path, 0x01, 0x01, &[0, pci_device]);
+        add_hard_drive_node(&mut path, guid);
+        path
+    }
+
+    /// Create a load option loading `\EFI\A` from the partition.
+    fn create_load_option(guid: [u8; 16]) -> Vec<u8> {
+        let mut path = partition_path(1, guid);
+        add_node(
+            &mut path,
+            0x04,
+            0x04,
+            &[b'\\', 0, b'E', 0, b'F', 0, b'I', 0],
+        );
+        add_node(&mut path, 0x04, 0x04, &[b'\\', 0, b'A', 0, 0, 0]);
+        add_end_node(&mut path);
+
+        let mut data = Vec::new();
+        data.extend(1u32.to_le_bytes());
+        data.extend(u16::try_from(path.len()).unwrap().to_le_bytes());
+        data.extend([b'O', 0, b'S', 0, 0, 0]);
+        data.extend(path);
+        data.extend([1, 2, 3]);
+        data
+    }
+
+    #[test]
+    fn test_load_option_parse() {
+        let data = create_load_option([0xaa; 16]);
+        let option = LoadOption::parse(&data).unwrap();
+        assert_eq!(option.attributes, 1);
+        assert_eq!(option.description, CString16::try_from("OS").unwrap());
+        assert_eq!(option.file_path().node_iter().count(), 5);
+        assert_eq!(option.optional_data, [1, 2, 3]);
+        assert_eq!(
+            option.partition_guid(),
+            // A GUID made of identical bytes has no byte order issues.
+            Some(Guid::from_values(
+                0xaaaaaaaa,
+                0xaaaa,
+                0xaaaa,
+                0xaaaa,
+                0xaaaaaaaaaaaa
+            ))
+        );
+        assert_eq!(option.to_bytes(), data);
+
+        // Truncated description.
+        assert_eq!(LoadOption::parse(&data[..9]), Err(LoadOptionError::Invalid));
+        // A hard drive node that is too short is not a partition.
+        let mut path = Vec::new();
+        add_node(&mut path, 0x04, 0x01, &[0; 20]);
+        add_end_node(&mut path);
+        let mut short_node = Vec::new();
+        short_node.extend(1u32.to_le_bytes());
+        short_node.extend(u16::try_from(path.len()).unwrap().to_le_bytes());
+        short_node.extend([0, 0]);
+        short_node.extend(path);
+        let option = LoadOption::parse(&short_node).unwrap();
+        assert_eq!(option.partition_guid(), None);
+
+        // File path list that doesn't end with an end node.
+        let mut bad_length = data;
+        bad_length[4] -= 4;
+        assert_eq!(
+            LoadOption::parse(&bad_length),
+            Err(LoadOptionError::Invalid)
+        );
+    }
+
+    #[test]
+    fn test_load_option_rebase() {
+        let data = create_load_option([0xaa; 16]);
+        let option = LoadOption::parse(&data).unwrap();
+        let old_path = option.file_path();
+        let old_suffix_len = old_path.len_bytes() - partition_path(1, [0xaa; 16]).len();
+
+        let mut new_partition = partition_path(2, [0xaa; 16]);
+        add_end_node(&mut new_partition);
+        let new_partition_path = unsafe { DevicePath::from_ffi_ptr(new_partition.as_ptr().cast()) };
+        let rebased = option.rebase(new_partition_path).unwrap();
+
+        // The partition is replaced, and the file path is kept.
+        let new_path = rebased.file_path();
+        assert!(new_path.starts_with(new_partition_path));
+        let new_bytes = &rebased.file_path_list;
+        assert_eq!(
+            &new_bytes[new_bytes.len() - old_suffix_len..],
+            &option.file_path_list[option.file_path_list.len() - old_suffix_len..]
+        );
+        assert_eq!(rebased.description, option.description);
+        assert_eq!(rebased.optional_data, option.optional_data);
+        assert_eq!(LoadOption::parse(&rebased.to_bytes()).unwrap(), rebased);
+
+        // A different partition.
+        let mut other_partition = partition_path(2, [0xbb; 16]);
+        add_end_node(&mut other_partition);
+        let other_partition_path =
+            unsafe { DevicePath::from_ffi_ptr(other_partition.as_ptr().cast()) };
+        assert_eq!(
+            option.rebase(other_partition_path),
+            Err(LoadOptionError::PartitionMismatch)
+        );
+
+        // A partition path that's too long to fit in a load option.
+        let mut long_partition = Vec::new();
+        for _ in 0..3 {
+            add_node(&mut long_partition, 0x01, 0x80, &vec![0; 0x7ff0]);
+        }
+        add_hard_drive_node(&mut long_partition, [0xaa; 16]);
+        add_end_node(&mut long_partition);
+        let long_partition_path =
+            unsafe { DevicePath::from_ffi_ptr(long_partition.as_ptr().cast()) };
+        assert!(matches!(
+            option.rebase(long_partition_path),
+            Err(LoadOptionError::PathTooLong { .. })
+        ));
+    }
+}
@@ -7,8 +7,11 @@ pub trait Table {
     const SIGNATURE: u64;
 }
 
+mod crc;
+pub use self::crc::{Crc32Validated, CrcError};
+
 mod header;
-pub use self::header::Header;
+pub use self::header::{Header, HeaderError};
 
 mod revision;
 pub use self::revision::Revision;
@@ -19,4 +22,11 @@ pub use self::system::{Boot, Runtime, SystemTable};
 pub mod boot;
 pub mod runtime;
 
+#[cfg(feature = "exts")]
+pub mod load_option;
+
+#[cfg(feature = "exts")]
+pub mod console_devices;
+
 pub mod cfg;
+pub mod smbios;
@@ -69,4 +69,17 @@ mod tests {
 
         assert!(Revision::EFI_1_10 < Revision::EFI_2_00);
     }
+
+    #[test]
+    fn test_revision_order() {
+        // The major revision takes precedence over the minor revision.
+        assert!(Revision::new(1, 99) < Revision::EFI_2_00);
+        assert!(Revision::new(3, 0) > Revision::EFI_2_90);
+        assert!(Revision::EFI_2_30 < Revision::EFI_2_31);
+        assert!(Revision::EFI_2_70 >= Revision::EFI_2_70);
+        assert_eq!(
+            Revision::EFI_2_70.max(Revision::EFI_2_30),
+            Revision::EFI_2_70
+        );
+    }
 }
@@ -1,19 +1,23 @@
 //! UEFI services available at runtime, even after the OS boots.
 
+#[cfg(feature = "exts")]
+use super::console_devices::{ConsoleDevices, ConsoleVariable};
 use super::{Header, Revision};
 #[cfg(feature = "exts")]
 use crate::data_types::FromSliceWithNulError;
 use crate::result::Error;
 use crate::table::boot::MemoryDescriptor;
+#[cfg(feature = "exts")]
+use crate::table::boot::MemoryType;
 use crate::{CStr16, Char16, Guid, Result, Status};
 #[cfg(feature = "exts")]
 use alloc_api::{vec, vec::Vec};
 use bitflags::bitflags;
+use core::ffi::c_void;
 use core::fmt::{Debug, Formatter};
-#[cfg(feature = "exts")]
-use core::mem;
-use core::mem::MaybeUninit;
+use core::mem::{self, MaybeUninit};
 use core::{fmt, ptr};
+use uefi_macros::cstr16;
 /// Contains pointers to all of the runtime services.
 ///
 /// This table, and the function pointers it contains are valid
@@ -38,7 +42,10 @@ pub struct RuntimeServices {
         desc_version: u32,
         virtual_map: *mut MemoryDescriptor,
     ) -> Status,
-    _pad2: usize,
+    convert_pointer: unsafe extern "efiapi" fn(
+        debug_disposition: DebugDisposition,
+        address: *mut *const c_void,
+    ) -> Status,
     get_variable: unsafe extern "efiapi" fn(
         variable_name: *const Char16,
         vendor_guid: *const Guid,
@@ -245,6 +252,48 @@ impl RuntimeServices {
         }
     }
 
+    /// Set the value of a variable whose writes must be authenticated with
+    /// a time-based signature, such as the Secure Boot `db` and `KEK`
+    /// variables.
+    ///
+    /// `attributes` must contain
+    /// [`VariableAttributes::TIME_BASED_AUTHENTICATED_WRITE_ACCESS`].
+    /// `signed_payload` must start with an `EFI_VARIABLE_AUTHENTICATION_2`
+    /// structure: the timestamp of the update, followed by a
+    /// `WIN_CERTIFICATE_UEFI_GUID` containing a PKCS#7 signature, followed
+    /// by the new value of the variable. The payload is signed by the
+    /// caller and passed unmodified to the firmware, after checking that
+    /// its structure is valid.
+    ///
+    /// Unless [`VariableAttributes::APPEND_WRITE`] is set, the timestamp
+    /// must be later than the timestamp of the previous update of the
+    /// variable, or the firmware rejects the update to protect against
+    /// replay attacks.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `attributes` doesn't contain
+    ///   `TIME_BASED_AUTHENTICATED_WRITE_ACCESS`, or `signed_payload`
+    ///   doesn't start with a valid `EFI_VARIABLE_AUTHENTICATION_2`.
+    /// * [`Status::SECURITY_VIOLATION`]: the signature couldn't be
+    ///   verified, or the timestamp is not later than the previous one.
+    ///
+    /// Errors from [`set_variable`](Self::set_variable) are also returned.
+    pub fn set_variable_authenticated(
+        &self,
+        name: &CStr16,
+        vendor: &VariableVendor,
+        attributes: VariableAttributes,
+        signed_payload: &[u8],
+    ) -> Result {
+        if !attributes.contains(VariableAttributes::TIME_BASED_AUTHENTICATED_WRITE_ACCESS)
+            || !is_valid_authentication_2(signed_payload)
+        {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        self.set_variable(name, vendor, attributes, signed_payload)
+    }
+
     /// Get information about UEFI variable storage space for the type
     /// of variable specified in `attributes`.
     ///
@@ -272,6 +321,328 @@ impl RuntimeServices {
         }
     }
 
+    /// Get the OS indications supported by the firmware, as reported by the
+    /// `OsIndicationsSupported` variable.
+    ///
+    /// If the firmware doesn't set the variable, no indications are
+    /// supported.
+    pub fn supported_os_indications(&self) -> Result<OsIndications> {
+        self.read_os_indications(cstr16!("OsIndicationsSupported"))
+            .map(OsIndications::from_bits_truncate)
+    }
+
+    /// Ask the firmware to stop at its setup UI on the next boot, by setting
+    /// [`OsIndications::BOOT_TO_FW_UI`] in the `OsIndications` variable. The
+    /// other bits of the variable are preserved.
+    ///
+    /// The request takes effect when the computer is next [reset].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the firmware doesn't support booting to its
+    ///   UI.
+    ///
+    /// [reset]: Self::reset
+    pub fn request_boot_to_firmware_ui(&self) -> Result {
+        self.request_os_indication(OsIndications::BOOT_TO_FW_UI)
+    }
+
+    /// Ask the firmware to process the capsules stored on the EFI system
+    /// partition, in the `\EFI\UpdateCapsule` directory, on the next boot.
+    /// This sets [`OsIndications::FILE_CAPSULE_DELIVERY_SUPPORTED`] in the
+    /// `OsIndications` variable, preserving the other bits of the variable.
+    ///
+    /// The request takes effect when the computer is next [reset].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the firmware doesn't support capsule
+    ///   delivery on disk.
+    ///
+    /// [reset]: Self::reset
+    pub fn request_capsule_reset(&self) -> Result {
+        self.request_os_indication(OsIndications::FILE_CAPSULE_DELIVERY_SUPPORTED)
+    }
+
+    /// Set `indication` in the `OsIndications` variable, if supported.
+    fn request_os_indication(&self, indication: OsIndications) -> Result {
+        if !self.supported_os_indications()?.contains(indication) {
+            return Err(Status::UNSUPPORTED.into());
+        }
+
+        let name = cstr16!("OsIndications");
+        // Work on the raw value, so that unknown and reserved bits are kept.
+        let indications = self.read_os_indications(name)? | indication.bits();
+        self.set_variable(
+            name,
+            &VariableVendor::GLOBAL_VARIABLE,
+            VariableAttributes::NON_VOLATILE
+                | VariableAttributes::BOOTSERVICE_ACCESS
+                | VariableAttributes::RUNTIME_ACCESS,
+            &indications.to_le_bytes(),
+        )
+    }
+
+    /// Read a global variable holding OS indications. A missing variable is
+    /// treated as having no bits set.
+    fn read_os_indications(&self, name: &CStr16) -> Result<u64> {
+        let mut buf = [0; mem::size_of::<u64>()];
+        match self.get_variable(name, &VariableVendor::GLOBAL_VARIABLE, &mut buf) {
+            Ok((data, _)) => {
+                let mut bytes = [0; mem::size_of::<u64>()];
+                bytes[..data.len()].copy_from_slice(data);
+                Ok(u64::from_le_bytes(bytes))
+            }
+            Err(err) if err.status() == Status::NOT_FOUND => Ok(0),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Get the number of the `Boot####` entry used for the current boot,
+    /// from the `BootCurrent` variable. Returns `None` if the firmware
+    /// doesn't set the variable, for example when the image was started from
+    /// the UEFI shell rather than the boot manager.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::COMPROMISED_DATA`]: the variable is not 2 bytes long.
+    pub fn boot_current(&self) -> Result<Option<u16>> {
+        self.read_boot_number(cstr16!("BootCurrent"))
+    }
+
+    /// Get the number of the `Boot####` entry to use for the next boot
+    /// only, from the `BootNext` variable. Returns `None` if no such entry is
+    /// set.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::COMPROMISED_DATA`]: the variable is not 2 bytes long.
+    pub fn boot_next(&self) -> Result<Option<u16>> {
+        self.read_boot_number(cstr16!("BootNext"))
+    }
+
+    /// Set the `Boot####` entry to use for the next boot only, in the
+    /// `BootNext` variable, or delete the variable if `number` is `None`.
+    ///
+    /// The firmware deletes the variable when it uses it, so later boots
+    /// follow `BootOrder` again.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: `number` is `None` and the variable doesn't
+    ///   exist.
+    pub fn set_boot_next(&self, number: Option<u16>) -> Result {
+        let bytes = number.map(u16::to_le_bytes);
+        let data: &[u8] = match &bytes {
+            Some(bytes) => bytes,
+            // Writing no data deletes the variable.
+            None => &[],
+        };
+        self.set_variable(
+            cstr16!("BootNext"),
+            &VariableVendor::GLOBAL_VARIABLE,
+            VariableAttributes::NON_VOLATILE
+                | VariableAttributes::BOOTSERVICE_ACCESS
+                | VariableAttributes::RUNTIME_ACCESS,
+            data,
+        )
+    }
+
+    /// Read a global variable holding the number of a `Boot####` entry. A
+    /// missing variable is returned as `None`.
+    fn read_boot_number(&self, name: &CStr16) -> Result<Option<u16>> {
+        let mut buf = [0; mem::size_of::<u16>()];
+        match self.get_variable(name, &VariableVendor::GLOBAL_VARIABLE, &mut buf) {
+            Ok((data, _)) => <[u8; 2]>::try_from(data)
+                .map(|bytes| Some(u16::from_le_bytes(bytes)))
+                .map_err(|_| Status::COMPROMISED_DATA.into()),
+            Err(err) if err.status() == Status::NOT_FOUND => Ok(None),
+            // The variable is larger than 2 bytes.
+            Err(err) if err.status() == Status::BUFFER_TOO_SMALL => {
+                Err(Status::COMPROMISED_DATA.into())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Get the Secure Boot mode of the platform, from the `SetupMode`,
+    /// `AuditMode` and `DeployedMode` variables.
+    ///
+    /// Firmware older than UEFI 2.5 doesn't set `AuditMode` and
+    /// `DeployedMode`, and is then only in setup or user mode. Whether
+    /// signatures are being checked is reported by [`secure_boot_enabled`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the firmware doesn't support Secure Boot,
+    ///   and doesn't set `SetupMode`.
+    /// * [`Status::COMPROMISED_DATA`]: a variable is not a single byte, or
+    ///   the variables don't describe a valid mode.
+    ///
+    /// [`secure_boot_enabled`]: Self::secure_boot_enabled
+    pub fn secure_boot_mode(&self) -> Result<SecureBootMode> {
+        let setup = self
+            .read_bool_variable(cstr16!("SetupMode"))?
+            .ok_or(Status::UNSUPPORTED)?;
+        let audit = self
+            .read_bool_variable(cstr16!("AuditMode"))?
+            .unwrap_or(false);
+        let deployed = self
+            .read_bool_variable(cstr16!("DeployedMode"))?
+            .unwrap_or(false);
+        SecureBootMode::from_variables(setup, audit, deployed)
+            .ok_or_else(|| Status::COMPROMISED_DATA.into())
+    }
+
+    /// Whether Secure Boot is enabled, and the firmware only starts images
+    /// with valid signatures, from the `SecureBoot` variable. A missing
+    /// variable means that the firmware doesn't support Secure Boot.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::COMPROMISED_DATA`]: the variable is not a single byte.
+    pub fn secure_boot_enabled(&self) -> Result<bool> {
+        Ok(self
+            .read_bool_variable(cstr16!("SecureBoot"))?
+            .unwrap_or(false))
+    }
+
+    /// Read a global variable holding a single byte that is 0 or 1. A
+    /// missing variable is returned as `None`.
+    fn read_bool_variable(&self, name: &CStr16) -> Result<Option<bool>> {
+        let mut buf = [0; 1];
+        match self.get_variable(name, &VariableVendor::GLOBAL_VARIABLE, &mut buf) {
+            Ok((&[value], _)) if value <= 1 => Ok(Some(value == 1)),
+            Ok(_) => Err(Status::COMPROMISED_DATA.into()),
+            Err(err) if err.status() == Status::NOT_FOUND => Ok(None),
+            Err(err) if err.status() == Status::BUFFER_TOO_SMALL => {
+                Err(Status::COMPROMISED_DATA.into())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Get the firmware's hints on how many pages of each memory type to
+    /// reserve, from the `MemoryTypeInformation` variable.
+    ///
+    /// Firmware based on EDK II records the memory usage of each boot in
+    /// this variable, and reserves a matching region for each type on the
+    /// next boot, which keeps the memory map stable across boots. Each
+    /// entry of the list is a memory type and its number of pages.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the firmware doesn't set the variable.
+    /// * [`Status::COMPROMISED_DATA`]: the size of the variable is not a
+    ///   whole number of entries.
+    #[cfg(feature = "exts")]
+    pub fn memory_type_information(&self) -> Result<Vec<(MemoryType, u32)>> {
+        let name = cstr16!("MemoryTypeInformation");
+        let vendor = VariableVendor::MEMORY_TYPE_INFORMATION;
+        let mut buf = vec![0; self.get_variable_size(name, &vendor)?];
+        let (data, _) = self.get_variable(name, &vendor, &mut buf)?;
+        parse_memory_type_information(data).ok_or_else(|| Status::COMPROMISED_DATA.into())
+    }
+
+    /// Read the devices listed in a console variable. A missing variable is
+    /// treated as listing no devices.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::COMPROMISED_DATA`]: the variable isn't a valid device
+    ///   path.
+    #[cfg(feature = "exts")]
+    pub fn console_devices(&self, variable: ConsoleVariable) -> Result<ConsoleDevices> {
+        let name = variable.name();
+        let vendor = VariableVendor::GLOBAL_VARIABLE;
+        let size = match self.get_variable_size(name, &vendor) {
+            Ok(size) => size,
+            Err(err) if err.status() == Status::NOT_FOUND => return Ok(ConsoleDevices::new()),
+            Err(err) => return Err(err),
+        };
+        let mut buf = vec![0; size];
+        let (data, _) = self.get_variable(name, &vendor, &mut buf)?;
+        ConsoleDevices::parse(data).ok_or_else(|| Status::COMPROMISED_DATA.into())
+    }
+
+    /// Write the devices listed in a console variable, replacing its
+    /// previous contents. Writing an empty list deletes the variable.
+    ///
+    /// The firmware uses the new devices from the next boot on.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::WRITE_PROTECTED`]: the variable is one of the read-only
+    ///   `*Dev` variables, see [`ConsoleVariable::is_read_only`].
+    /// * [`Status::NOT_FOUND`]: the list is empty and the variable doesn't
+    ///   exist.
+    #[cfg(feature = "exts")]
+    pub fn set_console_devices(
+        &self,
+        variable: ConsoleVariable,
+        devices: &ConsoleDevices,
+    ) -> Result {
+        if variable.is_read_only() {
+            return Err(Status::WRITE_PROTECTED.into());
+        }
+        self.set_variable(
+            variable.name(),
+            &VariableVendor::GLOBAL_VARIABLE,
+            VariableAttributes::NON_VOLATILE
+                | VariableAttributes::BOOTSERVICE_ACCESS
+                | VariableAttributes::RUNTIME_ACCESS,
+            &devices.to_bytes(),
+        )
+    }
+
+    /// Convert `ptr` from a physical address to the virtual address it is
+    /// mapped to by [`SystemTable::set_virtual_address_map`].
+    ///
+    /// A null pointer is only accepted if `debug_disposition` contains
+    /// [`DebugDisposition::OPTIONAL_PTR`], and is left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: `ptr` is not part of the memory map passed
+    ///   to `set_virtual_address_map`.
+    /// * [`Status::INVALID_PARAMETER`]: `ptr` is null and `debug_disposition`
+    ///   doesn't contain [`DebugDisposition::OPTIONAL_PTR`].
+    ///
+    /// # Safety
+    ///
+    /// This is only valid while `set_virtual_address_map` is running, in the
+    /// notification function of an event created with
+    /// [`EventType::SIGNAL_VIRTUAL_ADDRESS_CHANGE`]. The converted pointer
+    /// must not be used until the new mapping is in effect.
+    ///
+    /// [`SystemTable::set_virtual_address_map`]: super::SystemTable::set_virtual_address_map
+    /// [`EventType::SIGNAL_VIRTUAL_ADDRESS_CHANGE`]: super::boot::EventType::SIGNAL_VIRTUAL_ADDRESS_CHANGE
+    pub unsafe fn convert_pointer(
+        &self,
+        debug_disposition: DebugDisposition,
+        ptr: &mut *const c_void,
+    ) -> Result {
+        (self.convert_pointer)(debug_disposition, ptr).into()
+    }
+
+    /// Convert a reference to its virtual address, as with
+    /// [`convert_pointer`]. References are never null, so
+    /// [`DebugDisposition::OPTIONAL_PTR`] is not used.
+    ///
+    /// # Safety
+    ///
+    /// The same as [`convert_pointer`]. In addition, `reference` must not be
+    /// dereferenced until the new mapping is in effect, as it no longer
+    /// points to valid memory before that.
+    ///
+    /// [`convert_pointer`]: Self::convert_pointer
+    pub unsafe fn convert_ref<T>(&self, reference: &mut &'static T) -> Result {
+        let mut ptr = (*reference as *const T).cast::<c_void>();
+        self.convert_pointer(DebugDisposition::empty(), &mut ptr)?;
+        *reference = &*ptr.cast::<T>();
+        Ok(())
+    }
+
     /// Resets the computer.
     pub fn reset(&self, rt: ResetType, status: Status, data: Option<&[u8]>) -> ! {
         let (size, data) = match data {
@@ -308,6 +679,74 @@ impl Debug for RuntimeServices {
     }
 }
 
+/// Parse the entries of the `MemoryTypeInformation` variable, each made of
+/// a 32-bit memory type and a 32-bit number of pages.
+///
+/// EDK II ends the list with an entry for `EfiMaxMemoryType`, the first
+/// type that isn't defined by the version of the specification it
+/// implements. That entry is dropped. Returns `None` if `data` is not made
+/// of whole entries.
+#[cfg(feature = "exts")]
+fn parse_memory_type_information(data: &[u8]) -> Option<Vec<(MemoryType, u32)>> {
+    const ENTRY_SIZE: usize = 2 * mem::size_of::<u32>();
+    if data.len() % ENTRY_SIZE != 0 {
+        return None;
+    }
+
+    let mut entries: Vec<_> = data
+        .chunks_exact(ENTRY_SIZE)
+        .map(|entry| {
+            let ty = u32::from_le_bytes(entry[0..4].try_into().unwrap());
+            let pages = u32::from_le_bytes(entry[4..8].try_into().unwrap());
+            (MemoryType(ty), pages)
+        })
+        .collect();
+    // Types from 0x7000_0000 on are reserved for OEMs and OS loaders.
+    if let Some(&(ty, 0)) = entries.last() {
+        if ty.0 > MemoryType::PERSISTENT_MEMORY.0 && ty.0 < 0x7000_0000 {
+            entries.pop();
+        }
+    }
+    Some(entries)
+}
+
+/// `WIN_CERT_TYPE_EFI_GUID`, the certificate type of a
+/// `WIN_CERTIFICATE_UEFI_GUID`.
+const WIN_CERT_TYPE_EFI_GUID: u16 = 0x0ef1;
+
+/// The supported revision of `WIN_CERTIFICATE`.
+const WIN_CERT_REVISION: u16 = 0x0200;
+
+/// `EFI_CERT_TYPE_PKCS7_GUID`, the certificate type of the signature in an
+/// `EFI_VARIABLE_AUTHENTICATION_2`.
+const EFI_CERT_TYPE_PKCS7_GUID: Guid =
+    Guid::from_values(0x4aafd29d, 0x68df, 0x49ee, 0x8aa9, 0x347d375665a7);
+
+/// Size of an `EFI_VARIABLE_AUTHENTICATION_2` without the signature: the
+/// timestamp, the `WIN_CERTIFICATE` header and the certificate type GUID.
+const AUTHENTICATION_2_HEADER_SIZE: usize = mem::size_of::<Time>() + 8 + 16;
+
+/// Checks that `payload` starts with an `EFI_VARIABLE_AUTHENTICATION_2`
+/// containing a PKCS#7 signature that fits within the payload.
+fn is_valid_authentication_2(payload: &[u8]) -> bool {
+    if payload.len() < AUTHENTICATION_2_HEADER_SIZE {
+        return false;
+    }
+
+    let cert = &payload[mem::size_of::<Time>()..];
+    let length = u32::from_le_bytes(cert[0..4].try_into().unwrap()) as usize;
+    let revision = u16::from_le_bytes(cert[4..6].try_into().unwrap());
+    let cert_type = u16::from_le_bytes(cert[6..8].try_into().unwrap());
+    let cert_guid = unsafe { ptr::read_unaligned(cert[8..24].as_ptr().cast::<Guid>()) };
+
+    // The length covers the `WIN_CERTIFICATE_UEFI_GUID`, including the
+    // signature, but not the timestamp.
+    (24..=cert.len()).contains(&length)
+        && revision == WIN_CERT_REVISION
+        && cert_type == WIN_CERT_TYPE_EFI_GUID
+        && cert_guid == EFI_CERT_TYPE_PKCS7_GUID
+}
+
 /// Date and time representation.
 #[derive(Copy, Clone)]
 #[repr(C)]
@@ -600,6 +1039,50 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Flags for [`RuntimeServices::convert_pointer`].
+    #[repr(transparent)]
+    pub struct DebugDisposition: usize {
+        /// The pointer may be null, in which case it is not converted.
+        const OPTIONAL_PTR = 0x01;
+    }
+}
+
+bitflags! {
+    /// Indications from the OS to the firmware, stored in the `OsIndications`
+    /// variable. The indications supported by the firmware are listed in the
+    /// `OsIndicationsSupported` variable.
+    ///
+    /// See [`RuntimeServices::supported_os_indications`].
+    pub struct OsIndications: u64 {
+        /// Stop at the firmware setup UI on the next boot.
+        const BOOT_TO_FW_UI = 0x01;
+
+        /// The firmware supports timestamp based revocation of signatures.
+        const TIMESTAMP_REVOCATION = 0x02;
+
+        /// Process the capsules stored on the EFI system partition on the
+        /// next boot.
+        const FILE_CAPSULE_DELIVERY_SUPPORTED = 0x04;
+
+        /// The firmware supports Firmware Management Protocol capsules.
+        const FMP_CAPSULE_SUPPORTED = 0x08;
+
+        /// The firmware reports the results of processing capsules in
+        /// `CapsuleNNNN` variables.
+        const CAPSULE_RESULT_VAR_SUPPORTED = 0x10;
+
+        /// Start OS-defined recovery on the next boot.
+        const START_OS_RECOVERY = 0x20;
+
+        /// Start platform-defined recovery on the next boot.
+        const START_PLATFORM_RECOVERY = 0x40;
+
+        /// Refresh the JSON configuration data on the next boot.
+        const JSON_CONFIG_DATA_REFRESH = 0x80;
+    }
+}
+
 newtype_enum! {
     /// Variable vendor GUID. This serves as a namespace for variables to
     /// avoid naming conflicts between vendors. The UEFI specification
@@ -622,6 +1105,57 @@ newtype_enum! {
             0xa3bc,
             0xdad00e67656f,
         ),
+
+        /// Used to access the results of processed capsules, such as
+        /// `CapsuleMax` and `CapsuleLast`.
+        CAPSULE_REPORT = Guid::from_values(
+            0x39b68c46,
+            0xf7fb,
+            0x441b,
+            0xb6ec,
+            0x16b0f69821f3,
+        ),
+
+        /// Used by EDK II to store the `MemoryTypeInformation` variable.
+        ///
+        /// See [`RuntimeServices::memory_type_information`]. This is also the
+        /// GUID of the configuration table holding the same data.
+        MEMORY_TYPE_INFORMATION = crate::table::cfg::MEMORY_TYPE_INFORMATION_GUID,
+
+        /// Used to access variables of the UEFI Shell, such as its
+        /// environment variables.
+        SHELL = Guid::from_values(
+            0x158def5a,
+            0xf656,
+            0x419c,
+            0xb027,
+            0x7a3192c079d2,
+        ),
+    }
+}
+
+impl VariableVendor {
+    /// Create a vendor from its GUID.
+    pub const fn from_guid(guid: Guid) -> Self {
+        Self(guid)
+    }
+
+    /// The name of the vendor, if it is one of the vendors defined above.
+    pub fn name(&self) -> Option<&'static str> {
+        Self::VARIANTS
+            .iter()
+            .find(|(vendor, _)| vendor == self)
+            .map(|(_, name)| *name)
+    }
+}
+
+/// Known vendors are displayed by name, and other vendors by their GUID.
+impl fmt::Display for VariableVendor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{}", self.0),
+        }
     }
 }
 
@@ -654,13 +1188,7 @@ impl fmt::Display for VariableKey {
 
         write!(f, ", vendor: ")?;
 
-        if self.vendor == VariableVendor::GLOBAL_VARIABLE {
-            write!(f, "GLOBAL_VARIABLE")?;
-        } else {
-            write!(f, "{}", self.vendor.0)?;
-        }
-
-        write!(f, " }}")
+        write!(f, "{} }}", self.vendor)
     }
 }
 
@@ -682,6 +1210,40 @@ pub struct VariableStorageInfo {
     pub maximum_variable_size: u64,
 }
 
+/// The Secure Boot mode of the platform, as returned by
+/// [`RuntimeServices::secure_boot_mode`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SecureBootMode {
+    /// No platform key is enrolled, and the Secure Boot variables can be
+    /// written without authentication.
+    Setup,
+    /// A platform key is enrolled, and images are checked against the
+    /// signature databases if Secure Boot is enabled.
+    User,
+    /// No platform key is enrolled, and images are checked, but started even
+    /// if the check fails. The results are recorded in the image execution
+    /// information table.
+    Audit,
+    /// A platform key is enrolled, and the platform can't return to setup
+    /// or audit mode without a platform-specific action.
+    Deployed,
+}
+
+impl SecureBootMode {
+    /// Get the mode from the values of the `SetupMode`, `AuditMode` and
+    /// `DeployedMode` variables, or `None` if they are not a valid
+    /// combination.
+    fn from_variables(setup: bool, audit: bool, deployed: bool) -> Option<Self> {
+        match (setup, audit, deployed) {
+            (true, false, false) => Some(Self::Setup),
+            (false, false, false) => Some(Self::User),
+            (true, true, false) => Some(Self::Audit),
+            (false, false, true) => Some(Self::Deployed),
+            _ => None,
+        }
+    }
+}
+
 /// The type of system reset.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[repr(u32)]
@@ -703,3 +1265,86 @@ pub enum ResetType {
     //         the firmware. Therefore, unexpected values can never come from
     //         the firmware, and modeling this as a Rust enum seems safe.
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secure_boot_mode() {
+        use SecureBootMode::*;
+        assert_eq!(
+            SecureBootMode::from_variables(true, false, false),
+            Some(Setup)
+        );
+        assert_eq!(
+            SecureBootMode::from_variables(false, false, false),
+            Some(User)
+        );
+        assert_eq!(
+            SecureBootMode::from_variables(true, true, false),
+            Some(Audit)
+        );
+        assert_eq!(
+            SecureBootMode::from_variables(false, false, true),
+            Some(Deployed)
+        );
+        assert_eq!(SecureBootMode::from_variables(false, true, false), None);
+        assert_eq!(SecureBootMode::from_variables(true, false, true), None);
+        assert_eq!(SecureBootMode::from_variables(true, true, true), None);
+    }
+
+    #[test]
+    fn test_authentication_2() {
+        let mut payload = [0u8; AUTHENTICATION_2_HEADER_SIZE + 8];
+        let cert = &mut payload[mem::size_of::<Time>()..];
+        cert[0..4].copy_from_slice(&32u32.to_le_bytes());
+        cert[4..6].copy_from_slice(&WIN_CERT_REVISION.to_le_bytes());
+        cert[6..8].copy_from_slice(&WIN_CERT_TYPE_EFI_GUID.to_le_bytes());
+        unsafe {
+            ptr::write_unaligned(cert[8..24].as_mut_ptr().cast(), EFI_CERT_TYPE_PKCS7_GUID);
+        }
+        assert!(is_valid_authentication_2(&payload));
+
+        // The value of the variable may follow the signature.
+        let mut longer = [0u8; AUTHENTICATION_2_HEADER_SIZE + 16];
+        longer[..payload.len()].copy_from_slice(&payload);
+        assert!(is_valid_authentication_2(&longer));
+
+        // The signature extends beyond the end of the payload.
+        assert!(!is_valid_authentication_2(&payload[..payload.len() - 1]));
+
+        let mut bad = payload;
+        bad[mem::size_of::<Time>() + 6] = 0;
+        assert!(!is_valid_authentication_2(&bad));
+
+        let mut bad = payload;
+        bad[mem::size_of::<Time>() + 8] ^= 1;
+        assert!(!is_valid_authentication_2(&bad));
+    }
+
+    #[test]
+    #[cfg(feature = "exts")]
+    fn test_memory_type_information() {
+        let mut data = Vec::new();
+        for (ty, pages) in [(MemoryType::ACPI_RECLAIM, 0x40), (MemoryType(15), 0)] {
+            data.extend(ty.0.to_le_bytes());
+            data.extend(u32::to_le_bytes(pages));
+        }
+        assert_eq!(
+            parse_memory_type_information(&data).unwrap(),
+            [(MemoryType::ACPI_RECLAIM, 0x40)]
+        );
+
+        // Entries without pages are only dropped at the end of the list
+        // for undefined types.
+        let data = [4, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(
+            parse_memory_type_information(&data).unwrap(),
+            [(MemoryType::BOOT_SERVICES_DATA, 0)]
+        );
+
+        assert!(parse_memory_type_information(&data[..6]).is_none());
+        assert!(parse_memory_type_information(&[]).unwrap().is_empty());
+    }
+}
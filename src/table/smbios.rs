@@ -0,0 +1,370 @@
+//! Parsing of the SMBIOS tables.
+//!
+//! The SMBIOS tables describe the hardware of the machine, such as its
+//! processors and memory modules. The firmware points to them from the
+//! configuration table, with the [`SMBIOS3_GUID`] or [`SMBIOS_GUID`]
+//! entries. [`SmbiosTable::find`] locates them, and iterates over their
+//! structures.
+//!
+//! For example, the installed memory can be read from the memory device
+//! structures:
+//!
+//! ```no_run
+//! use uefi::table::smbios::SmbiosTable;
+//! use uefi::table::{Boot, SystemTable};
+//!
+//! fn installed_memory(st: &SystemTable<Boot>) -> Option<u64> {
+//!     let table = unsafe { SmbiosTable::find(st.config_table()) }?;
+//!     Some(table.total_physical_memory())
+//! }
+//! ```
+//!
+//! This is the memory installed in the machine, which is larger than the
+//! memory available in the UEFI memory map.
+//!
+//! [`SMBIOS3_GUID`]: super::cfg::SMBIOS3_GUID
+//! [`SMBIOS_GUID`]: super::cfg::SMBIOS_GUID
+
+use super::cfg::{ConfigTableEntry, SMBIOS3_GUID, SMBIOS_GUID};
+use crate::Guid;
+use core::slice;
+
+/// Size of the header of each structure: its type, length and handle.
+const HEADER_SIZE: usize = 4;
+
+/// Type of the memory device structures.
+const MEMORY_DEVICE_TYPE: u8 = 17;
+
+/// Type of the structure marking the end of the table.
+const END_OF_TABLE_TYPE: u8 = 127;
+
+/// The structure table of the SMBIOS tables.
+#[derive(Clone, Copy, Debug)]
+pub struct SmbiosTable<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> SmbiosTable<'a> {
+    /// Create a table from the bytes of its structures.
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Find the structure table from the SMBIOS entry point in the
+    /// configuration table. The SMBIOS 3 entry point is used if both
+    /// versions are present, as only it can describe tables above 4 GiB.
+    ///
+    /// Returns `None` if there is no entry point, or if it doesn't have a
+    /// valid anchor string.
+    ///
+    /// # Safety
+    ///
+    /// The entry point and the structure table must be valid, which is the
+    /// case before boot services are exited.
+    pub unsafe fn find(config_table: &[ConfigTableEntry]) -> Option<SmbiosTable<'static>> {
+        let entry = |guid: Guid| {
+            config_table
+                .iter()
+                .find(|entry| entry.guid == guid)
+                .map(|entry| entry.address.cast::<u8>())
+        };
+
+        if let Some(entry) = entry(SMBIOS3_GUID) {
+            // `_SM3_` anchor, then the maximum size of the structure table
+            // at offset 0x0c, and its address at offset 0x10.
+            if slice::from_raw_parts(entry, 5) == b"_SM3_" {
+                let size = entry.add(0x0c).cast::<u32>().read_unaligned();
+                let address = entry.add(0x10).cast::<u64>().read_unaligned();
+                let data = slice::from_raw_parts(address as *const u8, size as usize);
+                return Some(SmbiosTable::new(data));
+            }
+        }
+        if let Some(entry) = entry(SMBIOS_GUID) {
+            // `_SM_` anchor, then the size of the structure table at offset
+            // 0x16, and its address at offset 0x18.
+            if slice::from_raw_parts(entry, 4) == b"_SM_" {
+                let size = entry.add(0x16).cast::<u16>().read_unaligned();
+                let address = entry.add(0x18).cast::<u32>().read_unaligned();
+                let data = slice::from_raw_parts(address as *const u8, usize::from(size));
+                return Some(SmbiosTable::new(data));
+            }
+        }
+        None
+    }
+
+    /// Iterate over the structures of the table, up to the end-of-table
+    /// structure.
+    pub fn structures(&self) -> StructureIter<'a> {
+        StructureIter { rest: self.data }
+    }
+
+    /// Iterate over the memory device (type 17) structures, one per memory
+    /// slot, including empty ones.
+    pub fn memory_devices(&self) -> impl Iterator<Item = MemoryDevice<'a>> {
+        self.structures().filter_map(MemoryDevice::parse)
+    }
+
+    /// The total size in bytes of the installed memory devices. Devices of
+    /// unknown size are not counted.
+    pub fn total_physical_memory(&self) -> u64 {
+        self.memory_devices().filter_map(|device| device.size).sum()
+    }
+}
+
+/// A structure of the SMBIOS table.
+#[derive(Clone, Copy, Debug)]
+pub struct Structure<'a> {
+    formatted: &'a [u8],
+    strings: &'a [u8],
+}
+
+impl<'a> Structure<'a> {
+    /// The type of the structure.
+    pub fn structure_type(&self) -> u8 {
+        self.formatted[0]
+    }
+
+    /// The handle of the structure, used by other structures to refer to
+    /// it.
+    pub fn handle(&self) -> u16 {
+        u16::from_le_bytes([self.formatted[2], self.formatted[3]])
+    }
+
+    /// The formatted area of the structure, including its header.
+    pub fn formatted(&self) -> &'a [u8] {
+        self.formatted
+    }
+
+    /// Get the string with the given number, as stored in a string field of
+    /// the formatted area. Strings are numbered from 1, and 0 means that the
+    /// field has no string.
+    pub fn string(&self, number: u8) -> Option<&'a [u8]> {
+        let index = usize::from(number.checked_sub(1)?);
+        self.strings
+            .split(|&b| b == 0)
+            .take_while(|string| !string.is_empty())
+            .nth(index)
+    }
+
+    /// Read a byte of the formatted area, or `None` if the structure is too
+    /// short to have it.
+    fn byte(&self, offset: usize) -> Option<u8> {
+        self.formatted.get(offset).copied()
+    }
+
+    /// Read a 16-bit field of the formatted area.
+    fn word(&self, offset: usize) -> Option<u16> {
+        let bytes = self.formatted.get(offset..offset + 2)?;
+        Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Read a 32-bit field of the formatted area.
+    fn dword(&self, offset: usize) -> Option<u32> {
+        let bytes = self.formatted.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// Read the string referred to by the string field at `offset`, if it
+    /// is valid UTF-8.
+    fn string_field(&self, offset: usize) -> Option<&'a str> {
+        core::str::from_utf8(self.string(self.byte(offset)?)?).ok()
+    }
+}
+
+/// Iterator over the structures of an [`SmbiosTable`].
+///
+/// This struct is returned by [`SmbiosTable::structures`]. Iteration stops
+/// at the end-of-table structure, or at the first truncated structure.
+#[derive(Clone, Debug)]
+pub struct StructureIter<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Iterator for StructureIter<'a> {
+    type Item = Structure<'a>;
+
+    fn next(&mut self) -> Option<Structure<'a>> {
+        let length = usize::from(*self.rest.get(1)?);
+        if length < HEADER_SIZE || self.rest.len() < length {
+            self.rest = &[];
+            return None;
+        }
+        let (formatted, rest) = self.rest.split_at(length);
+
+        // The string set ends with two null bytes, which are also present
+        // if there are no strings.
+        let strings_len = match rest.windows(2).position(|pair| pair == [0, 0]) {
+            Some(position) => position + 2,
+            None => {
+                self.rest = &[];
+                return None;
+            }
+        };
+        let (strings, rest) = rest.split_at(strings_len);
+
+        self.rest = if formatted[0] == END_OF_TABLE_TYPE {
+            &[]
+        } else {
+            rest
+        };
+        Some(Structure { formatted, strings })
+    }
+}
+
+/// A memory device (type 17) structure, describing a memory slot and the
+/// module installed in it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryDevice<'a> {
+    /// Handle of the structure.
+    pub handle: u16,
+    /// Size of the module in bytes, 0 for an empty slot, or `None` if
+    /// unknown.
+    pub size: Option<u64>,
+    /// Maximum speed of the module in megatransfers per second, or `None`
+    /// if unknown.
+    pub speed: Option<u32>,
+    /// Label of the slot, for example `DIMM 0`.
+    pub device_locator: Option<&'a str>,
+    /// Label of the bank of the slot.
+    pub bank_locator: Option<&'a str>,
+    /// Manufacturer of the module.
+    pub manufacturer: Option<&'a str>,
+    /// Serial number of the module.
+    pub serial_number: Option<&'a str>,
+    /// Part number of the module. Firmware often pads it with spaces.
+    pub part_number: Option<&'a str>,
+}
+
+impl<'a> MemoryDevice<'a> {
+    /// Parse a memory device structure, or return `None` if `structure` is
+    /// of another type.
+    pub fn parse(structure: Structure<'a>) -> Option<Self> {
+        if structure.structure_type() != MEMORY_DEVICE_TYPE {
+            return None;
+        }
+
+        let size = match structure.word(0x0c) {
+            None | Some(0xffff) => None,
+            // The size doesn't fit in the field, and is stored in MiB in
+            // the extended size field instead.
+            Some(0x7fff) => structure
+                .dword(0x1c)
+                .map(|size| u64::from(size & 0x7fff_ffff) << 20),
+            // Bit 15 selects KiB rather than MiB units.
+            Some(size) if size & 0x8000 != 0 => Some(u64::from(size & 0x7fff) << 10),
+            Some(size) => Some(u64::from(size) << 20),
+        };
+        let speed = match structure.word(0x15) {
+            None | Some(0) => None,
+            Some(0xffff) => structure.dword(0x54).filter(|&speed| speed != 0),
+            Some(speed) => Some(u32::from(speed)),
+        };
+
+        Some(Self {
+            handle: structure.handle(),
+            size,
+            speed,
+            device_locator: structure.string_field(0x10),
+            bank_locator: structure.string_field(0x11),
+            manufacturer: structure.string_field(0x17),
+            serial_number: structure.string_field(0x18),
+            part_number: structure.string_field(0x1a),
+        })
+    }
+
+    /// Whether a module is installed in the slot.
+    pub fn is_installed(&self) -> bool {
+        self.size != Some(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_api::vec::Vec;
+
+    /// Append a memory device structure with the given size fields.
+    fn add_memory_device(
+        table: &mut Vec<u8>,
+        handle: u16,
+        size: u16,
+        extended_size: u32,
+        strings: &[u8],
+    ) {
+        let mut formatted = [0; 0x22];
+        formatted[0] = MEMORY_DEVICE_TYPE;
+        formatted[1] = formatted.len() as u8;
+        formatted[2..4].copy_from_slice(&handle.to_le_bytes());
+        formatted[0x0c..0x0e].copy_from_slice(&size.to_le_bytes());
+        formatted[0x10] = 1;
+        formatted[0x15..0x17].copy_from_slice(&3200u16.to_le_bytes());
+        formatted[0x17] = 2;
+        formatted[0x1a] = 3;
+        formatted[0x1c..0x20].copy_from_slice(&extended_size.to_le_bytes());
+        table.extend(formatted);
+        table.extend(strings);
+    }
+
+    fn end_of_table(table: &mut Vec<u8>) {
+        table.extend([END_OF_TABLE_TYPE, 4, 0xff, 0xff, 0, 0]);
+    }
+
+    #[test]
+    fn test_memory_devices() {
+        let mut table = Vec::new();
+        // A BIOS information structure without strings.
+        table.extend([0, 4, 0, 0, 0, 0]);
+        add_memory_device(&mut table, 1, 8192, 0, b"DIMM 0\0Acme\0PN-1  \0\0");
+        // 64 GiB, stored in the extended size field.
+        add_memory_device(&mut table, 2, 0x7fff, 65536, b"DIMM 1\0\0");
+        // 512 KiB.
+        add_memory_device(&mut table, 3, 0x8200, 0, b"\0\0");
+        // Empty slot.
+        add_memory_device(&mut table, 4, 0, 0, b"DIMM 3\0\0");
+        end_of_table(&mut table);
+        // Structures after the end of the table are ignored.
+        add_memory_device(&mut table, 5, 1024, 0, b"\0\0");
+
+        let table = SmbiosTable::new(&table);
+        assert_eq!(table.structures().count(), 6);
+
+        let devices: Vec<_> = table.memory_devices().collect();
+        assert_eq!(devices.len(), 4);
+        assert_eq!(
+            devices[0],
+            MemoryDevice {
+                handle: 1,
+                size: Some(8 << 30),
+                speed: Some(3200),
+                device_locator: Some("DIMM 0"),
+                bank_locator: None,
+                manufacturer: Some("Acme"),
+                serial_number: None,
+                part_number: Some("PN-1  "),
+            }
+        );
+        assert_eq!(devices[1].size, Some(64 << 30));
+        assert_eq!(devices[1].manufacturer, None);
+        assert_eq!(devices[2].size, Some(512 << 10));
+        assert_eq!(devices[2].device_locator, None);
+        assert!(!devices[3].is_installed());
+
+        assert_eq!(
+            table.total_physical_memory(),
+            (8 << 30) + (64 << 30) + (512 << 10)
+        );
+    }
+
+    #[test]
+    fn test_truncated_table() {
+        let mut table = Vec::new();
+        add_memory_device(&mut table, 1, 1024, 0, b"DIMM 0\0\0");
+        // Missing the second null byte ending the strings.
+        table.pop();
+        assert_eq!(SmbiosTable::new(&table).structures().count(), 0);
+
+        // Too short for its length.
+        assert_eq!(SmbiosTable::new(&[17, 0x22, 0, 0]).structures().count(), 0);
+        assert_eq!(SmbiosTable::new(&[]).structures().count(), 0);
+    }
+}
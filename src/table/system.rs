@@ -2,14 +2,14 @@ use core::ffi::c_void;
 use core::fmt::{Debug, Formatter};
 use core::marker::PhantomData;
 use core::ptr::NonNull;
-use core::{ptr, slice};
+use core::{mem, ptr, slice};
 
 use crate::proto::console::text;
-use crate::{CStr16, Char16, Handle, Result, ResultExt, Status};
+use crate::{CStr16, Char16, Error, Handle, Result, ResultExt, Status};
 
-use super::boot::{BootServices, MemoryDescriptor};
+use super::boot::{BootServices, MemoryDescriptor, MemoryMapKey};
 use super::runtime::RuntimeServices;
-use super::{cfg, Header, Revision};
+use super::{cfg, Header, HeaderError, Revision, Table};
 
 /// Marker trait used to provide different views of the UEFI System Table
 pub trait SystemTableView {}
@@ -58,16 +58,37 @@ impl<View: SystemTableView> SystemTable<View> {
     }
 
     /// Return the firmware revision
+    ///
+    /// The meaning of the revision is defined by the firmware vendor, so
+    /// the [`Revision::major`] and [`Revision::minor`] parts don't
+    /// necessarily correspond to a UEFI specification version.
     pub fn firmware_revision(&self) -> Revision {
         self.table.fw_revision
     }
 
+    /// Returns the header of the system table, with its signature, revision,
+    /// size and CRC.
+    pub fn header(&self) -> &Header {
+        &self.table.header
+    }
+
     /// Returns the revision of this table, which is defined to be
     /// the revision of the UEFI specification implemented by the firmware.
     pub fn uefi_revision(&self) -> Revision {
         self.table.header.revision
     }
 
+    /// Returns whether the firmware implements at least revision `min` of
+    /// the UEFI specification.
+    ///
+    /// This can be used to check for functions that were added by a later
+    /// revision before calling them, for example
+    /// `st.supports(Revision::EFI_2_00)` before using the functions of
+    /// [`BootServices`] added by UEFI 2.0.
+    pub fn supports(&self, min: Revision) -> bool {
+        self.uefi_revision() >= min
+    }
+
     /// Returns the config table entries, a linear array of structures
     /// pointing to other system-specific tables.
     pub fn config_table(&self) -> &[cfg::ConfigTableEntry] {
@@ -129,6 +150,23 @@ impl SystemTable<Boot> {
         unsafe { &*self.table.boot }
     }
 
+    /// Checks that the system table header is valid: the signature must
+    /// identify the system table, the size must be large enough for all the
+    /// fields of the table, and the CRC must match the contents, computed
+    /// with the `CalculateCrc32` boot service.
+    ///
+    /// A failed check means that the table has been corrupted, or modified
+    /// without updating the CRC.
+    pub fn validate_header(&self) -> core::result::Result<(), HeaderError> {
+        unsafe {
+            self.table.header.validate(
+                <Self as Table>::SIGNATURE,
+                mem::size_of::<SystemTableImpl>(),
+                self.boot_services(),
+            )
+        }
+    }
+
     /// Exit the UEFI boot services
     ///
     /// After this function completes, UEFI hands over control of the hardware
@@ -211,6 +249,39 @@ impl SystemTable<Boot> {
         }
     }
 
+    /// Exit the UEFI boot services, using the key of a memory map that the
+    /// caller already retrieved with [`BootServices::memory_map`], or with
+    /// `BootServices::memory_map_owned` if the `exts` feature is enabled.
+    ///
+    /// This is a lower-level alternative to [`exit_boot_services`], for
+    /// callers that want to get the memory map separately from exiting.
+    /// Everything that is explained there about the logger, the allocator and
+    /// `image` also applies here.
+    ///
+    /// The key must be from the current memory map: the map must be read
+    /// right before calling this function, and nothing may allocate or free
+    /// memory in between, including the logger and the global allocator.
+    /// Otherwise, the firmware rejects the key with
+    /// [`Status::INVALID_PARAMETER`] and the system table is returned as the
+    /// error data. In that case, only the memory map functions of the boot
+    /// services may be used: get a new memory map without allocating, and
+    /// try again.
+    ///
+    /// [`exit_boot_services`]: Self::exit_boot_services
+    /// [`BootServices::memory_map`]: super::boot::BootServices::memory_map
+    pub fn exit_boot_services_raw(
+        self,
+        image: Handle,
+        mmap_key: MemoryMapKey,
+    ) -> Result<SystemTable<Runtime>, SystemTable<Boot>> {
+        unsafe { self.boot_services().exit_boot_services(image, mmap_key) }
+            .map(|_| SystemTable {
+                table: self.table,
+                _marker: PhantomData,
+            })
+            .map_err(|err| Error::new(err.status(), self))
+    }
+
     /// Clone this boot-time UEFI system table interface
     ///
     /// # Safety
@@ -314,7 +385,7 @@ struct SystemTableImpl {
     cfg_table: *const cfg::ConfigTableEntry,
 }
 
-impl<View: SystemTableView> super::Table for SystemTable<View> {
+impl<View: SystemTableView> Table for SystemTable<View> {
     const SIGNATURE: u64 = 0x5453_5953_2049_4249;
 }
 
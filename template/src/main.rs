@@ -6,7 +6,7 @@ use uefi::prelude::*;
 
 #[entry]
 fn main(_handle: Handle, mut system_table: SystemTable<Boot>) -> Status {
-    uefi_services::init(&mut system_table).unwrap();
+    uefi_services::init(&mut system_table, Default::default()).unwrap();
 
     Status::SUCCESS
 }
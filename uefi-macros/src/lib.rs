@@ -297,6 +297,7 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
 /// ```
 /// # use uefi_macros::cstr8;
 /// assert_eq!(cstr8!("test").to_bytes_with_nul(), [116, 101, 115, 116, 0]);
+/// assert_eq!(cstr8!("").to_bytes_with_nul(), [0]);
 /// ```
 #[proc_macro]
 pub fn cstr8(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -308,7 +309,7 @@ pub fn cstr8(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         .collect::<Result<Vec<u8>, _>>()
     {
         Ok(c) => {
-            quote!(unsafe { ::uefi::CStr8::from_bytes_with_nul_unchecked(&[ #(#c),* , 0 ]) }).into()
+            quote!(unsafe { ::uefi::CStr8::from_bytes_with_nul_unchecked(&[ #(#c,)* 0 ]) }).into()
         }
         Err(_) => syn::Error::new_spanned(input, "invalid character in string")
             .into_compile_error()
@@ -324,6 +325,7 @@ pub fn cstr8(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 /// ```
 /// # use uefi_macros::cstr16;
 /// assert_eq!(cstr16!("test €").to_u16_slice_with_nul(), [116, 101, 115, 116, 32, 8364, 0]);
+/// assert_eq!(cstr16!("").to_u16_slice_with_nul(), [0]);
 /// ```
 #[proc_macro]
 pub fn cstr16(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -335,7 +337,7 @@ pub fn cstr16(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         .collect::<Result<Vec<u16>, _>>()
     {
         Ok(c) => {
-            quote!(unsafe { ::uefi::CStr16::from_u16_with_nul_unchecked(&[ #(#c),* , 0 ]) }).into()
+            quote!(unsafe { ::uefi::CStr16::from_u16_with_nul_unchecked(&[ #(#c,)* 0 ]) }).into()
         }
         Err(_) => syn::Error::new_spanned(input, "invalid character in string")
             .into_compile_error()
@@ -0,0 +1,176 @@
+//! Busy-wait delays that keep working after boot services are exited.
+//!
+//! [`BootServices::stall`] can only be used while boot services are active.
+//! This module provides a spin loop whose speed is calibrated once during
+//! [`init`], either against the [`Timestamp`] protocol or, if that isn't
+//! available, against a firmware timer event. The calibration is stored in a
+//! global, so [`delay_us`] and [`delay_ns`] can be called at any time
+//! afterwards, including after `exit_boot_services`.
+//!
+//! A failed calibration doesn't make [`init`] fail: it is logged, and the
+//! delay functions panic instead.
+//!
+//! # Accuracy
+//!
+//! The delays are approximate and should only be used where waiting a bit
+//! longer than requested is acceptable, such as polling hardware:
+//!
+//! - The loop is not interrupt-aware: interrupts are neither disabled nor
+//!   accounted for, so any time spent in interrupt handlers (or in another
+//!   process, under virtualization) extends the delay.
+//! - Changes of the CPU frequency after calibration shift the timing.
+//! - The timer-event fallback is limited by the resolution of the firmware
+//!   timer.
+//!
+//! [`BootServices::stall`]: uefi::table::boot::BootServices::stall
+//! [`init`]: crate::init
+
+use core::hint;
+
+use uefi::prelude::*;
+use uefi::proto::misc::Timestamp;
+use uefi::table::boot::{EventType, TimerTrigger, Tpl};
+use uefi::Result;
+
+/// Calibration stored by `init`.
+static mut DELAY: Option<Delay> = None;
+
+/// Number of loop iterations used to calibrate against the timestamp counter.
+const TIMESTAMP_CALIBRATION_LOOPS: u64 = 1_000_000;
+
+/// Length of the timer used by the fallback calibration, in 100ns units.
+const TIMER_CALIBRATION_PERIOD: u64 = 10 * 10_000;
+
+/// Number of loop iterations run between two checks of the calibration timer.
+const TIMER_CALIBRATION_BATCH: u64 = 1_000;
+
+/// A calibrated busy-wait delay.
+#[derive(Clone, Copy, Debug)]
+pub struct Delay {
+    loops_per_ms: u64,
+}
+
+impl Delay {
+    /// Measure the speed of the spin loop.
+    ///
+    /// This uses the [`Timestamp`] protocol if available, and a firmware
+    /// timer event otherwise. It takes a few milliseconds.
+    pub fn calibrate(bt: &BootServices) -> Result<Self> {
+        let loops_per_ms = match Self::calibrate_with_timestamp(bt) {
+            Ok(loops_per_ms) => loops_per_ms,
+            Err(_) => Self::calibrate_with_timer(bt)?,
+        };
+        Ok(Self {
+            loops_per_ms: loops_per_ms.max(1),
+        })
+    }
+
+    fn calibrate_with_timestamp(bt: &BootServices) -> Result<u64> {
+        // Safety: the protocol is only used during this function.
+        let timestamp = unsafe { bt.get_first_protocol::<Timestamp>()? };
+        let properties = timestamp.get_properties()?;
+        if properties.frequency == 0 {
+            return Err(Status::UNSUPPORTED.into());
+        }
+
+        let start = timestamp.get_timestamp();
+        spin(TIMESTAMP_CALIBRATION_LOOPS);
+        let end = timestamp.get_timestamp();
+
+        let ticks = if end >= start {
+            end - start
+        } else {
+            // The counter wrapped around. This wraps in turn if `end_value`
+            // is `u64::MAX`, which gives the right result.
+            (properties.end_value - start)
+                .wrapping_add(end)
+                .wrapping_add(1)
+        };
+        let elapsed_us = u128::from(ticks) * 1_000_000 / u128::from(properties.frequency);
+        if elapsed_us == 0 {
+            return Err(Status::UNSUPPORTED.into());
+        }
+
+        let loops_per_ms = u128::from(TIMESTAMP_CALIBRATION_LOOPS) * 1_000 / elapsed_us;
+        Ok(loops_per_ms.try_into().unwrap_or(u64::MAX))
+    }
+
+    fn calibrate_with_timer(bt: &BootServices) -> Result<u64> {
+        let timer = unsafe { bt.create_event(EventType::TIMER, Tpl::APPLICATION, None, None)? };
+        let result = bt
+            .set_timer(&timer, TimerTrigger::Relative(TIMER_CALIBRATION_PERIOD))
+            .and_then(|_| {
+                let mut loops = 0;
+                while !bt.check_event(&timer)? {
+                    spin(TIMER_CALIBRATION_BATCH);
+                    loops += TIMER_CALIBRATION_BATCH;
+                }
+                // The timer period is in 100ns units.
+                Ok(loops * 10_000 / TIMER_CALIBRATION_PERIOD)
+            });
+        bt.close_event(timer)?;
+        result
+    }
+
+    /// Busy-wait for at least `us` microseconds (see the [module-level
+    /// documentation] for accuracy limits).
+    ///
+    /// [module-level documentation]: crate::delay
+    pub fn delay_us(&self, us: u64) {
+        spin(self.loops_per_ms.saturating_mul(us) / 1_000);
+    }
+
+    /// Busy-wait for at least `ns` nanoseconds (see the [module-level
+    /// documentation] for accuracy limits).
+    ///
+    /// [module-level documentation]: crate::delay
+    pub fn delay_ns(&self, ns: u64) {
+        spin(self.loops_per_ms.saturating_mul(ns) / 1_000_000);
+    }
+}
+
+/// The spin loop whose speed is calibrated.
+#[inline(never)]
+fn spin(loops: u64) {
+    for _ in 0..loops {
+        hint::spin_loop();
+    }
+}
+
+/// Calibrate the global delay. Called by `init`, which doesn't fail if the
+/// calibration does.
+pub(crate) fn init(bt: &BootServices) {
+    match Delay::calibrate(bt) {
+        Ok(delay) => unsafe { DELAY = Some(delay) },
+        Err(err) => log::warn!("Failed to calibrate the delay: {:?}", err.status()),
+    }
+}
+
+/// Returns the delay calibrated by `init`.
+///
+/// # Panics
+///
+/// Panics if `init` has not been called, or if the calibration failed.
+pub fn delay() -> Delay {
+    unsafe { DELAY.expect("The delay has not been calibrated") }
+}
+
+/// Busy-wait for at least `us` microseconds, using the calibration done
+/// during `init`. This can also be used after boot services are exited.
+///
+/// # Panics
+///
+/// Panics if the delay has not been calibrated (see [`delay`]).
+pub fn delay_us(us: u64) {
+    delay().delay_us(us);
+}
+
+/// Busy-wait for at least `ns` nanoseconds, using the calibration done
+/// during `init`. This can also be used after boot services are exited.
+///
+/// # Panics
+///
+/// Panics if the delay has not been calibrated (see [`delay`]).
+pub fn delay_ns(ns: u64) {
+    delay().delay_ns(ns);
+}
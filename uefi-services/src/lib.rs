@@ -14,6 +14,17 @@
 //! Library code can simply use global UEFI functions
 //! through the reference provided by `system_table`.
 //!
+//! The [`delay`] module provides busy-wait delays which, unlike
+//! `BootServices::stall`, keep working after boot services are exited.
+//!
+//! Normal output and errors can be sent to a serial device rather than to
+//! the console, with the [`Config`] passed to [`init`] or with [`set_stderr`].
+//!
+//! Log output can also be appended to a file with [`set_log_file`].
+//!
+//! With the `log_ring` feature, log output is also kept in memory, where it
+//! can be read with [`read_log_ring`] even after boot services are exited.
+//!
 //! [`exit_boot_services`]: uefi::table::SystemTable::exit_boot_services
 
 #![no_std]
@@ -36,6 +47,17 @@ use uefi::table::boot::{EventType, Tpl};
 use uefi::table::{Boot, SystemTable};
 use uefi::{Event, Result};
 
+pub mod delay;
+mod log_file;
+#[cfg(feature = "log_ring")]
+mod log_ring;
+mod stderr;
+
+pub use log_file::{close_log_file, set_log_file};
+#[cfg(feature = "log_ring")]
+pub use log_ring::{read_log_ring, LOG_RING_SIZE};
+pub use stderr::{set_stderr, take_stderr, Config, Sink};
+
 /// Reference to the system table.
 ///
 /// This table is only fully safe to use until UEFI boot services have been exited.
@@ -63,11 +85,43 @@ pub fn system_table() -> NonNull<SystemTable<Boot>> {
     }
 }
 
+/// Obtains a reference to the boot services.
+///
+/// This is meant for helper code that would otherwise need a `&BootServices`
+/// parameter threaded through every call just to reach the boot services.
+///
+/// `init` must have been called first by the UEFI app.
+///
+/// Although the returned reference is `'static`, it is only valid until boot
+/// services are exited. Calling this function after that point will panic,
+/// but a reference obtained earlier will not be invalidated by the borrow
+/// checker, so it must not be kept around across a call to
+/// [`exit_boot_services`].
+///
+/// # Panics
+///
+/// Panics if `init` has not been called, or if boot services have been exited.
+///
+/// [`exit_boot_services`]: uefi::table::SystemTable::exit_boot_services
+pub fn boot_services() -> &'static BootServices {
+    unsafe {
+        let table_ref = SYSTEM_TABLE
+            .as_ref()
+            .expect("The system table handle is not available");
+        // The boot services table is owned by the firmware, not by the
+        // `SystemTable` wrapper, so it outlives the borrow of `SYSTEM_TABLE`.
+        &*(table_ref.boot_services() as *const BootServices)
+    }
+}
+
 /// Initialize the UEFI utility library.
 ///
 /// This must be called as early as possible,
 /// before trying to use logging or memory allocation capabilities.
-pub fn init(st: &mut SystemTable<Boot>) -> Result {
+///
+/// `config` selects where normal output and errors go. Use
+/// `Config::default()` to send everything to the console.
+pub fn init(st: &mut SystemTable<Boot>, config: Config) -> Result {
     unsafe {
         // Avoid double initialization.
         if SYSTEM_TABLE.is_some() {
@@ -83,20 +137,31 @@ pub fn init(st: &mut SystemTable<Boot>) -> Result {
         uefi::alloc::init(boot_services);
 
         // Schedule these tools to be disabled on exit from UEFI boot services
-        boot_services
-            .create_event(
-                EventType::SIGNAL_EXIT_BOOT_SERVICES,
-                Tpl::NOTIFY,
-                Some(exit_boot_services),
-                None,
-            )
-            .map(|_| ())
+        boot_services.create_event(
+            EventType::SIGNAL_EXIT_BOOT_SERVICES,
+            Tpl::NOTIFY,
+            Some(exit_boot_services),
+            None,
+        )?;
+
+        // Open the serial devices selected by the configuration
+        stderr::open_sinks(config);
+
+        // Calibrate the busy-wait delay, which stays usable after boot
+        // services are exited. This comes last, so that a failure can't
+        // leave the steps above undone.
+        delay::init(boot_services);
+        Ok(())
     }
 }
 
 // Internal function for print macros.
 #[doc(hidden)]
 pub fn _print(args: core::fmt::Arguments) {
+    if stderr::print(args) {
+        return;
+    }
+
     unsafe {
         let st = SYSTEM_TABLE
             .as_mut()
@@ -154,6 +219,16 @@ unsafe fn init_logger(st: &mut SystemTable<Boot>) {
         LOGGER.as_ref().unwrap()
     };
 
+    // Send errors to the serial device set with `set_stderr`, if any.
+    let logger = stderr::init(logger);
+
+    // Also keep a copy of the log output in memory, if enabled.
+    #[cfg(feature = "log_ring")]
+    let logger = log_ring::init(st.boot_services(), logger);
+
+    // Also append the log output to the file set with `set_log_file`, if any.
+    let logger = log_file::init(logger);
+
     // Set the logger.
     log::set_logger(logger).unwrap(); // Can only fail if already initialized.
 
@@ -174,6 +249,8 @@ unsafe extern "efiapi" fn exit_boot_services(_e: Event, _ctx: Option<NonNull<c_v
     if let Some(ref mut logger) = LOGGER {
         logger.disable();
     }
+    stderr::exit_boot_services();
+    log_file::exit_boot_services();
 
     uefi::alloc::exit_boot_services();
 }
@@ -181,8 +258,21 @@ unsafe extern "efiapi" fn exit_boot_services(_e: Event, _ctx: Option<NonNull<c_v
 #[cfg(feature = "panic_handler")]
 #[panic_handler]
 fn panic_handler(info: &core::panic::PanicInfo) -> ! {
+    // This goes to the device set with `set_stderr`, if any.
     error!("{}", info);
 
+    // If running in QEMU, use the f4 exit port to signal the error and exit
+    // right away, so that CI runs fail fast.
+    #[cfg(all(target_arch = "x86_64", feature = "qemu"))]
+    if running_in_hypervisor() {
+        use qemu_exit::QEMUExit;
+        // Must match the success code of the test runner, so that the
+        // failure code (1) is distinguishable from it.
+        let custom_exit_success = 3;
+        let qemu_exit_handle = qemu_exit::X86::new(0xF4, custom_exit_success);
+        qemu_exit_handle.exit_failure();
+    }
+
     // Give the user some time to read the message
     if let Some(st) = unsafe { SYSTEM_TABLE.as_ref() } {
         st.boot_services().stall(10_000_000);
@@ -196,49 +286,47 @@ fn panic_handler(info: &core::panic::PanicInfo) -> ! {
         }
     }
 
+    // If the system table is available, use UEFI's standard shutdown mechanism
+    if let Some(st) = unsafe { SYSTEM_TABLE.as_ref() } {
+        use uefi::table::runtime::ResetType;
+        st.runtime_services()
+            .reset(ResetType::Shutdown, uefi::Status::ABORTED, None);
+    }
+
+    // If we don't have any shutdown mechanism handy, the best we can do is loop
+    error!("Could not shut down, please power off the system manually...");
+
     cfg_if! {
-        if #[cfg(all(target_arch = "x86_64", feature = "qemu"))] {
-            // If running in QEMU, use the f4 exit port to signal the error and exit
-            use qemu_exit::QEMUExit;
-            let custom_exit_success = 3;
-            let qemu_exit_handle = qemu_exit::X86::new(0xF4, custom_exit_success);
-            qemu_exit_handle.exit_failure();
-        } else {
-            // If the system table is available, use UEFI's standard shutdown mechanism
-            if let Some(st) = unsafe { SYSTEM_TABLE.as_ref() } {
-                use uefi::table::runtime::ResetType;
-                st.runtime_services()
-                    .reset(ResetType::Shutdown, uefi::Status::ABORTED, None);
+        if #[cfg(target_arch = "x86_64")] {
+            loop {
+                unsafe {
+                    // Try to at least keep CPU from running at 100%
+                    core::arch::asm!("hlt", options(nomem, nostack));
+                }
             }
-
-            // If we don't have any shutdown mechanism handy, the best we can do is loop
-            error!("Could not shut down, please power off the system manually...");
-
-            cfg_if! {
-                if #[cfg(target_arch = "x86_64")] {
-                    loop {
-                        unsafe {
-                            // Try to at least keep CPU from running at 100%
-                            core::arch::asm!("hlt", options(nomem, nostack));
-                        }
-                    }
-                } else if #[cfg(target_arch = "aarch64")] {
-                    loop {
-                        unsafe {
-                            // Try to at least keep CPU from running at 100%
-                            core::arch::asm!("hlt 420", options(nomem, nostack));
-                        }
-                    }
-                } else {
-                    loop {
-                        // just run forever dammit how do you return never anyway
-                    }
+        } else if #[cfg(target_arch = "aarch64")] {
+            loop {
+                unsafe {
+                    // Try to at least keep CPU from running at 100%
+                    core::arch::asm!("hlt 420", options(nomem, nostack));
                 }
             }
+        } else {
+            loop {
+                // just run forever dammit how do you return never anyway
+            }
         }
     }
 }
 
+/// Check the CPUID hypervisor bit, so that the QEMU exit port is not written
+/// to on real hardware, where it may belong to another device.
+#[cfg(all(target_arch = "x86_64", feature = "qemu", feature = "panic_handler"))]
+fn running_in_hypervisor() -> bool {
+    let features = unsafe { core::arch::x86_64::__cpuid(1) };
+    features.ecx & (1 << 31) != 0
+}
+
 #[alloc_error_handler]
 fn out_of_memory(layout: ::core::alloc::Layout) -> ! {
     panic!(
@@ -0,0 +1,161 @@
+//! Copy of the log output in a file.
+//!
+//! After [`set_log_file`] has been called, every log record is also appended
+//! to a file, which is flushed after each record so that the output survives
+//! a crash or a reset. This is useful to collect the logs of a machine without
+//! a serial port.
+//!
+//! If writing to the file fails, for example because the volume is full, the
+//! file is closed and logging goes on without it.
+//!
+//! Records logged above [`Tpl::CALLBACK`], such as from event notification
+//! functions, are not written to the file, since the file system drivers
+//! can't be called at such levels. Records logged while a record is being
+//! written, for example by a file system driver, are not written either.
+//!
+//! The file must be closed with [`close_log_file`] before boot services are
+//! exited, as the file system drivers may already be stopped when the exit
+//! notification runs.
+
+use core::fmt;
+use core::mem;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use uefi::proto::media::file::{File, FileAttribute, FileMode, RegularFile};
+use uefi::proto::media::fs::SimpleFileSystem;
+use uefi::table::boot::Tpl;
+use uefi::{CStr16, Result, Status};
+
+/// File receiving the log records, if any.
+static mut LOG_FILE: Option<RegularFile> = None;
+
+/// Set while a record is written to the file, so that records logged in the
+/// meantime don't access it again.
+static WRITING: AtomicBool = AtomicBool::new(false);
+
+/// Append every log record to the file at `path` on `fs`, creating it if
+/// needed. Calling this again replaces the previous file, which is closed.
+///
+/// The file must be closed with [`close_log_file`] before boot services are
+/// exited.
+///
+/// # Errors
+///
+/// If the file can't be opened for writing, for example because the volume
+/// is read-only, the error is returned and the log records are not copied to
+/// a file. See [`File::open`] for the possible errors.
+///
+/// * [`Status::UNSUPPORTED`]: `path` is a directory.
+pub fn set_log_file(fs: &mut SimpleFileSystem, path: &CStr16) -> Result {
+    let mut root = fs.open_volume()?;
+    let mut file = root
+        .open(path, FileMode::CreateReadWrite, FileAttribute::empty())?
+        .into_regular_file()
+        .ok_or(Status::UNSUPPORTED)?;
+    // Append to the previous runs.
+    file.set_position(RegularFile::END_OF_FILE)?;
+    unsafe {
+        LOG_FILE = Some(file);
+    }
+    Ok(())
+}
+
+/// Flush and close the file set with [`set_log_file`], if any. The log
+/// records are no longer copied to a file afterwards.
+///
+/// This must be called before boot services are exited. The file can't be
+/// closed by the exit notification, as the file system drivers may already
+/// be stopped: it is leaked instead. No log output is lost that way, since
+/// each record is flushed after it is written, but the last changes to the
+/// file, such as its modification time, may not be saved.
+pub fn close_log_file() {
+    if let Some(mut file) = unsafe { LOG_FILE.take() } {
+        let _ = file.flush();
+    }
+}
+
+/// Stop using the file, which must not be closed or flushed anymore as boot
+/// services are being exited. Called from the `exit_boot_services`
+/// notification.
+pub(crate) unsafe fn exit_boot_services() {
+    mem::forget(LOG_FILE.take());
+}
+
+/// Adapter writing formatted text to a file, keeping the error.
+struct FileWriter<'a> {
+    file: &'a mut RegularFile,
+    status: Status,
+}
+
+impl fmt::Write for FileWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.file.write(s.as_bytes()).map_err(|err| {
+            self.status = err.status();
+            fmt::Error
+        })
+    }
+}
+
+/// Write a record to the file and flush it.
+fn write_record(file: &mut RegularFile, record: &log::Record) -> Result {
+    let mut writer = FileWriter {
+        file,
+        status: Status::SUCCESS,
+    };
+    if uefi::logger::write_record(&mut writer, record).is_err() {
+        // Formatting itself can't fail, only writing to the file.
+        return Err(writer.status.into());
+    }
+    writer.file.flush()
+}
+
+/// Returns `true` if the file system drivers can be called, that is if boot
+/// services are active and the TPL is not above [`Tpl::CALLBACK`].
+fn can_write_file() -> bool {
+    unsafe { crate::SYSTEM_TABLE.as_ref() }.map_or(false, |st| {
+        st.boot_services().current_tpl().0 <= Tpl::CALLBACK.0
+    })
+}
+
+/// Logger writing to both the console logger and the log file.
+struct FileLogger {
+    console: &'static dyn log::Log,
+}
+
+static mut FILE_LOGGER: Option<FileLogger> = None;
+
+impl log::Log for FileLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.console.enabled(metadata) || unsafe { LOG_FILE.is_some() }
+    }
+
+    fn log(&self, record: &log::Record) {
+        if can_write_file() && !WRITING.swap(true, Ordering::Acquire) {
+            if let Some(file) = unsafe { LOG_FILE.as_mut() } {
+                // A full volume or a device error must not bring down the
+                // application, so the file is dropped instead.
+                if write_record(file, record).is_err() {
+                    unsafe {
+                        LOG_FILE = None;
+                    }
+                }
+            }
+            WRITING.store(false, Ordering::Release);
+        }
+
+        if self.console.enabled(record.metadata()) {
+            self.console.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.console.flush();
+    }
+}
+
+/// Wrap the console logger, returning the logger to install. Called by
+/// `init`.
+pub(crate) unsafe fn init(console: &'static dyn log::Log) -> &'static dyn log::Log {
+    FILE_LOGGER = Some(FileLogger { console });
+    FILE_LOGGER.as_ref().unwrap()
+}
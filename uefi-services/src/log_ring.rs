@@ -0,0 +1,134 @@
+//! In-memory copy of the log output, for post-mortem debugging.
+//!
+//! With the `log_ring` feature, every log record is also written to a ring
+//! buffer allocated as `RUNTIME_SERVICES_DATA`. Such memory is not reclaimed
+//! by the OS, so the buffer survives `exit_boot_services`, and records are
+//! still added to it after the console logger has been disabled. A debugger
+//! or a later boot stage can then recover the last [`LOG_RING_SIZE`] bytes of
+//! log output with [`read_log_ring`].
+
+use core::fmt;
+use core::ptr;
+
+use uefi::table::boot::{BootServices, MemoryType};
+
+/// Size of the log ring buffer in bytes.
+pub const LOG_RING_SIZE: usize = 64 * 1024;
+
+/// Ring buffer holding the most recent log output.
+///
+/// The output is stored from `start`, wrapping around at the end of the
+/// buffer. When the buffer is full, new bytes overwrite the oldest ones.
+struct Ring {
+    buf: *mut u8,
+    start: usize,
+    len: usize,
+}
+
+static mut RING: Ring = Ring {
+    buf: ptr::null_mut(),
+    start: 0,
+    len: 0,
+};
+
+impl Ring {
+    /// Fill `dest` with the bytes starting `offset` bytes after the oldest
+    /// one, wrapping around at the end of the buffer.
+    unsafe fn copy_out(&self, offset: usize, dest: &mut [u8]) {
+        let from = (self.start + offset) % LOG_RING_SIZE;
+        let first = dest.len().min(LOG_RING_SIZE - from);
+        let (head, tail) = dest.split_at_mut(first);
+        ptr::copy_nonoverlapping(self.buf.add(from), head.as_mut_ptr(), head.len());
+        ptr::copy_nonoverlapping(self.buf, tail.as_mut_ptr(), tail.len());
+    }
+}
+
+impl fmt::Write for Ring {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.buf.is_null() {
+            return Ok(());
+        }
+
+        // Only the end of a string longer than the buffer would be kept.
+        let bytes = s.as_bytes();
+        let bytes = &bytes[bytes.len().saturating_sub(LOG_RING_SIZE)..];
+
+        // Write after the newest byte, in up to two parts.
+        let end = (self.start + self.len) % LOG_RING_SIZE;
+        let first = bytes.len().min(LOG_RING_SIZE - end);
+        let (head, tail) = bytes.split_at(first);
+        unsafe {
+            ptr::copy_nonoverlapping(head.as_ptr(), self.buf.add(end), head.len());
+            ptr::copy_nonoverlapping(tail.as_ptr(), self.buf, tail.len());
+        }
+
+        // Drop the oldest bytes that were overwritten.
+        let overflow = (self.len + bytes.len()).saturating_sub(LOG_RING_SIZE);
+        self.start = (self.start + overflow) % LOG_RING_SIZE;
+        self.len += bytes.len() - overflow;
+        Ok(())
+    }
+}
+
+/// Logger writing to both the console logger and the ring buffer.
+struct RingLogger {
+    console: &'static dyn log::Log,
+}
+
+static mut RING_LOGGER: Option<RingLogger> = None;
+
+impl log::Log for RingLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        (unsafe { !RING.buf.is_null() }) || self.console.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        // Errors can't be reported from here, and writing to the ring doesn't
+        // fail anyway.
+        let _ = uefi::logger::write_record(unsafe { &mut RING }, record);
+
+        if self.console.enabled(record.metadata()) {
+            self.console.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.console.flush();
+    }
+}
+
+/// Allocate the ring buffer and wrap the console logger, returning the logger
+/// to install. Called by `init`.
+///
+/// If the buffer can't be allocated, log records only go to the console.
+pub(crate) unsafe fn init(
+    bt: &BootServices,
+    console: &'static dyn log::Log,
+) -> &'static dyn log::Log {
+    if let Ok(buf) = bt.allocate_pool(MemoryType::RUNTIME_SERVICES_DATA, LOG_RING_SIZE) {
+        RING.buf = buf;
+    }
+
+    RING_LOGGER = Some(RingLogger { console });
+    RING_LOGGER.as_ref().unwrap()
+}
+
+/// Copy the most recent log output to `buffer`, oldest first, and return the
+/// number of bytes copied.
+///
+/// At most [`LOG_RING_SIZE`] bytes are kept, and only the most recent ones
+/// are copied if `buffer` is smaller. The oldest record may be cut off, so
+/// the copied bytes don't necessarily start with valid UTF-8.
+///
+/// This can also be called after boot services are exited. Nothing is copied
+/// if `init` has not been called, or if the buffer could not be allocated.
+pub fn read_log_ring(buffer: &mut [u8]) -> usize {
+    unsafe {
+        if RING.buf.is_null() {
+            return 0;
+        }
+        let count = buffer.len().min(RING.len);
+        RING.copy_out(RING.len - count, &mut buffer[..count]);
+        count
+    }
+}
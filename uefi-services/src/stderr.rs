@@ -0,0 +1,170 @@
+//! Separate outputs for normal output and errors.
+//!
+//! By default, all output goes to the console. The [`Config`] passed to
+//! [`init`] can send normal output (`print!`, `println!` and the log levels
+//! other than `error!`) and errors (`error!` records, including the message
+//! of the panic handler) to the first serial device instead, like stdout and
+//! stderr. [`set_stderr`] changes the error output at runtime. This keeps
+//! errors visible while the console is used for a user interface.
+//!
+//! [`init`]: crate::init
+
+use core::fmt::{self, Write};
+use core::mem;
+
+use uefi::proto::console::serial::Serial;
+use uefi::table::boot::ScopedProtocol;
+
+/// An output selected by [`Config`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sink {
+    /// The UEFI console.
+    #[default]
+    Console,
+    /// The first serial device. It is opened without exclusive access, so it
+    /// can also be the device connected to the console.
+    Serial,
+}
+
+/// Outputs used by the UEFI utility library, passed to [`init`].
+///
+/// If a serial device is selected but none is available, the output goes to
+/// the console instead.
+///
+/// [`init`]: crate::init
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Output of `print!`, `println!` and the log records other than errors.
+    pub stdout: Sink,
+    /// Output of `error!` records and of the panic handler. This can be
+    /// changed later with [`set_stderr`].
+    pub stderr: Sink,
+}
+
+/// Serial device receiving the normal output, if any.
+static mut STDOUT: Option<ScopedProtocol<'static, Serial<'static>>> = None;
+
+/// Serial device receiving the error records, if any.
+static mut STDERR: Option<ScopedProtocol<'static, Serial<'static>>> = None;
+
+/// Send `error!` records, and the panic message, to `serial` instead of the
+/// console.
+///
+/// The serial device is kept open until boot services are exited, or until
+/// it is taken back with [`take_stderr`]. Calling this again replaces the
+/// previous device, which is closed.
+///
+/// The protocol can be opened with the `'static` reference returned by
+/// [`boot_services`].
+///
+/// [`boot_services`]: crate::boot_services
+pub fn set_stderr(serial: ScopedProtocol<'static, Serial<'static>>) {
+    unsafe {
+        STDERR = Some(serial);
+    }
+}
+
+/// Send the error records to the console again, and return the serial
+/// device they were sent to, if any.
+pub fn take_stderr() -> Option<ScopedProtocol<'static, Serial<'static>>> {
+    unsafe { STDERR.take() }
+}
+
+/// Open the serial devices selected by `config`. Called by `init`.
+pub(crate) unsafe fn open_sinks(config: Config) {
+    let open = |sink| match sink {
+        Sink::Console => None,
+        Sink::Serial => crate::boot_services().get_first_protocol::<Serial>().ok(),
+    };
+    STDOUT = open(config.stdout);
+    STDERR = open(config.stderr);
+}
+
+/// Stop using the serial devices, which must not be closed anymore as boot
+/// services are being exited. Called from the `exit_boot_services`
+/// notification.
+pub(crate) unsafe fn exit_boot_services() {
+    mem::forget(STDOUT.take());
+    mem::forget(STDERR.take());
+}
+
+/// Print to the serial device selected for the normal output. Returns
+/// `false` if there is none, in which case the caller prints to the console.
+pub(crate) fn print(args: fmt::Arguments) -> bool {
+    if let Some(serial) = unsafe { STDOUT.as_mut() } {
+        // Like the console, fail loudly if the output is lost.
+        SerialWriter(serial)
+            .write_fmt(args)
+            .expect("Failed to write to stdout");
+        true
+    } else {
+        false
+    }
+}
+
+/// Adapter writing `\r\n` line endings to a serial device, as expected by
+/// terminals.
+struct SerialWriter<'a>(&'a mut Serial<'static>);
+
+impl Write for SerialWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.0.write_str("\r\n")?;
+            }
+            self.0.write_str(line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Logger sending errors to the serial device set with [`set_stderr`], the
+/// other records to the serial device selected for the normal output, and
+/// everything else to the console logger.
+struct SplitLogger {
+    console: &'static dyn log::Log,
+}
+
+static mut SPLIT_LOGGER: Option<SplitLogger> = None;
+
+impl SplitLogger {
+    fn serial(level: log::Level) -> Option<&'static mut ScopedProtocol<'static, Serial<'static>>> {
+        unsafe {
+            if level == log::Level::Error {
+                STDERR.as_mut()
+            } else {
+                STDOUT.as_mut()
+            }
+        }
+    }
+}
+
+impl log::Log for SplitLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.console.enabled(metadata) || Self::serial(metadata.level()).is_some()
+    }
+
+    fn log(&self, record: &log::Record) {
+        if let Some(serial) = Self::serial(record.level()) {
+            // Errors can't be reported from here, and there is nowhere
+            // else to send them.
+            let _ = uefi::logger::write_record(&mut SerialWriter(serial), record);
+            return;
+        }
+
+        if self.console.enabled(record.metadata()) {
+            self.console.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.console.flush();
+    }
+}
+
+/// Wrap the console logger, returning the logger to install. Called by
+/// `init`.
+pub(crate) unsafe fn init(console: &'static dyn log::Log) -> &'static dyn log::Log {
+    SPLIT_LOGGER = Some(SplitLogger { console });
+    SPLIT_LOGGER.as_ref().unwrap()
+}
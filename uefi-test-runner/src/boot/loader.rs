@@ -0,0 +1,106 @@
+use alloc::vec::Vec;
+use core::slice;
+use uefi::loader::elf::{self, LoadError};
+use uefi::table::boot::{AllocateType, BootServices, MemoryType};
+
+/// The ELF machine type of the current target, if the loader supports it.
+const MACHINE: Option<u16> = if cfg!(target_arch = "x86_64") {
+    Some(62)
+} else if cfg!(target_arch = "aarch64") {
+    Some(183)
+} else {
+    None
+};
+
+const EHDR_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+
+pub fn test(bt: &BootServices) {
+    let machine = if let Some(machine) = MACHINE {
+        machine
+    } else {
+        info!("The ELF loader does not support this architecture");
+        return;
+    };
+
+    info!("Testing the ELF loader");
+    test_load_elf(bt, machine);
+    test_overlapping_segments(bt, machine);
+}
+
+/// Build an executable with a writable `PT_LOAD` segment for each of
+/// `segments`, given as `(address, data, mem_size)`.
+fn build_elf(machine: u16, entry: u64, segments: &[(u64, &[u8], u64)]) -> Vec<u8> {
+    let mut image = vec![0; EHDR_SIZE + segments.len() * PHDR_SIZE];
+    image[..4].copy_from_slice(b"\x7fELF");
+    // Little-endian ELF64, current version.
+    image[4..7].copy_from_slice(&[2, 1, 1]);
+    // Executable file.
+    image[16..18].copy_from_slice(&2u16.to_le_bytes());
+    image[18..20].copy_from_slice(&machine.to_le_bytes());
+    image[24..32].copy_from_slice(&entry.to_le_bytes());
+    image[32..40].copy_from_slice(&(EHDR_SIZE as u64).to_le_bytes());
+    image[54..56].copy_from_slice(&(PHDR_SIZE as u16).to_le_bytes());
+    image[56..58].copy_from_slice(&(segments.len() as u16).to_le_bytes());
+
+    for (i, &(address, data, mem_size)) in segments.iter().enumerate() {
+        let offset = image.len() as u64;
+        let ph = &mut image[EHDR_SIZE + i * PHDR_SIZE..][..PHDR_SIZE];
+        // `PT_LOAD`, readable and writable.
+        ph[0..4].copy_from_slice(&1u32.to_le_bytes());
+        ph[4..8].copy_from_slice(&6u32.to_le_bytes());
+        ph[8..16].copy_from_slice(&offset.to_le_bytes());
+        ph[16..24].copy_from_slice(&address.to_le_bytes());
+        ph[24..32].copy_from_slice(&address.to_le_bytes());
+        ph[32..40].copy_from_slice(&(data.len() as u64).to_le_bytes());
+        ph[40..48].copy_from_slice(&mem_size.to_le_bytes());
+        image.extend_from_slice(data);
+    }
+    image
+}
+
+/// Find two free pages to load a segment at.
+fn free_address(bt: &BootServices) -> u64 {
+    let address = bt
+        .allocate_pages(AllocateType::AnyPages, MemoryType::LOADER_DATA, 2)
+        .expect("Failed to allocate pages");
+    bt.free_pages(address, 2).expect("Failed to free pages");
+    address
+}
+
+fn test_load_elf(bt: &BootServices, machine: u16) {
+    let address = free_address(bt);
+    let data = b"uefi-rs ELF loader test";
+    let image = build_elf(machine, address + 4, &[(address, data, 0x2000)]);
+
+    let entry = elf::load(bt, &image).expect("Failed to load ELF executable");
+    assert_eq!(entry, address + 4);
+
+    // The segment is copied, and the rest of its memory is zeroed.
+    let loaded = unsafe { slice::from_raw_parts(address as usize as *const u8, 0x2000) };
+    assert_eq!(&loaded[..data.len()], data);
+    assert!(loaded[data.len()..].iter().all(|byte| *byte == 0));
+
+    bt.free_pages(address, 2)
+        .expect("Failed to free the pages of the segment");
+}
+
+fn test_overlapping_segments(bt: &BootServices, machine: u16) {
+    let address = free_address(bt);
+    let image = build_elf(
+        machine,
+        address,
+        &[
+            (address, b"first", 0x10),
+            (address + 0x800, b"second", 0x10),
+        ],
+    );
+
+    assert!(matches!(
+        elf::load(bt, &image),
+        Err(LoadError::OverlappingSegments {
+            first: 0,
+            second: 1
+        })
+    ));
+}
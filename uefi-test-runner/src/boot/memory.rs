@@ -1,38 +1,37 @@
 use uefi::table::boot::{AllocateType, BootServices, MemoryType};
+use uefi::Status;
 
 use crate::alloc::vec::Vec;
 
 pub fn test(bt: &BootServices) {
     info!("Testing memory functions");
 
-    allocate_pages(bt);
+    allocate_pool_boxed(bt);
     vec_alloc();
     alloc_alignment();
     memmove(bt);
 
     memory_map(bt);
+    memory_map_diff(bt);
 }
 
-fn allocate_pages(bt: &BootServices) {
-    info!("Allocating some pages of memory");
+fn allocate_pool_boxed(bt: &BootServices) {
+    info!("Allocating a value from the pool");
 
-    let ty = AllocateType::AnyPages;
-    let mem_ty = MemoryType::LOADER_DATA;
-    let pgs = bt
-        .allocate_pages(ty, mem_ty, 1)
-        .expect("Failed to allocate a page of memory");
+    let mut value = bt
+        .allocate_pool_boxed(MemoryType::LOADER_DATA, [1u64; 4])
+        .expect("Failed to allocate a value from the pool");
+    value[3] = 7;
+    assert_eq!(*value, [1, 1, 1, 7]);
 
-    assert_eq!(pgs % 4096, 0, "Page pointer is not page-aligned");
-
-    // Reinterpret the page as an array of bytes
-    let buf = unsafe { &mut *(pgs as *mut [u8; 4096]) };
-
-    // If these don't fail then we properly allocated some memory.
-    buf[0] = 0xF0;
-    buf[4095] = 0x23;
+    #[derive(Debug)]
+    #[repr(align(0x100))]
+    struct Block([u8; 0x100]);
 
-    // Clean up to avoid memory leaks.
-    bt.free_pages(pgs, 1).unwrap();
+    let err = bt
+        .allocate_pool_boxed(MemoryType::LOADER_DATA, Block([0; 0x100]))
+        .unwrap_err();
+    assert_eq!(err.status(), Status::UNSUPPORTED);
 }
 
 // Simple test to ensure our custom allocator works with the `alloc` crate.
@@ -57,7 +56,7 @@ fn alloc_alignment() {
     assert_eq!(value.as_ptr() as usize % 0x100, 0, "Wrong alignment");
 }
 
-// Test that the `memmove` / `set_mem` functions work.
+// Test that the `memmove` / `set_mem` functions and their safe wrappers work.
 fn memmove(bt: &BootServices) {
     info!("Testing the `memmove` / `set_mem` functions");
 
@@ -77,6 +76,27 @@ fn memmove(bt: &BootServices) {
     }
 
     assert_eq!(dest, src, "Failed to copy memory");
+
+    // Same with the safe wrappers
+    bt.fill_mem(&mut dest, 2);
+    assert_eq!(dest, [2; 4], "Failed to fill memory");
+    bt.copy_mem(&mut dest, &src);
+    assert_eq!(dest, src, "Failed to copy memory");
+
+    // Overlapping moves in both directions
+    let mut buf = [1, 2, 3, 4, 5, 6, 7, 8];
+    bt.copy_mem_within(&mut buf, 0..6, 2);
+    assert_eq!(
+        buf,
+        [1, 2, 1, 2, 3, 4, 5, 6],
+        "Failed to move memory forward"
+    );
+    bt.copy_mem_within(&mut buf, 2..8, 0);
+    assert_eq!(
+        buf,
+        [1, 2, 3, 4, 5, 6, 5, 6],
+        "Failed to move memory backward"
+    );
 }
 
 fn memory_map(bt: &BootServices) {
@@ -113,3 +133,33 @@ fn memory_map(bt: &BootServices) {
     let page_count = first_desc.page_count;
     assert!(page_count != 0, "Memory map entry has zero size");
 }
+
+fn memory_map_diff(bt: &BootServices) {
+    info!("Testing memory map diffing");
+
+    let before = bt
+        .memory_map_owned()
+        .expect("Failed to retrieve UEFI memory map");
+
+    let pages = 16;
+    let addr = bt
+        .allocate_pages(AllocateType::AnyPages, MemoryType::LOADER_DATA, pages)
+        .expect("Failed to allocate pages");
+
+    let after = bt
+        .memory_map_owned()
+        .expect("Failed to retrieve UEFI memory map");
+
+    let diff = before.diff(&after);
+    info!("Memory map changes: {:?}", diff.changes());
+    assert!(
+        diff.changes()
+            .iter()
+            .any(|change| change.new_ty == MemoryType::LOADER_DATA
+                && change.phys_start <= addr
+                && addr + pages as u64 * 4096 <= change.phys_start + change.page_count * 4096),
+        "Allocation not found in memory map diff"
+    );
+
+    bt.free_pages(addr, pages).unwrap();
+}
@@ -1,27 +1,71 @@
+use core::cell::Cell;
 use core::ffi::c_void;
+use core::ops::ControlFlow;
 use core::ptr::NonNull;
+use core::time::Duration;
 
+use uefi::rt::Executor;
+use uefi::stopwatch::Stopwatch;
 use uefi::table::boot::{BootServices, EventType, TimerTrigger, Tpl};
 use uefi::Event;
 
 pub fn test(bt: &BootServices) {
     info!("Testing timer...");
-    test_timer(bt);
+    test_timer_polling(bt);
+    test_executor(bt);
     info!("Testing events...");
     test_event_callback(bt);
     test_callback_with_ctx(bt);
     info!("Testing watchdog...");
     test_watchdog(bt);
+    info!("Testing delay...");
+    test_delay(bt);
 }
 
-fn test_timer(bt: &BootServices) {
+fn test_timer_polling(bt: &BootServices) {
     let timer_event = unsafe { bt.create_event(EventType::TIMER, Tpl::APPLICATION, None, None) }
         .expect("Failed to create TIMER event");
-    let mut events = unsafe { [timer_event.unsafe_clone()] };
     bt.set_timer(&timer_event, TimerTrigger::Relative(5_0 /*00 ns */))
         .expect("Failed to set timer");
-    bt.wait_for_event(&mut events)
-        .expect("Wait for event failed");
+
+    // Poll the one-shot timer until it fires.
+    while !bt.check_event(&timer_event).expect("Failed to check event") {
+        bt.stall(10);
+    }
+
+    // Checking the event cleared the signaled state.
+    assert!(!bt.check_event(&timer_event).unwrap());
+
+    bt.close_event(timer_event).expect("Failed to close event");
+}
+
+fn test_executor(bt: &BootServices) {
+    let fast_runs = Cell::new(0);
+    let slow_runs = Cell::new(0);
+    // Stops after the given number of runs.
+    let counter = |name: &str, cell: &Cell<u32>, limit: u32| {
+        cell.set(cell.get() + 1);
+        info!("Executor task {} ran", name);
+        if cell.get() == limit {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    };
+
+    let mut executor = Executor::new(bt);
+    executor
+        .spawn_periodic(Duration::from_millis(1), || counter("fast", &fast_runs, 4))
+        .expect("Failed to spawn task");
+    executor
+        .spawn_periodic(Duration::from_millis(2), || counter("slow", &slow_runs, 2))
+        .expect("Failed to spawn task");
+    assert_eq!(executor.len(), 2);
+
+    executor.run().expect("Failed to run tasks");
+    assert!(executor.is_empty());
+    assert_eq!(fast_runs.get(), 4);
+    assert_eq!(slow_runs.get(), 2);
 }
 
 fn test_event_callback(bt: &BootServices) {
@@ -32,7 +76,7 @@ fn test_event_callback(bt: &BootServices) {
     let event =
         unsafe { bt.create_event(EventType::NOTIFY_WAIT, Tpl::CALLBACK, Some(callback), None) }
             .expect("Failed to create custom event");
-    bt.check_event(event).expect("Failed to check event");
+    bt.check_event(&event).expect("Failed to check event");
 }
 
 fn test_callback_with_ctx(bt: &BootServices) {
@@ -61,7 +105,7 @@ fn test_callback_with_ctx(bt: &BootServices) {
         .expect("Failed to create event with context")
     };
 
-    bt.check_event(event).expect("Failed to check event");
+    bt.check_event(&event).expect("Failed to check event");
 
     // Check that `data` was updated inside the event callback.
     assert_eq!(data, 456);
@@ -72,3 +116,31 @@ fn test_watchdog(bt: &BootServices) {
     bt.set_watchdog_timer(0, 0x10000, None)
         .expect("Could not set watchdog timer");
 }
+
+fn test_delay(bt: &BootServices) {
+    // The loop was calibrated by `uefi_services::init`.
+    uefi_services::delay::delay_ns(1_000);
+
+    // The delay must last at least as long as a shorter firmware timer.
+    let timer = unsafe { bt.create_event(EventType::TIMER, Tpl::APPLICATION, None, None) }
+        .expect("Failed to create TIMER event");
+    bt.set_timer(&timer, TimerTrigger::Relative(10 * 10_000 /* 10 ms */))
+        .expect("Failed to set timer");
+    uefi_services::delay::delay_us(50_000);
+    assert!(
+        bt.check_event(&timer).expect("Failed to check timer"),
+        "The delay returned before the timer expired"
+    );
+    bt.close_event(timer).expect("Failed to close timer");
+
+    // Measure it more precisely if the timestamp counter is available.
+    if let Ok(stopwatch) = Stopwatch::start_with_timestamp(bt) {
+        uefi_services::delay::delay_us(50_000);
+        let elapsed = stopwatch.elapsed().expect("Failed to read the stopwatch");
+        // Leave some room for calibration errors.
+        assert!(
+            elapsed >= Duration::from_millis(25),
+            "A 50 ms delay took only {elapsed:?}"
+        );
+    }
+}
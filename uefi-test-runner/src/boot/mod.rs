@@ -1,17 +1,94 @@
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicBool, Ordering};
+use uefi::proto::console::serial::Serial;
 use uefi::proto::console::text::Output;
-use uefi::table::boot::{BootServices, SearchType};
-use uefi::Identify;
+use uefi::proto::Protocol;
+use uefi::table::boot::{BootServices, EventType, ImageToVerify, LoadImageSource, SearchType, Tpl};
+use uefi::{unsafe_guid, Guid, Handle, Identify, Status};
 
 pub fn test(bt: &BootServices) {
     info!("Testing boot services");
     memory::test(bt);
     misc::test(bt);
+    loader::test(bt);
     test_locate_handle_buffer(bt);
+    test_boot_services_global(bt);
+    test_log_ring();
+    test_stderr();
+    test_install_multiple_protocol_interfaces(bt);
+    test_reinstall_protocol_interface(bt);
+    test_register_protocol_notify(bt);
+    test_protocol_notify_event(bt);
+    test_handle_database_version(bt);
+    test_image_verifier(bt);
+    test_selftest(bt);
 }
 
+mod loader;
 mod memory;
 mod misc;
 
+/// A dummy protocol interface installed for a test, and uninstalled when
+/// this is dropped.
+pub struct TestInterface<'a> {
+    bt: &'a BootServices,
+    handle: Handle,
+    guid: Guid,
+    interface: *const c_void,
+}
+
+impl<'a> TestInterface<'a> {
+    /// Install a dummy interface of the `guid` protocol on `handle`, or on a
+    /// new handle if `None`.
+    pub fn install(bt: &'a BootServices, handle: Option<Handle>, guid: Guid) -> Self {
+        static INTERFACE: u32 = 1;
+        let interface: *const u32 = &INTERFACE;
+        let interface = interface.cast();
+        let handle = unsafe { bt.install_protocol_interface(handle, &guid, interface) }
+            .expect("Failed to install protocol interface");
+        Self {
+            bt,
+            handle,
+            guid,
+            interface,
+        }
+    }
+
+    /// The handle the interface is installed on.
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+
+    /// The installed interface.
+    pub fn interface(&self) -> *const c_void {
+        self.interface
+    }
+
+    /// Replace the installed interface with `new_interface`.
+    pub fn reinstall(&mut self, new_interface: *const c_void) -> uefi::Result {
+        unsafe {
+            self.bt.reinstall_protocol_interface(
+                self.handle,
+                &self.guid,
+                self.interface,
+                new_interface,
+            )
+        }?;
+        self.interface = new_interface;
+        Ok(())
+    }
+}
+
+impl Drop for TestInterface<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            self.bt
+                .uninstall_protocol_interface(self.handle, &self.guid, self.interface)
+        }
+        .expect("Failed to uninstall protocol interface");
+    }
+}
+
 fn test_locate_handle_buffer(bt: &BootServices) {
     info!("Testing the `locate_handle_buffer` function");
 
@@ -32,5 +109,253 @@ fn test_locate_handle_buffer(bt: &BootServices) {
             !handles.handles().is_empty(),
             "Could not find any OUTPUT protocol handles"
         );
+        // The buffer can also be used as a slice.
+        assert_eq!(handles.len(), handles.handles().len());
+    }
+}
+
+fn test_boot_services_global(bt: &BootServices) {
+    info!("Testing the global boot services reference");
+
+    let global = uefi_services::boot_services();
+    assert!(
+        core::ptr::eq(global, bt),
+        "Global boot services reference does not match"
+    );
+}
+
+fn test_log_ring() {
+    info!("Testing the log ring buffer");
+
+    let mut buffer = vec![0; uefi_services::LOG_RING_SIZE];
+    let len = uefi_services::read_log_ring(&mut buffer);
+    let ring = &buffer[..len];
+    let needle = b"Testing the log ring buffer";
+    assert!(
+        ring.windows(needle.len()).any(|window| window == needle),
+        "Log record is missing from the ring buffer"
+    );
+}
+
+fn test_stderr() {
+    info!("Testing the error output");
+
+    // The host ignores everything but screenshot requests on the serial
+    // device used for them, so errors can be sent there. The device must be
+    // given back before the next screenshot request.
+    let bt = uefi_services::boot_services();
+    if let Some(handle) = bt.find_non_console_serial() {
+        let serial = bt
+            .open_protocol_exclusive::<Serial>(handle)
+            .expect("Failed to open serial device");
+        uefi_services::set_stderr(serial);
+        error!("Testing the error output, this is not a failure");
+        assert!(
+            uefi_services::take_stderr().is_some(),
+            "The error output was not set"
+        );
+        assert!(uefi_services::take_stderr().is_none());
+    } else {
+        info!("No serial device for the error output");
     }
 }
+
+fn test_install_multiple_protocol_interfaces(bt: &BootServices) {
+    info!("Testing multiple protocol interface installation");
+
+    const TEST_GUID_A: Guid = Guid::from_values(0x8d2b4c56, 0x0a4e, 0x4b57, 0x9f2e, 0x3c1d7a9e5b10);
+    const TEST_GUID_B: Guid = Guid::from_values(0x8d2b4c56, 0x0a4e, 0x4b57, 0x9f2e, 0x3c1d7a9e5b11);
+    const TEST_GUID_BASE: Guid =
+        Guid::from_values(0x8d2b4c56, 0x0a4e, 0x4b57, 0x9f2e, 0x3c1d7a9e5b16);
+    const TEST_GUID_C: Guid = Guid::from_values(0x8d2b4c56, 0x0a4e, 0x4b57, 0x9f2e, 0x3c1d7a9e5b17);
+    let interfaces = [
+        (TEST_GUID_A, core::ptr::null()),
+        (TEST_GUID_B, core::ptr::null()),
+    ];
+
+    // Install the interfaces on an existing handle, which keeps it alive
+    // while they are uninstalled.
+    let base = TestInterface::install(bt, None, TEST_GUID_BASE);
+    let handle = base.handle();
+    let installed = unsafe { bt.install_multiple_protocol_interfaces(Some(handle), &interfaces) }
+        .expect("Failed to install protocol interfaces");
+    assert_eq!(installed.handle(), handle);
+    assert_eq!(
+        bt.protocols_per_handle(handle).unwrap().protocols().len(),
+        3
+    );
+
+    // Installing one of the protocols again must fail without leaving the
+    // other one installed.
+    let duplicate = [
+        (TEST_GUID_C, core::ptr::null()),
+        (TEST_GUID_A, core::ptr::null()),
+    ];
+    assert!(unsafe { bt.install_multiple_protocol_interfaces(Some(handle), &duplicate) }.is_err());
+    assert_eq!(
+        bt.protocols_per_handle(handle).unwrap().protocols().len(),
+        3
+    );
+
+    installed
+        .uninstall()
+        .expect("Failed to uninstall protocol interfaces");
+    assert!(bt
+        .locate_handle_buffer(SearchType::ByProtocol(&TEST_GUID_A))
+        .is_err());
+    assert_eq!(
+        bt.protocols_per_handle(handle).unwrap().protocols().len(),
+        1
+    );
+}
+
+fn test_reinstall_protocol_interface(bt: &BootServices) {
+    info!("Testing protocol interface reinstallation");
+
+    const TEST_GUID: Guid = Guid::from_values(0x8d2b4c56, 0x0a4e, 0x4b57, 0x9f2e, 0x3c1d7a9e5b12);
+    static NEW_INTERFACE: u32 = 2;
+    let new: *const u32 = &NEW_INTERFACE;
+    let new = new.cast();
+
+    let mut installed = TestInterface::install(bt, None, TEST_GUID);
+    let handle = installed.handle();
+    let old = installed.interface();
+
+    // Replacing an interface that isn't installed must fail.
+    let err = unsafe { bt.reinstall_protocol_interface(handle, &TEST_GUID, new, old) }.unwrap_err();
+    assert_eq!(err.status(), Status::NOT_FOUND);
+
+    installed
+        .reinstall(new)
+        .expect("Failed to reinstall protocol interface");
+
+    // Only the new interface is installed now, and is uninstalled on drop.
+    assert!(unsafe { bt.uninstall_protocol_interface(handle, &TEST_GUID, old) }.is_err());
+}
+
+fn test_register_protocol_notify(bt: &BootServices) {
+    info!("Testing protocol notification registration");
+
+    const TEST_GUID: Guid = Guid::from_values(0x8d2b4c56, 0x0a4e, 0x4b57, 0x9f2e, 0x3c1d7a9e5b13);
+
+    let event = unsafe { bt.create_event(EventType::empty(), Tpl::CALLBACK, None, None) }
+        .expect("Failed to create event");
+    let search_type = bt
+        .register_protocol_notify(&TEST_GUID, &event)
+        .expect("Failed to register protocol notification");
+
+    {
+        let installed = TestInterface::install(bt, None, TEST_GUID);
+        let handles = bt
+            .locate_handle_buffer(search_type)
+            .expect("Failed to locate newly installed handles");
+        assert_eq!(&*handles, &[installed.handle()]);
+    }
+    // The handle was already returned for this registration.
+    assert!(bt.locate_handle_buffer(search_type).is_err());
+
+    bt.close_event(event).expect("Failed to close event");
+}
+
+fn test_protocol_notify_event(bt: &BootServices) {
+    info!("Testing protocol notification events");
+
+    #[unsafe_guid("8d2b4c56-0a4e-4b57-9f2e-3c1d7a9e5b14")]
+    #[derive(Protocol)]
+    struct TestProtocol;
+
+    let (event, key) = bt
+        .create_protocol_notify_event::<TestProtocol>()
+        .expect("Failed to create protocol notification event");
+    assert!(!bt.check_event(&event).unwrap());
+    assert_eq!(bt.locate_handle_by_registration(key), None);
+
+    {
+        let installed = [
+            TestInterface::install(bt, None, TestProtocol::GUID),
+            TestInterface::install(bt, None, TestProtocol::GUID),
+        ];
+
+        // Each call returns one of the new handles.
+        assert!(bt.check_event(&event).unwrap());
+        assert_eq!(
+            bt.locate_handle_by_registration(key),
+            Some(installed[0].handle())
+        );
+        assert_eq!(
+            bt.locate_handle_by_registration(key),
+            Some(installed[1].handle())
+        );
+        assert_eq!(bt.locate_handle_by_registration(key), None);
+    }
+    bt.close_event(event).expect("Failed to close event");
+}
+
+/// The self-test also covers page allocation, waiting for timers and raising
+/// the TPL, which are not tested elsewhere.
+fn test_selftest(bt: &BootServices) {
+    info!("Running the boot services self-test");
+
+    let report = uefi::selftest::run_boot_services_tests(bt);
+    for check in report.checks() {
+        info!("{}: {:?}", check.name, check.outcome);
+    }
+    assert!(
+        report.all_passed(),
+        "Boot services self-test failed: {:?}",
+        report.failures().collect::<alloc::vec::Vec<_>>()
+    );
+}
+
+fn test_handle_database_version(bt: &BootServices) {
+    info!("Testing handle database change detection");
+
+    const TEST_GUID: Guid = Guid::from_values(0x8d2b4c56, 0x0a4e, 0x4b57, 0x9f2e, 0x3c1d7a9e5b15);
+
+    let before = bt
+        .handle_database_version()
+        .expect("Failed to get handle database version");
+    assert_eq!(bt.handle_database_version().unwrap(), before);
+    assert_eq!(
+        before.handle_count(),
+        bt.locate_handle_buffer(SearchType::AllHandles)
+            .unwrap()
+            .len()
+    );
+
+    let installed = TestInterface::install(bt, None, TEST_GUID);
+    let with_handle = bt.handle_database_version().unwrap();
+    assert_ne!(with_handle, before);
+    assert_eq!(with_handle.handle_count(), before.handle_count() + 1);
+
+    // Removing the last interface destroys the handle.
+    drop(installed);
+    assert_eq!(bt.handle_database_version().unwrap(), before);
+}
+
+fn test_image_verifier(bt: &BootServices) {
+    info!("Testing the image verifier");
+
+    static CALLED: AtomicBool = AtomicBool::new(false);
+    fn deny_all(image: ImageToVerify) -> bool {
+        assert_eq!(image.data(), b"not an image");
+        assert!(image.file_path().is_none());
+        CALLED.store(true, Ordering::Relaxed);
+        false
+    }
+
+    let source = || LoadImageSource::FromBuffer {
+        buffer: b"not an image",
+        file_path: None,
+    };
+
+    bt.set_image_verifier(Some(deny_all));
+    let result = bt.load_image(bt.image_handle(), source());
+    bt.set_image_verifier(None);
+    assert_eq!(result.unwrap_err().status(), Status::SECURITY_VIOLATION);
+    assert!(CALLED.swap(false, Ordering::Relaxed));
+
+    // Without a verifier, the firmware rejects the buffer itself.
+    assert!(bt.load_image(bt.image_handle(), source()).is_err());
+    assert!(!CALLED.load(Ordering::Relaxed));
+}
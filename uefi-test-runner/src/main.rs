@@ -1,6 +1,7 @@
 #![no_std]
 #![no_main]
 #![feature(abi_efiapi)]
+#![feature(negative_impls)]
 
 #[macro_use]
 extern crate log;
@@ -10,6 +11,8 @@ extern crate alloc;
 use alloc::string::String;
 use uefi::prelude::*;
 use uefi::proto::console::serial::Serial;
+use uefi::table::smbios::SmbiosTable;
+use uefi::table::Revision;
 use uefi_services::{print, println};
 
 mod boot;
@@ -19,7 +22,7 @@ mod runtime;
 #[entry]
 fn efi_main(image: Handle, mut st: SystemTable<Boot>) -> Status {
     // Initialize utilities (logging, memory allocation...)
-    uefi_services::init(&mut st).expect("Failed to initialize utilities");
+    uefi_services::init(&mut st, Default::default()).expect("Failed to initialize utilities");
 
     // unit tests here
 
@@ -27,6 +30,21 @@ fn efi_main(image: Handle, mut st: SystemTable<Boot>) -> Status {
     let mut buf = String::new();
     st.firmware_vendor().as_str_in_buf(&mut buf).unwrap();
     info!("Firmware Vendor: {}", buf.as_str());
+    let fw_revision = st.firmware_revision();
+    info!(
+        "Firmware Revision: {}.{}",
+        fw_revision.major(),
+        fw_revision.minor()
+    );
+
+    // Check that the system table is intact.
+    let header = st.header();
+    info!(
+        "System table: revision {:?}, {} bytes, CRC {:#010x}",
+        header.revision, header.size, header.crc
+    );
+    st.validate_header()
+        .expect("System table header is not valid");
 
     // Test print! and println! macros.
     let (print, println) = ("print!", "println!"); // necessary for clippy to ignore
@@ -40,15 +58,29 @@ fn efi_main(image: Handle, mut st: SystemTable<Boot>) -> Status {
     st.stdout().reset(false).expect("Failed to reset stdout");
 
     // Ensure the tests are run on a version of UEFI we support.
-    check_revision(st.uefi_revision());
+    check_revision(&st);
+
+    // Log the memory modules described by the SMBIOS tables.
+    check_smbios(&st);
+
+    #[cfg(target_arch = "x86_64")]
+    check_x86_64();
 
     // Test all the boot services.
     let bt = st.boot_services();
 
     // Try retrieving a handle to the file system the image was booted from.
-    bt.get_image_file_system(image)
+    let mut fs = bt
+        .get_image_file_system(image)
         .expect("Failed to retrieve boot file system");
 
+    // Keep the log output on the boot file system, for machines without a
+    // serial port. This fails on read-only media, which is not an error.
+    if let Err(err) = uefi_services::set_log_file(&mut fs, cstr16!("\\uefi-test.log")) {
+        warn!("Not logging to a file: {:?}", err.status());
+    }
+    drop(fs);
+
     boot::test(bt);
 
     // Test all the supported protocols.
@@ -63,18 +95,68 @@ fn efi_main(image: Handle, mut st: SystemTable<Boot>) -> Status {
     shutdown(image, st);
 }
 
-fn check_revision(rev: uefi::table::Revision) {
-    let (major, minor) = (rev.major(), rev.minor());
-
-    info!("UEFI {}.{}", major, minor / 10);
+fn check_revision(st: &SystemTable<Boot>) {
+    let rev = st.uefi_revision();
+    info!("UEFI {}.{}", rev.major(), rev.minor() / 10);
 
-    assert!(major >= 2, "Running on an old, unsupported version of UEFI");
     assert!(
-        minor >= 30,
+        st.supports(Revision::EFI_2_00),
+        "Running on an old, unsupported version of UEFI"
+    );
+    assert!(
+        st.supports(Revision::EFI_2_30),
         "Old version of UEFI 2, some features might not be available."
     );
 }
 
+fn check_smbios(st: &SystemTable<Boot>) {
+    if let Some(table) = unsafe { SmbiosTable::find(st.config_table()) } {
+        for device in table
+            .memory_devices()
+            .filter(|device| device.is_installed())
+        {
+            info!(
+                "Memory device {:?}: {:?} bytes, {:?} MT/s",
+                device.device_locator, device.size, device.speed
+            );
+        }
+        let total = table.total_physical_memory();
+        info!("Installed memory: {} MiB", total >> 20);
+        assert_ne!(total, 0, "SMBIOS tables describe no memory");
+    } else {
+        warn!("SMBIOS tables are not present");
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn check_x86_64() {
+    use uefi::arch::x86_64::*;
+
+    // The tests run on the bootstrap processor.
+    let apic_base = unsafe { read_msr(IA32_APIC_BASE) };
+    info!("IA32_APIC_BASE: {:#x}", apic_base);
+    assert_ne!(apic_base & APIC_BASE_BSP, 0, "not running on the BSP");
+
+    if unsafe { read_msr(IA32_MTRRCAP) } & MTRRCAP_SMRR != 0 {
+        let (base, mask) = unsafe { (read_msr(IA32_SMRR_PHYSBASE), read_msr(IA32_SMRR_PHYSMASK)) };
+        info!("SMRR: base {:#x}, mask {:#x}", base, mask);
+    } else {
+        info!("SMRR is not supported");
+    }
+
+    if cfg!(feature = "qemu") {
+        // Select the signature item of QEMU's firmware configuration device,
+        // and read it from the data port.
+        let mut selector = Port::<u16>::new(0x510);
+        let data = Port::<u8>::new(0x511);
+        let signature = unsafe {
+            selector.write(0);
+            [data.read(), data.read(), data.read(), data.read()]
+        };
+        assert_eq!(&signature, b"QEMU");
+    }
+}
+
 /// Ask the test runner to check the current screen output against a reference
 ///
 /// This functionality is very specific to our QEMU-based test runner. Outside
@@ -82,44 +164,36 @@ fn check_revision(rev: uefi::table::Revision) {
 /// inspection of the output.
 fn check_screenshot(bt: &BootServices, name: &str) {
     if cfg!(feature = "qemu") {
-        let serial_handles = bt
-            .find_handles::<Serial>()
-            .expect("Failed to get serial handles");
-
-        // Use the second serial device handle. Opening a serial device
-        // in exclusive mode breaks the connection between stdout and
-        // the serial device, and we don't want that to happen to the
-        // first serial device since it's used for log transport.
-        let serial_handle = *serial_handles
-            .get(1)
-            .expect("Second serial device is missing");
-
-        let mut serial = bt
-            .open_protocol_exclusive::<Serial>(serial_handle)
-            .expect("Could not open serial protocol");
-
-        // Set a large timeout to avoid problems with Travis
-        let mut io_mode = *serial.io_mode();
-        io_mode.timeout = 10_000_000;
-        serial
-            .set_attributes(&io_mode)
-            .expect("Failed to configure serial port timeout");
-
-        // Send a screenshot request to the host
-        serial
-            .write(b"SCREENSHOT: ")
-            .expect("Failed to send request");
-        let name_bytes = name.as_bytes();
-        serial.write(name_bytes).expect("Failed to send request");
-        serial.write(b"\n").expect("Failed to send request");
-
-        // Wait for the host's acknowledgement before moving forward
-        let mut reply = [0; 3];
-        serial
-            .read(&mut reply[..])
-            .expect("Failed to read host reply");
-
-        assert_eq!(&reply[..], b"OK\n", "Unexpected screenshot request reply");
+        // Opening a serial device in exclusive mode breaks the connection
+        // between stdout and the serial device, and we don't want that to
+        // happen to the serial device used for log transport.
+        let serial_handle = bt
+            .find_non_console_serial()
+            .expect("Non-console serial device is missing");
+
+        bt.with_protocol_exclusive::<Serial, _>(serial_handle, |serial| {
+            // Send a screenshot request to the host
+            serial
+                .write(b"SCREENSHOT: ")
+                .expect("Failed to send request");
+            let name_bytes = name.as_bytes();
+            serial.write(name_bytes).expect("Failed to send request");
+            serial.write(b"\n").expect("Failed to send request");
+
+            // Wait for the host's acknowledgement before moving forward. Use a
+            // large timeout, as taking the screenshot can be slow on CI.
+            let mut reply = [0; 3];
+            let read = serial
+                .read_with_timeout(&mut reply[..], 10_000_000)
+                .expect("Failed to read host reply");
+
+            assert_eq!(
+                &reply[..read],
+                b"OK\n",
+                "Unexpected screenshot request reply"
+            );
+        })
+        .expect("Could not open serial protocol");
     } else {
         // Outside of QEMU, give the user some time to inspect the output
         bt.stall(3_000_000);
@@ -140,6 +214,9 @@ fn shutdown(image: uefi::Handle, mut st: SystemTable<Boot>) -> ! {
         info!("Testing complete, shutting down...");
     }
 
+    // The log file must be closed while the file system is still usable.
+    uefi_services::close_log_file();
+
     // Exit boot services as a proof that it works :)
     let sizes = st.boot_services().memory_map_size();
     let max_mmap_size = sizes.map_size + 2 * sizes.entry_size;
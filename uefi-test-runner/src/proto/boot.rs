@@ -0,0 +1,22 @@
+use uefi::prelude::*;
+use uefi::proto::boot::{BootManagerPolicy, DeviceClass};
+
+pub fn test(bt: &BootServices) {
+    info!("Running boot manager policy protocol test");
+
+    if let Ok(handle) = bt.get_handle_for_protocol::<BootManagerPolicy>() {
+        let policy = bt
+            .open_protocol_exclusive::<BootManagerPolicy>(handle)
+            .expect("Failed to open BootManagerPolicy protocol");
+
+        match policy.connect_device_class(DeviceClass::CONSOLE) {
+            Ok(()) => info!("Connected console devices"),
+            Err(err) if [Status::UNSUPPORTED, Status::NOT_FOUND].contains(&err.status()) => {
+                info!("Connecting console devices by class is not supported")
+            }
+            Err(err) => panic!("Failed to connect console devices: {:?}", err),
+        }
+    } else {
+        info!("BootManagerPolicy protocol is not supported");
+    }
+}
@@ -1,5 +1,9 @@
+use uefi::graphics::{Canvas, DrawTarget};
 use uefi::prelude::*;
-use uefi::proto::console::gop::{BltOp, BltPixel, FrameBuffer, GraphicsOutput, PixelFormat};
+use uefi::proto::console::gop::{
+    encode_bmp, save_bmp, BackBuffer, BltOp, BltPixel, GraphicsOutput, PixelConverter, Rect,
+};
+use uefi::proto::media::file::{File, FileAttribute, FileInfo, FileMode};
 use uefi::table::boot::{BootServices, OpenProtocolAttributes, OpenProtocolParams};
 
 pub unsafe fn test(image: Handle, bt: &BootServices) {
@@ -24,6 +28,12 @@ pub unsafe fn test(image: Handle, bt: &BootServices) {
         draw_fb(gop);
 
         crate::check_screenshot(bt, "gop_test");
+        capture_screen(bt, gop);
+
+        // Runs after the screenshot, since the reference image doesn't
+        // contain the animation.
+        animate_back_buffer(bt, gop);
+        draw_canvas(gop);
     } else {
         // No tests can be run.
         warn!("UEFI Graphics Output Protocol is not supported");
@@ -62,23 +72,16 @@ fn draw_fb(gop: &mut GraphicsOutput) {
     let stride = mi.stride();
     let (width, height) = mi.resolution();
 
-    let mut fb = gop.frame_buffer();
-
-    type PixelWriter = unsafe fn(&mut FrameBuffer, usize, [u8; 3]);
-    unsafe fn write_pixel_rgb(fb: &mut FrameBuffer, pixel_base: usize, rgb: [u8; 3]) {
-        fb.write_value(pixel_base, rgb);
-    }
-    unsafe fn write_pixel_bgr(fb: &mut FrameBuffer, pixel_base: usize, rgb: [u8; 3]) {
-        fb.write_value(pixel_base, [rgb[2], rgb[1], rgb[0]]);
-    }
-    let write_pixel: PixelWriter = match mi.pixel_format() {
-        PixelFormat::Rgb => write_pixel_rgb,
-        PixelFormat::Bgr => write_pixel_bgr,
-        _ => {
+    let converter = match PixelConverter::new(&mi) {
+        Some(converter) => converter,
+        None => {
             info!("This pixel format is not supported by the drawing demo");
             return;
         }
     };
+    let bytes_per_pixel = converter.bytes_per_pixel();
+
+    let mut fb = gop.frame_buffer();
 
     let mut fill_rectangle = |(x1, y1), (x2, y2), color| {
         assert!((x1 < width) && (x2 < width), "Bad X coordinate");
@@ -87,13 +90,91 @@ fn draw_fb(gop: &mut GraphicsOutput) {
             for column in x1..x2 {
                 unsafe {
                     let pixel_index = (row * stride) + column;
-                    let pixel_base = 4 * pixel_index;
-                    write_pixel(&mut fb, pixel_base, color);
+                    let pixel_base = bytes_per_pixel * pixel_index;
+                    fb.write_pixel(pixel_base, &converter, color);
                 }
             }
         }
     };
 
-    fill_rectangle((50, 30), (150, 600), [250, 128, 64]);
-    fill_rectangle((400, 120), (750, 450), [16, 128, 255]);
+    fill_rectangle((50, 30), (150, 600), BltPixel::new(250, 128, 64));
+    fill_rectangle((400, 120), (750, 450), BltPixel::new(16, 128, 255));
+}
+
+// Read the screen back and save it as a BMP file.
+fn capture_screen(bt: &BootServices, gop: &mut GraphicsOutput) {
+    let (pixels, dims) = gop.capture().expect("Failed to capture the screen");
+    assert_eq!(dims, (1024, 768));
+
+    // The top-left corner still has the fill color.
+    let corner = pixels[0];
+    assert_eq!((corner.red, corner.green, corner.blue), (100, 149, 237));
+
+    let mut esp = bt.locate_esp().expect("Failed to locate the ESP");
+    let mut root = esp.open_volume().expect("Failed to open the ESP volume");
+    let path = cstr16!("gop_capture.bmp");
+    save_bmp(&mut root, path, &pixels, dims).expect("Failed to save the capture");
+
+    let mut file = root
+        .open(path, FileMode::ReadWrite, FileAttribute::empty())
+        .expect("Failed to open the capture")
+        .into_regular_file()
+        .expect("Not a regular file");
+    let info = file
+        .get_boxed_info::<FileInfo>()
+        .expect("Failed to get the capture's info");
+    assert_eq!(info.file_size(), encode_bmp(&pixels, dims).len() as u64);
+    file.delete().expect("Failed to delete the capture");
+}
+
+// Move a square across the screen using a back buffer.
+fn animate_back_buffer(bt: &BootServices, gop: &mut GraphicsOutput) {
+    const SIZE: usize = 64;
+    const FRAMES: usize = 30;
+
+    let background = BltPixel::new(100, 149, 237);
+    let foreground = BltPixel::new(255, 255, 255);
+
+    let mut back_buffer = BackBuffer::new(gop);
+    let (width, height) = back_buffer.resolution();
+    back_buffer.clear(background);
+    back_buffer
+        .present(gop)
+        .expect("Failed to present back buffer");
+    assert_eq!(back_buffer.dirty_region(), None);
+
+    let y = (height - SIZE) / 2;
+    let step = (width - SIZE) / FRAMES;
+    for frame in 0..FRAMES {
+        let x = frame * step;
+        if frame > 0 {
+            back_buffer.fill_rect(Rect::new(x - step, y, SIZE, SIZE), background);
+        }
+        back_buffer.fill_rect(Rect::new(x, y, SIZE, SIZE), foreground);
+        back_buffer
+            .present_dirty(gop)
+            .expect("Failed to present back buffer");
+        bt.stall(10_000);
+    }
+}
+
+// Draw off-screen on a canvas, then copy it to the screen.
+fn draw_canvas(gop: &mut GraphicsOutput) {
+    let mut canvas = Canvas::new(128, 32);
+    canvas.clear(BltPixel::new(0, 0, 128));
+    canvas.draw_line((0, 31), (127, 31), BltPixel::new(255, 255, 255));
+    canvas.draw_text((4, 4), "uefi-rs", BltPixel::new(255, 255, 0), 3);
+    assert_eq!(
+        canvas.to_bmp().len(),
+        encode_bmp(canvas.pixels(), (128, 32)).len()
+    );
+
+    canvas
+        .present_to(gop, (16, 16))
+        .expect("Failed to present canvas");
+    let (pixels, (width, _)) = gop.capture().expect("Failed to capture the screen");
+    let corner = pixels[16 * width + 16];
+    assert_eq!((corner.red, corner.green, corner.blue), (0, 0, 128));
+    let line = pixels[(16 + 31) * width + 16];
+    assert_eq!((line.red, line.green, line.blue), (255, 255, 255));
 }
@@ -1,9 +1,12 @@
-use uefi::proto::console::serial::{ControlBits, Serial};
+use core::fmt::Write;
+use uefi::proto::console::serial::{BufferedSerial, ControlBits, Serial};
 use uefi::table::boot::{BootServices, OpenProtocolAttributes, OpenProtocolParams};
 use uefi::Handle;
 
 pub unsafe fn test(image: Handle, bt: &BootServices) {
     info!("Running serial protocol test");
+    test_serial_ports(bt);
+
     if let Ok(handle) = bt.get_handle_for_protocol::<Serial>() {
         let mut serial = bt
             .open_protocol::<Serial>(
@@ -54,6 +57,41 @@ pub unsafe fn test(image: Handle, bt: &BootServices) {
 
         assert_eq!(OUTPUT, &input[..]);
 
+        // A read with a timeout returns what was received until then.
+        let old_timeout = serial.io_mode().timeout;
+        serial
+            .write(OUTPUT)
+            .expect("Failed to write to serial port");
+        let mut input = [0u8; MSG_LEN + 4];
+        let read = serial
+            .read_with_timeout(&mut input, 100_000)
+            .expect("Failed to read from serial port");
+        assert_eq!(OUTPUT, &input[..read]);
+        assert_eq!(serial.io_mode().timeout, old_timeout);
+
+        // `write_all` writes the whole message as well.
+        serial
+            .write_all(OUTPUT)
+            .expect("Failed to write to serial port");
+        let mut input = [0u8; MSG_LEN];
+        serial
+            .read(&mut input)
+            .expect("Failed to read from serial port");
+        assert_eq!(OUTPUT, &input[..]);
+
+        // A buffered writer only writes on flush, and the formatted output
+        // must fit in the FIFO too.
+        {
+            let mut buffered = BufferedSerial::new(&mut serial);
+            write!(buffered, "Hi {}!", 42).expect("Failed to format to serial port");
+            buffered.flush().expect("Failed to flush serial port");
+        }
+        let mut input = [0u8; 6];
+        serial
+            .read(&mut input)
+            .expect("Failed to read from serial port");
+        assert_eq!(&input, b"Hi 42!");
+
         // Clean up after ourselves
         serial.reset().expect("Could not reset the serial device");
         serial
@@ -63,3 +101,21 @@ pub unsafe fn test(image: Handle, bt: &BootServices) {
         warn!("No serial device found");
     }
 }
+
+fn test_serial_ports(bt: &BootServices) {
+    let ports = bt.serial_ports();
+    for (_, info) in &ports {
+        info!("Serial device: {:?}", info);
+    }
+
+    // QEMU has one serial device for the console and log output, and one
+    // for the screenshot requests.
+    if cfg!(feature = "qemu") {
+        assert_eq!(ports.len(), 2);
+        assert_eq!(ports.iter().filter(|(_, info)| info.is_console).count(), 1);
+        let handle = bt.find_non_console_serial().unwrap();
+        assert!(ports
+            .iter()
+            .any(|(port, info)| *port == handle && !info.is_console));
+    }
+}
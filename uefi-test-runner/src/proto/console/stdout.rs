@@ -7,6 +7,7 @@ pub fn test(stdout: &mut Output) {
     get_current_mode(stdout);
     change_text_mode(stdout);
     change_color(stdout);
+    probe_bright_background(stdout);
     center_text(stdout);
 
     // Print all modes.
@@ -42,9 +43,31 @@ fn change_color(stdout: &mut Output) {
     stdout
         .set_color(Color::White, Color::Blue)
         .expect("Failed to change console color");
+    assert_eq!(stdout.attribute(), 0x1f);
+
+    // Bit 7 is not passed to the firmware.
+    stdout
+        .set_raw_attribute(0x9f)
+        .expect("Failed to set console attribute");
+    assert_eq!(stdout.attribute(), 0x1f);
     stdout.clear().expect("Failed to clear screen");
 }
 
+// Check that probing for bright backgrounds doesn't change the attribute.
+fn probe_bright_background(stdout: &mut Output) {
+    let attribute = stdout.attribute();
+    let supported = stdout.supports_bright_background();
+    info!("Bright background colors supported: {}", supported);
+    assert_eq!(stdout.attribute(), attribute);
+    // The cached result is returned the second time.
+    assert_eq!(stdout.supports_bright_background(), supported);
+
+    let output_info = stdout.info();
+    info!("Output info: {:?}", output_info);
+    assert!(output_info.max_mode > 0);
+    assert_eq!(output_info.cursor_visible, stdout.cursor_visible());
+}
+
 // Print a text centered on screen.
 fn center_text(stdout: &mut Output) {
     // Move the cursor.
@@ -47,4 +47,12 @@ pub fn test(image: Handle, bt: &BootServices) {
             .expect("Failed to convert text to device path");
         assert_eq!(path, convert);
     }
+
+    // Copy the path so that it outlives the protocol.
+    let owned = device_path
+        .to_owned(bt)
+        .expect("Failed to copy device path");
+    assert_eq!(owned.len_bytes(), device_path.len_bytes());
+    assert_eq!(*owned, *device_path);
+    assert!(owned.starts_with(&device_path));
 }
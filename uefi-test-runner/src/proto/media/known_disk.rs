@@ -1,10 +1,11 @@
 use alloc::string::ToString;
+use alloc::vec::Vec;
 use core::ptr::NonNull;
 use uefi::prelude::*;
 use uefi::proto::media::block::BlockIO;
 use uefi::proto::media::disk::{DiskIo, DiskIo2, DiskIo2Token};
 use uefi::proto::media::file::{
-    Directory, File, FileAttribute, FileInfo, FileMode, FileSystemInfo,
+    Directory, File, FileAttribute, FileInfo, FileMode, FileReader, FileSystemInfo,
 };
 use uefi::proto::media::fs::SimpleFileSystem;
 use uefi::table::boot::{EventType, OpenProtocolAttributes, OpenProtocolParams, Tpl};
@@ -35,6 +36,18 @@ fn test_existing_dir(directory: &mut Directory) {
         }
     }
     assert_eq!(entry_names, [".", "..", "test_input.txt"]);
+
+    // Collect the entries again, with owned names.
+    let entries = dir
+        .entries()
+        .collect::<uefi::Result<Vec<_>>>()
+        .expect("failed to read directory");
+    let entry_names: Vec<_> = entries
+        .iter()
+        .map(|entry| entry.file_name.to_string())
+        .collect();
+    assert_eq!(entry_names, [".", "..", "test_input.txt"]);
+    assert_eq!(entries[2].file_size, 15);
 }
 
 /// Test that deleting a file opened in read-only mode fails with a
@@ -145,6 +158,110 @@ fn test_create_file(directory: &mut Directory) {
     file.write(b"test output data").unwrap();
 }
 
+/// Test reading a file in chunks.
+fn test_file_reader(directory: &mut Directory) {
+    info!("Testing chunked file reads");
+
+    let file = directory
+        .open(
+            cstr16!("new_test_file.txt"),
+            FileMode::Read,
+            FileAttribute::empty(),
+        )
+        .expect("failed to open file")
+        .into_regular_file()
+        .expect("not a regular file");
+    let mut reader = FileReader::new(file).expect("failed to create reader");
+    assert_eq!(reader.remaining(), 16);
+
+    let mut data = Vec::new();
+    let mut chunk = [0; 5];
+    loop {
+        let read = reader.read_chunk(&mut chunk).expect("failed to read chunk");
+        if read == 0 {
+            break;
+        }
+        assert!(!reader.is_eof());
+        data.extend_from_slice(&chunk[..read]);
+    }
+    assert!(reader.is_eof());
+    assert_eq!(reader.remaining(), 0);
+    assert_eq!(reader.position(), 16);
+    assert_eq!(data, b"test output data");
+}
+
+/// Test asynchronous reads and writes.
+fn test_async_file_io(bt: &BootServices, directory: &mut Directory) {
+    info!("Testing asynchronous file I/O");
+
+    let mut file = directory
+        .open(
+            cstr16!("async_test_file.txt"),
+            FileMode::CreateReadWrite,
+            FileAttribute::empty(),
+        )
+        .expect("failed to create file")
+        .into_regular_file()
+        .expect("not a regular file");
+
+    let data = b"async data";
+    // Safety: the tokens are not leaked.
+    let written = unsafe { file.write_async(bt, data) }
+        .expect("failed to start write")
+        .wait()
+        .expect("failed to write file");
+    assert_eq!(written, data.len());
+
+    file.set_position(0).expect("failed to seek");
+    let mut buffer = [0; 16];
+    let token = unsafe { file.read_async(bt, &mut buffer) }.expect("failed to start read");
+    // The completion event is signaled once the read is done.
+    bt.wait_for_event(&mut [unsafe { token.event().unsafe_clone() }])
+        .expect("failed to wait for read");
+    assert!(token.is_complete());
+    let read = token.wait().expect("failed to read file");
+    assert_eq!(&buffer[..read], data);
+
+    file.delete().expect("failed to delete file");
+}
+
+/// Test directory and file creation with attributes.
+fn test_create_with_attributes(directory: &mut Directory) {
+    info!("Testing creation with attributes");
+
+    let mut dir = directory
+        .create_directory(cstr16!("hidden_dir"), FileAttribute::HIDDEN)
+        .expect("failed to create directory");
+    let info = dir.get_boxed_info::<FileInfo>().unwrap();
+    assert!(info.attribute().contains(FileAttribute::HIDDEN));
+    assert!(info.attribute().contains(FileAttribute::DIRECTORY));
+
+    let mut file = dir
+        .create_file(cstr16!("file.txt"), FileAttribute::ARCHIVE)
+        .expect("failed to create file");
+    file.write(b"test output data").unwrap();
+    let info = file.get_boxed_info::<FileInfo>().unwrap();
+    assert!(!info.attribute().contains(FileAttribute::DIRECTORY));
+
+    // The directory attribute can't be used for regular files.
+    assert_eq!(
+        dir.create_file(cstr16!("invalid.txt"), FileAttribute::DIRECTORY)
+            .err()
+            .unwrap()
+            .status(),
+        Status::INVALID_PARAMETER
+    );
+
+    // Creating an existing entry with the wrong type fails.
+    assert_eq!(
+        dir.create_directory(cstr16!("file.txt"), FileAttribute::empty())
+            .err()
+            .unwrap()
+            .status(),
+        Status::ACCESS_DENIED
+    );
+}
+
 /// Get the media ID via the BlockIO protocol.
 fn get_block_media_id(handle: Handle, bt: &BootServices) -> u32 {
     // This cannot be opened in `EXCLUSIVE` mode, as doing so
@@ -303,6 +420,9 @@ pub fn test_known_disk(bt: &BootServices) {
             test_delete_warning(&mut root_directory);
             test_existing_file(&mut root_directory);
             test_create_file(&mut root_directory);
+            test_file_reader(&mut root_directory);
+            test_async_file_io(bt, &mut root_directory);
+            test_create_with_attributes(&mut root_directory);
         }
 
         test_raw_disk_io(handle, bt);
@@ -1,9 +1,30 @@
 mod known_disk;
 
+use alloc::boxed::Box;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::ptr;
 use uefi::prelude::*;
-use uefi::proto::media::file::{Directory, File, FileSystemInfo, FileSystemVolumeLabel};
+use uefi::proto::device_path::build::DevicePathBuilder;
+use uefi::proto::device_path::DevicePath;
+use uefi::proto::media::block::BlockIO;
+use uefi::proto::media::file::{
+    Directory, File, FileAttribute, FileMode, FileSystemInfo, FileSystemVolumeLabel,
+};
+use uefi::proto::media::fs::provider::{
+    install_file_system, StaticContents, StaticFileSystem, StaticNode,
+};
 use uefi::proto::media::fs::SimpleFileSystem;
+use uefi::proto::media::gpt::read_gpt;
+use uefi::proto::media::load_file::{LoadFile, LoadFile2};
 use uefi::proto::media::partition::PartitionInfo;
+use uefi::proto::media::ram_disk::{RamDisk, RamDiskType};
+use uefi::proto::media::sdmmc::SdMmcPassThru;
+use uefi::proto::media::security::StorageSecurityCommand;
+use uefi::table::boot::{AllocateType, MemoryType, OpenProtocolAttributes, OpenProtocolParams};
+use uefi::table::Crc32Validated;
+use uefi::{Guid, Identify};
 
 /// Test `FileSystemInfo` and `FileSystemVolumeLabel`.
 fn test_file_system_info(directory: &mut Directory) {
@@ -23,6 +44,351 @@ fn test_file_system_info(directory: &mut Directory) {
     assert_eq!(fs_info.volume_label(), fs_vol.volume_label());
 }
 
+/// Locate the ESP and open its root directory.
+fn test_locate_esp(bt: &BootServices) {
+    let mut esp = bt.locate_esp().expect("failed to locate the ESP");
+    let mut root = esp.open_volume().expect("failed to open the ESP volume");
+    test_file_system_info(&mut root);
+}
+
+/// Open all the file systems, and find the test file on them.
+fn test_file_systems(bt: &BootServices) {
+    let file_systems = bt.file_systems();
+    info!("Found {} file systems", file_systems.len());
+
+    // The file systems must be closed before searching them again.
+    drop(file_systems);
+
+    // The test disk is only available in QEMU.
+    if cfg!(feature = "qemu") {
+        assert_eq!(bt.file_systems().len(), 2);
+
+        let (_, mut file) = bt
+            .find_file_on_any_fs(cstr16!("test_dir\\test_input.txt"))
+            .expect("failed to find the test file");
+        let mut contents = [0; 15];
+        let read = file.read(&mut contents).expect("failed to read the file");
+        assert_eq!(&contents[..read], b"test input data");
+    }
+
+    assert_eq!(
+        bt.find_file_on_any_fs(cstr16!("does_not_exist.txt"))
+            .err()
+            .unwrap()
+            .status(),
+        Status::NOT_FOUND
+    );
+}
+
+/// Read the GPT of each whole disk that has one.
+fn test_read_gpt(bt: &BootServices) {
+    let handles = bt
+        .find_handles::<BlockIO>()
+        .expect("failed to get handles for `BlockIO` protocol");
+
+    for handle in handles {
+        // Opening in `EXCLUSIVE` mode would disconnect the drivers bound
+        // to the disk.
+        let block_io = unsafe {
+            bt.open_protocol::<BlockIO>(
+                OpenProtocolParams {
+                    handle,
+                    agent: bt.image_handle(),
+                    controller: None,
+                },
+                OpenProtocolAttributes::GetProtocol,
+            )
+        }
+        .expect("failed to open `BlockIO` protocol");
+
+        let media = block_io.media();
+        if media.is_logical_partition() || !media.is_media_present() {
+            continue;
+        }
+
+        match read_gpt(bt, &block_io) {
+            Ok(gpt) => {
+                info!(
+                    "GPT disk {:?} (backup: {}):",
+                    { gpt.header().disk_guid },
+                    gpt.is_backup()
+                );
+                // Safety: the header is followed by the rest of the
+                // `header_size` bytes covered by the CRC.
+                unsafe { gpt.header().validate_crc(bt) }
+                    .expect("GPT header does not match its CRC");
+                for entry in gpt.used_entries() {
+                    info!("GPT entry: {:?}", entry);
+                }
+            }
+            Err(err) if err.status() == Status::NOT_FOUND => {
+                info!("Disk does not have a GPT");
+            }
+            Err(err) => panic!("failed to read GPT: {:?}", err),
+        }
+    }
+}
+
+/// Register a small RAM disk and unregister it again.
+fn test_ram_disk(bt: &BootServices) {
+    let handle = if let Ok(handle) = bt.get_handle_for_protocol::<RamDisk>() {
+        handle
+    } else {
+        info!("`RamDisk` protocol is not supported");
+        return;
+    };
+    let ram_disk = bt
+        .open_protocol_exclusive::<RamDisk>(handle)
+        .expect("failed to open RamDisk protocol");
+
+    const PAGES: usize = 4;
+    let base = bt
+        .allocate_pages(AllocateType::AnyPages, MemoryType::LOADER_DATA, PAGES)
+        .expect("failed to allocate RAM disk memory");
+    let size = (PAGES * 4096) as u64;
+
+    // Unaligned sizes are rejected before calling the firmware.
+    assert_eq!(
+        unsafe { ram_disk.register(base, size - 1, RamDiskType::VIRTUAL_DISK, None) }
+            .unwrap_err()
+            .status(),
+        Status::INVALID_PARAMETER
+    );
+
+    let device_path = unsafe { ram_disk.register(base, size, RamDiskType::VIRTUAL_DISK, None) }
+        .expect("failed to register RAM disk");
+    ram_disk
+        .unregister(device_path)
+        .expect("failed to unregister RAM disk");
+
+    bt.free_pages(base, PAGES).unwrap();
+}
+
+/// Lists the slots of the SD/MMC host controllers, which QEMU doesn't
+/// provide by default.
+fn test_sd_mmc_pass_thru(bt: &BootServices) {
+    if let Ok(handle) = bt.get_handle_for_protocol::<SdMmcPassThru>() {
+        let sd_mmc = bt
+            .open_protocol_exclusive::<SdMmcPassThru>(handle)
+            .expect("failed to open SdMmcPassThru protocol");
+
+        for slot in sd_mmc.slots() {
+            let path = sd_mmc
+                .build_device_path(bt, slot)
+                .expect("failed to build device path of slot");
+            assert_eq!(sd_mmc.get_slot_number(&path), Ok(slot));
+            info!("SD/MMC slot {}: {:?}", slot, path);
+        }
+    } else {
+        warn!("`SdMmcPassThru` protocol is not available");
+    }
+}
+
+/// Lists the security protocols supported by a storage device. QEMU's
+/// emulated disks usually don't support security commands.
+fn test_storage_security_command(bt: &BootServices) {
+    if let Ok(handle) = bt.get_handle_for_protocol::<StorageSecurityCommand>() {
+        // Opening the disk's BlockIO exclusively would disconnect its file
+        // systems, so share it to read the media ID.
+        let media_id = unsafe {
+            bt.with_protocol::<BlockIO, _>(handle, |block_io| block_io.media().media_id())
+        }
+        .expect("failed to open BlockIO protocol");
+        let mut security = bt
+            .open_protocol_exclusive::<StorageSecurityCommand>(handle)
+            .expect("failed to open StorageSecurityCommand protocol");
+
+        let mut buffer = [0; 512];
+        match security.supported_protocols(media_id, 10_000_000, &mut buffer) {
+            Ok(protocols) => info!("Supported security protocols: {:?}", protocols),
+            Err(err) if err.status() == Status::UNSUPPORTED => {
+                info!("Storage device doesn't support security commands");
+            }
+            Err(err) => panic!("failed to list security protocols: {:?}", err),
+        }
+    } else {
+        warn!("`StorageSecurityCommand` protocol is not available");
+    }
+}
+
+fn test_load_file(bt: &BootServices) {
+    if let Ok(handles) = bt.find_handles::<LoadFile>() {
+        // Loading from these devices starts a network boot, which takes too
+        // long here, so only check that the protocol can be opened.
+        for handle in handles {
+            bt.open_protocol_exclusive::<LoadFile>(handle)
+                .expect("failed to open LoadFile protocol");
+            info!("Load file device: {}", bt.debug_handle(handle));
+        }
+    } else {
+        warn!("`LoadFile` protocol is not available");
+    }
+}
+
+/// Contents of the file served by [`TestLoadFile2`].
+const LOAD_FILE2_CONTENTS: &[u8] = b"uefi-rs LoadFile2 test";
+
+/// A `LoadFile2` implementation serving [`LOAD_FILE2_CONTENTS`] for any path.
+#[repr(C)]
+struct TestLoadFile2 {
+    load_file: unsafe extern "efiapi" fn(
+        this: *mut TestLoadFile2,
+        file_path: *const c_void,
+        boot_policy: bool,
+        buffer_size: *mut usize,
+        buffer: *mut c_void,
+    ) -> Status,
+}
+
+unsafe extern "efiapi" fn load_file2_impl(
+    _this: *mut TestLoadFile2,
+    _file_path: *const c_void,
+    boot_policy: bool,
+    buffer_size: *mut usize,
+    buffer: *mut c_void,
+) -> Status {
+    if boot_policy {
+        return Status::UNSUPPORTED;
+    }
+    let size = *buffer_size;
+    *buffer_size = LOAD_FILE2_CONTENTS.len();
+    if buffer.is_null() || size < LOAD_FILE2_CONTENTS.len() {
+        return Status::BUFFER_TOO_SMALL;
+    }
+    ptr::copy_nonoverlapping(
+        LOAD_FILE2_CONTENTS.as_ptr(),
+        buffer.cast(),
+        LOAD_FILE2_CONTENTS.len(),
+    );
+    Status::SUCCESS
+}
+
+/// Test `LoadFile2` with an implementation installed on a vendor device path.
+fn test_load_file2(bt: &BootServices) {
+    info!("Testing a LoadFile2 provider");
+
+    let vendor = Guid::from_values(0x8d2b4c56, 0x0a4e, 0x4b57, 0x9f2e, 0x3c1d7a9e5b20);
+    let mut path_buf = Vec::new();
+    let path = DevicePathBuilder::with_vec(&mut path_buf)
+        .push_vendor_hw(vendor, &[])
+        .unwrap()
+        .finalize();
+    let interface = TestLoadFile2 {
+        load_file: load_file2_impl,
+    };
+
+    let interfaces = [
+        (DevicePath::GUID, path.as_ffi_ptr().cast()),
+        (LoadFile2::GUID, (&interface as *const TestLoadFile2).cast()),
+    ];
+    let installed = unsafe { bt.install_multiple_protocol_interfaces(None, &interfaces) }
+        .expect("failed to install LoadFile2");
+
+    {
+        let mut remaining = path;
+        let handle = bt
+            .locate_device_path::<LoadFile2>(&mut remaining)
+            .expect("failed to locate LoadFile2 device");
+        assert_eq!(handle, installed.handle());
+
+        let mut load_file = bt
+            .open_protocol_exclusive::<LoadFile2>(handle)
+            .expect("failed to open LoadFile2 protocol");
+
+        // `None` queries the size.
+        let size = load_file.load_file(remaining, None).unwrap();
+        assert_eq!(size, LOAD_FILE2_CONTENTS.len());
+
+        // An empty buffer is too small, and the error holds the size.
+        let err = load_file.load_file(remaining, Some(&mut [])).unwrap_err();
+        assert_eq!(err.status(), Status::BUFFER_TOO_SMALL);
+        assert_eq!(*err.data(), Some(LOAD_FILE2_CONTENTS.len()));
+
+        let mut buffer = vec![0; size];
+        let size = load_file.load_file(remaining, Some(&mut buffer)).unwrap();
+        assert_eq!(&buffer[..size], LOAD_FILE2_CONTENTS);
+    }
+
+    installed
+        .uninstall()
+        .expect("failed to uninstall LoadFile2");
+}
+
+/// Test serving a file system implemented in Rust.
+fn test_static_file_system(bt: &BootServices) {
+    info!("Testing a static file system");
+
+    let files = vec![StaticNode {
+        name: cstr16!("hello.txt"),
+        contents: StaticContents::File(b"Hello, world!"),
+    }];
+    let root = vec![
+        StaticNode {
+            name: cstr16!("docs"),
+            contents: StaticContents::Directory(files.leak()),
+        },
+        StaticNode {
+            name: cstr16!("empty"),
+            contents: StaticContents::File(&[]),
+        },
+    ];
+    let fs = StaticFileSystem::new(cstr16!("static"), root.leak());
+    let installed = unsafe { install_file_system(bt, None, Box::new(fs)) }
+        .expect("failed to install file system");
+
+    {
+        let mut sfs = bt
+            .open_protocol_exclusive::<SimpleFileSystem>(installed.handle())
+            .expect("failed to open installed file system");
+        let mut root = sfs.open_volume().unwrap();
+
+        let names: Vec<_> = root
+            .entries()
+            .map(|entry| entry.unwrap().file_name.to_string())
+            .collect();
+        assert_eq!(names, ["docs", "empty"]);
+
+        let mut file = root
+            .open(
+                cstr16!("docs\\..\\docs\\hello.txt"),
+                FileMode::Read,
+                FileAttribute::empty(),
+            )
+            .unwrap()
+            .into_regular_file()
+            .expect("not a regular file");
+        let mut data = vec![0; 32];
+        let size = file.read(&mut data).unwrap();
+        assert_eq!(&data[..size], b"Hello, world!");
+        assert_eq!(file.read(&mut data).unwrap(), 0);
+
+        let err = root
+            .open(cstr16!("missing"), FileMode::Read, FileAttribute::empty())
+            .err()
+            .unwrap();
+        assert_eq!(err.status(), Status::NOT_FOUND);
+        let err = root
+            .open(
+                cstr16!("empty"),
+                FileMode::ReadWrite,
+                FileAttribute::empty(),
+            )
+            .err()
+            .unwrap();
+        assert_eq!(err.status(), Status::WRITE_PROTECTED);
+
+        let mut fs_info_buf = vec![0; 128];
+        let fs_info = root.get_info::<FileSystemInfo>(&mut fs_info_buf).unwrap();
+        assert_eq!(fs_info.volume_label(), cstr16!("static"));
+        assert_eq!(fs_info.volume_size(), 13);
+        assert!(fs_info.read_only());
+    }
+
+    installed
+        .uninstall()
+        .expect("failed to uninstall file system");
+}
+
 pub fn test(bt: &BootServices) {
     info!("Testing Media Access protocols");
 
@@ -77,5 +443,14 @@ pub fn test(bt: &BootServices) {
         }
     }
 
+    test_locate_esp(bt);
+    test_file_systems(bt);
+    test_read_gpt(bt);
+    test_ram_disk(bt);
+    test_sd_mmc_pass_thru(bt);
+    test_storage_security_command(bt);
+    test_load_file(bt);
+    test_load_file2(bt);
     known_disk::test_known_disk(bt);
+    test_static_file_system(bt);
 }
@@ -0,0 +1,71 @@
+use core::time::Duration;
+use uefi::prelude::*;
+use uefi::proto::misc::{FirmwareManagement, Timestamp};
+use uefi::stopwatch::{ClockSource, Stopwatch};
+
+pub fn test(bt: &BootServices) {
+    test_timestamp(bt);
+    test_firmware_management(bt);
+}
+
+fn test_timestamp(bt: &BootServices) {
+    info!("Running timestamp protocol test");
+
+    if let Ok(handle) = bt.get_handle_for_protocol::<Timestamp>() {
+        let timestamp = bt
+            .open_protocol_exclusive::<Timestamp>(handle)
+            .expect("Failed to open Timestamp protocol");
+
+        let properties = timestamp
+            .get_properties()
+            .expect("Failed to get timestamp properties");
+        info!("Timestamp properties: {:?}", properties);
+
+        let timestamp = timestamp.get_timestamp();
+        info!("Timestamp: {}", timestamp);
+
+        let mut stopwatch =
+            Stopwatch::start_with_timestamp(bt).expect("Failed to start the stopwatch");
+        assert_eq!(stopwatch.source(), ClockSource::Timestamp);
+        let first = stopwatch.lap().expect("Failed to read the stopwatch");
+        bt.stall(1000);
+        let second = stopwatch.lap().unwrap();
+        assert!(second >= Duration::from_micros(1000), "{:?}", second);
+        assert!(stopwatch.elapsed().unwrap() >= first + second);
+        info!(
+            "Timestamp stopwatch resolution: {:?}",
+            stopwatch.resolution()
+        );
+    } else {
+        info!("Timestamp protocol is not supported");
+    }
+}
+
+fn test_firmware_management(bt: &BootServices) {
+    info!("Running firmware management protocol test");
+
+    if let Ok(handle) = bt.get_handle_for_protocol::<FirmwareManagement>() {
+        let fmp = bt
+            .open_protocol_exclusive::<FirmwareManagement>(handle)
+            .expect("Failed to open firmware management protocol");
+
+        let info = fmp
+            .get_image_info(bt)
+            .expect("Failed to get firmware image info");
+        info!(
+            "Firmware package version: {:#x} ({:?})",
+            info.package_version, info.package_version_name
+        );
+        for descriptor in &info.descriptors {
+            info!(
+                "Firmware image {}: {:?} version {:#x} ({:?})",
+                descriptor.image_index,
+                descriptor.image_id_name,
+                descriptor.version,
+                descriptor.version_name
+            );
+        }
+    } else {
+        info!("Firmware management protocol is not supported");
+    }
+}
@@ -1,24 +1,42 @@
 use uefi::prelude::*;
 
+use crate::boot::TestInterface;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use uefi::proto::driver::model::DriverModel;
+use uefi::proto::driver::ComponentName2;
 use uefi::proto::loaded_image::LoadedImage;
-use uefi::{proto, Identify};
+use uefi::table::boot::{OpenProtocolAttributes, ScopedProtocol};
+use uefi::{proto, Guid, Identify};
 
 pub fn test(image: Handle, st: &mut SystemTable<Boot>) {
     info!("Testing various protocols");
 
     console::test(image, st);
+    test_selftest(image, st);
 
     let bt = st.boot_services();
     find_protocol(bt);
+    test_get_first_protocol(bt);
+    test_with_protocol(bt);
     test_protocols_per_handle(image, bt);
+    test_debug_handle(image, bt);
+    test_open_protocol_information(image, bt);
+    test_component_name(bt);
+    test_driver_model(image, bt);
+    uefi::debug::print_device_tree(bt);
 
+    boot::test(bt);
     debug::test(bt);
     device_path::test(image, bt);
     loaded_image::test(image, bt);
     media::test(bt);
+    misc::test(bt);
     network::test(bt);
     pi::test(bt);
     rng::test(bt);
+    security::test(bt);
+    string::test(bt);
 
     #[cfg(any(
         target_arch = "i386",
@@ -27,6 +45,22 @@ pub fn test(image: Handle, st: &mut SystemTable<Boot>) {
         target_arch = "aarch64"
     ))]
     shim::test(bt);
+    tpm::test(bt);
+    usb::test(bt);
+}
+
+fn test_selftest(image: Handle, st: &mut SystemTable<Boot>) {
+    info!("Running the protocol self-test");
+
+    let report = uefi::selftest::run_protocol_tests(image, st);
+    for check in report.checks() {
+        info!("{}: {:?}", check.name, check.outcome);
+    }
+    assert!(
+        report.all_passed(),
+        "Protocol self-test failed: {:?}",
+        report.failures().collect::<alloc::vec::Vec<_>>()
+    );
 }
 
 fn find_protocol(bt: &BootServices) {
@@ -42,6 +76,44 @@ fn find_protocol(bt: &BootServices) {
     );
 }
 
+fn test_get_first_protocol(bt: &BootServices) {
+    type SearchedProtocol<'boot> = proto::console::text::Output<'boot>;
+
+    // Safety: the protocol is only used for the duration of this function,
+    // and not concurrently with stdout.
+    let output = unsafe { bt.get_first_protocol::<SearchedProtocol>() }
+        .expect("Failed to open the first Simple Text Output instance");
+    info!("First Simple Text Output mode: {:?}", output.current_mode());
+}
+
+fn test_with_protocol(bt: &BootServices) {
+    type SearchedProtocol<'boot> = proto::console::text::Output<'boot>;
+
+    let handle = bt
+        .get_handle_for_protocol::<SearchedProtocol>()
+        .expect("Failed to get a Simple Text Output handle");
+
+    // Safety: the protocol is only used inside the closure, and not
+    // concurrently with stdout.
+    let mode =
+        unsafe { bt.with_protocol::<SearchedProtocol, _>(handle, |output| output.current_mode()) }
+            .expect("Failed to open Simple Text Output")
+            .expect("Failed to get the current mode");
+    info!("Simple Text Output mode: {:?}", mode);
+
+    // The protocol is closed when the closure returns.
+    let opened = bt
+        .open_protocol_information(handle, &SearchedProtocol::GUID)
+        .expect("Failed to get open protocol information");
+    assert!(
+        !opened
+            .entries()
+            .iter()
+            .any(|entry| entry.agent == Some(bt.image_handle())),
+        "Simple Text Output was not closed"
+    );
+}
+
 fn test_protocols_per_handle(image: Handle, bt: &BootServices) {
     let pph = bt
         .protocols_per_handle(image)
@@ -56,14 +128,136 @@ fn test_protocols_per_handle(image: Handle, bt: &BootServices) {
         .any(|guid| **guid == LoadedImage::GUID));
 }
 
+fn test_debug_handle(image: Handle, bt: &BootServices) {
+    let description = bt.debug_handle(image);
+    info!("Image handle: {}", description);
+
+    // The protocol list includes `LoadedImage`.
+    assert!(description.contains(&format!("{}", LoadedImage::GUID)));
+}
+
+fn test_component_name(bt: &BootServices) {
+    if let Ok(handles) = bt.find_handles::<ComponentName2>() {
+        for handle in handles {
+            let component_name = bt
+                .open_protocol_exclusive::<ComponentName2>(handle)
+                .expect("Failed to open ComponentName2 protocol");
+            let languages = match component_name.supported_languages() {
+                Some(languages) => languages,
+                None => {
+                    info!("Driver without a list of languages");
+                    assert!(component_name.languages().next().is_none());
+                    continue;
+                }
+            };
+            assert!(component_name.languages().next().is_some());
+            match component_name.get_driver_name(cstr8!("en")) {
+                Ok(name) => info!("Driver {} ({})", name, languages),
+                Err(err) => info!("Driver without an English name ({}): {:?}", languages, err),
+            }
+        }
+    } else {
+        warn!("ComponentName2 protocol is not supported");
+    }
+}
+
+fn test_driver_model(image: Handle, bt: &BootServices) {
+    info!("Testing the driver model");
+
+    const TEST_GUID: Guid = Guid::from_values(0x5b0c6f5e, 0x27d4, 0x4d3a, 0x8c1e, 0x6f0a9d2b7e41);
+    static STARTED: AtomicUsize = AtomicUsize::new(0);
+    static STOPPED: AtomicUsize = AtomicUsize::new(0);
+
+    let installed = TestInterface::install(bt, None, TEST_GUID);
+    let controller = installed.handle();
+
+    let supports = move |handle: Handle| -> uefi::Result {
+        if handle == controller {
+            Ok(())
+        } else {
+            Err(Status::UNSUPPORTED.into())
+        }
+    };
+    let driver = DriverModel::new(
+        0x10,
+        move |handle, _| supports(handle),
+        move |handle, _| {
+            STARTED.fetch_add(1, Ordering::Relaxed);
+            supports(handle)
+        },
+        |_, _| {
+            STOPPED.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        },
+    )
+    .component_name(cstr8!("en"), cstr16!("Test Driver"))
+    .controller_name(move |handle, child| {
+        (handle == controller && child.is_none()).then_some(cstr16!("Test Controller"))
+    });
+    let driver = unsafe { driver.install(bt, image) }.expect("Failed to install the driver");
+
+    bt.connect_controller(controller, None, None, false)
+        .expect("Failed to connect the test controller");
+    assert_eq!(STARTED.load(Ordering::Relaxed), 1);
+
+    {
+        let component_name = bt
+            .open_protocol_exclusive::<ComponentName2>(image)
+            .expect("Failed to open ComponentName2 protocol");
+        assert_eq!(
+            component_name.get_driver_name(cstr8!("en")).unwrap(),
+            cstr16!("Test Driver")
+        );
+        assert_eq!(
+            component_name
+                .get_controller_name(controller, None, cstr8!("en"))
+                .unwrap(),
+            cstr16!("Test Controller")
+        );
+        assert_eq!(
+            component_name
+                .get_driver_name(cstr8!("fr"))
+                .unwrap_err()
+                .status(),
+            Status::UNSUPPORTED
+        );
+    }
+
+    // Uninstalling the driver stops it on the controller.
+    driver.uninstall().expect("Failed to uninstall the driver");
+    assert_eq!(STOPPED.load(Ordering::Relaxed), 1);
+}
+
+fn test_open_protocol_information(image: Handle, bt: &BootServices) {
+    let loaded_image = bt
+        .open_protocol_exclusive::<LoadedImage>(image)
+        .expect("Failed to open LoadedImage protocol");
+    let (params, attributes) = ScopedProtocol::open_info(&loaded_image);
+    assert_eq!(params.agent, image);
+    assert_eq!(attributes, OpenProtocolAttributes::Exclusive);
+
+    let info = bt
+        .open_protocol_information(image, &LoadedImage::GUID)
+        .expect("Failed to get open protocol information");
+    info!("LoadedImage open entries: {:?}", info.entries());
+
+    // Our own exclusive open must be listed.
+    assert!(info.entries().iter().any(|entry| {
+        entry.agent == Some(image) && entry.attributes == OpenProtocolAttributes::Exclusive as u32
+    }));
+}
+
+mod boot;
 mod console;
 mod debug;
 mod device_path;
 mod loaded_image;
 mod media;
+mod misc;
 mod network;
 mod pi;
 mod rng;
+mod security;
 #[cfg(any(
     target_arch = "i386",
     target_arch = "x86_64",
@@ -71,3 +265,6 @@ mod rng;
     target_arch = "aarch64"
 ))]
 mod shim;
+mod string;
+mod tpm;
+mod usb;
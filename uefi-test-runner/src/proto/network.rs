@@ -1,10 +1,15 @@
 use uefi::{
     prelude::BootServices,
     proto::network::{
+        adapter_info::{AdapterInfoType, AdapterInformation},
+        ip6_config::Ip6Config,
         pxe::{BaseCode, DhcpV4Packet, IpFilter, IpFilters, UdpOpFlags},
+        udp6::{Udp6, Udp6ConfigData, Udp6ServiceBinding},
+        wifi::WirelessMacConnection2,
         IpAddress,
     },
-    CStr8,
+    table::boot::SearchType,
+    CStr8, Status,
 };
 
 pub fn test(bt: &BootServices) {
@@ -101,4 +106,135 @@ pub fn test(bt: &BootServices) {
     } else {
         warn!("PXE Base Code protocol is not supported");
     }
+
+    test_adapter_info(bt);
+    test_ip6_config(bt);
+    test_udp6(bt);
+    test_wifi(bt);
+}
+
+fn test_adapter_info(bt: &BootServices) {
+    if let Ok(handle) = bt.get_handle_for_protocol::<AdapterInformation>() {
+        let adapter_info = bt
+            .open_protocol_exclusive::<AdapterInformation>(handle)
+            .expect("failed to open adapter information protocol");
+
+        let types = adapter_info
+            .get_supported_types(bt)
+            .expect("failed to get supported information types");
+        info!("Adapter information types: {:?}", types);
+
+        if types.contains(&AdapterInfoType::MEDIA_STATE) {
+            let state = adapter_info
+                .media_state(bt)
+                .expect("failed to get media state");
+            info!("Adapter media state: {:?}", state);
+        } else {
+            let err = adapter_info.media_state(bt).unwrap_err();
+            assert_eq!(err.status(), Status::UNSUPPORTED);
+        }
+    } else {
+        warn!("Adapter information protocol is not supported");
+    }
+}
+
+fn test_wifi(bt: &BootServices) {
+    if let Ok(handle) = bt.get_handle_for_protocol::<WirelessMacConnection2>() {
+        let mut wifi = bt
+            .open_protocol_exclusive::<WirelessMacConnection2>(handle)
+            .expect("failed to open wireless MAC connection protocol");
+
+        let networks = wifi.get_networks(bt).expect("failed to scan for networks");
+        for network in networks {
+            info!("Wireless network: {:?}", network);
+        }
+    } else {
+        warn!("Wireless MAC connection protocol is not supported");
+    }
+}
+
+fn test_ip6_config(bt: &BootServices) {
+    if let Ok(handle) = bt.get_handle_for_protocol::<Ip6Config>() {
+        let ip6_config = bt
+            .open_protocol_exclusive::<Ip6Config>(handle)
+            .expect("failed to open IP6 config protocol");
+
+        #[repr(C, align(8))]
+        struct Buffer([u8; 1024]);
+        let mut buffer = Buffer([0; 1024]);
+        let info = ip6_config
+            .interface_info(&mut buffer.0)
+            .expect("failed to get interface info");
+        info!(
+            "IPv6 interface {:?}: {} addresses, {} routes",
+            info.name(),
+            info.addresses().len(),
+            info.routes().len()
+        );
+
+        let policy = ip6_config.policy().expect("failed to get policy");
+        info!("IPv6 configuration policy: {:?}", policy);
+    } else {
+        warn!("IP6 config protocol is not supported");
+    }
+}
+
+fn test_udp6(bt: &BootServices) {
+    if let Ok(handle) = bt.get_handle_for_protocol::<Udp6ServiceBinding>() {
+        let service_binding = bt
+            .open_protocol_exclusive::<Udp6ServiceBinding>(handle)
+            .expect("failed to open UDP6 service binding");
+
+        info!("Creating UDP6 child");
+        let child = service_binding
+            .create_scoped_child()
+            .expect("failed to create UDP6 child");
+
+        {
+            let mut udp6 = bt
+                .open_protocol_exclusive::<Udp6>(child.handle())
+                .expect("failed to open UDP6 protocol");
+
+            let config = Udp6ConfigData {
+                hop_limit: 64,
+                station_port: 4321,
+                ..Default::default()
+            };
+            match udp6.configure(Some(&config)) {
+                Ok(()) => {
+                    let current = udp6.config_data().expect("failed to get UDP6 config");
+                    assert_eq!(current.station_port, 4321);
+                    udp6.configure(None).expect("failed to reset UDP6 instance");
+                }
+                // The interface may not have an address yet.
+                Err(err) if err.status() == Status::NO_MAPPING => {
+                    info!("UDP6 instance could not be configured: no IPv6 address");
+                }
+                Err(err) => panic!("failed to configure UDP6 instance: {:?}", err),
+            }
+        }
+
+        child.destroy().expect("failed to destroy UDP6 child");
+
+        // A child must not outlive a setup that fails part way.
+        let handle_count = || {
+            bt.locate_handle_buffer(SearchType::AllHandles)
+                .expect("failed to count handles")
+                .len()
+        };
+        let before = handle_count();
+        let err = failing_udp6_setup(bt, &service_binding).unwrap_err();
+        assert_eq!(err.status(), Status::ABORTED);
+        assert_eq!(handle_count(), before, "UDP6 child was leaked");
+    } else {
+        warn!("UDP6 protocol is not supported");
+    }
+}
+
+/// Creates a UDP6 child and opens its protocol, then fails before the child
+/// is used.
+fn failing_udp6_setup(bt: &BootServices, service_binding: &Udp6ServiceBinding) -> uefi::Result {
+    let child = service_binding.create_scoped_child()?;
+    let _udp6 = bt.open_protocol_exclusive::<Udp6>(child.handle())?;
+    Err(Status::ABORTED.into())
 }
@@ -0,0 +1,52 @@
+use uefi::prelude::*;
+use uefi::proto::security::{hash_all, DeferredImageLoad, HashAlgorithm};
+use uefi::table::boot::BootServices;
+
+pub fn test(bt: &BootServices) {
+    test_hash2(bt);
+    test_deferred_image_load(bt);
+}
+
+fn test_hash2(bt: &BootServices) {
+    info!("Running hash2 protocol test");
+
+    // SHA-256 of "abc", from FIPS 180-2.
+    let expected = [
+        0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22,
+        0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00,
+        0x15, 0xad,
+    ];
+    match hash_all(bt, HashAlgorithm::SHA256, b"abc") {
+        Ok(digest) => assert_eq!(digest, expected),
+        Err(err) if err.status() == Status::UNSUPPORTED => {
+            warn!("Hash2 protocol is not supported");
+        }
+        Err(err) => panic!("failed to hash data: {:?}", err),
+    }
+}
+
+fn test_deferred_image_load(bt: &BootServices) {
+    info!("Running deferred image load protocol test");
+
+    let handles = match bt.find_handles::<DeferredImageLoad>() {
+        Ok(handles) => handles,
+        Err(_) => {
+            warn!("DeferredImageLoad protocol is not supported");
+            return;
+        }
+    };
+    for handle in handles {
+        let deferred = bt
+            .open_protocol_exclusive::<DeferredImageLoad>(handle)
+            .expect("failed to open DeferredImageLoad protocol");
+        for image in deferred.images() {
+            let image = image.expect("failed to get deferred image info");
+            info!(
+                "Deferred image: {} bytes, boot option: {}, device path nodes: {:?}",
+                image.image().len(),
+                image.is_boot_option(),
+                image.device_path().map(|path| path.node_iter().count())
+            );
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use core::cmp::Ordering;
+
+use uefi::prelude::*;
+use uefi::proto::string::{RegularExpression, UnicodeCollation};
+
+pub fn test(bt: &BootServices) {
+    info!("Running Unicode Collation protocol test");
+
+    let handles = bt
+        .find_handles::<UnicodeCollation>()
+        .expect("Failed to get Unicode Collation handles");
+
+    // Look for the instance handling English; there may be one per language.
+    let uc = handles
+        .into_iter()
+        .map(|handle| {
+            bt.open_protocol_exclusive::<UnicodeCollation>(handle)
+                .expect("Failed to open Unicode Collation protocol")
+        })
+        .find(|uc| uc.supports_language("en"))
+        .expect("No English Unicode Collation instance");
+
+    assert_eq!(
+        uc.stri_coll(cstr16!("Hello"), cstr16!("hello")),
+        Ordering::Equal
+    );
+    assert_eq!(uc.stri_coll(cstr16!("a"), cstr16!("B")), Ordering::Less);
+
+    assert!(uc.metai_match(cstr16!("Readme.TXT"), cstr16!("*.txt")));
+    assert!(!uc.metai_match(cstr16!("Readme.md"), cstr16!("*.txt")));
+
+    let mut buf = [0; 16];
+    let lower = uc.str_lwr(cstr16!("ABCdef"), &mut buf).unwrap();
+    assert_eq!(lower, cstr16!("abcdef"));
+    let upper = uc.str_upr(cstr16!("ABCdef"), &mut buf).unwrap();
+    assert_eq!(upper, cstr16!("ABCDEF"));
+
+    let mut fat_buf = [0; 16];
+    let (fat, _) = uc.str_to_fat(cstr16!("foo"), &mut fat_buf).unwrap();
+    assert_eq!(fat.to_bytes(), b"FOO");
+
+    test_regular_expression(bt);
+}
+
+fn test_regular_expression(bt: &BootServices) {
+    info!("Running Regular Expression protocol test");
+
+    let handle = match bt.get_handle_for_protocol::<RegularExpression>() {
+        Ok(handle) => handle,
+        Err(_) => {
+            info!("Regular Expression protocol is not supported");
+            return;
+        }
+    };
+    let regex = bt
+        .open_protocol_exclusive::<RegularExpression>(handle)
+        .expect("Failed to open Regular Expression protocol");
+
+    let syntaxes = regex.get_info().expect("Failed to get regex syntaxes");
+    info!("Supported regex syntaxes: {:?}", syntaxes);
+
+    let captures = regex
+        .match_string(bt, cstr16!("b(c+)"), cstr16!("abccd"), None)
+        .expect("Failed to match string");
+    assert_eq!(captures.len(), 2);
+    assert_eq!(captures[0].text, cstr16!("bcc"));
+    assert_eq!(captures[0].start, 1);
+    assert_eq!(captures[1].text, cstr16!("cc"));
+
+    let captures = regex
+        .match_string(bt, cstr16!("x+"), cstr16!("abccd"), None)
+        .expect("Failed to match string");
+    assert!(captures.is_empty());
+}
@@ -0,0 +1,30 @@
+use uefi::proto::tpm::{EventLogFormat, Tcg2};
+use uefi::table::boot::BootServices;
+
+pub fn test(bt: &BootServices) {
+    info!("Running TCG2 protocol test");
+
+    if let Ok(handle) = bt.get_handle_for_protocol::<Tcg2>() {
+        let mut tcg2 = bt
+            .open_protocol_exclusive::<Tcg2>(handle)
+            .expect("failed to open TCG2 protocol");
+
+        let capability = tcg2.get_capability().expect("failed to get capability");
+        info!("TCG2 capability: {:?}", capability);
+
+        if capability.tpm_present() {
+            let format = if capability
+                .supported_event_logs()
+                .contains(EventLogFormat::TCG_2)
+            {
+                EventLogFormat::TCG_2
+            } else {
+                EventLogFormat::TCG_1_2
+            };
+            let log = tcg2.get_event_log(format).expect("failed to get event log");
+            info!("TCG2 event log size: {} bytes", log.bytes().len());
+        }
+    } else {
+        info!("TCG2 protocol is not supported");
+    }
+}
@@ -0,0 +1,79 @@
+use uefi::prelude::*;
+use uefi::proto::usb::{DeviceInfoId, Usb2HostController, UsbFunctionIo};
+use uefi::table::boot::{OpenProtocolAttributes, OpenProtocolParams};
+
+pub fn test(bt: &BootServices) {
+    info!("Running USB host controller protocol test");
+
+    if let Ok(handles) = bt.find_handles::<Usb2HostController>() {
+        for handle in handles {
+            // Opening the controller exclusively would disconnect the USB
+            // drivers, including the keyboard driver.
+            let hc = unsafe {
+                bt.open_protocol::<Usb2HostController>(
+                    OpenProtocolParams {
+                        handle,
+                        agent: bt.image_handle(),
+                        controller: None,
+                    },
+                    OpenProtocolAttributes::GetProtocol,
+                )
+                .expect("Failed to open Usb2HostController protocol")
+            };
+
+            let capability = hc.get_capability().expect("Failed to get capability");
+            let state = hc.get_state().expect("Failed to get state");
+            info!(
+                "USB host controller: USB {:?}, {:?}, state {:?}",
+                hc.revision(),
+                capability,
+                state
+            );
+
+            for port in 0..capability.port_number {
+                let status = hc
+                    .get_root_hub_port_status(port)
+                    .expect("Failed to get port status");
+                info!("Root hub port {}: {:?}", port, status);
+            }
+        }
+    } else {
+        info!("Usb2HostController protocol is not supported");
+    }
+
+    test_usb_function_io(bt);
+}
+
+fn test_usb_function_io(bt: &BootServices) {
+    if let Ok(handle) = bt.get_handle_for_protocol::<UsbFunctionIo>() {
+        let mut usbfn = bt
+            .open_protocol_exclusive::<UsbFunctionIo>(handle)
+            .expect("Failed to open UsbFunctionIo protocol");
+
+        let (vendor_id, product_id) = usbfn
+            .get_vendor_id_product_id()
+            .expect("Failed to get vendor and product ids");
+        info!(
+            "USB function controller {:04x}:{:04x}, revision {:#x}",
+            vendor_id,
+            product_id,
+            usbfn.revision()
+        );
+
+        let mut buffer = [0; 64];
+        match usbfn.get_device_info(DeviceInfoId::PRODUCT_NAME, &mut buffer) {
+            Ok(name) => info!("USB function product name: {}", name),
+            Err(err) => info!("USB function product name is unavailable: {:?}", err),
+        }
+
+        let size = usbfn
+            .get_max_transfer_size()
+            .expect("Failed to get maximum transfer size");
+        let buffer = usbfn
+            .allocate_transfer_buffer(size.min(512))
+            .expect("Failed to allocate transfer buffer");
+        unsafe { usbfn.free_transfer_buffer(buffer) }.expect("Failed to free transfer buffer");
+    } else {
+        info!("UsbFunctionIo protocol is not supported");
+    }
+}
@@ -1,8 +1,22 @@
+use uefi::stopwatch::{ClockSource, Stopwatch};
 use uefi::table::runtime::RuntimeServices;
 
 pub fn test(rt: &RuntimeServices) {
     info!("Testing runtime services");
     vars::test(rt);
+    test_rtc_stopwatch(rt);
 }
 
 mod vars;
+
+fn test_rtc_stopwatch(rt: &RuntimeServices) {
+    info!("Testing the real time clock stopwatch");
+
+    let mut stopwatch = Stopwatch::start_with_rtc(rt).expect("Failed to start the stopwatch");
+    assert_eq!(stopwatch.source(), ClockSource::Rtc);
+    let lap = stopwatch.lap().expect("Failed to read the stopwatch");
+    // The real time clock usually has a resolution of one second, so only
+    // check that nothing went wrong.
+    assert!(stopwatch.elapsed().unwrap() >= lap);
+    info!("RTC stopwatch resolution: {:?}", stopwatch.resolution());
+}
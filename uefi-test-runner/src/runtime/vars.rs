@@ -1,6 +1,7 @@
 use log::info;
 use uefi::prelude::*;
-use uefi::table::runtime::{VariableAttributes, VariableVendor};
+use uefi::table::console_devices::ConsoleVariable;
+use uefi::table::runtime::{OsIndications, VariableAttributes, VariableVendor};
 use uefi::Guid;
 
 fn test_variables(rt: &RuntimeServices) {
@@ -21,6 +22,12 @@ fn test_variables(rt: &RuntimeServices) {
     rt.set_variable(name, &vendor, test_attrs, test_value)
         .expect("failed to set variable");
 
+    // Authenticated writes need the matching attribute.
+    let err = rt
+        .set_variable_authenticated(name, &vendor, test_attrs, &[0; 64])
+        .unwrap_err();
+    assert_eq!(err.status(), Status::INVALID_PARAMETER);
+
     info!("Testing get_variable_size");
     let size = rt
         .get_variable_size(name, &vendor)
@@ -62,7 +69,114 @@ fn test_variable_info(rt: &RuntimeServices) {
     );
 }
 
+fn test_os_indications(rt: &RuntimeServices) {
+    let supported = rt
+        .supported_os_indications()
+        .expect("failed to get supported OS indications");
+    info!("Supported OS indications: {:?}", supported);
+
+    // Requesting an indication only takes effect on the next reset, so
+    // restore the previous value afterwards to avoid affecting later boots.
+    let name = cstr16!("OsIndications");
+    let vendor = VariableVendor::GLOBAL_VARIABLE;
+    let mut buf = [0; 8];
+    let previous = match rt.get_variable(name, &vendor, &mut buf) {
+        Ok((data, attrs)) => Some((data.len(), attrs)),
+        Err(err) if err.status() == Status::NOT_FOUND => None,
+        Err(err) => panic!("failed to read OS indications: {:?}", err),
+    };
+
+    let result = rt.request_boot_to_firmware_ui();
+    if supported.contains(OsIndications::BOOT_TO_FW_UI) {
+        result.expect("failed to request boot to firmware UI");
+        // An empty value deletes the variable if it didn't exist before.
+        let (len, attrs) = previous.unwrap_or((
+            0,
+            VariableAttributes::NON_VOLATILE
+                | VariableAttributes::BOOTSERVICE_ACCESS
+                | VariableAttributes::RUNTIME_ACCESS,
+        ));
+        rt.set_variable(name, &vendor, attrs, &buf[..len])
+            .expect("failed to restore OS indications");
+
+        let mut restored = [0; 8];
+        match rt.get_variable(name, &vendor, &mut restored) {
+            Ok((data, _)) => assert_eq!(data, &buf[..len]),
+            Err(err) => assert_eq!((err.status(), len), (Status::NOT_FOUND, 0)),
+        }
+    } else {
+        assert_eq!(result.unwrap_err().status(), Status::UNSUPPORTED);
+    }
+}
+
+fn test_memory_type_information(rt: &RuntimeServices) {
+    match rt.memory_type_information() {
+        Ok(hints) => info!("Memory type information: {:?}", hints),
+        Err(err) if err.status() == Status::NOT_FOUND => {
+            info!("Firmware has no memory type information");
+        }
+        Err(err) => panic!("failed to read memory type information: {:?}", err),
+    }
+}
+
+fn test_console_devices(rt: &RuntimeServices) {
+    let devices = rt
+        .console_devices(ConsoleVariable::ConOutDev)
+        .expect("failed to read ConOutDev");
+    info!("Console output devices: {}", devices.len());
+
+    // Writing the variable back unchanged keeps the other instances as
+    // they are.
+    let con_out = rt
+        .console_devices(ConsoleVariable::ConOut)
+        .expect("failed to read ConOut");
+    if !con_out.is_empty() {
+        rt.set_console_devices(ConsoleVariable::ConOut, &con_out)
+            .expect("failed to write ConOut");
+        assert_eq!(
+            rt.console_devices(ConsoleVariable::ConOut).unwrap(),
+            con_out
+        );
+    }
+
+    let err = rt
+        .set_console_devices(ConsoleVariable::ConOutDev, &devices)
+        .unwrap_err();
+    assert_eq!(err.status(), Status::WRITE_PROTECTED);
+}
+
+fn test_boot_next(rt: &RuntimeServices) {
+    let current = rt.boot_current().expect("failed to read BootCurrent");
+    info!("Current boot entry: {:?}", current);
+
+    // Restore the previous value afterwards to avoid affecting later boots.
+    let previous = rt.boot_next().expect("failed to read BootNext");
+    rt.set_boot_next(Some(0x1234))
+        .expect("failed to write BootNext");
+    assert_eq!(rt.boot_next(), Ok(Some(0x1234)));
+    rt.set_boot_next(previous)
+        .expect("failed to restore BootNext");
+    assert_eq!(rt.boot_next(), Ok(previous));
+}
+
+fn test_secure_boot(rt: &RuntimeServices) {
+    let enabled = rt.secure_boot_enabled().expect("failed to read SecureBoot");
+    match rt.secure_boot_mode() {
+        Ok(mode) => info!("Secure Boot mode: {:?}, enabled: {}", mode, enabled),
+        Err(err) if err.status() == Status::UNSUPPORTED => {
+            assert!(!enabled);
+            info!("Firmware doesn't support Secure Boot");
+        }
+        Err(err) => panic!("failed to read the Secure Boot mode: {:?}", err),
+    }
+}
+
 pub fn test(rt: &RuntimeServices) {
     test_variables(rt);
     test_variable_info(rt);
+    test_memory_type_information(rt);
+    test_os_indications(rt);
+    test_console_devices(rt);
+    test_boot_next(rt);
+    test_secure_boot(rt);
 }
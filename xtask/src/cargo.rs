@@ -47,6 +47,8 @@ impl Package {
 pub enum Feature {
     Alloc,
     Exts,
+    KnownGuids,
+    Loader,
     Logger,
 
     Ci,
@@ -58,6 +60,8 @@ impl Feature {
         match self {
             Self::Alloc => "alloc",
             Self::Exts => "exts",
+            Self::KnownGuids => "known-guids",
+            Self::Loader => "loader",
             Self::Logger => "logger",
 
             Self::Ci => "uefi-test-runner/ci",
@@ -67,7 +71,13 @@ impl Feature {
 
     /// Set of features that enables more code in the root uefi crate.
     pub fn more_code() -> Vec<Self> {
-        vec![Self::Alloc, Self::Exts, Self::Logger]
+        vec![
+            Self::Alloc,
+            Self::Exts,
+            Self::KnownGuids,
+            Self::Loader,
+            Self::Logger,
+        ]
     }
 
     fn comma_separated_string(features: &[Feature]) -> String {
@@ -214,7 +224,7 @@ mod tests {
     fn test_comma_separated_features() {
         assert_eq!(
             Feature::comma_separated_string(&Feature::more_code()),
-            "alloc,exts,logger"
+            "alloc,exts,known-guids,loader,logger"
         );
     }
 